@@ -0,0 +1,92 @@
+//! Writes pyparsing_rs.pyi, the type stub maturin ships with the module, from the
+//! API table in src/api.rs.
+
+use std::fmt::Write as _;
+use std::path::Path;
+use std::{env, fs};
+
+#[allow(dead_code)]
+mod api {
+    include!("src/api.rs");
+}
+
+use api::{ApiKind, ApiMember, MemberKind, API};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/api.rs");
+    if env::var_os("CARGO_FEATURE_PYTHON").is_none() {
+        return;
+    }
+    let path = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("pyparsing_rs.pyi");
+    let stub = render_stub();
+    // Rewriting an unchanged stub would only bump its mtime
+    if fs::read_to_string(&path).ok().as_deref() != Some(stub.as_str()) {
+        fs::write(&path, stub).unwrap_or_else(|e| panic!("writing {}: {e}", path.display()));
+    }
+}
+
+fn render_stub() -> String {
+    let mut out = String::from(
+        "# Generated by build.rs from src/api.rs; do not edit.\n\n\
+         from types import ModuleType\n\
+         from typing import Any\n",
+    );
+    for entry in API {
+        let name = entry.name;
+        match entry.kind {
+            ApiKind::Function => {
+                out.push_str("\n\n");
+                writeln!(out, "def {name}{}:", entry.signature).unwrap();
+                writeln!(out, "    {}", docstring(entry.summary)).unwrap();
+            }
+            ApiKind::Class | ApiKind::Exception => {
+                let bases = match entry.base {
+                    "" => String::new(),
+                    base => format!("({base})"),
+                };
+                out.push_str("\n\n");
+                writeln!(out, "class {name}{bases}:").unwrap();
+                writeln!(out, "    {}", docstring(entry.summary)).unwrap();
+                if !entry.signature.is_empty() {
+                    let params = entry.signature.trim_start_matches('(');
+                    let sep = if params == ")" { "" } else { ", " };
+                    writeln!(out, "    def __init__(self{sep}{params}: ...").unwrap();
+                }
+                for member in entry.members {
+                    render_member(&mut out, member);
+                }
+            }
+            ApiKind::Alias => writeln!(out, "\n{name} = {}", entry.base).unwrap(),
+            ApiKind::Constant => writeln!(out, "\n{name}: Any").unwrap(),
+            ApiKind::Module => writeln!(out, "\n{name}: ModuleType").unwrap(),
+        }
+    }
+    out
+}
+
+fn render_member(out: &mut String, member: &ApiMember) {
+    let name = member.name;
+    match member.kind {
+        MemberKind::Method => writeln!(out, "    def {name}{}:", member.signature),
+        MemberKind::StaticMethod => {
+            writeln!(
+                out,
+                "    @staticmethod\n    def {name}{}:",
+                member.signature
+            )
+        }
+        MemberKind::Property => writeln!(out, "    @property\n    def {name}(self):"),
+    }
+    .unwrap();
+    writeln!(out, "        {}", docstring(member.summary)).unwrap();
+}
+
+/// `text` as a triple-quoted Python string literal.
+fn docstring(text: &str) -> String {
+    let mut escaped = text.replace('\\', "\\\\").replace("\"\"\"", "\\\"\\\"\\\"");
+    if escaped.ends_with('"') {
+        escaped.insert(escaped.len() - 1, '\\');
+    }
+    format!("\"\"\"{escaped}\"\"\"")
+}
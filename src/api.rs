@@ -0,0 +1,1014 @@
+// The public names of the pyparsing_rs module: one entry per module attribute,
+// with its Python signature and the first sentence of its docstring. The module
+// sets __all__ from it and returns it from api_summary(); build.rs includes this
+// file to write pyparsing_rs.pyi, so it uses nothing outside core.
+// tests/test_edge_cases.py checks it against the module and the stub.
+
+/// What a module attribute is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKind {
+    Function,
+    Class,
+    Exception,
+    /// Another name for the class or exception in `base`
+    Alias,
+    Constant,
+    Module,
+}
+
+impl ApiKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiKind::Function => "function",
+            ApiKind::Class => "class",
+            ApiKind::Exception => "exception",
+            ApiKind::Alias => "alias",
+            ApiKind::Constant => "constant",
+            ApiKind::Module => "module",
+        }
+    }
+}
+
+/// What a class attribute is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberKind {
+    Method,
+    StaticMethod,
+    Property,
+}
+
+impl MemberKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MemberKind::Method => "method",
+            MemberKind::StaticMethod => "staticmethod",
+            MemberKind::Property => "property",
+        }
+    }
+}
+
+/// One module attribute.
+#[derive(Debug)]
+pub struct ApiEntry {
+    pub name: &'static str,
+    pub kind: ApiKind,
+    /// As inspect.signature() shows it; for a class, its constructor's. Empty when
+    /// there is none.
+    pub signature: &'static str,
+    /// Base class of a class or exception, or what an alias names
+    pub base: &'static str,
+    pub summary: &'static str,
+    /// Methods and properties a class defines itself, not those it inherits
+    pub members: &'static [ApiMember],
+}
+
+/// One attribute defined on a class.
+#[derive(Debug)]
+pub struct ApiMember {
+    pub name: &'static str,
+    pub kind: MemberKind,
+    /// As inspect.signature() shows it on the class, `self` included; empty for a
+    /// property
+    pub signature: &'static str,
+    pub summary: &'static str,
+}
+
+const fn function(name: &'static str, signature: &'static str, summary: &'static str) -> ApiEntry {
+    ApiEntry {
+        name,
+        kind: ApiKind::Function,
+        signature,
+        base: "",
+        summary,
+        members: &[],
+    }
+}
+
+const fn class(
+    name: &'static str,
+    base: &'static str,
+    signature: &'static str,
+    summary: &'static str,
+    members: &'static [ApiMember],
+) -> ApiEntry {
+    ApiEntry {
+        name,
+        kind: ApiKind::Class,
+        signature,
+        base,
+        summary,
+        members,
+    }
+}
+
+const fn exception(
+    name: &'static str,
+    base: &'static str,
+    summary: &'static str,
+    members: &'static [ApiMember],
+) -> ApiEntry {
+    ApiEntry {
+        name,
+        kind: ApiKind::Exception,
+        signature: "",
+        base,
+        summary,
+        members,
+    }
+}
+
+const fn alias(name: &'static str, target: &'static str, summary: &'static str) -> ApiEntry {
+    ApiEntry {
+        name,
+        kind: ApiKind::Alias,
+        signature: "",
+        base: target,
+        summary,
+        members: &[],
+    }
+}
+
+const fn constant(name: &'static str, summary: &'static str) -> ApiEntry {
+    ApiEntry {
+        name,
+        kind: ApiKind::Constant,
+        signature: "",
+        base: "",
+        summary,
+        members: &[],
+    }
+}
+
+const fn module(name: &'static str, summary: &'static str) -> ApiEntry {
+    ApiEntry {
+        name,
+        kind: ApiKind::Module,
+        signature: "",
+        base: "",
+        summary,
+        members: &[],
+    }
+}
+
+const fn method(name: &'static str, signature: &'static str, summary: &'static str) -> ApiMember {
+    ApiMember {
+        name,
+        kind: MemberKind::Method,
+        signature,
+        summary,
+    }
+}
+
+const fn staticmethod(
+    name: &'static str,
+    signature: &'static str,
+    summary: &'static str,
+) -> ApiMember {
+    ApiMember {
+        name,
+        kind: MemberKind::StaticMethod,
+        signature,
+        summary,
+    }
+}
+
+const fn property(name: &'static str, summary: &'static str) -> ApiMember {
+    ApiMember {
+        name,
+        kind: MemberKind::Property,
+        signature: "",
+        summary,
+    }
+}
+
+/// Every public attribute of the module, in the order it registers them.
+pub const API: &[ApiEntry] = &[
+    exception(
+        "PyparsingError",
+        "ValueError",
+        "Base class for pyparsing_rs errors.",
+        &[],
+    ),
+    exception(
+        "ParseError",
+        "PyparsingError",
+        "Input did not match.",
+        &[
+            method("mark_input_line", "(exc, marker='>!<')", "ParseError.mark_input_line(): the failing line with `marker` inserted before the failing column, like pyparsing's."),
+            method("explain", "(exc)", "ParseError.explain(): the failing line, a caret under the failing column and the error."),
+        ],
+    ),
+    alias("ParseException", "ParseError", "pyparsing's name for ParseError."),
+    exception(
+        "ParseFatalError",
+        "ParseError",
+        "Input did not match past an error stop (the `-` operator); alternatives and repetitions don't backtrack over it.",
+        &[],
+    ),
+    alias("ParseFatalException", "ParseFatalError", "pyparsing's name for ParseFatalError."),
+    exception(
+        "PatternError",
+        "PyparsingError",
+        "Invalid grammar: bad regex, character set, length limits or element.",
+        &[],
+    ),
+    exception(
+        "FileProcessingError",
+        "PyparsingError",
+        "Reading an input file failed.",
+        &[],
+    ),
+    class(
+        "ParserElement",
+        "",
+        "()",
+        "Base class of every element: holds the element for the methods shared by all of them (parsing, operators, results names, ...).",
+        &[
+            method("__repr__", "(self, /)", "Return repr(self)."),
+            method("__call__", "(self, /, *args, **kwargs)", "Call self as a function."),
+            method("__str__", "(self, /)", "Return str(self)."),
+            method("__add__", "(self, value, /)", "Return self+value."),
+            method("__radd__", "(self, value, /)", "Return value+self."),
+            method("__sub__", "(self, value, /)", "Return self-value."),
+            method("__rsub__", "(self, value, /)", "Return value-self."),
+            method("__mul__", "(self, value, /)", "Return self*value."),
+            method("__rmul__", "(self, value, /)", "Return value*self."),
+            method("__or__", "(self, value, /)", "Return self|value."),
+            method("__ror__", "(self, value, /)", "Return value|self."),
+            method("__getitem__", "(self, key, /)", "Return self[key]."),
+            method("parse_string", "(self, /, s, parse_all=False, normalize=False, timeout_ms=None)", "Parse `s` from its start and return the tokens; raises ParseError if it doesn't match, or with `parse_all`, if text other than whitespace is left over."),
+            method("matches", "(self, /, s, parse_all=True)", "Whether parse_string(s, parse_all) would succeed."),
+            method("find_all_positions", "(self, /, s, overlap=False)", "`(start, end)` byte offsets of each match in `s`, like scan_string's without the tokens: found with the GIL released, without building results or running parse actions."),
+            method("split", "(self, /, s, maxsplit=None, include_separators=False)", "`s` split at the matches scan_string finds, at most `maxsplit` of them, like pyparsing's split."),
+            method("find_all_positions_batch", "(self, /, inputs, overlap=False, max_matches_per_input=None, as_arrays=False, timeout_ms=None)", "find_all_positions() of each input in `inputs`, as `(input_index, start, end)` triples in input order; inputs without a match have none."),
+            method("matches_batch", "(self, /, inputs, parse_all=True, timeout_ms=None)", "matches() on each str in `inputs`, as a list of bools."),
+            method("parse_file", "(self, /, path, parse_all=False, encoding='utf-8', timeout_ms=None)", "parse_string on the text of the file at `path` (str or os.PathLike), read and decoded in Rust."),
+            method("search_file", "(self, /, path, encoding='utf-8', timeout_ms=None)", "search_string on the text of the file at `path`; see parse_file."),
+            method("scan_file", "(self, /, path, overlap=False, encoding='utf-8')", "scan_iter over the text of the file at `path`: `(tokens, start, end)` for each match, found only when asked for; see parse_file."),
+            method("parse_with_tabs", "(self, /)", "pyparsing compatibility: tabs are never expanded before parsing here, so offsets and columns always refer to the input as given."),
+            method("freeze", "(self, /)", "Freeze the grammar: Forwards inside it can no longer be set."),
+            method("is_frozen", "(self, /)", "Whether freeze() was called on this grammar."),
+            method("copy", "(self, /)", "An independent element of the same class and configuration; its children are shared."),
+            method("suppress", "(self, /)", "Match this element but drop its tokens."),
+            method("set_parse_action", "(self, /, *fns)", "Run `fns` on this element's tokens after each match; see ParseAction."),
+            method("add_parse_action", "(self, /, *fns)", "Run `fns` on this element's tokens after each match, like set_parse_action()."),
+            method("add_condition", "(self, /, fn, message=None)", "Reject matches for which `fn(tokens)` is falsy, failing with `message`."),
+            method("to_int", "(self, /)", "Convert the tokens to ints in Rust; a token that isn't one fails the match."),
+            method("to_float", "(self, /)", "Convert the tokens to floats in Rust; a token that isn't a number fails the match."),
+            method("strip_quotes", "(self, /)", "Remove one pair of surrounding quotes from each token."),
+            method("strip", "(self, /)", "Remove leading and trailing whitespace from each token."),
+            method("upcase", "(self, /)", "Convert each token to uppercase."),
+            method("downcase", "(self, /)", "Convert each token to lowercase."),
+            method("join", "(self, /, sep='')", "Join the tokens into one, separated by `sep`."),
+            method("set_whitespace_chars", "(self, /, chars)", "Skip `chars` as whitespace before this element and within it, instead of the default set (see set_default_whitespace_chars())."),
+            method("leave_whitespace", "(self, /, recursive=True)", "Don't skip whitespace before this element, nor, if `recursive`, within it."),
+            method("ignore_whitespace", "(self, /, recursive=True)", "Skip whitespace before this element, and, if `recursive`, within it (e.g. undoing leave_whitespace() or Combine for part of a grammar)."),
+            method("ignore", "(self, /, *exprs)", "Skip matches of `exprs` (e.g. comments) before this element and within it, wherever whitespace is skipped."),
+            method("run_tests", "(self, /, tests, parse_all=True, comment='#', full_dump=True, print_results=True)", "Parse each case in `tests` (one per line, or a list), printing the tokens or the marked error; see run_tests."),
+            method("set_debug", "(self, /, flag=True)", "Report each match attempt of this element, and its outcome, through the debug actions (printing by default); `flag=False` turns tracing off."),
+            method("__reduce__", "(self, /)", "Pickle (and copy.deepcopy) support: the grammar is rebuilt from a description of its elements."),
+            method("set_results_name", "(self, /, name, list_all_matches=False)", "Register this element's tokens under `name` in the parse results."),
+            method("highlight_class", "(self, /, class_name)", "Tag this element with a syntax-highlighting class for highlight_spans()."),
+            method("highlight_spans", "(self, /, s)", "Non-overlapping `(start, end, class)` byte spans of the tagged elements in this grammar: at each position the longest match wins, then the first declared."),
+            method("search_string_count", "(self, /, s)", "Number of matches search_string would find."),
+            method("search_string", "(self, /, s, max_matches=None, timeout_ms=None)", "Token lists of the matches found scanning `s`, at most `max_matches` of them."),
+            method("search_string_json", "(self, /, s, max_matches=None, pretty=False, jsonl_path=None)", "search_string's matches as a JSON str built in Rust: a list of objects with each match's `tokens`, `named` results and `start`/`end` byte offsets, compact or `pretty`."),
+            method("scan_string", "(self, /, s, max_matches=None, overlap=False, normalize=False, timeout_ms=None)", "Matches as `(tokens, start, end)` byte offsets, like pyparsing's scan_string."),
+            method("scan_iter", "(self, /, s, start=0, end=None, overlap=False)", "Lazy scan_string: an iterator of `(tokens, start, end)` that finds each match only when asked for it."),
+            method("parse_lines", "(self, /, lines, skip_failures=True, chunk_size=1000)", "Lazy parse_string over each line of any iterable (a file, a generator): an iterator of `(line_index, tokens)` for the lines that parse."),
+            method("parse_batch_count", "(self, /, inputs, timeout_ms=None)", "Number of `inputs` this element matches at their start."),
+            method("parse_batch", "(self, /, inputs, to_json=False, pretty=False, jsonl_path=None, timeout_ms=None)", "The tokens of each input."),
+            method("parse_segments", "(self, /, segments)", "parse_string over a list of str segments treated as one input."),
+            method("search_segments", "(self, /, segments)", "search_string over a list of str segments treated as one input, as `(tokens, start, end)` tuples with offsets into the joined text."),
+            method("transform_string", "(self, /, s, replacement=None, timeout_ms=None)", "`s` with each match replaced by `replacement`, or by its joined tokens (after any parse actions) when there is none."),
+        ],
+    ),
+    class(
+        "Literal",
+        "ParserElement",
+        "(s)",
+        "Matches the string `s` exactly.",
+        &[
+            method("parse_string", "(self, /, s, parse_all=False, normalize=False, timeout_ms=None)", "Fast inline parse — returns PyList with cached PyString, zero Rust allocation"),
+            method("parse_batch", "(self, /, inputs, to_json=False, pretty=False, jsonl_path=None, timeout_ms=None)", "Batch parse: prefix checks with the GIL released, one shared result list each for matches and misses"),
+            method("search_string", "(self, /, s, max_matches=None, timeout_ms=None)", "Search string — cycle-aware count + PySequence_Repeat for optimal list creation"),
+            method("search_string_count", "(self, /, s)", "Count occurrences — cycle detection fast path + SIMD memchr fallback"),
+            method("parse_batch_count", "(self, /, inputs, timeout_ms=None)", "Full raw FFI count — uniform detection + last-ptr fallback"),
+            method("transform_string", "(self, /, s, replacement=None, timeout_ms=None)", "Replace all non-overlapping matches with replacement string."),
+        ],
+    ),
+    class(
+        "Keyword",
+        "ParserElement",
+        "(s, ident_chars=None, caseless=False)",
+        "Matches `s` when not followed by an identifier character (`ident_chars`, by default those of set_default_keyword_chars()), so `if` doesn't match `iffy`.",
+        &[
+            staticmethod("set_default_keyword_chars", "(chars=None)", "Keyword.set_default_keyword_chars(): see the module function."),
+            method("parse_string", "(self, /, s, parse_all=False, normalize=False, timeout_ms=None)", "Fast keyword parse — uses try_match_at + cached PyString, zero allocation"),
+            method("search_string_count", "(self, /, s)", "Occurrences found with memmem, checking the boundary only at candidates"),
+            method("search_string", "(self, /, s, max_matches=None, timeout_ms=None)", "Search string — count + PySequence_Repeat (same pattern as Literal)"),
+            method("parse_batch_count", "(self, /, inputs, timeout_ms=None)", "Number of `inputs` the keyword matches at their start."),
+            method("parse_batch", "(self, /, inputs, to_json=False, pretty=False, jsonl_path=None, timeout_ms=None)", "Specialized parse_batch: matching with the GIL released, one shared result list each for matches and misses"),
+            property("caseless", "Whether the keyword matches in any case."),
+            property("ident_chars", "The identifier characters, as a class spec."),
+        ],
+    ),
+    class(
+        "Word",
+        "ParserElement",
+        "(init_chars, body_chars=None, min=1, max=0, exact=0, exclude_chars=None, as_keyword=False)",
+        "A run of characters: one of `init_chars`, then any number of `body_chars` (default `init_chars`).",
+        &[
+            method("parse_string", "(self, /, s, parse_all=False, normalize=False, timeout_ms=None)", "Fast-path word parse — returns PyList directly, no Rust String allocation"),
+            method("parse_batch", "(self, /, inputs, to_json=False, pretty=False, jsonl_path=None, timeout_ms=None)", "Cyclic detection + hash-based cache fallback + bulk INCREF"),
+            method("parse_batch_count", "(self, /, inputs, timeout_ms=None)", "Count word matches in batch, with the GIL released"),
+            method("search_string_count", "(self, /, s)", "Count word matches in large text — cycle detection + branchless scan"),
+            method("search_string", "(self, /, s, max_matches=None, timeout_ms=None)", "Optimized Word search_string — O(1) byte-table scanning, dedup, list-of-lists output"),
+            method("transform_string", "(self, /, s, replacement=None, timeout_ms=None)", "Specialized transform: uses 256-byte lookup tables for direct byte scanning."),
+        ],
+    ),
+    class(
+        "Regex",
+        "ParserElement",
+        "(pattern, as_group_list=False, as_match=False)",
+        "Matches a regular expression.",
+        &[
+            method("parse_string", "(self, /, s, parse_all=False, normalize=False, timeout_ms=None)", "Fast-path regex parse — returns PyList directly, no Rust String allocation"),
+            method("search_string_count", "(self, /, s)", "Count regex matches in text — uses find_iter for SIMD-accelerated search"),
+            method("search_string", "(self, /, s, max_matches=None, timeout_ms=None)", "Optimized regex search — uses find_iter for SIMD-accelerated scanning"),
+            method("parse_batch", "(self, /, inputs, to_json=False, pretty=False, jsonl_path=None, timeout_ms=None)", "Cyclic detection + hash-based cache fallback + bulk INCREF"),
+            method("parse_batch_count", "(self, /, inputs, timeout_ms=None)", "Count regex matches in batch, with the GIL released"),
+            method("transform_string", "(self, /, s, replacement=None, timeout_ms=None)", "Specialized: uses regex replace_all for efficient in-engine replacement."),
+        ],
+    ),
+    class(
+        "And",
+        "ParserElement",
+        "(exprs)",
+        "Matches each of `exprs` in turn; also built with `+`.",
+        &[
+            method("__add__", "(self, value, /)", "Return self+value."),
+            method("__radd__", "(self, value, /)", "Return value+self."),
+            method("__sub__", "(self, value, /)", "Return self-value."),
+            method("__rsub__", "(self, value, /)", "Return value-self."),
+            method("__len__", "(self, /)", "Return len(self)."),
+            method("parse_string", "(self, /, s, parse_all=False, normalize=False, timeout_ms=None)", "Parse using parse_impl for correct multi-token handling."),
+            method("parse_batch_count", "(self, /, inputs, timeout_ms=None)", "Cyclic detection + hash-based pointer cache count"),
+            method("parse_batch", "(self, /, inputs, to_json=False, pretty=False, jsonl_path=None, timeout_ms=None)", "The tokens of each input."),
+            property("exprs", "The And's elements; chained operators build one flat And."),
+        ],
+    ),
+    class(
+        "MatchFirst",
+        "ParserElement",
+        "(exprs)",
+        "Matches the first of `exprs` that matches; also built with `|`.",
+        &[
+            method("__or__", "(self, value, /)", "Return self|value."),
+            method("__ror__", "(self, value, /)", "Return value|self."),
+            method("__len__", "(self, /)", "Return len(self)."),
+            method("parse_string", "(self, /, s, parse_all=False, normalize=False, timeout_ms=None)", "Like ParserElement.parse_string."),
+            method("parse_all_alternatives", "(self, /, s, max_parses=5)", "Up to `max_parses` distinct parses of `s` that differ in the alternatives taken, best first, as `(tokens, end, score, choices)`."),
+            property("exprs", "The MatchFirst's elements; chained operators build one flat MatchFirst."),
+        ],
+    ),
+    class(
+        "ZeroOrMore",
+        "ParserElement",
+        "(expr, stop_on=None)",
+        "Matches `expr` any number of times, stopping early where `stop_on` matches.",
+        &[],
+    ),
+    class(
+        "OneOrMore",
+        "ParserElement",
+        "(expr, stop_on=None)",
+        "Matches `expr` one or more times, stopping early where `stop_on` matches.",
+        &[],
+    ),
+    class(
+        "Optional",
+        "ParserElement",
+        "(expr)",
+        "Matches `expr` if it can, and the empty string if not.",
+        &[
+            method("parse_string", "(self, /, s, parse_all=False, normalize=False, timeout_ms=None)", "Like ParserElement.parse_string."),
+        ],
+    ),
+    class(
+        "Group",
+        "ParserElement",
+        "(expr)",
+        "Matches `expr` and nests its tokens in one list token.",
+        &[
+            method("parse_string", "(self, /, s, parse_all=False, normalize=False, timeout_ms=None)", "Like ParserElement.parse_string."),
+        ],
+    ),
+    class(
+        "Dict",
+        "ParserElement",
+        "(expr)",
+        "Matches `expr` and names each of its groups by the group's first token.",
+        &[
+            method("parse_string", "(self, /, s)", "Parse `s` from its start into ParseResults, with the names set inside."),
+        ],
+    ),
+    class(
+        "ParseResults",
+        "",
+        "()",
+        r#"Tokens of a parse: indexes like a list by position and like a dict by name (`results["key"]`), and compares equal to the plain list of its tokens."#,
+        &[
+            method("__repr__", "(self, /)", "Return repr(self)."),
+            method("__str__", "(self, /)", "Return str(self)."),
+            method("__lt__", "(self, value, /)", "Return self<value."),
+            method("__le__", "(self, value, /)", "Return self<=value."),
+            method("__eq__", "(self, value, /)", "Return self==value."),
+            method("__ne__", "(self, value, /)", "Return self!=value."),
+            method("__gt__", "(self, value, /)", "Return self>value."),
+            method("__ge__", "(self, value, /)", "Return self>=value."),
+            method("__iter__", "(self, /)", "Implement iter(self)."),
+            method("__getitem__", "(self, key, /)", "Return self[key]."),
+            method("__len__", "(self, /)", "Return len(self)."),
+            method("__contains__", "(self, key, /)", "Return key in self."),
+            method("get", "(self, /, name, default=None)", "The token stored under `name`, or `default`."),
+            method("keys", "(self, /)", "The results names set."),
+            method("as_list", "(self, /)", "The tokens as a plain list."),
+            method("as_dict", "(self, /)", "The named tokens as a dict."),
+            method("dump", "(self, /, indent='')", "The tokens followed by one `- name: value` line per named entry (sorted by name), nested results indented below their name."),
+        ],
+    ),
+    class(
+        "Suppress",
+        "ParserElement",
+        "(expr)",
+        "Matches `expr` and drops its tokens.",
+        &[
+            method("parse_string", "(self, /, s, parse_all=False, normalize=False, timeout_ms=None)", "Like ParserElement.parse_string."),
+            method("parse_batch", "(self, /, inputs, to_json=False, pretty=False, jsonl_path=None, timeout_ms=None)", "Like ParserElement.parse_batch."),
+        ],
+    ),
+    class(
+        "Forward",
+        "ParserElement",
+        "()",
+        "Placeholder for a recursive grammar, given its element later with `<<=` or set().",
+        &[
+            method("__ilshift__", "(self, value, /)", "Return self<<=value."),
+            method("set", "(self, /, expr)", "Set the inner parser expression (equivalent to <<= in pyparsing)."),
+        ],
+    ),
+    class(
+        "Combine",
+        "ParserElement",
+        "(expr, join_string='', adjacent=True)",
+        "Matches `expr` and joins its tokens into one with `join_string`; with `adjacent`, no whitespace may separate them.",
+        &[],
+    ),
+    class(
+        "OriginalTextFor",
+        "ParserElement",
+        "(expr)",
+        "Matches `expr` and returns the text it spanned as one token.",
+        &[],
+    ),
+    class(
+        "Located",
+        "ParserElement",
+        "(expr)",
+        "Matches `expr` and returns `[start, [tokens], end]`, its offsets in the input.",
+        &[
+            method("parse_string", "(self, /, s)", "Parse `s` from its start into ParseResults, with the names set inside."),
+        ],
+    ),
+    class(
+        "HighlightClass",
+        "ParserElement",
+        "(expr, class_name)",
+        "Matches `expr`, labelling its spans `class_name` for highlight_spans().",
+        &[
+            property("class_name", "The label given to this element's spans."),
+        ],
+    ),
+    class(
+        "Named",
+        "ParserElement",
+        "(expr, name, list_all_matches=False)",
+        "Matches `expr` and stores its tokens under the results name `name`.",
+        &[
+            property("name", "The results name the tokens are stored under."),
+        ],
+    ),
+    class(
+        "ParseAction",
+        "ParserElement",
+        "(expr, *fns)",
+        "Element whose tokens are passed through Python parse actions.",
+        &[
+            method("set_parse_action", "(self, /, *fns)", "Replace the actions, keeping the element they run on."),
+            method("add_parse_action", "(self, /, *fns)", "Run `fns` after the existing actions."),
+        ],
+    ),
+    class(
+        "Condition",
+        "ParserElement",
+        "(expr, fn, message=None)",
+        "Element whose matches are rejected when a Python predicate is falsy.",
+        &[],
+    ),
+    class(
+        "Transform",
+        "ParserElement",
+        "(expr, transform, sep='')",
+        "Element whose tokens are converted in Rust after it matches: to_int(), to_float(), strip_quotes(), strip(), upcase(), downcase() or join().",
+        &[],
+    ),
+    class(
+        "WhitespaceScope",
+        "ParserElement",
+        "(expr, chars)",
+        "Matches `expr` with `chars` as the whitespace it skips.",
+        &[],
+    ),
+    class(
+        "DebugTrace",
+        "ParserElement",
+        "(expr, flag=True)",
+        "Matches `expr`, reporting each attempt through the debug actions.",
+        &[
+            method("set_debug", "(self, /, flag=True)", "Report each match attempt of this element, and its outcome, through the debug actions (printing by default); `flag=False` turns tracing off."),
+        ],
+    ),
+    class(
+        "Exactly",
+        "ParserElement",
+        "(expr, count)",
+        "Matches `expr` exactly `count` times.",
+        &[],
+    ),
+    class(
+        "Repeat",
+        "ParserElement",
+        "(expr, min=0, max=None)",
+        "Matches `expr` between `min` and `max` times (no upper bound when None).",
+        &[],
+    ),
+    class(
+        "AtLeastN",
+        "ParserElement",
+        "(exprs, n)",
+        "Matches `exprs` in any order, each at most once, and at least `n` of them. The indices of the clauses matched are named `clauses_seen`.",
+        &[
+            method("clauses_seen", "(self, /, s)", "Indices of the clauses present in `s`, in the order they appeared."),
+        ],
+    ),
+    class(
+        "AtMostN",
+        "ParserElement",
+        "(exprs, n)",
+        "Matches `exprs` in any order, each at most once, and at most `n` of them. The indices of the clauses matched are named `clauses_seen`.",
+        &[
+            method("clauses_seen", "(self, /, s)", "Indices of the clauses present in `s`, in the order they appeared."),
+        ],
+    ),
+    class(
+        "CaselessLiteral",
+        "ParserElement",
+        "(s)",
+        "Matches `s` in any case; the token is `s` as given.",
+        &[],
+    ),
+    class(
+        "CaselessKeyword",
+        "ParserElement",
+        "(s)",
+        "Matches keyword `s` in any case; the token is `s` as given.",
+        &[],
+    ),
+    class(
+        "CloseMatch",
+        "ParserElement",
+        "(match_string, max_mismatches=1, caseless=False)",
+        "Matches text differing from `match_string` in at most `max_mismatches` characters.",
+        &[
+            property("max_mismatches", "How many characters may differ from `match_string`."),
+            property("match_string", "The text matches are compared with."),
+        ],
+    ),
+    class(
+        "HtmlTag",
+        "ParserElement",
+        "(tag, xml=False)",
+        "Start tag from make_html_tags or make_xml_tags.",
+        &[
+            property("tag", "The tag name, as given."),
+        ],
+    ),
+    class(
+        "HtmlEndTag",
+        "ParserElement",
+        "(tag, xml=False)",
+        "End tag from make_html_tags or make_xml_tags.",
+        &[],
+    ),
+    class(
+        "AttributeFilter",
+        "",
+        "()",
+        "Start-tag filter from with_attribute, checked in Rust when passed to add_condition or add_parse_action.",
+        &[
+            method("__repr__", "(self, /)", "Return repr(self)."),
+        ],
+    ),
+    class(
+        "Char",
+        "ParserElement",
+        "(s)",
+        "Matches one character from `s`.",
+        &[],
+    ),
+    class(
+        "StringStart",
+        "ParserElement",
+        "()",
+        "Matches at the start of the input.",
+        &[],
+    ),
+    class(
+        "StringEnd",
+        "ParserElement",
+        "()",
+        "Matches at the end of the input.",
+        &[],
+    ),
+    class(
+        "LineStart",
+        "ParserElement",
+        "()",
+        "Matches at the start of a line.",
+        &[],
+    ),
+    class(
+        "LineEnd",
+        "ParserElement",
+        "()",
+        "Matches at the end of a line; the newline is the token.",
+        &[],
+    ),
+    class(
+        "RestOfLine",
+        "ParserElement",
+        "()",
+        "Matches the rest of the line, up to but not including the newline.",
+        &[],
+    ),
+    class(
+        "GoToColumn",
+        "ParserElement",
+        "(colno, suppress=False)",
+        "Advances to column `colno` of the current line.",
+        &[
+            property("colno", "The column advanced to, 1-based."),
+        ],
+    ),
+    class(
+        "AsLine",
+        "ParserElement",
+        "(expr)",
+        "Matches `expr` as a whole line: only whitespace may surround it on that line.",
+        &[],
+    ),
+    class(
+        "IndentedBlock",
+        "ParserElement",
+        "(expr, *, recursive=False, grouped=True)",
+        "Lines of `expr` indented alike and further than the enclosing block, for indentation-based formats.",
+        &[],
+    ),
+    class(
+        "Comment",
+        "ParserElement",
+        "()",
+        "A comment; built by c_style_comment() and the other comment functions, C++ style by default.",
+        &[],
+    ),
+    class(
+        "Number",
+        "ParserElement",
+        "()",
+        "A number, its token an int or float; see `common`.",
+        &[],
+    ),
+    class(
+        "Ipv6Address",
+        "ParserElement",
+        "()",
+        "An IPv6 address, also prebuilt as `common.ipv6_address`.",
+        &[],
+    ),
+    class(
+        "QuotedString",
+        "ParserElement",
+        r#"(quote_char='"', esc_char=None, multiline=False, unquote=True)"#,
+        "Matches text between `quote_char`s, with `esc_char` escapes; `unquote` drops the quotes from the token.",
+        &[],
+    ),
+    class(
+        "Empty",
+        "ParserElement",
+        "()",
+        "Always matches, consuming nothing.",
+        &[],
+    ),
+    class(
+        "NoMatch",
+        "ParserElement",
+        "()",
+        "Never matches.",
+        &[],
+    ),
+    class(
+        "SkipTo",
+        "ParserElement",
+        "(expr)",
+        "Matches the text up to the next place `expr` matches.",
+        &[],
+    ),
+    class(
+        "MatchPreviousLiteral",
+        "ParserElement",
+        "(expr)",
+        "Matches the text that `expr` last matched.",
+        &[],
+    ),
+    class(
+        "MatchPreviousExpr",
+        "ParserElement",
+        "(expr)",
+        "Matches the text that `expr` last matched, re-parsed with `expr` so its tokens must be the same.",
+        &[],
+    ),
+    class(
+        "IncrementalScanner",
+        "",
+        "(grammar, context_lines=1)",
+        "Caches the match spans of a grammar in a text and updates them after edits.",
+        &[
+            method("set_text", "(self, /, text)", "Replace the text and scan it from scratch."),
+            method("apply_edit", "(self, /, start, old_len, new_text)", "Replace `old_len` bytes at byte offset `start` with `new_text`, re-scanning around the edit."),
+            method("spans", "(self, /)", "Current match spans as `(start, end)` byte offsets."),
+            property("text", "The current text."),
+        ],
+    ),
+    class(
+        "StreamParser",
+        "",
+        "(grammar, max_buffer=1048576)",
+        "search_string over text fed in chunks; see StreamParser's constructor.",
+        &[
+            method("feed", "(self, /, chunk)", "Add `chunk` (str, or UTF-8 bytes, bytearray or memoryview, which may end partway through a character) and return the matches it settles as `(tokens, start, end)`, with byte offsets into the whole stream."),
+            method("close", "(self, /)", "End the stream: return `(matches, leftover)`, the matches still held now that no more input comes, and whether text after the last match was left unmatched (whitespace aside) or a character was left unfinished."),
+            property("position", "Stream byte offset the next attempt starts at."),
+            property("closed", "Whether close() was called."),
+            property("buffered", "Bytes held for matches still to be decided."),
+        ],
+    ),
+    class(
+        "Normalized",
+        "",
+        "()",
+        "Text returned by normalize(), with offsets mapping back to the original.",
+        &[
+            method("__str__", "(self, /)", "Return str(self)."),
+            method("to_original_offset", "(self, /, loc)", "Byte offset in the original text of normalized byte offset `loc`."),
+            method("to_original_span", "(self, /, start, end)", "Original `(start, end)` of the normalized span `start..end`, leaving out characters removed around it."),
+            property("text", "The normalized text."),
+        ],
+    ),
+    class(
+        "ScanIterator",
+        "",
+        "()",
+        "Iterator returned by scan_iter(): finds one match per step, from `loc` on.",
+        &[
+            method("__iter__", "(self, /)", "Implement iter(self)."),
+            method("__next__", "(self, /)", "Implement next(self)."),
+            property("loc", "Byte offset the next match is searched from."),
+        ],
+    ),
+    class(
+        "LineParseIterator",
+        "",
+        "()",
+        "Iterator returned by parse_lines(): pulls a chunk of lines from `lines` when its buffer of parsed ones runs out.",
+        &[
+            method("__iter__", "(self, /)", "Implement iter(self)."),
+            method("__next__", "(self, /)", "Implement next(self)."),
+            property("lines_read", "Lines pulled from the iterable so far, parsed or buffered."),
+        ],
+    ),
+    class(
+        "ArrowColumn",
+        "",
+        "()",
+        "Column returned by parse_arrow_column().",
+        &[
+            method("__len__", "(self, /)", "Return len(self)."),
+            method("to_pylist", "(self, /)", "Rows as Python values: None, a str, or a list of str."),
+            method("__arrow_c_array__", "(self, /, requested_schema=None)", "Export a copy of the column as `(schema, array)` capsules."),
+            property("null_count", "Number of rows that failed to parse."),
+        ],
+    ),
+    class(
+        "CharClass",
+        "",
+        "(spec, negate=False)",
+        "Set of characters from a class spec, for Word's init_chars and body_chars.",
+        &[
+            method("__repr__", "(self, /)", "Return repr(self)."),
+            method("__contains__", "(self, key, /)", "Return key in self."),
+            property("spec", "The spec as given, with `^` prepended when negated."),
+        ],
+    ),
+    class(
+        "RegexMatchResult",
+        "",
+        "()",
+        "A Regex match returned with `as_match=True`: its groups and their offsets.",
+        &[
+            method("__repr__", "(self, /)", "Return repr(self)."),
+            method("__getitem__", "(self, key, /)", "Return self[key]."),
+            method("group", "(self, /, *groups)", "Text of group `n` (an index or a name; 0 is the whole match), or None if the group didn't participate."),
+            method("groups", "(self, /, default=None)", "Text of every group after the whole match, `default` for those that didn't participate."),
+            method("groupdict", "(self, /, default=None)", "`{name: text}` for the named groups."),
+            method("start", "(self, /, n=None)", "Byte offset in the input where group `n` starts, or -1 if it didn't participate."),
+            method("end", "(self, /, n=None)", "Byte offset in the input where group `n` ends, or -1 if it didn't participate."),
+            method("span", "(self, /, n=None)", "`(start(n), end(n))`."),
+        ],
+    ),
+    class(
+        "UnicodeSet",
+        "",
+        "()",
+        "A `unicode` namespace member, e.g. `unicode.Greek`: character strings for Word and Char drawn from a script's code point ranges.",
+        &[
+            method("__repr__", "(self, /)", "Return repr(self)."),
+            property("alphas", "The letters of this set."),
+            property("nums", "The digits of this set."),
+            property("identchars", "Characters of this set that may start an identifier."),
+            property("printables", "The printable characters of this set other than whitespace."),
+            property("identbodychars", "Characters of this set that may continue an identifier."),
+            property("alphanums", "The letters and digits of this set."),
+        ],
+    ),
+    class(
+        "CompactResults",
+        "",
+        "()",
+        "Tokens of a batch as flat buffers; see parse_batch_compact.",
+        &[
+            method("__repr__", "(self, /)", "Return repr(self)."),
+            method("__len__", "(self, /)", "Return len(self)."),
+            method("__getitem__", "(self, key, /)", "Return self[key]."),
+            method("to_list", "(self, /)", "Every input's tokens, as a list of lists of str."),
+            method("buffers", "(self, /)", r#"The results as a dict of `inputs` (the input count), `data` (all token text, UTF-8) and `input_index`, `token_start`, `token_end` (bytes of native-endian int64, one per token, e.g. for `numpy.frombuffer(b, dtype=numpy.int64)` or `memoryview(b).cast("q")`)."#),
+            property("token_count", "Total number of tokens across all rows."),
+        ],
+    ),
+    class(
+        "CompiledGrammar",
+        "",
+        "()",
+        "Grammar returned by compile(): the same grammar run as a flat program.",
+        &[
+            method("__repr__", "(self, /)", "Return repr(self)."),
+            method("__len__", "(self, /)", "Return len(self)."),
+            method("parse_string", "(self, /, s, parse_all=False, timeout_ms=None)", "Like ParserElement.parse_string; a mismatch raises the same ParseError."),
+            method("parse_batch", "(self, /, inputs, to_json=False, pretty=False, jsonl_path=None, timeout_ms=None)", "Like ParserElement.parse_batch, matching with the GIL released."),
+            method("search_string", "(self, /, s, max_matches=None, timeout_ms=None)", "Like ParserElement.search_string."),
+            property("grammar", "The grammar this was compiled from."),
+        ],
+    ),
+    class(
+        "FastScanner",
+        "",
+        "(literals, caseless=False, overlapping=False, whole_words=False)",
+        "Finds many literals in one pass over a text; see FastScanner's constructor.",
+        &[
+            method("__len__", "(self, /)", "Return len(self)."),
+            method("scan", "(self, /, text)", "Matches as `(literal_index, start, end)` byte offsets, ordered by position."),
+            method("count", "(self, /, text)", "Number of matches scan() would return."),
+            method("parallel_scan", "(self, /, texts, num_threads=None)", "scan() of each text, across `num_threads` threads (default: one per CPU) with the GIL released."),
+        ],
+    ),
+    class(
+        "KeyValueParser",
+        "",
+        "(separator=None, assign='=', key_chars=None, skip_malformed=True)",
+        "Reader of key=value records; see core::kv.",
+        &[
+            method("parse_line", "(self, /, s)", "The pairs of `s` (str or UTF-8 bytes-like) as a dict; a key given twice keeps its last value."),
+            method("parse_batch", "(self, /, lines, as_arrays=False, num_threads=None)", "parse_line() of each line, across `num_threads` threads (default: one per CPU) with the GIL released."),
+        ],
+    ),
+    class(
+        "ParserPipeline",
+        "",
+        "(grammar, source, include=None, exclude=None, transforms=None, sink='return', jsonl_path=None, follow_symlinks=False, max_depth=None, num_threads=None)",
+        "Grammar, files and output run together; see ParserPipeline's constructor.",
+        &[
+            method("files", "(self, /)", "The files run() would read, in order."),
+            method("match_line", "(self, /, line)", "The tokens of the first match in `line`, after the transforms, or None; what run() finds in each line of a file."),
+            method("run", "(self, /)", "Read every file and send its matching lines to the sink."),
+        ],
+    ),
+    class(
+        "InferredPattern",
+        "",
+        "()",
+        "Grammar returned by infer_pattern(), with how well it fits.",
+        &[
+            method("__repr__", "(self, /)", "Return repr(self)."),
+            property("fit_score", "Fraction of the samples the grammar matches entirely (always 1.0)."),
+            property("description", "The grammar as a Python expression."),
+            property("generalization", "Fraction of the held-out samples the grammar matches entirely, if given."),
+            property("element", "The inferred grammar element."),
+        ],
+    ),
+    function("alphas", "()", "The ASCII letters, a-z and A-Z."),
+    function("alphanums", "()", "The ASCII letters and digits."),
+    function("nums", "()", "The digits 0-9."),
+    function("printables", "()", "The printable ASCII characters other than space."),
+    function("hexnums", "()", "The hexadecimal digits, in both cases."),
+    function("alphas_upper", "()", "The uppercase ASCII letters."),
+    function("alphas_lower", "()", "The lowercase ASCII letters."),
+    function("srange", "(spec)", "The characters of a bracketed class like `[a-zA-Z0-9_]`, for Word and Char: pyparsing's srange."),
+    function("make_html_tags", "(tag)", "`(start, end)` elements for an HTML tag."),
+    function("make_xml_tags", "(tag)", "`(start, end)` elements for an XML tag: like make_html_tags, but names are case-sensitive and attribute values must be quoted."),
+    function("with_attribute", "(*args, **kwargs)", "Filter for start tags whose attributes have the given values, from `(name, value)` pairs and keywords; `ANY_VALUE` only requires presence."),
+    constant("ANY_VALUE", "The `ANY_VALUE` sentinel: with_attribute only requires the attribute be present."),
+    function("identchars", "()", "Characters that may start an identifier: letters and `_`."),
+    function("identbodychars", "()", "Characters that may continue an identifier: letters, digits and `_`."),
+    function("one_of", "(strs)", "Create a MatchFirst from a space-separated string of literal alternatives."),
+    function("rest_of_line", "(strip=False)", "Create an element capturing everything up to (not including) the next newline."),
+    function("as_line", "(grammar)", "Require `grammar` to match a whole line: only blanks may surround it before the newline (CRLF tolerated), and the newline is consumed."),
+    function("lineno", "(loc, s)", "Line number (1-based) of byte offset `loc` in `s`, like pyparsing's lineno()."),
+    function("col", "(loc, s)", "Column (1-based, in characters) of byte offset `loc` in `s`, like pyparsing's col(): the character after a newline is column 1."),
+    function("line", "(loc, s)", "The line of `s` containing byte offset `loc`, without its newline, like pyparsing's line()."),
+    function("generate", "(grammar, rng_seed=0, max_depth=16, count=None)", "Generate random strings that `grammar` parses in full, reproducibly for a given seed."),
+    function("compile", "(grammar)", "Lower `grammar` into a flat instruction program that parses like it without recursing through the element tree."),
+    function("c_style_comment", "()", "`/* ... */` comment, possibly spanning lines."),
+    function("cpp_style_comment", "()", "`/* ... */` or `// ...` comment."),
+    function("python_style_comment", "()", "`# ...` comment up to the end of the line."),
+    function("html_comment", "()", "`<!-- ... -->` comment."),
+    function("original_text_for", "(expr, as_string=True)", "Wrap `expr` so its result is the original input text it matched, whitespace included."),
+    function("match_previous_literal", "(expr)", "Create an element that matches the exact text `expr` most recently matched."),
+    function("match_previous_expr", "(expr)", "Create an element that re-parses `expr` and requires the same tokens as its last match."),
+    function("batch_str_predicates", "(strings, prefix=None, suffix=None, contains=None, caseless=False, num_threads=None)", "Test each string for a literal `prefix`, `suffix` and/or `contains` substring, combined with AND; predicates left as `None` are ignored."),
+    function("batch_regex_set", "(patterns, strings, spans=False, num_threads=None)", "Test each string against every regex in `patterns` in one pass."),
+    function("stream_batch_count", "(grammar, inputs, chunk_size=10000, num_threads=None)", "Count the items of any iterable (a generator, a file, a database cursor) that `grammar` matches at the start, like parse_batch_count, pulling `chunk_size` items at a time and matching each chunk across `num_threads` threads with the GIL released, so memory stays bounded by the chunk."),
+    function("aggregate_stats", "(grammar, inputs, num_threads=None)", "Match statistics of `grammar` (or of each grammar in a list) over `inputs`, with matches found like search_string_count."),
+    function("batch_count_matches", "(grammar, inputs, overlap=False, total=False, as_numpy=False, num_threads=None)", "Number of matches of `grammar` (an element, or a str found literally) in each input, found like find_all_positions: with `overlap`, counting resumes one character after each match's start."),
+    function("multi_match_indices", "(patterns, inputs, report_all=False, as_numpy=False, num_threads=None)", "Occurrences of a set of patterns in each input, as `(input_index, pattern_index, start, end)` records in input order, then by start and end."),
+    function("length_histogram", "(inputs, grammar=None, bin_width=None, num_bins=None, min=0.0, max=None, num_threads=None)", "Histogram of the lengths in characters of each input, or with `grammar` of each match of it in the inputs (found like search_string_count)."),
+    function("parse_batch_compact", "(grammar, inputs, num_threads=None)", "Parse each string like parse_string and return its tokens as a CompactResults: one buffer of all token text plus int64 arrays of each token's input index and span in that buffer, instead of a list per input and a str per token."),
+    function("decode_compact", "(buffers)", "Rebuild CompactResults from the dict returned by its buffers(), e.g. after sending it to another process."),
+    function("parse_arrow_column", "(grammar, column, all_tokens=False)", "Parse every row of an Arrow string column (anything with `__arrow_c_stream__` or `__arrow_c_array__`, e.g. a pyarrow Array or a table column) like parse_string, reading the strings in place from the Arrow buffers."),
+    function("sample_scan", "(source, grammar, fraction=0.01, seed=42, window=65536, max_examples=5)", "Estimate how often `grammar` matches in a corpus by scanning a reproducible random `fraction` of it."),
+    function("transform_multi", "(text, rules)", "Replace matches of several grammars in one pass."),
+    function("batch_replace", "(strings, pattern, replacement, max_replacements=None, first_only=False, num_threads=None)", "Replace matches of `pattern` in each string."),
+    function("set_checkpoint_interval", "(interval=16777216, yield_gil=False)", "Check for signals (and optionally let other threads run) every `interval` bytes of progress in parse_string, search_string, scan_string and transform_string, or every 64 KiB while their timeout_ms is running."),
+    function("enable_metrics", "(enabled=True)", "Start (or stop) recording parse_string, parse_batch, search_string, scan_string and file calls."),
+    function("metrics_snapshot", "()", r#"`{"enabled", "totals", "grammars": {label: counters}}`; each counters dict has parses, scans, files, failures, matches, bytes, latency_us_buckets (cumulative) and latency_us_sum."#),
+    function("reset_metrics", "()", "Clear all recorded counters."),
+    function("enable_profiling", "(enabled=True)", "Start (or stop) counting calls and time per grammar element, in every thread."),
+    function("get_profile_stats", "()", "One dict per element counted, most total time first: name (as `str()` gives it), parse_calls, match_calls (match-only calls, as made by repetitions and scans), successes, failures, total_ns and self_ns (less the time of the elements it called)."),
+    function("reset_profile_stats", "()", "Clear all element counters."),
+    function("enable_packrat", "(cache_size=128, *, force=False)", "Cache each element's outcome (tokens or failure) at each position within a parse, keeping at most `cache_size` (0 for no limit), oldest evicted first."),
+    function("disable_packrat", "()", "Turn packrat caching off; see enable_packrat()."),
+    function("packrat_stats", "()", r#"`{"enabled", "hits", "misses"}`: outcomes reused from the cache and element parses run, since enable_packrat()."#),
+    function("enable_left_recursion", "(*, force=False)", r#"Let Forwards be left-recursive (`expr <<= expr + "+" + term | term`): a recursive call at the position a Forward started first fails, then the definition is re-parsed with the previous match until it stops lengthening."#),
+    function("disable_left_recursion", "()", "Turn left recursion off again; see enable_left_recursion()."),
+    function("normalize", "(s, collapse_whitespace=True, strip_zero_width=True, nfc=True, lowercase=False)", "Collapse whitespace runs to one space, drop zero-width characters, compose to NFC and optionally lowercase `s`."),
+    function("infer_pattern", "(samples, holdout=None)", "Propose a grammar matching every string in `samples`: constant regions become Literals, varying ones Words (or a Regex) of the characters seen, and regions some samples lack Optional."),
+    function("set_debug_actions", "(start_fn=None, success_fn=None, fail_fn=None)", "Replace the debug actions of elements with set_debug(): `start_fn(s, loc, name)` before each attempt, `success_fn(s, start, end, name, tokens)` after a match and `fail_fn(s, loc, name, message)` after a failure."),
+    function("set_default_whitespace_chars", r#"(chars=' \t\n\r')"#, "Set the whitespace skipped by elements without set_whitespace_chars(), for parses started afterwards."),
+    function("set_recursion_limit", "(limit=3000)", "Limit how deeply element parses may nest (`limit`, default 3000), so a recursive grammar fed deeply nested input fails with a ParseException naming the element instead of overflowing the stack and aborting the interpreter."),
+    function("set_regex_limits", "(size=None, dfa_size=None)", "Bound the memory a regex may take to compile (`size`, bytes of compiled program) and to search (`dfa_size`, bytes of lazy DFA cache), for Regex and batch_regex_set from now on; None restores the regex crate's default (10 MiB and 2 MiB)."),
+    function("regex_cache_stats", "()", r#"`{"hits", "misses", "size", "capacity", "size_limit", "dfa_size_limit"}`: compiles answered from the regex cache and run, since clear_regex_cache(); regexes held and the most it holds; the limits set by set_regex_limits()."#),
+    function("clear_regex_cache", "()", "Drop every cached regex and zero the cache counters."),
+    function("set_default_keyword_chars", "(chars=None)", "Set the identifier characters (a class spec like `A-Za-z0-9_-`, or a CharClass) of Keywords created afterwards without ident_chars; None restores `A-Za-z0-9_`."),
+    function("transform_multi_file", "(input_path, output_path, rules, block_size=1048576)", "transform_multi over a file, streamed to `output_path` in blocks of whole lines of about `block_size` bytes; matches must not span lines."),
+    function("process_file_streaming", "(path, grammar, callback, batch_size=10000, timeout_ms=None)", "Call `callback` with lists of up to `batch_size` `(line_number, tokens)` pairs for the lines of the file at `path` that `grammar` matches somewhere in: 1-based line numbers, the tokens of the first match in the line."),
+    function("process_csv_field", "(path, column_index, grammar, has_header=True, delimiter=',')", "Match `grammar` against column `column_index` of each record of the CSV file at `path`, like parse_string on the field's text."),
+    function("split_file_process", "(path, grammar, num_chunks=None, chunk_size=None, num_threads=None, to_json=False, pretty=False, jsonl_path=None, timeout_ms=None)", "The matching lines of process_file_streaming as one list, with the file split into line-aligned chunks matched on `num_threads` threads (default: one per CPU): `num_chunks` chunks (default: one per thread), or chunks of about `chunk_size` bytes."),
+    function("scan_directory", "(root, grammar, include=None, exclude=None, follow_symlinks=False, max_depth=None, max_results=None, num_threads=None, timeout_ms=None)", "Match `grammar` against each line of the files under `root`, returning `{path: [(line_number, tokens), ...]}` for the files with a matching line, in walk order: depth first, names sorted. 1-based line numbers; the tokens of the first match in the line, as in process_file_streaming."),
+    function("api_summary", "()", r#"Every public name of the module as a list of dicts: name, kind ("function", "class", "exception", "alias", "constant" or "module"), signature, base, summary and members, the methods and properties a class defines as dicts of name, kind ("method", "staticmethod" or "property"), signature and summary."#),
+    module("common", "Prebuilt elements, like pyparsing's pyparsing_common."),
+    module("unicode", "Character sets of Unicode scripts, like pyparsing's pyparsing_unicode."),
+];
+
+/// The entry for `name`.
+pub fn entry(name: &str) -> Option<&'static ApiEntry> {
+    API.iter().find(|e| e.name == name)
+}
@@ -0,0 +1,78 @@
+use crate::core::context::{skip_ws, ParseContext};
+use crate::core::parser::ParserElement;
+use crate::core::results::ParseResults;
+use std::collections::BTreeSet;
+
+/// Parse attempts allowed per requested parse, bounding the work on inputs where
+/// most explored choices fail.
+const ATTEMPTS_PER_PARSE: usize = 16;
+
+/// One successful parse found by parse_all_alternatives.
+#[derive(Debug, Clone)]
+pub struct Alternative<'a> {
+    pub end: usize,
+    pub results: ParseResults<'a>,
+    /// Alternative index taken at each MatchFirst choice point, in parse order
+    pub choices: Vec<usize>,
+    /// Optionals that matched nothing
+    pub optional_skips: usize,
+}
+
+impl Alternative<'_> {
+    /// Longer matches score higher, each skipped Optional costs one.
+    pub fn score(&self) -> i64 {
+        self.end as i64 - self.optional_skips as i64
+    }
+}
+
+/// Up to `max_parses` distinct parses of `input` from the start, differing in the
+/// alternatives taken at MatchFirst choice points, best score first.
+///
+/// This is a bounded search, not GLR: each attempt forces a prefix of choices and
+/// takes the first matching alternative after it, and every successful parse queues
+/// its untried later alternatives. Alternatives that failed under the default
+/// choices are not revisited. Ties keep exploration order, which is lexicographic
+/// in the choice indices.
+pub fn parse_all_alternatives<'a>(
+    root: &dyn ParserElement,
+    input: &'a str,
+    max_parses: usize,
+) -> Vec<Alternative<'a>> {
+    let mut found: Vec<Alternative> = Vec::new();
+    let mut pending: BTreeSet<Vec<usize>> = BTreeSet::from([Vec::new()]);
+    let mut attempts = 0;
+    let start = skip_ws(input, 0);
+
+    while found.len() < max_parses && attempts < max_parses * ATTEMPTS_PER_PARSE {
+        let Some(forced) = pending.pop_first() else {
+            break;
+        };
+        attempts += 1;
+        let depth = forced.len();
+        let mut ctx = ParseContext::exploring(input, forced);
+        let Ok((end, results)) = root.parse_impl(&mut ctx, start) else {
+            continue;
+        };
+        let trace = ctx.take_choices().unwrap_or_default();
+        let choices: Vec<usize> = trace.taken.iter().map(|&(alt, _)| alt).collect();
+        if found.iter().any(|a| a.choices == choices) {
+            continue;
+        }
+        for (i, &(alt, count)) in trace.taken.iter().enumerate().skip(depth) {
+            for next in alt + 1..count {
+                let mut prefix = choices[..i].to_vec();
+                prefix.push(next);
+                pending.insert(prefix);
+            }
+        }
+        found.push(Alternative {
+            end,
+            results,
+            choices,
+            optional_skips: trace.optional_skips,
+        });
+    }
+
+    found.sort_by_key(|a| std::cmp::Reverse(a.score()));
+    found
+}
@@ -0,0 +1,611 @@
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::fmt;
+use std::ptr;
+
+/// `ArrowSchema` of the Arrow C data interface.
+#[repr(C)]
+pub struct ArrowSchema {
+    format: *const c_char,
+    name: *const c_char,
+    metadata: *const c_char,
+    flags: i64,
+    n_children: i64,
+    children: *mut *mut ArrowSchema,
+    dictionary: *mut ArrowSchema,
+    release: Option<unsafe extern "C" fn(*mut ArrowSchema)>,
+    private_data: *mut c_void,
+}
+
+/// `ArrowArray` of the Arrow C data interface.
+#[repr(C)]
+pub struct ArrowArray {
+    length: i64,
+    null_count: i64,
+    offset: i64,
+    n_buffers: i64,
+    n_children: i64,
+    buffers: *mut *const c_void,
+    children: *mut *mut ArrowArray,
+    dictionary: *mut ArrowArray,
+    release: Option<unsafe extern "C" fn(*mut ArrowArray)>,
+    private_data: *mut c_void,
+}
+
+/// `ArrowArrayStream` of the Arrow C stream interface.
+#[repr(C)]
+pub struct ArrowArrayStream {
+    get_schema: Option<unsafe extern "C" fn(*mut ArrowArrayStream, *mut ArrowSchema) -> c_int>,
+    get_next: Option<unsafe extern "C" fn(*mut ArrowArrayStream, *mut ArrowArray) -> c_int>,
+    get_last_error: Option<unsafe extern "C" fn(*mut ArrowArrayStream) -> *const c_char>,
+    release: Option<unsafe extern "C" fn(*mut ArrowArrayStream)>,
+    private_data: *mut c_void,
+}
+
+// Released structs own their buffers; the interface lets them move between threads.
+unsafe impl Send for ArrowSchema {}
+unsafe impl Send for ArrowArray {}
+unsafe impl Send for ArrowArrayStream {}
+
+/// Error raised for an Arrow column that can't be read.
+#[derive(Debug, Clone)]
+pub struct ArrowError {
+    pub msg: String,
+}
+
+impl ArrowError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+}
+
+impl fmt::Display for ArrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ArrowError: {}", self.msg)
+    }
+}
+
+impl std::error::Error for ArrowError {}
+
+impl ArrowSchema {
+    fn empty() -> Self {
+        Self {
+            format: ptr::null(),
+            name: ptr::null(),
+            metadata: ptr::null(),
+            flags: 0,
+            n_children: 0,
+            children: ptr::null_mut(),
+            dictionary: ptr::null_mut(),
+            release: None,
+            private_data: ptr::null_mut(),
+        }
+    }
+
+    /// Move the schema out of `src` (e.g. a PyCapsule), leaving it released.
+    ///
+    /// # Safety
+    /// `src` must point to a valid ArrowSchema.
+    pub unsafe fn take(src: *mut Self) -> Self {
+        let schema = ptr::read(src);
+        (*src).release = None;
+        schema
+    }
+
+    fn format(&self) -> &str {
+        if self.format.is_null() {
+            return "";
+        }
+        unsafe { CStr::from_ptr(self.format) }
+            .to_str()
+            .unwrap_or("")
+    }
+}
+
+impl Drop for ArrowSchema {
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            unsafe { release(self) };
+        }
+    }
+}
+
+impl ArrowArray {
+    fn empty() -> Self {
+        Self {
+            length: 0,
+            null_count: 0,
+            offset: 0,
+            n_buffers: 0,
+            n_children: 0,
+            buffers: ptr::null_mut(),
+            children: ptr::null_mut(),
+            dictionary: ptr::null_mut(),
+            release: None,
+            private_data: ptr::null_mut(),
+        }
+    }
+
+    /// Move the array out of `src` (e.g. a PyCapsule), leaving it released.
+    ///
+    /// # Safety
+    /// `src` must point to a valid ArrowArray.
+    pub unsafe fn take(src: *mut Self) -> Self {
+        let array = ptr::read(src);
+        (*src).release = None;
+        array
+    }
+
+    fn buffer(&self, i: usize) -> *const c_void {
+        unsafe { *self.buffers.add(i) }
+    }
+}
+
+impl Drop for ArrowArray {
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            unsafe { release(self) };
+        }
+    }
+}
+
+impl ArrowArrayStream {
+    /// Move the stream out of `src` (e.g. a PyCapsule), leaving it released.
+    ///
+    /// # Safety
+    /// `src` must point to a valid ArrowArrayStream.
+    pub unsafe fn take(src: *mut Self) -> Self {
+        let stream = ptr::read(src);
+        (*src).release = None;
+        stream
+    }
+
+    fn error(&mut self, code: c_int) -> ArrowError {
+        let msg = self
+            .get_last_error
+            .map(|f| unsafe { f(self) })
+            .filter(|msg| !msg.is_null())
+            .map(|msg| {
+                unsafe { CStr::from_ptr(msg) }
+                    .to_string_lossy()
+                    .into_owned()
+            });
+        ArrowError::new(msg.unwrap_or_else(|| format!("stream failed with error {}", code)))
+    }
+
+    fn schema(&mut self) -> Result<ArrowSchema, ArrowError> {
+        let get_schema = self.get_schema.ok_or_else(released)?;
+        let mut schema = ArrowSchema::empty();
+        match unsafe { get_schema(self, &mut schema) } {
+            0 => Ok(schema),
+            code => Err(self.error(code)),
+        }
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<ArrowArray>, ArrowError> {
+        let get_next = self.get_next.ok_or_else(released)?;
+        let mut array = ArrowArray::empty();
+        match unsafe { get_next(self, &mut array) } {
+            0 if array.release.is_none() => Ok(None),
+            0 => Ok(Some(array)),
+            code => Err(self.error(code)),
+        }
+    }
+}
+
+impl Drop for ArrowArrayStream {
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            unsafe { release(self) };
+        }
+    }
+}
+
+fn released() -> ArrowError {
+    ArrowError::new("the Arrow data was already consumed")
+}
+
+/// A string column imported through the C data interface, read in place.
+pub struct StringColumn {
+    schema: ArrowSchema,
+    chunks: Vec<ArrowArray>,
+}
+
+impl StringColumn {
+    pub fn from_array(schema: ArrowSchema, array: ArrowArray) -> Result<Self, ArrowError> {
+        Self::new(schema, vec![array])
+    }
+
+    /// Collect every chunk of `stream`, e.g. a ChunkedArray column of a table.
+    pub fn from_stream(mut stream: ArrowArrayStream) -> Result<Self, ArrowError> {
+        let schema = stream.schema()?;
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next_chunk()? {
+            chunks.push(chunk);
+        }
+        Self::new(schema, chunks)
+    }
+
+    fn new(schema: ArrowSchema, chunks: Vec<ArrowArray>) -> Result<Self, ArrowError> {
+        match schema.format() {
+            "u" | "U" => Ok(Self { schema, chunks }),
+            format => Err(ArrowError::new(format!(
+                "expected a string or large_string column, got format {:?}",
+                format
+            ))),
+        }
+    }
+
+    /// Every row of the column in order, None for nulls. Offsets are bounds-checked
+    /// and values checked to be UTF-8.
+    pub fn rows(&self) -> Result<Vec<Option<&str>>, ArrowError> {
+        let large = self.schema.format() == "U";
+        let mut rows = Vec::with_capacity(self.chunks.iter().map(|c| c.length as usize).sum());
+        for chunk in &self.chunks {
+            read_chunk(chunk, large, &mut rows)?;
+        }
+        Ok(rows)
+    }
+}
+
+/// Append the rows of one string array to `rows`.
+fn read_chunk<'a>(
+    array: &'a ArrowArray,
+    large: bool,
+    rows: &mut Vec<Option<&'a str>>,
+) -> Result<(), ArrowError> {
+    let (Ok(offset), Ok(len)) = (usize::try_from(array.offset), usize::try_from(array.length))
+    else {
+        return Err(ArrowError::new("negative array offset or length"));
+    };
+    if len == 0 {
+        return Ok(());
+    }
+    if array.n_buffers != 3 || array.buffers.is_null() {
+        return Err(ArrowError::new("a string array needs 3 buffers"));
+    }
+    let (validity, offsets, data) = (array.buffer(0), array.buffer(1), array.buffer(2));
+    if offsets.is_null() {
+        return Err(ArrowError::new("missing offsets buffer"));
+    }
+    let offsets: Vec<i64> = unsafe {
+        if large {
+            let raw = std::slice::from_raw_parts(offsets as *const i64, offset + len + 1);
+            raw[offset..].to_vec()
+        } else {
+            let raw = std::slice::from_raw_parts(offsets as *const i32, offset + len + 1);
+            raw[offset..].iter().map(|&o| o as i64).collect()
+        }
+    };
+    if offsets[0] < 0 || offsets.windows(2).any(|w| w[0] > w[1]) {
+        return Err(ArrowError::new(
+            "offsets must be non-negative and ascending",
+        ));
+    }
+    let offsets: Vec<usize> = offsets.into_iter().map(|o| o as usize).collect();
+    let data_len = offsets[len];
+    let data: &[u8] = match data.is_null() {
+        true => &[],
+        false => unsafe { std::slice::from_raw_parts(data as *const u8, data_len) },
+    };
+    let validity: Option<&[u8]> = match validity.is_null() || array.null_count == 0 {
+        true => None,
+        false => Some(unsafe {
+            std::slice::from_raw_parts(validity as *const u8, (offset + len).div_ceil(8))
+        }),
+    };
+    for i in 0..len {
+        let bit = offset + i;
+        if validity.is_some_and(|bits| bits[bit / 8] >> (bit % 8) & 1 == 0) {
+            rows.push(None);
+            continue;
+        }
+        let value = data
+            .get(offsets[i]..offsets[i + 1])
+            .ok_or_else(|| ArrowError::new(format!("row {} is out of bounds", rows.len())))?;
+        let value = std::str::from_utf8(value)
+            .map_err(|_| ArrowError::new(format!("row {} is not valid UTF-8", rows.len())))?;
+        rows.push(Some(value));
+    }
+    Ok(())
+}
+
+/// String values with a validity bit per row.
+#[derive(Clone)]
+pub struct StringValues {
+    offsets: Vec<i64>,
+    data: Vec<u8>,
+    valid: Vec<bool>,
+}
+
+impl StringValues {
+    fn new() -> Self {
+        Self {
+            offsets: vec![0],
+            data: Vec::new(),
+            valid: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, value: Option<&str>) {
+        self.data.extend_from_slice(value.unwrap_or("").as_bytes());
+        self.offsets.push(self.data.len() as i64);
+        self.valid.push(value.is_some());
+    }
+
+    fn get(&self, i: usize) -> Option<&str> {
+        let (start, end) = (self.offsets[i] as usize, self.offsets[i + 1] as usize);
+        match self.valid[i] {
+            true => std::str::from_utf8(&self.data[start..end]).ok(),
+            false => None,
+        }
+    }
+}
+
+/// Column of match results built row by row: the first token of each row, or all of
+/// its tokens as a list. Rows without a match (or first token) are null.
+#[derive(Clone)]
+pub enum ResultColumn {
+    Strings(StringValues),
+    Lists {
+        offsets: Vec<i64>,
+        valid: Vec<bool>,
+        values: StringValues,
+    },
+}
+
+/// One row of a ResultColumn.
+pub enum ResultRow<'a> {
+    Null,
+    String(&'a str),
+    List(Vec<&'a str>),
+}
+
+impl ResultColumn {
+    /// A column of each row's first token.
+    pub fn strings() -> Self {
+        Self::Strings(StringValues::new())
+    }
+
+    /// A column of each row's tokens.
+    pub fn lists() -> Self {
+        Self::Lists {
+            offsets: vec![0],
+            valid: Vec::new(),
+            values: StringValues::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Strings(values) => values.valid.len(),
+            Self::Lists { valid, .. } => valid.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn null_count(&self) -> usize {
+        let valid = match self {
+            Self::Strings(values) => &values.valid,
+            Self::Lists { valid, .. } => valid,
+        };
+        valid.iter().filter(|v| !**v).count()
+    }
+
+    /// Add a row for an input that didn't match.
+    pub fn push_null(&mut self) {
+        match self {
+            Self::Strings(values) => values.push(None),
+            Self::Lists {
+                offsets,
+                valid,
+                values,
+            } => {
+                offsets.push(values.valid.len() as i64);
+                valid.push(false);
+            }
+        }
+    }
+
+    /// Add a row for a match with `tokens`.
+    pub fn push_tokens<S: AsRef<str>>(&mut self, tokens: &[S]) {
+        match self {
+            Self::Strings(values) => values.push(tokens.first().map(AsRef::as_ref)),
+            Self::Lists {
+                offsets,
+                valid,
+                values,
+            } => {
+                for token in tokens {
+                    values.push(Some(token.as_ref()));
+                }
+                offsets.push(values.valid.len() as i64);
+                valid.push(true);
+            }
+        }
+    }
+
+    pub fn row(&self, i: usize) -> ResultRow<'_> {
+        match self {
+            Self::Strings(values) => values.get(i).map_or(ResultRow::Null, ResultRow::String),
+            Self::Lists {
+                offsets,
+                valid,
+                values,
+            } => match valid[i] {
+                true => ResultRow::List(
+                    (offsets[i] as usize..offsets[i + 1] as usize)
+                        .filter_map(|j| values.get(j))
+                        .collect(),
+                ),
+                false => ResultRow::Null,
+            },
+        }
+    }
+
+    /// Export a copy of the column through the C data interface.
+    pub fn export(&self) -> (ArrowSchema, ArrowArray) {
+        match self {
+            Self::Strings(values) => (export_schema("u", "", None), export_strings(values)),
+            Self::Lists {
+                offsets,
+                valid,
+                values,
+            } => {
+                let child = export_schema("u", "item", None);
+                let schema = export_schema(list_format(offsets), "", Some(child));
+                let array = export_array(
+                    valid,
+                    offset_buffer(offsets),
+                    None,
+                    Some(export_strings(values)),
+                );
+                (schema, array)
+            }
+        }
+    }
+}
+
+/// Buffers and children kept alive until the consumer releases an exported array.
+struct ExportedArray {
+    _buffers: Vec<Buffer>,
+    buffer_ptrs: Vec<*const c_void>,
+    children: Vec<*mut ArrowArray>,
+}
+
+/// Strings and children kept alive until the consumer releases an exported schema.
+struct ExportedSchema {
+    format: CString,
+    name: CString,
+    children: Vec<*mut ArrowSchema>,
+}
+
+enum Buffer {
+    Bytes(Vec<u8>),
+    Small(Vec<i32>),
+    Large(Vec<i64>),
+}
+
+impl Buffer {
+    fn as_ptr(&self) -> *const c_void {
+        match self {
+            Buffer::Bytes(b) => b.as_ptr() as *const c_void,
+            Buffer::Small(b) => b.as_ptr() as *const c_void,
+            Buffer::Large(b) => b.as_ptr() as *const c_void,
+        }
+    }
+}
+
+fn list_format(offsets: &[i64]) -> &'static str {
+    match fits_i32(offsets) {
+        true => "+l",
+        false => "+L",
+    }
+}
+
+fn fits_i32(offsets: &[i64]) -> bool {
+    offsets.last().is_none_or(|&o| o <= i32::MAX as i64)
+}
+
+/// 32-bit offsets when they fit, else 64-bit (large_string / large_list).
+fn offset_buffer(offsets: &[i64]) -> Buffer {
+    match fits_i32(offsets) {
+        true => Buffer::Small(offsets.iter().map(|&o| o as i32).collect()),
+        false => Buffer::Large(offsets.to_vec()),
+    }
+}
+
+fn export_strings(values: &StringValues) -> ArrowArray {
+    let data = Buffer::Bytes(values.data.clone());
+    export_array(
+        &values.valid,
+        offset_buffer(&values.offsets),
+        Some(data),
+        None,
+    )
+}
+
+fn export_array(
+    valid: &[bool],
+    offsets: Buffer,
+    data: Option<Buffer>,
+    child: Option<ArrowArray>,
+) -> ArrowArray {
+    let null_count = valid.iter().filter(|v| !**v).count();
+    let mut buffers = Vec::with_capacity(3);
+    let mut bitmap = vec![0u8; valid.len().div_ceil(8)];
+    for (i, _) in valid.iter().enumerate().filter(|(_, v)| **v) {
+        bitmap[i / 8] |= 1 << (i % 8);
+    }
+    buffers.push(Buffer::Bytes(bitmap));
+    buffers.push(offsets);
+    buffers.extend(data);
+    let buffer_ptrs: Vec<*const c_void> = buffers.iter().map(Buffer::as_ptr).collect();
+    let children: Vec<*mut ArrowArray> = child
+        .map(|c| Box::into_raw(Box::new(c)))
+        .into_iter()
+        .collect();
+    let mut private = Box::new(ExportedArray {
+        _buffers: buffers,
+        buffer_ptrs,
+        children,
+    });
+    ArrowArray {
+        length: valid.len() as i64,
+        null_count: null_count as i64,
+        offset: 0,
+        n_buffers: private.buffer_ptrs.len() as i64,
+        n_children: private.children.len() as i64,
+        buffers: private.buffer_ptrs.as_mut_ptr(),
+        children: private.children.as_mut_ptr(),
+        dictionary: ptr::null_mut(),
+        release: Some(release_array),
+        private_data: Box::into_raw(private) as *mut c_void,
+    }
+}
+
+/// Schema flag: the field may contain nulls.
+const NULLABLE: i64 = 2;
+
+fn export_schema(format: &str, name: &str, child: Option<ArrowSchema>) -> ArrowSchema {
+    let format = CString::new(format).unwrap_or_default();
+    let name = CString::new(name).unwrap_or_default();
+    let children: Vec<*mut ArrowSchema> = child
+        .map(|c| Box::into_raw(Box::new(c)))
+        .into_iter()
+        .collect();
+    let mut private = Box::new(ExportedSchema {
+        format,
+        name,
+        children,
+    });
+    ArrowSchema {
+        format: private.format.as_ptr(),
+        name: private.name.as_ptr(),
+        metadata: ptr::null(),
+        flags: NULLABLE,
+        n_children: private.children.len() as i64,
+        children: private.children.as_mut_ptr(),
+        dictionary: ptr::null_mut(),
+        release: Some(release_schema),
+        private_data: Box::into_raw(private) as *mut c_void,
+    }
+}
+
+unsafe extern "C" fn release_array(array: *mut ArrowArray) {
+    let private = Box::from_raw((*array).private_data as *mut ExportedArray);
+    for child in &private.children {
+        drop(Box::from_raw(*child));
+    }
+    (*array).release = None;
+}
+
+unsafe extern "C" fn release_schema(schema: *mut ArrowSchema) {
+    let private = Box::from_raw((*schema).private_data as *mut ExportedSchema);
+    for child in &private.children {
+        drop(Box::from_raw(*child));
+    }
+    (*schema).release = None;
+}
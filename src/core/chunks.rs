@@ -0,0 +1,158 @@
+use crate::core::context::ParseContext;
+use crate::core::exceptions::FileError;
+use crate::core::parser::{scan_next, ParserElement};
+use crate::core::results::ParseResults;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Bytes read at a time while looking for the newline after a nominal split point.
+const BOUNDARY_WINDOW: usize = 64 * 1024;
+
+/// Tokens of the first match of `parser` in `line`, without its line terminator.
+pub fn first_match_in_line<'a>(
+    parser: &Arc<dyn ParserElement>,
+    line: &'a str,
+) -> Option<ParseResults<'a>> {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let mut ctx = ParseContext::new(line);
+    scan_next(parser, &mut ctx, 0, false).map(|((results, _, _), _)| results)
+}
+
+/// Split the file at `path` into at most `num_chunks` byte ranges of about equal size
+/// that start and end on line boundaries: each split point moves forward to just
+/// past the next newline. Empty ranges are dropped.
+pub fn line_aligned_chunks(path: &Path, num_chunks: usize) -> Result<Vec<(u64, u64)>, FileError> {
+    let mut file = File::open(path).map_err(|e| FileError::new(path, e))?;
+    let len = file.metadata().map_err(|e| FileError::new(path, e))?.len();
+    let num_chunks = num_chunks.max(1) as u64;
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for i in 1..=num_chunks {
+        let nominal = len * i / num_chunks;
+        let end = match nominal > start && nominal < len {
+            true => {
+                line_start_at_or_after(&mut file, nominal).map_err(|e| FileError::new(path, e))?
+            }
+            false => nominal.max(start),
+        };
+        if end > start {
+            chunks.push((start, end));
+            start = end;
+        }
+    }
+    Ok(chunks)
+}
+
+/// The first line start at or after `pos` (> 0): just past the first newline at or
+/// after `pos - 1`, or the end of the file.
+fn line_start_at_or_after(file: &mut File, pos: u64) -> io::Result<u64> {
+    let mut buf = vec![0; BOUNDARY_WINDOW];
+    let mut at = pos - 1;
+    file.seek(SeekFrom::Start(at))?;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(at);
+        }
+        if let Some(i) = memchr::memchr(b'\n', &buf[..n]) {
+            return Ok(at + i as u64 + 1);
+        }
+        at += n as u64;
+    }
+}
+
+/// Matching lines of one chunk: `(line, tokens)` with 1-based line numbers counted
+/// from the chunk's start, and the number of lines in the chunk.
+pub struct ChunkMatches {
+    pub matches: Vec<(usize, ParseResults<'static>)>,
+    pub lines: usize,
+}
+
+/// Match `parser` against each line of the byte range `start..end` of the file.
+/// Errors carry the chunk-relative number of the line being read.
+pub fn match_chunk_lines(
+    path: &Path,
+    (start, end): (u64, u64),
+    parser: &Arc<dyn ParserElement>,
+) -> Result<ChunkMatches, FileError> {
+    let mut file = File::open(path).map_err(|e| FileError::new(path, e))?;
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| FileError::new(path, e))?;
+    let mut reader = BufReader::new(file.take(end - start));
+    let mut line = String::new();
+    let mut out = ChunkMatches {
+        matches: Vec::new(),
+        lines: 0,
+    };
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => return Ok(out),
+            Ok(_) => out.lines += 1,
+            Err(e) => {
+                let mut err = FileError::new(path, e);
+                err.line = Some(out.lines + 1);
+                return Err(err);
+            }
+        }
+        if let Some(results) = first_match_in_line(parser, &line) {
+            out.matches.push((out.lines, results.into_owned()));
+        }
+    }
+}
+
+/// `(line, tokens)` of matching lines, with 1-based line numbers.
+pub type LineHits = Vec<(usize, ParseResults<'static>)>;
+
+/// What match_file_lines read of a file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadCount {
+    pub bytes: usize,
+    pub lines: usize,
+}
+
+/// Matching lines of the text file at `path`, as `(line, tokens)` with 1-based line
+/// numbers, stopping after `limit` of them, adding the bytes and lines read to `read`.
+/// None when the file looks binary: it holds a NUL byte or isn't UTF-8, which is
+/// only found out when that line is read.
+pub fn match_file_lines(
+    path: &Path,
+    parser: &Arc<dyn ParserElement>,
+    limit: Option<usize>,
+    read: &mut ReadCount,
+) -> Result<Option<LineHits>, FileError> {
+    let file = File::open(path).map_err(|e| FileError::new(path, e))?;
+    let mut reader = BufReader::new(file);
+    let mut buf = Vec::new();
+    let mut matches = Vec::new();
+    let mut lineno = 0;
+    while limit.is_none_or(|limit| matches.len() < limit) {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                lineno += 1;
+                read.bytes += n;
+                read.lines += 1;
+            }
+            Err(e) => {
+                let mut err = FileError::new(path, e);
+                err.line = Some(lineno + 1);
+                return Err(err);
+            }
+        }
+        if memchr::memchr(0, &buf).is_some() {
+            return Ok(None);
+        }
+        let Ok(line) = std::str::from_utf8(&buf) else {
+            return Ok(None);
+        };
+        if let Some(results) = first_match_in_line(parser, line) {
+            matches.push((lineno, results.into_owned()));
+        }
+    }
+    Ok(Some(matches))
+}
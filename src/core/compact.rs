@@ -0,0 +1,151 @@
+use std::fmt;
+
+/// Buffers that don't form valid compact results.
+#[derive(Debug, Clone)]
+pub struct CompactError {
+    pub msg: String,
+}
+
+impl CompactError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+}
+
+impl fmt::Display for CompactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CompactError: {}", self.msg)
+    }
+}
+
+impl std::error::Error for CompactError {}
+
+/// Tokens of a batch of inputs without an object per token: all token text
+/// concatenated in `data`, and per token the input it came from and its byte span
+/// in `data`. Tokens are ordered by input, so each input's tokens are contiguous;
+/// inputs that didn't match have none.
+#[derive(Clone, Default)]
+pub struct CompactResults {
+    inputs: usize,
+    data: Vec<u8>,
+    input_index: Vec<i64>,
+    token_start: Vec<i64>,
+    token_end: Vec<i64>,
+}
+
+impl CompactResults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild from the arrays returned by the accessors, checking that every span
+    /// is in bounds on a character boundary and that input indices are ordered.
+    pub fn from_parts(
+        inputs: usize,
+        data: Vec<u8>,
+        input_index: Vec<i64>,
+        token_start: Vec<i64>,
+        token_end: Vec<i64>,
+    ) -> Result<Self, CompactError> {
+        if input_index.len() != token_start.len() || input_index.len() != token_end.len() {
+            return Err(CompactError::new(format!(
+                "array lengths differ: {} input indices, {} starts, {} ends",
+                input_index.len(),
+                token_start.len(),
+                token_end.len()
+            )));
+        }
+        let text = std::str::from_utf8(&data)
+            .map_err(|_| CompactError::new("token data is not valid UTF-8"))?;
+        let mut previous = 0;
+        for (k, ((&input, &start), &end)) in input_index
+            .iter()
+            .zip(&token_start)
+            .zip(&token_end)
+            .enumerate()
+        {
+            if input < previous || input as u64 >= inputs as u64 {
+                return Err(CompactError::new(format!(
+                    "token {} has input index {}, out of order or range",
+                    k, input
+                )));
+            }
+            previous = input;
+            let in_bounds = 0 <= start && start <= end && end as u64 <= data.len() as u64;
+            if !in_bounds
+                || !text.is_char_boundary(start as usize)
+                || !text.is_char_boundary(end as usize)
+            {
+                return Err(CompactError::new(format!(
+                    "token {} has an invalid span {}..{}",
+                    k, start, end
+                )));
+            }
+        }
+        Ok(Self {
+            inputs,
+            data,
+            input_index,
+            token_start,
+            token_end,
+        })
+    }
+
+    /// Add the next input's tokens (none for a miss).
+    pub fn push_input<S: AsRef<str>>(&mut self, tokens: &[S]) {
+        for token in tokens {
+            self.input_index.push(self.inputs as i64);
+            self.token_start.push(self.data.len() as i64);
+            self.data.extend_from_slice(token.as_ref().as_bytes());
+            self.token_end.push(self.data.len() as i64);
+        }
+        self.inputs += 1;
+    }
+
+    /// Number of inputs.
+    pub fn len(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs == 0
+    }
+
+    pub fn token_count(&self) -> usize {
+        self.input_index.len()
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn input_index(&self) -> &[i64] {
+        &self.input_index
+    }
+
+    pub fn token_start(&self) -> &[i64] {
+        &self.token_start
+    }
+
+    pub fn token_end(&self) -> &[i64] {
+        &self.token_end
+    }
+
+    /// Tokens of input `i`, found by binary search.
+    pub fn row(&self, i: usize) -> Vec<&str> {
+        let first = self
+            .input_index
+            .partition_point(|&input| (input as usize) < i);
+        let last = self
+            .input_index
+            .partition_point(|&input| (input as usize) <= i);
+        (first..last).map(|k| self.token(k)).collect()
+    }
+
+    /// Text of token `k`.
+    pub fn token(&self, k: usize) -> &str {
+        let span = self.token_start[k] as usize..self.token_end[k] as usize;
+        // Spans are pushed or checked on character boundaries of UTF-8 data
+        std::str::from_utf8(&self.data[span]).expect("token spans are valid UTF-8")
+    }
+}
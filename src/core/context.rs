@@ -1,9 +1,103 @@
+use crate::core::exceptions::{line_col, ParseException};
+use crate::core::packrat::{self, Memo, MemoKey};
+use crate::core::parser::{element_key, ParseResult, ParserElement, ParserKind};
+use crate::core::profile;
+use rustc_hash::FxHashMap;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
 /// Context for parsing operations — holds a reference to the input string.
 pub struct ParseContext<'a> {
     input: &'a str,
     /// Whether to auto-skip whitespace before element matches (pyparsing default: true).
     /// Set to false inside Combine to prevent whitespace skipping.
     pub skip_whitespace: bool,
+    /// Characters skipped as whitespace: the default set, or that of the nearest
+    /// enclosing element with its own (see WhitespaceScope).
+    pub whitespace: WhitespaceChars,
+    /// Column (1-based) of the lines of the innermost IndentedBlock being parsed, 0
+    /// outside any; a nested block must be indented further.
+    pub indent: usize,
+    /// Where a non-recursive leave_whitespace scope starts: whitespace there belongs
+    /// to the scope's element, so its first nested element doesn't skip it either.
+    keep_whitespace_at: Option<usize>,
+    /// Expressions skipped along with whitespace (comments etc.), registered by
+    /// enclosing ignore scopes, innermost last.
+    ignore: Vec<Arc<dyn ParserElement>>,
+    /// Spans matched by elements, keyed by element identity, for back-references.
+    /// Only populated while `record_matches` is set.
+    match_log: Vec<(usize, usize, usize)>,
+    /// Set by sequences that contain back-reference elements.
+    record_matches: bool,
+    /// Choice points taken, when exploring alternative parses (see core::alternatives).
+    choices: Option<ChoiceTrace>,
+    /// Periodic callback for long parses and scans.
+    checkpoint: Option<Checkpoint<'a>>,
+    /// Packrat cache of element outcomes, when enabled (see core::packrat).
+    memo: Option<Memo<'a>>,
+    /// Best match so far of each left-recursive Forward at each position, when
+    /// left recursion is enabled.
+    seeds: Option<FxHashMap<MemoKey, ParseResult<'a>>>,
+    /// Element parses in progress, nested; past depth_limit parsing fails instead
+    /// of overflowing the native stack.
+    depth: usize,
+    depth_limit: usize,
+    /// Furthest position any element failed at, for with_furthest_failure. None
+    /// unless tracking was asked for, so batch parses don't pay for it.
+    furthest_loc: Option<usize>,
+    /// Distinct messages of the leaf elements that failed at furthest_loc
+    furthest_msgs: Vec<Arc<str>>,
+}
+
+/// Messages kept per furthest failure position; more are dropped.
+const MAX_FURTHEST_MESSAGES: usize = 8;
+
+/// Checkpoints passed between hook calls when parsing isn't advancing, as when a
+/// grammar backtracks over the same text again and again.
+const CHECKPOINT_CALLS: u32 = 1 << 10;
+
+/// Calls a hook with the position reached each time parsing or scanning advances
+/// `interval` bytes, or passes CHECKPOINT_CALLS checkpoints without doing so, e.g.
+/// so the host can handle signals. A hook returning false aborts the parse; every
+/// later checkpoint then fails too, so enclosing alternatives give up quickly.
+pub struct Checkpoint<'a> {
+    interval: usize,
+    next: usize,
+    calls: u32,
+    aborted: bool,
+    hook: Box<dyn FnMut(usize) -> bool + 'a>,
+}
+
+impl<'a> Checkpoint<'a> {
+    pub fn new(interval: usize, hook: impl FnMut(usize) -> bool + 'a) -> Self {
+        let interval = interval.max(1);
+        Self {
+            interval,
+            next: interval,
+            calls: 0,
+            aborted: false,
+            hook: Box::new(hook),
+        }
+    }
+}
+
+/// Alternatives chosen at each choice point, in the order they were reached.
+#[derive(Debug, Default)]
+pub struct ChoiceTrace {
+    /// Alternatives to take at the first choice points, instead of the first that matches
+    forced: Vec<usize>,
+    /// `(alternative, alternative count)` per choice point on the current parse path
+    pub taken: Vec<(usize, usize)>,
+    /// Optionals on the current parse path that matched nothing
+    pub optional_skips: usize,
+}
+
+/// Position in a ChoiceTrace to rewind to when a branch fails.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChoiceMark {
+    taken: usize,
+    optional_skips: usize,
 }
 
 impl<'a> ParseContext<'a> {
@@ -11,6 +105,294 @@ impl<'a> ParseContext<'a> {
         Self {
             input,
             skip_whitespace: true,
+            whitespace: default_whitespace(),
+            indent: 0,
+            keep_whitespace_at: None,
+            ignore: Vec::new(),
+            match_log: Vec::new(),
+            record_matches: false,
+            choices: None,
+            checkpoint: None,
+            memo: Memo::for_parse(),
+            seeds: packrat::left_recursion_enabled().then(FxHashMap::default),
+            // A context made inside a match-only check continues its nesting
+            depth: MATCH_DEPTH.get(),
+            depth_limit: recursion_limit(),
+            furthest_loc: None,
+            furthest_msgs: Vec::new(),
+        }
+    }
+
+    /// Continue the nesting of `outer`, for a context that parses part of its input.
+    pub fn nested_in(mut self, outer: &ParseContext<'_>) -> Self {
+        self.depth = outer.depth;
+        self
+    }
+
+    /// Attach a checkpoint hook (or none).
+    pub fn with_checkpoint(mut self, checkpoint: Option<Checkpoint<'a>>) -> Self {
+        self.checkpoint = checkpoint;
+        self
+    }
+
+    /// Track where parsing fails furthest, for with_furthest_failure.
+    pub fn tracking_furthest_failure(mut self) -> Self {
+        self.furthest_loc = Some(0);
+        self
+    }
+
+    /// Run the checkpoint hook if parsing has reached `pos` since the last one, or
+    /// has been stuck behind it for CHECKPOINT_CALLS checkpoints. Fails once the hook
+    /// has asked to abort.
+    #[inline(always)]
+    pub fn checkpoint(&mut self, pos: usize) -> Result<(), ParseException> {
+        match &mut self.checkpoint {
+            Some(cp) if cp.aborted || pos >= cp.next || cp.calls >= CHECKPOINT_CALLS => {
+                if !cp.aborted {
+                    cp.next = pos.saturating_add(cp.interval);
+                    cp.calls = 0;
+                    cp.aborted = !(cp.hook)(pos);
+                }
+                if cp.aborted {
+                    return Err(ParseException::new(pos, "parse interrupted"));
+                }
+                Ok(())
+            }
+            Some(cp) => {
+                cp.calls += 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Context that records choice points, taking the `forced` alternatives at the first ones.
+    pub fn exploring(input: &'a str, forced: Vec<usize>) -> Self {
+        Self {
+            choices: Some(ChoiceTrace {
+                forced,
+                ..ChoiceTrace::default()
+            }),
+            ..Self::new(input)
+        }
+    }
+
+    /// The recorded choices, if this context is exploring.
+    pub fn take_choices(&mut self) -> Option<ChoiceTrace> {
+        self.choices.take()
+    }
+
+    #[inline(always)]
+    pub fn is_exploring(&self) -> bool {
+        self.choices.is_some()
+    }
+
+    /// Alternative forced at the next choice point, if any.
+    #[inline]
+    pub fn forced_choice(&self) -> Option<usize> {
+        let trace = self.choices.as_ref()?;
+        trace.forced.get(trace.taken.len()).copied()
+    }
+
+    /// Record taking `alt` of `count` alternatives at a choice point.
+    #[inline]
+    pub fn record_choice(&mut self, alt: usize, count: usize) {
+        if let Some(trace) = &mut self.choices {
+            trace.taken.push((alt, count));
+        }
+    }
+
+    /// Record an Optional that matched nothing.
+    #[inline]
+    pub fn record_optional_skip(&mut self) {
+        if let Some(trace) = &mut self.choices {
+            trace.optional_skips += 1;
+        }
+    }
+
+    #[inline(always)]
+    pub fn choice_mark(&self) -> ChoiceMark {
+        match &self.choices {
+            Some(trace) => ChoiceMark {
+                taken: trace.taken.len(),
+                optional_skips: trace.optional_skips,
+            },
+            None => ChoiceMark::default(),
+        }
+    }
+
+    /// Forget choices recorded since `mark` (used when a branch fails).
+    #[inline(always)]
+    pub fn rewind_choices(&mut self, mark: ChoiceMark) {
+        if let Some(trace) = &mut self.choices {
+            trace.taken.truncate(mark.taken);
+            trace.optional_skips = mark.optional_skips;
+        }
+    }
+
+    /// Parse `elem` at `loc`, reusing its earlier outcome there if packrat is
+    /// enabled. Exploring contexts always re-parse, to record their choices.
+    /// Fails fatally when parses are already nested recursion_limit() deep.
+    #[inline(always)]
+    pub fn parse(&mut self, elem: &Arc<dyn ParserElement>, loc: usize) -> ParseResult<'a> {
+        if self.depth >= self.depth_limit {
+            return Err(recursion_error(elem.as_ref(), loc, self.depth_limit));
+        }
+        self.depth += 1;
+        let outcome = if profile::enabled() {
+            self.parse_profiled(elem, loc)
+        } else if self.memo.is_none() || self.choices.is_some() {
+            elem.parse_impl(self, loc)
+        } else {
+            self.parse_memoized(elem, loc)
+        };
+        self.depth -= 1;
+        if self.record_matches {
+            if let Ok((end, _)) = &outcome {
+                self.match_log.push((element_key(elem), loc, *end));
+            }
+        }
+        if let (Err(e), Some(furthest)) = (&outcome, self.furthest_loc) {
+            if e.loc >= furthest {
+                self.record_furthest(elem.as_ref(), e);
+            }
+        }
+        outcome
+    }
+
+    /// Note a failure at or past the furthest one so far. Only leaf elements'
+    /// messages are kept: combinators repeat or join those of their children.
+    fn record_furthest(&mut self, elem: &dyn ParserElement, e: &ParseException) {
+        if self.furthest_loc.is_some_and(|furthest| e.loc > furthest) {
+            self.furthest_loc = Some(e.loc);
+            self.furthest_msgs.clear();
+        }
+        let leaf = !matches!(elem.parser_kind(), ParserKind::Complex | ParserKind::Group);
+        if leaf
+            && self.furthest_msgs.len() < MAX_FURTHEST_MESSAGES
+            && !self.furthest_msgs.iter().any(|msg| **msg == *e.msg)
+        {
+            self.furthest_msgs.push(e.msg.clone());
+        }
+    }
+
+    /// `e`, the error a top-level parse ended with, noting where parsing got
+    /// furthest when tracking found some element failing past it: "...; farthest failure at line 3
+    /// col 7, expected one of: 'if', 'while'".
+    pub fn with_furthest_failure(&self, mut e: ParseException) -> ParseException {
+        let Some(furthest) = self.furthest_loc.filter(|&furthest| furthest > e.loc) else {
+            return e;
+        };
+        let expected: Vec<&str> = self
+            .furthest_msgs
+            .iter()
+            .filter_map(|msg| msg.strip_prefix("Expected "))
+            .collect();
+        let (line, col) = line_col(self.input, furthest);
+        let note = match expected.as_slice() {
+            [] => format!("farthest failure at line {} col {}", line, col),
+            [what] => format!(
+                "farthest failure at line {} col {}, expected {}",
+                line, col, what
+            ),
+            _ => format!(
+                "farthest failure at line {} col {}, expected one of: {}",
+                line,
+                col,
+                expected.join(", ")
+            ),
+        };
+        e.msg = format!("{}; {}", e.msg, note).into();
+        e
+    }
+
+    /// parse() while profiling, kept out of line so the usual path stays small.
+    #[cold]
+    #[inline(never)]
+    fn parse_profiled(&mut self, elem: &Arc<dyn ParserElement>, loc: usize) -> ParseResult<'a> {
+        let started = profile::enter();
+        let outcome = if self.memo.is_none() || self.choices.is_some() {
+            elem.parse_impl(self, loc)
+        } else {
+            self.parse_memoized(elem, loc)
+        };
+        profile::exit(elem, started, profile::Call::Parse, outcome.is_ok());
+        outcome
+    }
+
+    fn parse_memoized(&mut self, elem: &Arc<dyn ParserElement>, loc: usize) -> ParseResult<'a> {
+        let key = self.memo_key(element_key(elem), loc);
+        let memo = self.memo.as_mut().expect("packrat enabled");
+        if !memo.is_cacheable(key.element, elem.as_ref()) {
+            return elem.parse_impl(self, loc);
+        }
+        if let Some(outcome) = memo.get(&key) {
+            return outcome;
+        }
+        let outcome = elem.parse_impl(self, loc);
+        if let Some(memo) = &mut self.memo {
+            memo.insert(key, outcome.clone());
+        }
+        outcome
+    }
+
+    fn memo_key(&self, element: usize, loc: usize) -> MemoKey {
+        MemoKey {
+            element,
+            loc,
+            skip_whitespace: self.skip_whitespace,
+            whitespace: self.whitespace,
+            keep_whitespace: self.keeps_whitespace_at(loc),
+            indent: self.indent,
+            ignore: (self.ignore.last().map_or(0, element_key), self.ignore.len()),
+        }
+    }
+
+    /// Whether left-recursive Forwards are grown with parse_growing().
+    #[inline(always)]
+    pub fn grows_left_recursion(&self) -> bool {
+        self.seeds.is_some()
+    }
+
+    /// Parse `elem`, the definition of the Forward identified by `forward`, at `loc`
+    /// by growing a seed: recursive calls at `loc` first fail, then see the previous
+    /// match, and the definition is re-parsed for as long as the match lengthens.
+    pub fn parse_growing(
+        &mut self,
+        forward: usize,
+        elem: &Arc<dyn ParserElement>,
+        loc: usize,
+    ) -> ParseResult<'a> {
+        let key = self.memo_key(forward, loc);
+        let seeds = self.seeds.as_mut().expect("left recursion enabled");
+        if let Some(outcome) = seeds.get(&key) {
+            return outcome.clone();
+        }
+        seeds.insert(
+            key,
+            Err(ParseException::new(loc, "left recursion not yet matched")),
+        );
+        let mut best = elem.parse_impl(self, loc);
+        if let Ok((mut best_end, _)) = best {
+            loop {
+                self.set_seed(key, best.clone());
+                match elem.parse_impl(self, loc) {
+                    Ok((end, res)) if end > best_end => {
+                        best_end = end;
+                        best = Ok((end, res));
+                    }
+                    Err(e) if e.fatal => return Err(e),
+                    _ => break,
+                }
+            }
+        }
+        self.set_seed(key, best.clone());
+        best
+    }
+
+    fn set_seed(&mut self, key: MemoKey, outcome: ParseResult<'a>) {
+        if let Some(seeds) = &mut self.seeds {
+            seeds.insert(key, outcome);
         }
     }
 
@@ -18,16 +400,260 @@ impl<'a> ParseContext<'a> {
     pub fn input(&self) -> &'a str {
         self.input
     }
+
+    /// Skip the active whitespace characters starting at `loc`, and any ignored
+    /// expressions among them.
+    #[inline(always)]
+    pub fn skip_ws(&self, loc: usize) -> usize {
+        if self.ignore.is_empty() {
+            return skip_ws_with(self.input, loc, self.whitespace);
+        }
+        skip_ignored(self.input, loc, self.whitespace, &self.ignore)
+    }
+
+    /// Where an element parsed at `loc` starts: past whitespace, unless skipping is
+    /// off, the element doesn't skip (`skips` is false) or whitespace at `loc` is kept.
+    #[inline(always)]
+    pub fn pre_skip(&self, loc: usize, skips: bool) -> usize {
+        if self.skip_whitespace && skips && !self.keeps_whitespace_at(loc) {
+            self.skip_ws(loc)
+        } else {
+            loc
+        }
+    }
+
+    #[inline(always)]
+    pub fn keeps_whitespace_at(&self, loc: usize) -> bool {
+        self.keep_whitespace_at == Some(loc)
+    }
+
+    /// Keep whitespace at `loc` (or nowhere) from being skipped; returns the previous
+    /// setting to restore.
+    #[inline]
+    pub fn keep_whitespace_at(&mut self, loc: Option<usize>) -> Option<usize> {
+        std::mem::replace(&mut self.keep_whitespace_at, loc)
+    }
+
+    /// Whether any ignore scope is active.
+    #[inline(always)]
+    pub fn has_ignore(&self) -> bool {
+        !self.ignore.is_empty()
+    }
+
+    /// Skip `exprs` too, until truncate_ignore() with the returned length.
+    pub fn push_ignore(&mut self, exprs: &[Arc<dyn ParserElement>]) -> usize {
+        let len = self.ignore.len();
+        self.ignore.extend_from_slice(exprs);
+        len
+    }
+
+    #[inline]
+    pub fn truncate_ignore(&mut self, len: usize) {
+        self.ignore.truncate(len);
+    }
+
+    /// Start (or stop) recording the span of every element parsed, wherever it is
+    /// nested, for last_match. Returns the previous setting.
+    #[inline]
+    pub fn record_matches(&mut self, record: bool) -> bool {
+        std::mem::replace(&mut self.record_matches, record)
+    }
+
+    /// Most recent span matched by the element identified by `key`.
+    #[inline]
+    pub fn last_match(&self, key: usize) -> Option<(usize, usize)> {
+        self.match_log
+            .iter()
+            .rev()
+            .find(|&&(k, _, _)| k == key)
+            .map(|&(_, start, end)| (start, end))
+    }
+
+    #[inline(always)]
+    pub fn match_log_len(&self) -> usize {
+        self.match_log.len()
+    }
+
+    /// Discard spans recorded after `len` (used when a sequence backtracks).
+    #[inline]
+    pub fn truncate_match_log(&mut self, len: usize) {
+        self.match_log.truncate(len);
+    }
 }
 
-/// Skip whitespace characters (space, tab, newline, carriage return) starting at `loc`.
+/// A set of ASCII characters skipped as whitespace between elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WhitespaceChars([u64; 2]);
+
+impl WhitespaceChars {
+    /// Space, tab, newline and carriage return, like pyparsing.
+    pub const DEFAULT: Self = Self([(1 << b' ') | (1 << b'\t') | (1 << b'\n') | (1 << b'\r'), 0]);
+
+    /// The set of `chars`, or None if any is not ASCII.
+    pub fn new(chars: &str) -> Option<Self> {
+        let mut bits = [0u64; 2];
+        for b in chars.bytes() {
+            if !b.is_ascii() {
+                return None;
+            }
+            bits[(b >> 6) as usize] |= 1 << (b & 63);
+        }
+        Some(Self(bits))
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, b: u8) -> bool {
+        b < 128 && self.0[(b >> 6) as usize] & (1 << (b & 63)) != 0
+    }
+
+    /// The characters in the set, in byte order.
+    pub fn chars(&self) -> String {
+        (0..128u8)
+            .filter(|&b| self.contains(b))
+            .map(char::from)
+            .collect()
+    }
+}
+
+/// Nesting of element parses allowed by default: deep enough for any sensible
+/// grammar and input, shallow enough for a 2 MiB thread stack.
+pub const DEFAULT_RECURSION_LIMIT: usize = 3000;
+
+static RECURSION_LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_RECURSION_LIMIT);
+
+/// How deeply element parses may nest before parsing fails.
+#[inline]
+pub fn recursion_limit() -> usize {
+    RECURSION_LIMIT.load(Ordering::Relaxed)
+}
+
+/// Change the nesting limit, for parses started afterwards.
+pub fn set_recursion_limit(limit: usize) {
+    RECURSION_LIMIT.store(limit.max(1), Ordering::Relaxed);
+}
+
+/// Nesting levels a Forward counts for in match-only checks, where only Forwards
+/// are counted: recursion passes through one, and the elements in between add
+/// stack too.
+const FORWARD_MATCH_LEVELS: usize = 4;
+
+thread_local! {
+    /// Nesting of match-only checks (try_match_at) on this thread, which recurse
+    /// without a ParseContext to count them.
+    static MATCH_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Run the match-only check `f` of a Forward's definition one level deeper, or
+/// give None without running it when checks are already nested recursion_limit()
+/// deep.
+#[inline]
+pub fn nested_match<T>(f: impl FnOnce() -> Option<T>) -> Option<T> {
+    let depth = MATCH_DEPTH.get();
+    if depth + FORWARD_MATCH_LEVELS > recursion_limit() {
+        return None;
+    }
+    MATCH_DEPTH.set(depth + FORWARD_MATCH_LEVELS);
+    let outcome = f();
+    MATCH_DEPTH.set(depth);
+    outcome
+}
+
+/// The fatal failure of a parse nested `limit` deep, naming the element that
+/// would have gone deeper. Fatal, so alternatives don't retry at every level.
+pub fn recursion_error(elem: &dyn ParserElement, loc: usize, limit: usize) -> ParseException {
+    let name = elem.name();
+    let mut shown: String = name.chars().take(60).collect();
+    if shown.len() < name.len() {
+        shown.push_str("...");
+    }
+    ParseException::new(
+        loc,
+        format!("Recursion limit of {} reached at {}", limit, shown),
+    )
+    .into_fatal()
+}
+
+static DEFAULT_WHITESPACE: [AtomicU64; 2] = [
+    AtomicU64::new(WhitespaceChars::DEFAULT.0[0]),
+    AtomicU64::new(WhitespaceChars::DEFAULT.0[1]),
+];
+/// Whether DEFAULT_WHITESPACE was ever changed; until then skip_ws uses a fixed set.
+static DEFAULT_WHITESPACE_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Whitespace skipped by elements without a set of their own.
+#[inline]
+pub fn default_whitespace() -> WhitespaceChars {
+    if !DEFAULT_WHITESPACE_CHANGED.load(Ordering::Relaxed) {
+        return WhitespaceChars::DEFAULT;
+    }
+    WhitespaceChars([
+        DEFAULT_WHITESPACE[0].load(Ordering::Relaxed),
+        DEFAULT_WHITESPACE[1].load(Ordering::Relaxed),
+    ])
+}
+
+/// Change the whitespace skipped by elements without a set of their own, for
+/// parses started afterwards.
+pub fn set_default_whitespace(chars: WhitespaceChars) {
+    DEFAULT_WHITESPACE[0].store(chars.0[0], Ordering::Relaxed);
+    DEFAULT_WHITESPACE[1].store(chars.0[1], Ordering::Relaxed);
+    DEFAULT_WHITESPACE_CHANGED.store(true, Ordering::Relaxed);
+}
+
+/// Skip default whitespace characters starting at `loc`.
 /// Returns the position of the first non-whitespace character.
 #[inline(always)]
 pub fn skip_ws(input: &str, loc: usize) -> usize {
+    skip_ws_with(input, loc, default_whitespace())
+}
+
+thread_local! {
+    /// Set while matching an ignored expression. Ignore scopes inside it only skip
+    /// whitespace, so an expression that ignores itself can't recurse forever.
+    static SKIPPING_IGNORED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Skip characters in `whitespace` and matches of `ignore` starting at `loc`, for
+/// as long as either advances.
+pub fn skip_ignored(
+    input: &str,
+    loc: usize,
+    whitespace: WhitespaceChars,
+    ignore: &[Arc<dyn ParserElement>],
+) -> usize {
+    let mut pos = skip_ws_with(input, loc, whitespace);
+    if ignore.is_empty() || SKIPPING_IGNORED.replace(true) {
+        return pos;
+    }
+    'skip: loop {
+        for expr in ignore {
+            match expr.try_match_at(input, pos) {
+                Some(end) if end > pos => {
+                    pos = skip_ws_with(input, end, whitespace);
+                    continue 'skip;
+                }
+                _ => {}
+            }
+        }
+        break;
+    }
+    SKIPPING_IGNORED.set(false);
+    pos
+}
+
+/// Skip characters in `whitespace` starting at `loc`.
+#[inline(always)]
+pub fn skip_ws_with(input: &str, loc: usize, whitespace: WhitespaceChars) -> usize {
     let bytes = input.as_bytes();
     let mut pos = loc;
-    while pos < bytes.len() && matches!(bytes[pos], b' ' | b'\t' | b'\n' | b'\r') {
-        pos += 1;
+    if whitespace == WhitespaceChars::DEFAULT {
+        while pos < bytes.len() && matches!(bytes[pos], b' ' | b'\t' | b'\n' | b'\r') {
+            pos += 1;
+        }
+    } else {
+        while pos < bytes.len() && whitespace.contains(bytes[pos]) {
+            pos += 1;
+        }
     }
     pos
 }
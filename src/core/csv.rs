@@ -0,0 +1,89 @@
+use std::io::{self, BufRead};
+
+/// Streaming reader of RFC 4180 records: fields split on `delimiter`, quoted fields
+/// may contain delimiters, line breaks and doubled quotes (`""` for `"`). Blank lines
+/// are skipped. Text after a closing quote is kept, as Python's csv module does.
+pub struct CsvReader<R> {
+    reader: R,
+    delimiter: char,
+    line: String,
+    /// 1-based number of the last physical line read
+    line_number: usize,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    pub fn new(reader: R, delimiter: char) -> Self {
+        Self {
+            reader,
+            delimiter,
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    fn read_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        let n = self.reader.read_line(&mut self.line)?;
+        self.line_number += 1;
+        Ok(n > 0)
+    }
+
+    /// Read the next record into `fields`. Ok(false) at the end of the input; an
+    /// InvalidData error for a quoted field left open at the end of the input.
+    pub fn read_record(&mut self, fields: &mut Vec<String>) -> io::Result<bool> {
+        fields.clear();
+        loop {
+            if !self.read_line()? {
+                return Ok(false);
+            }
+            if !self.line.trim_end_matches(['\r', '\n']).is_empty() {
+                break;
+            }
+        }
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut at_field_start = true;
+        loop {
+            let mut chars = self.line.chars().peekable();
+            while let Some(c) = chars.next() {
+                if in_quotes {
+                    match c {
+                        '"' if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        '"' => in_quotes = false,
+                        _ => field.push(c),
+                    }
+                    continue;
+                }
+                match c {
+                    '\r' | '\n' => break,
+                    c if c == self.delimiter => {
+                        fields.push(std::mem::take(&mut field));
+                        at_field_start = true;
+                        continue;
+                    }
+                    '"' if at_field_start => in_quotes = true,
+                    _ => field.push(c),
+                }
+                at_field_start = false;
+            }
+            if !in_quotes {
+                fields.push(field);
+                return Ok(true);
+            }
+            // A quoted field continues on the next line
+            if !self.read_line()? {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unterminated quoted field",
+                ));
+            }
+        }
+    }
+}
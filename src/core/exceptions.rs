@@ -1,10 +1,16 @@
+use smallvec::SmallVec;
 use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct ParseException {
     pub loc: usize,
     pub msg: Arc<str>,
+    /// Raised past an And's error stop (pyparsing's `-`): alternatives and
+    /// repetitions propagate it instead of backtracking
+    pub fatal: bool,
 }
 
 impl ParseException {
@@ -12,8 +18,88 @@ impl ParseException {
         Self {
             loc,
             msg: msg.into(),
+            fatal: false,
         }
     }
+
+    pub fn into_fatal(mut self) -> Self {
+        self.fatal = true;
+        self
+    }
+}
+
+/// The most useful failure among alternatives tried at the same position: the
+/// one that got furthest into the input. Alternatives failing at that same
+/// furthest position are reported together ("Expected 'if' | 'while' | identifier").
+#[derive(Default)]
+pub struct FurthestFailure {
+    errors: SmallVec<[ParseException; 4]>,
+}
+
+impl FurthestFailure {
+    pub fn record(&mut self, e: ParseException) {
+        match self.errors.first() {
+            Some(first) if e.loc < first.loc => {}
+            Some(first) if e.loc > first.loc => {
+                self.errors.clear();
+                self.errors.push(e);
+            }
+            _ => self.errors.push(e),
+        }
+    }
+
+    /// The furthest failure, or a generic one at `loc` if nothing was recorded.
+    pub fn into_error(mut self, loc: usize) -> ParseException {
+        if self.errors.len() <= 1 {
+            return self
+                .errors
+                .pop()
+                .unwrap_or_else(|| ParseException::new(loc, "No match found"));
+        }
+        let mut expected: Vec<&str> = Vec::with_capacity(self.errors.len());
+        for e in &self.errors {
+            if let Some(what) = e.msg.strip_prefix("Expected ") {
+                if !expected.contains(&what) {
+                    expected.push(what);
+                }
+            }
+        }
+        match expected.len() {
+            0 => self.errors.swap_remove(0),
+            1 => self
+                .errors
+                .iter()
+                .find(|e| e.msg.starts_with("Expected "))
+                .cloned()
+                .unwrap(),
+            _ => ParseException::new(
+                self.errors[0].loc,
+                format!("Expected {}", expected.join(" | ")),
+            ),
+        }
+    }
+}
+
+/// 1-based line number and column of byte offset `loc` in `input`. The column
+/// counts characters; an offset inside a character is that character's column.
+pub fn line_col(input: &str, loc: usize) -> (usize, usize) {
+    let mut loc = loc.min(input.len());
+    while !input.is_char_boundary(loc) {
+        loc -= 1;
+    }
+    let before = &input.as_bytes()[..loc];
+    let line_start = memchr::memrchr(b'\n', before).map_or(0, |i| i + 1);
+    let line = memchr::memchr_iter(b'\n', before).count() + 1;
+    (line, input[line_start..loc].chars().count() + 1)
+}
+
+/// The line of `input` containing byte offset `loc`, without its newline.
+pub fn line_at(input: &str, loc: usize) -> &str {
+    let loc = loc.min(input.len());
+    let bytes = input.as_bytes();
+    let start = memchr::memrchr(b'\n', &bytes[..loc]).map_or(0, |i| i + 1);
+    let end = memchr::memchr(b'\n', &bytes[loc..]).map_or(input.len(), |i| loc + i);
+    &input[start..end]
 }
 
 impl fmt::Display for ParseException {
@@ -23,3 +109,57 @@ impl fmt::Display for ParseException {
 }
 
 impl std::error::Error for ParseException {}
+
+/// Attempt to reconfigure an element after its grammar was frozen.
+#[derive(Debug, Clone)]
+pub struct FrozenError {
+    pub msg: Arc<str>,
+}
+
+impl FrozenError {
+    pub fn new(msg: impl Into<Arc<str>>) -> Self {
+        Self { msg: msg.into() }
+    }
+}
+
+impl fmt::Display for FrozenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FrozenError: {}", self.msg)
+    }
+}
+
+impl std::error::Error for FrozenError {}
+
+/// I/O failure while reading an input file.
+#[derive(Debug)]
+pub struct FileError {
+    pub path: PathBuf,
+    /// 1-based line being processed, when known
+    pub line: Option<usize>,
+    pub source: io::Error,
+}
+
+impl FileError {
+    pub fn new(path: impl AsRef<Path>, source: io::Error) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            line: None,
+            source,
+        }
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.source),
+            None => write!(f, "{}: {}", self.path.display(), self.source),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
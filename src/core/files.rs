@@ -0,0 +1,72 @@
+use crate::core::exceptions::FileError;
+use std::io;
+use std::path::Path;
+
+/// Text encodings a file can be read with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    /// ISO-8859-1: every byte is the code point of the same value.
+    Latin1,
+    Ascii,
+}
+
+impl TextEncoding {
+    /// The encoding for a Python-style codec name, e.g. `"utf-8"`, `"UTF8"`,
+    /// `"latin-1"` or `"iso-8859-1"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase().replace(['-', ' '], "_");
+        match name.as_str() {
+            "utf_8" | "utf8" | "u8" => Some(Self::Utf8),
+            "latin_1" | "latin1" | "latin" | "l1" | "iso_8859_1" | "iso8859_1" | "8859" => {
+                Some(Self::Latin1)
+            }
+            "ascii" | "us_ascii" => Some(Self::Ascii),
+            _ => None,
+        }
+    }
+
+    /// Decode `bytes`, or hand them back with the offset of the first byte that
+    /// doesn't decode.
+    pub fn decode(self, bytes: Vec<u8>) -> Result<String, (Vec<u8>, usize)> {
+        match self {
+            Self::Utf8 => String::from_utf8(bytes).map_err(|e| {
+                let at = e.utf8_error().valid_up_to();
+                (e.into_bytes(), at)
+            }),
+            Self::Ascii => match bytes.iter().position(|b| !b.is_ascii()) {
+                Some(at) => Err((bytes, at)),
+                None => String::from_utf8(bytes).map_err(|e| (e.into_bytes(), 0)),
+            },
+            Self::Latin1 => match bytes.is_ascii() {
+                true => String::from_utf8(bytes).map_err(|e| (e.into_bytes(), 0)),
+                false => Ok(bytes.iter().map(|&b| b as char).collect()),
+            },
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Latin1 => "latin-1",
+            Self::Ascii => "ascii",
+        }
+    }
+}
+
+/// The whole text of the file at `path`, decoded with `encoding`. Undecodable bytes
+/// are an `InvalidData` error carrying the 1-based line they are on.
+pub fn read_text_file(path: &Path, encoding: TextEncoding) -> Result<String, FileError> {
+    let bytes = std::fs::read(path).map_err(|e| FileError::new(path, e))?;
+    encoding.decode(bytes).map_err(|(bytes, at)| {
+        let msg = format!(
+            "'{}' codec can't decode byte 0x{:02x} at offset {}",
+            encoding.name(),
+            bytes[at],
+            at
+        );
+        let mut err = FileError::new(path, io::Error::new(io::ErrorKind::InvalidData, msg));
+        err.line = Some(memchr::memchr_iter(b'\n', &bytes[..at]).count() + 1);
+        err
+    })
+}
@@ -0,0 +1,169 @@
+use crate::core::context::{skip_ws, ParseContext};
+use crate::core::parser::ParserElement;
+use std::fmt;
+
+/// Upper bound on random repetition counts for ZeroOrMore / OneOrMore.
+pub const MAX_REPEAT: usize = 3;
+
+/// Attempts at producing a sample the grammar accepts before giving up.
+const MAX_ATTEMPTS: usize = 100;
+
+/// Error raised when a grammar cannot produce sample input.
+#[derive(Debug, Clone)]
+pub struct GenerateError {
+    pub msg: String,
+}
+
+impl GenerateError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+
+    /// Error for an element that has no generator, named as `str()` names it.
+    pub fn unsupported(name: &str) -> Self {
+        Self::new(format!("cannot generate input for {}", name))
+    }
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GenerateError: {}", self.msg)
+    }
+}
+
+impl std::error::Error for GenerateError {}
+
+/// Seeded random text builder that elements write sample input into.
+pub struct Generator {
+    state: u64,
+    out: String,
+    depth: usize,
+    max_depth: usize,
+    /// Set inside Combine: emit elements back to back, without separators
+    pub adjacent: bool,
+    /// A separator is owed before the next emitted text
+    pending_space: bool,
+}
+
+/// Saved output state for abandoning a partially generated alternative.
+#[derive(Clone, Copy)]
+pub struct Mark {
+    len: usize,
+    pending_space: bool,
+}
+
+impl Generator {
+    pub fn new(seed: u64, max_depth: usize) -> Self {
+        Self {
+            state: seed,
+            out: String::new(),
+            depth: 0,
+            max_depth,
+            adjacent: false,
+            pending_space: false,
+        }
+    }
+
+    /// splitmix64
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..n` (`n` must be non-zero).
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Uniform value in `lo..=hi`.
+    pub fn range(&mut self, lo: usize, hi: usize) -> usize {
+        lo + self.below(hi - lo + 1)
+    }
+
+    pub fn coin(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.flush_space();
+        self.out.push_str(s);
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.flush_space();
+        self.out.push(c);
+    }
+
+    /// Request a space before the next token so adjacent elements don't run
+    /// together. Deferred, so elements that emit nothing leave no stray space.
+    pub fn separate(&mut self) {
+        if !self.adjacent && !self.out.is_empty() {
+            self.pending_space = true;
+        }
+    }
+
+    fn flush_space(&mut self) {
+        if std::mem::take(&mut self.pending_space) {
+            self.out.push(' ');
+        }
+    }
+
+    pub fn mark(&self) -> Mark {
+        Mark {
+            len: self.out.len(),
+            pending_space: self.pending_space,
+        }
+    }
+
+    /// Discard output written since `mark` (used when an alternative is abandoned).
+    pub fn rollback(&mut self, mark: Mark) {
+        self.out.truncate(mark.len);
+        self.pending_space = mark.pending_space;
+    }
+
+    /// Generate a nested element, enforcing `max_depth`.
+    pub fn descend(&mut self, elem: &dyn ParserElement) -> Result<(), GenerateError> {
+        if self.depth >= self.max_depth {
+            return Err(GenerateError::new(format!(
+                "grammar nesting exceeds max_depth {} (recursive Forward?)",
+                self.max_depth
+            )));
+        }
+        self.depth += 1;
+        let result = elem.generate(self);
+        self.depth -= 1;
+        result
+    }
+}
+
+/// Produce a random string that `parser` accepts in full.
+/// Candidates the grammar rejects (e.g. an earlier MatchFirst alternative
+/// shadowing the chosen one) are discarded and regenerated.
+pub fn generate_sample(
+    parser: &dyn ParserElement,
+    gen: &mut Generator,
+) -> Result<String, GenerateError> {
+    for _ in 0..MAX_ATTEMPTS {
+        gen.out.clear();
+        gen.adjacent = false;
+        gen.pending_space = false;
+        gen.descend(parser)?;
+        let sample = std::mem::take(&mut gen.out);
+        let start = skip_ws(&sample, 0);
+        let parsed = parser
+            .parse_impl(&mut ParseContext::new(&sample), start)
+            .map(|(end, _)| end);
+        if let Ok(end) = parsed {
+            if skip_ws(&sample, end) == sample.len() {
+                return Ok(sample);
+            }
+        }
+    }
+    Err(GenerateError::new(format!(
+        "no parseable sample after {} attempts",
+        MAX_ATTEMPTS
+    )))
+}
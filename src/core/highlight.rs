@@ -0,0 +1,61 @@
+use crate::core::parser::ParserElement;
+use rustc_hash::FxHashSet;
+use std::sync::Arc;
+
+/// A highlighted region: `(start, end, class)` in byte offsets.
+pub type HighlightSpan = (usize, usize, Arc<str>);
+
+/// Tagged elements reachable from `root`, in declaration (pre-order) order.
+/// Elements nested inside a tagged element belong to it and are not collected.
+fn tagged_elements(root: &Arc<dyn ParserElement>) -> Vec<(Arc<dyn ParserElement>, Arc<str>)> {
+    fn walk(
+        elem: &Arc<dyn ParserElement>,
+        seen: &mut FxHashSet<usize>,
+        out: &mut Vec<(Arc<dyn ParserElement>, Arc<str>)>,
+    ) {
+        if !seen.insert(Arc::as_ptr(elem) as *const () as usize) {
+            return;
+        }
+        if let Some(class) = elem.highlight_class() {
+            out.push((elem.clone(), class.clone()));
+            return;
+        }
+        elem.visit_children(&mut |child| walk(child, seen, out));
+    }
+    let mut out = Vec::new();
+    walk(root, &mut FxHashSet::default(), &mut out);
+    out
+}
+
+/// Scan `input` left to right with every tagged element in `root`. At each position
+/// the longest non-empty match wins, ties going to the first declared element; the
+/// scan then resumes at its end. Untagged or unmatched text produces no span.
+pub fn highlight_spans(root: &Arc<dyn ParserElement>, input: &str) -> Vec<HighlightSpan> {
+    let tagged = tagged_elements(root);
+    let bytes = input.as_bytes();
+    let mut spans = Vec::new();
+    let mut loc = 0;
+    while loc < bytes.len() {
+        // Never start a match in the middle of a UTF-8 sequence
+        if bytes[loc] & 0xC0 == 0x80 {
+            loc += 1;
+            continue;
+        }
+        let mut best: Option<(usize, &Arc<str>)> = None;
+        for (elem, class) in &tagged {
+            if let Some(end) = elem.try_match_at(input, loc) {
+                if end > loc && best.is_none_or(|(best_end, _)| end > best_end) {
+                    best = Some((end, class));
+                }
+            }
+        }
+        match best {
+            Some((end, class)) => {
+                spans.push((loc, end, class.clone()));
+                loc = end;
+            }
+            None => loc += 1,
+        }
+    }
+    spans
+}
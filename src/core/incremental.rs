@@ -0,0 +1,199 @@
+use crate::core::context::skip_ws;
+use crate::core::parser::ParserElement;
+use crate::core::profile;
+use std::sync::Arc;
+
+/// A match span `(start, end)` in byte offsets.
+pub type Span = (usize, usize);
+
+/// Spans removed from (old offsets) and added to (new offsets) the match set by an edit.
+/// Matches that only moved with the text around them are not reported.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EditDelta {
+    pub removed: Vec<Span>,
+    pub added: Vec<Span>,
+}
+
+/// Invalid edit arguments.
+#[derive(Debug, Clone)]
+pub struct EditError {
+    pub msg: String,
+}
+
+/// Caches the spans a scan_string-style scan finds in a text and updates them
+/// after edits by re-scanning only a window around the edited region.
+///
+/// The window starts `context_lines` non-blank lines before the edit, backed up
+/// to the start of any match reaching that far, and ends at the first match past
+/// the end of the edited line that the previous scan also found. This is exact
+/// unless a match attempt starting before the window reads past it into the edit
+/// (e.g. a sequence spread over more lines than `context_lines`).
+pub struct IncrementalScanner {
+    parser: Arc<dyn ParserElement>,
+    text: String,
+    spans: Vec<Span>,
+    context_lines: usize,
+}
+
+/// Scan `text` from `from` like scan_string: leftmost non-empty matches, starting
+/// past any whitespace the parser skips, resuming after each one. Stops early once
+/// `stop` accepts a match, which is not included.
+fn scan(
+    parser: &Arc<dyn ParserElement>,
+    text: &str,
+    from: usize,
+    mut stop: impl FnMut(Span) -> bool,
+) -> (Vec<Span>, Option<Span>) {
+    let mut spans = Vec::new();
+    let mut loc = from;
+    while loc < text.len() {
+        let start = match parser.skip_whitespace_before() {
+            true => skip_ws(text, loc),
+            false => loc,
+        };
+        let found = match text.is_char_boundary(start) {
+            true => profile::try_match(parser, text, start),
+            false => None,
+        };
+        match found {
+            Some(end) if end > start => {
+                if stop((start, end)) {
+                    return (spans, Some((start, end)));
+                }
+                spans.push((start, end));
+                loc = end;
+            }
+            // Every loc up to `start` skips to the same failed attempt
+            _ => loc = start + 1,
+        }
+    }
+    (spans, None)
+}
+
+impl IncrementalScanner {
+    pub fn new(parser: Arc<dyn ParserElement>, context_lines: usize) -> Self {
+        Self {
+            parser,
+            text: String::new(),
+            spans: Vec::new(),
+            context_lines,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Replace the whole text and scan it from scratch.
+    pub fn set_text(&mut self, text: String) {
+        self.spans = scan(&self.parser, &text, 0, |_| false).0;
+        self.text = text;
+    }
+
+    /// Replace `old_len` bytes at `start` with `new_text` and update the cached spans.
+    pub fn apply_edit(
+        &mut self,
+        start: usize,
+        old_len: usize,
+        new_text: &str,
+    ) -> Result<EditDelta, EditError> {
+        let old_end = start
+            .checked_add(old_len)
+            .filter(|&end| end <= self.text.len())
+            .ok_or_else(|| EditError {
+                msg: format!(
+                    "edit {}..{} is out of range for text of length {}",
+                    start,
+                    start.saturating_add(old_len),
+                    self.text.len()
+                ),
+            })?;
+        if !self.text.is_char_boundary(start) || !self.text.is_char_boundary(old_end) {
+            return Err(EditError {
+                msg: "edit boundaries must fall on character boundaries".to_string(),
+            });
+        }
+
+        // Re-scan from a line boundary before the edit. Blank lines don't count, as
+        // matches skip them like any whitespace.
+        let bytes = self.text.as_bytes();
+        let line_start = |pos: usize| memchr::memrchr(b'\n', &bytes[..pos]).map_or(0, |i| i + 1);
+        let mut from = line_start(start);
+        let mut context = self.context_lines;
+        while context > 0 && from > 0 {
+            let prev = line_start(from - 1);
+            if !bytes[prev..from].iter().all(u8::is_ascii_whitespace) {
+                context -= 1;
+            }
+            from = prev;
+        }
+        // Back up to the start of any match reaching the window, which a match
+        // ending right at it may do by looking at the next character
+        let mut first = self.spans.partition_point(|&(_, e)| e < from);
+        while let Some(&(s, _)) = self.spans.get(first) {
+            if s >= from {
+                break;
+            }
+            from = line_start(s);
+            first = self.spans.partition_point(|&(_, e)| e < from);
+        }
+
+        self.text.replace_range(start..old_end, new_text);
+        let new_end = start + new_text.len();
+        let shift = |pos: usize| pos + new_text.len() - old_len;
+        // Matches past the end of the edited line only look back within that line
+        let stable_from = memchr::memchr(b'\n', &self.text.as_bytes()[new_end..])
+            .map_or(self.text.len(), |i| new_end + i + 1);
+
+        // Old spans after the edit, in new offsets, for spotting where the scans converge
+        let tail = self.spans.partition_point(|&(s, _)| s < old_end);
+        let old_after: Vec<Span> = self.spans[tail..]
+            .iter()
+            .map(|&(s, e)| (shift(s), shift(e)))
+            .collect();
+        let (fresh, resync) = scan(&self.parser, &self.text, from, |(s, e)| {
+            s >= stable_from && old_after.binary_search(&(s, e)).is_ok()
+        });
+        let resume = resync.map_or(old_after.len(), |span| {
+            old_after.binary_search(&span).unwrap()
+        });
+
+        // Old spans replaced by the re-scan, mapped to new offsets where they survive the edit
+        let replaced = &self.spans[first..tail + resume];
+        let mapped: Vec<Option<Span>> = replaced
+            .iter()
+            .map(|&(s, e)| {
+                if e <= start {
+                    Some((s, e))
+                } else if s >= old_end {
+                    Some((shift(s), shift(e)))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let delta = EditDelta {
+            removed: replaced
+                .iter()
+                .zip(&mapped)
+                .filter(|(_, m)| m.is_none_or(|span| fresh.binary_search(&span).is_err()))
+                .map(|(&span, _)| span)
+                .collect(),
+            added: fresh
+                .iter()
+                .filter(|span| !mapped.contains(&Some(**span)))
+                .copied()
+                .collect(),
+        };
+
+        let mut spans = self.spans[..first].to_vec();
+        spans.extend(fresh);
+        spans.extend_from_slice(&old_after[resume..]);
+        self.spans = spans;
+        Ok(delta)
+    }
+}
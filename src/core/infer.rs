@@ -0,0 +1,310 @@
+/// Characters accepted by an inferred Word, the narrowest covering a column's samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CharClass {
+    Nums,
+    Alphas,
+    Alphanums,
+    /// Alphanumerics and '_'
+    Identifier,
+}
+
+impl CharClass {
+    pub fn chars(self) -> String {
+        let alphas = ('a'..='z').chain('A'..='Z');
+        match self {
+            CharClass::Nums => ('0'..='9').collect(),
+            CharClass::Alphas => alphas.collect(),
+            CharClass::Alphanums => alphas.chain('0'..='9').collect(),
+            CharClass::Identifier => alphas.chain('0'..='9').chain(['_']).collect(),
+        }
+    }
+
+    /// The expression for the characters, as written in Python.
+    fn describe(self) -> &'static str {
+        match self {
+            CharClass::Nums => "nums()",
+            CharClass::Alphas => "alphas()",
+            CharClass::Alphanums => "alphanums()",
+            CharClass::Identifier => "alphanums() + \"_\"",
+        }
+    }
+}
+
+/// Matches a run of word characters as tokenized here, for words with non-ASCII
+/// characters (Regex's `\w` is ASCII-only).
+pub const WORD_PATTERN: &str = r"[\p{Alphabetic}\p{N}_]+";
+
+/// What an inferred column matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Piece {
+    Literal(String),
+    Word(CharClass),
+    Regex(&'static str),
+}
+
+/// One element of an inferred sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub piece: Piece,
+    /// Whether some samples lack this region
+    pub optional: bool,
+}
+
+/// Elements matched one after another, whitespace skipped between them.
+pub type Sequence = Vec<Column>;
+
+/// A region of a sample: a run of word characters or one other non-space character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Word(&'a str),
+    Punct(char),
+}
+
+impl Token<'_> {
+    fn aligns_with(self, other: Token<'_>) -> bool {
+        match (self, other) {
+            (Token::Word(_), Token::Word(_)) => true,
+            (Token::Punct(a), Token::Punct(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn tokenize(sample: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = sample.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if !is_word_char(c) {
+            tokens.push(Token::Punct(c));
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, c)) = chars.peek() {
+            if !is_word_char(c) {
+                break;
+            }
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        tokens.push(Token::Word(&sample[start..end]));
+    }
+    tokens
+}
+
+/// A column while aligning: the token kind and each sample's text in it, if any.
+struct Slot<'a> {
+    token: Token<'a>,
+    texts: Vec<Option<&'a str>>,
+}
+
+impl<'a> Slot<'a> {
+    fn new(token: Token<'a>, sample: usize, samples: usize) -> Self {
+        let mut texts = vec![None; samples];
+        texts[sample] = Some(token_text(token));
+        Self { token, texts }
+    }
+
+    /// Alignment score of putting `token` in this slot: punctuation anchors the
+    /// alignment more than words, and a word seen before more than a new one.
+    fn score(&self, token: Token<'_>) -> Option<usize> {
+        if !self.token.aligns_with(token) {
+            return None;
+        }
+        Some(match token {
+            Token::Punct(_) => 3,
+            Token::Word(w) if self.texts.contains(&Some(w)) => 3,
+            Token::Word(_) => 2,
+        })
+    }
+
+    fn column(&self) -> Column {
+        let present: Vec<&str> = self.texts.iter().flatten().copied().collect();
+        let piece = match self.token {
+            Token::Punct(c) => Piece::Literal(c.to_string()),
+            _ if present.iter().all(|&w| w == present[0]) => Piece::Literal(present[0].to_string()),
+            _ => word_piece(&present),
+        };
+        Column {
+            piece,
+            optional: present.len() < self.texts.len(),
+        }
+    }
+}
+
+fn token_text(token: Token<'_>) -> &str {
+    match token {
+        Token::Word(w) => w,
+        // Only words are compared by text
+        Token::Punct(_) => "",
+    }
+}
+
+/// The narrowest piece matching every word in `words`.
+fn word_piece(words: &[&str]) -> Piece {
+    let chars = || words.iter().flat_map(|w| w.chars());
+    if !chars().all(|c| c.is_ascii()) {
+        return Piece::Regex(WORD_PATTERN);
+    }
+    let class = chars()
+        .map(|c| match c {
+            '0'..='9' => CharClass::Nums,
+            '_' => CharClass::Identifier,
+            _ => CharClass::Alphas,
+        })
+        .fold(None, |acc: Option<CharClass>, class| match acc {
+            None => Some(class),
+            Some(acc) if acc == class => Some(acc),
+            Some(acc) => Some(acc.max(class).max(CharClass::Alphanums)),
+        });
+    Piece::Word(class.unwrap_or(CharClass::Alphanums))
+}
+
+/// Align `tokens` (of sample `sample`) with `slots`, maximizing the total score,
+/// and add them: matched tokens fill their slot, others get a new slot.
+fn align<'a>(slots: &mut Vec<Slot<'a>>, tokens: &[Token<'a>], sample: usize, samples: usize) {
+    let (n, m) = (slots.len(), tokens.len());
+    // best[i][j]: best score aligning slots[i..] with tokens[j..]
+    let mut best = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            let skip = best[i + 1][j].max(best[i][j + 1]);
+            best[i][j] = match slots[i].score(tokens[j]) {
+                Some(score) => skip.max(score + best[i + 1][j + 1]),
+                None => skip,
+            };
+        }
+    }
+    let mut merged = Vec::with_capacity(n.max(m));
+    let mut old = std::mem::take(slots).into_iter().peekable();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        let score = match (old.peek(), tokens.get(j)) {
+            (Some(slot), Some(&token)) => slot.score(token),
+            _ => None,
+        };
+        if score.is_some_and(|score| best[i][j] == score + best[i + 1][j + 1]) {
+            let mut slot = old.next().expect("slot in range");
+            slot.texts[sample] = Some(token_text(tokens[j]));
+            merged.push(slot);
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || best[i][j] == best[i + 1][j]) {
+            merged.push(old.next().expect("slot in range"));
+            i += 1;
+        } else {
+            merged.push(Slot::new(tokens[j], sample, samples));
+            j += 1;
+        }
+    }
+    *slots = merged;
+}
+
+/// All samples aligned into one sequence, with Optional columns where some lack a region.
+fn aligned(tokenized: &[Vec<Token<'_>>]) -> Sequence {
+    let mut slots = Vec::new();
+    for (sample, tokens) in tokenized.iter().enumerate() {
+        align(&mut slots, tokens, sample, tokenized.len());
+    }
+    slots.iter().map(Slot::column).collect()
+}
+
+/// One sequence per token layout, longest first so no alternative stops at a
+/// prefix of a longer sample.
+fn per_layout(tokenized: &[Vec<Token<'_>>]) -> Vec<Sequence> {
+    let mut layouts: Vec<Vec<&[Token<'_>]>> = Vec::new();
+    for tokens in tokenized {
+        let layout = layouts.iter_mut().find(|l| {
+            l[0].len() == tokens.len() && l[0].iter().zip(tokens).all(|(a, b)| a.aligns_with(*b))
+        });
+        match layout {
+            Some(layout) => layout.push(tokens),
+            None => layouts.push(vec![tokens]),
+        }
+    }
+    layouts.sort_by_key(|l| std::cmp::Reverse(l[0].len()));
+    layouts
+        .iter()
+        .map(|samples| {
+            (0..samples[0].len())
+                .map(|col| {
+                    let mut slot = Slot::new(samples[0][col], 0, samples.len());
+                    for (sample, tokens) in samples.iter().enumerate().skip(1) {
+                        slot.texts[sample] = Some(token_text(tokens[col]));
+                    }
+                    slot.column()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Each distinct sample as a literal, longest first.
+fn literals(samples: &[&str]) -> Vec<Sequence> {
+    let mut texts: Vec<&str> = samples.iter().map(|s| s.trim()).collect();
+    texts.sort_by_key(|t| std::cmp::Reverse(t.len()));
+    texts.dedup();
+    texts
+        .into_iter()
+        .map(|t| {
+            vec![Column {
+                piece: Piece::Literal(t.to_string()),
+                optional: false,
+            }]
+        })
+        .collect()
+}
+
+/// Candidate grammars for `samples` (each a list of alternatives), most general
+/// first: all samples aligned into one sequence, one sequence per token layout,
+/// and each sample as a literal. Every sample matches the last. Samples must not
+/// be blank.
+pub fn candidates(samples: &[&str]) -> Vec<Vec<Sequence>> {
+    let tokenized: Vec<Vec<Token<'_>>> = samples.iter().map(|s| tokenize(s)).collect();
+    let mut out = vec![vec![aligned(&tokenized)]];
+    let layouts = per_layout(&tokenized);
+    if layouts.len() > 1 {
+        out.push(layouts);
+    }
+    out.push(literals(samples));
+    out
+}
+
+/// The grammar as a Python expression, e.g. `Word(nums()) + "-" + Word(nums())`.
+pub fn describe(alternatives: &[Sequence]) -> String {
+    let sequences: Vec<String> = alternatives
+        .iter()
+        .map(|seq| {
+            let text = seq
+                .iter()
+                .map(describe_column)
+                .collect::<Vec<_>>()
+                .join(" + ");
+            if alternatives.len() > 1 && seq.len() > 1 {
+                format!("({})", text)
+            } else {
+                text
+            }
+        })
+        .collect();
+    sequences.join(" | ")
+}
+
+fn describe_column(column: &Column) -> String {
+    let piece = match &column.piece {
+        Piece::Literal(text) => format!("{:?}", text),
+        Piece::Word(class) => format!("Word({})", class.describe()),
+        Piece::Regex(pattern) => format!("Regex(r\"{}\")", pattern),
+    };
+    if column.optional {
+        format!("Optional({})", piece)
+    } else {
+        piece
+    }
+}
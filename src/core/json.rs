@@ -0,0 +1,121 @@
+use crate::core::exceptions::FileError;
+use crate::core::results::{NamedValue, OpaqueValue, ParseResultItem, ParseResults};
+use serde_json::{Map, Value};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// JSON for the values parse actions return, which only the caller can convert
+/// (e.g. Python objects). `bool_or_null` is the fallback.
+pub type ObjectJson<'f> = &'f dyn Fn(&OpaqueValue) -> Value;
+
+/// A bool as itself; any other value as null.
+pub fn bool_or_null(value: &OpaqueValue) -> Value {
+    value
+        .0
+        .downcast_ref::<bool>()
+        .map_or(Value::Null, |&b| b.into())
+}
+
+/// `item` as JSON: a token as a string, a number as a number (null for NaN and the
+/// infinities, which JSON can't hold), a group as a list.
+pub fn item_json(item: &ParseResultItem, object: ObjectJson) -> Value {
+    match item {
+        ParseResultItem::Token(t) => Value::String(String::from(&**t)),
+        ParseResultItem::Int(v) => (*v).into(),
+        ParseResultItem::Float(v) => {
+            serde_json::Number::from_f64(*v).map_or(Value::Null, Value::Number)
+        }
+        ParseResultItem::Group(inner) => items_json(inner.items(), object),
+        ParseResultItem::Object(value) => object(value),
+    }
+}
+
+pub fn items_json(items: &[ParseResultItem], object: ObjectJson) -> Value {
+    Value::Array(items.iter().map(|item| item_json(item, object)).collect())
+}
+
+/// The named results as an object, keyed as ParseResults looks them up (see
+/// ParseResults::get_named). A value of one item is that item, as in
+/// `results["name"]`; a list_all_matches name has an array of its values.
+pub fn named_json(results: &ParseResults, object: ObjectJson) -> Map<String, Value> {
+    let one = |items: &[ParseResultItem]| match items {
+        [] => Value::String(String::new()),
+        [item] => item_json(item, object),
+        items => items_json(items, object),
+    };
+    let mut out = Map::new();
+    for (name, value) in results.named_values() {
+        let value = match value {
+            NamedValue::One(items) => one(items),
+            NamedValue::All(values) => Value::Array(values.into_iter().map(one).collect()),
+        };
+        out.insert(name.to_string(), value);
+    }
+    out
+}
+
+/// One match as a JSON object with `tokens` and `named`.
+pub fn results_record(results: &ParseResults, object: ObjectJson) -> Map<String, Value> {
+    let mut record = Map::new();
+    record.insert("tokens".into(), items_json(results.items(), object));
+    record.insert("named".into(), Value::Object(named_json(results, object)));
+    record
+}
+
+/// `records` as one JSON array: compact, or indented two spaces with `pretty`.
+pub fn to_json_string(records: Vec<Value>, pretty: bool) -> String {
+    let records = Value::Array(records);
+    let text = match pretty {
+        true => serde_json::to_string_pretty(&records),
+        false => serde_json::to_string(&records),
+    };
+    text.expect("JSON values always serialize")
+}
+
+/// Write `records` to the file at `path` as JSON Lines, one compact record per line,
+/// replacing the file. Returns how many were written.
+pub fn write_jsonl(path: &Path, records: &[Value]) -> Result<usize, FileError> {
+    let mut out = JsonlWriter::create(path)?;
+    out.write(records)?;
+    out.finish()
+}
+
+/// A JSON Lines file written a batch of records at a time, so they needn't all be
+/// held at once.
+pub struct JsonlWriter {
+    path: PathBuf,
+    out: io::BufWriter<std::fs::File>,
+    written: usize,
+}
+
+impl JsonlWriter {
+    /// Create (or truncate) the file at `path`.
+    pub fn create(path: &Path) -> Result<Self, FileError> {
+        let file = std::fs::File::create(path).map_err(|e| FileError::new(path, e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            out: io::BufWriter::new(file),
+            written: 0,
+        })
+    }
+
+    /// Append `records`, one compact record per line.
+    pub fn write(&mut self, records: &[Value]) -> Result<(), FileError> {
+        for record in records {
+            serde_json::to_writer(&mut self.out, record)
+                .map_err(io::Error::from)
+                .and_then(|()| self.out.write_all(b"\n"))
+                .map_err(|e| FileError::new(&self.path, e))?;
+        }
+        self.written += records.len();
+        Ok(())
+    }
+
+    /// Flush the file; returns how many records were written.
+    pub fn finish(mut self) -> Result<usize, FileError> {
+        self.out
+            .flush()
+            .map_err(|e| FileError::new(&self.path, e))?;
+        Ok(self.written)
+    }
+}
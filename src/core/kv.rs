@@ -0,0 +1,188 @@
+use crate::elements::chars::CharSet;
+use std::borrow::Cow;
+
+/// What separates one pair from the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairSeparator {
+    /// Any run of whitespace.
+    Whitespace,
+    /// The character, with optional whitespace around it.
+    Char(char),
+}
+
+/// A pair that can't be read, at byte `offset` of its line.
+#[derive(Debug, Clone)]
+pub struct RecordError {
+    pub offset: usize,
+    pub msg: String,
+}
+
+impl RecordError {
+    fn new(offset: usize, msg: impl Into<String>) -> Self {
+        Self {
+            offset,
+            msg: msg.into(),
+        }
+    }
+}
+
+/// `(key, value)` pairs of a record, in line order; values are borrowed from the
+/// line unless they had escapes.
+pub type Pairs<'a> = Vec<(&'a str, Cow<'a, str>)>;
+
+/// Reader of `key=value` records such as `ts=2024-01-01 level=error msg="boom"`.
+/// A key is a nonempty run of `key_chars` followed directly by `assign`. A value
+/// is everything up to the next separator, or a double-quoted string where `\"`,
+/// `\\`, `\n`, `\t` and `\r` are escapes (any other backslash is kept as is) and
+/// that must be followed by a separator or the end of the line.
+#[derive(Clone)]
+pub struct KeyValueFormat {
+    pub separator: PairSeparator,
+    pub assign: char,
+    pub key_chars: CharSet,
+    /// Leave out pairs that can't be read, resuming at the next separator, instead
+    /// of failing the record.
+    pub skip_malformed: bool,
+}
+
+impl KeyValueFormat {
+    /// The pairs of `line`, without its line terminator.
+    pub fn parse_record<'a>(&self, line: &'a str) -> Result<Pairs<'a>, RecordError> {
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let mut pairs = Vec::new();
+        let mut pos = self.skip_separators(line, 0);
+        while pos < line.len() {
+            match self.pair_at(line, pos) {
+                Ok((key, value, end)) => {
+                    pairs.push((key, value));
+                    pos = end;
+                }
+                Err(e) if self.skip_malformed => pos = self.next_separator(line, e.offset),
+                Err(e) => return Err(e),
+            }
+            pos = self.skip_separators(line, pos);
+        }
+        Ok(pairs)
+    }
+
+    /// The pair starting at `start` and the offset just past it.
+    fn pair_at<'a>(
+        &self,
+        line: &'a str,
+        start: usize,
+    ) -> Result<(&'a str, Cow<'a, str>, usize), RecordError> {
+        let key_len = line[start..]
+            .char_indices()
+            .find(|&(_, c)| !self.key_chars.contains_char(c))
+            .map_or(line.len() - start, |(i, _)| i);
+        let key_end = start + key_len;
+        if key_len == 0 {
+            return Err(RecordError::new(start, "expected a key"));
+        }
+        if !line[key_end..].starts_with(self.assign) {
+            return Err(RecordError::new(
+                key_end,
+                format!(
+                    "expected '{}' after key '{}'",
+                    self.assign,
+                    &line[start..key_end]
+                ),
+            ));
+        }
+        let value_start = key_end + self.assign.len_utf8();
+        let (value, end) = match line[value_start..].starts_with('"') {
+            true => self.quoted_value(line, value_start)?,
+            false => {
+                let end = self.next_separator(line, value_start);
+                let value = &line[value_start..end];
+                let value = match self.separator {
+                    PairSeparator::Char(_) => value.trim(),
+                    PairSeparator::Whitespace => value,
+                };
+                (Cow::Borrowed(value), end)
+            }
+        };
+        Ok((&line[start..key_end], value, end))
+    }
+
+    /// The quoted value opening at `open` and the offset just past its closing quote.
+    fn quoted_value<'a>(
+        &self,
+        line: &'a str,
+        open: usize,
+    ) -> Result<(Cow<'a, str>, usize), RecordError> {
+        let body = open + 1;
+        let mut unescaped: Option<String> = None;
+        let mut chars = line[body..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    let end = body + i + 1;
+                    if !self.at_separator_or_end(line, end) {
+                        return Err(RecordError::new(
+                            end,
+                            "expected a separator after quoted value",
+                        ));
+                    }
+                    let value = match unescaped {
+                        Some(text) => Cow::Owned(text),
+                        None => Cow::Borrowed(&line[body..body + i]),
+                    };
+                    return Ok((value, end));
+                }
+                '\\' => {
+                    let text = unescaped.get_or_insert_with(|| line[body..body + i].to_string());
+                    match chars.next().map(|(_, e)| e) {
+                        Some('n') => text.push('\n'),
+                        Some('t') => text.push('\t'),
+                        Some('r') => text.push('\r'),
+                        Some(e @ ('"' | '\\')) => text.push(e),
+                        Some(e) => {
+                            text.push('\\');
+                            text.push(e);
+                        }
+                        None => text.push('\\'),
+                    }
+                }
+                c => {
+                    if let Some(text) = unescaped.as_mut() {
+                        text.push(c);
+                    }
+                }
+            }
+        }
+        Err(RecordError::new(open, "unterminated quoted value"))
+    }
+
+    fn is_separator(&self, c: char) -> bool {
+        match self.separator {
+            PairSeparator::Whitespace => c.is_whitespace(),
+            PairSeparator::Char(sep) => c == sep,
+        }
+    }
+
+    /// Offset of the first separator at or after `pos`, or the end of the line.
+    fn next_separator(&self, line: &str, pos: usize) -> usize {
+        line[pos..]
+            .char_indices()
+            .find(|&(_, c)| self.is_separator(c))
+            .map_or(line.len(), |(i, _)| pos + i)
+    }
+
+    /// Offset past the separators (and, with a separator char, whitespace) at `pos`.
+    fn skip_separators(&self, line: &str, pos: usize) -> usize {
+        line[pos..]
+            .char_indices()
+            .find(|&(_, c)| !self.is_separator(c) && !c.is_whitespace())
+            .map_or(line.len(), |(i, _)| pos + i)
+    }
+
+    fn at_separator_or_end(&self, line: &str, pos: usize) -> bool {
+        let rest = line[pos..].trim_start();
+        match self.separator {
+            PairSeparator::Whitespace => pos == line.len() || rest.len() < line.len() - pos,
+            PairSeparator::Char(sep) => rest.is_empty() || rest.starts_with(sep),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use rustc_hash::FxHashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds, in microseconds, of the latency histogram buckets. A final
+/// bucket counts everything slower.
+pub const LATENCY_BUCKETS_US: [u64; 6] = [10, 100, 1_000, 10_000, 100_000, 1_000_000];
+
+/// Kind of call being recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// One input parsed from the start (parse_string, each parse_batch item)
+    Parse,
+    /// One input searched for matches (search_string, scan_string)
+    Scan,
+    /// One file processed
+    File,
+}
+
+/// Counters for one grammar label.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GrammarMetrics {
+    pub parses: u64,
+    pub scans: u64,
+    pub files: u64,
+    /// Calls that raised: parses that did not match, scans or files that errored
+    pub failures: u64,
+    pub matches: u64,
+    pub bytes: u64,
+    /// Calls per latency bucket (not cumulative), slowest bucket last
+    pub latency_buckets: [u64; LATENCY_BUCKETS_US.len() + 1],
+    pub latency_sum_us: u64,
+}
+
+impl GrammarMetrics {
+    fn add(&mut self, other: &GrammarMetrics) {
+        self.parses += other.parses;
+        self.scans += other.scans;
+        self.files += other.files;
+        self.failures += other.failures;
+        self.matches += other.matches;
+        self.bytes += other.bytes;
+        for (total, n) in self.latency_buckets.iter_mut().zip(&other.latency_buckets) {
+            *total += n;
+        }
+        self.latency_sum_us += other.latency_sum_us;
+    }
+}
+
+/// One recorded call.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub op: Operation,
+    pub ok: bool,
+    pub bytes: usize,
+    pub matches: usize,
+    pub elapsed: Duration,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static REGISTRY: Mutex<Option<FxHashMap<String, GrammarMetrics>>> = Mutex::new(None);
+
+/// Whether calls are being recorded. Callers skip timing entirely when not.
+#[inline(always)]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Drop all recorded counters.
+pub fn reset() {
+    *REGISTRY.lock().unwrap() = None;
+}
+
+/// Add `samples` to the counters for `label`. A no-op while disabled.
+pub fn record(label: &str, samples: &[Sample]) {
+    if !enabled() || samples.is_empty() {
+        return;
+    }
+    let mut registry = REGISTRY.lock().unwrap();
+    let registry = registry.get_or_insert_with(FxHashMap::default);
+    // Labels repeat, so avoid allocating one per call
+    if !registry.contains_key(label) {
+        registry.insert(label.to_string(), GrammarMetrics::default());
+    }
+    let metrics = registry.get_mut(label).unwrap();
+    for sample in samples {
+        match sample.op {
+            Operation::Parse => metrics.parses += 1,
+            Operation::Scan => metrics.scans += 1,
+            Operation::File => metrics.files += 1,
+        }
+        if !sample.ok {
+            metrics.failures += 1;
+        }
+        metrics.matches += sample.matches as u64;
+        metrics.bytes += sample.bytes as u64;
+        let us = sample.elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = LATENCY_BUCKETS_US.partition_point(|&bound| bound < us);
+        metrics.latency_buckets[bucket] += 1;
+        metrics.latency_sum_us = metrics.latency_sum_us.saturating_add(us);
+    }
+}
+
+/// Counters per label, sorted by label, and their totals.
+pub fn snapshot() -> (Vec<(String, GrammarMetrics)>, GrammarMetrics) {
+    let registry = REGISTRY.lock().unwrap();
+    let mut labels: Vec<(String, GrammarMetrics)> = registry
+        .iter()
+        .flatten()
+        .map(|(label, metrics)| (label.clone(), metrics.clone()))
+        .collect();
+    labels.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut totals = GrammarMetrics::default();
+    for (_, metrics) in &labels {
+        totals.add(metrics);
+    }
+    (labels, totals)
+}
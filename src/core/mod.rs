@@ -1,4 +1,29 @@
+pub mod alternatives;
+pub mod arrow;
+pub mod chunks;
+pub mod compact;
 pub mod context;
+pub mod csv;
 pub mod exceptions;
+pub mod files;
+pub mod generate;
+pub mod highlight;
+pub mod incremental;
+pub mod infer;
+pub mod json;
+pub mod kv;
+pub mod metrics;
+pub mod normalize;
+pub mod packrat;
 pub mod parser;
+pub mod profile;
+pub mod regex_cache;
+pub mod replace;
 pub mod results;
+pub mod sampling;
+pub mod scanner;
+pub mod segments;
+pub mod spec;
+pub mod stats;
+pub mod stream;
+pub mod walk;
@@ -0,0 +1,133 @@
+use crate::core::incremental::Span;
+
+/// Which rewrites normalize() applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// Replace each run of whitespace (any Unicode space) with one ' '
+    pub collapse_whitespace: bool,
+    /// Drop zero-width spaces, joiners and byte order marks
+    pub strip_zero_width: bool,
+    /// Compose to Unicode NFC
+    pub nfc: bool,
+    pub lowercase: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            collapse_whitespace: true,
+            strip_zero_width: true,
+            nfc: true,
+            lowercase: false,
+        }
+    }
+}
+
+/// Normalized text with a map back to byte offsets in the original.
+#[derive(Debug, Clone)]
+pub struct Normalized {
+    pub text: String,
+    /// Original offset of the char starting at each byte of `text`, plus the
+    /// original length
+    starts: Vec<usize>,
+    /// Original offset just past the char ending before each byte of `text`
+    ends: Vec<usize>,
+}
+
+impl Normalized {
+    /// Original offset of the text at normalized offset `loc`, or None past the end.
+    /// Offsets inside a rewritten run (a collapsed space, a composed character) map
+    /// to its start.
+    pub fn to_original_offset(&self, loc: usize) -> Option<usize> {
+        self.starts.get(loc).copied()
+    }
+
+    /// Original span of the normalized `start..end`: from the start of its first char
+    /// to the end of its last, so removed characters around it are left out.
+    pub fn to_original_span(&self, (start, end): Span) -> Option<Span> {
+        if start > end {
+            return None;
+        }
+        let start = self.to_original_offset(start)?;
+        let end = *self.ends.get(end)?;
+        Some((start, end.max(start)))
+    }
+}
+
+fn is_zero_width(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'
+    )
+}
+
+/// Apply `options` to `input`. Composition is delegated to `compose`, called on runs
+/// of non-ASCII text together with the ASCII char before them; an ASCII char never
+/// combines with the char before it, so runs are independent and ASCII input never
+/// needs it.
+pub fn normalize(
+    input: &str,
+    options: NormalizeOptions,
+    mut compose: impl FnMut(&str) -> String,
+) -> Normalized {
+    // Output chars with the original span each came from
+    let mut chars: Vec<(char, usize, usize)> = Vec::with_capacity(input.len());
+    let mut in_space = false;
+    for (i, c) in input.char_indices() {
+        let end = i + c.len_utf8();
+        if options.strip_zero_width && is_zero_width(c) {
+            continue;
+        }
+        if options.collapse_whitespace && c.is_whitespace() {
+            match chars.last_mut() {
+                Some(last) if in_space => last.2 = end,
+                _ => chars.push((' ', i, end)),
+            }
+            in_space = true;
+            continue;
+        }
+        in_space = false;
+        if options.lowercase {
+            chars.extend(c.to_lowercase().map(|lc| (lc, i, end)));
+        } else {
+            chars.push((c, i, end));
+        }
+    }
+
+    let mut out = Normalized {
+        text: String::with_capacity(chars.len()),
+        starts: Vec::with_capacity(chars.len() + 1),
+        ends: Vec::with_capacity(chars.len() + 1),
+    };
+    let mut prev_end = 0;
+    let mut push = |out: &mut Normalized, s: &str, start: usize, end: usize| {
+        out.text.push_str(s);
+        for b in 0..s.len() {
+            out.starts.push(start);
+            out.ends.push(if b == 0 { prev_end } else { end });
+        }
+        prev_end = end;
+    };
+    let mut buf = [0u8; 4];
+    let mut i = 0;
+    while i < chars.len() {
+        let run_end = i
+            + 1
+            + chars[i + 1..]
+                .iter()
+                .take_while(|c| !c.0.is_ascii())
+                .count();
+        if !options.nfc || chars[i..run_end].iter().all(|c| c.0.is_ascii()) {
+            for &(c, start, end) in &chars[i..run_end] {
+                push(&mut out, c.encode_utf8(&mut buf), start, end);
+            }
+        } else {
+            let run: String = chars[i..run_end].iter().map(|c| c.0).collect();
+            push(&mut out, &compose(&run), chars[i].1, chars[run_end - 1].2);
+        }
+        i = run_end;
+    }
+    out.starts.push(input.len());
+    out.ends.push(prev_end);
+    out
+}
@@ -0,0 +1,117 @@
+use crate::core::context::WhitespaceChars;
+use crate::core::parser::{has_parse_actions, ParseResult, ParserElement};
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Most outcomes kept per parse; 0 for no limit
+static CACHE_SIZE: AtomicUsize = AtomicUsize::new(0);
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+/// Whether Forwards grow left-recursive matches from a seed (see
+/// ParseContext::parse_growing) instead of recursing unboundedly
+static LEFT_RECURSION: AtomicBool = AtomicBool::new(false);
+
+/// Memoize parse outcomes in parses started afterwards, keeping at most
+/// `cache_size` per parse (0 for no limit), and reset the counters.
+pub fn enable(cache_size: usize) {
+    CACHE_SIZE.store(cache_size, Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Relaxed);
+    reset_stats();
+}
+
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+#[inline(always)]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Grow left-recursive Forwards in parses started afterwards. Outcomes cached
+/// while a seed grows go stale, so callers keep this and packrat exclusive.
+pub fn set_left_recursion(enabled: bool) {
+    LEFT_RECURSION.store(enabled, Ordering::Relaxed);
+}
+
+#[inline(always)]
+pub fn left_recursion_enabled() -> bool {
+    LEFT_RECURSION.load(Ordering::Relaxed)
+}
+
+/// `(hits, misses)`: outcomes reused from the cache, and element parses run and
+/// cached, since packrat was enabled or the counters reset.
+pub fn stats() -> (u64, u64) {
+    (HITS.load(Ordering::Relaxed), MISSES.load(Ordering::Relaxed))
+}
+
+pub fn reset_stats() {
+    HITS.store(0, Ordering::Relaxed);
+    MISSES.store(0, Ordering::Relaxed);
+}
+
+/// What an element's outcome at a position depends on besides the input: the
+/// element and the context state that changes how it skips whitespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoKey {
+    pub element: usize,
+    pub loc: usize,
+    pub skip_whitespace: bool,
+    pub whitespace: WhitespaceChars,
+    /// Whether whitespace at `loc` is kept
+    pub keep_whitespace: bool,
+    /// Identity of the innermost ignored expression and how many there are
+    pub ignore: (usize, usize),
+    /// Indentation of the enclosing IndentedBlock
+    pub indent: usize,
+}
+
+/// Parse outcomes of one parse, evicted oldest first past the size limit (like
+/// pyparsing's FIFO packrat cache).
+pub struct Memo<'a> {
+    outcomes: FxHashMap<MemoKey, ParseResult<'a>>,
+    order: VecDeque<MemoKey>,
+    limit: usize,
+    /// Whether each element seen can be cached: elements running parse actions
+    /// (anywhere below them) or matching back-references are always re-parsed
+    cacheable: FxHashMap<usize, bool>,
+}
+
+impl<'a> Memo<'a> {
+    /// A cache for a new parse, if packrat is enabled.
+    pub fn for_parse() -> Option<Self> {
+        enabled().then(|| Self {
+            outcomes: FxHashMap::default(),
+            order: VecDeque::new(),
+            limit: CACHE_SIZE.load(Ordering::Relaxed),
+            cacheable: FxHashMap::default(),
+        })
+    }
+
+    pub fn is_cacheable(&mut self, key: usize, elem: &dyn ParserElement) -> bool {
+        *self
+            .cacheable
+            .entry(key)
+            .or_insert_with(|| !elem.has_backref() && !has_parse_actions(elem))
+    }
+
+    pub fn get(&self, key: &MemoKey) -> Option<ParseResult<'a>> {
+        let outcome = self.outcomes.get(key).cloned();
+        let counter = if outcome.is_some() { &HITS } else { &MISSES };
+        counter.fetch_add(1, Ordering::Relaxed);
+        outcome
+    }
+
+    pub fn insert(&mut self, key: MemoKey, outcome: ParseResult<'a>) {
+        if self.limit > 0 && self.order.len() >= self.limit {
+            if let Some(oldest) = self.order.pop_front() {
+                self.outcomes.remove(&oldest);
+            }
+        }
+        if self.outcomes.insert(key, outcome).is_none() {
+            self.order.push_back(key);
+        }
+    }
+}
@@ -1,16 +1,27 @@
-use crate::core::context::{skip_ws, ParseContext};
+use crate::core::context::{default_whitespace, skip_ignored, skip_ws, ParseContext};
 use crate::core::exceptions::ParseException;
+use crate::core::generate::{GenerateError, Generator};
+use crate::core::profile;
 use crate::core::results::ParseResults;
+use crate::core::segments::Segments;
+use crate::core::spec::{SpecError, SpecNode, SpecWriter};
+use rustc_hash::FxHashSet;
+use std::any::Any;
+use std::sync::Arc;
 
 /// Result of a parse attempt
-pub type ParseResult<'a> = Result<(usize, ParseResults), ParseException>;
+pub type ParseResult<'a> = Result<(usize, ParseResults<'a>), ParseException>;
+
+/// A match found by scan_string: `(tokens, start, end)` in byte offsets.
+pub type ScanMatch<'a> = (ParseResults<'a>, usize, usize);
 
 /// Describes how a parser's results should be handled by parent combinators.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ParserKind {
-    /// Normal parser — produces tokens from its match span (Literal, Word, Keyword, Regex, etc.)
+    /// Normal parser — produces exactly one token, its match span, even when empty
+    /// (Literal, Word, Keyword, Regex, etc.)
     Normal,
-    /// Suppress — matches but produces no tokens
+    /// Suppress — matches but produces no tokens (also zero-width anchors like LineStart, Empty)
     Suppress,
     /// Group — wraps its result tokens in a nested list
     Group,
@@ -19,8 +30,55 @@ pub enum ParserKind {
     Complex,
 }
 
+/// A set of byte values, such as the bytes an element's matches can start with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    pub fn insert(&mut self, b: u8) {
+        self.0[(b / 64) as usize] |= 1u64 << (b % 64);
+    }
+
+    /// Insert `b` in both ASCII cases.
+    pub fn insert_caseless(&mut self, b: u8) {
+        self.insert(b.to_ascii_lowercase());
+        self.insert(b.to_ascii_uppercase());
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, b: u8) -> bool {
+        (self.0[(b / 64) as usize] >> (b % 64)) & 1 != 0
+    }
+
+    pub fn union(mut self, other: ByteSet) -> Self {
+        for (w, o) in self.0.iter_mut().zip(other.0) {
+            *w |= o;
+        }
+        self
+    }
+}
+
+impl FromIterator<u8> for ByteSet {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        let mut set = Self::default();
+        iter.into_iter().for_each(|b| set.insert(b));
+        set
+    }
+}
+
+/// Recover an element's concrete type, e.g. to hand children back to Python.
+pub trait AsAnyElement {
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+impl<T: ParserElement + 'static> AsAnyElement for T {
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
 /// Core trait that all parser elements implement
-pub trait ParserElement: Send + Sync {
+pub trait ParserElement: AsAnyElement + Send + Sync {
     /// Attempt to parse at the given location
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a>;
 
@@ -32,11 +90,20 @@ pub trait ParserElement: Send + Sync {
     }
 
     /// Parse a string from the beginning, skipping leading whitespace.
-    fn parse_string(&self, input: &str) -> Result<ParseResults, ParseException> {
-        let mut ctx = ParseContext::new(input);
+    fn parse_string<'a>(&self, input: &'a str) -> Result<ParseResults<'a>, ParseException> {
+        let mut ctx = ParseContext::new(input).tracking_furthest_failure();
         let loc = skip_ws(input, 0);
-        let (_, results) = self.parse_impl(&mut ctx, loc)?;
-        Ok(results)
+        match self.parse_impl(&mut ctx, loc) {
+            Ok((_, results)) => Ok(results),
+            Err(e) => Err(ctx.with_furthest_failure(e)),
+        }
+    }
+
+    /// The `(start, end)` spans scan_string would report, found with try_match_at
+    /// alone: no results are built and parse actions don't run. The scan stops
+    /// after `max` spans.
+    fn find_spans(&self, input: &str, overlap: bool, max: Option<usize>) -> Vec<(usize, usize)> {
+        find_spans_by_offset(self, input, overlap, max)
     }
 
     /// How this parser's results should be handled by parent combinators.
@@ -49,4 +116,325 @@ pub trait ParserElement: Send + Sync {
     fn skip_whitespace_before(&self) -> bool {
         true
     }
+
+    /// Expressions this element skips along with whitespace (see WhitespaceScope::ignoring).
+    fn ignore_exprs(&self) -> &[Arc<dyn ParserElement>] {
+        &[]
+    }
+
+    /// Whether this parser (or any parser nested in it) refers back to an earlier match.
+    /// Sequences containing back-references record their element spans in the context.
+    fn has_backref(&self) -> bool {
+        false
+    }
+
+    /// Syntax-highlighting class this element was tagged with, if any.
+    fn highlight_class(&self) -> Option<&Arc<str>> {
+        None
+    }
+
+    /// Short description for debug traces and `str()`, like pyparsing's default
+    /// element names: `'abc'`, `W:(0123...)`, `{a b}`, `[a]...`.
+    fn name(&self) -> String {
+        self.default_name(&mut Namer::default())
+    }
+
+    /// The default name, subexpressions named through `namer` so recursion through
+    /// a Forward stops. Defaults to the type name.
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        let full = std::any::type_name::<Self>();
+        full.rsplit("::").next().unwrap_or(full).to_string()
+    }
+
+    /// Results name this element registers its tokens under, if any.
+    fn results_name(&self) -> Option<&Arc<str>> {
+        None
+    }
+
+    /// Bytes a match at `loc` always has at `loc`, so an alternation can skip this
+    /// element by the next byte alone. None when unknown, e.g. when the element can
+    /// match empty.
+    fn first_chars(&self) -> Option<ByteSet> {
+        None
+    }
+
+    /// Whether this element runs parse actions (callbacks) on its results.
+    fn has_parse_actions(&self) -> bool {
+        false
+    }
+
+    /// Call `visit` on each directly nested element. Used for whole-grammar walks.
+    fn visit_children(&self, _visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {}
+
+    /// Lock this element's own mutable configuration (not its children's).
+    /// See `freeze_grammar` to freeze everything reachable from an element.
+    fn freeze(&self) {}
+
+    /// Whether this element's own configuration is locked. Elements with nothing
+    /// to configure after construction are always frozen.
+    fn is_frozen(&self) -> bool {
+        true
+    }
+
+    /// Match against segmented input without joining the segments; returns the
+    /// global end offset. Byte-level matchers override this to walk part boundaries.
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        window_match(self, input, loc)
+    }
+
+    /// Write a random input this element accepts into `gen`.
+    /// Elements without a generator (e.g. Regex) refuse with an error.
+    fn generate(&self, _gen: &mut Generator) -> Result<(), GenerateError> {
+        Err(GenerateError::unsupported(
+            &self.default_name(&mut Namer::default()),
+        ))
+    }
+
+    /// A new element configured like this one, sharing its children, so Python's
+    /// `copy()` gives an independent element (a copied Forward can be redefined).
+    fn copy(&self) -> Arc<dyn ParserElement>;
+
+    /// This element's kind and constructor parameters, nested elements written
+    /// through `spec`, so `build_grammar` can rebuild it (used for pickling).
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Err(SpecError::unsupported::<Self>())
+    }
+}
+
+/// Name of a leaf element from its "Expected ..." error message.
+pub fn name_from_error(msg: &str) -> String {
+    msg.strip_prefix("Expected ").unwrap_or(msg).to_string()
+}
+
+/// Builds default names. Each Forward is spelled out the first time it is reached;
+/// later references, recursive ones included, are abbreviated.
+#[derive(Default)]
+pub struct Namer {
+    forwards: FxHashSet<usize>,
+}
+
+impl Namer {
+    pub fn child(&mut self, elem: &Arc<dyn ParserElement>) -> String {
+        elem.default_name(self)
+    }
+
+    /// Names of `elements` joined by `separator`, in braces, like pyparsing's And and
+    /// MatchFirst names.
+    pub fn sequence(&mut self, elements: &[Arc<dyn ParserElement>], separator: &str) -> String {
+        let names: Vec<String> = elements.iter().map(|e| self.child(e)).collect();
+        format!("{{{}}}", names.join(separator))
+    }
+
+    /// Whether the Forward at `key` is reached for the first time.
+    pub fn first_visit(&mut self, key: usize) -> bool {
+        self.forwards.insert(key)
+    }
+}
+
+/// Characters of leftover text quoted in expect_end errors.
+const LEFTOVER_SNIPPET_CHARS: usize = 20;
+
+/// Require only whitespace, or matches of `ignore`, after `end`. The error points at
+/// the first unconsumed character and quotes the text from there.
+pub fn expect_end(
+    input: &str,
+    end: usize,
+    ignore: &[Arc<dyn ParserElement>],
+) -> Result<(), ParseException> {
+    let rest = skip_ignored(input, end, default_whitespace(), ignore);
+    if rest >= input.len() {
+        return Ok(());
+    }
+    let leftover = &input[rest..];
+    let snippet: String = leftover.chars().take(LEFTOVER_SNIPPET_CHARS).collect();
+    let more = if snippet.len() < leftover.len() {
+        "..."
+    } else {
+        ""
+    };
+    Err(ParseException::new(
+        rest,
+        format!("Expected end of text, found '{}{}'", snippet, more),
+    ))
+}
+
+/// The default find_spans: try_match_at at every offset. Elements that can jump
+/// between candidate offsets override find_spans and fall back to this.
+pub fn find_spans_by_offset<P: ParserElement + ?Sized>(
+    parser: &P,
+    input: &str,
+    overlap: bool,
+    max: Option<usize>,
+) -> Vec<(usize, usize)> {
+    let skip = parser.skip_whitespace_before();
+    spans_by_offset(input, overlap, max, skip, |start| {
+        parser.try_match_at(input, start)
+    })
+}
+
+/// find_spans_by_offset with `try_match` giving the end of the match at an offset,
+/// which is past the whitespace skipped first if `skip_whitespace`.
+pub(crate) fn spans_by_offset(
+    input: &str,
+    overlap: bool,
+    max: Option<usize>,
+    skip_whitespace: bool,
+    mut try_match: impl FnMut(usize) -> Option<usize>,
+) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut loc = 0;
+    while loc < input.len() && max.is_none_or(|max| spans.len() < max) {
+        let start = match skip_whitespace {
+            true => skip_ws(input, loc),
+            false => loc,
+        };
+        let found = match input.is_char_boundary(start) {
+            true => try_match(start),
+            false => None,
+        };
+        let end = match found {
+            Some(end) if end > start => end,
+            _ => {
+                loc += 1;
+                continue;
+            }
+        };
+        spans.push((start, end));
+        loc = match overlap {
+            true => start + input[start..].chars().next().map_or(1, char::len_utf8),
+            false => end,
+        };
+    }
+    spans
+}
+
+/// Scan `input` for matches of `parser` like search_string, returning their tokens
+/// and spans. `start` is past any whitespace skipped before the match. With
+/// `overlap`, scanning resumes one character after each match's start instead of at
+/// its end. Stops early if a checkpoint in `ctx` aborts.
+pub fn scan_string<'a>(
+    parser: &Arc<dyn ParserElement>,
+    ctx: &mut ParseContext<'a>,
+    max_matches: Option<usize>,
+    overlap: bool,
+) -> Vec<ScanMatch<'a>> {
+    let mut matches = Vec::new();
+    let mut loc = 0;
+    while max_matches.is_none_or(|max| matches.len() < max) {
+        let Some((found, next)) = scan_next(parser, ctx, loc, overlap) else {
+            break;
+        };
+        matches.push(found);
+        loc = next;
+    }
+    matches
+}
+
+/// Find the first match of `parser` at or after `loc`, returning it with the offset
+/// to resume scanning from. Uses try_match_at to find candidates and parse_impl only
+/// where one matches, both counted while profiling. None once the input is
+/// exhausted or a checkpoint in `ctx` aborts.
+pub fn scan_next<'a>(
+    parser: &Arc<dyn ParserElement>,
+    ctx: &mut ParseContext<'a>,
+    mut loc: usize,
+    overlap: bool,
+) -> Option<(ScanMatch<'a>, usize)> {
+    let input = ctx.input();
+    while loc < input.len() {
+        ctx.checkpoint(loc).ok()?;
+        if !input.is_char_boundary(loc) {
+            loc += 1;
+            continue;
+        }
+        // As in pyparsing: whitespace is skipped, then the match tried there.
+        // Zero-length matches are skipped.
+        let start = match parser.skip_whitespace_before() {
+            true => skip_ws(input, loc),
+            false => loc,
+        };
+        let found = match profile::try_match(parser, input, start) {
+            Some(end) if end > start => ctx.parse(parser, start).ok(),
+            _ => None,
+        };
+        let Some((end, results)) = found.filter(|&(end, _)| end > start) else {
+            loc += input[loc..].chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+        let next = if overlap {
+            start + input[start..].chars().next().map_or(1, char::len_utf8)
+        } else {
+            end
+        };
+        return Some(((results, start, end), next));
+    }
+    None
+}
+
+/// Segment fallback for matchers that need a contiguous `&str` (e.g. Regex): match
+/// within a materialized window at `loc`, widening to the rest of the input only
+/// when the match runs up to the end of an incomplete window. The window is sized
+/// from past any whitespace at `loc`, so a long run of it can't hide the match.
+pub fn window_match<P: ParserElement + ?Sized>(
+    parser: &P,
+    input: &Segments<'_>,
+    loc: usize,
+) -> Option<usize> {
+    let from = input.skip_ws(loc);
+    let window = input.slice(loc, from + input.window(from).len());
+    let end = parser.try_match_at(&window, 0)?;
+    if end == window.len() && !input.window_is_complete(loc, window.len()) {
+        let rest = input.slice(loc, input.len());
+        return parser.try_match_at(&rest, 0).map(|e| loc + e);
+    }
+    Some(loc + end)
+}
+
+/// Call `f` once on `root` and on every element reachable from it.
+/// Shared and recursive (Forward) elements are visited only once.
+fn walk_grammar(root: &dyn ParserElement, f: &mut dyn FnMut(&dyn ParserElement)) {
+    fn walk(
+        elem: &dyn ParserElement,
+        seen: &mut FxHashSet<usize>,
+        f: &mut dyn FnMut(&dyn ParserElement),
+    ) {
+        if !seen.insert(elem as *const dyn ParserElement as *const () as usize) {
+            return;
+        }
+        f(elem);
+        elem.visit_children(&mut |child| walk(child.as_ref(), seen, f));
+    }
+    walk(root, &mut FxHashSet::default(), f);
+}
+
+/// Freeze every element reachable from `root`: Forwards reject further `set` calls.
+pub fn freeze_grammar(root: &dyn ParserElement) {
+    walk_grammar(root, &mut |elem| elem.freeze());
+}
+
+/// Whether every element reachable from `root` is frozen.
+pub fn is_grammar_frozen(root: &dyn ParserElement) -> bool {
+    let mut frozen = true;
+    walk_grammar(root, &mut |elem| frozen &= elem.is_frozen());
+    frozen
+}
+
+/// Whether any element reachable from `root` registers a results name.
+pub fn has_results_names(root: &dyn ParserElement) -> bool {
+    let mut named = false;
+    walk_grammar(root, &mut |elem| named |= elem.results_name().is_some());
+    named
+}
+
+/// Whether any element reachable from `root` runs parse actions.
+pub fn has_parse_actions(root: &dyn ParserElement) -> bool {
+    let mut actions = false;
+    walk_grammar(root, &mut |elem| actions |= elem.has_parse_actions());
+    actions
+}
+
+/// Identity key for a shared parser element, used to look up its recorded matches.
+#[inline(always)]
+pub fn element_key(elem: &Arc<dyn ParserElement>) -> usize {
+    Arc::as_ptr(elem) as *const () as usize
 }
@@ -0,0 +1,184 @@
+use crate::core::parser::{element_key, spans_by_offset, ParserElement};
+use rustc_hash::FxHashMap;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Counters for one element.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ElementProfile {
+    /// Calls through ParseContext::parse (packrat hits included)
+    pub parse_calls: u64,
+    /// try_match_at calls made by enclosing elements
+    pub match_calls: u64,
+    pub successes: u64,
+    pub failures: u64,
+    /// Time inside the calls, nested elements included
+    pub total_ns: u64,
+    /// Time inside the calls less the time counted for nested elements
+    pub self_ns: u64,
+}
+
+impl ElementProfile {
+    fn add(&mut self, other: &ElementProfile) {
+        self.parse_calls += other.parse_calls;
+        self.match_calls += other.match_calls;
+        self.successes += other.successes;
+        self.failures += other.failures;
+        self.total_ns += other.total_ns;
+        self.self_ns += other.self_ns;
+    }
+}
+
+/// Kind of call being recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Call {
+    Parse,
+    Match,
+}
+
+/// One thread's counters, keyed by element_key. The element is kept so its key
+/// isn't reused by another element while counted, and to name it on read.
+type Shard = FxHashMap<usize, (Arc<dyn ParserElement>, ElementProfile)>;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Every thread's shard, merged on read
+static SHARDS: Mutex<Vec<Arc<Mutex<Shard>>>> = Mutex::new(Vec::new());
+/// Counters of threads that have exited
+static RETIRED: Mutex<Option<Shard>> = Mutex::new(None);
+
+thread_local! {
+    static LOCAL: Arc<Mutex<Shard>> = {
+        let shard = Arc::new(Mutex::new(Shard::default()));
+        SHARDS.lock().unwrap().push(shard.clone());
+        shard
+    };
+    /// Time counted for elements nested in each open call, innermost last
+    static NESTED_NS: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Whether element calls are being counted. Callers skip timing entirely when not.
+#[inline(always)]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Start timing a call; pair with exit().
+pub fn enter() -> Instant {
+    NESTED_NS.with(|stack| stack.borrow_mut().push(0));
+    Instant::now()
+}
+
+/// Count the call to `elem` started by enter() at `started`.
+pub fn exit(elem: &Arc<dyn ParserElement>, started: Instant, call: Call, ok: bool) {
+    let elapsed = started.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+    let nested = NESTED_NS.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        let nested = stack.pop().unwrap_or(0);
+        if let Some(outer) = stack.last_mut() {
+            *outer = outer.saturating_add(elapsed);
+        }
+        nested
+    });
+    LOCAL.with(|shard| {
+        let mut shard = shard.lock().unwrap();
+        let (_, counts) = shard
+            .entry(element_key(elem))
+            .or_insert_with(|| (elem.clone(), ElementProfile::default()));
+        match call {
+            Call::Parse => counts.parse_calls += 1,
+            Call::Match => counts.match_calls += 1,
+        }
+        match ok {
+            true => counts.successes += 1,
+            false => counts.failures += 1,
+        }
+        counts.total_ns = counts.total_ns.saturating_add(elapsed);
+        counts.self_ns = counts
+            .self_ns
+            .saturating_add(elapsed.saturating_sub(nested));
+    });
+}
+
+/// `elem.try_match_at(input, loc)`, counted while profiling.
+#[inline(always)]
+pub fn try_match(elem: &Arc<dyn ParserElement>, input: &str, loc: usize) -> Option<usize> {
+    if !enabled() {
+        return elem.try_match_at(input, loc);
+    }
+    try_match_counted(elem, input, loc)
+}
+
+/// try_match while profiling, kept out of line so the disabled path stays small.
+#[cold]
+#[inline(never)]
+fn try_match_counted(elem: &Arc<dyn ParserElement>, input: &str, loc: usize) -> Option<usize> {
+    let started = enter();
+    let end = elem.try_match_at(input, loc);
+    exit(elem, started, Call::Match, end.is_some());
+    end
+}
+
+/// `parser.find_spans(input, overlap, max)`, with the match tried at each offset
+/// counted while profiling.
+pub fn find_spans(
+    parser: &Arc<dyn ParserElement>,
+    input: &str,
+    overlap: bool,
+    max: Option<usize>,
+) -> Vec<(usize, usize)> {
+    if !enabled() {
+        return parser.find_spans(input, overlap, max);
+    }
+    let skip = parser.skip_whitespace_before();
+    spans_by_offset(input, overlap, max, skip, |start| {
+        try_match_counted(parser, input, start)
+    })
+}
+
+/// Drop all recorded counters.
+pub fn reset() {
+    let mut shards = SHARDS.lock().unwrap();
+    // Only the owning thread holds another reference
+    shards.retain(|shard| Arc::strong_count(shard) > 1);
+    for shard in shards.iter() {
+        shard.lock().unwrap().clear();
+    }
+    *RETIRED.lock().unwrap() = None;
+}
+
+/// `(element, counters)` merged across threads, most total time first.
+pub fn snapshot() -> Vec<(Arc<dyn ParserElement>, ElementProfile)> {
+    let mut shards = SHARDS.lock().unwrap();
+    let mut retired = RETIRED.lock().unwrap();
+    let retired = retired.get_or_insert_with(Shard::default);
+    // Fold in the shards of exited threads so the list doesn't grow with each one
+    shards.retain(|shard| {
+        if Arc::strong_count(shard) > 1 {
+            return true;
+        }
+        merge(retired, &shard.lock().unwrap());
+        false
+    });
+    let mut merged = retired.clone();
+    for shard in shards.iter() {
+        merge(&mut merged, &shard.lock().unwrap());
+    }
+    let mut elements: Vec<_> = merged.into_values().collect();
+    elements.sort_by_key(|(_, counts)| std::cmp::Reverse(counts.total_ns));
+    elements
+}
+
+fn merge(into: &mut Shard, from: &Shard) {
+    for (&key, (elem, counts)) in from {
+        into.entry(key)
+            .or_insert_with(|| (elem.clone(), ElementProfile::default()))
+            .1
+            .add(counts);
+    }
+}
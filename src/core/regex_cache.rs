@@ -0,0 +1,218 @@
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use rustc_hash::FxHashMap;
+use std::sync::Mutex;
+
+/// The regex crate's own default for the compiled program size, in bytes.
+pub const DEFAULT_SIZE_LIMIT: usize = 10 << 20;
+/// The regex crate's own default for the lazy DFA's cache, in bytes.
+pub const DEFAULT_DFA_SIZE_LIMIT: usize = 2 << 20;
+/// Compiled regexes kept; the least recently used one is dropped past this.
+pub const CAPACITY: usize = 512;
+
+/// Bounds on the memory one regex may use, applied to every compile. Patterns
+/// whose program would be bigger fail with `regex::Error::CompiledTooBig`, so an
+/// untrusted pattern like `a{100000}{100000}` can't exhaust memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegexLimits {
+    pub size: usize,
+    pub dfa_size: usize,
+}
+
+impl Default for RegexLimits {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_SIZE_LIMIT,
+            dfa_size: DEFAULT_DFA_SIZE_LIMIT,
+        }
+    }
+}
+
+/// Counters of the cache since it was last cleared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Compiles answered from the cache
+    pub hits: u64,
+    /// Compiles run because the pattern wasn't cached
+    pub misses: u64,
+    /// Entries held now
+    pub size: usize,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq)]
+enum Key {
+    One { pattern: Box<str>, unicode: bool },
+    Set(Box<[Box<str>]>),
+}
+
+#[derive(Clone)]
+enum Compiled {
+    One(Regex),
+    Set(RegexSet),
+}
+
+#[derive(Default)]
+struct Cache {
+    /// Each entry with the tick of its last use
+    entries: FxHashMap<Key, (Compiled, u64)>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+    limits: RegexLimits,
+}
+
+impl Cache {
+    fn get(&mut self, key: &Key) -> Option<Compiled> {
+        self.tick += 1;
+        let tick = self.tick;
+        match self.entries.get_mut(key) {
+            Some((compiled, used)) => {
+                *used = tick;
+                self.hits += 1;
+                Some(compiled.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, key: Key, compiled: Compiled) {
+        if self.entries.len() >= CAPACITY && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(key, (compiled, self.tick));
+    }
+}
+
+static CACHE: Mutex<Option<Cache>> = Mutex::new(None);
+
+/// Look `key` up, or compile it with the current limits and remember the result.
+/// The lock isn't held while compiling, so a slow pattern doesn't stall others;
+/// two threads may then both compile the same pattern, which is harmless.
+fn cached(
+    key: Key,
+    compile: impl FnOnce(RegexLimits) -> Result<Compiled, regex::Error>,
+) -> Result<Compiled, regex::Error> {
+    let limits = {
+        let mut cache = CACHE.lock().unwrap();
+        let cache = cache.get_or_insert_with(Cache::default);
+        if let Some(compiled) = cache.get(&key) {
+            return Ok(compiled);
+        }
+        cache.limits
+    };
+    let compiled = compile(limits)?;
+    let mut cache = CACHE.lock().unwrap();
+    let cache = cache.get_or_insert_with(Cache::default);
+    // Limits changed while compiling: the result may break the new ones
+    if cache.limits == limits {
+        cache.insert(key, compiled.clone());
+    }
+    Ok(compiled)
+}
+
+/// `pattern` compiled under the current limits, from the cache when it was
+/// compiled before. `unicode` false makes `\w`, `\d` and the like ASCII-only.
+pub fn regex(pattern: &str, unicode: bool) -> Result<Regex, regex::Error> {
+    let key = Key::One {
+        pattern: pattern.into(),
+        unicode,
+    };
+    let compiled = cached(key, |limits| {
+        RegexBuilder::new(pattern)
+            .unicode(unicode)
+            .size_limit(limits.size)
+            .dfa_size_limit(limits.dfa_size)
+            .build()
+            .map(Compiled::One)
+    })?;
+    match compiled {
+        Compiled::One(regex) => Ok(regex),
+        Compiled::Set(_) => unreachable!("a single pattern's key holds a Regex"),
+    }
+}
+
+/// `patterns` compiled as one RegexSet under the current limits, from the cache
+/// when the same list was compiled before.
+pub fn regex_set<P: AsRef<str>>(patterns: &[P]) -> Result<RegexSet, regex::Error> {
+    let key = Key::Set(patterns.iter().map(|p| p.as_ref().into()).collect());
+    let compiled = cached(key, |limits| {
+        RegexSetBuilder::new(patterns.iter().map(|p| p.as_ref()))
+            .size_limit(limits.size)
+            .dfa_size_limit(limits.dfa_size)
+            .build()
+            .map(Compiled::Set)
+    })?;
+    match compiled {
+        Compiled::Set(set) => Ok(set),
+        Compiled::One(_) => unreachable!("a pattern list's key holds a RegexSet"),
+    }
+}
+
+/// The limits compiles use now.
+pub fn limits() -> RegexLimits {
+    CACHE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or_else(RegexLimits::default, |c| c.limits)
+}
+
+/// Use `limits` for compiles from now on. Cached regexes were compiled under the
+/// old limits, so they're dropped; the counters are kept.
+pub fn set_limits(limits: RegexLimits) {
+    let mut cache = CACHE.lock().unwrap();
+    let cache = cache.get_or_insert_with(Cache::default);
+    cache.entries.clear();
+    cache.limits = limits;
+}
+
+pub fn stats() -> CacheStats {
+    let cache = CACHE.lock().unwrap();
+    cache
+        .as_ref()
+        .map_or_else(CacheStats::default, |c| CacheStats {
+            hits: c.hits,
+            misses: c.misses,
+            size: c.entries.len(),
+        })
+}
+
+/// Drop every cached regex and zero the counters; the limits are kept.
+pub fn clear() {
+    let mut cache = CACHE.lock().unwrap();
+    if let Some(cache) = cache.as_mut() {
+        let limits = cache.limits;
+        *cache = Cache {
+            limits,
+            ..Cache::default()
+        };
+    }
+}
+
+/// Message for a failed compile of `pattern`. An over-limit pattern is named,
+/// since its size, not its syntax, is what's wrong.
+pub fn error_message(pattern: &str, err: &regex::Error) -> String {
+    match err {
+        regex::Error::CompiledTooBig(limit) => {
+            let mut shown: String = pattern.chars().take(60).collect();
+            if shown.len() < pattern.len() {
+                shown.push_str("...");
+            }
+            format!(
+                "regex {:?} is too big to compile: it exceeds the size limit of {} bytes",
+                shown, limit
+            )
+        }
+        _ => err.to_string(),
+    }
+}
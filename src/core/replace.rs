@@ -0,0 +1,238 @@
+use crate::core::parser::ParserElement;
+use regex::Regex;
+use std::fmt;
+use std::sync::Arc;
+
+/// A match of rule `rule` covering `start..end` (byte offsets).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleMatch {
+    pub start: usize,
+    pub end: usize,
+    pub rule: usize,
+}
+
+/// Scan `text` once with every rule. The earliest position where any rule matches
+/// wins; among rules matching there the longest match wins, ties going to the
+/// earlier rule. Scanning resumes after the match, so no rule can match inside it.
+pub fn scan_rules(rules: &[Arc<dyn ParserElement>], text: &str) -> Vec<RuleMatch> {
+    let bytes = text.as_bytes();
+    let mut matches = Vec::new();
+    let mut loc = 0;
+    while loc < bytes.len() {
+        // Never start a match in the middle of a UTF-8 sequence
+        if bytes[loc] & 0xC0 == 0x80 {
+            loc += 1;
+            continue;
+        }
+        let mut best: Option<RuleMatch> = None;
+        for (rule, parser) in rules.iter().enumerate() {
+            if let Some(end) = parser.try_match_at(text, loc) {
+                if end > loc && best.is_none_or(|b| end > b.end) {
+                    best = Some(RuleMatch {
+                        start: loc,
+                        end,
+                        rule,
+                    });
+                }
+            }
+        }
+        match best {
+            Some(m) => {
+                matches.push(m);
+                loc = m.end;
+            }
+            None => loc += 1,
+        }
+    }
+    matches
+}
+
+/// A replacement template that can't be used with its pattern.
+#[derive(Debug, Clone)]
+pub struct TemplateError {
+    pub msg: String,
+}
+
+impl TemplateError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TemplateError: {}", self.msg)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+enum TemplatePart {
+    Text(String),
+    Group(usize),
+}
+
+/// Replacement text with group references: `$0` is the whole match, `$1`, `$2`, ...
+/// a regex's capture groups (`${1}` where digits follow), and `$$` a literal `$`.
+/// A `$` followed by anything else is kept as is.
+pub struct Template {
+    parts: Vec<TemplatePart>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+        while let Some(dollar) = rest.find('$') {
+            text.push_str(&rest[..dollar]);
+            rest = &rest[dollar + 1..];
+            let (digits, after) = match rest.strip_prefix('{') {
+                Some(braced) => match braced.find('}') {
+                    Some(close)
+                        if close > 0 && braced[..close].bytes().all(|b| b.is_ascii_digit()) =>
+                    {
+                        (&braced[..close], &braced[close + 1..])
+                    }
+                    _ => ("", rest),
+                },
+                None => {
+                    let len = rest.bytes().take_while(u8::is_ascii_digit).count();
+                    (&rest[..len], &rest[len..])
+                }
+            };
+            match digits.parse::<usize>() {
+                Ok(group) => {
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(TemplatePart::Group(group));
+                    rest = after;
+                }
+                Err(_) if rest.starts_with('$') => {
+                    text.push('$');
+                    rest = &rest[1..];
+                }
+                Err(_) => text.push('$'),
+            }
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(text));
+        }
+        Self { parts }
+    }
+
+    /// Highest group referenced, 0 if only the whole match or none.
+    pub fn max_group(&self) -> usize {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Group(group) => *group,
+                TemplatePart::Text(_) => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Append the replacement to `out`, `group(n)` giving the text of group `n`
+    /// (None for a group that didn't participate, which renders as nothing).
+    fn render<'t>(&self, out: &mut String, group: impl Fn(usize) -> Option<&'t str>) {
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(text) => out.push_str(text),
+                TemplatePart::Group(n) => out.push_str(group(*n).unwrap_or("")),
+            }
+        }
+    }
+}
+
+/// What batch replacement searches for.
+pub enum ReplacePattern {
+    /// Non-overlapping leftmost matches, with capture groups for the template
+    Regex(Regex),
+    /// Matches found like scan_rules with a single rule; only `$0` is available
+    Element(Arc<dyn ParserElement>),
+}
+
+/// A pattern with its replacement template, checked against each other.
+pub struct Replacer {
+    pattern: ReplacePattern,
+    template: Template,
+    /// Stop after this many replacements per text
+    limit: Option<usize>,
+}
+
+impl Replacer {
+    pub fn new(
+        pattern: ReplacePattern,
+        template: Template,
+        limit: Option<usize>,
+    ) -> Result<Self, TemplateError> {
+        let groups = match &pattern {
+            ReplacePattern::Regex(regex) => regex.captures_len() - 1,
+            ReplacePattern::Element(_) => 0,
+        };
+        if template.max_group() > groups {
+            return Err(TemplateError::new(format!(
+                "template refers to group {} but the pattern has {} group{}",
+                template.max_group(),
+                groups,
+                if groups == 1 { "" } else { "s" }
+            )));
+        }
+        Ok(Self {
+            pattern,
+            template,
+            limit,
+        })
+    }
+
+    /// `text` with matches replaced and the number of replacements, or None if
+    /// nothing was replaced. Empty matches are never replaced.
+    pub fn replace(&self, text: &str) -> Option<(String, usize)> {
+        let limit = self.limit.unwrap_or(usize::MAX);
+        if limit == 0 {
+            return None;
+        }
+        let mut out = String::new();
+        let mut copied = 0;
+        let mut count = 0;
+        match &self.pattern {
+            ReplacePattern::Regex(regex) => {
+                for caps in regex.captures_iter(text) {
+                    let whole = caps.get(0).expect("group 0 always matches");
+                    if whole.is_empty() {
+                        continue;
+                    }
+                    out.push_str(&text[copied..whole.start()]);
+                    self.template
+                        .render(&mut out, |n| caps.get(n).map(|m| m.as_str()));
+                    copied = whole.end();
+                    count += 1;
+                    if count == limit {
+                        break;
+                    }
+                }
+            }
+            ReplacePattern::Element(parser) => {
+                for m in scan_rules(std::slice::from_ref(parser), text)
+                    .into_iter()
+                    .take(limit)
+                {
+                    out.push_str(&text[copied..m.start]);
+                    let matched = &text[m.start..m.end];
+                    self.template
+                        .render(&mut out, |n| (n == 0).then_some(matched));
+                    copied = m.end;
+                    count += 1;
+                }
+            }
+        }
+        if count == 0 {
+            return None;
+        }
+        out.push_str(&text[copied..]);
+        Some((out, count))
+    }
+}
@@ -1,54 +1,331 @@
+use rustc_hash::FxHashMap;
 use smallvec::SmallVec;
+use std::any::Any;
+use std::fmt;
+use std::ops::Deref;
 use std::sync::Arc;
 
-/// A single item in parse results — either a token string or a nested group
-#[derive(Debug, Clone)]
-pub enum ParseResultItem {
-    Token(Arc<str>),
-    /// Group wraps inner items in a nested structure (uses Box for indirection)
-    Group(Box<[ParseResultItem]>),
+/// Text of a token: a slice of the parsed input, or shared text when the token
+/// isn't one (a parse action's rewrite, a caseless literal's match string, ...).
+/// Borrowing keeps matching free of a heap allocation per token; results that
+/// outlive the input are made owned with `into_owned`.
+#[derive(Clone)]
+pub enum TokenText<'a> {
+    Borrowed(&'a str),
+    Shared(Arc<str>),
+}
+
+impl TokenText<'_> {
+    pub fn into_owned(self) -> TokenText<'static> {
+        match self {
+            TokenText::Borrowed(s) => TokenText::Shared(Arc::from(s)),
+            TokenText::Shared(s) => TokenText::Shared(s),
+        }
+    }
+}
+
+impl Deref for TokenText<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            TokenText::Borrowed(s) => s,
+            TokenText::Shared(s) => s,
+        }
+    }
+}
+
+impl PartialEq for TokenText<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl fmt::Debug for TokenText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a> From<&'a str> for TokenText<'a> {
+    fn from(s: &'a str) -> Self {
+        TokenText::Borrowed(s)
+    }
+}
+
+impl From<Arc<str>> for TokenText<'_> {
+    fn from(s: Arc<str>) -> Self {
+        TokenText::Shared(s)
+    }
+}
+
+impl From<String> for TokenText<'_> {
+    fn from(s: String) -> Self {
+        TokenText::Shared(s.into())
+    }
+}
+
+/// A value produced by a parse action that has no token representation (e.g. a
+/// Python object). Equal only to itself.
+#[derive(Clone)]
+pub struct OpaqueValue(pub Arc<dyn Any + Send + Sync>);
+
+impl PartialEq for OpaqueValue {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for OpaqueValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OpaqueValue(..)")
+    }
+}
+
+/// A single item in parse results — a token string, a typed number, or a nested group
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseResultItem<'a> {
+    Token(TokenText<'a>),
+    /// Integer value from a numeric element (becomes a Python int)
+    Int(i64),
+    /// Floating-point value from a numeric element (becomes a Python float)
+    Float(f64),
+    /// Group wraps inner results in a nested structure; names set inside the group
+    /// stay with it
+    Group(Box<ParseResults<'a>>),
+    /// Value returned by a parse action
+    Object(OpaqueValue),
+}
+
+/// What a name refers to in parse results (see ParseResults::get_named).
+#[derive(Debug, Clone, PartialEq)]
+pub enum NamedValue<'r, 'a> {
+    /// The value of a single match
+    One(&'r [ParseResultItem<'a>]),
+    /// The value of each match of a list_all_matches name, in order
+    All(Vec<&'r [ParseResultItem<'a>]>),
+}
+
+/// A named entry in parse results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedResult<'a> {
+    pub name: Arc<str>,
+    pub value: Box<[ParseResultItem<'a>]>,
+    /// Accumulate with other list-all entries of the same name instead of shadowing them
+    pub list_all: bool,
 }
 
 /// Parse results that can contain tokens and nested groups
-#[derive(Debug, Clone)]
-pub struct ParseResults {
-    items: SmallVec<[ParseResultItem; 2]>,
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseResults<'a> {
+    items: SmallVec<[ParseResultItem<'a>; 2]>,
+    /// Named entries, each mapped to a list of items (e.g. the value tokens of a Dict row),
+    /// in registration order; see get_named for how entries of one name combine.
+    named: Vec<NamedResult<'a>>,
 }
 
-impl Default for ParseResults {
+impl Default for ParseResults<'_> {
     fn default() -> Self {
         Self {
             items: SmallVec::new(),
+            named: Vec::new(),
         }
     }
 }
 
-impl ParseResults {
+impl<'a> ParseResults<'a> {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn from_single(s: &str) -> Self {
+    /// A single token, borrowing `s` (usually a slice of the input).
+    pub fn from_single(s: &'a str) -> Self {
+        Self::from_token(TokenText::Borrowed(s))
+    }
+
+    /// A single token of text that may not be part of the input.
+    pub fn from_token(text: impl Into<TokenText<'a>>) -> Self {
         let mut items = SmallVec::new();
-        items.push(ParseResultItem::Token(Arc::from(s)));
-        Self { items }
+        items.push(ParseResultItem::Token(text.into()));
+        Self {
+            items,
+            named: Vec::new(),
+        }
     }
 
-    /// Create a ParseResults containing a single Group item wrapping the inner results
-    pub fn from_group(inner: ParseResults) -> Self {
+    pub fn from_item(item: ParseResultItem<'a>) -> Self {
         let mut items = SmallVec::new();
-        items.push(ParseResultItem::Group(
-            inner.items.into_vec().into_boxed_slice(),
-        ));
-        Self { items }
+        items.push(item);
+        Self {
+            items,
+            named: Vec::new(),
+        }
+    }
+
+    pub fn from_items(items: impl IntoIterator<Item = ParseResultItem<'a>>) -> Self {
+        Self {
+            items: items.into_iter().collect(),
+            named: Vec::new(),
+        }
     }
 
-    pub fn extend(&mut self, other: ParseResults) {
+    /// Create a ParseResults containing a single Group item wrapping the inner results.
+    /// Names inside the group stay on the group, as in pyparsing: they are looked up
+    /// on the group's results, not on the outer ones.
+    pub fn from_group(inner: ParseResults<'a>) -> Self {
+        Self::from_item(ParseResultItem::Group(Box::new(inner)))
+    }
+
+    /// Append another element's results. Zero-width and suppressed elements return
+    /// empty results, so they contribute nothing here.
+    pub fn extend(&mut self, other: ParseResults<'a>) {
         self.items.extend(other.items);
+        self.named.extend(other.named);
     }
 
     /// Access the structured items (tokens and groups)
-    pub fn items(&self) -> &[ParseResultItem] {
+    pub fn items(&self) -> &[ParseResultItem<'a>] {
         &self.items
     }
+
+    /// Replace the items, keeping the named entries.
+    pub fn set_items(&mut self, items: Vec<ParseResultItem<'a>>) {
+        self.items = items.into();
+    }
+
+    /// Register `name` as referring to `value`.
+    pub fn add_named(&mut self, name: impl Into<Arc<str>>, value: Box<[ParseResultItem<'a>]>) {
+        self.named.push(NamedResult {
+            name: name.into(),
+            value,
+            list_all: false,
+        });
+    }
+
+    /// Register `value` as one more match for `name`, keeping earlier ones
+    /// (pyparsing's `list_all_matches`).
+    pub fn add_named_all(&mut self, name: impl Into<Arc<str>>, value: Box<[ParseResultItem<'a>]>) {
+        self.named.push(NamedResult {
+            name: name.into(),
+            value,
+            list_all: true,
+        });
+    }
+
+    /// Take over the named entries of `other`, e.g. when its tokens are combined.
+    pub fn adopt_named(&mut self, other: ParseResults<'a>) {
+        self.named.extend(other.named);
+    }
+
+    /// All named entries in registration order, including shadowed ones.
+    pub fn named(&self) -> &[NamedResult<'a>] {
+        &self.named
+    }
+
+    /// What `name` refers to: the value of its last entry, or for a list_all_matches
+    /// name every value since its last plain entry. A plain entry after list-all ones
+    /// replaces them (last wins).
+    pub fn get_named(&self, name: &str) -> Option<NamedValue<'_, 'a>> {
+        let last = self.named.iter().rposition(|entry| &*entry.name == name)?;
+        let entry = &self.named[last];
+        if !entry.list_all {
+            return Some(NamedValue::One(&entry.value));
+        }
+        let mut values: Vec<_> = self.named[..=last]
+            .iter()
+            .rev()
+            .filter(|entry| &*entry.name == name)
+            .take_while(|entry| entry.list_all)
+            .map(|entry| &*entry.value)
+            .collect();
+        values.reverse();
+        Some(NamedValue::All(values))
+    }
+
+    /// Every name with what it refers to (see get_named), in order of each name's
+    /// first entry.
+    pub fn named_values(&self) -> Vec<(&Arc<str>, NamedValue<'_, 'a>)> {
+        let mut out: Vec<(&Arc<str>, NamedValue<'_, 'a>)> = Vec::new();
+        let mut index: FxHashMap<&str, usize> = FxHashMap::default();
+        for entry in &self.named {
+            let value = &*entry.value;
+            let slot = *index.entry(&entry.name).or_insert_with(|| {
+                out.push((&entry.name, NamedValue::All(Vec::new())));
+                out.len() - 1
+            });
+            match (&mut out[slot].1, entry.list_all) {
+                (NamedValue::All(values), true) => values.push(value),
+                (current, true) => *current = NamedValue::All(vec![value]),
+                (current, false) => *current = NamedValue::One(value),
+            }
+        }
+        out
+    }
+
+    /// These results with every top-level item, and every item of a named value,
+    /// replaced by `f` of it; the first error stops the mapping.
+    pub fn try_map_items<E>(
+        self,
+        mut f: impl FnMut(ParseResultItem<'a>) -> Result<ParseResultItem<'a>, E>,
+    ) -> Result<Self, E> {
+        let items = self
+            .items
+            .into_iter()
+            .map(&mut f)
+            .collect::<Result<_, E>>()?;
+        let mut named = Vec::with_capacity(self.named.len());
+        for entry in self.named {
+            let value = entry.value.into_vec().into_iter().map(&mut f);
+            named.push(NamedResult {
+                value: value.collect::<Result<_, E>>()?,
+                ..entry
+            });
+        }
+        Ok(Self { items, named })
+    }
+
+    /// These results with every token owned, so they no longer borrow the input.
+    pub fn into_owned(self) -> ParseResults<'static> {
+        ParseResults {
+            items: self
+                .items
+                .into_iter()
+                .map(ParseResultItem::into_owned)
+                .collect(),
+            named: self
+                .named
+                .into_iter()
+                .map(|entry| NamedResult {
+                    name: entry.name,
+                    value: owned_items(entry.value),
+                    list_all: entry.list_all,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'a> ParseResultItem<'a> {
+    /// A group of `items`, with no names of its own.
+    pub fn group(items: impl IntoIterator<Item = ParseResultItem<'a>>) -> Self {
+        ParseResultItem::Group(Box::new(ParseResults::from_items(items)))
+    }
+
+    pub fn into_owned(self) -> ParseResultItem<'static> {
+        match self {
+            ParseResultItem::Token(text) => ParseResultItem::Token(text.into_owned()),
+            ParseResultItem::Int(n) => ParseResultItem::Int(n),
+            ParseResultItem::Float(x) => ParseResultItem::Float(x),
+            ParseResultItem::Group(inner) => ParseResultItem::Group(Box::new(inner.into_owned())),
+            ParseResultItem::Object(value) => ParseResultItem::Object(value),
+        }
+    }
+}
+
+fn owned_items(items: Box<[ParseResultItem<'_>]>) -> Box<[ParseResultItem<'static>]> {
+    items
+        .into_vec()
+        .into_iter()
+        .map(ParseResultItem::into_owned)
+        .collect()
 }
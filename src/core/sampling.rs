@@ -0,0 +1,228 @@
+use crate::core::exceptions::FileError;
+use crate::core::generate::Generator;
+use crate::core::parser::ParserElement;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// z-score for a two-sided 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+/// Match-count estimate from scanning a random subset of a corpus.
+#[derive(Debug, Clone, Default)]
+pub struct SampleReport {
+    /// Sampling units (lines for text, line-aligned windows for files) in the corpus
+    pub units_total: usize,
+    pub units_sampled: usize,
+    pub matches_sampled: usize,
+    pub estimate: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+    /// First matches found in the sample, in corpus order
+    pub examples: Vec<String>,
+}
+
+/// Collects per-unit match counts and turns them into a report.
+struct Tally {
+    counts: Vec<usize>,
+    examples: Vec<String>,
+    max_examples: usize,
+}
+
+impl Tally {
+    fn new(max_examples: usize) -> Self {
+        Self {
+            counts: Vec::new(),
+            examples: Vec::new(),
+            max_examples,
+        }
+    }
+
+    /// Count non-overlapping matches in `text`, keeping the first few as examples.
+    fn scan(&mut self, parser: &dyn ParserElement, text: &str) -> usize {
+        let bytes = text.as_bytes();
+        let mut count = 0;
+        let mut loc = 0;
+        while loc < bytes.len() {
+            // Never start a match in the middle of a UTF-8 sequence
+            if bytes[loc] & 0xC0 == 0x80 {
+                loc += 1;
+                continue;
+            }
+            match parser.try_match_at(text, loc) {
+                Some(end) if end > loc => {
+                    count += 1;
+                    if self.examples.len() < self.max_examples {
+                        self.examples.push(text[loc..end].to_string());
+                    }
+                    loc = end;
+                }
+                _ => loc += 1,
+            }
+        }
+        count
+    }
+
+    /// Scale the sample mean up to `units_total`, with a normal-approximation
+    /// interval that includes the finite population correction.
+    fn report(self, units_total: usize) -> SampleReport {
+        let n = self.counts.len();
+        let matches_sampled: usize = self.counts.iter().sum();
+        if n == 0 {
+            return SampleReport {
+                units_total,
+                examples: self.examples,
+                ..SampleReport::default()
+            };
+        }
+        let total = units_total as f64;
+        let mean = matches_sampled as f64 / n as f64;
+        let estimate = mean * total;
+        let (ci_low, ci_high) = if n > 1 {
+            let var = self
+                .counts
+                .iter()
+                .map(|&c| (c as f64 - mean).powi(2))
+                .sum::<f64>()
+                / (n - 1) as f64;
+            let fpc = 1.0 - n as f64 / total;
+            let half = Z_95 * total * (fpc * var / n as f64).sqrt();
+            (
+                (estimate - half).max(matches_sampled as f64),
+                estimate + half,
+            )
+        } else {
+            (estimate, estimate)
+        };
+        SampleReport {
+            units_total,
+            units_sampled: n,
+            matches_sampled,
+            estimate,
+            ci_low,
+            ci_high,
+            examples: self.examples,
+        }
+    }
+}
+
+/// Pick `ceil(total * fraction)` distinct indices in `0..total`, sorted,
+/// reproducibly for `seed` (Floyd's algorithm: memory proportional to the sample).
+pub fn select_units(total: usize, fraction: f64, seed: u64) -> Vec<usize> {
+    let k = ((total as f64 * fraction.clamp(0.0, 1.0)).ceil() as usize).min(total);
+    let mut rng = Generator::new(seed, 0);
+    let mut chosen = rustc_hash::FxHashSet::default();
+    for j in total - k..total {
+        let t = rng.below(j + 1);
+        if !chosen.insert(t) {
+            chosen.insert(j);
+        }
+    }
+    let mut units: Vec<usize> = chosen.into_iter().collect();
+    units.sort_unstable();
+    units
+}
+
+/// Estimate how many times `parser` matches in `text` by scanning a sample of its lines.
+pub fn sample_text(
+    parser: &dyn ParserElement,
+    text: &str,
+    fraction: f64,
+    seed: u64,
+    max_examples: usize,
+) -> SampleReport {
+    let mut starts = vec![0];
+    starts.extend(memchr::memchr_iter(b'\n', text.as_bytes()).map(|i| i + 1));
+    if starts.last() == Some(&text.len()) {
+        // A trailing newline does not start another line
+        starts.pop();
+    }
+    let mut tally = Tally::new(max_examples);
+    for i in select_units(starts.len(), fraction, seed) {
+        let end = starts.get(i + 1).map_or(text.len(), |&next| next - 1);
+        let count = tally.scan(parser, &text[starts[i]..end]);
+        tally.counts.push(count);
+    }
+    tally.report(starts.len())
+}
+
+/// Read the lines that start in `start..start + window` of `file`: the region is
+/// moved forward to the first line start and extended to the end of its last line.
+fn read_aligned_window(file: &mut File, start: u64, window: u64) -> io::Result<String> {
+    let from = start.saturating_sub(1);
+    file.seek(SeekFrom::Start(from))?;
+    let mut buf = Vec::new();
+    file.by_ref()
+        .take(start + window - from)
+        .read_to_end(&mut buf)?;
+    let skip = if start == 0 {
+        0
+    } else {
+        match memchr::memchr(b'\n', &buf) {
+            Some(i) if i + 1 < buf.len() => i + 1,
+            // No line starts in this window
+            _ => return Ok(String::new()),
+        }
+    };
+    // Finish the last line, which may run past the window
+    if buf.len() as u64 == start + window - from && buf.last() != Some(&b'\n') {
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = file.read(&mut chunk)?;
+            if n == 0 {
+                break;
+            }
+            match memchr::memchr(b'\n', &chunk[..n]) {
+                Some(i) => {
+                    buf.extend_from_slice(&chunk[..=i]);
+                    break;
+                }
+                None => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+    Ok(String::from_utf8_lossy(&buf[skip..]).into_owned())
+}
+
+/// Estimate how many times `parser` matches across `paths` by scanning a sample of
+/// `window`-byte regions aligned to line boundaries. Only sampled regions are read.
+pub fn sample_files<P: AsRef<Path>>(
+    parser: &dyn ParserElement,
+    paths: &[P],
+    window: usize,
+    fraction: f64,
+    seed: u64,
+    max_examples: usize,
+) -> Result<SampleReport, FileError> {
+    let window = window.max(1) as u64;
+    // Windows are numbered across all files, in order
+    let mut first_window = Vec::with_capacity(paths.len() + 1);
+    let mut total = 0usize;
+    for path in paths {
+        first_window.push(total);
+        let len = std::fs::metadata(path)
+            .map_err(|e| FileError::new(path, e))?
+            .len();
+        total += len.div_ceil(window) as usize;
+    }
+    first_window.push(total);
+
+    let mut tally = Tally::new(max_examples);
+    let mut open: Option<(usize, File)> = None;
+    for unit in select_units(total, fraction, seed) {
+        let file_idx = first_window.partition_point(|&w| w <= unit) - 1;
+        if open.as_ref().is_none_or(|(i, _)| *i != file_idx) {
+            let file =
+                File::open(&paths[file_idx]).map_err(|e| FileError::new(&paths[file_idx], e))?;
+            open = Some((file_idx, file));
+        }
+        let (_, file) = open.as_mut().unwrap();
+        let start = (unit - first_window[file_idx]) as u64 * window;
+        let region = read_aligned_window(file, start, window)
+            .map_err(|e| FileError::new(&paths[file_idx], e))?;
+        // Matches never span lines, as in sample_text
+        let count = region.lines().map(|line| tally.scan(parser, line)).sum();
+        tally.counts.push(count);
+    }
+    Ok(tally.report(total))
+}
@@ -0,0 +1,389 @@
+use crate::core::context::skip_ws;
+use crate::core::parser::ParserElement;
+use crate::core::regex_cache;
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, Anchored, Input, MatchKind, StartKind};
+use regex::{Regex, RegexSet};
+use std::cmp::Reverse;
+
+/// Which matches FastScanner reports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScannerOptions {
+    /// Compare ASCII letters case-insensitively
+    pub caseless: bool,
+    /// Report every match, including ones inside or overlapping others
+    pub overlapping: bool,
+    /// Only matches with no identifier character (`[A-Za-z0-9_]`) on either side,
+    /// as Keyword checks after its match
+    pub whole_words: bool,
+}
+
+/// A literal list FastScanner can't be built from.
+#[derive(Debug, Clone)]
+pub struct ScannerError {
+    pub msg: String,
+}
+
+impl ScannerError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+}
+
+/// A match: index of the literal in the list, then byte offsets of its span.
+pub type LiteralMatch = (usize, usize, usize);
+
+/// Finds occurrences of many literals in one pass over the text (Aho-Corasick).
+/// Without `overlapping`, matches are leftmost-longest and don't overlap; equally
+/// long literals at the same place go to the one listed first.
+pub struct FastScanner {
+    automaton: AhoCorasick,
+    options: ScannerOptions,
+    /// Byte length of the longest literal
+    max_len: usize,
+}
+
+/// How far past a nominal chunk boundary scan_chunked looks for a newline to split
+/// at instead.
+const SPLIT_WINDOW: usize = 4096;
+
+impl FastScanner {
+    pub fn new<P: AsRef<str>>(
+        literals: &[P],
+        options: ScannerOptions,
+    ) -> Result<Self, ScannerError> {
+        if literals.is_empty() {
+            return Err(ScannerError::new(
+                "FastScanner requires at least one literal",
+            ));
+        }
+        if literals.iter().any(|lit| lit.as_ref().is_empty()) {
+            return Err(ScannerError::new("FastScanner literals must not be empty"));
+        }
+        // Overlapping iteration needs standard semantics; whole-word matching picks
+        // the longest matches itself, among those with word boundaries
+        let kind = match options.overlapping || options.whole_words {
+            true => MatchKind::Standard,
+            false => MatchKind::LeftmostLongest,
+        };
+        let automaton = AhoCorasickBuilder::new()
+            .match_kind(kind)
+            .ascii_case_insensitive(options.caseless)
+            .build(literals.iter().map(|lit| lit.as_ref()))
+            .map_err(|e| ScannerError::new(e.to_string()))?;
+        let max_len = literals
+            .iter()
+            .map(|lit| lit.as_ref().len())
+            .max()
+            .unwrap_or(0);
+        Ok(Self {
+            automaton,
+            options,
+            max_len,
+        })
+    }
+
+    pub fn literal_count(&self) -> usize {
+        self.automaton.patterns_len()
+    }
+
+    /// Matches in `text`, ordered by start, then end.
+    pub fn scan(&self, text: &str) -> Vec<LiteralMatch> {
+        let as_triple = |m: aho_corasick::Match| (m.pattern().as_usize(), m.start(), m.end());
+        if !self.options.overlapping && !self.options.whole_words {
+            return self.automaton.find_iter(text).map(as_triple).collect();
+        }
+        let mut found: Vec<LiteralMatch> = self
+            .automaton
+            .find_overlapping_iter(text)
+            .map(as_triple)
+            .filter(|&(_, start, end)| !self.options.whole_words || is_whole_word(text, start, end))
+            .collect();
+        if self.options.overlapping {
+            found.sort_unstable_by_key(|&(pattern, start, end)| (start, end, pattern));
+            return found;
+        }
+        // Leftmost-longest among the whole words
+        found.sort_unstable_by_key(|&(pattern, start, end)| (start, Reverse(end), pattern));
+        let mut taken = Vec::new();
+        let mut next = 0;
+        for m in found {
+            if m.1 >= next {
+                next = m.2;
+                taken.push(m);
+            }
+        }
+        taken
+    }
+
+    /// Split `text` into at most `num_chunks` ranges for scan_chunk, each ending
+    /// just past a newline when one is near its nominal end, else on a char
+    /// boundary.
+    pub fn chunk_bounds(&self, text: &str, num_chunks: usize) -> Vec<(usize, usize)> {
+        let len = text.len();
+        let num_chunks = num_chunks.clamp(1, len.max(1));
+        let mut bounds = Vec::with_capacity(num_chunks);
+        let mut start = 0;
+        for i in 1..=num_chunks {
+            let nominal = (len * i / num_chunks).max(start);
+            let window = &text.as_bytes()[nominal..len.min(nominal + SPLIT_WINDOW)];
+            let mut end = match memchr::memchr(b'\n', window) {
+                Some(at) => nominal + at + 1,
+                None => nominal,
+            };
+            while !text.is_char_boundary(end) {
+                end += 1;
+            }
+            if end > start {
+                bounds.push((start, end));
+                start = end;
+            }
+        }
+        bounds
+    }
+
+    /// The matches of a scan of the whole `text` that start in `start..end`, except
+    /// that without `overlapping` or `whole_words` the scan starts afresh at
+    /// `start`; merge_chunks reconciles that with the chunk before. Reads at most
+    /// one literal's length past `end`.
+    pub fn scan_chunk(&self, text: &str, (start, end): (usize, usize)) -> Vec<LiteralMatch> {
+        let input = Input::new(text).span(start..text.len().min(end + self.max_len));
+        let as_triple = |m: aho_corasick::Match| (m.pattern().as_usize(), m.start(), m.end());
+        if !self.options.overlapping && !self.options.whole_words {
+            return self
+                .automaton
+                .find_iter(input)
+                .map(as_triple)
+                .take_while(|&(_, s, _)| s < end)
+                .collect();
+        }
+        let mut found: Vec<LiteralMatch> = self
+            .automaton
+            .find_overlapping_iter(input)
+            .map(as_triple)
+            .filter(|&(_, s, e)| {
+                s < end && (!self.options.whole_words || is_whole_word(text, s, e))
+            })
+            .collect();
+        match self.options.overlapping {
+            true => found.sort_unstable_by_key(|&(pattern, s, e)| (s, e, pattern)),
+            false => found.sort_unstable_by_key(|&(pattern, s, e)| (s, Reverse(e), pattern)),
+        }
+        found
+    }
+
+    /// scan() of `text` from the scan_chunk results of each of `bounds`, in order.
+    /// Without `overlapping`, a chunk's matches that a match of the chunk before
+    /// runs into are rescanned from that match's end until they agree again.
+    pub fn merge_chunks(
+        &self,
+        text: &str,
+        bounds: &[(usize, usize)],
+        chunks: Vec<Vec<LiteralMatch>>,
+    ) -> Vec<LiteralMatch> {
+        let mut out: Vec<LiteralMatch> = Vec::new();
+        if self.options.overlapping {
+            chunks.into_iter().for_each(|chunk| out.extend(chunk));
+            return out;
+        }
+        if self.options.whole_words {
+            // Candidates are exact; take leftmost-longest among them in one pass
+            let mut next = 0;
+            for m in chunks.into_iter().flatten() {
+                if m.1 >= next {
+                    next = m.2;
+                    out.push(m);
+                }
+            }
+            return out;
+        }
+        for (&(start, end), chunk) in bounds.iter().zip(chunks) {
+            let next = out.last().map_or(0, |m| m.2);
+            if next <= start {
+                out.extend(chunk);
+                continue;
+            }
+            // Leftmost-longest from `next` is the chunk's own scan from the first
+            // match both find
+            let input = Input::new(text).span(next..text.len().min(end + self.max_len));
+            for m in self.automaton.find_iter(input) {
+                let m = (m.pattern().as_usize(), m.start(), m.end());
+                if m.1 >= end {
+                    break;
+                }
+                let at = chunk.partition_point(|c| c.1 < m.1);
+                if chunk.get(at) == Some(&m) {
+                    out.extend_from_slice(&chunk[at..]);
+                    break;
+                }
+                out.push(m);
+            }
+        }
+        out
+    }
+
+    /// Number of matches scan() would return.
+    pub fn count(&self, text: &str) -> usize {
+        if !self.options.overlapping && !self.options.whole_words {
+            return self.automaton.find_iter(text).count();
+        }
+        if !self.options.whole_words {
+            return self.automaton.find_overlapping_iter(text).count();
+        }
+        self.scan(text).len()
+    }
+}
+
+/// Matches of any of `parsers` in `text`, as `(parser_index, start, end)` found with
+/// try_match_at like find_spans. By default leftmost-longest without overlaps: at
+/// each offset the match starting first wins, then the longest, then the parser
+/// listed first. With `all`, every parser's matches at every offset, ordered by
+/// start, then end, then parser.
+pub fn scan_elements<P: AsRef<dyn ParserElement>>(
+    parsers: &[P],
+    text: &str,
+    all: bool,
+) -> Vec<LiteralMatch> {
+    if all {
+        let mut found: Vec<LiteralMatch> = parsers
+            .iter()
+            .enumerate()
+            .flat_map(|(i, p)| {
+                let spans = p.as_ref().find_spans(text, true, None);
+                spans.into_iter().map(move |(start, end)| (i, start, end))
+            })
+            .collect();
+        found.sort_unstable_by_key(|&(i, start, end)| (start, end, i));
+        return found;
+    }
+    let mut found = Vec::new();
+    let mut loc = 0;
+    while loc < text.len() {
+        let mut best: Option<LiteralMatch> = None;
+        if text.is_char_boundary(loc) {
+            for (i, parser) in parsers.iter().enumerate() {
+                let parser = parser.as_ref();
+                let Some(end) = parser.try_match_at(text, loc).filter(|&end| end > loc) else {
+                    continue;
+                };
+                let start = match parser.skip_whitespace_before() {
+                    true => skip_ws(text, loc).min(end),
+                    false => loc,
+                };
+                if best.is_none_or(|(_, s, e)| (start, Reverse(end)) < (s, Reverse(e))) {
+                    best = Some((i, start, end));
+                }
+            }
+        }
+        match best {
+            Some(m) => {
+                found.push(m);
+                loc = m.2;
+            }
+            None => loc += 1,
+        }
+    }
+    found
+}
+
+#[inline]
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// Whether `text[start..end]` has no identifier character right before or after it.
+fn is_whole_word(text: &str, start: usize, end: usize) -> bool {
+    let bytes = text.as_bytes();
+    !(start > 0 && is_ident_byte(bytes[start - 1]))
+        && !bytes.get(end).copied().is_some_and(is_ident_byte)
+}
+
+/// Literals matched at a given position, the first listed winning like MatchFirst's
+/// alternatives.
+#[derive(Clone)]
+pub struct LiteralSet {
+    automaton: AhoCorasick,
+}
+
+impl LiteralSet {
+    /// None if the automaton can't be built (e.g. it would be too large).
+    pub fn new<P: AsRef<str>>(literals: &[P]) -> Option<Self> {
+        let automaton = AhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostFirst)
+            .start_kind(StartKind::Anchored)
+            .build(literals.iter().map(|lit| lit.as_ref()))
+            .ok()?;
+        Some(Self { automaton })
+    }
+
+    /// Index and end of the first literal that matches at `loc`.
+    #[inline]
+    pub fn first_at(&self, input: &str, loc: usize) -> Option<(usize, usize)> {
+        if loc > input.len() {
+            return None;
+        }
+        let search = Input::new(input)
+            .span(loc..input.len())
+            .anchored(Anchored::Yes);
+        self.automaton
+            .find(search)
+            .map(|m| (m.pattern().as_usize(), m.end()))
+    }
+}
+
+/// Tests many regexes against a text in one pass (RegexSet), for batches of lines
+/// checked against a pattern list.
+pub struct PatternSet {
+    set: RegexSet,
+    /// The same patterns one by one, for the spans of the ones that matched
+    regexes: Vec<Regex>,
+}
+
+impl PatternSet {
+    /// Fails listing every pattern that doesn't compile, by index.
+    pub fn new<P: AsRef<str>>(patterns: &[P]) -> Result<Self, ScannerError> {
+        let mut regexes = Vec::with_capacity(patterns.len());
+        let mut failed = Vec::new();
+        for (i, pattern) in patterns.iter().enumerate() {
+            match regex_cache::regex(pattern.as_ref(), true) {
+                Ok(regex) => regexes.push(regex),
+                Err(e) => failed.push(format!(
+                    "pattern {}: {}",
+                    i,
+                    regex_cache::error_message(pattern.as_ref(), &e)
+                )),
+            }
+        }
+        if !failed.is_empty() {
+            return Err(ScannerError::new(format!(
+                "{} of {} patterns failed to compile:\n{}",
+                failed.len(),
+                patterns.len(),
+                failed.join("\n")
+            )));
+        }
+        let set = regex_cache::regex_set(patterns).map_err(|e| match e {
+            regex::Error::CompiledTooBig(limit) => ScannerError::new(format!(
+                "the {} patterns together exceed the regex size limit of {} bytes",
+                patterns.len(),
+                limit
+            )),
+            e => ScannerError::new(e.to_string()),
+        })?;
+        Ok(Self { set, regexes })
+    }
+
+    /// Indices of the patterns that match somewhere in `text`, ascending.
+    pub fn matching(&self, text: &str) -> Vec<usize> {
+        self.set.matches(text).into_iter().collect()
+    }
+
+    /// Index and byte span of the first match of each pattern that matches `text`,
+    /// ordered by index. Only the patterns the set reports are searched again.
+    pub fn first_matches(&self, text: &str) -> Vec<LiteralMatch> {
+        self.set
+            .matches(text)
+            .into_iter()
+            .filter_map(|i| self.regexes[i].find(text).map(|m| (i, m.start(), m.end())))
+            .collect()
+    }
+}
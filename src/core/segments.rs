@@ -0,0 +1,199 @@
+use crate::core::context::default_whitespace;
+use std::borrow::Cow;
+use std::cell::Cell;
+
+/// Minimum size of a materialized window for elements without a segment-aware matcher.
+const MIN_WINDOW: usize = 4096;
+
+/// A logical input made of several string segments (e.g. rope chunks), addressed
+/// by global byte offsets without concatenating the segments.
+pub struct Segments<'a> {
+    parts: Vec<&'a str>,
+    /// Global offset at which each part starts
+    starts: Vec<usize>,
+    len: usize,
+    /// Whether a lookup since the last take_reached_end() depended on where the
+    /// input ends, so more input could have changed its answer
+    reached_end: Cell<bool>,
+}
+
+impl<'a> Segments<'a> {
+    pub fn new(parts: Vec<&'a str>) -> Self {
+        let mut starts = Vec::with_capacity(parts.len());
+        let mut len = 0;
+        for p in &parts {
+            starts.push(len);
+            len += p.len();
+        }
+        Self {
+            parts,
+            starts,
+            len,
+            reached_end: Cell::new(false),
+        }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether a lookup since the last call depended on where the input ends: it
+    /// looked at or past the end, or a window reached it. A match whose lookups
+    /// didn't is the same however the input continues. Resets the flag.
+    pub fn take_reached_end(&self) -> bool {
+        self.reached_end.replace(false)
+    }
+
+    /// Whether `pos` is at (or past) the end of input.
+    #[inline]
+    pub fn at_end(&self, pos: usize) -> bool {
+        let at_end = pos >= self.len;
+        if at_end {
+            self.reached_end.set(true);
+        }
+        at_end
+    }
+
+    /// Index of the part containing global offset `pos` (the last part if `pos == len`).
+    #[inline]
+    fn part_of(&self, pos: usize) -> usize {
+        // Skip empty parts: find the last part starting at or before `pos`
+        // that actually contains it.
+        let idx = self.starts.partition_point(|&s| s <= pos).saturating_sub(1);
+        let mut i = idx;
+        while i + 1 < self.parts.len() && pos >= self.starts[i] + self.parts[i].len() {
+            i += 1;
+        }
+        i
+    }
+
+    /// Byte at global offset `pos`, or None at end of input.
+    #[inline]
+    pub fn byte_at(&self, pos: usize) -> Option<u8> {
+        if self.at_end(pos) {
+            return None;
+        }
+        let i = self.part_of(pos);
+        Some(self.parts[i].as_bytes()[pos - self.starts[i]])
+    }
+
+    /// Whether `bytes` occurs at global offset `pos`, comparing across part boundaries.
+    pub fn matches_at(&self, pos: usize, bytes: &[u8]) -> bool {
+        self.matches_at_by(pos, bytes, |a, b| a == b)
+    }
+
+    /// Like `matches_at`, but compares ASCII case-insensitively against lowercase `bytes`.
+    pub fn matches_at_caseless(&self, pos: usize, lower: &[u8]) -> bool {
+        self.matches_at_by(pos, lower, |a, b| a.to_ascii_lowercase() == b)
+    }
+
+    fn matches_at_by(&self, pos: usize, bytes: &[u8], eq: impl Fn(u8, u8) -> bool) -> bool {
+        // Input cut short by the end is only a miss if what there is already differs
+        if pos + bytes.len() > self.len && pos >= self.len {
+            self.reached_end.set(true);
+            return false;
+        }
+        let have = bytes.len().min(self.len - pos);
+        let cut_short = have < bytes.len();
+        let mut i = self.part_of(pos);
+        let mut local = pos - self.starts[i];
+        let mut rest = &bytes[..have];
+        while !rest.is_empty() {
+            let part = &self.parts[i].as_bytes()[local..];
+            let n = part.len().min(rest.len());
+            if !part[..n].iter().zip(&rest[..n]).all(|(&a, &b)| eq(a, b)) {
+                return false;
+            }
+            rest = &rest[n..];
+            i += 1;
+            local = 0;
+        }
+        if cut_short {
+            self.reached_end.set(true);
+        }
+        !cut_short
+    }
+
+    /// Advance from `pos` while `pred` holds for each byte; returns the first failing offset.
+    pub fn scan_while(&self, pos: usize, pred: impl Fn(u8) -> bool) -> usize {
+        if self.at_end(pos) {
+            return pos;
+        }
+        let mut i = self.part_of(pos);
+        let mut local = pos - self.starts[i];
+        while i < self.parts.len() {
+            let bytes = self.parts[i].as_bytes();
+            while local < bytes.len() {
+                if !pred(bytes[local]) {
+                    return self.starts[i] + local;
+                }
+                local += 1;
+            }
+            i += 1;
+            local = 0;
+        }
+        self.reached_end.set(true);
+        self.len
+    }
+
+    /// Skip default whitespace characters starting at `pos`.
+    #[inline]
+    pub fn skip_ws(&self, pos: usize) -> usize {
+        let whitespace = default_whitespace();
+        self.scan_while(pos, |b| whitespace.contains(b))
+    }
+
+    /// Text between global offsets `start..end`. Borrowed when the span lies within
+    /// one part; only a span crossing a boundary is copied.
+    pub fn slice(&self, start: usize, end: usize) -> Cow<'a, str> {
+        if start >= end {
+            return Cow::Borrowed("");
+        }
+        let first = self.part_of(start);
+        let last = self.part_of(end - 1);
+        if first == last {
+            let base = self.starts[first];
+            return Cow::Borrowed(&self.parts[first][start - base..end - base]);
+        }
+        let mut out = String::with_capacity(end - start);
+        for i in first..=last {
+            let base = self.starts[i];
+            let from = start.max(base) - base;
+            let to = end.min(base + self.parts[i].len()) - base;
+            out.push_str(&self.parts[i][from..to]);
+        }
+        Cow::Owned(out)
+    }
+
+    /// Window of text starting at `pos` for matchers that need a contiguous `&str`.
+    /// Covers the rest of the part containing `pos` plus following parts, until at
+    /// least `MIN_WINDOW` bytes (or the end of input) are included.
+    /// A window reaching the end counts as a lookup there (see take_reached_end),
+    /// since a matcher may have looked anywhere in it.
+    pub fn window(&self, pos: usize) -> Cow<'a, str> {
+        if self.at_end(pos) {
+            return Cow::Borrowed("");
+        }
+        let i = self.part_of(pos);
+        let mut end = self.starts[i] + self.parts[i].len();
+        let mut j = i + 1;
+        while j < self.parts.len() && end - pos < MIN_WINDOW {
+            end += self.parts[j].len();
+            j += 1;
+        }
+        self.at_end(end);
+        self.slice(pos, end)
+    }
+
+    /// Whether the window starting at `pos` reaches the end of input.
+    #[inline]
+    pub fn window_is_complete(&self, pos: usize, window_len: usize) -> bool {
+        pos + window_len >= self.len
+    }
+}
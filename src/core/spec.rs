@@ -0,0 +1,208 @@
+use crate::core::parser::{element_key, ParserElement};
+use rustc_hash::FxHashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Version of the node layout; bumped when an element's parameters change.
+pub const SPEC_VERSION: usize = 1;
+
+/// Error raised when a grammar cannot be described or rebuilt.
+#[derive(Debug, Clone)]
+pub struct SpecError {
+    pub msg: String,
+}
+
+impl SpecError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+
+    /// Error for an element type that cannot be described.
+    pub fn unsupported<T: ?Sized>() -> Self {
+        let name = std::any::type_name::<T>()
+            .rsplit("::")
+            .next()
+            .unwrap_or("?");
+        Self::new(format!("cannot serialize {}", name))
+    }
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SpecError: {}", self.msg)
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+/// One constructor parameter. Nested elements are `Int` indexes into the spec's nodes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpecValue {
+    None,
+    Bool(bool),
+    Int(usize),
+    Str(Arc<str>),
+    List(Vec<usize>),
+}
+
+impl From<bool> for SpecValue {
+    fn from(b: bool) -> Self {
+        SpecValue::Bool(b)
+    }
+}
+
+impl From<usize> for SpecValue {
+    fn from(n: usize) -> Self {
+        SpecValue::Int(n)
+    }
+}
+
+impl From<&str> for SpecValue {
+    fn from(s: &str) -> Self {
+        SpecValue::Str(s.into())
+    }
+}
+
+impl<T: Into<SpecValue>> From<Option<T>> for SpecValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(SpecValue::None, Into::into)
+    }
+}
+
+/// An element's kind (its type name) and constructor parameters, in a fixed order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecNode {
+    pub kind: Arc<str>,
+    pub params: Vec<SpecValue>,
+}
+
+impl SpecNode {
+    pub fn new(kind: &str, params: Vec<SpecValue>) -> Self {
+        Self {
+            kind: kind.into(),
+            params,
+        }
+    }
+
+    fn param(&self, i: usize) -> Result<&SpecValue, SpecError> {
+        self.params
+            .get(i)
+            .ok_or_else(|| self.bad_param(i, "is missing"))
+    }
+
+    fn bad_param(&self, i: usize, what: &str) -> SpecError {
+        SpecError::new(format!("{} parameter {} {}", self.kind, i, what))
+    }
+
+    pub fn str(&self, i: usize) -> Result<&str, SpecError> {
+        self.opt_str(i)?
+            .ok_or_else(|| self.bad_param(i, "must be a string"))
+    }
+
+    pub fn opt_str(&self, i: usize) -> Result<Option<&str>, SpecError> {
+        match self.param(i)? {
+            SpecValue::Str(s) => Ok(Some(s)),
+            SpecValue::None => Ok(None),
+            _ => Err(self.bad_param(i, "must be a string")),
+        }
+    }
+
+    pub fn int(&self, i: usize) -> Result<usize, SpecError> {
+        self.opt_int(i)?
+            .ok_or_else(|| self.bad_param(i, "must be an int"))
+    }
+
+    pub fn opt_int(&self, i: usize) -> Result<Option<usize>, SpecError> {
+        match self.param(i)? {
+            SpecValue::Int(n) => Ok(Some(*n)),
+            SpecValue::None => Ok(None),
+            _ => Err(self.bad_param(i, "must be an int")),
+        }
+    }
+
+    pub fn bool(&self, i: usize) -> Result<bool, SpecError> {
+        self.opt_bool(i)?
+            .ok_or_else(|| self.bad_param(i, "must be a bool"))
+    }
+
+    pub fn opt_bool(&self, i: usize) -> Result<Option<bool>, SpecError> {
+        match self.param(i)? {
+            SpecValue::Bool(b) => Ok(Some(*b)),
+            SpecValue::None => Ok(None),
+            _ => Err(self.bad_param(i, "must be a bool")),
+        }
+    }
+
+    pub fn list(&self, i: usize) -> Result<&[usize], SpecError> {
+        match self.param(i)? {
+            SpecValue::List(items) => Ok(items),
+            _ => Err(self.bad_param(i, "must be a list")),
+        }
+    }
+}
+
+/// A grammar as a flat table of nodes; the root is node 0. Shared and recursive
+/// subexpressions (through a Forward) appear once and are referenced by index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrammarSpec {
+    pub nodes: Vec<SpecNode>,
+}
+
+/// Collects the nodes of a grammar while elements describe themselves.
+pub struct SpecWriter {
+    nodes: Vec<Option<SpecNode>>,
+    seen: FxHashMap<usize, usize>,
+}
+
+impl SpecWriter {
+    /// Index of `elem`'s node, describing it the first time it is seen. The index is
+    /// reserved before the element's children are described, so cycles terminate.
+    pub fn child(&mut self, elem: &Arc<dyn ParserElement>) -> Result<SpecValue, SpecError> {
+        let key = element_key(elem);
+        if let Some(&index) = self.seen.get(&key) {
+            return Ok(SpecValue::Int(index));
+        }
+        let index = self.nodes.len();
+        self.nodes.push(None);
+        self.seen.insert(key, index);
+        let node = elem.describe(self)?;
+        self.nodes[index] = Some(node);
+        Ok(SpecValue::Int(index))
+    }
+
+    pub fn opt_child(
+        &mut self,
+        elem: Option<&Arc<dyn ParserElement>>,
+    ) -> Result<SpecValue, SpecError> {
+        match elem {
+            Some(elem) => self.child(elem),
+            None => Ok(SpecValue::None),
+        }
+    }
+
+    pub fn children(&mut self, elems: &[Arc<dyn ParserElement>]) -> Result<SpecValue, SpecError> {
+        let mut indexes = Vec::with_capacity(elems.len());
+        for elem in elems {
+            if let SpecValue::Int(index) = self.child(elem)? {
+                indexes.push(index);
+            }
+        }
+        Ok(SpecValue::List(indexes))
+    }
+}
+
+/// Describe `root` and everything reachable from it. Fails on elements that cannot
+/// be rebuilt from data, such as parse actions.
+pub fn describe_grammar(root: &Arc<dyn ParserElement>) -> Result<GrammarSpec, SpecError> {
+    let mut writer = SpecWriter {
+        nodes: Vec::new(),
+        seen: FxHashMap::default(),
+    };
+    writer.child(root)?;
+    let nodes = writer
+        .nodes
+        .into_iter()
+        .map(|node| node.expect("every reserved node is described"))
+        .collect();
+    Ok(GrammarSpec { nodes })
+}
@@ -0,0 +1,207 @@
+use crate::core::parser::ParserElement;
+use std::sync::Arc;
+
+/// Call `f` with the span of each match of `pattern` in `text`, found like
+/// search_string_count: scanning forward, resuming after each match.
+pub fn for_each_match(pattern: &dyn ParserElement, text: &str, mut f: impl FnMut(usize, usize)) {
+    let mut loc = 0;
+    while loc < text.len() {
+        match pattern.try_match_at(text, loc) {
+            Some(end) => {
+                f(loc, end);
+                loc = if end > loc { end } else { loc + 1 };
+            }
+            None => loc += 1,
+        }
+    }
+}
+
+/// Length in characters of `text[start..end]`, or in bytes if that isn't on
+/// character boundaries.
+pub fn char_len(text: &str, start: usize, end: usize) -> usize {
+    text.get(start..end)
+        .map_or(end - start, |m| m.chars().count())
+}
+
+/// Match statistics over a batch of inputs, kept as running totals so no match is
+/// stored. Matches are found like search_string_count, each pattern on its own.
+#[derive(Clone)]
+pub struct MatchStats {
+    per_pattern: Vec<usize>,
+    /// Matches of all patterns in each input, in input order
+    per_input: Vec<usize>,
+    inputs_without_matches: usize,
+    /// Match lengths in characters
+    min_len: Option<usize>,
+    max_len: usize,
+    total_len: usize,
+    /// Match start byte offsets: bin 0 counts offset 0, bin k offsets in
+    /// `2^(k-1)..2^k` (see start_bin_edges)
+    start_bins: Vec<usize>,
+}
+
+impl MatchStats {
+    pub fn new(patterns: usize) -> Self {
+        Self {
+            per_pattern: vec![0; patterns],
+            per_input: Vec::new(),
+            inputs_without_matches: 0,
+            min_len: None,
+            max_len: 0,
+            total_len: 0,
+            start_bins: Vec::new(),
+        }
+    }
+
+    /// Scan `text` with every pattern and add its matches.
+    pub fn add_input(&mut self, patterns: &[Arc<dyn ParserElement>], text: &str) {
+        let mut found = 0;
+        for (count, pattern) in self.per_pattern.iter_mut().zip(patterns) {
+            for_each_match(pattern.as_ref(), text, |start, end| {
+                *count += 1;
+                found += 1;
+                let len = char_len(text, start, end);
+                self.min_len = Some(self.min_len.map_or(len, |min| min.min(len)));
+                self.max_len = self.max_len.max(len);
+                self.total_len += len;
+                let bin = (usize::BITS - start.leading_zeros()) as usize;
+                if self.start_bins.len() <= bin {
+                    self.start_bins.resize(bin + 1, 0);
+                }
+                self.start_bins[bin] += 1;
+            });
+        }
+        self.per_input.push(found);
+        self.inputs_without_matches += (found == 0) as usize;
+    }
+
+    /// Add the statistics of the inputs that follow this batch's.
+    pub fn merge(&mut self, other: MatchStats) {
+        for (count, more) in self.per_pattern.iter_mut().zip(other.per_pattern) {
+            *count += more;
+        }
+        self.per_input.extend(other.per_input);
+        self.inputs_without_matches += other.inputs_without_matches;
+        self.min_len = match (self.min_len, other.min_len) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max_len = self.max_len.max(other.max_len);
+        self.total_len += other.total_len;
+        if self.start_bins.len() < other.start_bins.len() {
+            self.start_bins.resize(other.start_bins.len(), 0);
+        }
+        for (count, more) in self.start_bins.iter_mut().zip(other.start_bins) {
+            *count += more;
+        }
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.per_input.len()
+    }
+
+    pub fn matches(&self) -> usize {
+        self.per_pattern.iter().sum()
+    }
+
+    pub fn per_pattern(&self) -> &[usize] {
+        &self.per_pattern
+    }
+
+    pub fn per_input(&self) -> &[usize] {
+        &self.per_input
+    }
+
+    pub fn inputs_without_matches(&self) -> usize {
+        self.inputs_without_matches
+    }
+
+    /// Min, max and mean match length in characters, or None without matches.
+    pub fn match_length(&self) -> Option<(usize, usize, f64)> {
+        let min = self.min_len?;
+        Some((
+            min,
+            self.max_len,
+            self.total_len as f64 / self.matches() as f64,
+        ))
+    }
+
+    /// Match counts per start offset bin, up to the last non-empty bin.
+    pub fn start_bins(&self) -> &[usize] {
+        &self.start_bins
+    }
+
+    /// Lowest start offset of each bin in start_bins(): 0, 1, 2, 4, 8, ...
+    pub fn start_bin_edges(&self) -> Vec<usize> {
+        (0..self.start_bins.len())
+            .map(|bin| match bin {
+                0 => 0,
+                _ => 1 << (bin - 1),
+            })
+            .collect()
+    }
+}
+
+/// Counts of values in `num_bins` equal bins from `min`, plus the values below
+/// and at or above the range.
+#[derive(Clone)]
+pub struct Histogram {
+    min: f64,
+    bin_width: f64,
+    counts: Vec<usize>,
+    underflow: usize,
+    overflow: usize,
+}
+
+impl Histogram {
+    /// `bin_width` must be positive and `num_bins` at least 1.
+    pub fn new(min: f64, bin_width: f64, num_bins: usize) -> Self {
+        Self {
+            min,
+            bin_width,
+            counts: vec![0; num_bins],
+            underflow: 0,
+            overflow: 0,
+        }
+    }
+
+    pub fn add(&mut self, value: usize) {
+        let offset = (value as f64 - self.min) / self.bin_width;
+        if offset < 0.0 {
+            self.underflow += 1;
+        } else {
+            match self.counts.get_mut(offset as usize) {
+                Some(count) => *count += 1,
+                None => self.overflow += 1,
+            }
+        }
+    }
+
+    /// Add the counts of a histogram with the same bins.
+    pub fn merge(&mut self, other: Histogram) {
+        for (count, more) in self.counts.iter_mut().zip(other.counts) {
+            *count += more;
+        }
+        self.underflow += other.underflow;
+        self.overflow += other.overflow;
+    }
+
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    pub fn underflow(&self) -> usize {
+        self.underflow
+    }
+
+    pub fn overflow(&self) -> usize {
+        self.overflow
+    }
+
+    /// The `num_bins + 1` bin boundaries, from `min` to the top of the range.
+    pub fn edges(&self) -> Vec<f64> {
+        (0..=self.counts.len())
+            .map(|k| self.min + k as f64 * self.bin_width)
+            .collect()
+    }
+}
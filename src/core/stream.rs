@@ -0,0 +1,160 @@
+use crate::core::context::ParseContext;
+use crate::core::parser::ParserElement;
+use crate::core::results::ParseResults;
+use crate::core::segments::Segments;
+use std::sync::Arc;
+
+/// A match found in a stream: its tokens and `(start, end)` stream offsets.
+pub type StreamMatch = (ParseResults<'static>, usize, usize);
+
+/// Bytes fed that aren't valid UTF-8, at `offset` in the stream.
+#[derive(Debug, Clone)]
+pub struct StreamError {
+    pub offset: usize,
+}
+
+/// search_string over text that arrives in pieces, such as reads from a socket.
+///
+/// Each feed scans the text buffered so far, but only commits to what more input
+/// can't change: an attempt at a position (matching or not) whose matcher looked
+/// at the end of the buffer is left for later, and so is everything after it. So
+/// a Word ending at the end of the buffer waits for the next chunk, and so does
+/// `"ab" + Optional("cde")` with only `abcd` buffered. Matchers that read through
+/// a contiguous window (Regex, QuotedString, ...) can't tell how far they looked,
+/// so their attempts wait until the end of the stream. Once more than
+/// `max_buffer` bytes are held from a position, its attempt is decided on the text
+/// buffered so far instead, which bounds the buffer on input that never settles.
+pub struct StreamScanner {
+    parser: Arc<dyn ParserElement>,
+    /// Text from just before the next attempt: one character already scanned is
+    /// kept so matchers that look back (LineStart, Keyword) see it.
+    buffer: String,
+    /// Where in `buffer` the next attempt starts
+    resume: usize,
+    /// Stream offset of `buffer[0]`
+    offset: usize,
+    /// Trailing bytes of a UTF-8 sequence that the next chunk completes
+    partial_char: Vec<u8>,
+    max_buffer: usize,
+}
+
+impl StreamScanner {
+    pub fn new(parser: Arc<dyn ParserElement>, max_buffer: usize) -> Self {
+        Self {
+            parser,
+            buffer: String::new(),
+            resume: 0,
+            offset: 0,
+            partial_char: Vec::new(),
+            max_buffer,
+        }
+    }
+
+    /// Bytes held for attempts still to be decided.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.resume + self.partial_char.len()
+    }
+
+    /// Stream offset of the next attempt.
+    pub fn position(&self) -> usize {
+        self.offset + self.resume
+    }
+
+    /// Whether the bytes fed so far end partway through a UTF-8 sequence.
+    pub fn has_partial_char(&self) -> bool {
+        !self.partial_char.is_empty()
+    }
+
+    /// Append `text` and return the matches it settles.
+    pub fn feed(&mut self, text: &str) -> Vec<StreamMatch> {
+        self.buffer.push_str(text);
+        self.scan(false)
+    }
+
+    /// Append UTF-8 `bytes`, which may end partway through a character that the
+    /// next chunk completes. Invalid UTF-8 is an error and leaves nothing appended.
+    pub fn feed_bytes(&mut self, bytes: &[u8]) -> Result<Vec<StreamMatch>, StreamError> {
+        let mut data = self.partial_char.clone();
+        data.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&data) {
+            Ok(_) => data.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => {
+                return Err(StreamError {
+                    offset: self.offset + self.buffer.len() + e.valid_up_to(),
+                })
+            }
+        };
+        self.partial_char = data.split_off(valid);
+        let text = std::str::from_utf8(&data).expect("checked to be UTF-8 above");
+        Ok(self.feed(text))
+    }
+
+    /// End the stream: decide every attempt still held, now that no more input
+    /// comes. Also returns whether data was left over: held text after the last
+    /// match that isn't whitespace, or an unfinished UTF-8 sequence.
+    pub fn finish(&mut self) -> (Vec<StreamMatch>, bool) {
+        let held_from = self.position();
+        let held = self.buffer[self.resume..].to_string();
+        let found = self.scan(true);
+        let tail_from = found.last().map_or(held_from, |&(_, _, end)| end) - held_from;
+        let leftover = !held[tail_from..].trim().is_empty() || !self.partial_char.is_empty();
+        self.offset += self.buffer.len();
+        self.buffer.clear();
+        self.resume = 0;
+        self.partial_char.clear();
+        (found, leftover)
+    }
+
+    /// Scan from `resume`, like search_string, committing to each attempt whose
+    /// outcome can't change, or every attempt `at_end` of the stream.
+    fn scan(&mut self, at_end: bool) -> Vec<StreamMatch> {
+        let parser = &self.parser;
+        let text = self.buffer.as_str();
+        let input = Segments::new(vec![text]);
+        let mut ctx = ParseContext::new(text);
+        let mut found = Vec::new();
+        let mut pos = self.resume;
+        while pos < text.len() {
+            if !text.is_char_boundary(pos) {
+                pos += 1;
+                continue;
+            }
+            let decided = at_end || text.len() - pos > self.max_buffer;
+            input.take_reached_end();
+            let attempt = parser.try_match_segments(&input, pos);
+            if input.take_reached_end() && !decided {
+                break;
+            }
+            let matched = match attempt {
+                Some(end) if end > pos => ctx.parse(parser, pos).ok(),
+                _ => None,
+            };
+            let Some((end, results)) = matched else {
+                pos += 1;
+                continue;
+            };
+            let start = match parser.skip_whitespace_before() {
+                true => ctx.skip_ws(pos).min(end),
+                false => pos,
+            };
+            // Zero-length matches are skipped, as in search_string
+            if end <= start {
+                pos += 1;
+                continue;
+            }
+            found.push((results.into_owned(), self.offset + start, self.offset + end));
+            pos = end;
+        }
+        drop(ctx);
+        // Keep the character before `pos` for matchers that look back
+        let keep = text[..pos]
+            .chars()
+            .next_back()
+            .map_or(pos, |c| pos - c.len_utf8());
+        self.buffer.drain(..keep);
+        self.offset += keep;
+        self.resume = pos - keep;
+        found
+    }
+}
@@ -0,0 +1,160 @@
+use crate::core::exceptions::FileError;
+use rustc_hash::FxHashSet;
+use std::path::{Path, PathBuf};
+
+/// A shell-style wildcard pattern as Python's fnmatch reads it: `*` matches any
+/// run of characters (path separators included), `?` any one character, `[seq]`
+/// any character in seq and `[!seq]` any character not in it.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    regex: regex::Regex,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        let mut re = String::from("(?s)^");
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '*' => re.push_str(".*"),
+                '?' => re.push('.'),
+                '[' => match class_end(&chars, i) {
+                    Some(end) => {
+                        let mut body = &chars[i + 1..end];
+                        re.push('[');
+                        if let Some(('!', rest)) = body.split_first() {
+                            re.push('^');
+                            body = rest;
+                        }
+                        for &c in body {
+                            if matches!(c, '\\' | '[' | ']' | '^' | '&' | '~') {
+                                re.push('\\');
+                            }
+                            re.push(c);
+                        }
+                        re.push(']');
+                        i = end;
+                    }
+                    None => re.push_str(r"\["),
+                },
+                c => re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+            i += 1;
+        }
+        re.push('$');
+        Ok(Self {
+            regex: regex::Regex::new(&re)?,
+        })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+/// Index of the `]` closing the class opened at `chars[open]`; a `]` right after
+/// `[` or `[!` is part of the class, as in fnmatch.
+fn class_end(chars: &[char], open: usize) -> Option<usize> {
+    let mut i = open + 1;
+    if chars.get(i) == Some(&'!') {
+        i += 1;
+    }
+    if chars.get(i) == Some(&']') {
+        i += 1;
+    }
+    (i..chars.len()).find(|&j| chars[j] == ']')
+}
+
+/// Which files walk_files yields.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Files whose path matches none of these are left out; empty keeps every file.
+    pub include: Vec<Glob>,
+    /// Files and directories whose path matches any of these are left out, and
+    /// excluded directories aren't entered.
+    pub exclude: Vec<Glob>,
+    /// Enter symlinked directories and yield symlinked files; otherwise symlinks
+    /// are skipped.
+    pub follow_symlinks: bool,
+    /// How many directory levels below the root to enter; None for no limit.
+    pub max_depth: Option<usize>,
+}
+
+/// Paths of the files under `root`, depth first with each directory's entries in
+/// name order. Paths are `root` joined with the relative path, and that is what
+/// the patterns are matched against; a directory also matches with a trailing
+/// separator, so `*/node_modules/*` prunes node_modules. Following symlinks, a
+/// directory is entered only once, so links back up the tree don't loop.
+pub fn walk_files(root: &Path, options: &WalkOptions) -> Result<Vec<PathBuf>, FileError> {
+    let mut out = Vec::new();
+    let mut seen = FxHashSet::default();
+    if std::fs::metadata(root)
+        .map_err(|e| FileError::new(root, e))?
+        .is_file()
+    {
+        if wanted_file(root, options) {
+            out.push(root.to_path_buf());
+        }
+        return Ok(out);
+    }
+    walk_dir(root, 0, options, &mut seen, &mut out)?;
+    Ok(out)
+}
+
+fn walk_dir(
+    dir: &Path,
+    depth: usize,
+    options: &WalkOptions,
+    seen: &mut FxHashSet<PathBuf>,
+    out: &mut Vec<PathBuf>,
+) -> Result<(), FileError> {
+    if options.follow_symlinks {
+        let real = dir.canonicalize().map_err(|e| FileError::new(dir, e))?;
+        if !seen.insert(real) {
+            return Ok(());
+        }
+    }
+    let mut entries = std::fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| FileError::new(dir, e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let mut file_type = entry.file_type().map_err(|e| FileError::new(&path, e))?;
+        if file_type.is_symlink() {
+            if !options.follow_symlinks {
+                continue;
+            }
+            // A dangling link has nothing to read
+            match std::fs::metadata(&path) {
+                Ok(meta) => file_type = meta.file_type(),
+                Err(_) => continue,
+            }
+        }
+        if file_type.is_dir() {
+            let within = options.max_depth.is_none_or(|max| depth < max);
+            if within && !excluded_dir(&path, options) {
+                walk_dir(&path, depth + 1, options, seen, out)?;
+            }
+        } else if file_type.is_file() && wanted_file(&path, options) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn wanted_file(path: &Path, options: &WalkOptions) -> bool {
+    let text = path.to_string_lossy();
+    (options.include.is_empty() || options.include.iter().any(|g| g.is_match(&text)))
+        && !options.exclude.iter().any(|g| g.is_match(&text))
+}
+
+fn excluded_dir(path: &Path, options: &WalkOptions) -> bool {
+    let text = path.to_string_lossy();
+    let with_sep = format!("{}{}", text, std::path::MAIN_SEPARATOR);
+    options
+        .exclude
+        .iter()
+        .any(|g| g.is_match(&text) || g.is_match(&with_sep))
+}
@@ -0,0 +1,135 @@
+use crate::core::context::ParseContext;
+use crate::core::exceptions::ParseException;
+use crate::core::parser::{element_key, Namer, ParseResult, ParserElement, ParserKind};
+use crate::core::results::ParseResults;
+use crate::core::spec::{SpecError, SpecNode, SpecWriter};
+use std::sync::Arc;
+
+/// MatchPreviousLiteral - matches the exact text most recently matched by another
+/// element earlier in the enclosing sequence, however deeply it is nested there.
+/// `Word(alphas) + match_previous_literal(word)` accepts "foo foo" but rejects "foo bar".
+#[derive(Clone)]
+pub struct MatchPreviousLiteral {
+    expr: Arc<dyn ParserElement>,
+}
+
+impl MatchPreviousLiteral {
+    pub fn new(expr: Arc<dyn ParserElement>) -> Self {
+        Self { expr }
+    }
+}
+
+impl ParserElement for MatchPreviousLiteral {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let input = ctx.input();
+        let (start, end) = ctx
+            .last_match(element_key(&self.expr))
+            .ok_or_else(|| ParseException::new(loc, "No previous match to refer back to"))?;
+        let prev = &input[start..end];
+        if input[loc..].starts_with(prev) {
+            Ok((loc + prev.len(), ParseResults::from_single(prev)))
+        } else {
+            Err(ParseException::new(
+                loc,
+                format!("Expected '{}' (matching previous)", prev),
+            ))
+        }
+    }
+
+    /// Without a parse context there is no previous match to compare against.
+    #[inline]
+    fn try_match_at(&self, _input: &str, _loc: usize) -> Option<usize> {
+        None
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn has_backref(&self) -> bool {
+        true
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.expr);
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("MatchPreviousLiteral:({})", namer.child(&self.expr))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "MatchPreviousLiteral",
+            vec![spec.child(&self.expr)?],
+        ))
+    }
+}
+
+/// MatchPreviousExpr - re-parses another element at the current position and
+/// requires the tokens to equal those it produced for its most recent match.
+#[derive(Clone)]
+pub struct MatchPreviousExpr {
+    expr: Arc<dyn ParserElement>,
+}
+
+impl MatchPreviousExpr {
+    pub fn new(expr: Arc<dyn ParserElement>) -> Self {
+        Self { expr }
+    }
+}
+
+impl ParserElement for MatchPreviousExpr {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let (start, _) = ctx
+            .last_match(element_key(&self.expr))
+            .ok_or_else(|| ParseException::new(loc, "No previous match to refer back to"))?;
+        let (_, prev) = self.expr.parse_impl(ctx, start)?;
+        let (end, current) = self.expr.parse_impl(ctx, loc)?;
+        if current.items() == prev.items() {
+            Ok((end, current))
+        } else {
+            Err(ParseException::new(
+                loc,
+                "Expected tokens matching previous expression",
+            ))
+        }
+    }
+
+    /// Without a parse context there is no previous match to compare against.
+    #[inline]
+    fn try_match_at(&self, _input: &str, _loc: usize) -> Option<usize> {
+        None
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn has_backref(&self) -> bool {
+        true
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.expr);
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("MatchPreviousExpr:({})", namer.child(&self.expr))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "MatchPreviousExpr",
+            vec![spec.child(&self.expr)?],
+        ))
+    }
+}
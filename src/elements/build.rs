@@ -0,0 +1,272 @@
+use crate::core::context::WhitespaceChars;
+use crate::core::parser::ParserElement;
+use crate::core::spec::{GrammarSpec, SpecError, SpecNode};
+use crate::elements::backref::{MatchPreviousExpr, MatchPreviousLiteral};
+use crate::elements::chars::{CharClassError, QuotedString, RegexMatch, RegexOutput, Word};
+use crate::elements::combinators::{And, Each, MatchFirst};
+use crate::elements::comments::Comment;
+use crate::elements::forward::Forward;
+use crate::elements::literals::{
+    CaselessKeyword, CaselessLiteral, Char, CloseMatch, EmptyStringError, Keyword, Literal,
+};
+use crate::elements::markup::{HtmlEndTag, HtmlTag};
+use crate::elements::network::Ipv6Address;
+use crate::elements::numbers::{Number, NumberKind};
+use crate::elements::positional::{
+    AsLine, GoToColumn, IndentedBlock, LineEnd, LineStart, RestOfLine, StringEnd, StringStart,
+};
+use crate::elements::repetition::{Exactly, OneOrMore, Optional, Repeat, ZeroOrMore};
+use crate::elements::structure::{
+    Combine, DebugFn, DebugTrace, Dict, Empty, Group, HighlightClass, Located, Named, NoMatch,
+    OriginalTextFor, SkipTo, Suppress, TokenTransform, Transform, WhitespaceScope,
+};
+use std::sync::Arc;
+
+/// Rebuild the grammar described by `spec` (see `describe_grammar`). Debug traces
+/// that were on report to `debug_hook`.
+pub fn build_grammar(
+    spec: &GrammarSpec,
+    debug_hook: &Arc<DebugFn>,
+) -> Result<Arc<dyn ParserElement>, SpecError> {
+    let mut builder = Builder {
+        spec,
+        debug_hook,
+        built: vec![None; spec.nodes.len()],
+        building: vec![false; spec.nodes.len()],
+    };
+    builder.element(0)
+}
+
+struct Builder<'s> {
+    spec: &'s GrammarSpec,
+    debug_hook: &'s Arc<DebugFn>,
+    built: Vec<Option<Arc<dyn ParserElement>>>,
+    /// Nodes whose children are being built, to reject loops without a Forward
+    building: Vec<bool>,
+}
+
+impl Builder<'_> {
+    /// The element for node `index`, shared by every reference to it. Forwards are
+    /// registered before their definition is built, which closes recursive loops.
+    fn element(&mut self, index: usize) -> Result<Arc<dyn ParserElement>, SpecError> {
+        if let Some(Some(elem)) = self.built.get(index) {
+            return Ok(elem.clone());
+        }
+        let spec = self.spec;
+        let node = spec
+            .nodes
+            .get(index)
+            .ok_or_else(|| SpecError::new(format!("no node {}", index)))?;
+        if &*node.kind == "Forward" {
+            let forward = Arc::new(Forward::new());
+            self.built[index] = Some(forward.clone());
+            if let Some(inner) = node.opt_int(0)? {
+                let inner = self.element(inner)?;
+                forward
+                    .set(inner)
+                    .map_err(|_| SpecError::new("Forward frozen while rebuilding"))?;
+            }
+            if node.bool(1)? {
+                forward.freeze();
+            }
+            return Ok(forward);
+        }
+        // Only a Forward can close a loop, and it is registered above
+        if std::mem::replace(&mut self.building[index], true) {
+            return Err(SpecError::new(format!("node {} contains itself", index)));
+        }
+        let elem = self.build(node)?;
+        self.built[index] = Some(elem.clone());
+        Ok(elem)
+    }
+
+    fn child(&mut self, node: &SpecNode, i: usize) -> Result<Arc<dyn ParserElement>, SpecError> {
+        self.element(node.int(i)?)
+    }
+
+    fn opt_child(
+        &mut self,
+        node: &SpecNode,
+        i: usize,
+    ) -> Result<Option<Arc<dyn ParserElement>>, SpecError> {
+        node.opt_int(i)?
+            .map(|index| self.element(index))
+            .transpose()
+    }
+
+    fn children(
+        &mut self,
+        node: &SpecNode,
+        i: usize,
+    ) -> Result<Vec<Arc<dyn ParserElement>>, SpecError> {
+        node.list(i)?
+            .iter()
+            .map(|&index| self.element(index))
+            .collect()
+    }
+
+    fn build(&mut self, node: &SpecNode) -> Result<Arc<dyn ParserElement>, SpecError> {
+        let invalid = |what: &str| SpecError::new(format!("invalid {} for {}", what, node.kind));
+        let empty_error = |e: EmptyStringError| SpecError::new(e.to_string());
+        Ok(match &*node.kind {
+            "Char" => Arc::new(Char::new(node.str(0)?)),
+            "Literal" => Arc::new(Literal::try_new(node.str(0)?).map_err(empty_error)?),
+            "Keyword" => Arc::new(
+                Keyword::try_new(node.str(0)?)
+                    .map_err(empty_error)?
+                    .with_caseless(node.bool(1)?)
+                    .with_ident_chars(node.str(2)?)
+                    .map_err(|e| SpecError::new(e.to_string()))?,
+            ),
+            "CaselessLiteral" => {
+                Arc::new(CaselessLiteral::try_new(node.str(0)?).map_err(empty_error)?)
+            }
+            "CloseMatch" => Arc::new(
+                CloseMatch::try_new(node.str(0)?, node.int(1)?)
+                    .map_err(empty_error)?
+                    .with_caseless(node.bool(2)?),
+            ),
+            "HtmlTag" if node.bool(1)? => Arc::new(HtmlTag::xml(node.str(0)?)),
+            "HtmlTag" => Arc::new(HtmlTag::html(node.str(0)?)),
+            "HtmlEndTag" if node.bool(1)? => Arc::new(HtmlEndTag::xml(node.str(0)?)),
+            "HtmlEndTag" => Arc::new(HtmlEndTag::html(node.str(0)?)),
+            "CaselessKeyword" => {
+                Arc::new(CaselessKeyword::try_new(node.str(0)?).map_err(empty_error)?)
+            }
+            "Word" => {
+                // The class flags were added later; specs without them use plain chars
+                let is_class = |i: usize| match node.params.len() > i {
+                    true => node.bool(i),
+                    false => Ok(false),
+                };
+                let class_error = |e: CharClassError| SpecError::new(e.to_string());
+                let mut word = match is_class(6)? {
+                    true => Word::from_class(node.str(0)?).map_err(class_error)?,
+                    false => Word::try_new(node.str(0)?).map_err(class_error)?,
+                }
+                .with_min(node.int(2)?)
+                .with_max(node.int(3)?)
+                .with_as_keyword(node.bool(4)?);
+                if let Some(body) = node.opt_str(1)? {
+                    word = match is_class(7)? {
+                        true => word.with_body_class(body).map_err(class_error)?,
+                        false => word.with_body_chars(body),
+                    };
+                }
+                if let Some(exclude) = node.opt_str(5)? {
+                    word = word.with_exclude_chars(exclude);
+                }
+                Arc::new(word)
+            }
+            "RegexMatch" => {
+                let regex =
+                    RegexMatch::new(node.str(0)?).map_err(|e| SpecError::new(e.to_string()))?;
+                let output = match (node.bool(1)?, node.bool(2)?) {
+                    (true, _) => RegexOutput::GroupList,
+                    (_, true) => RegexOutput::Match,
+                    _ => RegexOutput::Tokens,
+                };
+                Arc::new(regex.with_output(output))
+            }
+            "QuotedString" => Arc::new(QuotedString::new(
+                node.str(0)?,
+                node.opt_str(1)?,
+                node.bool(2)?,
+                node.bool(3)?,
+            )),
+            "Comment" => {
+                Arc::new(Comment::from_style(node.str(0)?).ok_or_else(|| invalid("style"))?)
+            }
+            "Number" => {
+                let kind = NumberKind::from_label(node.str(0)?).ok_or_else(|| invalid("kind"))?;
+                Arc::new(Number::new(kind))
+            }
+            "Ipv6Address" => Arc::new(Ipv6Address::new()),
+            "StringStart" => Arc::new(StringStart),
+            "StringEnd" => Arc::new(StringEnd),
+            "LineStart" => Arc::new(LineStart),
+            "LineEnd" => Arc::new(LineEnd),
+            "RestOfLine" if node.bool(0)? => Arc::new(RestOfLine::stripped()),
+            "RestOfLine" => Arc::new(RestOfLine::new()),
+            "GoToColumn" if node.bool(1)? => Arc::new(GoToColumn::new(node.int(0)?).suppressed()),
+            "GoToColumn" => Arc::new(GoToColumn::new(node.int(0)?)),
+            "Empty" => Arc::new(Empty),
+            "NoMatch" => Arc::new(NoMatch),
+            "And" => Arc::new(And::new(self.children(node, 0)?).with_error_stop(node.opt_int(1)?)),
+            "MatchFirst" => Arc::new(MatchFirst::new(self.children(node, 0)?)),
+            "Each" => Arc::new(Each::with_bounds(
+                self.children(node, 0)?,
+                node.int(1)?,
+                node.int(2)?,
+            )),
+            "ZeroOrMore" => Arc::new(
+                ZeroOrMore::new(self.child(node, 0)?).with_stop_on(self.opt_child(node, 1)?),
+            ),
+            "OneOrMore" => Arc::new(
+                OneOrMore::new(self.child(node, 0)?).with_stop_on(self.opt_child(node, 1)?),
+            ),
+            "Optional" => Arc::new(Optional::new(self.child(node, 0)?)),
+            "Exactly" => Arc::new(Exactly::new(self.child(node, 0)?, node.int(1)?)),
+            "Repeat" => Arc::new(Repeat::new(
+                self.child(node, 0)?,
+                node.int(1)?,
+                node.opt_int(2)?,
+            )),
+            "SkipTo" => Arc::new(SkipTo::new(self.child(node, 0)?)),
+            "Group" => Arc::new(Group::new(self.child(node, 0)?)),
+            "Dict" => Arc::new(Dict::new(self.child(node, 0)?)),
+            "Located" => Arc::new(Located::new(self.child(node, 0)?)),
+            "Suppress" => Arc::new(Suppress::new(self.child(node, 0)?)),
+            // The join string and adjacent flag were added later
+            "Combine" if node.params.len() > 1 => Arc::new(
+                Combine::new(self.child(node, 0)?)
+                    .with_join_string(node.str(1)?)
+                    .with_adjacent(node.bool(2)?),
+            ),
+            "Combine" => Arc::new(Combine::new(self.child(node, 0)?)),
+            "Transform" => {
+                let transform = TokenTransform::from_name(node.str(1)?, node.str(2)?)
+                    .ok_or_else(|| invalid("transform"))?;
+                Arc::new(Transform::new(self.child(node, 0)?, transform))
+            }
+            "AsLine" => Arc::new(AsLine::new(self.child(node, 0)?)),
+            "IndentedBlock" => Arc::new(
+                IndentedBlock::new(self.child(node, 0)?)
+                    .with_recursive(node.bool(1)?)
+                    .with_grouped(node.bool(2)?),
+            ),
+            "MatchPreviousLiteral" => Arc::new(MatchPreviousLiteral::new(self.child(node, 0)?)),
+            "MatchPreviousExpr" => Arc::new(MatchPreviousExpr::new(self.child(node, 0)?)),
+            "OriginalTextFor" if node.bool(1)? => {
+                Arc::new(OriginalTextFor::new(self.child(node, 0)?))
+            }
+            "OriginalTextFor" => Arc::new(OriginalTextFor::with_locations(self.child(node, 0)?)),
+            "HighlightClass" => Arc::new(HighlightClass::new(self.child(node, 0)?, node.str(1)?)),
+            "Named" => Arc::new(Named::new(
+                self.child(node, 0)?,
+                node.str(1)?,
+                node.bool(2)?,
+            )),
+            "DebugTrace" => {
+                let hook = node.bool(1)?.then(|| self.debug_hook.clone());
+                Arc::new(DebugTrace::new(self.child(node, 0)?, hook))
+            }
+            "WhitespaceScope" => {
+                let whitespace = match node.opt_str(1)? {
+                    Some(chars) => {
+                        Some(WhitespaceChars::new(chars).ok_or_else(|| invalid("whitespace"))?)
+                    }
+                    None => None,
+                };
+                Arc::new(WhitespaceScope::from_parts(
+                    self.child(node, 0)?,
+                    whitespace,
+                    node.opt_bool(2)?,
+                    node.opt_bool(3)?,
+                    self.children(node, 4)?,
+                ))
+            }
+            kind => return Err(SpecError::new(format!("unknown element kind {}", kind))),
+        })
+    }
+}
@@ -0,0 +1,332 @@
+use crate::core::parser::ParserElement;
+use crate::elements::chars::{RegexMatch, Word};
+use crate::elements::combinators::{And, MatchFirst};
+use crate::elements::literals::{CaselessLiteral, Keyword, Literal};
+use crate::elements::repetition::{Exactly, OneOrMore, Optional, Repeat, ZeroOrMore};
+use crate::elements::structure::{Combine, Group, Named, Suppress};
+use std::ops::{Add, BitOr, Deref, Sub};
+use std::sync::Arc;
+
+pub const ALPHAS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+pub const NUMS: &str = "0123456789";
+pub const ALPHANUMS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+pub const IDENTCHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_";
+pub const IDENTBODYCHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_";
+pub const PRINTABLES: &str =
+    "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// An element for building grammars from Rust with the Python API's operators:
+/// `a + b` is a sequence, `a | b` the first alternative that matches and `a - b` a
+/// sequence whose elements after `a` fail fatally (an error stop). A `&str` operand
+/// is a Literal. Sequences and alternatives are flattened as they are chained, and
+/// `+` binds tighter than `|`, so `lit("a") + "b" | "c"` is `("a" + "b") | "c"`.
+///
+/// ```
+/// use pyparsing_rs::elements::builder::{kw, lit, word, ALPHAS, NUMS};
+///
+/// let value = word(NUMS) | word(ALPHAS);
+/// let assign = word(ALPHAS).named("target") + lit("=").suppress() + value + ";";
+/// let stmt = assign | kw("pass") + ";";
+///
+/// let results = stmt.parse_string("x = 42;").unwrap();
+/// assert_eq!(results.items().len(), 3);
+/// assert_eq!(&*results.named()[0].name, "target");
+/// assert!(stmt.parse_string("pass;").is_ok());
+/// assert_eq!(stmt.parse_string("x = ;").unwrap_err().loc, 4);
+/// ```
+#[derive(Clone)]
+pub struct Expr(pub Arc<dyn ParserElement>);
+
+/// Literal text.
+pub fn lit(s: &str) -> Expr {
+    Expr::new(Literal::new(s))
+}
+
+/// A keyword: literal text not followed by an identifier character.
+pub fn kw(s: &str) -> Expr {
+    Expr::new(Keyword::new(s))
+}
+
+/// Literal text matched regardless of ASCII case.
+pub fn caseless(s: &str) -> Expr {
+    Expr::new(CaselessLiteral::new(s))
+}
+
+/// A word of the characters in `chars`.
+pub fn word(chars: &str) -> Expr {
+    Expr::new(Word::new(chars))
+}
+
+/// A word starting with a character of `init_chars`, continuing with `body_chars`.
+pub fn word2(init_chars: &str, body_chars: &str) -> Expr {
+    Expr::new(Word::new(init_chars).with_body_chars(body_chars))
+}
+
+pub fn regex(pattern: &str) -> Result<Expr, regex::Error> {
+    RegexMatch::new(pattern).map(Expr::new)
+}
+
+impl Expr {
+    pub fn new(element: impl ParserElement + 'static) -> Self {
+        Expr(Arc::new(element))
+    }
+
+    pub fn element(&self) -> &Arc<dyn ParserElement> {
+        &self.0
+    }
+
+    pub fn into_element(self) -> Arc<dyn ParserElement> {
+        self.0
+    }
+
+    pub fn optional(self) -> Expr {
+        Expr::new(Optional::new(self.0))
+    }
+
+    pub fn zero_or_more(self) -> Expr {
+        Expr::new(ZeroOrMore::new(self.0))
+    }
+
+    pub fn one_or_more(self) -> Expr {
+        Expr::new(OneOrMore::new(self.0))
+    }
+
+    /// Exactly `n` repetitions.
+    pub fn times(self, n: usize) -> Expr {
+        Expr::new(Exactly::new(self.0, n))
+    }
+
+    /// `min` to `max` repetitions (no upper bound if None).
+    pub fn repeat(self, min: usize, max: Option<usize>) -> Expr {
+        Expr::new(Repeat::new(self.0, min, max))
+    }
+
+    pub fn group(self) -> Expr {
+        Expr::new(Group::new(self.0))
+    }
+
+    pub fn suppress(self) -> Expr {
+        Expr::new(Suppress::new(self.0))
+    }
+
+    pub fn combine(self) -> Expr {
+        Expr::new(Combine::new(self.0))
+    }
+
+    /// Register the tokens under `name` in the results.
+    pub fn named(self, name: &str) -> Expr {
+        Expr::new(Named::new(self.0, name, false))
+    }
+
+    /// The elements of a sequence, or this element alone, with the error stop.
+    fn sequence_parts(self) -> (Vec<Arc<dyn ParserElement>>, Option<usize>) {
+        match self.0.clone().into_any().downcast::<And>() {
+            Ok(and) => (and.elements().to_vec(), and.error_stop()),
+            Err(_) => (vec![self.0], None),
+        }
+    }
+
+    fn alternatives(self) -> Vec<Arc<dyn ParserElement>> {
+        match self.0.clone().into_any().downcast::<MatchFirst>() {
+            Ok(first) => first.elements().to_vec(),
+            Err(_) => vec![self.0],
+        }
+    }
+
+    /// `self + other`, or `self - other` with `error_stop`.
+    fn join(self, other: Expr, error_stop: bool) -> Expr {
+        let (mut elements, stop) = self.sequence_parts();
+        let left_len = elements.len();
+        let stop = stop.or(error_stop.then_some(left_len));
+        let (right, right_stop) = other.sequence_parts();
+        elements.extend(right);
+        let stop = stop.or(right_stop.map(|s| s + left_len));
+        Expr::new(And::new(elements).with_error_stop(stop))
+    }
+}
+
+impl Deref for Expr {
+    type Target = dyn ParserElement;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl From<&str> for Expr {
+    fn from(s: &str) -> Self {
+        lit(s)
+    }
+}
+
+impl From<Arc<dyn ParserElement>> for Expr {
+    fn from(element: Arc<dyn ParserElement>) -> Self {
+        Expr(element)
+    }
+}
+
+/// E.g. a shared Forward, defined after the expressions that refer to it.
+impl<T: ParserElement + 'static> From<Arc<T>> for Expr {
+    fn from(element: Arc<T>) -> Self {
+        Expr(element)
+    }
+}
+
+impl<R: Into<Expr>> Add<R> for Expr {
+    type Output = Expr;
+
+    fn add(self, other: R) -> Expr {
+        self.join(other.into(), false)
+    }
+}
+
+impl<R: Into<Expr>> Sub<R> for Expr {
+    type Output = Expr;
+
+    fn sub(self, other: R) -> Expr {
+        self.join(other.into(), true)
+    }
+}
+
+impl<R: Into<Expr>> BitOr<R> for Expr {
+    type Output = Expr;
+
+    fn bitor(self, other: R) -> Expr {
+        let mut elements = self.alternatives();
+        elements.extend(other.into().alternatives());
+        Expr::new(MatchFirst::new(elements))
+    }
+}
+
+impl Add<Expr> for &str {
+    type Output = Expr;
+
+    fn add(self, other: Expr) -> Expr {
+        lit(self) + other
+    }
+}
+
+impl BitOr<Expr> for &str {
+    type Output = Expr;
+
+    fn bitor(self, other: Expr) -> Expr {
+        lit(self) | other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::results::{NamedValue, ParseResultItem, ParseResults};
+    use crate::elements::forward::Forward;
+
+    /// Tokens as nested strings, like the Python API's as_list().
+    fn as_list(items: &[ParseResultItem<'_>]) -> String {
+        let parts: Vec<String> = items
+            .iter()
+            .map(|item| match item {
+                ParseResultItem::Token(text) => format!("{:?}", &**text),
+                ParseResultItem::Int(n) => n.to_string(),
+                ParseResultItem::Float(x) => x.to_string(),
+                ParseResultItem::Group(inner) => as_list(inner.items()),
+                ParseResultItem::Object(_) => "?".to_string(),
+            })
+            .collect();
+        format!("[{}]", parts.join(", "))
+    }
+
+    fn parse(grammar: &Expr, input: &str) -> String {
+        let results: ParseResults<'_> = grammar.parse_string(input).unwrap();
+        as_list(results.items())
+    }
+
+    #[test]
+    fn hello_world() {
+        // Word(alphas) + "," + Word(alphas) + "!"
+        let greeting = word(ALPHAS) + "," + word(ALPHAS) + "!";
+        assert_eq!(
+            parse(&greeting, "Hello, World!"),
+            r#"["Hello", ",", "World", "!"]"#
+        );
+        let and = greeting
+            .0
+            .clone()
+            .into_any()
+            .downcast::<And>()
+            .ok()
+            .unwrap();
+        assert_eq!(and.elements().len(), 4);
+        assert_eq!(greeting.parse_string("Hello World").unwrap_err().loc, 6);
+    }
+
+    #[test]
+    fn recursive_arithmetic() {
+        // expr <<= term + ZeroOrMore(one_of("+ -") + term)
+        // term = factor + ZeroOrMore(one_of("* /") + factor)
+        // factor = integer | Group("(" + expr + ")")
+        let expr = Arc::new(Forward::new());
+        let integer = word(NUMS);
+        let factor = integer | ("(" + Expr::from(expr.clone()) + ")").group();
+        let term = factor.clone() + ((lit("*") | "/") + factor).zero_or_more();
+        let sum = term.clone() + ((lit("+") | "-") + term).zero_or_more();
+        expr.set(sum.into_element()).unwrap();
+        let expr = Expr::from(expr);
+        assert_eq!(
+            parse(&expr, "1 + 2 * (3 - 4) / 5"),
+            r#"["1", "+", "2", "*", ["(", "3", "-", "4", ")"], "/", "5"]"#
+        );
+        assert!(expr.parse_string("(1 + 2").is_err());
+    }
+
+    #[test]
+    fn alternatives_flatten_and_error_stop() {
+        let keyword = kw("if") | kw("while") | "for";
+        let first = keyword
+            .0
+            .clone()
+            .into_any()
+            .downcast::<MatchFirst>()
+            .ok()
+            .unwrap();
+        assert_eq!(first.elements().len(), 3);
+
+        let stmt = kw("if") - "(" + word(ALPHAS) + ")";
+        let statements = stmt | word(ALPHAS);
+        assert_eq!(parse(&statements, "if (x)"), r#"["if", "(", "x", ")"]"#);
+        assert_eq!(parse(&statements, "iffy"), r#"["iffy"]"#);
+        // Past the error stop the failure is fatal: no fallback to the word
+        let error = statements.parse_string("if x").unwrap_err();
+        assert!(error.fatal);
+        assert_eq!(error.loc, 3);
+    }
+
+    #[test]
+    fn repetition_and_structure() {
+        let pair = (word(ALPHAS).named("key") + lit("=").suppress() + word(ALPHANUMS)).group();
+        let pairs = pair.clone() + ("," + pair).zero_or_more().optional();
+        assert_eq!(
+            parse(&pairs, "a=1, b=2"),
+            r#"[["a", "1"], ",", ["b", "2"]]"#
+        );
+        // Names inside a group are the group's, not the sequence's
+        let results = pairs.parse_string("a=1, b=2").unwrap();
+        assert!(results.get_named("key").is_none());
+        let ParseResultItem::Group(second) = &results.items()[2] else {
+            panic!("expected a group");
+        };
+        assert!(matches!(
+            second.get_named("key"),
+            Some(NamedValue::One([ParseResultItem::Token(key)])) if &**key == "b"
+        ));
+        let digits = word(NUMS).times(3);
+        assert_eq!(parse(&digits, "1 2 3"), r#"["1", "2", "3"]"#);
+        assert!(digits.parse_string("1 2").is_err());
+        let version = (word(NUMS) + "." + word(NUMS)).combine();
+        assert_eq!(parse(&version, "3.14"), r#"["3.14"]"#);
+        assert!(version.parse_string("3 .14").is_err());
+        let ident = word2(ALPHAS, ALPHANUMS).repeat(1, Some(2));
+        assert_eq!(parse(&ident, "a1 b2 c3"), r#"["a1", "b2"]"#);
+        assert_eq!(parse(&caseless("SELECT"), "select"), r#"["SELECT"]"#);
+        assert_eq!(parse(&regex(r"\d+").unwrap(), "123"), r#"["123"]"#);
+    }
+}
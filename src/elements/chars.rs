@@ -1,151 +1,485 @@
-use crate::core::context::ParseContext;
+use crate::core::context::{default_whitespace, ParseContext};
 use crate::core::exceptions::ParseException;
-use crate::core::parser::{ParseResult, ParserElement};
-use crate::core::results::ParseResults;
+use crate::core::generate::{GenerateError, Generator};
+use crate::core::parser::{
+    name_from_error, window_match, ByteSet, Namer, ParseResult, ParserElement, ParserKind,
+};
+use crate::core::regex_cache;
+use crate::core::results::{OpaqueValue, ParseResultItem, ParseResults, TokenText};
+use crate::core::segments::Segments;
+use crate::core::spec::{SpecError, SpecNode, SpecValue, SpecWriter};
+use regex_syntax::hir::{Class, HirKind, Literal};
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
 use std::sync::Arc;
 
-/// 256-bit bitset for O(1) character lookup
+/// A character class spec that can't be parsed (see CharSet::from_class).
+#[derive(Debug, Clone)]
+pub struct CharClassError {
+    pub msg: String,
+}
+
+impl CharClassError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+}
+
+impl fmt::Display for CharClassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CharClassError: {}", self.msg)
+    }
+}
+
+impl std::error::Error for CharClassError {}
+
+/// Surrogate code points, which are not chars; ranges are kept clear of them.
+const SURROGATES: (u32, u32) = (0xD800, 0xDFFF);
+
+/// Character set: a 128-bit bitmap for ASCII, which byte-scanning paths test
+/// directly, and sorted, disjoint inclusive code point ranges for the rest.
 #[derive(Clone)]
 pub struct CharSet {
-    bits: [u64; 4], // 256 bits total
+    bits: [u64; 2],
+    non_ascii: Box<[(u32, u32)]>,
 }
 
 impl CharSet {
     pub fn from_chars(chars: &str) -> Self {
-        let mut bits = [0u64; 4];
-        for c in chars.chars() {
-            let c = c as usize;
-            if c < 256 {
-                bits[c / 64] |= 1u64 << (c % 64);
+        let mut ranges: Vec<(u32, u32)> = chars.chars().map(|c| (c as u32, c as u32)).collect();
+        ranges.sort_unstable();
+        Self::from_ranges(ranges)
+    }
+
+    /// Set from a regex-style character class body, without the brackets: single
+    /// characters, ranges (`a-zA-Z0-9_`), escapes (`\d`, `\w`), Unicode general
+    /// categories (`\p{L}`, `\p{Nd}`) and scripts, and a leading `^` for negation.
+    pub fn from_class(spec: &str) -> Result<Self, CharClassError> {
+        let invalid = |why: String| {
+            CharClassError::new(format!("invalid character class '{}': {}", spec, why))
+        };
+        let hir = regex_syntax::Parser::new()
+            .parse(&format!("[{}]", spec))
+            .map_err(|e| invalid(e.to_string()))?;
+        match hir.kind() {
+            HirKind::Class(Class::Unicode(class)) => Ok(Self::from_ranges(
+                class
+                    .ranges()
+                    .iter()
+                    .map(|r| (r.start() as u32, r.end() as u32)),
+            )),
+            // A class of one character comes back as that character
+            HirKind::Literal(Literal(bytes)) => match std::str::from_utf8(bytes) {
+                Ok(text) => Ok(Self::from_chars(text)),
+                Err(_) => Err(invalid("not a set of characters".into())),
+            },
+            _ => Err(invalid("not a single class".into())),
+        }
+    }
+
+    /// Set from inclusive code point ranges sorted by start.
+    fn from_ranges(ranges: impl IntoIterator<Item = (u32, u32)>) -> Self {
+        let mut bits = [0u64; 2];
+        let mut non_ascii: Vec<(u32, u32)> = Vec::new();
+        for (lo, hi) in ranges {
+            for c in lo..=hi.min(0x7F) {
+                bits[c as usize / 64] |= 1u64 << (c % 64);
+            }
+            let lo = lo.max(0x80);
+            // Split around the surrogates, so every member converts to a char
+            for (lo, hi) in [
+                (lo, hi.min(SURROGATES.0 - 1)),
+                (lo.max(SURROGATES.1 + 1), hi),
+            ] {
+                if lo > hi {
+                    continue;
+                }
+                match non_ascii.last_mut() {
+                    Some(last) if lo <= last.1 + 1 => last.1 = last.1.max(hi),
+                    _ => non_ascii.push((lo, hi)),
+                }
+            }
+        }
+        Self {
+            bits,
+            non_ascii: non_ascii.into(),
+        }
+    }
+
+    /// Members of `self` that are not in `other`.
+    pub fn without(&self, other: &CharSet) -> Self {
+        let mut bits = self.bits;
+        for (b, o) in bits.iter_mut().zip(other.bits) {
+            *b &= !o;
+        }
+        let mut non_ascii = Vec::new();
+        let mut removed = other.non_ascii.iter().peekable();
+        for &(lo, hi) in self.non_ascii.iter() {
+            let mut lo = lo;
+            while let Some(&&(r_lo, r_hi)) = removed.peek() {
+                if r_hi < lo {
+                    removed.next();
+                    continue;
+                }
+                if r_lo > hi {
+                    break;
+                }
+                if r_lo > lo {
+                    non_ascii.push((lo, r_lo - 1));
+                }
+                lo = r_hi.saturating_add(1);
+                if r_hi >= hi {
+                    break;
+                }
+                removed.next();
+            }
+            if lo <= hi {
+                non_ascii.push((lo, hi));
             }
         }
-        Self { bits }
+        Self {
+            bits,
+            non_ascii: non_ascii.into(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == [0, 0] && self.non_ascii.is_empty()
+    }
+
+    /// Whether every member is ASCII, so testing bytes with contains() is exact.
+    #[inline(always)]
+    pub fn is_ascii(&self) -> bool {
+        self.non_ascii.is_empty()
     }
 
+    /// Whether byte `b` is an ASCII member. Bytes of multi-byte characters never are.
     #[inline(always)]
-    pub fn contains(&self, c: u8) -> bool {
-        let c = c as usize;
-        (self.bits[c / 64] >> (c % 64)) & 1 != 0
+    pub fn contains(&self, b: u8) -> bool {
+        b < 128 && (self.bits[(b / 64) as usize] >> (b % 64)) & 1 != 0
+    }
+
+    /// Bytes the UTF-8 encoding of a member can start with.
+    pub fn first_bytes(&self) -> ByteSet {
+        let mut set: ByteSet = (0..128u8).filter(|&b| self.contains(b)).collect();
+        for &(lo, hi) in self.non_ascii.iter() {
+            let lead =
+                |c: u32| char::from_u32(c).map_or(0, |c| c.encode_utf8(&mut [0; 4]).as_bytes()[0]);
+            (lead(lo)..=lead(hi)).for_each(|b| set.insert(b));
+        }
+        set
+    }
+
+    /// Uniformly pick a member of the set, or None if it is empty.
+    pub fn sample(&self, gen: &mut Generator) -> Option<char> {
+        let ascii: u32 = self.bits.iter().map(|w| w.count_ones()).sum();
+        let non_ascii: usize = self
+            .non_ascii
+            .iter()
+            .map(|&(lo, hi)| (hi - lo) as usize + 1)
+            .sum();
+        let count = ascii as usize + non_ascii;
+        if count == 0 {
+            return None;
+        }
+        let mut k = gen.below(count);
+        if k < ascii as usize {
+            return (0..128u8)
+                .filter(|&b| self.contains(b))
+                .nth(k)
+                .map(char::from);
+        }
+        k -= ascii as usize;
+        for &(lo, hi) in self.non_ascii.iter() {
+            let len = (hi - lo) as usize + 1;
+            if k < len {
+                return char::from_u32(lo + k as u32);
+            }
+            k -= len;
+        }
+        None
     }
 
     #[inline(always)]
     pub fn contains_char(&self, c: char) -> bool {
+        if c.is_ascii() {
+            return self.contains(c as u8);
+        }
         let c = c as u32;
-        if c >= 256 {
-            return false;
+        self.non_ascii
+            .binary_search_by(|&(lo, hi)| match (hi < c, lo > c) {
+                (true, _) => Ordering::Less,
+                (_, true) => Ordering::Greater,
+                _ => Ordering::Equal,
+            })
+            .is_ok()
+    }
+}
+
+/// Expand a bracketed character class like `[a-zA-Z0-9_]` into its characters, in
+/// order, like pyparsing's `srange`. Supports ranges, `-` first or last as itself,
+/// and the escapes `\t`, `\n`, `\r`, `\f`, `\v`, `\\`, `\]`, `\-`, `\^`, `\xHH`,
+/// `\uHHHH` and `\UHHHHHHHH`. Negated classes (`[^...]`) can't be expanded.
+pub fn srange(spec: &str) -> Result<String, CharClassError> {
+    let invalid = |why: &str| CharClassError::new(format!("invalid srange '{}': {}", spec, why));
+    let body = spec
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or_else(|| invalid("expected a class in brackets, like [a-z]"))?;
+    if body.starts_with('^') {
+        return Err(invalid(
+            "negated classes are not supported; use CharClass or exclude_chars",
+        ));
+    }
+    let mut chars = body.chars().peekable();
+    let mut out = String::new();
+    while let Some(lo) = srange_char(&mut chars).map_err(|why| invalid(&why))? {
+        let mut ahead = chars.clone();
+        if ahead.next() != Some('-') || ahead.peek().is_none() {
+            out.push(lo);
+            continue;
         }
-        self.contains(c as u8)
+        chars.next();
+        let hi = srange_char(&mut chars)
+            .map_err(|why| invalid(&why))?
+            .ok_or_else(|| invalid("unfinished range"))?;
+        if hi < lo {
+            return Err(invalid(&format!("range {}-{} is out of order", lo, hi)));
+        }
+        out.extend(lo..=hi);
     }
+    Ok(out)
+}
+
+/// The next character of an srange body, with escapes resolved.
+fn srange_char(chars: &mut Peekable<Chars<'_>>) -> Result<Option<char>, String> {
+    let Some(c) = chars.next() else {
+        return Ok(None);
+    };
+    if c != '\\' {
+        return Ok(Some(c));
+    }
+    let hex = |chars: &mut Peekable<Chars<'_>>, n: usize| {
+        let digits: String = chars.take(n).collect();
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|_| digits.len() == n)
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("bad escape code '{}'", digits))
+    };
+    let escaped = match chars.next().ok_or("trailing backslash")? {
+        't' => '\t',
+        'n' => '\n',
+        'r' => '\r',
+        'f' => '\x0c',
+        'v' => '\x0b',
+        'x' => hex(chars, 2)?,
+        'u' => hex(chars, 4)?,
+        'U' => hex(chars, 8)?,
+        c => c,
+    };
+    Ok(Some(escaped))
 }
 
 /// Match a word made up of characters from specified set
+#[derive(Clone)]
 pub struct Word {
     init_chars: CharSet,
     body_chars: CharSet,
+    /// Removed from both sets, including a body set given later
+    exclude_chars: CharSet,
     min_len: usize,
     max_len: usize,
+    /// Fail when a neighbouring character is also a word character
+    as_keyword: bool,
     error_msg: Arc<str>,
+    /// The character arguments as given, for describe()
+    init_source: Arc<str>,
+    body_source: Option<Arc<str>>,
+    exclude_source: Option<Arc<str>>,
+    /// Whether the init and body sources are class specs (see CharSet::from_class)
+    init_is_class: bool,
+    body_is_class: bool,
 }
 
 impl Word {
+    /// # Panics
+    /// If `init_chars` is empty; see try_new.
     pub fn new(init_chars: &str) -> Self {
-        let charset = CharSet::from_chars(init_chars);
-        let name = format!("W:({}...)", &init_chars[..init_chars.len().min(8)]);
+        Self::try_new(init_chars).unwrap_or_else(|e| panic!("{}", e.msg))
+    }
+
+    pub fn try_new(init_chars: &str) -> Result<Self, CharClassError> {
+        Self::with_init_set(init_chars, CharSet::from_chars(init_chars))
+    }
+
+    /// Word whose characters are given as a class spec, e.g. `\p{L}` or `a-zA-Z_`.
+    pub fn from_class(spec: &str) -> Result<Self, CharClassError> {
+        let mut word = Self::with_init_set(spec, CharSet::from_class(spec)?)?;
+        word.init_is_class = true;
+        Ok(word)
+    }
+
+    fn with_init_set(init_chars: &str, charset: CharSet) -> Result<Self, CharClassError> {
+        if charset.is_empty() {
+            return Err(CharClassError::new(
+                "Word requires a non-empty character set",
+            ));
+        }
+        let name = format!("W:({}...)", init_chars.chars().take(8).collect::<String>());
         let error_msg: Arc<str> = format!("Expected {}", name).into();
 
-        Self {
+        Ok(Self {
             init_chars: charset.clone(),
             body_chars: charset,
+            exclude_chars: CharSet::from_chars(""),
             min_len: 1,
             max_len: 0, // 0 means unlimited
+            as_keyword: false,
             error_msg,
-        }
+            init_source: init_chars.into(),
+            body_source: None,
+            exclude_source: None,
+            init_is_class: false,
+            body_is_class: false,
+        })
     }
 
     pub fn with_body_chars(mut self, body: &str) -> Self {
-        self.body_chars = CharSet::from_chars(body);
+        self.body_chars = CharSet::from_chars(body).without(&self.exclude_chars);
+        self.body_source = Some(body.into());
+        self.body_is_class = false;
         self
     }
 
-    #[inline(always)]
-    pub fn init_chars_contains(&self, b: u8) -> bool {
-        self.init_chars.contains(b)
+    /// Body characters given as a class spec, like with_body_chars.
+    pub fn with_body_class(mut self, spec: &str) -> Result<Self, CharClassError> {
+        self.body_chars = CharSet::from_class(spec)?.without(&self.exclude_chars);
+        self.body_source = Some(spec.into());
+        self.body_is_class = true;
+        Ok(self)
     }
 
-    #[inline(always)]
-    pub fn body_chars_contains(&self, b: u8) -> bool {
-        self.body_chars.contains(b)
+    /// Remove `chars` from the init and body sets, like pyparsing's `exclude_chars`.
+    pub fn with_exclude_chars(mut self, chars: &str) -> Self {
+        self.exclude_chars = CharSet::from_chars(chars);
+        self.exclude_source = Some(chars.into());
+        self.init_chars = self.init_chars.without(&self.exclude_chars);
+        self.body_chars = self.body_chars.without(&self.exclude_chars);
+        self
     }
-}
 
-impl ParserElement for Word {
-    #[inline]
-    fn parse_impl<'a>(&self, _ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
-        let input = _ctx.input();
+    /// Require at least `min` characters (pyparsing's `min`; at least 1).
+    pub fn with_min(mut self, min: usize) -> Self {
+        self.min_len = min.max(1);
+        self
+    }
 
-        if loc >= input.len() {
-            return Err(ParseException::new(loc, self.error_msg.clone()));
-        }
+    /// Take at most `max` characters; a longer run matches only its first `max`.
+    /// 0 means unlimited.
+    pub fn with_max(mut self, max: usize) -> Self {
+        self.max_len = max;
+        self
+    }
 
-        // Check first character (ASCII fast path)
-        let first_byte = input.as_bytes()[loc];
-        if !self.init_chars.contains(first_byte) {
-            return Err(ParseException::new(loc, self.error_msg.clone()));
-        }
+    /// Match exactly `n` characters, like pyparsing's `exact`.
+    pub fn with_exact(self, n: usize) -> Self {
+        self.with_min(n).with_max(n)
+    }
 
-        // Find end of word using byte scan
-        let mut end = loc + 1;
-        let bytes = input.as_bytes();
+    /// Only match whole words: fail if the character before the start or after the
+    /// end is in the init or body set, like pyparsing's `as_keyword`.
+    pub fn with_as_keyword(mut self, as_keyword: bool) -> Self {
+        self.as_keyword = as_keyword;
+        self
+    }
 
-        while end < bytes.len() {
-            let b = bytes[end];
-            // Fast ASCII check
-            if b < 128 {
-                if !self.body_chars.contains(b) {
-                    break;
-                }
-                end += 1;
-            } else {
-                // UTF-8 handling
-                let c = input[end..].chars().next().unwrap();
-                if !self.body_chars.contains_char(c) {
-                    break;
-                }
-                end += c.len_utf8();
-            }
+    /// Whether the word takes the whole run of body characters with no length limits
+    /// or keyword boundaries, and its characters are all ASCII, which is what the
+    /// byte-scanning fast paths assume.
+    #[inline(always)]
+    pub fn is_plain(&self) -> bool {
+        self.min_len <= 1 && self.max_len == 0 && !self.as_keyword && self.is_ascii()
+    }
+
+    /// Whether the word can start with a default whitespace character. Scans skip
+    /// whitespace before matching, which the byte-scanning fast paths don't.
+    pub fn starts_in_whitespace(&self) -> bool {
+        let whitespace = default_whitespace();
+        (0..128).any(|b| whitespace.contains(b) && self.init_chars.contains(b))
+    }
 
-            if self.max_len > 0 && end - loc >= self.max_len {
+    /// Whether both character sets are ASCII-only, so bytes can be tested directly.
+    #[inline(always)]
+    fn is_ascii(&self) -> bool {
+        self.init_chars.is_ascii() && self.body_chars.is_ascii()
+    }
+
+    /// End of the word at `loc`, testing characters rather than bytes.
+    fn match_chars(&self, input: &str, loc: usize) -> Option<usize> {
+        let mut chars = input.get(loc..)?.char_indices();
+        let (_, first) = chars.next()?;
+        if !self.init_chars.contains_char(first) {
+            return None;
+        }
+        let mut end = loc + first.len_utf8();
+        let mut count = 1;
+        for (i, c) in chars {
+            if (self.max_len > 0 && count >= self.max_len) || !self.body_chars.contains_char(c) {
                 break;
             }
+            end = loc + i + c.len_utf8();
+            count += 1;
         }
-
-        // Check minimum length (byte count == char count for ASCII)
-        if self.min_len > 0 {
-            let byte_len = end - loc;
-            if byte_len < self.min_len {
-                return Err(ParseException::new(loc, self.error_msg.clone()));
-            }
-            // Only do expensive char count for non-ASCII content
-            if byte_len >= self.min_len {
-                // For ASCII-only content, byte_len == char_count, already checked
-                // For multi-byte chars, we need the actual count
-                let all_ascii = bytes[loc..end].iter().all(|&b| b < 128);
-                if !all_ascii {
-                    let char_count = input[loc..end].chars().count();
-                    if char_count < self.min_len {
-                        return Err(ParseException::new(loc, self.error_msg.clone()));
-                    }
-                }
-            }
+        if count < self.min_len || (self.as_keyword && !self.at_word_boundary(input, loc, end)) {
+            return None;
         }
+        Some(end)
+    }
+
+    #[inline]
+    fn is_word_char(&self, c: char) -> bool {
+        self.init_chars.contains_char(c) || self.body_chars.contains_char(c)
+    }
+
+    /// Keyword boundary check for a match of `input[loc..end]`.
+    #[inline]
+    fn at_word_boundary(&self, input: &str, loc: usize, end: usize) -> bool {
+        let before = input.get(..loc).and_then(|s| s.chars().next_back());
+        let after = input.get(end..).and_then(|s| s.chars().next());
+        !before.is_some_and(|c| self.is_word_char(c))
+            && !after.is_some_and(|c| self.is_word_char(c))
+    }
+
+    #[inline(always)]
+    pub fn init_chars_contains(&self, b: u8) -> bool {
+        self.init_chars.contains(b)
+    }
+
+    #[inline(always)]
+    pub fn body_chars_contains(&self, b: u8) -> bool {
+        self.body_chars.contains(b)
+    }
+}
 
-        let matched = &input[loc..end];
-        Ok((end, ParseResults::from_single(matched)))
+impl ParserElement for Word {
+    #[inline]
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let input = ctx.input();
+        match self.try_match_at(input, loc) {
+            Some(end) => Ok((end, ParseResults::from_single(&input[loc..end]))),
+            None => Err(ParseException::new(loc, self.error_msg.clone())),
+        }
     }
 
     /// Zero-alloc match — just returns end position, no ParseResults
     #[inline]
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        if !self.is_ascii() {
+            return self.match_chars(input, loc);
+        }
         let bytes = input.as_bytes();
         if loc >= bytes.len() || !self.init_chars.contains(bytes[loc]) {
             return None;
@@ -154,22 +488,110 @@ impl ParserElement for Word {
         while end < bytes.len() && self.body_chars.contains(bytes[end]) {
             end += 1;
         }
+        if self.is_plain() {
+            return Some(end);
+        }
+        // Length limits count characters, not bytes
+        let run = &input[loc..end];
+        if self.max_len > 0 {
+            if let Some((cut, _)) = run.char_indices().nth(self.max_len) {
+                end = loc + cut;
+            }
+        }
+        if input[loc..end].chars().count() < self.min_len
+            || (self.as_keyword && !self.at_word_boundary(input, loc, end))
+        {
+            return None;
+        }
+        Some(end)
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if !self.is_ascii() {
+            return window_match(self, input, loc);
+        }
+        match input.byte_at(loc) {
+            Some(b) if self.init_chars.contains(b) => {}
+            _ => return None,
+        }
+        let mut end = input.scan_while(loc + 1, |b| self.body_chars.contains(b));
         if self.max_len > 0 && end - loc > self.max_len {
             end = loc + self.max_len;
         }
-        // Check min_len — must match at least this many characters
         if end - loc < self.min_len {
             return None;
         }
+        if self.as_keyword {
+            let is_word = |b: u8| self.init_chars.contains(b) || self.body_chars.contains(b);
+            if (loc > 0 && input.byte_at(loc - 1).is_some_and(is_word))
+                || input.byte_at(end).is_some_and(is_word)
+            {
+                return None;
+            }
+        }
         Some(end)
     }
+
+    fn first_chars(&self) -> Option<ByteSet> {
+        Some(self.init_chars.first_bytes())
+    }
+
+    /// Length is drawn from `min_len..=max_len` (up to `min_len + 7` when unbounded).
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        let min = self.min_len.max(1);
+        let max = if self.max_len > 0 {
+            self.max_len.max(min)
+        } else {
+            min + 7
+        };
+        let len = gen.range(min, max);
+        let empty = || GenerateError::new("cannot generate input for a Word with an empty charset");
+        let first = self.init_chars.sample(gen).ok_or_else(empty)?;
+        gen.push(first);
+        for _ in 1..len {
+            let c = self.body_chars.sample(gen).ok_or_else(empty)?;
+            gen.push(c);
+        }
+        Ok(())
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: init chars, body chars, min, max, as_keyword, exclude chars, and
+    /// whether the init and body chars are class specs.
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "Word",
+            vec![
+                SpecValue::Str(self.init_source.clone()),
+                self.body_source
+                    .clone()
+                    .map_or(SpecValue::None, SpecValue::Str),
+                self.min_len.into(),
+                self.max_len.into(),
+                self.as_keyword.into(),
+                self.exclude_source
+                    .clone()
+                    .map_or(SpecValue::None, SpecValue::Str),
+                self.init_is_class.into(),
+                self.body_is_class.into(),
+            ],
+        ))
+    }
 }
 
 /// Fast-path category for common regex patterns
+#[derive(Clone)]
 enum FastPath {
     /// \s+ — one or more whitespace
     WhitespacePlus,
-    /// Single-char class like [+\-*/] — stored as 256-bit lookup
+    /// Single-char ASCII class like [+\-*/] — stored as a bitmap lookup
     SingleCharClass(CharSet),
     /// No fast path, use regex engine
     None,
@@ -198,20 +620,72 @@ fn detect_fast_path(pattern: &str) -> FastPath {
                 chars.push(c);
             }
         }
-        if !chars.is_empty() {
+        // Bytes are tested directly, so only ASCII classes qualify
+        if !chars.is_empty() && chars.is_ascii() {
             return FastPath::SingleCharClass(CharSet::from_chars(&chars));
         }
     }
     FastPath::None
 }
 
+/// What a RegexMatch returns for a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegexOutput {
+    /// The match as one token, or one token per participating capture group
+    Tokens,
+    /// The groups' tokens as one Group (pyparsing's `as_group_list`)
+    GroupList,
+    /// One opaque `RegexCaptures` item (pyparsing's `as_match`)
+    Match,
+}
+
+/// The capture groups of one Regex match, kept for `as_match` results.
+#[derive(Debug)]
+pub struct RegexCaptures {
+    /// Text of the whole match
+    pub text: Box<str>,
+    /// Byte offset of the match in the input
+    pub offset: usize,
+    /// Span of each group within `text` (group 0 is the whole match); None when a
+    /// group didn't participate
+    pub spans: Vec<Option<(usize, usize)>>,
+    /// Group names, aligned with `spans`
+    pub names: Arc<[Option<Arc<str>>]>,
+}
+
+impl RegexCaptures {
+    /// Text of group `i`, or None if it didn't participate.
+    pub fn group(&self, i: usize) -> Option<&str> {
+        let (start, end) = (*self.spans.get(i)?)?;
+        Some(&self.text[start..end])
+    }
+
+    /// Index of the group called `name`.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n.as_deref() == Some(name))
+    }
+
+    /// Input span of group `i`, or None if it didn't participate.
+    pub fn span(&self, i: usize) -> Option<(usize, usize)> {
+        let (start, end) = (*self.spans.get(i)?)?;
+        Some((self.offset + start, self.offset + end))
+    }
+}
+
 /// Match using a regular expression
+#[derive(Clone)]
 pub struct RegexMatch {
+    /// The pattern as given
+    source: Arc<str>,
     pattern: regex::Regex,
     /// Unanchored version for search_string / find_iter operations
     search_pattern: regex::Regex,
     error_msg: Arc<str>,
     fast_path: FastPath,
+    /// Names of the capture groups after the whole match (None when unnamed); the
+    /// groups become the tokens when there are any
+    group_names: Arc<[Option<Arc<str>>]>,
+    output: RegexOutput,
 }
 
 impl RegexMatch {
@@ -228,32 +702,85 @@ impl RegexMatch {
         let fast_path = detect_fast_path(pattern);
 
         // Try unicode(false) first for ASCII-optimized matching (much faster for \w, \d, etc.)
-        // Fall back to unicode(true) if the pattern requires Unicode features
+        // Fall back to unicode(true) if the pattern requires Unicode features. Both
+        // come from the process-wide cache, so rebuilding a pattern is cheap.
         let (compiled, search_compiled) = match (
-            regex::RegexBuilder::new(&anchored).unicode(false).build(),
-            regex::RegexBuilder::new(&unanchored).unicode(false).build(),
+            regex_cache::regex(&anchored, false),
+            regex_cache::regex(&unanchored, false),
         ) {
             (Ok(a), Ok(s)) => (a, s),
             _ => (
-                regex::Regex::new(&anchored)?,
-                regex::Regex::new(&unanchored)?,
+                regex_cache::regex(&anchored, true)?,
+                regex_cache::regex(&unanchored, true)?,
             ),
         };
 
+        let group_names = compiled
+            .capture_names()
+            .skip(1)
+            .map(|name| name.map(Arc::from))
+            .collect();
         Ok(Self {
+            source: pattern.into(),
             pattern: compiled,
             search_pattern: search_compiled,
             error_msg,
             fast_path,
+            group_names,
+            output: RegexOutput::Tokens,
         })
     }
 
+    /// Return matches as `output` instead of as tokens.
+    pub fn with_output(mut self, output: RegexOutput) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn output(&self) -> RegexOutput {
+        self.output
+    }
+
+    /// Whether a match's only token is its matched text, so fast paths can slice it.
+    #[inline]
+    pub fn match_is_token(&self) -> bool {
+        self.output == RegexOutput::Tokens && !self.has_groups()
+    }
+
+    /// The match's groups, in order, with their names; group 0 is the whole match.
+    fn captures(&self, caps: &regex::Captures<'_>, offset: usize) -> RegexCaptures {
+        let whole = caps.get_match();
+        let names = std::iter::once(None)
+            .chain(self.group_names.iter().cloned())
+            .collect();
+        RegexCaptures {
+            text: whole.as_str().into(),
+            offset,
+            spans: caps
+                .iter()
+                .map(|g| g.map(|g| (g.start(), g.end())))
+                .collect(),
+            names,
+        }
+    }
+
+    /// Whether the pattern has capture groups, whose text is then the tokens.
+    #[inline]
+    pub fn has_groups(&self) -> bool {
+        !self.group_names.is_empty()
+    }
+
     /// Direct regex match without ParseContext overhead — returns matched substring
     #[inline]
     pub fn try_match<'a>(&self, input: &'a str) -> Option<&'a str> {
         self.pattern.find(input).map(|m| m.as_str())
     }
 
+    /// The unanchored regex, with the pattern's capture groups.
+    pub fn search_regex(&self) -> &regex::Regex {
+        &self.search_pattern
+    }
+
     /// Iterator over all non-overlapping matches in a haystack
     #[inline]
     pub fn find_iter<'r, 'h>(&'r self, haystack: &'h str) -> regex::Matches<'r, 'h> {
@@ -266,11 +793,44 @@ impl ParserElement for RegexMatch {
     fn parse_impl<'a>(&self, _ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
         let input = &_ctx.input()[loc..];
 
-        if let Some(m) = self.pattern.find(input) {
-            let matched = m.as_str();
-            Ok((loc + matched.len(), ParseResults::from_single(matched)))
-        } else {
-            Err(ParseException::new(loc, self.error_msg.clone()))
+        if self.match_is_token() {
+            return match self.pattern.find(input) {
+                Some(m) => Ok((loc + m.end(), ParseResults::from_single(m.as_str()))),
+                None => Err(ParseException::new(loc, self.error_msg.clone())),
+            };
+        }
+        let Some(caps) = self.pattern.captures(input) else {
+            return Err(ParseException::new(loc, self.error_msg.clone()));
+        };
+        let end = loc + caps.get_match().end();
+        if self.output == RegexOutput::Match {
+            let value = OpaqueValue(Arc::new(self.captures(&caps, loc)));
+            return Ok((end, ParseResults::from_item(ParseResultItem::Object(value))));
+        }
+        // One token per participating group; named groups are also results names
+        let mut results = ParseResults::new();
+        for (i, name) in self.group_names.iter().enumerate() {
+            let Some(group) = caps.get(i + 1) else {
+                continue;
+            };
+            let token = ParseResultItem::Token(TokenText::Borrowed(group.as_str()));
+            if let Some(name) = name {
+                results.add_named(name.clone(), Box::new([token.clone()]));
+            }
+            results.extend(ParseResults::from_item(token));
+        }
+        if self.output == RegexOutput::GroupList {
+            results = ParseResults::from_group(results);
+        }
+        Ok((end, results))
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        // Groups give zero or more tokens, not the match span
+        match self.output {
+            RegexOutput::GroupList => ParserKind::Group,
+            _ if self.match_is_token() => ParserKind::Normal,
+            _ => ParserKind::Complex,
         }
     }
 
@@ -295,13 +855,37 @@ impl ParserElement for RegexMatch {
                 }
                 Some(loc + 1)
             }
-            FastPath::None => self.pattern.find(&input[loc..]).map(|m| loc + m.end()),
+            // No match starts inside a character
+            FastPath::None => self.pattern.find(input.get(loc..)?).map(|m| loc + m.end()),
         }
     }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        self.error_msg
+            .strip_prefix("Expected match for ")
+            .unwrap_or(&self.error_msg)
+            .to_string()
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "RegexMatch",
+            vec![
+                SpecValue::Str(self.source.clone()),
+                (self.output == RegexOutput::GroupList).into(),
+                (self.output == RegexOutput::Match).into(),
+            ],
+        ))
+    }
 }
 
 /// QuotedString - matches text enclosed in quote characters.
 /// Supports escape characters and multiline strings.
+#[derive(Clone)]
 pub struct QuotedString {
     quote_char: u8,
     esc_char: Option<u8>,
@@ -373,7 +957,7 @@ impl ParserElement for QuotedString {
                             i += 1;
                         }
                     }
-                    Ok((end, ParseResults::from_single(&unescaped)))
+                    Ok((end, ParseResults::from_token(unescaped)))
                 } else {
                     Ok((end, ParseResults::from_single(&input[cs..ce])))
                 }
@@ -389,4 +973,26 @@ impl ParserElement for QuotedString {
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
         self.find_end(input.as_bytes(), loc).map(|(end, _, _)| end)
     }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: quote char, escape char, multiline, unquote.
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let char_str = |b: u8| SpecValue::Str(char::from(b).to_string().into());
+        Ok(SpecNode::new(
+            "QuotedString",
+            vec![
+                char_str(self.quote_char),
+                self.esc_char.map_or(SpecValue::None, char_str),
+                self.multiline.into(),
+                self.unquote.into(),
+            ],
+        ))
+    }
 }
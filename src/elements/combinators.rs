@@ -1,57 +1,125 @@
-use crate::core::context::{skip_ws, ParseContext};
-use crate::core::exceptions::ParseException;
-use crate::core::parser::{ParseResult, ParserElement, ParserKind};
-use crate::core::results::ParseResults;
-use std::sync::Arc;
+use crate::core::context::{skip_ws, skip_ws_with, ParseContext};
+use crate::core::exceptions::{FurthestFailure, ParseException};
+use crate::core::generate::{GenerateError, Generator};
+use crate::core::parser::{
+    has_parse_actions, has_results_names, window_match, ByteSet, Namer, ParseResult, ParserElement,
+    ParserKind,
+};
+use crate::core::profile;
+use crate::core::results::{NamedValue, ParseResultItem, ParseResults};
+use crate::core::scanner::LiteralSet;
+use crate::core::segments::Segments;
+use crate::core::spec::{SpecError, SpecNode, SpecWriter};
+use crate::elements::literals::Literal;
+use smallvec::SmallVec;
+use std::sync::{Arc, OnceLock};
 
 /// Sequence combinator - all must match in order (And)
+#[derive(Clone)]
 pub struct And {
     elements: Vec<Arc<dyn ParserElement>>,
+    /// Record element spans in the context so back-references can see them
+    has_backref: bool,
+    /// Some element registers a results name, so token-only fast paths lose information
+    has_results_names: bool,
+    /// Some element runs parse actions, so its results must come from parse_impl
+    has_parse_actions: bool,
+    /// Elements from this index on fail fatally (pyparsing's `-`)
+    error_stop: Option<usize>,
 }
 
 impl And {
     pub fn new(elements: Vec<Arc<dyn ParserElement>>) -> Self {
-        Self { elements }
+        let has_backref = elements.iter().any(|e| e.has_backref());
+        let has_results_names = elements.iter().any(|e| has_results_names(e.as_ref()));
+        let has_parse_actions = elements.iter().any(|e| has_parse_actions(e.as_ref()));
+        Self {
+            elements,
+            has_backref,
+            has_results_names,
+            has_parse_actions,
+            error_stop: None,
+        }
+    }
+
+    /// Make failures of the elements from `index` on fatal, so enclosing
+    /// alternatives don't backtrack past them. Match-only paths still treat
+    /// them as plain mismatches.
+    pub fn with_error_stop(mut self, index: Option<usize>) -> Self {
+        self.error_stop = index;
+        self
+    }
+
+    pub fn error_stop(&self) -> Option<usize> {
+        self.error_stop
     }
 
     pub fn elements(&self) -> &[Arc<dyn ParserElement>] {
         &self.elements
     }
+
+    pub fn has_results_names(&self) -> bool {
+        self.has_results_names
+    }
+
+    pub fn has_parse_actions(&self) -> bool {
+        self.has_parse_actions
+    }
 }
 
 impl ParserElement for And {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, mut loc: usize) -> ParseResult<'a> {
         let mut results = ParseResults::new();
-        let input = ctx.input();
+        let log_len = ctx.match_log_len();
+        // Back-references may refer to any element matched so far, however nested.
+        // Whether this sequence turned recording on, to turn it off when done
+        let started_recording = self.has_backref && !ctx.record_matches(true);
 
-        for elem in self.elements.iter() {
+        for (i, elem) in self.elements.iter().enumerate() {
             // Skip whitespace before each element (like pyparsing's preParse),
             // unless ctx.skip_whitespace is false (e.g., inside Combine)
-            if ctx.skip_whitespace && elem.skip_whitespace_before() {
-                loc = skip_ws(input, loc);
-            }
-            match elem.parse_impl(ctx, loc) {
+            loc = ctx.pre_skip(loc, elem.skip_whitespace_before());
+            match ctx.parse(elem, loc) {
                 Ok((new_loc, res)) => {
                     results.extend(res);
                     loc = new_loc;
                 }
-                Err(e) => return Err(e),
+                Err(e) => {
+                    if started_recording {
+                        ctx.record_matches(false);
+                    }
+                    if self.has_backref {
+                        ctx.truncate_match_log(log_len);
+                    }
+                    if self.error_stop.is_some_and(|stop| i >= stop) {
+                        return Err(e.into_fatal());
+                    }
+                    return Err(e);
+                }
             }
         }
 
+        if started_recording {
+            ctx.record_matches(false);
+        }
         Ok((loc, results))
     }
 
     /// Zero-alloc match — chains try_match_at through all elements
     #[inline]
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        // Back-references need recorded spans, which only parse_impl provides
+        if self.has_backref {
+            let mut ctx = ParseContext::new(input);
+            return self.parse_impl(&mut ctx, loc).ok().map(|(end, _)| end);
+        }
         let mut pos = loc;
         for elem in self.elements.iter() {
             // Skip whitespace before each element
             if elem.skip_whitespace_before() {
                 pos = skip_ws(input, pos);
             }
-            pos = elem.try_match_at(input, pos)?;
+            pos = profile::try_match(elem, input, pos)?;
         }
         Some(pos)
     }
@@ -59,42 +127,228 @@ impl ParserElement for And {
     fn parser_kind(&self) -> ParserKind {
         ParserKind::Complex
     }
+
+    fn has_backref(&self) -> bool {
+        self.has_backref
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        self.elements.iter().for_each(visit);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if self.has_backref {
+            return window_match(self, input, loc);
+        }
+        let mut pos = loc;
+        for elem in self.elements.iter() {
+            if elem.skip_whitespace_before() {
+                pos = input.skip_ws(pos);
+            }
+            pos = elem.try_match_segments(input, pos)?;
+        }
+        Some(pos)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        for elem in self.elements.iter() {
+            if elem.skip_whitespace_before() {
+                gen.separate();
+            }
+            gen.descend(elem.as_ref())?;
+        }
+        Ok(())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.sequence(&self.elements, " ")
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: elements, error_stop.
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let params = vec![spec.children(&self.elements)?, self.error_stop.into()];
+        Ok(SpecNode::new("And", params))
+    }
+}
+
+/// Alternatives that are all (non-empty) literals, at least this many, are matched
+/// with one automaton instead of one comparison each (e.g. a large one_of()).
+const LITERAL_SET_MIN: usize = 16;
+
+/// Alternatives, at least this many and at least half with known first bytes,
+/// are only tried when the next byte can start them.
+const DISPATCH_MIN: usize = 8;
+
+/// The alternatives of a MatchFirst worth trying at each next byte: those that can
+/// start with it, and those whose first bytes are unknown, in their order. Row 256
+/// is for the end of the input, where only the unknown ones can match.
+#[derive(Clone)]
+struct FirstByteDispatch {
+    /// Row `r` is `alternatives[starts[r]..starts[r + 1]]`
+    starts: Box<[u32; 258]>,
+    alternatives: Box<[u32]>,
+}
+
+impl FirstByteDispatch {
+    fn new(elements: &[Arc<dyn ParserElement>]) -> Option<Self> {
+        let firsts: Vec<Option<ByteSet>> = elements.iter().map(|e| e.first_chars()).collect();
+        let known = firsts.iter().filter(|first| first.is_some()).count();
+        if elements.len() < DISPATCH_MIN || known * 2 < elements.len() {
+            return None;
+        }
+        let mut starts = Box::new([0u32; 258]);
+        let mut alternatives = Vec::new();
+        for row in 0..257 {
+            let fits = |first: &Option<ByteSet>| match first {
+                Some(set) => row < 256 && set.contains(row as u8),
+                None => true,
+            };
+            alternatives.extend((0..elements.len() as u32).filter(|&i| fits(&firsts[i as usize])));
+            starts[row + 1] = alternatives.len() as u32;
+        }
+        Some(Self {
+            starts,
+            alternatives: alternatives.into(),
+        })
+    }
+
+    /// Indexes of the alternatives that can match at `loc`.
+    #[inline]
+    fn candidates(&self, input: &str, loc: usize) -> &[u32] {
+        let row = input.as_bytes().get(loc).map_or(256, |&b| b as usize);
+        &self.alternatives[self.starts[row] as usize..self.starts[row + 1] as usize]
+    }
 }
 
 /// MatchFirst combinator - first match wins (| operator)
+#[derive(Clone)]
 pub struct MatchFirst {
     elements: Vec<Arc<dyn ParserElement>>,
+    /// Built on first use, since `a | b | c` makes a MatchFirst per operator
+    literal_set: OnceLock<Option<LiteralSet>>,
+    dispatch: OnceLock<Option<FirstByteDispatch>>,
 }
 
 impl MatchFirst {
     pub fn new(elements: Vec<Arc<dyn ParserElement>>) -> Self {
-        Self { elements }
+        Self {
+            elements,
+            literal_set: OnceLock::new(),
+            dispatch: OnceLock::new(),
+        }
     }
 
     pub fn elements(&self) -> &[Arc<dyn ParserElement>] {
         &self.elements
     }
+
+    /// The alternatives as one literal set, if they are all literals and numerous
+    /// enough to be worth it.
+    fn literal_set(&self) -> Option<&LiteralSet> {
+        self.literal_set
+            .get_or_init(|| {
+                if self.elements.len() < LITERAL_SET_MIN {
+                    return None;
+                }
+                let literals = self
+                    .elements
+                    .iter()
+                    .map(|elem| elem.clone().into_any().downcast::<Literal>().ok())
+                    .collect::<Option<Vec<_>>>()?;
+                let texts: Vec<&str> = literals.iter().map(|lit| lit.match_str()).collect();
+                if texts.iter().any(|text| text.is_empty()) {
+                    return None;
+                }
+                LiteralSet::new(&texts)
+            })
+            .as_ref()
+    }
+
+    /// The dispatch table by next byte, if enough alternatives have known first
+    /// bytes to be worth it. A literal set takes precedence.
+    fn dispatch(&self) -> Option<&FirstByteDispatch> {
+        self.dispatch
+            .get_or_init(|| FirstByteDispatch::new(&self.elements))
+            .as_ref()
+    }
+
+    /// parse_impl recording this choice point: takes the forced alternative if the
+    /// context has one, otherwise the first that matches.
+    fn parse_exploring<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let count = self.elements.len();
+        let mark = ctx.choice_mark();
+        let range = match ctx.forced_choice() {
+            Some(alt) if alt < count => alt..alt + 1,
+            Some(_) => 0..0,
+            None => 0..count,
+        };
+        let mut failure = FurthestFailure::default();
+        for alt in range {
+            ctx.record_choice(alt, count);
+            match ctx.parse(&self.elements[alt], loc) {
+                Ok(result) => return Ok(result),
+                Err(e) if e.fatal => return Err(e),
+                Err(e) => {
+                    ctx.rewind_choices(mark);
+                    failure.record(e);
+                }
+            }
+        }
+        Err(failure.into_error(loc))
+    }
 }
 
 impl ParserElement for MatchFirst {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
-        let mut last_error = None;
+        if ctx.is_exploring() {
+            return self.parse_exploring(ctx, loc);
+        }
+        // A miss falls through to the loop below, for the usual error
+        if let Some(set) = self.literal_set() {
+            if let Some((alt, _)) = set.first_at(ctx.input(), loc) {
+                return ctx.parse(&self.elements[alt], loc);
+            }
+        } else if let Some(dispatch) = self.dispatch() {
+            for &alt in dispatch.candidates(ctx.input(), loc) {
+                match ctx.parse(&self.elements[alt as usize], loc) {
+                    Ok(result) => return Ok(result),
+                    Err(e) if e.fatal => return Err(e),
+                    Err(_) => {}
+                }
+            }
+        }
+        // Report the alternative that got furthest, not just the last one tried
+        let mut failure = FurthestFailure::default();
 
         for elem in &self.elements {
-            match elem.parse_impl(ctx, loc) {
+            match ctx.parse(elem, loc) {
                 Ok(result) => return Ok(result),
-                Err(e) => last_error = Some(e),
+                Err(e) if e.fatal => return Err(e),
+                Err(e) => failure.record(e),
             }
         }
 
-        Err(last_error.unwrap_or_else(|| ParseException::new(loc, "No match found")))
+        Err(failure.into_error(loc))
     }
 
     /// Zero-alloc match — tries each element in order, returns first match
     #[inline]
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        if let Some(set) = self.literal_set() {
+            return set.first_at(input, loc).map(|(_, end)| end);
+        }
+        if let Some(dispatch) = self.dispatch() {
+            return dispatch
+                .candidates(input, loc)
+                .iter()
+                .find_map(|&alt| profile::try_match(&self.elements[alt as usize], input, loc));
+        }
         for elem in &self.elements {
-            if let Some(end) = elem.try_match_at(input, loc) {
+            if let Some(end) = profile::try_match(elem, input, loc) {
                 return Some(end);
             }
         }
@@ -104,4 +358,299 @@ impl ParserElement for MatchFirst {
     fn parser_kind(&self) -> ParserKind {
         ParserKind::Complex
     }
+
+    fn has_backref(&self) -> bool {
+        self.elements.iter().any(|e| e.has_backref())
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        self.elements.iter().for_each(visit);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.elements
+            .iter()
+            .find_map(|elem| elem.try_match_segments(input, loc))
+    }
+
+    fn first_chars(&self) -> Option<ByteSet> {
+        self.elements
+            .iter()
+            .try_fold(ByteSet::default(), |set, elem| {
+                Some(set.union(elem.first_chars()?))
+            })
+    }
+
+    /// Picks a random alternative, falling back to the others if it cannot generate
+    /// (e.g. a recursive branch that hits the depth limit).
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        if self.elements.is_empty() {
+            return Err(GenerateError::new(
+                "cannot generate input for an empty MatchFirst",
+            ));
+        }
+        let mark = gen.mark();
+        let first = gen.below(self.elements.len());
+        let mut last_error = None;
+        for i in 0..self.elements.len() {
+            let elem = &self.elements[(first + i) % self.elements.len()];
+            match gen.descend(elem.as_ref()) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    gen.rollback(mark);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.sequence(&self.elements, " | ")
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "MatchFirst",
+            vec![spec.children(&self.elements)?],
+        ))
+    }
+}
+
+/// Results name under which Each records the indices of the clauses it matched, in
+/// match order.
+pub const CLAUSES_SEEN: &str = "clauses_seen";
+
+/// Unordered combinator - matches its clauses in any order, each at most once,
+/// and requires the number of distinct clauses seen to fall within `min..=max`.
+/// Backs AtLeastN / AtMostN; with `min == max == len` it behaves like pyparsing's Each.
+/// The clauses matched are named CLAUSES_SEEN in its results.
+#[derive(Clone)]
+pub struct Each {
+    elements: Vec<Arc<dyn ParserElement>>,
+    min: usize,
+    max: usize,
+}
+
+impl Each {
+    pub fn with_bounds(elements: Vec<Arc<dyn ParserElement>>, min: usize, max: usize) -> Self {
+        Self { elements, min, max }
+    }
+
+    pub fn elements(&self) -> &[Arc<dyn ParserElement>] {
+        &self.elements
+    }
+
+    /// `(min, max)` distinct clauses required
+    pub fn bounds(&self) -> (usize, usize) {
+        (self.min, self.max)
+    }
+
+    /// Greedily match unseen clauses until none applies. Returns the end position
+    /// and the clause indices in the order they matched.
+    #[inline]
+    fn scan(
+        &self,
+        loc: usize,
+        skip: impl Fn(usize) -> usize,
+        mut try_clause: impl FnMut(&Arc<dyn ParserElement>, usize) -> Option<usize>,
+    ) -> (usize, SmallVec<[usize; 8]>) {
+        let mut seen: SmallVec<[usize; 8]> = SmallVec::new();
+        let mut pos = loc;
+        'outer: while seen.len() < self.elements.len() {
+            for (i, elem) in self.elements.iter().enumerate() {
+                if seen.contains(&i) {
+                    continue;
+                }
+                let start = if elem.skip_whitespace_before() {
+                    skip(pos)
+                } else {
+                    pos
+                };
+                if let Some(end) = try_clause(elem, start) {
+                    seen.push(i);
+                    pos = end;
+                    continue 'outer;
+                }
+            }
+            break;
+        }
+        (pos, seen)
+    }
+
+    #[inline]
+    fn count_ok(&self, seen: usize) -> bool {
+        seen >= self.min && seen <= self.max
+    }
+
+    /// Indices of the clauses matched at the start of `input`, in match order.
+    pub fn clauses_seen(&self, input: &str) -> Result<Vec<usize>, ParseException> {
+        let mut ctx = ParseContext::new(input);
+        let (_, results) = self.parse_impl(&mut ctx, skip_ws(input, 0))?;
+        let Some(NamedValue::One(seen)) = results.get_named(CLAUSES_SEEN) else {
+            return Ok(Vec::new());
+        };
+        Ok(seen
+            .iter()
+            .filter_map(|item| match item {
+                ParseResultItem::Int(i) => Some(*i as usize),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+impl ParserElement for Each {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let input = ctx.input();
+        let skip_whitespace = ctx.skip_whitespace;
+        let whitespace = ctx.whitespace;
+        let keep_at = ctx.keeps_whitespace_at(loc).then_some(loc);
+        let mut results = ParseResults::new();
+        let mut fatal = None;
+        let (end, seen) = self.scan(
+            loc,
+            |p| {
+                if skip_whitespace && keep_at != Some(p) {
+                    skip_ws_with(input, p, whitespace)
+                } else {
+                    p
+                }
+            },
+            |elem, p| {
+                if fatal.is_some() {
+                    return None;
+                }
+                let mark = ctx.choice_mark();
+                match ctx.parse(elem, p) {
+                    Ok((end, res)) => {
+                        results.extend(res);
+                        Some(end)
+                    }
+                    Err(e) => {
+                        if e.fatal {
+                            fatal = Some(e);
+                        }
+                        ctx.rewind_choices(mark);
+                        None
+                    }
+                }
+            },
+        );
+        if let Some(e) = fatal {
+            return Err(e);
+        }
+        if self.count_ok(seen.len()) {
+            if !seen.is_empty() {
+                let indices = seen.iter().map(|&i| ParseResultItem::Int(i as i64));
+                results.add_named(CLAUSES_SEEN, indices.collect());
+            }
+            return Ok((end, results));
+        }
+        if seen.len() > self.max {
+            return Err(ParseException::new(
+                loc,
+                format!(
+                    "Expected at most {} of {} clauses, found {}",
+                    self.max,
+                    self.elements.len(),
+                    seen.len()
+                ),
+            ));
+        }
+        // Describe each missing clause by the error it reports where parsing stopped
+        let missing: Vec<String> = self
+            .elements
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !seen.contains(i))
+            .map(|(_, elem)| {
+                let at = if skip_whitespace && elem.skip_whitespace_before() {
+                    skip_ws_with(input, end, whitespace)
+                } else {
+                    end
+                };
+                match ctx.parse(elem, at) {
+                    Err(e) => e.msg.to_string(),
+                    Ok(_) => "clause".to_string(),
+                }
+            })
+            .collect();
+        Err(ParseException::new(
+            loc,
+            format!(
+                "Expected at least {} of {} clauses, found {}; missing: {}",
+                self.min,
+                self.elements.len(),
+                seen.len(),
+                missing.join(", ")
+            ),
+        ))
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        let (end, seen) = self.scan(
+            loc,
+            |p| skip_ws(input, p),
+            |elem, p| profile::try_match(elem, input, p),
+        );
+        self.count_ok(seen.len()).then_some(end)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn has_backref(&self) -> bool {
+        self.elements.iter().any(|e| e.has_backref())
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        self.elements.iter().for_each(visit);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        let (end, seen) = self.scan(
+            loc,
+            |p| input.skip_ws(p),
+            |elem, p| elem.try_match_segments(input, p),
+        );
+        self.count_ok(seen.len()).then_some(end)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        let mut order: Vec<usize> = (0..self.elements.len()).collect();
+        for i in (1..order.len()).rev() {
+            order.swap(i, gen.below(i + 1));
+        }
+        let count = gen.range(self.min.min(order.len()), self.max.min(order.len()));
+        for &i in &order[..count] {
+            gen.separate();
+            gen.descend(self.elements[i].as_ref())?;
+        }
+        Ok(())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.sequence(&self.elements, " & ")
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: elements, min, max.
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let params = vec![
+            spec.children(&self.elements)?,
+            self.min.into(),
+            self.max.into(),
+        ];
+        Ok(SpecNode::new("Each", params))
+    }
 }
@@ -0,0 +1,126 @@
+use crate::core::context::ParseContext;
+use crate::core::exceptions::ParseException;
+use crate::core::parser::{name_from_error, Namer, ParseResult, ParserElement};
+use crate::core::results::ParseResults;
+use crate::core::spec::{SpecError, SpecNode, SpecWriter};
+use memchr::memmem;
+use std::sync::Arc;
+
+/// Comment - matches a block comment (`open ... close`, possibly spanning lines),
+/// a line comment (`prefix ...` up to the newline), or either, depending on style.
+/// A block comment ends at the first closing delimiter; nesting is not supported.
+#[derive(Clone)]
+pub struct Comment {
+    block: Option<(&'static str, &'static str)>,
+    line_prefix: Option<&'static str>,
+    error_msg: Arc<str>,
+    unterminated_msg: Arc<str>,
+}
+
+impl Comment {
+    fn new(
+        block: Option<(&'static str, &'static str)>,
+        line_prefix: Option<&'static str>,
+        name: &str,
+    ) -> Self {
+        let close = block.map(|(_, close)| close).unwrap_or_default();
+        Self {
+            block,
+            line_prefix,
+            error_msg: format!("Expected {}", name).into(),
+            unterminated_msg: format!("Unterminated comment: expected '{}'", close).into(),
+        }
+    }
+
+    /// `/* ... */`
+    pub fn c_style() -> Self {
+        Self::new(Some(("/*", "*/")), None, "C style comment")
+    }
+
+    /// `/* ... */` or `// ...`
+    pub fn cpp_style() -> Self {
+        Self::new(Some(("/*", "*/")), Some("//"), "C++ style comment")
+    }
+
+    /// `# ...`
+    pub fn python_style() -> Self {
+        Self::new(None, Some("#"), "Python style comment")
+    }
+
+    /// `<!-- ... -->`
+    pub fn html() -> Self {
+        Self::new(Some(("<!--", "-->")), None, "HTML comment")
+    }
+
+    /// Name of the constructor this comment came from: "c", "cpp", "python" or "html".
+    pub fn style(&self) -> &'static str {
+        match (self.block, self.line_prefix) {
+            (Some(_), Some(_)) => "cpp",
+            (None, _) => "python",
+            (Some(("/*", _)), None) => "c",
+            (Some(_), None) => "html",
+        }
+    }
+
+    /// The comment built by the constructor named `style`.
+    pub fn from_style(style: &str) -> Option<Self> {
+        match style {
+            "c" => Some(Self::c_style()),
+            "cpp" => Some(Self::cpp_style()),
+            "python" => Some(Self::python_style()),
+            "html" => Some(Self::html()),
+            _ => None,
+        }
+    }
+
+    /// End of the comment at `loc`: `Ok(None)` if no comment starts there,
+    /// `Err(())` if a block comment starts but is never closed.
+    #[inline]
+    fn match_end(&self, input: &str, loc: usize) -> Result<Option<usize>, ()> {
+        let rest = input.as_bytes().get(loc..).unwrap_or_default();
+        if let Some((open, close)) = self.block {
+            if rest.starts_with(open.as_bytes()) {
+                let body = &rest[open.len()..];
+                return match memmem::find(body, close.as_bytes()) {
+                    Some(p) => Ok(Some(loc + open.len() + p + close.len())),
+                    None => Err(()),
+                };
+            }
+        }
+        if let Some(prefix) = self.line_prefix {
+            if rest.starts_with(prefix.as_bytes()) {
+                let end = memchr::memchr(b'\n', rest).unwrap_or(rest.len());
+                return Ok(Some(loc + end));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl ParserElement for Comment {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let input = ctx.input();
+        match self.match_end(input, loc) {
+            Ok(Some(end)) => Ok((end, ParseResults::from_single(&input[loc..end]))),
+            Ok(None) => Err(ParseException::new(loc, self.error_msg.clone())),
+            Err(()) => Err(ParseException::new(loc, self.unterminated_msg.clone())),
+        }
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        self.match_end(input, loc).ok().flatten()
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("Comment", vec![self.style().into()]))
+    }
+}
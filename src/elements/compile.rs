@@ -0,0 +1,370 @@
+use crate::core::context::{skip_ws, ParseContext};
+use crate::core::parser::{ParseResult, ParserElement};
+use crate::core::results::{ParseResultItem, ParseResults};
+use crate::elements::chars::{RegexMatch, Word};
+use crate::elements::combinators::{And, MatchFirst};
+use crate::elements::literals::{Keyword, Literal};
+use crate::elements::repetition::{Optional, ZeroOrMore};
+use crate::elements::structure::{Group, Suppress};
+use std::fmt;
+use std::sync::Arc;
+
+/// An element tree that can't be lowered to a program.
+#[derive(Debug, Clone)]
+pub struct CompileError {
+    pub msg: String,
+}
+
+impl CompileError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CompileError: {}", self.msg)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Element matched by a single instruction, pushing its span as a token.
+enum Leaf {
+    Literal(Arc<Literal>),
+    Keyword(Arc<Keyword>),
+    Word(Arc<Word>),
+    Regex(Arc<RegexMatch>),
+}
+
+impl Leaf {
+    #[inline]
+    fn match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        match self {
+            Leaf::Literal(lit) => lit.try_match_at(input, loc),
+            Leaf::Keyword(kw) => kw.try_match_at(input, loc),
+            Leaf::Word(word) => word.try_match_at(input, loc),
+            Leaf::Regex(regex) => regex.try_match_at(input, loc),
+        }
+    }
+
+    /// Token of every match, when it isn't the text matched.
+    fn fixed_token(&self) -> Option<&Arc<str>> {
+        match self {
+            Leaf::Keyword(kw) => kw.caseless_token(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Wrap {
+    Group,
+    Suppress,
+}
+
+enum Inst {
+    /// Skip whitespace, as a sequence does before each element
+    SkipWs,
+    Leaf(Leaf),
+    /// Save the position; a later failure resumes at the target from there
+    Choice(usize),
+    /// Drop the innermost saved position and jump
+    Commit(usize),
+    /// End of a repetition: fail unless it got past the whitespace before it,
+    /// otherwise save the new position and jump back to the repetition's start
+    Repeat(usize),
+    Open(Wrap),
+    Close,
+    Fail,
+}
+
+/// Position to resume at when the instructions after a Choice fail.
+struct Frame {
+    resume: usize,
+    loc: usize,
+    captures: usize,
+}
+
+enum Capture {
+    Span(usize, usize),
+    Text(Arc<str>),
+    Open(Wrap),
+    Close,
+}
+
+/// A grammar lowered to a flat program for a backtracking matcher, so matching
+/// doesn't recurse through the element tree. Covers And, MatchFirst, Optional,
+/// ZeroOrMore, Literal, Word, Keyword, Regex (whose match is its token), Suppress
+/// and Group; results are the same as the source grammar's parse_impl.
+pub struct CompiledGrammar {
+    source: Arc<dyn ParserElement>,
+    program: Vec<Inst>,
+}
+
+impl CompiledGrammar {
+    pub fn compile(source: &Arc<dyn ParserElement>) -> Result<Self, CompileError> {
+        let mut program = Vec::new();
+        lower(source, &mut program)?;
+        Ok(Self {
+            source: source.clone(),
+            program,
+        })
+    }
+
+    /// The grammar this was compiled from.
+    pub fn source(&self) -> &Arc<dyn ParserElement> {
+        &self.source
+    }
+
+    /// Number of instructions in the program.
+    pub fn instruction_count(&self) -> usize {
+        self.program.len()
+    }
+
+    /// Match at `loc`, returning the end and tokens. None on a mismatch or when a
+    /// checkpoint in `ctx` aborts.
+    pub fn match_at<'a>(
+        &self,
+        ctx: &mut ParseContext<'a>,
+        mut loc: usize,
+    ) -> Option<(usize, ParseResults<'a>)> {
+        let input = ctx.input();
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut captures: Vec<Capture> = Vec::new();
+        let mut pc = 0;
+        while let Some(inst) = self.program.get(pc) {
+            let matched = match inst {
+                Inst::SkipWs => {
+                    loc = ctx.pre_skip(loc, true);
+                    true
+                }
+                Inst::Leaf(leaf) => match leaf.match_at(input, loc) {
+                    Some(end) => {
+                        captures.push(match leaf.fixed_token() {
+                            Some(text) => Capture::Text(text.clone()),
+                            None => Capture::Span(loc, end),
+                        });
+                        loc = end;
+                        true
+                    }
+                    None => false,
+                },
+                Inst::Choice(resume) => {
+                    frames.push(Frame {
+                        resume: *resume,
+                        loc,
+                        captures: captures.len(),
+                    });
+                    true
+                }
+                Inst::Commit(target) => {
+                    frames.pop();
+                    pc = *target;
+                    continue;
+                }
+                Inst::Repeat(start) => {
+                    let frame = frames.last_mut().expect("repetition has a saved position");
+                    // A repetition that matched nothing past the whitespace ends the loop
+                    if loc == ctx.pre_skip(frame.loc, true) {
+                        false
+                    } else {
+                        frame.loc = loc;
+                        frame.captures = captures.len();
+                        ctx.checkpoint(loc).ok()?;
+                        pc = *start;
+                        continue;
+                    }
+                }
+                Inst::Open(wrap) => {
+                    captures.push(Capture::Open(*wrap));
+                    true
+                }
+                Inst::Close => {
+                    captures.push(Capture::Close);
+                    true
+                }
+                Inst::Fail => false,
+            };
+            if matched {
+                pc += 1;
+                continue;
+            }
+            let frame = frames.pop()?;
+            loc = frame.loc;
+            captures.truncate(frame.captures);
+            pc = frame.resume;
+        }
+        Some((loc, build_results(input, &captures)))
+    }
+
+    /// Parse at `loc` like the source grammar's parse_impl. Mismatches are re-parsed
+    /// with the source grammar, so errors are the same too.
+    pub fn parse_at<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        match self.match_at(ctx, loc) {
+            Some(found) => Ok(found),
+            None => self.source.parse_impl(ctx, loc),
+        }
+    }
+
+    /// Tokens of each non-overlapping, non-empty match in the input, like
+    /// search_string, at most `max_matches` of them. Stops early if a checkpoint in
+    /// `ctx` aborts.
+    pub fn search<'a>(
+        &self,
+        ctx: &mut ParseContext<'a>,
+        max_matches: Option<usize>,
+    ) -> Vec<ParseResults<'a>> {
+        let input = ctx.input();
+        let len = input.len();
+        let mut found = Vec::new();
+        let mut loc = 0;
+        while loc < len && max_matches.is_none_or(|max| found.len() < max) {
+            if ctx.checkpoint(loc).is_err() {
+                break;
+            }
+            // Whitespace is skipped before the match, as scan_string does
+            let start = match self.source.skip_whitespace_before() {
+                true => skip_ws(input, loc),
+                false => loc,
+            };
+            match self.match_at(ctx, start) {
+                Some((end, results)) if end > start => {
+                    found.push(results);
+                    loc = end;
+                }
+                // Matches start on char boundaries, which Regex slices at
+                _ => loc += input[loc..].chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        found
+    }
+}
+
+/// Append the instructions for `elem` to `program`.
+fn lower(elem: &Arc<dyn ParserElement>, program: &mut Vec<Inst>) -> Result<(), CompileError> {
+    let any = elem.clone().into_any();
+    let any = match any.downcast::<Literal>() {
+        Ok(lit) => return emit(program, Inst::Leaf(Leaf::Literal(lit))),
+        Err(any) => any,
+    };
+    let any = match any.downcast::<Keyword>() {
+        Ok(kw) => return emit(program, Inst::Leaf(Leaf::Keyword(kw))),
+        Err(any) => any,
+    };
+    let any = match any.downcast::<Word>() {
+        Ok(word) => return emit(program, Inst::Leaf(Leaf::Word(word))),
+        Err(any) => any,
+    };
+    let any = match any.downcast::<RegexMatch>() {
+        Ok(regex) if regex.match_is_token() => {
+            return emit(program, Inst::Leaf(Leaf::Regex(regex)))
+        }
+        Ok(_) => {
+            return Err(unsupported(
+                elem,
+                "a Regex with groups or as_match can't be compiled",
+            ))
+        }
+        Err(any) => any,
+    };
+    if let Some(and) = any.downcast_ref::<And>() {
+        if and.error_stop().is_some() {
+            return Err(unsupported(elem, "error stops ('-') can't be compiled"));
+        }
+        for child in and.elements() {
+            program.push(Inst::SkipWs);
+            lower(child, program)?;
+        }
+    } else if let Some(first) = any.downcast_ref::<MatchFirst>() {
+        let Some((last, rest)) = first.elements().split_last() else {
+            return emit(program, Inst::Fail);
+        };
+        let mut commits = Vec::new();
+        for alt in rest {
+            let choice = program.len();
+            program.push(Inst::Choice(0));
+            lower(alt, program)?;
+            commits.push(program.len());
+            program.push(Inst::Commit(0));
+            program[choice] = Inst::Choice(program.len());
+        }
+        lower(last, program)?;
+        for commit in commits {
+            program[commit] = Inst::Commit(program.len());
+        }
+    } else if any.is::<Optional>() {
+        let choice = program.len();
+        program.push(Inst::Choice(0));
+        lower(&only_child(elem)?, program)?;
+        program.push(Inst::Commit(program.len() + 1));
+        program[choice] = Inst::Choice(program.len());
+    } else if any.is::<ZeroOrMore>() {
+        let choice = program.len();
+        program.push(Inst::Choice(0));
+        program.push(Inst::SkipWs);
+        lower(&only_child(elem)?, program)?;
+        program.push(Inst::Repeat(choice + 1));
+        program[choice] = Inst::Choice(program.len());
+    } else if any.is::<Group>() || any.is::<Suppress>() {
+        let wrap = match any.is::<Group>() {
+            true => Wrap::Group,
+            false => Wrap::Suppress,
+        };
+        program.push(Inst::Open(wrap));
+        lower(&only_child(elem)?, program)?;
+        program.push(Inst::Close);
+    } else {
+        return Err(unsupported(
+            elem,
+            "only And, MatchFirst, Optional, ZeroOrMore, Literal, Word, Keyword, \
+             Regex, Suppress and Group can be compiled",
+        ));
+    }
+    Ok(())
+}
+
+fn emit(program: &mut Vec<Inst>, inst: Inst) -> Result<(), CompileError> {
+    program.push(inst);
+    Ok(())
+}
+
+/// The one element wrapped by `elem`. A ZeroOrMore with `stop_on` has two.
+fn only_child(elem: &Arc<dyn ParserElement>) -> Result<Arc<dyn ParserElement>, CompileError> {
+    let mut children = Vec::new();
+    elem.visit_children(&mut |child| children.push(child.clone()));
+    match <[_; 1]>::try_from(children) {
+        Ok([child]) => Ok(child),
+        Err(_) => Err(unsupported(elem, "stop_on can't be compiled")),
+    }
+}
+
+fn unsupported(elem: &Arc<dyn ParserElement>, why: &str) -> CompileError {
+    CompileError::new(format!("cannot compile {}: {}", elem.name(), why))
+}
+
+/// Tokens from the captures of a match: spans become tokens, Group marks nest them
+/// and Suppress marks drop them.
+fn build_results<'a>(input: &'a str, captures: &[Capture]) -> ParseResults<'a> {
+    let mut open: Vec<(Wrap, Vec<ParseResultItem<'a>>)> = Vec::new();
+    let mut items = Vec::new();
+    for capture in captures {
+        match capture {
+            Capture::Span(start, end) => {
+                items.push(ParseResultItem::Token(input[*start..*end].into()))
+            }
+            Capture::Text(text) => items.push(ParseResultItem::Token(text.clone().into())),
+            Capture::Open(wrap) => open.push((*wrap, std::mem::take(&mut items))),
+            Capture::Close => {
+                let (wrap, outer) = open.pop().expect("balanced captures");
+                let inner = std::mem::replace(&mut items, outer);
+                if wrap == Wrap::Group {
+                    items.push(ParseResultItem::group(inner));
+                }
+            }
+        }
+    }
+    let mut results = ParseResults::new();
+    results.set_items(items);
+    results
+}
@@ -1,24 +1,55 @@
-use crate::core::context::ParseContext;
-use crate::core::exceptions::ParseException;
-use crate::core::parser::{ParseResult, ParserElement, ParserKind};
+use crate::core::context::{nested_match, ParseContext};
+use crate::core::exceptions::{FrozenError, ParseException};
+use crate::core::generate::{GenerateError, Generator};
+use crate::core::packrat::left_recursion_enabled;
+use crate::core::parser::{window_match, Namer, ParseResult, ParserElement, ParserKind};
+use crate::core::profile;
+use crate::core::segments::Segments;
+use crate::core::spec::{SpecError, SpecNode, SpecWriter};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 
 /// Forward - placeholder for recursive grammar definitions.
 /// Allows defining a parser before its content is known.
+/// Once frozen, the definition can no longer be replaced.
 pub struct Forward {
     inner: RwLock<Option<Arc<dyn ParserElement>>>,
+    frozen: AtomicBool,
+}
+
+impl Default for Forward {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Forward {
     pub fn new() -> Self {
         Self {
             inner: RwLock::new(None),
+            frozen: AtomicBool::new(false),
         }
     }
 
-    pub fn set(&self, parser: Arc<dyn ParserElement>) {
+    pub fn set(&self, parser: Arc<dyn ParserElement>) -> Result<(), FrozenError> {
         let mut guard = self.inner.write().unwrap();
+        if self.frozen.load(Ordering::Acquire) {
+            return Err(FrozenError::new(
+                "cannot set a Forward whose grammar has been frozen",
+            ));
+        }
         *guard = Some(parser);
+        Ok(())
+    }
+}
+
+/// The copy starts with the same definition and frozen state, and is set separately.
+impl Clone for Forward {
+    fn clone(&self) -> Self {
+        Self {
+            inner: RwLock::new(self.inner.read().unwrap().clone()),
+            frozen: AtomicBool::new(self.is_frozen()),
+        }
     }
 }
 
@@ -26,18 +57,95 @@ impl ParserElement for Forward {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
         let guard = self.inner.read().unwrap();
         match guard.as_ref() {
-            Some(parser) => parser.parse_impl(ctx, loc),
+            Some(parser) if ctx.grows_left_recursion() => {
+                ctx.parse_growing(self as *const Self as usize, parser, loc)
+            }
+            Some(parser) => ctx.parse(parser, loc),
             None => Err(ParseException::new(loc, "Forward not initialized")),
         }
     }
 
     #[inline]
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        // A left-recursive definition only terminates through parse_growing
+        if left_recursion_enabled() {
+            let mut ctx = ParseContext::new(input);
+            return self.parse_impl(&mut ctx, loc).ok().map(|(end, _)| end);
+        }
         let guard = self.inner.read().unwrap();
-        guard.as_ref()?.try_match_at(input, loc)
+        let parser = guard.as_ref()?;
+        nested_match(|| profile::try_match(parser, input, loc))
     }
 
     fn parser_kind(&self) -> ParserKind {
         ParserKind::Complex
     }
+
+    fn has_backref(&self) -> bool {
+        self.inner
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|p| p.has_backref())
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        if let Some(parser) = self.inner.read().unwrap().as_ref() {
+            visit(parser);
+        }
+    }
+
+    fn freeze(&self) {
+        // Taken under the write lock so a concurrent `set` either lands first or fails
+        let _guard = self.inner.write().unwrap();
+        self.frozen.store(true, Ordering::Release);
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::Acquire)
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if left_recursion_enabled() {
+            return window_match(self, input, loc);
+        }
+        let guard = self.inner.read().unwrap();
+        let parser = guard.as_ref()?;
+        nested_match(|| parser.try_match_segments(input, loc))
+    }
+
+    /// Recursion is bounded by the generator's `max_depth`; a Forward with no
+    /// non-recursive alternative fails with a depth error.
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        let guard = self.inner.read().unwrap();
+        match guard.as_ref() {
+            Some(parser) => gen.descend(parser.as_ref()),
+            None => Err(GenerateError::new(
+                "cannot generate input for an unset Forward",
+            )),
+        }
+    }
+
+    /// Spelled out once; recursive references show as `Forward: ...`.
+    fn default_name(&self, namer: &mut Namer) -> String {
+        let inner = self.inner.read().unwrap().clone();
+        match inner {
+            Some(parser) if namer.first_visit(self as *const Self as usize) => {
+                format!("Forward: {}", namer.child(&parser))
+            }
+            Some(_) => "Forward: ...".to_string(),
+            None => "Forward: None".to_string(),
+        }
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: definition, frozen.
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let inner = self.inner.read().unwrap().clone();
+        let params = vec![spec.opt_child(inner.as_ref())?, self.is_frozen().into()];
+        Ok(SpecNode::new("Forward", params))
+    }
 }
@@ -1,12 +1,79 @@
-use crate::core::context::ParseContext;
+use crate::core::context::{skip_ws, ParseContext};
 use crate::core::exceptions::ParseException;
-use crate::core::parser::{ParseResult, ParserElement};
-use crate::core::results::ParseResults;
-use std::sync::Arc;
+use crate::core::generate::{GenerateError, Generator};
+use crate::core::parser::{
+    find_spans_by_offset, name_from_error, window_match, ByteSet, Namer, ParseResult,
+    ParserElement, ParserKind,
+};
+use crate::core::results::{ParseResultItem, ParseResults};
+use crate::core::segments::Segments;
+use crate::core::spec::{SpecError, SpecNode, SpecValue, SpecWriter};
+use crate::elements::chars::{CharClassError, CharSet};
+use memchr::memmem;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// ParserElement::find_spans for an element whose matches all start with `needle`:
+/// memmem jumps between occurrences, and `verify` checks each one (returning the
+/// match end) instead of trying every offset. Stops after `max` spans.
+fn find_needle_spans(
+    needle: &[u8],
+    input: &str,
+    overlap: bool,
+    max: Option<usize>,
+    verify: impl Fn(usize) -> Option<usize>,
+) -> Vec<(usize, usize)> {
+    let finder = memmem::Finder::new(needle);
+    let bytes = input.as_bytes();
+    let mut spans = Vec::new();
+    let mut loc = 0;
+    while max.is_none_or(|max| spans.len() < max) {
+        let Some(found) = finder.find(&bytes[loc..]) else {
+            break;
+        };
+        let pos = loc + found;
+        let Some(end) = verify(pos).filter(|&end| end > pos) else {
+            loc = pos + 1;
+            continue;
+        };
+        // Same start and resume rules as the default scan
+        let start = skip_ws(input, pos).min(end);
+        spans.push((start, end));
+        loc = match overlap {
+            true => start + input[start..].chars().next().map_or(1, char::len_utf8),
+            false => end,
+        };
+    }
+    spans
+}
+
+/// A Literal or Keyword built from an empty string, which would match everywhere.
+#[derive(Debug, Clone)]
+pub struct EmptyStringError {
+    pub msg: String,
+}
+
+impl EmptyStringError {
+    fn new(element: &str) -> Self {
+        Self {
+            msg: format!("{} requires a non-empty match string", element),
+        }
+    }
+}
+
+impl fmt::Display for EmptyStringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EmptyStringError: {}", self.msg)
+    }
+}
+
+impl std::error::Error for EmptyStringError {}
 
 /// Match a single character from a set of characters
+#[derive(Clone)]
 pub struct Char {
     charset: [bool; 256],
+    chars: Arc<str>,
     error_msg: Arc<str>,
 }
 
@@ -18,6 +85,7 @@ impl Char {
         }
         Self {
             charset,
+            chars: chars.into(),
             error_msg: Arc::from(format!("Expected one of '{}'", chars)),
         }
     }
@@ -41,27 +109,72 @@ impl ParserElement for Char {
             None
         }
     }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        match input.byte_at(loc) {
+            Some(b) if self.charset[b as usize] => Some(loc + 1),
+            _ => None,
+        }
+    }
+
+    fn first_chars(&self) -> Option<ByteSet> {
+        Some((0..=255u8).filter(|&b| self.charset[b as usize]).collect())
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        let members: Vec<u8> = (0..=255u8).filter(|&b| self.charset[b as usize]).collect();
+        if members.is_empty() {
+            return Err(GenerateError::new(
+                "cannot generate input for an empty Char set",
+            ));
+        }
+        let i = gen.below(members.len());
+        gen.push(members[i] as char);
+        Ok(())
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "Char",
+            vec![SpecValue::Str(self.chars.clone())],
+        ))
+    }
 }
 
 /// Match an exact literal string
+#[derive(Clone)]
 pub struct Literal {
     match_string: String,
     first_char: u8,
     error_msg: Arc<str>,
-    cached_result: ParseResults,
 }
 
 impl Literal {
+    /// # Panics
+    /// If `s` is empty; see try_new.
     pub fn new(s: &str) -> Self {
-        let first_char = s.bytes().next().unwrap_or(0);
+        Self::try_new(s).unwrap_or_else(|e| panic!("{}", e.msg))
+    }
+
+    pub fn try_new(s: &str) -> Result<Self, EmptyStringError> {
+        let first_char = *s
+            .as_bytes()
+            .first()
+            .ok_or_else(|| EmptyStringError::new("Literal"))?;
         let error_msg: Arc<str> = format!("Expected '{}'", s).into();
-        let cached_result = ParseResults::from_single(s);
-        Self {
+        Ok(Self {
             match_string: s.to_string(),
             first_char,
             error_msg,
-            cached_result,
-        }
+        })
     }
 
     #[inline(always)]
@@ -73,6 +186,13 @@ impl Literal {
     pub fn first_byte(&self) -> u8 {
         self.first_char
     }
+
+    /// Whether `s` starts with the literal, with no whitespace skipped (batch parsing).
+    #[inline(always)]
+    pub fn is_prefix_of(&self, s: &str) -> bool {
+        s.as_bytes().first() == Some(&self.first_char)
+            && s.as_bytes().starts_with(self.match_string.as_bytes())
+    }
 }
 
 impl ParserElement for Literal {
@@ -100,7 +220,10 @@ impl ParserElement for Literal {
             return Err(ParseException::new(loc, self.error_msg.clone()));
         }
 
-        Ok((loc + match_len, self.cached_result.clone()))
+        Ok((
+            loc + match_len,
+            ParseResults::from_single(&input[loc..loc + match_len]),
+        ))
     }
 
     /// Zero-alloc match — just returns end position
@@ -118,37 +241,213 @@ impl ParserElement for Literal {
             None
         }
     }
+
+    fn find_spans(&self, input: &str, overlap: bool, max: Option<usize>) -> Vec<(usize, usize)> {
+        let len = self.match_string.len();
+        find_needle_spans(self.match_string.as_bytes(), input, overlap, max, |pos| {
+            Some(pos + len)
+        })
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if input.matches_at(loc, self.match_string.as_bytes()) {
+            Some(loc + self.match_string.len())
+        } else {
+            None
+        }
+    }
+
+    fn first_chars(&self) -> Option<ByteSet> {
+        Some([self.first_char].into_iter().collect())
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.push_str(&self.match_string);
+        Ok(())
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "Literal",
+            vec![self.match_string.as_str().into()],
+        ))
+    }
+}
+
+/// Identifier characters of Keywords built without their own, as a class spec.
+pub const DEFAULT_KEYWORD_CHARS: &str = "A-Za-z0-9_";
+
+/// The class spec and set that new Keywords use as identifier characters, once
+/// changed from DEFAULT_KEYWORD_CHARS.
+static KEYWORD_CHARS: RwLock<Option<(Arc<str>, CharSet)>> = RwLock::new(None);
+
+/// The identifier characters new Keywords use: their class spec and set.
+pub fn default_keyword_chars() -> (Arc<str>, CharSet) {
+    let current = KEYWORD_CHARS.read().unwrap_or_else(|e| e.into_inner());
+    match &*current {
+        Some(chars) => chars.clone(),
+        None => {
+            let set = CharSet::from_chars(
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_",
+            );
+            (DEFAULT_KEYWORD_CHARS.into(), set)
+        }
+    }
+}
+
+/// Change the identifier characters of Keywords created afterwards to the class
+/// `spec` (e.g. `A-Za-z0-9_-`). Returns the previous spec.
+pub fn set_default_keyword_chars(spec: &str) -> Result<Arc<str>, CharClassError> {
+    let set = CharSet::from_class(spec)?;
+    let mut current = KEYWORD_CHARS.write().unwrap_or_else(|e| e.into_inner());
+    let previous = current
+        .replace((spec.into(), set))
+        .map_or_else(|| DEFAULT_KEYWORD_CHARS.into(), |(spec, _)| spec);
+    Ok(previous)
 }
 
 /// Match a keyword (literal with word boundary checking)
+#[derive(Clone)]
 pub struct Keyword {
     match_string: String,
+    /// The match string lowercased, compared against when caseless
+    match_lower: String,
     match_len: usize,
     first_char: u8,
-    ident_chars: [bool; 256],
+    caseless: bool,
+    /// Class spec of `ident_chars`, kept for describe()
+    ident_spec: Arc<str>,
+    /// Characters that may not precede or follow the keyword
+    ident_chars: CharSet,
     error_msg: Arc<str>,
-    cached_result: ParseResults,
+    /// Token returned on a caseless match: the match string as given, not as found
+    match_text: Arc<str>,
 }
 
 impl Keyword {
+    /// # Panics
+    /// If `s` is empty; see try_new.
     pub fn new(s: &str) -> Self {
-        let mut ident_chars = [false; 256];
-        for c in b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_" {
-            ident_chars[*c as usize] = true;
-        }
+        Self::try_new(s).unwrap_or_else(|e| panic!("{}", e.msg))
+    }
 
-        let first_char = s.bytes().next().unwrap_or(0);
+    pub fn try_new(s: &str) -> Result<Self, EmptyStringError> {
+        let first_char = *s
+            .as_bytes()
+            .first()
+            .ok_or_else(|| EmptyStringError::new("Keyword"))?;
+        let (ident_spec, ident_chars) = default_keyword_chars();
         let error_msg: Arc<str> = format!("Expected keyword '{}'", s).into();
-        let cached_result = ParseResults::from_single(s);
 
-        Self {
+        Ok(Self {
             match_string: s.to_string(),
+            match_lower: s.to_ascii_lowercase(),
             match_len: s.len(),
             first_char,
+            caseless: false,
+            ident_spec,
             ident_chars,
             error_msg,
-            cached_result,
+            match_text: s.into(),
+        })
+    }
+
+    /// Match regardless of ASCII case, returning the keyword as given.
+    pub fn with_caseless(mut self, caseless: bool) -> Self {
+        self.caseless = caseless;
+        self.error_msg = match caseless {
+            true => format!("Expected keyword '{}' (caseless)", self.match_string),
+            false => format!("Expected keyword '{}'", self.match_string),
         }
+        .into();
+        self
+    }
+
+    /// Use the characters of the class `spec` (e.g. `A-Za-z0-9_-`) as identifier
+    /// characters: the keyword doesn't match when one precedes or follows it.
+    pub fn with_ident_chars(mut self, spec: &str) -> Result<Self, CharClassError> {
+        self.ident_chars = CharSet::from_class(spec)?;
+        self.ident_spec = spec.into();
+        Ok(self)
+    }
+
+    pub fn match_str(&self) -> &str {
+        &self.match_string
+    }
+
+    pub fn is_caseless(&self) -> bool {
+        self.caseless
+    }
+
+    /// Token of every match when caseless; None when it's the text matched.
+    pub fn caseless_token(&self) -> Option<&Arc<str>> {
+        self.caseless.then_some(&self.match_text)
+    }
+
+    /// Class spec of the identifier characters.
+    pub fn ident_chars(&self) -> &str {
+        &self.ident_spec
+    }
+
+    /// Whether the character at byte offset `loc` of `input` is an identifier
+    /// character (false at the end of the input).
+    #[inline(always)]
+    pub fn is_ident_at(&self, input: &str, loc: usize) -> bool {
+        match input.as_bytes().get(loc) {
+            None => false,
+            Some(&b) if b.is_ascii() => self.ident_chars.contains(b),
+            Some(_) => input[loc..]
+                .chars()
+                .next()
+                .is_some_and(|c| self.ident_chars.contains_char(c)),
+        }
+    }
+
+    /// Whether the character just before byte offset `loc` of `input` is an
+    /// identifier character (false at the start of the input).
+    #[inline(always)]
+    pub fn is_ident_before(&self, input: &str, loc: usize) -> bool {
+        match loc.checked_sub(1).map(|i| input.as_bytes()[i]) {
+            None => false,
+            Some(b) if b.is_ascii() => self.ident_chars.contains(b),
+            Some(_) => input[..loc]
+                .chars()
+                .next_back()
+                .is_some_and(|c| self.ident_chars.contains_char(c)),
+        }
+    }
+
+    /// is_ident_before for segmented input, whose character before `loc` may span
+    /// parts.
+    fn is_ident_before_segments(&self, input: &Segments<'_>, loc: usize) -> bool {
+        let Some(mut start) = loc.checked_sub(1) else {
+            return false;
+        };
+        while start > 0 && input.byte_at(start).is_some_and(|b| b & 0xC0 == 0x80) {
+            start -= 1;
+        }
+        self.is_ident_before(&input.slice(start, loc), loc - start)
+    }
+
+    /// Whether the keyword's text is at `loc`, ignoring the boundary.
+    #[inline(always)]
+    fn text_at(&self, bytes: &[u8], loc: usize, end_loc: usize) -> bool {
+        if self.caseless {
+            return bytes[loc..end_loc]
+                .iter()
+                .zip(self.match_lower.as_bytes())
+                .all(|(a, b)| a.to_ascii_lowercase() == *b);
+        }
+        bytes[loc] == self.first_char
+            && (self.match_len <= 1 || bytes[loc + 1..end_loc] == self.match_string.as_bytes()[1..])
     }
 }
 
@@ -158,75 +457,136 @@ impl ParserElement for Keyword {
         let input = _ctx.input();
         let end_loc = loc + self.match_len;
 
-        // Fast checks first
-        if end_loc > input.len() {
+        if end_loc > input.len()
+            || !self.text_at(input.as_bytes(), loc, end_loc)
+            || self.is_ident_at(input, end_loc)
+            || self.is_ident_before(input, loc)
+        {
             return Err(ParseException::new(loc, self.error_msg.clone()));
         }
+        let token = match self.caseless {
+            true => ParseResults::from_token(self.match_text.clone()),
+            false => ParseResults::from_single(&input[loc..end_loc]),
+        };
+        Ok((end_loc, token))
+    }
 
-        let input_bytes = input.as_bytes();
-
-        // Quick first char check
-        if input_bytes[loc] != self.first_char {
-            return Err(ParseException::new(loc, self.error_msg.clone()));
+    /// Zero-alloc keyword match with word boundary checks on both sides
+    #[inline(always)]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        let end_loc = loc + self.match_len;
+        if end_loc > input.len()
+            || !self.text_at(input.as_bytes(), loc, end_loc)
+            || self.is_ident_at(input, end_loc)
+            || self.is_ident_before(input, loc)
+        {
+            return None;
         }
+        Some(end_loc)
+    }
 
-        // Check rest of string
-        if self.match_len > 1 && input[loc + 1..end_loc] != self.match_string[1..] {
-            return Err(ParseException::new(loc, self.error_msg.clone()));
+    fn find_spans(&self, input: &str, overlap: bool, max: Option<usize>) -> Vec<(usize, usize)> {
+        if self.caseless {
+            return find_spans_by_offset(self, input, overlap, max);
         }
+        find_needle_spans(self.match_string.as_bytes(), input, overlap, max, |pos| {
+            self.try_match_at(input, pos)
+        })
+    }
 
-        // Check word boundary after
-        if end_loc < input.len() {
-            let next_byte = input_bytes[end_loc];
-            if self.ident_chars[next_byte as usize] {
-                return Err(ParseException::new(loc, self.error_msg.clone()));
-            }
+    /// A caseless match's token is the keyword as given, not the text matched.
+    fn parser_kind(&self) -> ParserKind {
+        match self.caseless {
+            true => ParserKind::Complex,
+            false => ParserKind::Normal,
         }
-
-        Ok((end_loc, self.cached_result.clone()))
     }
 
-    /// Zero-alloc keyword match with word boundary check
-    #[inline(always)]
-    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        // The window starts at `loc`, so the character before is checked here
+        if self.is_ident_before_segments(input, loc) {
+            return None;
+        }
+        // The boundary character may span segments
+        if !self.ident_chars.is_ascii() {
+            return window_match(self, input, loc);
+        }
         let end_loc = loc + self.match_len;
-        let bytes = input.as_bytes();
-        let match_bytes = self.match_string.as_bytes();
-
-        if end_loc > bytes.len()
-            || bytes[loc] != self.first_char
-            || (self.match_len > 1 && bytes[loc + 1..end_loc] != match_bytes[1..])
-        {
+        let found = match self.caseless {
+            true => input.matches_at_caseless(loc, self.match_lower.as_bytes()),
+            false => input.matches_at(loc, self.match_string.as_bytes()),
+        };
+        if !found {
             return None;
         }
+        match input.byte_at(end_loc) {
+            Some(b) if self.ident_chars.contains(b) => None,
+            _ => Some(end_loc),
+        }
+    }
 
-        // Word boundary check
-        if end_loc < bytes.len() && self.ident_chars[bytes[end_loc] as usize] {
-            return None;
+    fn first_chars(&self) -> Option<ByteSet> {
+        let mut set = ByteSet::default();
+        match self.caseless {
+            true => set.insert_caseless(self.first_char),
+            false => set.insert(self.first_char),
         }
+        Some(set)
+    }
 
-        Some(end_loc)
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.push_str(&self.match_string);
+        Ok(())
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "Keyword",
+            vec![
+                self.match_string.as_str().into(),
+                self.caseless.into(),
+                SpecValue::Str(self.ident_spec.clone()),
+            ],
+        ))
     }
 }
 
 /// Case-insensitive literal match. Returns the match string in its original case
 /// (as specified at construction), not the case found in the input.
+#[derive(Clone)]
 pub struct CaselessLiteral {
     match_lower: String,
     error_msg: Arc<str>,
-    cached_result: ParseResults,
+    /// Token returned on a match: the match string as given, not as found
+    match_text: Arc<str>,
 }
 
 impl CaselessLiteral {
+    /// # Panics
+    /// If `s` is empty; see try_new.
     pub fn new(s: &str) -> Self {
+        Self::try_new(s).unwrap_or_else(|e| panic!("{}", e.msg))
+    }
+
+    pub fn try_new(s: &str) -> Result<Self, EmptyStringError> {
+        if s.is_empty() {
+            return Err(EmptyStringError::new("CaselessLiteral"));
+        }
         let match_lower = s.to_ascii_lowercase();
         let error_msg: Arc<str> = format!("Expected '{}' (caseless)", s).into();
-        let cached_result = ParseResults::from_single(s);
-        Self {
+        Ok(Self {
             match_lower,
             error_msg,
-            cached_result,
-        }
+            match_text: s.into(),
+        })
     }
 }
 
@@ -242,7 +602,10 @@ impl ParserElement for CaselessLiteral {
             .zip(self.match_lower.as_bytes())
             .all(|(a, b)| a.to_ascii_lowercase() == *b)
         {
-            Ok((loc + match_len, self.cached_result.clone()))
+            Ok((
+                loc + match_len,
+                ParseResults::from_token(self.match_text.clone()),
+            ))
         } else {
             Err(ParseException::new(loc, self.error_msg.clone()))
         }
@@ -264,75 +627,212 @@ impl ParserElement for CaselessLiteral {
             None
         }
     }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if input.matches_at_caseless(loc, self.match_lower.as_bytes()) {
+            Some(loc + self.match_lower.len())
+        } else {
+            None
+        }
+    }
+
+    fn first_chars(&self) -> Option<ByteSet> {
+        let mut set = ByteSet::default();
+        set.insert_caseless(self.match_lower.as_bytes()[0]);
+        Some(set)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.push_str(&self.match_lower);
+        Ok(())
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let text = &*self.match_text;
+        Ok(SpecNode::new("CaselessLiteral", vec![text.into()]))
+    }
 }
 
-/// Case-insensitive keyword match with word boundary checking.
+/// Case-insensitive keyword match with word boundary checking: a caseless Keyword.
+#[derive(Clone)]
 pub struct CaselessKeyword {
-    match_lower: String,
-    match_len: usize,
-    ident_chars: [bool; 256],
-    error_msg: Arc<str>,
-    cached_result: ParseResults,
+    inner: Keyword,
 }
 
 impl CaselessKeyword {
+    /// # Panics
+    /// If `s` is empty; see try_new.
     pub fn new(s: &str) -> Self {
-        let mut ident_chars = [false; 256];
-        for c in b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_" {
-            ident_chars[*c as usize] = true;
+        Self::try_new(s).unwrap_or_else(|e| panic!("{}", e.msg))
+    }
+
+    pub fn try_new(s: &str) -> Result<Self, EmptyStringError> {
+        let inner = Keyword::try_new(s).map_err(|_| EmptyStringError::new("CaselessKeyword"))?;
+        Ok(Self {
+            inner: inner.with_caseless(true),
+        })
+    }
+}
+
+impl ParserElement for CaselessKeyword {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        self.inner.parse_impl(ctx, loc)
+    }
+
+    #[inline(always)]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        self.inner.try_match_at(input, loc)
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.inner.try_match_segments(input, loc)
+    }
+
+    fn first_chars(&self) -> Option<ByteSet> {
+        self.inner.first_chars()
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.push_str(&self.inner.match_lower);
+        Ok(())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        self.inner.default_name(namer)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let text = self.inner.match_str();
+        Ok(SpecNode::new("CaselessKeyword", vec![text.into()]))
+    }
+}
+
+/// Fuzzy literal: text as long as the match string (in characters) that differs from
+/// it in at most `max_mismatches` characters, like pyparsing's CloseMatch. The token
+/// is the text as found; `original` names the match string and `mismatches` the
+/// character positions that differ.
+#[derive(Clone)]
+pub struct CloseMatch {
+    match_string: Arc<str>,
+    chars: Box<[char]>,
+    max_mismatches: usize,
+    caseless: bool,
+    error_msg: Arc<str>,
+}
+
+impl CloseMatch {
+    pub fn try_new(s: &str, max_mismatches: usize) -> Result<Self, EmptyStringError> {
+        if s.is_empty() {
+            return Err(EmptyStringError::new("CloseMatch"));
         }
-        let match_lower = s.to_ascii_lowercase();
-        let error_msg: Arc<str> = format!("Expected keyword '{}' (caseless)", s).into();
-        let cached_result = ParseResults::from_single(s);
-        Self {
-            match_lower,
-            match_len: s.len(),
-            ident_chars,
+        let error_msg: Arc<str> = format!(
+            "Expected '{}' (with up to {} mismatches)",
+            s, max_mismatches
+        )
+        .into();
+        Ok(Self {
+            match_string: s.into(),
+            chars: s.chars().collect(),
+            max_mismatches,
+            caseless: false,
             error_msg,
-            cached_result,
+        })
+    }
+
+    /// Also count characters equal regardless of case as matching.
+    pub fn with_caseless(mut self, caseless: bool) -> Self {
+        self.caseless = caseless;
+        self
+    }
+
+    pub fn match_str(&self) -> &str {
+        &self.match_string
+    }
+
+    pub fn max_mismatches(&self) -> usize {
+        self.max_mismatches
+    }
+
+    /// End of a close match at `loc`, passing each mismatched position to `mismatch`.
+    fn match_at(&self, input: &str, loc: usize, mut mismatch: impl FnMut(usize)) -> Option<usize> {
+        let rest = input.get(loc..)?;
+        let mut found = rest.chars();
+        let mut count = 0;
+        for (i, &want) in self.chars.iter().enumerate() {
+            let c = found.next()?;
+            let same = c == want || (self.caseless && c.to_lowercase().eq(want.to_lowercase()));
+            if !same {
+                count += 1;
+                if count > self.max_mismatches {
+                    return None;
+                }
+                mismatch(i);
+            }
         }
+        Some(loc + rest.len() - found.as_str().len())
     }
 }
 
-impl ParserElement for CaselessKeyword {
+impl ParserElement for CloseMatch {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
         let input = ctx.input();
-        let end_loc = loc + self.match_len;
-        if end_loc > input.len() {
-            return Err(ParseException::new(loc, self.error_msg.clone()));
-        }
-        // Case-insensitive compare
-        if !input.as_bytes()[loc..end_loc]
-            .iter()
-            .zip(self.match_lower.as_bytes())
-            .all(|(a, b)| a.to_ascii_lowercase() == *b)
-        {
-            return Err(ParseException::new(loc, self.error_msg.clone()));
-        }
-        // Word boundary check
-        if end_loc < input.len() && self.ident_chars[input.as_bytes()[end_loc] as usize] {
-            return Err(ParseException::new(loc, self.error_msg.clone()));
-        }
-        Ok((end_loc, self.cached_result.clone()))
+        let mut mismatches = Vec::new();
+        let end = self
+            .match_at(input, loc, |i| {
+                mismatches.push(ParseResultItem::Int(i as i64))
+            })
+            .ok_or_else(|| ParseException::new(loc, self.error_msg.clone()))?;
+        let mut results = ParseResults::from_single(&input[loc..end]);
+        let original = ParseResultItem::Token(self.match_string.clone().into());
+        results.add_named("original", Box::new([original]));
+        let mismatches = ParseResultItem::group(mismatches);
+        results.add_named("mismatches", Box::new([mismatches]));
+        Ok((end, results))
     }
 
     #[inline(always)]
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
-        let end_loc = loc + self.match_len;
-        let bytes = input.as_bytes();
-        if end_loc > bytes.len() {
-            return None;
-        }
-        if !bytes[loc..end_loc]
-            .iter()
-            .zip(self.match_lower.as_bytes())
-            .all(|(a, b)| a.to_ascii_lowercase() == *b)
-        {
-            return None;
-        }
-        if end_loc < bytes.len() && self.ident_chars[bytes[end_loc] as usize] {
-            return None;
-        }
-        Some(end_loc)
+        self.match_at(input, loc, |_| {})
+    }
+
+    /// The named results must be kept, so parents call parse_impl.
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.push_str(&self.match_string);
+        Ok(())
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        format!("CloseMatch:'{}'", self.match_string)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "CloseMatch",
+            vec![
+                SpecValue::Str(self.match_string.clone()),
+                self.max_mismatches.into(),
+                self.caseless.into(),
+            ],
+        ))
     }
 }
@@ -0,0 +1,297 @@
+use crate::core::context::ParseContext;
+use crate::core::exceptions::ParseException;
+use crate::core::parser::{name_from_error, Namer, ParseResult, ParserElement, ParserKind};
+use crate::core::results::{NamedValue, OpaqueValue, ParseResultItem, ParseResults};
+use crate::core::spec::{SpecError, SpecNode, SpecWriter};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Whether `b` may appear in a tag or attribute name.
+#[inline]
+fn is_name_byte(b: u8) -> bool {
+    !b.is_ascii_whitespace() && !matches!(b, b'=' | b'/' | b'>' | b'<' | b'"' | b'\'')
+}
+
+/// First position at or after `pos` that isn't ASCII whitespace.
+#[inline]
+fn skip_space(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Start tag of one element, like pyparsing's `make_html_tags(tag)[0]`: `<tag`,
+/// attributes in any order with any whitespace around them, then `>` or `/>`. The
+/// token is the tag's text; results name `tag`, each attribute (to its unquoted
+/// value, `""` when it has none) and `empty` (whether it self-closes). HTML tags
+/// match the tag name in any case, lowercase attribute names and allow unquoted
+/// values; XML tags are exact and need quotes.
+#[derive(Clone)]
+pub struct HtmlTag {
+    tag: Arc<str>,
+    xml: bool,
+    error_msg: Arc<str>,
+}
+
+impl HtmlTag {
+    pub fn html(tag: &str) -> Self {
+        Self::new(tag, false)
+    }
+
+    pub fn xml(tag: &str) -> Self {
+        Self::new(tag, true)
+    }
+
+    fn new(tag: &str, xml: bool) -> Self {
+        Self {
+            tag: tag.into(),
+            xml,
+            error_msg: format!("Expected <{}>", tag).into(),
+        }
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn is_xml(&self) -> bool {
+        self.xml
+    }
+
+    /// Scan the tag at `loc`, passing each attribute's name and value to
+    /// `attribute`. Returns the end and whether the tag self-closes.
+    fn scan<'a>(
+        &self,
+        input: &'a str,
+        loc: usize,
+        mut attribute: impl FnMut(&'a str, Option<&'a str>),
+    ) -> Option<(usize, bool)> {
+        let bytes = input.as_bytes();
+        let mut pos = loc;
+        if bytes.get(pos) != Some(&b'<') {
+            return None;
+        }
+        pos += 1;
+        let name = bytes.get(pos..pos + self.tag.len())?;
+        let same = match self.xml {
+            true => name == self.tag.as_bytes(),
+            false => name.eq_ignore_ascii_case(self.tag.as_bytes()),
+        };
+        if !same {
+            return None;
+        }
+        pos += self.tag.len();
+        if bytes.get(pos).is_some_and(|&b| is_name_byte(b)) {
+            return None;
+        }
+        loop {
+            pos = skip_space(bytes, pos);
+            match bytes.get(pos)? {
+                b'>' => return Some((pos + 1, false)),
+                b'/' if bytes.get(pos + 1) == Some(&b'>') => return Some((pos + 2, true)),
+                _ => {}
+            }
+            let name_start = pos;
+            while bytes.get(pos).is_some_and(|&b| is_name_byte(b)) {
+                pos += 1;
+            }
+            if pos == name_start {
+                return None;
+            }
+            let name = &input[name_start..pos];
+            let after_name = skip_space(bytes, pos);
+            if bytes.get(after_name) != Some(&b'=') {
+                attribute(name, None);
+                continue;
+            }
+            pos = skip_space(bytes, after_name + 1);
+            let value = match *bytes.get(pos)? {
+                q @ (b'"' | b'\'') => {
+                    let len = memchr::memchr(q, &bytes[pos + 1..])?;
+                    let value = &input[pos + 1..pos + 1 + len];
+                    pos += len + 2;
+                    value
+                }
+                _ if self.xml => return None,
+                _ => {
+                    let start = pos;
+                    while bytes
+                        .get(pos)
+                        .is_some_and(|&b| !b.is_ascii_whitespace() && b != b'>')
+                    {
+                        pos += 1;
+                    }
+                    if pos == start {
+                        return None;
+                    }
+                    &input[start..pos]
+                }
+            };
+            attribute(name, Some(value));
+        }
+    }
+}
+
+impl ParserElement for HtmlTag {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let input = ctx.input();
+        let mut attributes = Vec::new();
+        let (end, empty) = self
+            .scan(input, loc, |name, value| attributes.push((name, value)))
+            .ok_or_else(|| ParseException::new(loc, self.error_msg.clone()))?;
+        let mut results = ParseResults::from_single(&input[loc..end]);
+        let tag = ParseResultItem::Token(self.tag.clone().into());
+        results.add_named("tag", Box::new([tag]));
+        for (name, value) in attributes {
+            let name = match self.xml {
+                true => Cow::Borrowed(name),
+                false => Cow::Owned(name.to_ascii_lowercase()),
+            };
+            let value = ParseResultItem::Token(value.unwrap_or("").into());
+            results.add_named(&*name, Box::new([value]));
+        }
+        let empty = ParseResultItem::Object(OpaqueValue(Arc::new(empty)));
+        results.add_named("empty", Box::new([empty]));
+        Ok((end, results))
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        self.scan(input, loc, |_, _| {}).map(|(end, _)| end)
+    }
+
+    /// The named attributes must be kept, so parents call parse_impl.
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "HtmlTag",
+            vec![(&*self.tag).into(), self.xml.into()],
+        ))
+    }
+}
+
+/// End tag of one element, `</tag>` with optional whitespace before the `>`. The
+/// token is the tag's text. HTML end tags match the name in any case.
+#[derive(Clone)]
+pub struct HtmlEndTag {
+    tag: Arc<str>,
+    xml: bool,
+    error_msg: Arc<str>,
+}
+
+impl HtmlEndTag {
+    pub fn html(tag: &str) -> Self {
+        Self::new(tag, false)
+    }
+
+    pub fn xml(tag: &str) -> Self {
+        Self::new(tag, true)
+    }
+
+    fn new(tag: &str, xml: bool) -> Self {
+        Self {
+            tag: tag.into(),
+            xml,
+            error_msg: format!("Expected </{}>", tag).into(),
+        }
+    }
+}
+
+impl ParserElement for HtmlEndTag {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let input = ctx.input();
+        match self.try_match_at(input, loc) {
+            Some(end) => Ok((end, ParseResults::from_single(&input[loc..end]))),
+            None => Err(ParseException::new(loc, self.error_msg.clone())),
+        }
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        let bytes = input.as_bytes();
+        if bytes.get(loc..loc + 2)? != b"</" {
+            return None;
+        }
+        let pos = loc + 2;
+        let name = bytes.get(pos..pos + self.tag.len())?;
+        let same = match self.xml {
+            true => name == self.tag.as_bytes(),
+            false => name.eq_ignore_ascii_case(self.tag.as_bytes()),
+        };
+        if !same {
+            return None;
+        }
+        let pos = skip_space(bytes, pos + self.tag.len());
+        (bytes.get(pos) == Some(&b'>')).then_some(pos + 1)
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "HtmlEndTag",
+            vec![(&*self.tag).into(), self.xml.into()],
+        ))
+    }
+}
+
+/// Required attribute values for start tags, like pyparsing's `with_attribute`: each
+/// attribute must be present and, unless its value is None (ANY_VALUE), equal it.
+#[derive(Clone, Debug)]
+pub struct AttributeFilter {
+    required: Vec<(Arc<str>, Option<Arc<str>>)>,
+}
+
+impl AttributeFilter {
+    pub fn new(required: Vec<(Arc<str>, Option<Arc<str>>)>) -> Self {
+        Self { required }
+    }
+
+    /// Whether the named results of a start tag satisfy every requirement.
+    pub fn matches(&self, results: &ParseResults<'_>) -> bool {
+        self.required.iter().all(|(name, want)| {
+            let found = match results.get_named(name) {
+                Some(NamedValue::One(value)) => Some(value),
+                Some(NamedValue::All(values)) => values.last().copied(),
+                None => None,
+            };
+            match (found, want) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some([ParseResultItem::Token(value)]), Some(want)) => **value == **want,
+                (Some(_), Some(_)) => false,
+            }
+        })
+    }
+
+    /// Failure message for tags that don't match.
+    pub fn message(&self) -> String {
+        let parts: Vec<String> = self
+            .required
+            .iter()
+            .map(|(name, want)| match want {
+                Some(value) => format!("{}='{}'", name, value),
+                None => name.to_string(),
+            })
+            .collect();
+        format!("Expected tag with attributes {}", parts.join(", "))
+    }
+}
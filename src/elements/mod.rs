@@ -1,7 +1,16 @@
+pub mod backref;
+pub mod build;
+pub mod builder;
 pub mod chars;
 pub mod combinators;
+pub mod comments;
+pub mod compile;
 pub mod forward;
 pub mod literals;
+pub mod markup;
+pub mod network;
+pub mod numbers;
 pub mod positional;
 pub mod repetition;
 pub mod structure;
+pub mod unicode;
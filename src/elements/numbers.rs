@@ -0,0 +1,214 @@
+use crate::core::context::{skip_ws, ParseContext};
+use crate::core::exceptions::ParseException;
+use crate::core::parser::{name_from_error, Namer, ParseResult, ParserElement, ParserKind};
+use crate::core::results::{ParseResultItem, ParseResults};
+use crate::core::spec::{SpecError, SpecNode, SpecWriter};
+use std::sync::Arc;
+
+/// Which numeric form a `Number` accepts (mirrors pyparsing_common).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberKind {
+    /// `\d+` → int
+    Integer,
+    /// `[+-]?\d+` → int
+    SignedInteger,
+    /// `[+-]?(\d+\.\d*|\.\d+)` → float
+    Real,
+    /// Real with optional exponent, or integer with required exponent → float
+    SciReal,
+    /// SciReal | Real | SignedInteger → float or int
+    Number,
+    /// `signed_integer / signed_integer` → float
+    Fraction,
+}
+
+impl NumberKind {
+    const ALL: [NumberKind; 6] = [
+        NumberKind::Integer,
+        NumberKind::SignedInteger,
+        NumberKind::Real,
+        NumberKind::SciReal,
+        NumberKind::Number,
+        NumberKind::Fraction,
+    ];
+
+    /// Human-readable name, used in error messages.
+    pub fn label(self) -> &'static str {
+        match self {
+            NumberKind::Integer => "integer",
+            NumberKind::SignedInteger => "signed integer",
+            NumberKind::Real => "real number",
+            NumberKind::SciReal => "real number with scientific notation",
+            NumberKind::Number => "number",
+            NumberKind::Fraction => "fraction",
+        }
+    }
+
+    /// The kind whose `label` is `label`.
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.label() == label)
+    }
+}
+
+/// Numeric literal that produces typed (int/float) tokens instead of strings.
+#[derive(Clone)]
+pub struct Number {
+    kind: NumberKind,
+    error_msg: Arc<str>,
+}
+
+/// Shape of a scanned numeric literal.
+struct Scanned {
+    end: usize,
+    is_float: bool,
+}
+
+#[inline]
+fn scan_digits(bytes: &[u8], pos: usize) -> usize {
+    pos + bytes[pos..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count()
+}
+
+impl Number {
+    pub fn new(kind: NumberKind) -> Self {
+        Self {
+            kind,
+            error_msg: format!("Expected {}", kind.label()).into(),
+        }
+    }
+
+    /// Scan a single (non-fraction) numeric literal at `loc`.
+    fn scan_one(&self, bytes: &[u8], loc: usize, kind: NumberKind) -> Option<Scanned> {
+        let mut pos = loc;
+        if kind != NumberKind::Integer && matches!(bytes.get(pos), Some(b'+' | b'-')) {
+            pos += 1;
+        }
+        let int_end = scan_digits(bytes, pos);
+        let has_int = int_end > pos;
+        pos = int_end;
+
+        let allow_frac = matches!(
+            kind,
+            NumberKind::Real | NumberKind::SciReal | NumberKind::Number
+        );
+        let mut has_frac = false;
+        if allow_frac && bytes.get(pos) == Some(&b'.') {
+            let frac_end = scan_digits(bytes, pos + 1);
+            // "5." is a real; "." alone is not
+            if has_int || frac_end > pos + 1 {
+                has_frac = true;
+                pos = frac_end;
+            }
+        }
+        if !has_int && !has_frac {
+            return None;
+        }
+
+        let mut has_exp = false;
+        if matches!(kind, NumberKind::SciReal | NumberKind::Number)
+            && matches!(bytes.get(pos), Some(b'e' | b'E'))
+        {
+            let mut p = pos + 1;
+            if matches!(bytes.get(p), Some(b'+' | b'-')) {
+                p += 1;
+            }
+            let exp_end = scan_digits(bytes, p);
+            if exp_end > p {
+                has_exp = true;
+                pos = exp_end;
+            }
+        }
+
+        let ok = match kind {
+            NumberKind::Integer | NumberKind::SignedInteger => true,
+            NumberKind::Real => has_frac,
+            NumberKind::SciReal => has_frac || has_exp,
+            NumberKind::Number => true,
+            NumberKind::Fraction => unreachable!(),
+        };
+        ok.then_some(Scanned {
+            end: pos,
+            is_float: has_frac || has_exp,
+        })
+    }
+
+    /// Scan `signed_integer / signed_integer`, allowing whitespace around the slash.
+    fn scan_fraction(&self, input: &str, loc: usize) -> Option<(usize, usize, usize)> {
+        let bytes = input.as_bytes();
+        let num = self.scan_one(bytes, loc, NumberKind::SignedInteger)?;
+        let slash = skip_ws(input, num.end);
+        if bytes.get(slash) != Some(&b'/') {
+            return None;
+        }
+        let den_start = skip_ws(input, slash + 1);
+        let den = self.scan_one(bytes, den_start, NumberKind::SignedInteger)?;
+        Some((num.end, den_start, den.end))
+    }
+
+    /// The match at `loc`: its end and value, or the message of the error to raise.
+    /// Integers must fit in an i64 and fractions need a non-zero denominator, so
+    /// every way of matching rejects the same text.
+    fn scan<'a>(
+        &self,
+        input: &'a str,
+        loc: usize,
+    ) -> Result<(usize, ParseResultItem<'a>), Arc<str>> {
+        if self.kind == NumberKind::Fraction {
+            let (num_end, den_start, end) = self
+                .scan_fraction(input, loc)
+                .ok_or_else(|| self.error_msg.clone())?;
+            let num: f64 = input[loc..num_end].parse().unwrap_or(f64::NAN);
+            let den: f64 = input[den_start..end].parse().unwrap_or(f64::NAN);
+            if den == 0.0 {
+                return Err("Fraction has a zero denominator".into());
+            }
+            return Ok((end, ParseResultItem::Float(num / den)));
+        }
+        let scanned = self
+            .scan_one(input.as_bytes(), loc, self.kind)
+            .ok_or_else(|| self.error_msg.clone())?;
+        let text = &input[loc..scanned.end];
+        let item = if scanned.is_float {
+            ParseResultItem::Float(text.parse().unwrap_or(f64::NAN))
+        } else {
+            match text.parse::<i64>() {
+                Ok(v) => ParseResultItem::Int(v),
+                Err(_) => return Err(format!("Integer '{text}' does not fit in 64 bits").into()),
+            }
+        };
+        Ok((scanned.end, item))
+    }
+}
+
+impl ParserElement for Number {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let (end, item) = self
+            .scan(ctx.input(), loc)
+            .map_err(|msg| ParseException::new(loc, msg))?;
+        Ok((end, ParseResults::from_item(item)))
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        self.scan(input, loc).ok().map(|(end, _)| end)
+    }
+
+    /// Tokens are typed values, not the matched text, so parents must use parse_impl.
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("Number", vec![self.kind.label().into()]))
+    }
+}
@@ -1,10 +1,15 @@
 use crate::core::context::ParseContext;
 use crate::core::exceptions::ParseException;
-use crate::core::parser::{ParseResult, ParserElement};
+use crate::core::generate::{GenerateError, Generator};
+use crate::core::parser::{name_from_error, Namer, ParseResult, ParserElement, ParserKind};
+use crate::core::profile;
 use crate::core::results::ParseResults;
+use crate::core::segments::Segments;
+use crate::core::spec::{SpecError, SpecNode, SpecWriter};
 use std::sync::Arc;
 
 /// Matches at the start of the string (position 0 only).
+#[derive(Clone)]
 pub struct StringStart;
 
 impl ParserElement for StringStart {
@@ -24,9 +29,31 @@ impl ParserElement for StringStart {
             None
         }
     }
+
+    fn try_match_segments(&self, _input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if loc == 0 {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Zero-width and token-free.
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Suppress
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("StringStart", Vec::new()))
+    }
 }
 
 /// Matches at the end of the string.
+#[derive(Clone)]
 pub struct StringEnd;
 
 impl ParserElement for StringEnd {
@@ -46,9 +73,31 @@ impl ParserElement for StringEnd {
             None
         }
     }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if input.at_end(loc) {
+            Some(loc)
+        } else {
+            None
+        }
+    }
+
+    /// Zero-width and token-free.
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Suppress
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("StringEnd", Vec::new()))
+    }
 }
 
 /// Matches at the start of a line (position 0 or after \n).
+#[derive(Clone)]
 pub struct LineStart;
 
 impl ParserElement for LineStart {
@@ -68,18 +117,43 @@ impl ParserElement for LineStart {
             None
         }
     }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if loc == 0 || input.byte_at(loc - 1) == Some(b'\n') {
+            Some(loc)
+        } else {
+            None
+        }
+    }
+
+    /// Zero-width and token-free.
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Suppress
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("LineStart", Vec::new()))
+    }
 }
 
 /// Matches at the end of a line (before \n or at end of string).
+#[derive(Clone)]
 pub struct LineEnd;
 
 impl ParserElement for LineEnd {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
         let input = ctx.input();
         if loc >= input.len() || input.as_bytes()[loc] == b'\n' {
-            // Consume the newline if present
-            let new_loc = if loc < input.len() { loc + 1 } else { loc };
-            Ok((new_loc, ParseResults::from_single("\n")))
+            // Consume the newline if present; at end of input there is nothing to return
+            if loc < input.len() {
+                Ok((loc + 1, ParseResults::from_single("\n")))
+            } else {
+                Ok((loc, ParseResults::new()))
+            }
         } else {
             Err(ParseException::new(loc, "Expected end of line"))
         }
@@ -93,17 +167,56 @@ impl ParserElement for LineEnd {
             None
         }
     }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        match input.byte_at(loc) {
+            None => Some(loc),
+            Some(b'\n') => Some(loc + 1),
+            Some(_) => None,
+        }
+    }
+
+    /// Yields "\n" at a newline but nothing at end of input, so it isn't a plain span.
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("LineEnd", Vec::new()))
+    }
 }
 
 /// Matches the rest of the line (up to but not including the next newline).
+#[derive(Clone)]
 pub struct RestOfLine {
     error_msg: Arc<str>,
+    /// Trim surrounding spaces and tabs from the captured text
+    strip: bool,
+}
+
+impl Default for RestOfLine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RestOfLine {
     pub fn new() -> Self {
         Self {
             error_msg: Arc::from("Expected rest of line"),
+            strip: false,
+        }
+    }
+
+    /// RestOfLine whose token has leading and trailing spaces/tabs (and a `\r`) removed.
+    pub fn stripped() -> Self {
+        Self {
+            strip: true,
+            ..Self::new()
         }
     }
 }
@@ -116,7 +229,12 @@ impl ParserElement for RestOfLine {
         }
         let rest = &input[loc..];
         let end = rest.find('\n').map(|p| loc + p).unwrap_or(input.len());
-        Ok((end, ParseResults::from_single(&input[loc..end])))
+        let line = &input[loc..end];
+        if self.strip {
+            let trimmed = line.trim_matches(|c| matches!(c, ' ' | '\t' | '\r'));
+            return Ok((end, ParseResults::from_single(trimmed)));
+        }
+        Ok((end, ParseResults::from_single(line)))
     }
 
     #[inline(always)]
@@ -128,18 +246,415 @@ impl ParserElement for RestOfLine {
         Some(rest.find('\n').map(|p| loc + p).unwrap_or(input.len()))
     }
 
+    /// A stripped token is not the raw match span, so parents must use parse_impl.
+    fn parser_kind(&self) -> ParserKind {
+        if self.strip {
+            ParserKind::Complex
+        } else {
+            ParserKind::Normal
+        }
+    }
+
     /// RestOfLine should NOT have whitespace skipped before it (preserves leading space).
     fn skip_whitespace_before(&self) -> bool {
         false
     }
 
     /// RestOfLine's parse_string should NOT skip leading whitespace.
-    fn parse_string(
+    fn parse_string<'a>(
         &self,
-        input: &str,
-    ) -> Result<crate::core::results::ParseResults, crate::core::exceptions::ParseException> {
+        input: &'a str,
+    ) -> Result<crate::core::results::ParseResults<'a>, crate::core::exceptions::ParseException>
+    {
+        let mut ctx = ParseContext::new(input).tracking_furthest_failure();
+        match self.parse_impl(&mut ctx, 0) {
+            Ok((_, results)) => Ok(results),
+            Err(e) => Err(ctx.with_furthest_failure(e)),
+        }
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if loc > input.len() {
+            return None;
+        }
+        Some(input.scan_while(loc, |b| b != b'\n'))
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        name_from_error(&self.error_msg)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("RestOfLine", vec![self.strip.into()]))
+    }
+}
+
+/// Advances to a 1-based column of the current line, for fixed-column formats. The
+/// text passed over is the token (none when suppressed); it fails if the position is
+/// already past the column or the line ends before it. Columns count characters,
+/// not bytes, and restart after each `\n`.
+#[derive(Clone)]
+pub struct GoToColumn {
+    column: usize,
+    suppress: bool,
+    error_msg: Arc<str>,
+}
+
+impl GoToColumn {
+    pub fn new(column: usize) -> Self {
+        Self {
+            column,
+            suppress: false,
+            error_msg: format!("Text not in expected column {}", column).into(),
+        }
+    }
+
+    /// Pass over the text without returning it.
+    pub fn suppressed(mut self) -> Self {
+        self.suppress = true;
+        self
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// End of the advance from `loc`, reading bytes through `byte_at` so plain and
+    /// segmented input share it.
+    fn advance(&self, byte_at: impl Fn(usize) -> Option<u8>, loc: usize) -> Option<usize> {
+        let is_char_start = |b: u8| b & 0xC0 != 0x80;
+        // Current column: characters since the last newline
+        let mut col = 1;
+        let mut pos = loc;
+        while pos > 0 {
+            match byte_at(pos - 1) {
+                Some(b'\n') | None => break,
+                Some(b) => col += is_char_start(b) as usize,
+            }
+            pos -= 1;
+        }
+        let mut end = loc;
+        for _ in col..self.column {
+            match byte_at(end) {
+                Some(b'\n') | None => return None,
+                Some(_) => end += 1,
+            }
+            while byte_at(end).is_some_and(|b| !is_char_start(b)) {
+                end += 1;
+            }
+        }
+        (col <= self.column).then_some(end)
+    }
+}
+
+impl ParserElement for GoToColumn {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let input = ctx.input();
+        let end = self
+            .advance(|i| input.as_bytes().get(i).copied(), loc)
+            .ok_or_else(|| ParseException::new(loc, self.error_msg.clone()))?;
+        match self.suppress {
+            true => Ok((end, ParseResults::new())),
+            false => Ok((end, ParseResults::from_single(&input[loc..end]))),
+        }
+    }
+
+    #[inline(always)]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        self.advance(|i| input.as_bytes().get(i).copied(), loc)
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.advance(|i| input.byte_at(i), loc)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        match self.suppress {
+            true => ParserKind::Suppress,
+            false => ParserKind::Normal,
+        }
+    }
+
+    /// The whitespace up to the column is part of what it passes over.
+    fn skip_whitespace_before(&self) -> bool {
+        false
+    }
+
+    fn default_name(&self, _namer: &mut Namer) -> String {
+        format!("GoToColumn:({})", self.column)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "GoToColumn",
+            vec![self.column.into(), self.suppress.into()],
+        ))
+    }
+}
+
+/// AsLine - requires `element` to cover a whole line. Only spaces and tabs may
+/// precede it on its line, and only spaces, tabs and a `\r` may follow it before
+/// the newline (or end of input). The element is matched against that line alone,
+/// so it can't run on into the next one; the trailing newline is consumed.
+#[derive(Clone)]
+pub struct AsLine {
+    element: Arc<dyn ParserElement>,
+}
+
+impl AsLine {
+    pub fn new(element: Arc<dyn ParserElement>) -> Self {
+        Self { element }
+    }
+
+    /// End of the line containing `loc`, if `loc` is preceded only by blanks on it.
+    #[inline]
+    fn line_end(input: &str, loc: usize) -> Option<usize> {
+        let bytes = input.as_bytes();
+        let line_start = memchr::memrchr(b'\n', &bytes[..loc]).map_or(0, |p| p + 1);
+        if !bytes[line_start..loc]
+            .iter()
+            .all(|&b| b == b' ' || b == b'\t')
+        {
+            return None;
+        }
+        Some(memchr::memchr(b'\n', &bytes[loc..]).map_or(bytes.len(), |p| loc + p))
+    }
+
+    /// Position after the line's newline, if only blanks follow `end` on the line.
+    #[inline]
+    fn finish_line(input: &str, end: usize, line_end: usize) -> Option<usize> {
+        let trailing = &input.as_bytes()[end..line_end];
+        if !trailing.iter().all(|&b| matches!(b, b' ' | b'\t' | b'\r')) {
+            return None;
+        }
+        Some(if line_end < input.len() {
+            line_end + 1
+        } else {
+            line_end
+        })
+    }
+}
+
+impl ParserElement for AsLine {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let input = ctx.input();
+        let line_end = Self::line_end(input, loc)
+            .ok_or_else(|| ParseException::new(loc, "Expected start of line"))?;
+        let mut line_ctx = ParseContext::new(&input[..line_end]).nested_in(ctx);
+        line_ctx.skip_whitespace = ctx.skip_whitespace;
+        line_ctx.whitespace = ctx.whitespace;
+        let (end, results) = self.element.parse_impl(&mut line_ctx, loc)?;
+        match Self::finish_line(input, end, line_end) {
+            Some(new_loc) => Ok((new_loc, results)),
+            None => Err(ParseException::new(end, "Expected end of line")),
+        }
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        let line_end = Self::line_end(input, loc)?;
+        let end = profile::try_match(&self.element, &input[..line_end], loc)?;
+        Self::finish_line(input, end, line_end)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("AsLine:({})", namer.child(&self.element))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("AsLine", vec![spec.child(&self.element)?]))
+    }
+}
+
+/// IndentedBlock - one or more matches of `element` on lines indented alike, like
+/// pyparsing's IndentedBlock. The block's column is that of its first match, which
+/// must be greater than the column of the enclosing block's lines (any column at
+/// the top level); each further match starts at that same column, and the block
+/// ends at the first line indented less (or differently). With `recursive`, a line
+/// indented further starts a nested block of the same element. The block's tokens
+/// are grouped unless `grouped` is false. Blank lines, and ignored expressions
+/// between lines, are passed over whatever the whitespace characters are.
+#[derive(Clone)]
+pub struct IndentedBlock {
+    element: Arc<dyn ParserElement>,
+    recursive: bool,
+    grouped: bool,
+}
+
+impl IndentedBlock {
+    pub fn new(element: Arc<dyn ParserElement>) -> Self {
+        Self {
+            element,
+            recursive: false,
+            grouped: true,
+        }
+    }
+
+    pub fn with_recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    pub fn with_grouped(mut self, grouped: bool) -> Self {
+        self.grouped = grouped;
+        self
+    }
+
+    /// First position at or after `loc` that isn't whitespace, a line break or an
+    /// ignored expression.
+    fn next_content(ctx: &ParseContext<'_>, mut loc: usize) -> usize {
+        let bytes = ctx.input().as_bytes();
+        loop {
+            let mut pos = ctx.skip_ws(loc);
+            while bytes
+                .get(pos)
+                .is_some_and(|b| matches!(b, b' ' | b'\t' | b'\r' | b'\n'))
+            {
+                pos += 1;
+            }
+            if pos == loc {
+                return pos;
+            }
+            loc = pos;
+        }
+    }
+
+    /// 1-based column of `loc`, in characters.
+    fn column(input: &str, loc: usize) -> usize {
+        let line_start = memchr::memrchr(b'\n', &input.as_bytes()[..loc]).map_or(0, |p| p + 1);
+        input[line_start..loc].chars().count() + 1
+    }
+
+    /// Parse the block whose first line starts at `anchor`, in column `column`.
+    fn parse_block<'a>(
+        &self,
+        ctx: &mut ParseContext<'a>,
+        anchor: usize,
+        column: usize,
+    ) -> ParseResult<'a> {
+        let input = ctx.input();
+        let (mut end, mut results) = ctx.parse(&self.element, anchor)?;
+        loop {
+            let next = Self::next_content(ctx, end);
+            if next >= input.len() {
+                break;
+            }
+            let next_column = Self::column(input, next);
+            let parsed = match next_column {
+                c if c == column => ctx.parse(&self.element, next),
+                c if c > column && self.recursive => {
+                    let outer = std::mem::replace(&mut ctx.indent, column);
+                    let nested = self.parse_block(ctx, next, c);
+                    ctx.indent = outer;
+                    nested
+                }
+                _ => break,
+            };
+            match parsed {
+                Ok((new_end, res)) if new_end > end => {
+                    end = new_end;
+                    results.extend(res);
+                }
+                _ => break,
+            }
+        }
+        match self.grouped {
+            true => Ok((end, ParseResults::from_group(results))),
+            false => Ok((end, results)),
+        }
+    }
+}
+
+impl ParserElement for IndentedBlock {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let input = ctx.input();
+        let anchor = Self::next_content(ctx, loc);
+        let column = Self::column(input, anchor);
+        if column <= ctx.indent {
+            return Err(ParseException::new(
+                anchor,
+                format!("Expected block indented past column {}", ctx.indent),
+            ));
+        }
+        let outer = std::mem::replace(&mut ctx.indent, column);
+        let outcome = self.parse_block(ctx, anchor, column);
+        ctx.indent = outer;
+        outcome
+    }
+
+    /// The enclosing blocks' indentation is only known to parse_impl.
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
         let mut ctx = ParseContext::new(input);
-        let (_, results) = self.parse_impl(&mut ctx, 0)?;
-        Ok(results)
+        self.parse_impl(&mut ctx, loc).ok().map(|(end, _)| end)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    /// It passes over whitespace and line breaks itself, to find the block's column.
+    fn skip_whitespace_before(&self) -> bool {
+        false
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    /// One line of the block.
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("IndentedBlock:({})", namer.child(&self.element))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "IndentedBlock",
+            vec![
+                spec.child(&self.element)?,
+                self.recursive.into(),
+                self.grouped.into(),
+            ],
+        ))
     }
 }
@@ -1,41 +1,101 @@
 use crate::core::context::{skip_ws, ParseContext};
 use crate::core::exceptions::ParseException;
-use crate::core::parser::{ParseResult, ParserElement, ParserKind};
+use crate::core::generate::{GenerateError, Generator, MAX_REPEAT};
+use crate::core::parser::{Namer, ParseResult, ParserElement, ParserKind};
+use crate::core::profile;
 use crate::core::results::ParseResults;
+use crate::core::segments::Segments;
+use crate::core::spec::{SpecError, SpecNode, SpecWriter};
 use std::sync::Arc;
 
+/// Whether `stop_on` matches at `loc` (after its leading whitespace), ending a
+/// repetition there.
+#[inline]
+fn stops_at(ctx: &ParseContext<'_>, stop_on: &Option<Arc<dyn ParserElement>>, loc: usize) -> bool {
+    stop_on.as_ref().is_some_and(|stop| {
+        let start = ctx.pre_skip(loc, stop.skip_whitespace_before());
+        profile::try_match(stop, ctx.input(), start).is_some()
+    })
+}
+
+/// stops_at for the match-only paths, which skip default whitespace.
+#[inline]
+fn matches_stop(input: &str, stop_on: &Option<Arc<dyn ParserElement>>, loc: usize) -> bool {
+    stop_on.as_ref().is_some_and(|stop| {
+        let start = if stop.skip_whitespace_before() {
+            skip_ws(input, loc)
+        } else {
+            loc
+        };
+        profile::try_match(stop, input, start).is_some()
+    })
+}
+
+#[inline]
+fn matches_stop_segments(
+    input: &Segments<'_>,
+    stop_on: &Option<Arc<dyn ParserElement>>,
+    loc: usize,
+) -> bool {
+    stop_on.as_ref().is_some_and(|stop| {
+        let start = if stop.skip_whitespace_before() {
+            input.skip_ws(loc)
+        } else {
+            loc
+        };
+        stop.try_match_segments(input, start).is_some()
+    })
+}
+
 /// ZeroOrMore - matches 0 or more repetitions
+#[derive(Clone)]
 pub struct ZeroOrMore {
     element: Arc<dyn ParserElement>,
+    /// Ends the repetition where it matches, without consuming it
+    stop_on: Option<Arc<dyn ParserElement>>,
 }
 
 impl ZeroOrMore {
     pub fn new(element: Arc<dyn ParserElement>) -> Self {
-        Self { element }
+        Self {
+            element,
+            stop_on: None,
+        }
+    }
+
+    /// Stop before any repetition where `stop_on` matches, like pyparsing's `stop_on`.
+    pub fn with_stop_on(mut self, stop_on: Option<Arc<dyn ParserElement>>) -> Self {
+        self.stop_on = stop_on;
+        self
     }
 }
 
 impl ParserElement for ZeroOrMore {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, mut loc: usize) -> ParseResult<'a> {
         let mut results = ParseResults::new();
-        let input = ctx.input();
 
         loop {
             // Skip whitespace before each repetition (like pyparsing)
-            let try_loc = if ctx.skip_whitespace && self.element.skip_whitespace_before() {
-                skip_ws(input, loc)
-            } else {
-                loc
-            };
-            match self.element.parse_impl(ctx, try_loc) {
+            let try_loc = ctx.pre_skip(loc, self.element.skip_whitespace_before());
+            ctx.checkpoint(try_loc)?;
+            if stops_at(ctx, &self.stop_on, loc) {
+                break;
+            }
+            let mark = ctx.choice_mark();
+            match ctx.parse(&self.element, try_loc) {
                 Ok((new_loc, res)) => {
                     if new_loc == try_loc {
+                        ctx.rewind_choices(mark);
                         break;
                     }
                     results.extend(res);
                     loc = new_loc;
                 }
-                Err(_) => break,
+                Err(e) if e.fatal => return Err(e),
+                Err(_) => {
+                    ctx.rewind_choices(mark);
+                    break;
+                }
             }
         }
 
@@ -53,7 +113,10 @@ impl ParserElement for ZeroOrMore {
             } else {
                 pos
             };
-            match self.element.try_match_at(input, try_pos) {
+            if matches_stop(input, &self.stop_on, pos) {
+                break;
+            }
+            match profile::try_match(&self.element, input, try_pos) {
                 Some(end) if end > try_pos => pos = end,
                 _ => break,
             }
@@ -64,16 +127,82 @@ impl ParserElement for ZeroOrMore {
     fn parser_kind(&self) -> ParserKind {
         ParserKind::Complex
     }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref() || self.stop_on.as_ref().is_some_and(|s| s.has_backref())
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+        self.stop_on.iter().for_each(visit);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        let mut pos = loc;
+        loop {
+            let try_pos = if self.element.skip_whitespace_before() {
+                input.skip_ws(pos)
+            } else {
+                pos
+            };
+            if matches_stop_segments(input, &self.stop_on, pos) {
+                break;
+            }
+            match self.element.try_match_segments(input, try_pos) {
+                Some(end) if end > try_pos => pos = end,
+                _ => break,
+            }
+        }
+        Some(pos)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        let n = gen.range(0, MAX_REPEAT);
+        for _ in 0..n {
+            gen.separate();
+            gen.descend(self.element.as_ref())?;
+        }
+        Ok(())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("[{}]...", namer.child(&self.element))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: element, stop_on.
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let params = vec![
+            spec.child(&self.element)?,
+            spec.opt_child(self.stop_on.as_ref())?,
+        ];
+        Ok(SpecNode::new("ZeroOrMore", params))
+    }
 }
 
 /// OneOrMore - matches 1 or more repetitions
+#[derive(Clone)]
 pub struct OneOrMore {
     element: Arc<dyn ParserElement>,
+    /// Ends the repetition where it matches, without consuming it
+    stop_on: Option<Arc<dyn ParserElement>>,
 }
 
 impl OneOrMore {
     pub fn new(element: Arc<dyn ParserElement>) -> Self {
-        Self { element }
+        Self {
+            element,
+            stop_on: None,
+        }
+    }
+
+    /// Stop before any repetition where `stop_on` matches, like pyparsing's `stop_on`.
+    pub fn with_stop_on(mut self, stop_on: Option<Arc<dyn ParserElement>>) -> Self {
+        self.stop_on = stop_on;
+        self
     }
 }
 
@@ -81,25 +210,30 @@ impl ParserElement for OneOrMore {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, mut loc: usize) -> ParseResult<'a> {
         let mut results = ParseResults::new();
         let mut count = 0;
-        let input = ctx.input();
 
         loop {
             // Skip whitespace before each repetition (like pyparsing)
-            let try_loc = if ctx.skip_whitespace && self.element.skip_whitespace_before() {
-                skip_ws(input, loc)
-            } else {
-                loc
-            };
-            match self.element.parse_impl(ctx, try_loc) {
+            let try_loc = ctx.pre_skip(loc, self.element.skip_whitespace_before());
+            ctx.checkpoint(try_loc)?;
+            if stops_at(ctx, &self.stop_on, loc) {
+                break;
+            }
+            let mark = ctx.choice_mark();
+            match ctx.parse(&self.element, try_loc) {
                 Ok((new_loc, res)) => {
                     if new_loc == try_loc {
+                        ctx.rewind_choices(mark);
                         break;
                     }
                     results.extend(res);
                     loc = new_loc;
                     count += 1;
                 }
-                Err(_) => break,
+                Err(e) if e.fatal => return Err(e),
+                Err(_) => {
+                    ctx.rewind_choices(mark);
+                    break;
+                }
             }
         }
 
@@ -119,14 +253,20 @@ impl ParserElement for OneOrMore {
         } else {
             loc
         };
-        let mut pos = self.element.try_match_at(input, try_loc)?;
+        if matches_stop(input, &self.stop_on, loc) {
+            return None;
+        }
+        let mut pos = profile::try_match(&self.element, input, try_loc)?;
         loop {
             let try_pos = if self.element.skip_whitespace_before() {
                 skip_ws(input, pos)
             } else {
                 pos
             };
-            match self.element.try_match_at(input, try_pos) {
+            if matches_stop(input, &self.stop_on, pos) {
+                break;
+            }
+            match profile::try_match(&self.element, input, try_pos) {
                 Some(end) if end > try_pos => pos = end,
                 _ => break,
             }
@@ -137,9 +277,72 @@ impl ParserElement for OneOrMore {
     fn parser_kind(&self) -> ParserKind {
         ParserKind::Complex
     }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref() || self.stop_on.as_ref().is_some_and(|s| s.has_backref())
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+        self.stop_on.iter().for_each(visit);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        let try_loc = if self.element.skip_whitespace_before() {
+            input.skip_ws(loc)
+        } else {
+            loc
+        };
+        if matches_stop_segments(input, &self.stop_on, loc) {
+            return None;
+        }
+        let mut pos = self.element.try_match_segments(input, try_loc)?;
+        loop {
+            let try_pos = if self.element.skip_whitespace_before() {
+                input.skip_ws(pos)
+            } else {
+                pos
+            };
+            if matches_stop_segments(input, &self.stop_on, pos) {
+                break;
+            }
+            match self.element.try_match_segments(input, try_pos) {
+                Some(end) if end > try_pos => pos = end,
+                _ => break,
+            }
+        }
+        Some(pos)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        let n = gen.range(1, MAX_REPEAT);
+        for _ in 0..n {
+            gen.separate();
+            gen.descend(self.element.as_ref())?;
+        }
+        Ok(())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("{{{}}}...", namer.child(&self.element))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: element, stop_on.
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let params = vec![
+            spec.child(&self.element)?,
+            spec.opt_child(self.stop_on.as_ref())?,
+        ];
+        Ok(SpecNode::new("OneOrMore", params))
+    }
 }
 
 /// Optional - matches 0 or 1 times
+#[derive(Clone)]
 pub struct Optional {
     element: Arc<dyn ParserElement>,
 }
@@ -152,20 +355,63 @@ impl Optional {
 
 impl ParserElement for Optional {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
-        match self.element.parse_impl(ctx, loc) {
+        let mark = ctx.choice_mark();
+        match ctx.parse(&self.element, loc) {
             Ok(result) => Ok(result),
-            Err(_) => Ok((loc, ParseResults::new())),
+            Err(e) if e.fatal => Err(e),
+            Err(_) => {
+                ctx.rewind_choices(mark);
+                ctx.record_optional_skip();
+                Ok((loc, ParseResults::new()))
+            }
         }
     }
 
     /// Zero-alloc match — returns inner match end or loc (always succeeds)
     #[inline]
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
-        Some(self.element.try_match_at(input, loc).unwrap_or(loc))
+        Some(profile::try_match(&self.element, input, loc).unwrap_or(loc))
+    }
+
+    /// An absent Optional yields no token rather than an empty span.
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        Some(self.element.try_match_segments(input, loc).unwrap_or(loc))
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        if gen.coin() {
+            gen.descend(self.element.as_ref())?;
+        }
+        Ok(())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("[{}]", namer.child(&self.element))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("Optional", vec![spec.child(&self.element)?]))
     }
 }
 
 /// Exactly - matches exactly N repetitions of an element
+#[derive(Clone)]
 pub struct Exactly {
     element: Arc<dyn ParserElement>,
     count: usize,
@@ -180,14 +426,11 @@ impl Exactly {
 impl ParserElement for Exactly {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, mut loc: usize) -> ParseResult<'a> {
         let mut results = ParseResults::new();
-        let input = ctx.input();
 
         for _ in 0..self.count {
             // Skip whitespace before each repetition
-            if ctx.skip_whitespace && self.element.skip_whitespace_before() {
-                loc = skip_ws(input, loc);
-            }
-            let (new_loc, res) = self.element.parse_impl(ctx, loc)?;
+            loc = ctx.pre_skip(loc, self.element.skip_whitespace_before());
+            let (new_loc, res) = ctx.parse(&self.element, loc)?;
             if new_loc == loc {
                 return Err(ParseException::new(loc, "No progress in Exactly"));
             }
@@ -205,7 +448,7 @@ impl ParserElement for Exactly {
             if self.element.skip_whitespace_before() {
                 pos = skip_ws(input, pos);
             }
-            let end = self.element.try_match_at(input, pos)?;
+            let end = profile::try_match(&self.element, input, pos)?;
             if end == pos {
                 return None;
             }
@@ -213,4 +456,182 @@ impl ParserElement for Exactly {
         }
         Some(pos)
     }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        let mut pos = loc;
+        for _ in 0..self.count {
+            if self.element.skip_whitespace_before() {
+                pos = input.skip_ws(pos);
+            }
+            let end = self.element.try_match_segments(input, pos)?;
+            if end == pos {
+                return None;
+            }
+            pos = end;
+        }
+        Some(pos)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        for _ in 0..self.count {
+            gen.separate();
+            gen.descend(self.element.as_ref())?;
+        }
+        Ok(())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("{}*{}", namer.child(&self.element), self.count)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let params = vec![spec.child(&self.element)?, self.count.into()];
+        Ok(SpecNode::new("Exactly", params))
+    }
+}
+
+/// Repeat - matches `min` to `max` repetitions (any number above `min` when `max`
+/// is None), taking as many as it can; backs the `*` operator and `expr[min, max]`.
+#[derive(Clone)]
+pub struct Repeat {
+    element: Arc<dyn ParserElement>,
+    min: usize,
+    max: Option<usize>,
+}
+
+impl Repeat {
+    pub fn new(element: Arc<dyn ParserElement>, min: usize, max: Option<usize>) -> Self {
+        Self { element, min, max }
+    }
+
+    #[inline]
+    fn below_max(&self, count: usize) -> bool {
+        self.max.is_none_or(|max| count < max)
+    }
+}
+
+impl ParserElement for Repeat {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, mut loc: usize) -> ParseResult<'a> {
+        let mut results = ParseResults::new();
+        let mut count = 0;
+
+        while self.below_max(count) {
+            let try_loc = ctx.pre_skip(loc, self.element.skip_whitespace_before());
+            ctx.checkpoint(try_loc)?;
+            let mark = ctx.choice_mark();
+            match ctx.parse(&self.element, try_loc) {
+                Ok((new_loc, res)) => {
+                    if new_loc == try_loc {
+                        ctx.rewind_choices(mark);
+                        break;
+                    }
+                    results.extend(res);
+                    loc = new_loc;
+                    count += 1;
+                }
+                Err(e) if e.fatal || count < self.min => return Err(e),
+                Err(_) => {
+                    ctx.rewind_choices(mark);
+                    break;
+                }
+            }
+        }
+
+        if count < self.min {
+            return Err(ParseException::new(
+                loc,
+                format!("Expected at least {} matches, found {}", self.min, count),
+            ));
+        }
+        Ok((loc, results))
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        let mut pos = loc;
+        let mut count = 0;
+        while self.below_max(count) {
+            let try_pos = if self.element.skip_whitespace_before() {
+                skip_ws(input, pos)
+            } else {
+                pos
+            };
+            match profile::try_match(&self.element, input, try_pos) {
+                Some(end) if end > try_pos => pos = end,
+                _ => break,
+            }
+            count += 1;
+        }
+        (count >= self.min).then_some(pos)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        let mut pos = loc;
+        let mut count = 0;
+        while self.below_max(count) {
+            let try_pos = if self.element.skip_whitespace_before() {
+                input.skip_ws(pos)
+            } else {
+                pos
+            };
+            match self.element.try_match_segments(input, try_pos) {
+                Some(end) if end > try_pos => pos = end,
+                _ => break,
+            }
+            count += 1;
+        }
+        (count >= self.min).then_some(pos)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        let max = self.max.unwrap_or(self.min + MAX_REPEAT);
+        let n = gen.range(self.min, max.max(self.min));
+        for _ in 0..n {
+            gen.separate();
+            gen.descend(self.element.as_ref())?;
+        }
+        Ok(())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        match self.max {
+            Some(max) if max == self.min => format!("{}*{}", namer.child(&self.element), max),
+            Some(max) => format!("{}*{}..{}", namer.child(&self.element), self.min, max),
+            None => format!("{}*{}..", namer.child(&self.element), self.min),
+        }
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: element, min, max.
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let params = vec![spec.child(&self.element)?, self.min.into(), self.max.into()];
+        Ok(SpecNode::new("Repeat", params))
+    }
 }
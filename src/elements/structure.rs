@@ -1,10 +1,15 @@
-use crate::core::context::ParseContext;
+use crate::core::context::{skip_ws, ParseContext, WhitespaceChars};
 use crate::core::exceptions::ParseException;
-use crate::core::parser::{ParseResult, ParserElement, ParserKind};
-use crate::core::results::ParseResults;
+use crate::core::generate::{GenerateError, Generator};
+use crate::core::parser::{window_match, ByteSet, Namer, ParseResult, ParserElement, ParserKind};
+use crate::core::profile;
+use crate::core::results::{ParseResultItem, ParseResults};
+use crate::core::segments::Segments;
+use crate::core::spec::{SpecError, SpecNode, SpecValue, SpecWriter};
 use std::sync::Arc;
 
 /// Empty - always matches at the current position, consuming nothing.
+#[derive(Clone)]
 pub struct Empty;
 
 impl ParserElement for Empty {
@@ -16,9 +21,31 @@ impl ParserElement for Empty {
     fn try_match_at(&self, _input: &str, loc: usize) -> Option<usize> {
         Some(loc)
     }
+
+    fn try_match_segments(&self, _input: &Segments<'_>, loc: usize) -> Option<usize> {
+        Some(loc)
+    }
+
+    fn generate(&self, _gen: &mut Generator) -> Result<(), GenerateError> {
+        Ok(())
+    }
+
+    /// Zero-width and token-free.
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Suppress
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("Empty", Vec::new()))
+    }
 }
 
 /// NoMatch - never matches.
+#[derive(Clone)]
 pub struct NoMatch;
 
 impl ParserElement for NoMatch {
@@ -30,9 +57,22 @@ impl ParserElement for NoMatch {
     fn try_match_at(&self, _input: &str, _loc: usize) -> Option<usize> {
         None
     }
+
+    fn try_match_segments(&self, _input: &Segments<'_>, _loc: usize) -> Option<usize> {
+        None
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("NoMatch", Vec::new()))
+    }
 }
 
 /// SkipTo - matches everything up to (but not including) a specified expression.
+#[derive(Clone)]
 pub struct SkipTo {
     target: Arc<dyn ParserElement>,
 }
@@ -48,7 +88,7 @@ impl ParserElement for SkipTo {
         let input = ctx.input();
         let mut pos = loc;
         while pos <= input.len() {
-            if self.target.try_match_at(input, pos).is_some() {
+            if profile::try_match(&self.target, input, pos).is_some() {
                 return Ok((pos, ParseResults::from_single(&input[loc..pos])));
             }
             pos += 1;
@@ -60,16 +100,41 @@ impl ParserElement for SkipTo {
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
         let mut pos = loc;
         while pos <= input.len() {
-            if self.target.try_match_at(input, pos).is_some() {
+            if profile::try_match(&self.target, input, pos).is_some() {
                 return Some(pos);
             }
             pos += 1;
         }
         None
     }
+
+    fn has_backref(&self) -> bool {
+        self.target.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.target);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        (loc..=input.len()).find(|&pos| self.target.try_match_segments(input, pos).is_some())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("SkipTo:({})", namer.child(&self.target))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("SkipTo", vec![spec.child(&self.target)?]))
+    }
 }
 
 /// Group - wraps results in a nested structure
+#[derive(Clone)]
 pub struct Group {
     element: Arc<dyn ParserElement>,
 }
@@ -82,7 +147,7 @@ impl Group {
 
 impl ParserElement for Group {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
-        match self.element.parse_impl(ctx, loc) {
+        match ctx.parse(&self.element, loc) {
             Ok((new_loc, res)) => {
                 // Wrap inner results in a Group item so nesting is preserved
                 Ok((new_loc, ParseResults::from_group(res)))
@@ -94,77 +159,1389 @@ impl ParserElement for Group {
     /// Zero-alloc match — delegates to inner element
     #[inline]
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
-        self.element.try_match_at(input, loc)
+        profile::try_match(&self.element, input, loc)
     }
 
     fn parser_kind(&self) -> ParserKind {
         ParserKind::Group
     }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.element.try_match_segments(input, loc)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("Group:({})", namer.child(&self.element))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("Group", vec![spec.child(&self.element)?]))
+    }
 }
 
-/// Suppress - matches but doesn't add to results
-pub struct Suppress {
+/// Dict - for each group in the inner results, names the remaining tokens after the
+/// group's first token. Like pyparsing's `Dict(OneOrMore(Group(key + value)))`.
+/// The tokens themselves are passed through unchanged.
+#[derive(Clone)]
+pub struct Dict {
     element: Arc<dyn ParserElement>,
 }
 
-impl Suppress {
+impl Dict {
     pub fn new(element: Arc<dyn ParserElement>) -> Self {
         Self { element }
     }
 }
 
-impl ParserElement for Suppress {
+impl ParserElement for Dict {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
-        // Use try_match_at to avoid creating ParseResults from inner element
-        match self.element.try_match_at(ctx.input(), loc) {
-            Some(new_loc) => Ok((new_loc, ParseResults::new())),
-            None => Err(ParseException::new(loc, "Suppress: no match")),
+        let (new_loc, mut res) = ctx.parse(&self.element, loc)?;
+        let mut entries = Vec::new();
+        for item in res.items() {
+            let ParseResultItem::Group(row) = item else {
+                continue;
+            };
+            let row = row.items();
+            let key = match row.first() {
+                Some(ParseResultItem::Token(s)) => Arc::from(&**s),
+                Some(ParseResultItem::Int(v)) => Arc::from(v.to_string()),
+                Some(ParseResultItem::Float(v)) => Arc::from(v.to_string()),
+                Some(ParseResultItem::Group(_) | ParseResultItem::Object(_)) | None => continue,
+            };
+            entries.push((key, row[1..].to_vec().into_boxed_slice()));
+        }
+        for (key, value) in entries {
+            res.add_named(key, value);
         }
+        Ok((new_loc, res))
     }
 
-    /// Zero-alloc match — delegates to inner element
     #[inline]
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
-        self.element.try_match_at(input, loc)
+        profile::try_match(&self.element, input, loc)
     }
 
     fn parser_kind(&self) -> ParserKind {
-        ParserKind::Suppress
+        ParserKind::Complex
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.element.try_match_segments(input, loc)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("Dict:({})", namer.child(&self.element))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("Dict", vec![spec.child(&self.element)?]))
     }
 }
 
-/// Combine - joins matched tokens into a single concatenated string.
-/// Like pyparsing's Combine: `Combine(Word(alphas) + Literal("-") + Word(nums))`
-/// would produce `["abc-123"]` instead of `["abc", "-", "123"]`.
-pub struct Combine {
+/// Located - reports where the inner expression matched: tokens become
+/// `[start, [inner tokens], end]`, also named `locn_start`, `value` and `locn_end`.
+/// Whitespace skipped before the match is not counted in `start`.
+#[derive(Clone)]
+pub struct Located {
     element: Arc<dyn ParserElement>,
 }
 
-impl Combine {
+impl Located {
     pub fn new(element: Arc<dyn ParserElement>) -> Self {
         Self { element }
     }
 }
 
-impl ParserElement for Combine {
+impl ParserElement for Located {
     fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
-        // Combine disables whitespace skipping for its inner elements (like pyparsing's leave_whitespace)
-        let old_skip = ctx.skip_whitespace;
-        ctx.skip_whitespace = false;
-        let result = self.element.parse_impl(ctx, loc);
-        ctx.skip_whitespace = old_skip;
-        let (new_loc, _res) = result?;
-        // Instead of joining individual tokens, just slice the original input
-        let combined = &ctx.input()[loc..new_loc];
-        Ok((new_loc, ParseResults::from_single(combined)))
+        // The inner element may skip whitespace itself (e.g. a leading And element)
+        let start = ctx.pre_skip(loc, self.element.skip_whitespace_before());
+        let (end, inner) = ctx.parse(&self.element, start)?;
+        let value = ParseResultItem::Group(Box::new(inner));
+        let mut res = ParseResults::from_item(ParseResultItem::Int(start as i64));
+        res.extend(ParseResults::from_item(value.clone()));
+        res.extend(ParseResults::from_item(ParseResultItem::Int(end as i64)));
+        res.add_named("locn_start", Box::new([ParseResultItem::Int(start as i64)]));
+        res.add_named("value", Box::new([value]));
+        res.add_named("locn_end", Box::new([ParseResultItem::Int(end as i64)]));
+        Ok((end, res))
     }
 
-    /// Combine must use parse_impl for matching to correctly disable whitespace skipping.
-    /// Without this, try_match_at would delegate to And's try_match_at which skips whitespace
-    /// between elements, causing false positive matches in search_string.
     #[inline]
     fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
-        let mut ctx = ParseContext::new(input);
-        self.parse_impl(&mut ctx, loc).ok().map(|(end, _)| end)
+        profile::try_match(&self.element, input, loc)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.element.try_match_segments(input, loc)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.child(&self.element)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("Located", vec![spec.child(&self.element)?]))
+    }
+}
+
+/// HighlightClass - matches like its element, tagged with a class name for
+/// `highlight_spans`.
+#[derive(Clone)]
+pub struct HighlightClass {
+    element: Arc<dyn ParserElement>,
+    class: Arc<str>,
+}
+
+impl HighlightClass {
+    pub fn new(element: Arc<dyn ParserElement>, class: &str) -> Self {
+        Self {
+            element,
+            class: class.into(),
+        }
+    }
+}
+
+impl ParserElement for HighlightClass {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        ctx.parse(&self.element, loc)
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        profile::try_match(&self.element, input, loc)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        self.element.parser_kind()
+    }
+
+    fn skip_whitespace_before(&self) -> bool {
+        self.element.skip_whitespace_before()
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn highlight_class(&self) -> Option<&Arc<str>> {
+        Some(&self.class)
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.element.try_match_segments(input, loc)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.child(&self.element)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let params = vec![
+            spec.child(&self.element)?,
+            SpecValue::Str(self.class.clone()),
+        ];
+        Ok(SpecNode::new("HighlightClass", params))
+    }
+}
+
+/// Named - matches like its element and registers its tokens under a results name,
+/// like pyparsing's `set_results_name` / `expr("name")`.
+#[derive(Clone)]
+pub struct Named {
+    element: Arc<dyn ParserElement>,
+    name: Arc<str>,
+    /// Keep every match under the name instead of only the last
+    list_all: bool,
+}
+
+impl Named {
+    pub fn new(element: Arc<dyn ParserElement>, name: &str, list_all: bool) -> Self {
+        Self {
+            element,
+            name: name.into(),
+            list_all,
+        }
+    }
+}
+
+impl ParserElement for Named {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let (end, mut res) = ctx.parse(&self.element, loc)?;
+        // As in pyparsing, a match without tokens isn't named
+        if res.items().is_empty() {
+            return Ok((end, res));
+        }
+        let value = res.items().to_vec().into_boxed_slice();
+        if self.list_all {
+            res.add_named_all(self.name.clone(), value);
+        } else {
+            res.add_named(self.name.clone(), value);
+        }
+        Ok((end, res))
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        profile::try_match(&self.element, input, loc)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        self.element.parser_kind()
+    }
+
+    fn skip_whitespace_before(&self) -> bool {
+        self.element.skip_whitespace_before()
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn results_name(&self) -> Option<&Arc<str>> {
+        Some(&self.name)
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.element.try_match_segments(input, loc)
+    }
+
+    fn first_chars(&self) -> Option<ByteSet> {
+        self.element.first_chars()
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.child(&self.element)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: element, name, list_all.
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let params = vec![
+            spec.child(&self.element)?,
+            SpecValue::Str(self.name.clone()),
+            self.list_all.into(),
+        ];
+        Ok(SpecNode::new("Named", params))
+    }
+}
+
+/// Callback run on an element's results after it matches, given the input and the
+/// match start. Returns the results to use instead, or a ParseException to reject
+/// the match.
+pub type ActionFn = dyn for<'a> Fn(&'a str, usize, ParseResults<'a>) -> Result<ParseResults<'a>, ParseException>
+    + Send
+    + Sync;
+
+/// Action - runs parse actions on its element's results, in order, like pyparsing's
+/// `set_parse_action`. Actions run only where tokens are built (parse_impl); match-only
+/// scans skip them.
+#[derive(Clone)]
+pub struct Action {
+    element: Arc<dyn ParserElement>,
+    actions: Vec<Arc<ActionFn>>,
+}
+
+impl Action {
+    pub fn new(element: Arc<dyn ParserElement>, actions: Vec<Arc<ActionFn>>) -> Self {
+        Self { element, actions }
+    }
+
+    pub fn element(&self) -> &Arc<dyn ParserElement> {
+        &self.element
+    }
+
+    pub fn actions(&self) -> &[Arc<ActionFn>] {
+        &self.actions
+    }
+}
+
+impl ParserElement for Action {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let start = ctx.pre_skip(loc, self.element.skip_whitespace_before());
+        let (end, mut res) = ctx.parse(&self.element, start)?;
+        for action in &self.actions {
+            res = action(ctx.input(), start, res)?;
+        }
+        Ok((end, res))
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        profile::try_match(&self.element, input, loc)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        // Results depend on the actions, so parents must call parse_impl
+        ParserKind::Complex
+    }
+
+    fn has_parse_actions(&self) -> bool {
+        true
+    }
+
+    fn skip_whitespace_before(&self) -> bool {
+        self.element.skip_whitespace_before()
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.element.try_match_segments(input, loc)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.child(&self.element)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Actions are arbitrary callbacks, which have no data form.
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Err(SpecError::new(
+            "cannot serialize an element with parse actions",
+        ))
+    }
+}
+
+/// A token transformation done in Rust, without a Python callback (see Transform).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenTransform {
+    /// Tokens become integers; a token that isn't one, or overflows 64 bits, fails.
+    ToInt,
+    /// Tokens (and integers) become floats; a token that isn't a number fails.
+    ToFloat,
+    /// One pair of matching `"` or `'` quotes is removed from each token.
+    StripQuotes,
+    /// Leading and trailing whitespace is removed from each token.
+    Strip,
+    Upcase,
+    Downcase,
+    /// All tokens are joined with the separator into one.
+    Join(Arc<str>),
+}
+
+impl TokenTransform {
+    /// The name describe() records, and from_name() reads back.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ToInt => "to_int",
+            Self::ToFloat => "to_float",
+            Self::StripQuotes => "strip_quotes",
+            Self::Strip => "strip",
+            Self::Upcase => "upcase",
+            Self::Downcase => "downcase",
+            Self::Join(_) => "join",
+        }
+    }
+
+    /// The transform called `name`, with `sep` used by join.
+    pub fn from_name(name: &str, sep: &str) -> Option<Self> {
+        Some(match name {
+            "to_int" => Self::ToInt,
+            "to_float" => Self::ToFloat,
+            "strip_quotes" => Self::StripQuotes,
+            "strip" => Self::Strip,
+            "upcase" => Self::Upcase,
+            "downcase" => Self::Downcase,
+            "join" => Self::Join(sep.into()),
+            _ => return None,
+        })
+    }
+
+    /// Transform one item; text not from `input` (or no text) reports `loc`.
+    fn apply<'a>(
+        &self,
+        input: &str,
+        loc: usize,
+        item: ParseResultItem<'a>,
+    ) -> Result<ParseResultItem<'a>, ParseException> {
+        let ParseResultItem::Token(text) = item else {
+            return Ok(match (self, item) {
+                (Self::ToFloat, ParseResultItem::Int(n)) => ParseResultItem::Float(n as f64),
+                (_, item) => item,
+            });
+        };
+        let fail = |what: &str| {
+            // A token borrowed from the input fails where it was found
+            let at = (text.as_ptr() as usize)
+                .checked_sub(input.as_ptr() as usize)
+                .filter(|&at| at <= input.len())
+                .unwrap_or(loc);
+            ParseException::new(at, format!("Expected {}, found '{}'", what, &*text))
+        };
+        Ok(match self {
+            Self::ToInt => match text.trim().parse::<i64>() {
+                Ok(n) => ParseResultItem::Int(n),
+                Err(_) => return Err(fail("an integer")),
+            },
+            Self::ToFloat => match text.trim().parse::<f64>() {
+                Ok(x) => ParseResultItem::Float(x),
+                Err(_) => return Err(fail("a number")),
+            },
+            Self::StripQuotes => {
+                let quoted = ['"', '\'']
+                    .iter()
+                    .find_map(|&q| text.strip_prefix(q).and_then(|rest| rest.strip_suffix(q)));
+                match quoted {
+                    Some(inner) => ParseResultItem::Token(inner.to_string().into()),
+                    None => ParseResultItem::Token(text),
+                }
+            }
+            Self::Strip => ParseResultItem::Token(text.trim().to_string().into()),
+            Self::Upcase => ParseResultItem::Token(text.to_uppercase().into()),
+            Self::Downcase => ParseResultItem::Token(text.to_lowercase().into()),
+            Self::Join(_) => ParseResultItem::Token(text),
+        })
+    }
+}
+
+/// Transform - applies a TokenTransform to its element's tokens (and named values)
+/// after it matches, entirely in Rust, so it works in batch and parallel parsing and
+/// survives pickling. Like actions, it runs only where tokens are built: match-only
+/// scans accept text whose conversion would fail.
+#[derive(Clone)]
+pub struct Transform {
+    element: Arc<dyn ParserElement>,
+    transform: TokenTransform,
+}
+
+impl Transform {
+    pub fn new(element: Arc<dyn ParserElement>, transform: TokenTransform) -> Self {
+        Self { element, transform }
+    }
+
+    pub fn transform(&self) -> &TokenTransform {
+        &self.transform
+    }
+}
+
+impl ParserElement for Transform {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let start = ctx.pre_skip(loc, self.element.skip_whitespace_before());
+        let (end, res) = ctx.parse(&self.element, start)?;
+        let input = ctx.input();
+        if let TokenTransform::Join(sep) = &self.transform {
+            let mut joined = String::new();
+            for (i, item) in res.items().iter().enumerate() {
+                if i > 0 {
+                    joined.push_str(sep);
+                }
+                match item {
+                    ParseResultItem::Token(text) => joined.push_str(text),
+                    ParseResultItem::Int(n) => joined.push_str(&n.to_string()),
+                    ParseResultItem::Float(x) => joined.push_str(&x.to_string()),
+                    ParseResultItem::Group(_) | ParseResultItem::Object(_) => {
+                        return Err(ParseException::new(start, "join: tokens must be text"))
+                    }
+                }
+            }
+            let mut joined_res = ParseResults::from_token(joined);
+            joined_res.adopt_named(res);
+            return Ok((end, joined_res));
+        }
+        let res = res.try_map_items(|item| self.transform.apply(input, start, item))?;
+        Ok((end, res))
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        profile::try_match(&self.element, input, loc)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    fn skip_whitespace_before(&self) -> bool {
+        self.element.skip_whitespace_before()
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.element.try_match_segments(input, loc)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.child(&self.element)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let sep = match &self.transform {
+            TokenTransform::Join(sep) => sep.clone(),
+            _ => "".into(),
+        };
+        Ok(SpecNode::new(
+            "Transform",
+            vec![
+                spec.child(&self.element)?,
+                self.transform.name().into(),
+                SpecValue::Str(sep),
+            ],
+        ))
+    }
+}
+
+/// Predicate over a match's tokens; `(input, loc, tokens)` like an action.
+pub type ConditionFn = dyn Fn(&str, usize, &ParseResults) -> bool + Send + Sync;
+
+/// Condition - rejects its element's match when the predicate returns false, like
+/// pyparsing's `add_condition`. Unlike actions, conditions also gate match-only
+/// scans, so alternatives and searches move past rejected matches.
+#[derive(Clone)]
+pub struct Condition {
+    element: Arc<dyn ParserElement>,
+    predicate: Arc<ConditionFn>,
+    message: Arc<str>,
+}
+
+impl Condition {
+    pub fn new(
+        element: Arc<dyn ParserElement>,
+        predicate: Arc<ConditionFn>,
+        message: impl Into<Arc<str>>,
+    ) -> Self {
+        Self {
+            element,
+            predicate,
+            message: message.into(),
+        }
+    }
+}
+
+impl ParserElement for Condition {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let start = ctx.pre_skip(loc, self.element.skip_whitespace_before());
+        let (end, res) = ctx.parse(&self.element, start)?;
+        if !(self.predicate)(ctx.input(), start, &res) {
+            return Err(ParseException::new(start, self.message.to_string()));
+        }
+        Ok((end, res))
+    }
+
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        // Match exactly where the element does, then build tokens for the predicate
+        let end = profile::try_match(&self.element, input, loc)?;
+        let mut ctx = ParseContext::new(input);
+        let (_, res) = self.element.parse_impl(&mut ctx, loc).ok()?;
+        (self.predicate)(input, loc, &res).then_some(end)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        self.element.parser_kind()
+    }
+
+    fn has_parse_actions(&self) -> bool {
+        // The predicate calls back into Python
+        true
+    }
+
+    fn skip_whitespace_before(&self) -> bool {
+        self.element.skip_whitespace_before()
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        let end = self.element.try_match_segments(input, loc)?;
+        let text = input.slice(0, end);
+        self.try_match_at(&text, loc)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.child(&self.element)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Predicates are arbitrary callbacks, which have no data form.
+    fn describe(&self, _spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Err(SpecError::new(
+            "cannot serialize an element with conditions",
+        ))
+    }
+}
+
+/// What a traced element did, reported to its debug hook.
+pub enum DebugEvent<'r, 'a> {
+    /// About to match at `loc`, past any whitespace
+    Try { loc: usize },
+    Match {
+        start: usize,
+        end: usize,
+        tokens: &'r ParseResults<'a>,
+    },
+    Fail {
+        loc: usize,
+        error: &'r ParseException,
+    },
+}
+
+/// Debug hook; `(input, element name, event)`.
+pub type DebugFn = dyn Fn(&str, &str, DebugEvent<'_, '_>) + Send + Sync;
+
+/// DebugTrace - reports each match attempt of its element, and its outcome, to a
+/// hook, like pyparsing's `set_debug`. A disabled trace is transparent.
+#[derive(Clone)]
+pub struct DebugTrace {
+    element: Arc<dyn ParserElement>,
+    name: String,
+    hook: Option<Arc<DebugFn>>,
+}
+
+impl DebugTrace {
+    /// Trace `element` through `hook`, or not at all when `hook` is None.
+    pub fn new(element: Arc<dyn ParserElement>, hook: Option<Arc<DebugFn>>) -> Self {
+        Self {
+            name: element.name(),
+            element,
+            hook,
+        }
+    }
+
+    pub fn element(&self) -> &Arc<dyn ParserElement> {
+        &self.element
+    }
+}
+
+impl ParserElement for DebugTrace {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let Some(hook) = &self.hook else {
+            return ctx.parse(&self.element, loc);
+        };
+        let start = ctx.pre_skip(loc, self.element.skip_whitespace_before());
+        hook(ctx.input(), &self.name, DebugEvent::Try { loc: start });
+        let result = ctx.parse(&self.element, start);
+        let event = match &result {
+            Ok((end, tokens)) => DebugEvent::Match {
+                start,
+                end: *end,
+                tokens,
+            },
+            Err(error) => DebugEvent::Fail { loc: start, error },
+        };
+        hook(ctx.input(), &self.name, event);
+        result
+    }
+
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        if self.hook.is_none() {
+            return profile::try_match(&self.element, input, loc);
+        }
+        // Traced matches report their tokens too
+        let mut ctx = ParseContext::new(input);
+        self.parse_impl(&mut ctx, loc).ok().map(|(end, _)| end)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        self.element.parser_kind()
+    }
+
+    fn has_parse_actions(&self) -> bool {
+        // The hook calls back into Python
+        self.hook.is_some() || self.element.has_parse_actions()
+    }
+
+    fn skip_whitespace_before(&self) -> bool {
+        self.element.skip_whitespace_before()
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if self.hook.is_none() {
+            return self.element.try_match_segments(input, loc);
+        }
+        window_match(self, input, loc)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.child(&self.element)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: element, whether tracing is on (the hook is supplied on rebuild).
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let params = vec![spec.child(&self.element)?, self.hook.is_some().into()];
+        Ok(SpecNode::new("DebugTrace", params))
+    }
+}
+
+/// WhitespaceScope - changes whitespace skipping before its element and everything
+/// nested in it, down to the next scope: the characters skipped
+/// (`set_whitespace_chars`) or whether to skip at all (`leave_whitespace` /
+/// `ignore_whitespace`, like pyparsing).
+#[derive(Clone)]
+pub struct WhitespaceScope {
+    element: Arc<dyn ParserElement>,
+    /// Characters skipped within the scope; None keeps the enclosing set
+    whitespace: Option<WhitespaceChars>,
+    /// Whether elements within the scope skip whitespace; None keeps the enclosing setting
+    skip: Option<bool>,
+    /// Whether to skip whitespace before the element; None leaves it to the scope
+    /// and the element
+    leading: Option<bool>,
+    /// Expressions skipped along with whitespace, in addition to the enclosing ones
+    ignore: Vec<Arc<dyn ParserElement>>,
+}
+
+impl WhitespaceScope {
+    /// Skip `whitespace` instead of the enclosing set.
+    pub fn new(element: Arc<dyn ParserElement>, whitespace: WhitespaceChars) -> Self {
+        Self {
+            element,
+            whitespace: Some(whitespace),
+            skip: None,
+            leading: None,
+            ignore: Vec::new(),
+        }
+    }
+
+    /// Don't skip whitespace before the element, nor, if `recursive`, within it.
+    pub fn leave(element: Arc<dyn ParserElement>, recursive: bool) -> Self {
+        Self {
+            element,
+            whitespace: None,
+            skip: recursive.then_some(false),
+            leading: Some(false),
+            ignore: Vec::new(),
+        }
+    }
+
+    /// Skip whitespace before the element, and, if `recursive`, within it, even
+    /// inside a Combine or a `leave` scope.
+    pub fn ignore(element: Arc<dyn ParserElement>, recursive: bool) -> Self {
+        Self {
+            element,
+            whitespace: None,
+            skip: recursive.then_some(true),
+            leading: Some(true),
+            ignore: Vec::new(),
+        }
+    }
+
+    /// Skip matches of `exprs` (e.g. comments) before the element and within it,
+    /// wherever whitespace is skipped.
+    pub fn ignoring(element: Arc<dyn ParserElement>, exprs: Vec<Arc<dyn ParserElement>>) -> Self {
+        Self {
+            element,
+            whitespace: None,
+            skip: None,
+            leading: None,
+            ignore: exprs,
+        }
+    }
+
+    /// A scope with every setting given, as reported by `describe`.
+    pub fn from_parts(
+        element: Arc<dyn ParserElement>,
+        whitespace: Option<WhitespaceChars>,
+        skip: Option<bool>,
+        leading: Option<bool>,
+        ignore: Vec<Arc<dyn ParserElement>>,
+    ) -> Self {
+        Self {
+            element,
+            whitespace,
+            skip,
+            leading,
+            ignore,
+        }
+    }
+
+    /// Whether nested elements see the same settings as outside the scope, so
+    /// matching needs no context.
+    fn is_transparent(&self) -> bool {
+        self.whitespace.is_none()
+            && self.skip.is_none()
+            && self.leading != Some(false)
+            && self.ignore.is_empty()
+    }
+}
+
+impl ParserElement for WhitespaceScope {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let outer_whitespace = ctx.whitespace;
+        let outer_skip = ctx.skip_whitespace;
+        if let Some(whitespace) = self.whitespace {
+            ctx.whitespace = whitespace;
+        }
+        let outer_ignore = ctx.push_ignore(&self.ignore);
+        let start = match self.leading {
+            Some(true) => ctx.skip_ws(loc),
+            Some(false) => loc,
+            None => ctx.pre_skip(loc, self.element.skip_whitespace_before()),
+        };
+        let outer_keep = match self.leading {
+            Some(false) => ctx.keep_whitespace_at(Some(start)),
+            Some(true) => ctx.keep_whitespace_at(None),
+            None => ctx.keep_whitespace_at(ctx.keeps_whitespace_at(start).then_some(start)),
+        };
+        if let Some(skip) = self.skip {
+            ctx.skip_whitespace = skip;
+        }
+        let result = ctx.parse(&self.element, start);
+        ctx.whitespace = outer_whitespace;
+        ctx.skip_whitespace = outer_skip;
+        ctx.keep_whitespace_at(outer_keep);
+        ctx.truncate_ignore(outer_ignore);
+        result
+    }
+
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        if self.is_transparent() {
+            return profile::try_match(&self.element, input, skip_ws(input, loc));
+        }
+        // Nested elements only see the settings through the context
+        let mut ctx = ParseContext::new(input);
+        self.parse_impl(&mut ctx, loc).ok().map(|(end, _)| end)
+    }
+
+    /// A Normal element's token isn't the scope's match span, which takes in the
+    /// whitespace and ignored text skipped before it.
+    fn parser_kind(&self) -> ParserKind {
+        match self.element.parser_kind() {
+            ParserKind::Normal => ParserKind::Complex,
+            kind => kind,
+        }
+    }
+
+    /// Leading whitespace is skipped in parse_impl, with this scope's settings.
+    fn skip_whitespace_before(&self) -> bool {
+        false
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn ignore_exprs(&self) -> &[Arc<dyn ParserElement>] {
+        &self.ignore
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+        self.ignore.iter().for_each(visit);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        if !self.is_transparent() {
+            return window_match(self, input, loc);
+        }
+        self.element.try_match_segments(input, input.skip_ws(loc))
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        namer.child(&self.element)
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: element, whitespace chars, skip, leading, ignored expressions.
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let whitespace = self.whitespace.map(|ws| ws.chars());
+        let params = vec![
+            spec.child(&self.element)?,
+            whitespace.as_deref().into(),
+            self.skip.into(),
+            self.leading.into(),
+            spec.children(&self.ignore)?,
+        ];
+        Ok(SpecNode::new("WhitespaceScope", params))
+    }
+}
+
+/// Suppress - matches but doesn't add to results
+#[derive(Clone)]
+pub struct Suppress {
+    element: Arc<dyn ParserElement>,
+}
+
+impl Suppress {
+    pub fn new(element: Arc<dyn ParserElement>) -> Self {
+        Self { element }
+    }
+}
+
+impl ParserElement for Suppress {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        // Back-references and ignored expressions need the context
+        if self.element.has_backref() || ctx.has_ignore() {
+            let (new_loc, _) = ctx.parse(&self.element, loc)?;
+            return Ok((new_loc, ParseResults::new()));
+        }
+        // Use try_match_at to avoid creating ParseResults from inner element
+        match profile::try_match(&self.element, ctx.input(), loc) {
+            Some(new_loc) => Ok((new_loc, ParseResults::new())),
+            None => Err(ParseException::new(loc, "Suppress: no match")),
+        }
+    }
+
+    /// Zero-alloc match — delegates to inner element
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        profile::try_match(&self.element, input, loc)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Suppress
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.element.try_match_segments(input, loc)
+    }
+
+    fn first_chars(&self) -> Option<ByteSet> {
+        self.element.first_chars()
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("Suppress:({})", namer.child(&self.element))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new("Suppress", vec![spec.child(&self.element)?]))
+    }
+}
+
+/// Combine - joins matched tokens into a single concatenated string.
+/// Like pyparsing's Combine: `Combine(Word(alphas) + Literal("-") + Word(nums))`
+/// would produce `["abc-123"]` instead of `["abc", "-", "123"]`. Adjacent (the
+/// default) allows no whitespace between the inner elements; `join_string` goes
+/// between the joined tokens.
+#[derive(Clone)]
+pub struct Combine {
+    element: Arc<dyn ParserElement>,
+    join_string: Arc<str>,
+    adjacent: bool,
+}
+
+impl Combine {
+    pub fn new(element: Arc<dyn ParserElement>) -> Self {
+        Self {
+            element,
+            join_string: "".into(),
+            adjacent: true,
+        }
+    }
+
+    /// Put `join_string` between the joined tokens.
+    pub fn with_join_string(mut self, join_string: &str) -> Self {
+        self.join_string = join_string.into();
+        self
+    }
+
+    /// Whether the inner elements must be back to back; when not, they skip
+    /// whitespace as usual and only their tokens are joined.
+    pub fn with_adjacent(mut self, adjacent: bool) -> Self {
+        self.adjacent = adjacent;
+        self
+    }
+
+    /// The tokens of `items` joined, groups flattened, or None when the matched
+    /// text already spells them (the common case, kept as a zero-copy slice) or
+    /// one isn't text (a parse action's object, which can't be joined). Adjacent
+    /// Combine keeps whitespace an inner element was let skip (ignore_whitespace()).
+    fn joined(&self, items: &[ParseResultItem<'_>], matched: &str) -> Option<String> {
+        let mut spelled = Spelling {
+            text: matched,
+            sep: &self.join_string,
+            keep_gaps: self.adjacent && self.join_string.is_empty(),
+            pos: 0,
+            first: true,
+        };
+        if spelled.spells(items) && spelled.pos == matched.len() {
+            return None;
+        }
+        let mut out = String::with_capacity(matched.len());
+        let mut first = true;
+        push_tokens(items, &self.join_string, &mut out, &mut first)?;
+        Some(out)
+    }
+}
+
+/// Progress of checking that tokens spell out the text Combine matched.
+struct Spelling<'t> {
+    text: &'t str,
+    sep: &'t str,
+    /// Let whitespace in the text separate tokens
+    keep_gaps: bool,
+    pos: usize,
+    first: bool,
+}
+
+impl Spelling<'_> {
+    /// Whether the text tokens of `items`, groups flattened and `sep` between them,
+    /// continue the text at `pos`, advancing it past them.
+    fn spells(&mut self, items: &[ParseResultItem<'_>]) -> bool {
+        items.iter().all(|item| {
+            let token = match item {
+                ParseResultItem::Token(token) => &**token,
+                ParseResultItem::Group(inner) => return self.spells(inner.items()),
+                _ => return false,
+            };
+            if !std::mem::replace(&mut self.first, false) {
+                if self.keep_gaps {
+                    let rest = &self.text[self.pos..];
+                    self.pos += rest.len() - rest.trim_start().len();
+                }
+                if !self.text[self.pos..].starts_with(self.sep) {
+                    return false;
+                }
+                self.pos += self.sep.len();
+            }
+            if !self.text[self.pos..].starts_with(token) {
+                return false;
+            }
+            self.pos += token.len();
+            true
+        })
+    }
+}
+
+/// Append the tokens of `items`, groups flattened, to `out` with `sep` between
+/// them. None when one isn't text or a number.
+fn push_tokens(
+    items: &[ParseResultItem<'_>],
+    sep: &str,
+    out: &mut String,
+    first: &mut bool,
+) -> Option<()> {
+    for item in items {
+        if let ParseResultItem::Group(inner) = item {
+            push_tokens(inner.items(), sep, out, first)?;
+            continue;
+        }
+        if !std::mem::replace(first, false) {
+            out.push_str(sep);
+        }
+        match item {
+            ParseResultItem::Token(token) => out.push_str(token),
+            ParseResultItem::Int(n) => out.push_str(&n.to_string()),
+            ParseResultItem::Float(x) => out.push_str(&x.to_string()),
+            ParseResultItem::Group(_) | ParseResultItem::Object(_) => return None,
+        }
+    }
+    Some(())
+}
+
+impl ParserElement for Combine {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        // Adjacent Combine disables whitespace skipping for its inner elements (like
+        // pyparsing's leave_whitespace)
+        let old_skip = ctx.skip_whitespace;
+        if self.adjacent {
+            ctx.skip_whitespace = false;
+        }
+        let result = ctx.parse(&self.element, loc);
+        ctx.skip_whitespace = old_skip;
+        let (new_loc, res) = result?;
+        // Usually the tokens are exactly the matched text, so slice the input instead
+        // of joining; suppressed or rewritten tokens and a join string need a join
+        let matched = &ctx.input()[loc..new_loc];
+        let mut out = match self.joined(res.items(), matched) {
+            Some(joined) => ParseResults::from_token(joined),
+            None => ParseResults::from_single(matched),
+        };
+        out.adopt_named(res);
+        Ok((new_loc, out))
+    }
+
+    /// The token may differ from the matched text (suppressed parts, a join string).
+    fn parser_kind(&self) -> ParserKind {
+        ParserKind::Complex
+    }
+
+    /// Adjacent Combine must use parse_impl for matching to correctly disable whitespace
+    /// skipping. Without this, try_match_at would delegate to And's try_match_at which
+    /// skips whitespace between elements, causing false positive matches in search_string.
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        if !self.adjacent {
+            return profile::try_match(&self.element, input, loc);
+        }
+        let mut ctx = ParseContext::new(input);
+        self.parse_impl(&mut ctx, loc).ok().map(|(end, _)| end)
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    /// Inner elements are emitted back to back, as adjacent Combine requires.
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        if !self.adjacent {
+            return gen.descend(self.element.as_ref());
+        }
+        let old_adjacent = gen.adjacent;
+        gen.adjacent = true;
+        let result = gen.descend(self.element.as_ref());
+        gen.adjacent = old_adjacent;
+        result
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("Combine:({})", namer.child(&self.element))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        Ok(SpecNode::new(
+            "Combine",
+            vec![
+                spec.child(&self.element)?,
+                SpecValue::Str(self.join_string.clone()),
+                self.adjacent.into(),
+            ],
+        ))
+    }
+}
+
+/// OriginalTextFor - replaces the inner expression's tokens with the exact input
+/// slice it covered, including any interior whitespace.
+/// Unlike Combine, whitespace skipping between inner elements stays enabled.
+/// With `as_string` false, the results are the start and end offsets instead.
+#[derive(Clone)]
+pub struct OriginalTextFor {
+    element: Arc<dyn ParserElement>,
+    as_string: bool,
+}
+
+impl OriginalTextFor {
+    pub fn new(element: Arc<dyn ParserElement>) -> Self {
+        Self {
+            element,
+            as_string: true,
+        }
+    }
+
+    /// Report `[start, end]` offsets of the match rather than its text.
+    pub fn with_locations(element: Arc<dyn ParserElement>) -> Self {
+        Self {
+            element,
+            as_string: false,
+        }
+    }
+}
+
+impl ParserElement for OriginalTextFor {
+    fn parse_impl<'a>(&self, ctx: &mut ParseContext<'a>, loc: usize) -> ParseResult<'a> {
+        let (new_loc, _res) = ctx.parse(&self.element, loc)?;
+        if self.as_string {
+            return Ok((
+                new_loc,
+                ParseResults::from_single(&ctx.input()[loc..new_loc]),
+            ));
+        }
+        let mut res = ParseResults::from_token(loc.to_string());
+        res.extend(ParseResults::from_token(new_loc.to_string()));
+        Ok((new_loc, res))
+    }
+
+    #[inline]
+    fn try_match_at(&self, input: &str, loc: usize) -> Option<usize> {
+        profile::try_match(&self.element, input, loc)
+    }
+
+    fn parser_kind(&self) -> ParserKind {
+        if self.as_string {
+            ParserKind::Normal
+        } else {
+            ParserKind::Complex
+        }
+    }
+
+    fn has_backref(&self) -> bool {
+        self.element.has_backref()
+    }
+
+    fn visit_children(&self, visit: &mut dyn FnMut(&Arc<dyn ParserElement>)) {
+        visit(&self.element);
+    }
+
+    fn try_match_segments(&self, input: &Segments<'_>, loc: usize) -> Option<usize> {
+        self.element.try_match_segments(input, loc)
+    }
+
+    fn generate(&self, gen: &mut Generator) -> Result<(), GenerateError> {
+        gen.descend(self.element.as_ref())
+    }
+
+    fn default_name(&self, namer: &mut Namer) -> String {
+        format!("OriginalTextFor:({})", namer.child(&self.element))
+    }
+
+    fn copy(&self) -> Arc<dyn ParserElement> {
+        Arc::new(self.clone())
+    }
+
+    /// Parameters: element, as_string.
+    fn describe(&self, spec: &mut SpecWriter) -> Result<SpecNode, SpecError> {
+        let params = vec![spec.child(&self.element)?, self.as_string.into()];
+        Ok(SpecNode::new("OriginalTextFor", params))
     }
 }
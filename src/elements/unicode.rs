@@ -0,0 +1,118 @@
+use crate::elements::chars::CharSet;
+
+/// A named range of Unicode code points and the character strings drawn from it,
+/// like pyparsing's `pyparsing_unicode` sets. Each string lists the members of a
+/// Unicode property that fall in the ranges, in code point order.
+pub struct UnicodeSet {
+    pub name: &'static str,
+    /// Inclusive code point ranges
+    pub ranges: &'static [(u32, u32)],
+}
+
+/// The sets, by name.
+pub const UNICODE_SETS: &[UnicodeSet] = &[
+    UnicodeSet {
+        name: "Latin1",
+        ranges: &[(0x0020, 0x007E), (0x00A0, 0x00FF)],
+    },
+    UnicodeSet {
+        name: "LatinA",
+        ranges: &[(0x0100, 0x017F)],
+    },
+    UnicodeSet {
+        name: "LatinB",
+        ranges: &[(0x0180, 0x024F)],
+    },
+    UnicodeSet {
+        name: "Greek",
+        ranges: &[(0x0370, 0x03FF), (0x1F00, 0x1FFF)],
+    },
+    UnicodeSet {
+        name: "Cyrillic",
+        ranges: &[
+            (0x0400, 0x052F),
+            (0x1C80, 0x1C88),
+            (0x2DE0, 0x2DFF),
+            (0xA640, 0xA69F),
+        ],
+    },
+    UnicodeSet {
+        name: "Hebrew",
+        ranges: &[(0x0591, 0x05F4), (0xFB1D, 0xFB4F)],
+    },
+    UnicodeSet {
+        name: "Arabic",
+        ranges: &[(0x0600, 0x06FF), (0x0750, 0x077F)],
+    },
+    UnicodeSet {
+        name: "Devanagari",
+        ranges: &[(0x0900, 0x097F), (0xA8E0, 0xA8FF)],
+    },
+    UnicodeSet {
+        name: "Thai",
+        ranges: &[(0x0E01, 0x0E3A), (0x0E3F, 0x0E5B)],
+    },
+    UnicodeSet {
+        name: "CJK",
+        ranges: &[
+            (0x3000, 0x303F),
+            (0x3040, 0x309F),
+            (0x30A0, 0x30FF),
+            (0x3400, 0x4DBF),
+            (0x4E00, 0x9FFF),
+            (0xAC00, 0xD7AF),
+            (0xF900, 0xFAFF),
+            (0xFF00, 0xFFEF),
+            (0x20000, 0x2A6DF),
+        ],
+    },
+    UnicodeSet {
+        name: "BasicMultilingualPlane",
+        ranges: &[(0x0020, 0xFFFF)],
+    },
+];
+
+impl UnicodeSet {
+    pub fn by_name(name: &str) -> Option<&'static UnicodeSet> {
+        UNICODE_SETS.iter().find(|set| set.name == name)
+    }
+
+    /// Letters.
+    pub fn alphas(&self) -> String {
+        self.members(r"\p{L}")
+    }
+
+    /// Decimal digits.
+    pub fn nums(&self) -> String {
+        self.members(r"\p{Nd}")
+    }
+
+    pub fn alphanums(&self) -> String {
+        self.alphas() + &self.nums()
+    }
+
+    /// Characters that may start an identifier, `_` included.
+    pub fn identchars(&self) -> String {
+        self.members(r"\p{XID_Start}_")
+    }
+
+    /// Characters that may continue an identifier.
+    pub fn identbodychars(&self) -> String {
+        self.members(r"\p{XID_Continue}")
+    }
+
+    /// Everything but whitespace and control, format and unassigned characters.
+    pub fn printables(&self) -> String {
+        self.members(r"^\s\p{C}")
+    }
+
+    /// The characters in the ranges that belong to the class `spec`.
+    fn members(&self, spec: &str) -> String {
+        let class = CharSet::from_class(spec).expect("built-in class specs are valid");
+        self.ranges
+            .iter()
+            .flat_map(|&(lo, hi)| (lo..=hi).filter_map(char::from_u32))
+            .filter(|&c| class.contains_char(c))
+            .collect()
+    }
+}
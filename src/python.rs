@@ -0,0 +1,10208 @@
+//! Python bindings: the `pyparsing_rs` extension module and its classes.
+
+use pyo3::buffer::PyBuffer;
+use pyo3::create_exception;
+use pyo3::exceptions::PyIndexError;
+use pyo3::exceptions::PyKeyError;
+use pyo3::exceptions::PyLookupError;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::exceptions::PyTimeoutError;
+use pyo3::exceptions::PyTypeError;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{
+    PyBool, PyByteArray, PyBytes, PyCapsule, PyDict, PyFloat, PyInt, PyIterator, PyList, PyString,
+    PyTuple,
+};
+use rustc_hash::FxHashMap;
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api;
+use crate::core::alternatives::parse_all_alternatives;
+use crate::core::arrow::{
+    ArrowArray, ArrowArrayStream, ArrowError, ArrowSchema, ResultColumn, ResultRow, StringColumn,
+};
+use crate::core::chunks::{
+    first_match_in_line, line_aligned_chunks, match_chunk_lines, match_file_lines, ChunkMatches,
+    LineHits, ReadCount,
+};
+use crate::core::compact::CompactResults;
+use crate::core::context::{
+    default_whitespace, recursion_limit, set_default_whitespace, set_recursion_limit, skip_ws,
+    Checkpoint, ParseContext, WhitespaceChars,
+};
+use crate::core::csv::CsvReader;
+use crate::core::exceptions::{line_at, line_col, FileError, FurthestFailure, ParseException};
+use crate::core::files::{read_text_file, TextEncoding};
+use crate::core::generate::{generate_sample, Generator};
+use crate::core::highlight::highlight_spans;
+use crate::core::incremental::{IncrementalScanner, Span};
+use crate::core::infer::{self, Piece, Sequence};
+use crate::core::json;
+use crate::core::kv::{KeyValueFormat, PairSeparator};
+use crate::core::metrics::{self, GrammarMetrics, Operation, Sample, LATENCY_BUCKETS_US};
+use crate::core::normalize::{normalize, NormalizeOptions, Normalized};
+use crate::core::packrat;
+use crate::core::parser::{
+    expect_end, freeze_grammar, has_parse_actions, is_grammar_frozen, scan_next, scan_string,
+    Namer, ParserElement, ParserKind,
+};
+use crate::core::profile;
+use crate::core::regex_cache::{self, RegexLimits};
+use crate::core::replace::{scan_rules, ReplacePattern, Replacer, Template};
+use crate::core::results::{NamedValue, OpaqueValue, ParseResultItem, ParseResults};
+use crate::core::sampling::{sample_files, sample_text};
+use crate::core::scanner::{scan_elements, FastScanner, LiteralMatch, PatternSet, ScannerOptions};
+use crate::core::segments::Segments;
+use crate::core::spec::{describe_grammar, GrammarSpec, SpecNode, SpecValue, SPEC_VERSION};
+use crate::core::stats::{char_len, for_each_match, Histogram, MatchStats};
+use crate::core::stream::{StreamMatch, StreamScanner};
+use crate::core::walk::{walk_files, Glob, WalkOptions};
+use crate::elements::backref::{
+    MatchPreviousExpr as RustMatchPreviousExpr, MatchPreviousLiteral as RustMatchPreviousLiteral,
+};
+use crate::elements::build::build_grammar;
+use crate::elements::builder;
+use crate::elements::chars::{
+    self, CharClassError, CharSet, QuotedString as RustQuotedString, RegexCaptures, RegexMatch,
+    RegexOutput, Word as RustWord,
+};
+use crate::elements::combinators::{
+    And as RustAnd, Each as RustEach, MatchFirst as RustMatchFirst,
+};
+use crate::elements::comments::Comment as RustComment;
+use crate::elements::compile::CompiledGrammar;
+use crate::elements::forward::Forward as RustForward;
+use crate::elements::literals::{
+    set_default_keyword_chars, CaselessKeyword as RustCaselessKeyword,
+    CaselessLiteral as RustCaselessLiteral, Char as RustChar, CloseMatch as RustCloseMatch,
+    Keyword as RustKeyword, Literal as RustLiteral, DEFAULT_KEYWORD_CHARS,
+};
+use crate::elements::markup::{
+    AttributeFilter, HtmlEndTag as RustHtmlEndTag, HtmlTag as RustHtmlTag,
+};
+use crate::elements::network::Ipv6Address as RustIpv6Address;
+use crate::elements::numbers::{Number as RustNumber, NumberKind};
+use crate::elements::positional::{
+    AsLine as RustAsLine, GoToColumn as RustGoToColumn, IndentedBlock as RustIndentedBlock,
+    LineEnd as RustLineEnd, LineStart as RustLineStart, RestOfLine as RustRestOfLine,
+    StringEnd as RustStringEnd, StringStart as RustStringStart,
+};
+use crate::elements::repetition::{
+    Exactly as RustExactly, OneOrMore as RustOneOrMore, Optional as RustOptional,
+    Repeat as RustRepeat, ZeroOrMore as RustZeroOrMore,
+};
+use crate::elements::structure::{
+    Action as RustAction, ActionFn, Combine as RustCombine, Condition as RustCondition,
+    ConditionFn, DebugEvent, DebugFn, DebugTrace as RustDebugTrace, Dict as RustDict,
+    Empty as RustEmpty, Group as RustGroup, HighlightClass as RustHighlightClass,
+    Located as RustLocated, Named as RustNamed, NoMatch as RustNoMatch,
+    OriginalTextFor as RustOriginalTextFor, SkipTo as RustSkipTo, Suppress as RustSuppress,
+    TokenTransform, Transform as RustTransform, WhitespaceScope as RustWhitespaceScope,
+};
+use crate::elements::unicode::{UnicodeSet, UNICODE_SETS};
+
+// ============================================================================
+// Python exception hierarchy — all subclass ValueError for compatibility
+// ============================================================================
+
+create_exception!(
+    pyparsing_rs,
+    PyparsingError,
+    PyValueError,
+    "Base class for pyparsing_rs errors."
+);
+create_exception!(
+    pyparsing_rs,
+    ParseError,
+    PyparsingError,
+    "Input did not match. Attributes: msg, loc (byte offset), lineno, col (1-based), line; \
+     mark_input_line() and explain() show where in the line."
+);
+create_exception!(
+    pyparsing_rs,
+    ParseFatalError,
+    ParseError,
+    "Input did not match past an error stop (the `-` operator); alternatives and \
+     repetitions don't backtrack over it."
+);
+create_exception!(
+    pyparsing_rs,
+    PatternError,
+    PyparsingError,
+    "Invalid grammar: bad regex, character set, length limits or element."
+);
+create_exception!(
+    pyparsing_rs,
+    FileProcessingError,
+    PyparsingError,
+    "Reading an input file failed. Attributes: path, errno, os_error, lineno."
+);
+
+/// ParseError for a failure at byte offset `loc` of `input`, with pyparsing-style
+/// position attributes (`lineno` and `col` are 1-based; `col` counts characters).
+fn parse_error_at(input: &str, loc: usize, msg: &str) -> PyErr {
+    build_parse_error(input, loc, msg, false)
+}
+
+/// parse_error_at, raising ParseFatalError if `fatal`.
+fn build_parse_error(input: &str, loc: usize, msg: &str, fatal: bool) -> PyErr {
+    let loc = loc.min(input.len());
+    let (lineno, col) = line_col(input, loc);
+    Python::attach(|py| {
+        let build = || -> PyResult<PyErr> {
+            let cls = match fatal {
+                true => py.get_type::<ParseFatalError>(),
+                false => py.get_type::<ParseError>(),
+            };
+            let exc = cls.call1((format!(
+                "{} (at char {}), (line:{}, col:{})",
+                msg, loc, lineno, col
+            ),))?;
+            exc.setattr("msg", msg)?;
+            exc.setattr("loc", loc)?;
+            exc.setattr("lineno", lineno)?;
+            exc.setattr("col", col)?;
+            exc.setattr("line", line_at(input, loc))?;
+            Ok(PyErr::from_value(exc))
+        };
+        build().unwrap_or_else(|e| e)
+    })
+}
+
+/// Line number (1-based) of byte offset `loc` in `s`, like pyparsing's lineno().
+#[pyfunction]
+#[pyo3(name = "lineno")]
+fn lineno_py(loc: usize, s: &str) -> usize {
+    line_col(s, loc).0
+}
+
+/// Column (1-based, in characters) of byte offset `loc` in `s`, like pyparsing's
+/// col(): the character after a newline is column 1.
+#[pyfunction]
+#[pyo3(name = "col")]
+fn col_py(loc: usize, s: &str) -> usize {
+    line_col(s, loc).1
+}
+
+/// The line of `s` containing byte offset `loc`, without its newline, like
+/// pyparsing's line().
+#[pyfunction]
+#[pyo3(name = "line")]
+fn line_py(loc: usize, s: &str) -> &str {
+    line_at(s, loc)
+}
+
+/// ParseError.mark_input_line(): the failing line with `marker` inserted before the
+/// failing column, like pyparsing's.
+#[pyfunction]
+#[pyo3(signature = (exc, marker = ">!<"))]
+fn parse_error_mark_input_line(exc: &Bound<'_, PyAny>, marker: &str) -> PyResult<String> {
+    let line: String = exc.getattr("line")?.extract()?;
+    let col: usize = exc.getattr("col")?.extract()?;
+    let split = line
+        .char_indices()
+        .nth(col.saturating_sub(1))
+        .map_or(line.len(), |(i, _)| i);
+    Ok(format!("{}{}{}", &line[..split], marker, &line[split..]))
+}
+
+/// ParseError.explain(): the failing line, a caret under the failing column and the
+/// error.
+#[pyfunction]
+fn parse_error_explain(exc: &Bound<'_, PyAny>) -> PyResult<String> {
+    let line: String = exc.getattr("line")?.extract()?;
+    let col: usize = exc.getattr("col")?.extract()?;
+    Ok(format!(
+        "{}\n{}^\n{}: {}",
+        line,
+        " ".repeat(col.saturating_sub(1)),
+        exc.get_type().name()?,
+        exc.str()?
+    ))
+}
+
+/// Add mark_input_line() and explain() to ParseError, bound like methods.
+fn add_parse_error_methods(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    let partialmethod = py.import("functools")?.getattr("partialmethod")?;
+    let cls = py.get_type::<ParseError>();
+    cls.setattr(
+        "mark_input_line",
+        partialmethod.call1((wrap_pyfunction!(parse_error_mark_input_line, m)?,))?,
+    )?;
+    cls.setattr(
+        "explain",
+        partialmethod.call1((wrap_pyfunction!(parse_error_explain, m)?,))?,
+    )?;
+    Ok(())
+}
+
+/// ParseError for a ParseException raised while parsing `input`.
+/// A pending parse action exception takes precedence over the parse failure it caused.
+fn parse_error(input: &str, e: &ParseException) -> PyErr {
+    match check_action_error() {
+        Err(err) => err,
+        Ok(()) => build_parse_error(input, e.loc, &e.msg, e.fatal),
+    }
+}
+
+/// FileProcessingError carrying the path and OS error of a failed file read.
+fn file_error(e: &FileError) -> PyErr {
+    Python::attach(|py| {
+        let build = || -> PyResult<PyErr> {
+            let exc = py
+                .get_type::<FileProcessingError>()
+                .call1((e.to_string(),))?;
+            exc.setattr("path", e.path.to_string_lossy())?;
+            exc.setattr("errno", e.source.raw_os_error())?;
+            exc.setattr("os_error", e.source.to_string())?;
+            exc.setattr("lineno", e.line)?;
+            Ok(PyErr::from_value(exc))
+        };
+        build().unwrap_or_else(|e| e)
+    })
+}
+
+/// Text of the file at `path` decoded with the codec named `encoding`, read with the
+/// GIL released. Bytes that don't decode raise FileProcessingError.
+fn read_file_text(py: Python<'_>, path: &std::path::Path, encoding: &str) -> PyResult<String> {
+    let encoding = TextEncoding::from_name(encoding)
+        .ok_or_else(|| PyLookupError::new_err(format!("unknown encoding: {}", encoding)))?;
+    py.detach(|| read_text_file(path, encoding))
+        .map_err(|e| file_error(&e))
+}
+
+// ============================================================================
+// Parse actions — Python callables run on tokens during parse_impl
+// ============================================================================
+
+thread_local! {
+    /// First non-parse exception raised by a parse action on this thread. The parse
+    /// sees a ParseException; the binding that started it re-raises this instead.
+    static ACTION_ERROR: RefCell<Option<PyErr>> = const { RefCell::new(None) };
+}
+
+/// Raise the pending parse action exception, if any.
+fn check_action_error() -> PyResult<()> {
+    match ACTION_ERROR.with(|slot| slot.borrow_mut().take()) {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// How many leading arguments of `(s, loc, tokens)` an action takes, like pyparsing's
+/// signature detection: 0 to 3, counting from the end (`tokens`, `loc, tokens`, ...).
+fn action_arity(f: &Bound<'_, PyAny>) -> usize {
+    let count = || -> PyResult<usize> {
+        let inspect = f.py().import("inspect")?;
+        let params = inspect
+            .call_method1("signature", (f,))?
+            .getattr("parameters")?;
+        let mut n = 0;
+        for param in params.call_method0("values")?.try_iter()? {
+            let kind = param?.getattr("kind")?.getattr("name")?;
+            match kind.extract::<String>()?.as_str() {
+                "POSITIONAL_ONLY" | "POSITIONAL_OR_KEYWORD" => n += 1,
+                "VAR_POSITIONAL" => return Ok(3),
+                _ => {}
+            }
+        }
+        Ok(n.min(3))
+    };
+    // Builtins without a signature get the full argument list
+    count().unwrap_or(3)
+}
+
+/// Token for a value returned by a parse action.
+fn py_to_result_item<'a>(value: &Bound<'_, PyAny>) -> ParseResultItem<'a> {
+    if let Ok(s) = value.cast::<PyString>() {
+        if let Ok(s) = s.to_str() {
+            return ParseResultItem::Token(s.to_string().into());
+        }
+    } else if value.is_instance_of::<PyInt>() && !value.is_instance_of::<PyBool>() {
+        if let Ok(v) = value.extract::<i64>() {
+            return ParseResultItem::Int(v);
+        }
+    } else if let Ok(f) = value.cast::<PyFloat>() {
+        return ParseResultItem::Float(f.value());
+    } else if let Ok(list) = value.cast::<PyList>() {
+        return ParseResultItem::group(list.iter().map(|v| py_to_result_item(&v)));
+    }
+    ParseResultItem::Object(OpaqueValue(Arc::new(value.clone().unbind())))
+}
+
+/// A Python callable invoked with `(s, loc, tokens)` trimmed to its arity.
+struct ParseCallback {
+    f: Py<PyAny>,
+    arity: usize,
+    /// The input as a Python str, reused while the same input is being parsed
+    cached_input: Mutex<Option<(usize, usize, Py<PyString>)>>,
+}
+
+impl ParseCallback {
+    fn new(f: &Bound<'_, PyAny>, what: &str) -> PyResult<Self> {
+        if !f.is_callable() {
+            return Err(PyValueError::new_err(format!("{} must be callable", what)));
+        }
+        Ok(Self {
+            arity: action_arity(f),
+            f: f.clone().unbind(),
+            cached_input: Mutex::new(None),
+        })
+    }
+
+    fn call<'py>(
+        &self,
+        py: Python<'py>,
+        input: &str,
+        loc: usize,
+        res: &ParseResults,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let tokens = results_to_py_object(py, res)?;
+        let f = self.f.bind(py);
+        match self.arity {
+            0 => f.call0(),
+            1 => f.call1((tokens,)),
+            2 => f.call1((loc, tokens)),
+            _ => {
+                let mut cached = self.cached_input.lock().unwrap();
+                let key = (input.as_ptr() as usize, input.len());
+                let s = match &*cached {
+                    Some((ptr, len, s)) if (*ptr, *len) == key => s.clone_ref(py),
+                    _ => {
+                        let s = PyString::new(py, input).unbind();
+                        *cached = Some((key.0, key.1, s.clone_ref(py)));
+                        s
+                    }
+                };
+                drop(cached);
+                f.call1((s, loc, tokens))
+            }
+        }
+    }
+}
+
+/// Stash a callback exception to be raised once parsing returns to Python.
+fn stash_action_error(err: PyErr) {
+    ACTION_ERROR.with(|slot| {
+        slot.borrow_mut().get_or_insert(err);
+    });
+}
+
+/// Wrap a Python callable as a parse action. It is called with `(s, loc, tokens)`
+/// trimmed to its arity; `loc` is a byte offset and `tokens` a list (ParseResults when
+/// named). Returning None keeps the tokens, a list replaces them, any other value
+/// becomes the only token. Raising ParseError rejects the match.
+fn make_action(f: &Bound<'_, PyAny>) -> PyResult<Arc<ActionFn>> {
+    if let Ok(filter) = f.cast::<PyAttributeFilter>() {
+        let filter = filter.get().inner.clone();
+        let message: Arc<str> = filter.message().into();
+        return Ok(Arc::new(
+            move |_: &str, loc: usize, res: ParseResults| match filter.matches(&res) {
+                true => Ok(res),
+                false => Err(ParseException::new(loc, message.clone())),
+            },
+        ));
+    }
+    let callback = ParseCallback::new(f, "parse actions")?;
+    Ok(Arc::new(
+        move |input: &str, loc: usize, mut res: ParseResults| {
+            Python::attach(|py| match callback.call(py, input, loc, &res) {
+                Ok(value) if value.is_none() => Ok(res),
+                Ok(value) => {
+                    let items = if let Ok(results) = value.cast::<PyParseResults>() {
+                        let tokens = results.borrow().tokens.clone_ref(py);
+                        tokens
+                            .bind(py)
+                            .iter()
+                            .map(|v| py_to_result_item(&v))
+                            .collect()
+                    } else if let Ok(list) = value.cast::<PyList>() {
+                        list.iter().map(|v| py_to_result_item(&v)).collect()
+                    } else {
+                        vec![py_to_result_item(&value)]
+                    };
+                    res.set_items(items);
+                    Ok(res)
+                }
+                Err(err) if err.is_instance_of::<ParseError>(py) => {
+                    let msg = err
+                        .value(py)
+                        .getattr("msg")
+                        .and_then(|m| m.extract::<String>())
+                        .unwrap_or_else(|_| err.to_string());
+                    Err(ParseException::new(loc, msg))
+                }
+                Err(err) => {
+                    stash_action_error(err);
+                    Err(ParseException::new(loc, "parse action raised an exception"))
+                }
+            })
+        },
+    ))
+}
+
+/// Wrap a Python callable as a condition: called like a parse action, a falsy
+/// return (or raising ParseError) rejects the match.
+fn make_condition(f: &Bound<'_, PyAny>) -> PyResult<Arc<ConditionFn>> {
+    if let Ok(filter) = f.cast::<PyAttributeFilter>() {
+        let filter = filter.get().inner.clone();
+        return Ok(Arc::new(move |_: &str, _: usize, res: &ParseResults| {
+            filter.matches(res)
+        }));
+    }
+    let callback = ParseCallback::new(f, "conditions")?;
+    Ok(Arc::new(
+        move |input: &str, loc: usize, res: &ParseResults| {
+            Python::attach(|py| {
+                match callback
+                    .call(py, input, loc, res)
+                    .and_then(|value| value.is_truthy())
+                {
+                    Ok(keep) => keep,
+                    Err(err) if err.is_instance_of::<ParseError>(py) => false,
+                    Err(err) => {
+                        stash_action_error(err);
+                        false
+                    }
+                }
+            })
+        },
+    ))
+}
+
+/// ParseAction running `actions` and then `fns` on `element`'s tokens.
+fn parse_action_element(
+    element: Arc<dyn ParserElement>,
+    mut actions: Vec<Arc<ActionFn>>,
+    fns: &Bound<'_, PyTuple>,
+) -> PyResult<PyParseAction> {
+    for f in fns.iter() {
+        actions.push(make_action(&f)?);
+    }
+    Ok(PyParseAction {
+        inner: Arc::new(RustAction::new(element, actions)),
+    })
+}
+
+/// Condition rejecting `element`'s matches for which `f` is falsy.
+fn condition_element(
+    element: Arc<dyn ParserElement>,
+    f: &Bound<'_, PyAny>,
+    message: Option<String>,
+) -> PyResult<PyCondition> {
+    let message = message.unwrap_or_else(|| match f.cast::<PyAttributeFilter>() {
+        Ok(filter) => filter.get().inner.message(),
+        Err(_) => "failed user-defined condition".to_string(),
+    });
+    Ok(PyCondition {
+        inner: Arc::new(RustCondition::new(element, make_condition(f)?, message)),
+    })
+}
+
+/// Parse `chars` as a whitespace set.
+fn whitespace_chars(chars: &str) -> PyResult<WhitespaceChars> {
+    WhitespaceChars::new(chars)
+        .ok_or_else(|| PyValueError::new_err("whitespace characters must be ASCII"))
+}
+
+// ============================================================================
+// Debug tracing — set_debug() and set_debug_actions()
+// ============================================================================
+
+/// Callables registered by set_debug_actions(); None prints the default message.
+struct DebugActions {
+    start: Option<Py<PyAny>>,
+    success: Option<Py<PyAny>>,
+    fail: Option<Py<PyAny>>,
+}
+
+static DEBUG_ACTIONS: Mutex<DebugActions> = Mutex::new(DebugActions {
+    start: None,
+    success: None,
+    fail: None,
+});
+
+/// Report a traced element's event through the debug actions. Exceptions they
+/// raise are re-raised once parsing returns to Python.
+fn debug_event(input: &str, name: &str, event: DebugEvent<'_, '_>) {
+    Python::attach(|py| {
+        if let Err(err) = report_debug_event(py, input, name, event) {
+            stash_action_error(err);
+        }
+    })
+}
+
+fn report_debug_event(
+    py: Python<'_>,
+    input: &str,
+    name: &str,
+    event: DebugEvent<'_, '_>,
+) -> PyResult<()> {
+    let action = {
+        let actions = DEBUG_ACTIONS.lock().unwrap();
+        match event {
+            DebugEvent::Try { .. } => actions.start.as_ref(),
+            DebugEvent::Match { .. } => actions.success.as_ref(),
+            DebugEvent::Fail { .. } => actions.fail.as_ref(),
+        }
+        .map(|f| f.clone_ref(py))
+    };
+    let print = |message: String| -> PyResult<()> {
+        py.import("builtins")?.getattr("print")?.call1((message,))?;
+        Ok(())
+    };
+    match (event, action) {
+        (DebugEvent::Try { loc }, Some(f)) => f.call1(py, (input, loc, name)).map(drop),
+        (DebugEvent::Try { loc }, None) => print(format!("Match {} at loc {}", name, loc)),
+        (DebugEvent::Match { start, end, tokens }, f) => {
+            let tokens = results_to_py_object(py, tokens)?;
+            match f {
+                Some(f) => f.call1(py, (input, start, end, name, tokens)).map(drop),
+                None => print(format!("Matched {} -> {}", name, tokens.repr()?)),
+            }
+        }
+        (DebugEvent::Fail { loc, error }, Some(f)) => f
+            .call1(py, (input, loc, name, error.msg.to_string()))
+            .map(drop),
+        (DebugEvent::Fail { loc, error }, None) => print(format!(
+            "Match failed {} at loc {}: {}",
+            name, loc, error.msg
+        )),
+    }
+}
+
+/// `element` traced through the debug actions, or untraced when `flag` is false.
+fn debug_trace(element: Arc<dyn ParserElement>, flag: bool) -> PyDebugTrace {
+    let hook: Option<Arc<DebugFn>> = match flag {
+        true => Some(Arc::new(debug_event)),
+        false => None,
+    };
+    PyDebugTrace {
+        inner: Arc::new(RustDebugTrace::new(element, hook)),
+    }
+}
+
+/// Callable stored for set_debug_actions(), checked up front.
+fn debug_action(f: Option<&Bound<'_, PyAny>>, what: &str) -> PyResult<Option<Py<PyAny>>> {
+    match f {
+        Some(f) if f.is_none() => Ok(None),
+        Some(f) if !f.is_callable() => {
+            Err(PyValueError::new_err(format!("{} must be callable", what)))
+        }
+        f => Ok(f.map(|f| f.clone().unbind())),
+    }
+}
+
+/// Replace the debug actions of elements with set_debug(): `start_fn(s, loc, name)`
+/// before each attempt, `success_fn(s, start, end, name, tokens)` after a match and
+/// `fail_fn(s, loc, name, message)` after a failure. Offsets are in bytes. An action
+/// left as None prints the default message.
+#[pyfunction]
+#[pyo3(signature = (start_fn = None, success_fn = None, fail_fn = None))]
+fn set_debug_actions(
+    start_fn: Option<&Bound<'_, PyAny>>,
+    success_fn: Option<&Bound<'_, PyAny>>,
+    fail_fn: Option<&Bound<'_, PyAny>>,
+) -> PyResult<()> {
+    let actions = DebugActions {
+        start: debug_action(start_fn, "start_fn")?,
+        success: debug_action(success_fn, "success_fn")?,
+        fail: debug_action(fail_fn, "fail_fn")?,
+    };
+    *DEBUG_ACTIONS.lock().unwrap() = actions;
+    Ok(())
+}
+
+// ============================================================================
+// Self-tests — run_tests()
+// ============================================================================
+
+/// Parse each test case with `element.parse_string`, like pyparsing's run_tests.
+/// `tests` is a string with one case per line, or a list of cases; blank lines and
+/// lines starting with `comment` (unless empty) are skipped, comments being printed
+/// with the next case. Returns `(all_passed, [(case, tokens or exception)])`.
+fn run_tests<'py>(
+    element: &Bound<'py, PyAny>,
+    tests: &Bound<'py, PyAny>,
+    parse_all: bool,
+    comment: &str,
+    full_dump: bool,
+    print_results: bool,
+) -> PyResult<(bool, Bound<'py, PyList>)> {
+    let py = element.py();
+    let cases: Vec<String> = match tests.cast::<PyString>() {
+        Ok(text) => text
+            .to_str()?
+            .lines()
+            .map(|l| l.trim().to_string())
+            .collect(),
+        Err(_) => tests.extract()?,
+    };
+    let print = py.import("builtins")?.getattr("print")?;
+    let out = |line: String| -> PyResult<()> {
+        if print_results {
+            print.call1((line,))?;
+        }
+        Ok(())
+    };
+    let results = PyList::empty(py);
+    let mut all_passed = true;
+    let mut comments = Vec::new();
+    for case in cases {
+        if case.trim().is_empty() {
+            continue;
+        }
+        if !comment.is_empty() && case.trim_start().starts_with(comment) {
+            comments.push(case);
+            continue;
+        }
+        for line in comments.drain(..) {
+            out(line)?;
+        }
+        out(case.clone())?;
+        match element.call_method1("parse_string", (&case, parse_all)) {
+            Ok(tokens) => {
+                out(tokens.str()?.to_string())?;
+                if let (true, Ok(named)) = (full_dump, tokens.cast::<PyParseResults>()) {
+                    if let Some(named) = named.borrow().named_dict(py) {
+                        for (name, value) in named.iter() {
+                            out(format!("- {}: {}", name, value.repr()?))?;
+                        }
+                    }
+                }
+                results.append((&case, tokens))?;
+            }
+            Err(err) => {
+                all_passed = false;
+                let exc = err.value(py);
+                if err.is_instance_of::<ParseError>(py) {
+                    let line: String = exc.getattr("line")?.extract()?;
+                    let col: usize = exc.getattr("col")?.extract()?;
+                    out(line)?;
+                    out(format!("{}^", " ".repeat(col.saturating_sub(1))))?;
+                    out(format!("FAIL: {}", exc.str()?))?;
+                } else {
+                    out(format!(
+                        "FAIL-EXCEPTION: {}: {}",
+                        exc.get_type().name()?,
+                        exc.str()?
+                    ))?;
+                }
+                results.append((&case, exc))?;
+            }
+        }
+        out(String::new())?;
+    }
+    Ok((all_passed, results))
+}
+
+/// `element` skipping `chars` as whitespace, before it and within it.
+fn whitespace_scope(element: Arc<dyn ParserElement>, chars: &str) -> PyResult<PyWhitespaceScope> {
+    Ok(PyWhitespaceScope {
+        inner: Arc::new(RustWhitespaceScope::new(element, whitespace_chars(chars)?)),
+    })
+}
+
+/// `element` skipping matches of `exprs` wherever it skips whitespace.
+fn ignore_scope(
+    element: Arc<dyn ParserElement>,
+    exprs: &Bound<'_, PyTuple>,
+) -> PyResult<PyWhitespaceScope> {
+    let exprs = exprs
+        .iter()
+        .map(|expr| extract_parser(&expr))
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(PyWhitespaceScope {
+        inner: Arc::new(RustWhitespaceScope::ignoring(element, exprs)),
+    })
+}
+
+/// Set the whitespace skipped by elements without set_whitespace_chars(), for
+/// parses started afterwards. Returns the previous set.
+#[pyfunction]
+#[pyo3(signature = (chars = " \t\n\r"))]
+fn set_default_whitespace_chars(chars: &str) -> PyResult<String> {
+    let previous = default_whitespace().chars();
+    set_default_whitespace(whitespace_chars(chars)?);
+    Ok(previous)
+}
+
+/// A Keyword identifier character argument as a class spec: a str is one already,
+/// a CharClass gives its spec.
+fn keyword_chars_arg(arg: &Bound<'_, PyAny>) -> PyResult<String> {
+    if let Ok(class) = arg.cast::<PyCharClass>() {
+        return Ok(class.get().spec.clone());
+    }
+    arg.extract::<String>()
+        .map_err(|_| PyTypeError::new_err("ident_chars must be a str or CharClass"))
+}
+
+/// Set the identifier characters (a class spec like `A-Za-z0-9_-`, or a CharClass)
+/// of Keywords created afterwards without ident_chars; None restores `A-Za-z0-9_`.
+/// Returns the previous spec.
+#[pyfunction]
+#[pyo3(name = "set_default_keyword_chars", signature = (chars = None))]
+fn set_default_keyword_chars_py(chars: Option<&Bound<'_, PyAny>>) -> PyResult<String> {
+    let spec = match chars {
+        Some(chars) => keyword_chars_arg(chars)?,
+        None => DEFAULT_KEYWORD_CHARS.to_string(),
+    };
+    set_default_keyword_chars(&spec)
+        .map(|previous| previous.to_string())
+        .map_err(|e| PatternError::new_err(e.msg))
+}
+
+// ============================================================================
+// Checkpoints — signal handling during long parses and scans
+// ============================================================================
+
+/// Bytes of progress between checkpoints; large enough that checks cost nothing.
+/// Keep set_checkpoint_interval's default in sync.
+const DEFAULT_CHECKPOINT_INTERVAL: usize = 1 << 24;
+
+static CHECKPOINT_INTERVAL: AtomicUsize = AtomicUsize::new(DEFAULT_CHECKPOINT_INTERVAL);
+static CHECKPOINT_YIELDS_GIL: AtomicBool = AtomicBool::new(false);
+
+/// Bytes of progress between checkpoints while a call's timeout_ms is running, so
+/// the clock is read often enough to stop close to the deadline.
+const TIMED_CHECKPOINT_INTERVAL: usize = 1 << 16;
+
+/// When a call given `timeout_ms` has to give up.
+#[derive(Clone, Copy)]
+struct Deadline {
+    at: Instant,
+    timeout_ms: u64,
+}
+
+impl Deadline {
+    /// TimeoutError for a call stopped at offset `loc`, which it carries as `loc`
+    /// (None for batches, which stop between inputs).
+    fn error(&self, py: Python<'_>, loc: Option<usize>) -> PyErr {
+        let at = loc.map_or(String::new(), |loc| format!(" at offset {}", loc));
+        let err = PyTimeoutError::new_err(format!("timed out after {} ms{}", self.timeout_ms, at));
+        if let Err(e) = err.value(py).setattr("loc", loc) {
+            return e;
+        }
+        err
+    }
+}
+
+thread_local! {
+    /// Deadline of the running call given `timeout_ms`, for checkpoint()
+    static DEADLINE: Cell<Option<Deadline>> = const { Cell::new(None) };
+}
+
+/// Run `call` with checkpoints aborting it `timeout_ms` from now; None leaves the
+/// enclosing call's deadline, if any, in force.
+fn with_timeout<T>(timeout_ms: Option<u64>, call: impl FnOnce() -> T) -> T {
+    let Some(timeout_ms) = timeout_ms else {
+        return call();
+    };
+    let at = Instant::now() + Duration::from_millis(timeout_ms);
+    let outer = DEADLINE.replace(Some(Deadline { at, timeout_ms }));
+    let out = call();
+    DEADLINE.set(outer);
+    out
+}
+
+/// Checkpoint that runs Python signal handlers and, if configured, briefly releases
+/// the GIL. An exception from a handler (e.g. KeyboardInterrupt), or a TimeoutError
+/// once the deadline set by with_timeout has passed, aborts the parse and is raised
+/// by check_action_error.
+fn checkpoint(py: Python<'_>) -> Option<Checkpoint<'_>> {
+    let yield_gil = CHECKPOINT_YIELDS_GIL.load(Ordering::Relaxed);
+    let deadline = DEADLINE.get();
+    let interval = match deadline {
+        Some(_) => CHECKPOINT_INTERVAL
+            .load(Ordering::Relaxed)
+            .min(TIMED_CHECKPOINT_INTERVAL),
+        None => CHECKPOINT_INTERVAL.load(Ordering::Relaxed),
+    };
+    Some(Checkpoint::new(interval, move |pos| {
+        let checked = py.check_signals().and_then(|()| match deadline {
+            Some(deadline) if Instant::now() >= deadline.at => Err(deadline.error(py, Some(pos))),
+            _ => Ok(()),
+        });
+        match checked {
+            Ok(()) => {
+                if yield_gil {
+                    py.detach(std::thread::yield_now);
+                }
+                true
+            }
+            Err(err) => {
+                stash_action_error(err);
+                false
+            }
+        }
+    }))
+}
+
+/// Whether a scan of `s` has to take the generic path, which goes through
+/// checkpoints and the profiling hooks: a timeout is running, `s` is long enough
+/// for a checkpoint to fall due, or profiling is on.
+fn needs_generic_scan(s: &str) -> bool {
+    DEADLINE.get().is_some()
+        || s.len() >= CHECKPOINT_INTERVAL.load(Ordering::Relaxed)
+        || profile::enabled()
+}
+
+/// Check for signals (and optionally let other threads run) every `interval` bytes
+/// of progress in parse_string, search_string, scan_string and transform_string,
+/// or every 64 KiB while their timeout_ms is running.
+#[pyfunction]
+#[pyo3(signature = (interval = 16777216, yield_gil = false))]
+fn set_checkpoint_interval(interval: usize, yield_gil: bool) -> PyResult<()> {
+    if interval == 0 {
+        return Err(PyValueError::new_err("interval must be positive"));
+    }
+    CHECKPOINT_INTERVAL.store(interval, Ordering::Relaxed);
+    CHECKPOINT_YIELDS_GIL.store(yield_gil, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Limit how deeply element parses may nest (`limit`, default 3000), so a
+/// recursive grammar fed deeply nested input fails with a ParseException naming
+/// the element instead of overflowing the stack and aborting the interpreter.
+/// Match-only checks (search_string_count, matches, ...) treat the limit as no
+/// match. Applies to parses started afterwards; returns the previous limit.
+#[pyfunction]
+#[pyo3(name = "set_recursion_limit", signature = (limit = 3000))]
+fn set_recursion_limit_py(limit: usize) -> PyResult<usize> {
+    if limit == 0 {
+        return Err(PyValueError::new_err("limit must be positive"));
+    }
+    let previous = recursion_limit();
+    set_recursion_limit(limit);
+    Ok(previous)
+}
+
+// ============================================================================
+// Metrics — opt-in counters for parse activity
+// ============================================================================
+
+/// Label for `parser` in metrics: its results name, e.g. `grammar("ipv4")`, or else
+/// its `str()`, e.g. `W:(0-9)`.
+fn metrics_label(parser: &dyn ParserElement) -> Cow<'_, str> {
+    match parser.results_name() {
+        Some(name) => Cow::Borrowed(name),
+        None => Cow::Owned(parser.default_name(&mut Namer::default())),
+    }
+}
+
+/// Start timing a call, if metrics are enabled.
+#[inline(always)]
+fn metrics_start() -> Option<Instant> {
+    metrics::enabled().then(Instant::now)
+}
+
+/// Record a call timed by metrics_start under the label `label` gives, which is
+/// only built when metrics are enabled.
+#[inline(always)]
+fn metrics_finish<'a>(
+    started: Option<Instant>,
+    label: impl FnOnce() -> Cow<'a, str>,
+    op: Operation,
+    ok: bool,
+    bytes: usize,
+    matches: usize,
+) {
+    if let Some(started) = started {
+        let elapsed = started.elapsed();
+        metrics::record(
+            &label(),
+            &[Sample {
+                op,
+                ok,
+                bytes,
+                matches,
+                elapsed,
+            }],
+        );
+    }
+}
+
+/// Run one parse_string call on `parser`, aborting it `timeout_ms` from now, and
+/// record it when metrics are enabled. The tokens are returned as ParseResults.
+#[inline(always)]
+fn metered_parse<'py>(
+    parser: &dyn ParserElement,
+    s: &str,
+    timeout_ms: Option<u64>,
+    call: impl FnOnce() -> PyResult<Bound<'py, PyAny>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let started = metrics_start();
+    let out = with_timeout(timeout_ms, call).and_then(into_parse_results);
+    metrics_finish(
+        started,
+        || metrics_label(parser),
+        Operation::Parse,
+        out.is_ok(),
+        s.len(),
+        0,
+    );
+    out
+}
+
+/// Run one search_string or scan_string call on `parser`, aborting it `timeout_ms`
+/// from now, and record it when metrics are enabled. Each match's tokens are
+/// returned as ParseResults.
+#[inline(always)]
+fn metered_scan<'py>(
+    parser: &dyn ParserElement,
+    s: &str,
+    timeout_ms: Option<u64>,
+    call: impl FnOnce() -> PyResult<Bound<'py, PyList>>,
+) -> PyResult<Bound<'py, PyList>> {
+    let started = metrics_start();
+    let out = with_timeout(timeout_ms, call).and_then(matches_as_results);
+    let matches = out.as_ref().map_or(0, |list| list.len());
+    metrics_finish(
+        started,
+        || metrics_label(parser),
+        Operation::Scan,
+        out.is_ok(),
+        s.len(),
+        matches,
+    );
+    out
+}
+
+thread_local! {
+    /// Samples of the parse_batch call metered on this thread, one per input, added
+    /// by the batch paths as they match; None when no call is metered
+    static BATCH_SAMPLES: RefCell<Option<Vec<Sample>>> = const { RefCell::new(None) };
+}
+
+/// Run one parse_batch call on `parser`, recording a parse per input when metrics are
+/// enabled: its outcome in the batch and the time spent matching it.
+fn metered_batch<'py, T>(
+    parser: &dyn ParserElement,
+    inputs: &Bound<'py, PyList>,
+    call: impl FnOnce() -> PyResult<T>,
+) -> PyResult<T> {
+    if !metrics::enabled() {
+        return call();
+    }
+    let outer = BATCH_SAMPLES.replace(Some(Vec::with_capacity(inputs.len())));
+    let out = call();
+    let samples = BATCH_SAMPLES.replace(outer).unwrap_or_default();
+    metrics::record(&metrics_label(parser), &samples);
+    out
+}
+
+/// Whether the parse_batch call running on this thread is metered.
+fn batch_metered() -> bool {
+    BATCH_SAMPLES.with(|samples| samples.borrow().is_some())
+}
+
+/// Add the sample of one input to the parse_batch call metered on this thread.
+fn batch_sample(ok: bool, bytes: usize, elapsed: Duration) {
+    BATCH_SAMPLES.with(|samples| {
+        if let Some(samples) = samples.borrow_mut().as_mut() {
+            samples.push(Sample {
+                op: Operation::Parse,
+                ok,
+                bytes,
+                matches: 0,
+                elapsed,
+            });
+        }
+    });
+}
+
+/// Per-input result of a parse_batch path, read as a match or a miss for metrics.
+trait BatchOutcome {
+    fn matched(&self) -> bool;
+}
+
+impl BatchOutcome for bool {
+    fn matched(&self) -> bool {
+        *self
+    }
+}
+
+impl<T> BatchOutcome for Option<T> {
+    fn matched(&self) -> bool {
+        self.is_some()
+    }
+}
+
+/// `parse_one`, also timing each call when a parse_batch call is metered.
+fn timed_parse<'s, T>(
+    metered: bool,
+    parse_one: impl Fn(&'s str) -> T + Sync,
+) -> impl Fn(&'s str) -> (T, Option<Duration>) + Sync {
+    move |s| {
+        let started = metered.then(Instant::now);
+        let out = parse_one(s);
+        (out, started.map(|started| started.elapsed()))
+    }
+}
+
+/// Counters as a plain dict, for bridging to a metrics system.
+fn metrics_to_py<'py>(py: Python<'py>, m: &GrammarMetrics) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("parses", m.parses)?;
+    dict.set_item("scans", m.scans)?;
+    dict.set_item("files", m.files)?;
+    dict.set_item("failures", m.failures)?;
+    dict.set_item("matches", m.matches)?;
+    dict.set_item("bytes", m.bytes)?;
+    // Cumulative buckets keyed by upper bound, like Prometheus `le` labels
+    let buckets = PyDict::new(py);
+    let mut cumulative = 0;
+    for (i, n) in m.latency_buckets.iter().enumerate() {
+        cumulative += n;
+        match LATENCY_BUCKETS_US.get(i) {
+            Some(bound) => buckets.set_item(bound.to_string(), cumulative)?,
+            None => buckets.set_item("+Inf", cumulative)?,
+        }
+    }
+    dict.set_item("latency_us_buckets", buckets)?;
+    dict.set_item("latency_us_sum", m.latency_sum_us)?;
+    Ok(dict)
+}
+
+/// Start (or stop) recording parse_string, parse_batch, search_string, scan_string
+/// and file calls. Grammars are labelled by their results name, or else by their
+/// `str()`.
+#[pyfunction]
+#[pyo3(signature = (enabled = true))]
+fn enable_metrics(enabled: bool) {
+    metrics::set_enabled(enabled);
+}
+
+/// `{"enabled", "totals", "grammars": {label: counters}}`; each counters dict has
+/// parses, scans, files, failures, matches, bytes, latency_us_buckets (cumulative)
+/// and latency_us_sum.
+#[pyfunction]
+fn metrics_snapshot(py: Python<'_>) -> PyResult<Bound<'_, PyDict>> {
+    let (labels, totals) = metrics::snapshot();
+    let grammars = PyDict::new(py);
+    for (label, m) in &labels {
+        grammars.set_item(label, metrics_to_py(py, m)?)?;
+    }
+    let dict = PyDict::new(py);
+    dict.set_item("enabled", metrics::enabled())?;
+    dict.set_item("totals", metrics_to_py(py, &totals)?)?;
+    dict.set_item("grammars", grammars)?;
+    Ok(dict)
+}
+
+/// Clear all recorded counters.
+#[pyfunction]
+fn reset_metrics() {
+    metrics::reset();
+}
+
+/// Start (or stop) counting calls and time per grammar element, in every thread.
+/// The element a parse or scan starts from is counted once per attempt, and every
+/// element it calls in turn.
+#[pyfunction]
+#[pyo3(signature = (enabled = true))]
+fn enable_profiling(enabled: bool) {
+    profile::set_enabled(enabled);
+}
+
+/// One dict per element counted, most total time first: name (as `str()` gives
+/// it), parse_calls, match_calls (match-only calls, as made by repetitions and
+/// scans), successes, failures, total_ns and self_ns (less the time of the
+/// elements it called).
+#[pyfunction]
+fn get_profile_stats(py: Python<'_>) -> PyResult<Bound<'_, PyList>> {
+    let list = PyList::empty(py);
+    for (elem, p) in profile::snapshot() {
+        let dict = PyDict::new(py);
+        dict.set_item("name", elem.name())?;
+        dict.set_item("parse_calls", p.parse_calls)?;
+        dict.set_item("match_calls", p.match_calls)?;
+        dict.set_item("successes", p.successes)?;
+        dict.set_item("failures", p.failures)?;
+        dict.set_item("total_ns", p.total_ns)?;
+        dict.set_item("self_ns", p.self_ns)?;
+        list.append(dict)?;
+    }
+    Ok(list)
+}
+
+/// Clear all element counters.
+#[pyfunction]
+fn reset_profile_stats() {
+    profile::reset();
+}
+
+// ============================================================================
+// Packrat and left recursion — memoized outcomes per element and position
+// ============================================================================
+
+/// Cache each element's outcome (tokens or failure) at each position within a parse,
+/// keeping at most `cache_size` (0 for no limit), oldest evicted first. Elements
+/// with parse actions are always re-parsed, so actions run as without the cache.
+/// Resets the packrat_stats() counters. Raises RuntimeError if left recursion is
+/// enabled, unless `force` (which disables it).
+#[pyfunction]
+#[pyo3(signature = (cache_size = 128, *, force = false))]
+fn enable_packrat(cache_size: usize, force: bool) -> PyResult<()> {
+    if packrat::left_recursion_enabled() {
+        if !force {
+            return Err(PyRuntimeError::new_err(
+                "left recursion is enabled; packrat can't be combined with it",
+            ));
+        }
+        packrat::set_left_recursion(false);
+    }
+    packrat::enable(cache_size);
+    Ok(())
+}
+
+/// Turn packrat caching off; see enable_packrat().
+#[pyfunction]
+fn disable_packrat() {
+    packrat::disable();
+}
+
+/// Let Forwards be left-recursive (`expr <<= expr + "+" + term | term`): a
+/// recursive call at the position a Forward started first fails, then the
+/// definition is re-parsed with the previous match until it stops lengthening.
+/// Raises RuntimeError if packrat is enabled, unless `force` (which disables it).
+#[pyfunction]
+#[pyo3(signature = (*, force = false))]
+fn enable_left_recursion(force: bool) -> PyResult<()> {
+    if packrat::enabled() {
+        if !force {
+            return Err(PyRuntimeError::new_err(
+                "packrat is enabled; left recursion can't be combined with it",
+            ));
+        }
+        packrat::disable();
+    }
+    packrat::set_left_recursion(true);
+    Ok(())
+}
+
+/// Turn left recursion off again; see enable_left_recursion().
+#[pyfunction]
+fn disable_left_recursion() {
+    packrat::set_left_recursion(false);
+}
+
+/// `{"enabled", "hits", "misses"}`: outcomes reused from the cache and element
+/// parses run, since enable_packrat().
+#[pyfunction]
+fn packrat_stats(py: Python<'_>) -> PyResult<Bound<'_, PyDict>> {
+    let (hits, misses) = packrat::stats();
+    let dict = PyDict::new(py);
+    dict.set_item("enabled", packrat::enabled())?;
+    dict.set_item("hits", hits)?;
+    dict.set_item("misses", misses)?;
+    Ok(dict)
+}
+
+// ============================================================================
+// Regex compilation — process-wide pattern cache and size limits
+// ============================================================================
+
+/// PatternError for a pattern that failed to compile, naming it when it's over
+/// the size limits.
+fn regex_error(pattern: &str, err: &regex::Error) -> PyErr {
+    PatternError::new_err(regex_cache::error_message(pattern, err))
+}
+
+/// Bound the memory a regex may take to compile (`size`, bytes of compiled
+/// program) and to search (`dfa_size`, bytes of lazy DFA cache), for Regex and
+/// batch_regex_set from now on; None restores the regex crate's default (10 MiB
+/// and 2 MiB). Patterns over the limit raise PatternError. Cached regexes are
+/// dropped. Returns the previous `(size, dfa_size)`.
+#[pyfunction]
+#[pyo3(signature = (size = None, dfa_size = None))]
+fn set_regex_limits(size: Option<usize>, dfa_size: Option<usize>) -> (usize, usize) {
+    let previous = regex_cache::limits();
+    regex_cache::set_limits(RegexLimits {
+        size: size.unwrap_or(regex_cache::DEFAULT_SIZE_LIMIT),
+        dfa_size: dfa_size.unwrap_or(regex_cache::DEFAULT_DFA_SIZE_LIMIT),
+    });
+    (previous.size, previous.dfa_size)
+}
+
+/// `{"hits", "misses", "size", "capacity", "size_limit", "dfa_size_limit"}`: compiles
+/// answered from the regex cache and run, since clear_regex_cache(); regexes held
+/// and the most it holds; the limits set by set_regex_limits().
+#[pyfunction]
+fn regex_cache_stats(py: Python<'_>) -> PyResult<Bound<'_, PyDict>> {
+    let stats = regex_cache::stats();
+    let limits = regex_cache::limits();
+    let dict = PyDict::new(py);
+    dict.set_item("hits", stats.hits)?;
+    dict.set_item("misses", stats.misses)?;
+    dict.set_item("size", stats.size)?;
+    dict.set_item("capacity", regex_cache::CAPACITY)?;
+    dict.set_item("size_limit", limits.size)?;
+    dict.set_item("dfa_size_limit", limits.dfa_size)?;
+    Ok(dict)
+}
+
+/// Drop every cached regex and zero the cache counters.
+#[pyfunction]
+fn clear_regex_cache() {
+    regex_cache::clear();
+}
+
+// ============================================================================
+// Normalization — canonical text with offsets back to the original
+// ============================================================================
+
+/// normalize() with NFC composition done by the interpreter's unicodedata, so only
+/// text with non-ASCII characters pays for it.
+fn normalize_text(py: Python<'_>, s: &str, options: NormalizeOptions) -> PyResult<Normalized> {
+    let unicodedata_normalize = match options.nfc && !s.is_ascii() {
+        true => Some(py.import("unicodedata")?.getattr("normalize")?),
+        false => None,
+    };
+    let mut error = None;
+    let out = normalize(s, options, |run| {
+        let composed = unicodedata_normalize
+            .as_ref()
+            .map(|f| f.call1(("NFC", run)).and_then(|r| r.extract::<String>()));
+        match composed {
+            Some(Ok(composed)) => composed,
+            Some(Err(e)) => {
+                error.get_or_insert(e);
+                run.to_string()
+            }
+            None => run.to_string(),
+        }
+    });
+    error.map_or(Ok(out), Err)
+}
+
+/// Collapse whitespace runs to one space, drop zero-width characters, compose to
+/// NFC and optionally lowercase `s`. The result maps offsets back to `s`.
+#[pyfunction]
+#[pyo3(name = "normalize", signature = (s, collapse_whitespace = true, strip_zero_width = true, nfc = true, lowercase = false))]
+fn normalize_py(
+    py: Python<'_>,
+    s: &str,
+    collapse_whitespace: bool,
+    strip_zero_width: bool,
+    nfc: bool,
+    lowercase: bool,
+) -> PyResult<PyNormalized> {
+    let options = NormalizeOptions {
+        collapse_whitespace,
+        strip_zero_width,
+        nfc,
+        lowercase,
+    };
+    Ok(PyNormalized {
+        inner: normalize_text(py, s, options)?,
+    })
+}
+
+// ============================================================================
+// Pattern inference — starter grammars from sample strings
+// ============================================================================
+
+/// Fraction of `samples` that `parser` matches entirely.
+fn fit_fraction(parser: &Arc<dyn ParserElement>, samples: &[String]) -> f64 {
+    let matched = samples.iter().filter(|s| parses(parser, s, true)).count();
+    matched as f64 / samples.len().max(1) as f64
+}
+
+fn inferred_sequence(seq: &Sequence) -> PyResult<Arc<RustAnd>> {
+    let mut elements: Vec<Arc<dyn ParserElement>> = Vec::with_capacity(seq.len());
+    for column in seq {
+        let element: Arc<dyn ParserElement> = match &column.piece {
+            Piece::Literal(text) => Arc::new(RustLiteral::new(text)),
+            Piece::Word(class) => Arc::new(RustWord::new(&class.chars())),
+            Piece::Regex(pattern) => {
+                Arc::new(RegexMatch::new(pattern).map_err(|e| regex_error(pattern, &e))?)
+            }
+        };
+        elements.push(match column.optional {
+            true => Arc::new(RustOptional::new(element)),
+            false => element,
+        });
+    }
+    Ok(Arc::new(RustAnd::new(elements)))
+}
+
+/// Propose a grammar matching every string in `samples`: constant regions become
+/// Literals, varying ones Words (or a Regex) of the characters seen, and regions
+/// some samples lack Optional. When one aligned sequence doesn't fit every sample,
+/// falls back to one alternative per layout, then to the samples themselves.
+/// `generalization` is the fraction of `holdout` the grammar matches.
+#[pyfunction]
+#[pyo3(signature = (samples, holdout = None))]
+fn infer_pattern(
+    py: Python<'_>,
+    samples: Vec<String>,
+    holdout: Option<Vec<String>>,
+) -> PyResult<PyInferredPattern> {
+    if samples.is_empty() || samples.iter().any(|s| s.trim().is_empty()) {
+        return Err(PyValueError::new_err("samples must be non-empty strings"));
+    }
+    let sample_refs: Vec<&str> = samples.iter().map(String::as_str).collect();
+    for alternatives in infer::candidates(&sample_refs) {
+        let sequences = alternatives
+            .iter()
+            .map(inferred_sequence)
+            .collect::<PyResult<Vec<_>>>()?;
+        let (parser, element): (Arc<dyn ParserElement>, Py<PyAny>) = match sequences.len() {
+            1 => {
+                let and = sequences[0].clone();
+                (and.clone(), Py::new(py, PyAnd { inner: and })?.into_any())
+            }
+            _ => {
+                let elements = sequences
+                    .into_iter()
+                    .map(|and| and as Arc<dyn ParserElement>)
+                    .collect();
+                let first = Arc::new(RustMatchFirst::new(elements));
+                (
+                    first.clone(),
+                    Py::new(py, PyMatchFirst { inner: first })?.into_any(),
+                )
+            }
+        };
+        let fit_score = fit_fraction(&parser, &samples);
+        if fit_score < 1.0 {
+            continue;
+        }
+        return Ok(PyInferredPattern {
+            element,
+            description: infer::describe(&alternatives),
+            fit_score,
+            generalization: holdout.as_ref().map(|h| fit_fraction(&parser, h)),
+        });
+    }
+    unreachable!("the last candidate matches every sample")
+}
+
+// ============================================================================
+// Raw FFI helpers — deduplicated from repeated inline patterns
+// ============================================================================
+
+/// Raw CPython PyListObject layout for direct ob_item access.
+#[repr(C)]
+struct RawPyList {
+    _ob_refcnt: usize,
+    _ob_type: usize,
+    _ob_size: usize,
+    ob_item: *mut *mut pyo3::ffi::PyObject,
+}
+
+/// Extract UTF-8 bytes from a Python string object (no allocation).
+#[inline(always)]
+unsafe fn py_str_as_bytes<'a>(obj: *mut pyo3::ffi::PyObject) -> &'a [u8] {
+    let mut size: pyo3::ffi::Py_ssize_t = 0;
+    let data = pyo3::ffi::PyUnicode_AsUTF8AndSize(obj, &mut size);
+    std::slice::from_raw_parts(data as *const u8, size as usize)
+}
+
+/// Extract UTF-8 str from a Python string object (no allocation).
+#[inline(always)]
+unsafe fn py_str_as_str<'a>(obj: *mut pyo3::ffi::PyObject) -> &'a str {
+    std::str::from_utf8_unchecked(py_str_as_bytes(obj))
+}
+
+/// Text of one input: a str, or UTF-8 bytes, bytearray, memoryview or any other
+/// object exporting a byte buffer (e.g. an mmap), read in place where it can be.
+enum InputText<'a> {
+    /// A str, or bytes: immutable, so borrowed for as long as the object lives
+    Borrowed(&'a str),
+    /// A read-only buffer, kept exported so its owner can't resize or free it
+    Buffer(PyBuffer<u8>),
+    /// A writable buffer's contents, copied since Python code could change them
+    /// while the GIL is released
+    Copied(Box<str>),
+}
+
+impl std::ops::Deref for InputText<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            InputText::Borrowed(s) => s,
+            // Checked to be contiguous UTF-8 when extracted
+            InputText::Buffer(buf) => unsafe {
+                let bytes = std::slice::from_raw_parts(buf.buf_ptr() as *const u8, buf.len_bytes());
+                std::str::from_utf8_unchecked(bytes)
+            },
+            InputText::Copied(s) => s,
+        }
+    }
+}
+
+/// `bytes` as str, or a ValueError naming the offset of the first bad byte sequence
+/// and, for an item of a batch, the item's `index`.
+fn utf8_input(bytes: &[u8], index: Option<usize>) -> PyResult<&str> {
+    std::str::from_utf8(bytes).map_err(|e| {
+        let input = match index {
+            Some(i) => format!("input {}", i),
+            None => "input".to_string(),
+        };
+        PyValueError::new_err(format!(
+            "{} is not valid UTF-8: invalid byte sequence at offset {}",
+            input,
+            e.valid_up_to()
+        ))
+    })
+}
+
+/// Text of `obj` (see InputText); `index` is its position in a batch, for errors.
+/// Anything but a str or a contiguous byte buffer is None.
+fn input_text<'a>(
+    obj: Borrowed<'a, '_, PyAny>,
+    index: Option<usize>,
+) -> PyResult<Option<InputText<'a>>> {
+    // The object outlives 'a, and str and bytes contents never change
+    if obj.is_instance_of::<PyString>() {
+        return Ok(Some(InputText::Borrowed(unsafe {
+            py_str_as_str(obj.as_ptr())
+        })));
+    }
+    if obj.is_instance_of::<PyBytes>() {
+        let bytes: &'a [u8] = unsafe {
+            let data = pyo3::ffi::PyBytes_AsString(obj.as_ptr());
+            let len = pyo3::ffi::PyBytes_Size(obj.as_ptr());
+            std::slice::from_raw_parts(data as *const u8, len as usize)
+        };
+        return utf8_input(bytes, index).map(|s| Some(InputText::Borrowed(s)));
+    }
+    let Ok(buf) = PyBuffer::<u8>::get(&obj) else {
+        return Ok(None);
+    };
+    if !buf.is_c_contiguous() {
+        return Ok(None);
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(buf.buf_ptr() as *const u8, buf.len_bytes()) };
+    let text = utf8_input(bytes, index)?;
+    match buf.readonly() {
+        true => Ok(Some(InputText::Buffer(buf))),
+        false => Ok(Some(InputText::Copied(text.into()))),
+    }
+}
+
+/// Text of item `index` of a list argument named `what`.
+fn list_item_text<'a>(
+    item: Borrowed<'a, '_, PyAny>,
+    index: usize,
+    what: &str,
+) -> PyResult<InputText<'a>> {
+    input_text(item, Some(index))?.ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "{} must be a list of str or UTF-8 bytes-like objects",
+            what
+        ))
+    })
+}
+
+/// Texts of the items of a list argument, read from a private snapshot of it:
+/// nothing else can mutate the list or drop its items while they're read detached.
+struct TextList<'py> {
+    snapshot: Bound<'py, PyList>,
+    texts: Vec<InputText<'py>>,
+}
+
+impl<'py> TextList<'py> {
+    /// `what` names the argument in errors.
+    fn new(list: &Bound<'py, PyList>, what: &str) -> PyResult<Self> {
+        let snapshot = PyList::new(list.py(), list.iter())?;
+        let texts = (0..snapshot.len())
+            .map(|i| {
+                // The snapshot keeps the item alive as long as self
+                let item = unsafe { pyo3::ffi::PyList_GET_ITEM(snapshot.as_ptr(), i as _) };
+                list_item_text(unsafe { Borrowed::from_ptr(list.py(), item) }, i, what)
+            })
+            .collect::<PyResult<_>>()?;
+        Ok(Self { snapshot, texts })
+    }
+
+    fn strs(&self) -> Vec<&str> {
+        self.texts.iter().map(|t| &**t).collect()
+    }
+}
+
+impl<'a> FromPyObject<'a, '_> for InputText<'a> {
+    type Error = PyErr;
+
+    fn extract(obj: Borrowed<'a, '_, PyAny>) -> PyResult<Self> {
+        input_text(obj, None)?.ok_or_else(|| {
+            PyTypeError::new_err("input must be str, or UTF-8 bytes or a bytes-like object")
+        })
+    }
+}
+
+/// Bulk increment reference count for a Python object.
+#[inline(always)]
+unsafe fn bulk_incref(ptr: *mut pyo3::ffi::PyObject, count: usize) {
+    for _ in 0..count {
+        pyo3::ffi::Py_INCREF(ptr);
+    }
+}
+
+/// Access internal ob_item pointer of a PyList (raw FFI).
+#[inline(always)]
+unsafe fn list_ob_item(list_ptr: *mut pyo3::ffi::PyObject) -> *mut *mut pyo3::ffi::PyObject {
+    (*(list_ptr as *mut RawPyList)).ob_item
+}
+
+/// Build a u64 hash key from a byte slice at `bytes[start..start+word_len]`.
+/// Uses unaligned u64 read when possible for speed, with length XOR for >7 bytes.
+#[inline(always)]
+unsafe fn word_hash_key(bytes: &[u8], start: usize, word_len: usize, buf_len: usize) -> u64 {
+    if word_len <= 7 {
+        if start + 8 <= buf_len {
+            let raw = std::ptr::read_unaligned(bytes.as_ptr().add(start) as *const u64);
+            raw & ((1u64 << (word_len * 8)) - 1)
+        } else {
+            let mut k: u64 = 0;
+            for (j, &wb) in bytes[start..start + word_len].iter().enumerate() {
+                k |= (wb as u64) << (j * 8);
+            }
+            k
+        }
+    } else {
+        let raw = if start + 8 <= buf_len {
+            std::ptr::read_unaligned(bytes.as_ptr().add(start) as *const u64)
+        } else {
+            let mut k: u64 = 0;
+            for (j, &wb) in bytes[start..start + 7.min(buf_len - start)]
+                .iter()
+                .enumerate()
+            {
+                k |= (wb as u64) << (j * 8);
+            }
+            k
+        };
+        raw ^ ((word_len as u64) << 56)
+    }
+}
+
+/// Detect repeating period in a byte slice using SIMD-accelerated memchr.
+/// Returns the shortest P with bytes[0..P] == bytes[P..2P] if the whole slice
+/// repeats with period P, else 0.
+#[inline]
+unsafe fn detect_text_period(bytes: &[u8], len: usize) -> usize {
+    if len < 4 {
+        return 0;
+    }
+    let first_byte = *bytes.get_unchecked(0);
+    let max_search = len.min(1025);
+    let mut search_from = 0usize;
+    while search_from + 1 < max_search {
+        match memchr::memchr(first_byte, &bytes[search_from + 1..max_search]) {
+            Some(offset) => {
+                let p = search_from + offset + 1;
+                if len >= p * 2 && bytes[..p] == bytes[p..p * 2] {
+                    // Two equal blocks at the start don't make the rest repeat
+                    // ("  a b" starts with "  "); checking one candidate keeps this O(n)
+                    return if bytes[..len - p] == bytes[p..len] {
+                        p
+                    } else {
+                        0
+                    };
+                }
+                search_from = p;
+            }
+            None => break,
+        }
+    }
+    0
+}
+
+/// Branchless word count: scan bytes and count word starts using init/body tables.
+#[inline(always)]
+unsafe fn count_words_branchless(
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+    is_init: &[u8; 256],
+    is_body: &[u8; 256],
+) -> usize {
+    let mut count = 0usize;
+    let mut in_word = 0u8;
+    for i in start..end {
+        let b = *bytes.get_unchecked(i);
+        let cur_init = *is_init.get_unchecked(b as usize);
+        let cur_body = *is_body.get_unchecked(b as usize);
+        let starts = cur_init & !in_word;
+        count += starts as usize;
+        in_word = cur_body & (starts | in_word);
+    }
+    count
+}
+
+/// Fill a pointer array using memcpy doubling from a seed of `seed_len` elements
+/// already written at the start of `ob_item`, up to `total_len` elements.
+#[inline(always)]
+unsafe fn memcpy_double_fill(
+    ob_item: *mut *mut pyo3::ffi::PyObject,
+    seed_len: usize,
+    total_len: usize,
+) {
+    let mut filled = seed_len;
+    while filled < total_len {
+        let copy_len = (total_len - filled).min(filled);
+        std::ptr::copy_nonoverlapping(ob_item, ob_item.add(filled), copy_len);
+        filled += copy_len;
+    }
+}
+
+// ============================================================================
+// Generic batch/search/transform helpers for any ParserElement
+// ============================================================================
+
+/// Generic transform_string: replace all non-overlapping matches with a replacement string,
+/// or with the match's joined tokens (after any parse actions) when there is none.
+/// Finds the matches scan_string does, building the result efficiently.
+/// Copies non-matched regions by slice (no char-by-char), handles UTF-8 correctly.
+fn generic_transform_string<'py>(
+    py: Python<'py>,
+    parser: &Arc<dyn ParserElement>,
+    s: &str,
+    replacement: Option<&str>,
+) -> PyResult<Bound<'py, PyString>> {
+    // Normal elements yield the matched text as their only token
+    let rebuild = replacement.is_none() && parser.parser_kind() != ParserKind::Normal;
+    let mut ctx = ParseContext::new(s).with_checkpoint(checkpoint(py));
+    let mut result = String::with_capacity(s.len());
+    let mut copy_from = 0; // start of uncopied region
+    let mut loc = 0;
+    while let Some(((results, start, end), next)) = scan_next(parser, &mut ctx, loc, false) {
+        // Whitespace skipped before the match is kept, like unmatched text
+        result.push_str(&s[copy_from..start]);
+        match replacement {
+            Some(replacement) => result.push_str(replacement),
+            None if rebuild => push_joined_tokens(py, results.items(), &mut result)?,
+            None => result.push_str(&s[start..end]),
+        }
+        copy_from = end;
+        loc = next;
+    }
+    check_action_error()?;
+    // Flush remaining text
+    if copy_from < s.len() {
+        result.push_str(&s[copy_from..]);
+    }
+    Ok(PyString::new(py, &result))
+}
+
+/// Append tokens to `out` as pyparsing's transform_string does: groups flattened,
+/// every token converted with str() and joined with no separator.
+fn push_joined_tokens(py: Python<'_>, items: &[ParseResultItem], out: &mut String) -> PyResult<()> {
+    for item in items {
+        match item {
+            ParseResultItem::Token(t) => out.push_str(t),
+            ParseResultItem::Group(inner) => push_joined_tokens(py, inner.items(), out)?,
+            ParseResultItem::Int(v) => out.push_str(&v.to_string()),
+            ParseResultItem::Float(v) => out.push_str(PyFloat::new(py, *v).str()?.to_str()?),
+            ParseResultItem::Object(value) => {
+                if let Some(obj) = value.0.downcast_ref::<Py<PyAny>>() {
+                    out.push_str(obj.bind(py).str()?.to_str()?);
+                } else if let Some(captures) = value.0.downcast_ref::<RegexCaptures>() {
+                    out.push_str(&captures.text);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Generic scan_string: `(tokens, start, end)` tuples for each match. With
+/// `normalize`, scans the normalize()d text and reports offsets in `s`.
+fn generic_scan_string<'py>(
+    py: Python<'py>,
+    parser: &Arc<dyn ParserElement>,
+    s: &str,
+    max_matches: Option<usize>,
+    overlap: bool,
+    normalize: bool,
+) -> PyResult<Bound<'py, PyList>> {
+    let norm = match normalize {
+        true => Some(normalize_text(py, s, NormalizeOptions::default())?),
+        false => None,
+    };
+    let text = norm.as_ref().map_or(s, |n| n.text.as_str());
+    let mut ctx = ParseContext::new(text).with_checkpoint(checkpoint(py));
+    let matches = scan_string(parser, &mut ctx, max_matches, overlap);
+    check_action_error()?;
+    let out = PyList::empty(py);
+    for (results, start, end) in &matches {
+        let (start, end) = match &norm {
+            Some(n) => n
+                .to_original_span((*start, *end))
+                .unwrap_or((s.len(), s.len())),
+            None => (*start, *end),
+        };
+        out.append((results_to_py_results(py, results)?, start, end))?;
+    }
+    Ok(out)
+}
+
+/// Generic highlight_spans: `(start, end, class)` tuples for the tagged elements in `root`.
+fn generic_highlight_spans<'py>(
+    py: Python<'py>,
+    root: Arc<dyn ParserElement>,
+    s: &str,
+) -> PyResult<Bound<'py, PyList>> {
+    let spans = py.detach(|| highlight_spans(&root, s));
+    // One Python str per distinct class, shared by all its spans
+    let mut classes: FxHashMap<*const u8, Bound<'py, PyString>> = FxHashMap::default();
+    let items = spans.iter().map(|(start, end, class)| {
+        let name = classes
+            .entry(class.as_ptr())
+            .or_insert_with(|| PyString::new(py, class))
+            .clone();
+        (*start, *end, name)
+    });
+    PyList::new(py, items.collect::<Vec<_>>())
+}
+
+/// Generic search_string_count: count the spans find_all_positions reports
+fn generic_search_string_count(parser: &Arc<dyn ParserElement>, s: &str) -> usize {
+    profile::find_spans(parser, s, false, None).len()
+}
+
+/// Whether a match found by an unanchored search (e.g. Regex::find_iter) at
+/// `start..end` isn't one scan_string would report as is: scans skip empty matches,
+/// and leave whitespace before a match out of it.
+fn scans_differently(s: &str, start: usize, end: usize) -> bool {
+    end == start || skip_ws(s, start) > start
+}
+
+/// Generic search_string: returns list-of-lists like pyparsing.
+/// Each match is wrapped in a sublist: [['match1'], ['match2'], ...]
+/// Stops after `max_matches` matches when given.
+fn generic_search_string<'py>(
+    py: Python<'py>,
+    parser: &Arc<dyn ParserElement>,
+    s: &str,
+    max_matches: Option<usize>,
+) -> PyResult<Bound<'py, PyList>> {
+    let mut ctx = ParseContext::new(s).with_checkpoint(checkpoint(py));
+    // For complex parsers, the matches of scan_string with their multi-token results
+    if parser.parser_kind() != ParserKind::Normal {
+        let found = scan_string(parser, &mut ctx, max_matches, false);
+        check_action_error()?;
+        let out = PyList::empty(py);
+        for (results, _, _) in &found {
+            out.append(unsafe {
+                Bound::from_owned_ptr_or_err(py, match_tokens_ptr(py, results))?
+            })?;
+        }
+        return Ok(out);
+    }
+    unsafe {
+        // Collect match spans, skipping zero-length ones like scan_string
+        let mut matches: Vec<(usize, usize)> = Vec::new();
+        let mut loc = 0;
+        while loc < s.len() && max_matches.is_none_or(|max| matches.len() < max) {
+            if ctx.checkpoint(loc).is_err() {
+                break;
+            }
+            let start = match parser.skip_whitespace_before() {
+                true => skip_ws(s, loc),
+                false => loc,
+            };
+            match profile::try_match(parser, s, start) {
+                Some(end) if end > start => {
+                    matches.push((start, end));
+                    loc = end;
+                }
+                _ => loc += 1,
+            }
+        }
+
+        let n = matches.len() as pyo3::ffi::Py_ssize_t;
+        if n == 0 {
+            check_action_error()?;
+            return Ok(PyList::empty(py));
+        }
+
+        // Build list-of-lists: each match wrapped in a sublist
+        let list_ptr = pyo3::ffi::PyList_New(n);
+        if list_ptr.is_null() {
+            return Err(pyo3::PyErr::fetch(py));
+        }
+
+        // Simple parser: each match is a single token → [['token']]
+        let mut dedup: FxHashMap<&str, *mut pyo3::ffi::PyObject> = FxHashMap::default();
+        for (i, &(start, end)) in matches.iter().enumerate() {
+            let matched = &s[start..end];
+            let py_str = if let Some(&existing) = dedup.get(matched) {
+                pyo3::ffi::Py_INCREF(existing);
+                existing
+            } else {
+                let new_str = PyString::new(py, matched).into_ptr();
+                dedup.insert(matched, new_str);
+                pyo3::ffi::Py_INCREF(new_str);
+                new_str
+            };
+            // Wrap in sublist
+            let inner = pyo3::ffi::PyList_New(1);
+            if inner.is_null() {
+                pyo3::ffi::Py_DECREF(list_ptr);
+                for (_, ptr) in dedup {
+                    pyo3::ffi::Py_DECREF(ptr);
+                }
+                return Err(pyo3::PyErr::fetch(py));
+            }
+            pyo3::ffi::PyList_SET_ITEM(inner, 0, py_str);
+            pyo3::ffi::PyList_SET_ITEM(list_ptr, i as pyo3::ffi::Py_ssize_t, inner);
+        }
+        for (_, ptr) in dedup {
+            pyo3::ffi::Py_DECREF(ptr);
+        }
+
+        let out = Bound::from_owned_ptr(py, list_ptr).cast_into_unchecked();
+        check_action_error()?;
+        Ok(out)
+    }
+}
+
+/// Generic parse_string: parse and return results as a PyList of PyStrings.
+/// Uses parse_impl (full parse) to preserve multi-token results for
+/// repetition combinators like ZeroOrMore and OneOrMore. With `parse_all`,
+/// anything but whitespace (or expressions the parser ignores) after the match is
+/// an error.
+fn generic_parse_string<'py>(
+    py: Python<'py>,
+    parser: &Arc<dyn ParserElement>,
+    s: &str,
+    parse_all: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let mut ctx = ParseContext::new(s)
+        .with_checkpoint(checkpoint(py))
+        .tracking_furthest_failure();
+    let parsed =
+        parse_from_start(parser, &mut ctx, parse_all).map_err(|e| ctx.with_furthest_failure(e));
+    match parsed {
+        Ok(results) => {
+            check_action_error()?;
+            results_to_py_object(py, &results)
+        }
+        // An interrupted parse raises the handler's exception, not a ParseError
+        Err(e) => check_action_error().and_then(|()| Err(parse_error(s, &e))),
+    }
+}
+
+/// The parse behind parse_string and matches(): from past the leading whitespace,
+/// and through to the end of the input with `parse_all`.
+fn parse_from_start<'a>(
+    parser: &Arc<dyn ParserElement>,
+    ctx: &mut ParseContext<'a>,
+    parse_all: bool,
+) -> Result<ParseResults<'a>, ParseException> {
+    let input = ctx.input();
+    let (end, results) = ctx.parse(parser, skip_ws(input, 0))?;
+    if parse_all {
+        expect_end(input, end, parser.ignore_exprs())?;
+    }
+    Ok(results)
+}
+
+/// parse_string on the normalize()d `s`; a ParseError reports its offset in `s`.
+fn normalized_parse_string<'py>(
+    py: Python<'py>,
+    parser: &Arc<dyn ParserElement>,
+    s: &str,
+    parse_all: bool,
+) -> PyResult<Bound<'py, PyAny>> {
+    let norm = normalize_text(py, s, NormalizeOptions::default())?;
+    let text = norm.text.as_str();
+    let mut ctx = ParseContext::new(text).with_checkpoint(checkpoint(py));
+    let parsed = parse_from_start(parser, &mut ctx, parse_all);
+    match parsed {
+        Ok(results) => {
+            check_action_error()?;
+            results_to_py_object(py, &results)
+        }
+        Err(mut e) => {
+            check_action_error()?;
+            e.loc = norm.to_original_offset(e.loc).unwrap_or(s.len());
+            Err(parse_error(s, &e))
+        }
+    }
+}
+
+/// Whether parse_string(s, parse_all) would succeed: the same parse, its results
+/// and error dropped. An exception raised by a parse action or condition is raised.
+fn generic_matches(parser: &Arc<dyn ParserElement>, s: &str, parse_all: bool) -> PyResult<bool> {
+    let matched = parses(parser, s, parse_all);
+    check_action_error()?;
+    Ok(matched)
+}
+
+/// generic_matches for grammars without parse actions, which have nothing to raise.
+fn parses(parser: &Arc<dyn ParserElement>, s: &str, parse_all: bool) -> bool {
+    let mut ctx = ParseContext::new(s);
+    parse_from_start(parser, &mut ctx, parse_all).is_ok()
+}
+
+/// Convert a ParseResultItem to a Python object (PyString for Token, PyList or
+/// ParseResults for Group)
+unsafe fn result_item_to_py(py: Python<'_>, item: &ParseResultItem) -> *mut pyo3::ffi::PyObject {
+    match item {
+        ParseResultItem::Token(s) => PyString::new(py, s).into_ptr(),
+        ParseResultItem::Int(v) => pyo3::ffi::PyLong_FromLongLong(*v),
+        ParseResultItem::Float(v) => pyo3::ffi::PyFloat_FromDouble(*v),
+        // A group with names of its own becomes ParseResults, so they can be looked up
+        ParseResultItem::Group(inner) => match_tokens_ptr(py, inner),
+        ParseResultItem::Object(value) => {
+            if let Some(obj) = value.0.downcast_ref::<Py<PyAny>>() {
+                return obj.clone_ref(py).into_ptr();
+            }
+            if let Some(&flag) = value.0.downcast_ref::<bool>() {
+                return PyBool::new(py, flag).to_owned().into_ptr();
+            }
+            match value.0.clone().downcast::<RegexCaptures>() {
+                Ok(inner) => match Py::new(py, PyRegexMatchResult { inner }) {
+                    Ok(obj) => obj.into_ptr(),
+                    Err(e) => {
+                        e.restore(py);
+                        std::ptr::null_mut()
+                    }
+                },
+                Err(_) => py.None().into_ptr(),
+            }
+        }
+    }
+}
+
+/// Convert ParseResults to a Python list, handling nested Groups
+unsafe fn results_to_py_list(py: Python<'_>, results: &ParseResults) -> *mut pyo3::ffi::PyObject {
+    let items = results.items();
+    let n = items.len() as pyo3::ffi::Py_ssize_t;
+    let list_ptr = pyo3::ffi::PyList_New(n);
+    for (i, item) in items.iter().enumerate() {
+        pyo3::ffi::PyList_SET_ITEM(
+            list_ptr,
+            i as pyo3::ffi::Py_ssize_t,
+            result_item_to_py(py, item),
+        );
+    }
+    list_ptr
+}
+
+/// Python value for a named entry: empty string, the single item, or a list of items.
+unsafe fn named_value_to_py(py: Python<'_>, value: &[ParseResultItem]) -> *mut pyo3::ffi::PyObject {
+    match value {
+        [] => PyString::new(py, "").into_ptr(),
+        [item] => result_item_to_py(py, item),
+        items => {
+            let list_ptr = pyo3::ffi::PyList_New(items.len() as pyo3::ffi::Py_ssize_t);
+            for (i, item) in items.iter().enumerate() {
+                pyo3::ffi::PyList_SET_ITEM(
+                    list_ptr,
+                    i as pyo3::ffi::Py_ssize_t,
+                    result_item_to_py(py, item),
+                );
+            }
+            list_ptr
+        }
+    }
+}
+
+/// A ParseResults holding `tokens` and the named entries `named`.
+fn new_parse_results<'py>(
+    tokens: Bound<'py, PyList>,
+    named: Option<Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyParseResults>> {
+    Bound::new(
+        tokens.py(),
+        PyParseResults {
+            tokens: tokens.unbind(),
+            named: named.map(Bound::unbind),
+        },
+    )
+}
+
+/// `value` as ParseResults: lists of tokens from the fast paths are wrapped, anything
+/// else (already ParseResults) is returned as is.
+fn into_parse_results(value: Bound<'_, PyAny>) -> PyResult<Bound<'_, PyAny>> {
+    if value.is_instance_of::<PyParseResults>() {
+        return Ok(value);
+    }
+    match value.cast_into::<PyList>() {
+        Ok(tokens) => Ok(new_parse_results(tokens, None)?.into_any()),
+        Err(e) => Ok(e.into_inner()),
+    }
+}
+
+/// search_string's matches with each match's tokens as ParseResults (scan_string's
+/// `(tokens, start, end)` tuples already carry ParseResults and pass through).
+fn matches_as_results(matches: Bound<'_, PyList>) -> PyResult<Bound<'_, PyList>> {
+    for (i, tokens) in matches.iter().enumerate() {
+        if !tokens.is_instance_of::<PyParseResults>() {
+            matches.set_item(i, into_parse_results(tokens)?)?;
+        }
+    }
+    Ok(matches)
+}
+
+/// One search_string match as a new reference: a plain list of tokens, or ParseResults
+/// when the match carries names. Null with the Python error set on failure.
+unsafe fn match_tokens_ptr(py: Python<'_>, results: &ParseResults) -> *mut pyo3::ffi::PyObject {
+    if results.named().is_empty() {
+        return results_to_py_list(py, results);
+    }
+    match results_to_py_results(py, results) {
+        Ok(named) => named.into_ptr(),
+        Err(e) => {
+            e.restore(py);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Convert ParseResults to a Python ParseResults object carrying its named entries.
+fn results_to_py_results<'py>(
+    py: Python<'py>,
+    results: &ParseResults,
+) -> PyResult<Bound<'py, PyParseResults>> {
+    let tokens: Bound<'_, PyList> = unsafe {
+        let list_ptr = results_to_py_list(py, results);
+        if list_ptr.is_null() {
+            return Err(pyo3::PyErr::fetch(py));
+        }
+        Bound::from_owned_ptr(py, list_ptr).cast_into_unchecked()
+    };
+    if results.named().is_empty() {
+        return new_parse_results(tokens, None);
+    }
+    let named = PyDict::new(py);
+    for (name, value) in results.named_values() {
+        let one = |items| unsafe { Bound::from_owned_ptr(py, named_value_to_py(py, items)) };
+        match value {
+            NamedValue::One(items) => named.set_item(name.as_ref(), one(items))?,
+            NamedValue::All(values) => {
+                named.set_item(name.as_ref(), PyList::new(py, values.into_iter().map(one))?)?
+            }
+        }
+    }
+    new_parse_results(tokens, Some(named))
+}
+
+/// Tokens of a parse as a plain list, or as ParseResults when the match carries names.
+fn results_to_py_object<'py>(
+    py: Python<'py>,
+    results: &ParseResults,
+) -> PyResult<Bound<'py, PyAny>> {
+    if !results.named().is_empty() {
+        return Ok(results_to_py_results(py, results)?.into_any());
+    }
+    unsafe {
+        let list_ptr = results_to_py_list(py, results);
+        if list_ptr.is_null() {
+            return Err(pyo3::PyErr::fetch(py));
+        }
+        Ok(Bound::from_owned_ptr(py, list_ptr))
+    }
+}
+
+/// JSON for a value a parse action returned, or a Regex match object (its text).
+fn object_json(value: &OpaqueValue) -> JsonValue {
+    if let Some(obj) = value.0.downcast_ref::<Py<PyAny>>() {
+        return Python::attach(|py| py_json(obj.bind(py)));
+    }
+    if let Some(captures) = value.0.downcast_ref::<RegexCaptures>() {
+        return JsonValue::String(captures.text.to_string());
+    }
+    json::bool_or_null(value)
+}
+
+/// `obj` as JSON: None, bools, ints, floats, str, lists, tuples, ParseResults (their
+/// tokens) and dicts with str keys map across; anything else is its str().
+fn py_json(obj: &Bound<'_, PyAny>) -> JsonValue {
+    if obj.is_none() {
+        return JsonValue::Null;
+    }
+    if let Ok(flag) = obj.cast::<PyBool>() {
+        return flag.is_true().into();
+    }
+    if obj.is_instance_of::<PyInt>() {
+        if let Ok(v) = obj.extract::<i64>() {
+            return v.into();
+        }
+    }
+    if let Ok(v) = obj.cast::<PyFloat>() {
+        return serde_json::Number::from_f64(v.value()).map_or(JsonValue::Null, JsonValue::Number);
+    }
+    if let Ok(s) = obj.cast::<PyString>() {
+        return s.to_str().map_or(JsonValue::Null, |s| s.into());
+    }
+    if let Ok(results) = obj.cast::<PyParseResults>() {
+        return py_json(results.borrow().tokens.bind(obj.py()));
+    }
+    if let Ok(list) = obj.cast::<PyList>() {
+        return JsonValue::Array(list.iter().map(|item| py_json(&item)).collect());
+    }
+    if let Ok(tuple) = obj.cast::<PyTuple>() {
+        return JsonValue::Array(tuple.iter().map(|item| py_json(&item)).collect());
+    }
+    if let Ok(dict) = obj.cast::<PyDict>() {
+        let entries: Option<serde_json::Map<String, JsonValue>> = dict
+            .iter()
+            .map(|(k, v)| Some((k.cast::<PyString>().ok()?.to_string(), py_json(&v))))
+            .collect();
+        if let Some(entries) = entries {
+            return JsonValue::Object(entries);
+        }
+    }
+    obj.str()
+        .map_or(JsonValue::Null, |s| JsonValue::String(s.to_string()))
+}
+
+/// JSON `records` as a str, compact or with `pretty` indented; or, given
+/// `jsonl_path`, written to that file as JSON Lines, returning how many were written.
+fn json_output<'py>(
+    py: Python<'py>,
+    records: Vec<JsonValue>,
+    pretty: bool,
+    jsonl_path: Option<&std::path::Path>,
+) -> PyResult<Bound<'py, PyAny>> {
+    match jsonl_path {
+        Some(path) => {
+            let written = py
+                .detach(|| json::write_jsonl(path, &records))
+                .map_err(|e| file_error(&e))?;
+            Ok(written.into_pyobject(py)?.into_any())
+        }
+        None => {
+            let text = py.detach(|| json::to_json_string(records, pretty));
+            Ok(PyString::new(py, &text).into_any())
+        }
+    }
+}
+
+/// parse_batch's matches as JSON (see json_output): an object per matching input
+/// with its `index`, `tokens`, `named` results and the `end` byte offset of the match.
+fn batch_json<'py>(
+    py: Python<'py>,
+    parser: &Arc<dyn ParserElement>,
+    inputs: &Bound<'py, PyList>,
+    pretty: bool,
+    jsonl_path: Option<&std::path::Path>,
+) -> PyResult<Bound<'py, PyAny>> {
+    metered_batch(parser.as_ref(), inputs, || {
+        let text_list = TextList::new(inputs, "inputs")?;
+        let texts = text_list.strs();
+        let record = |s: &str| {
+            let mut ctx = ParseContext::new(s);
+            let (end, results) = ctx.parse(parser, 0).ok()?;
+            let mut record = json::results_record(&results, &object_json);
+            record.insert("end".into(), end.into());
+            Some(record)
+        };
+        let record = timed_parse(batch_metered(), record);
+        // Parse actions may have side effects or raise: run them once per item, in order
+        let found: Vec<_> = match has_parse_actions(parser.as_ref()) {
+            true => {
+                let mut signals = SignalPoll::new();
+                texts
+                    .iter()
+                    .map(|s| {
+                        signals.poll()?;
+                        let found = record(s);
+                        check_action_error().map(|()| found)
+                    })
+                    .collect::<PyResult<_>>()?
+            }
+            false => {
+                let threads = worker_threads(None)?;
+                py.detach(|| map_in_threads(&texts, threads, PARALLEL_MIN_INPUTS, record))?
+            }
+        };
+        for (s, (record, elapsed)) in texts.iter().zip(&found) {
+            if let Some(elapsed) = elapsed {
+                batch_sample(record.is_some(), s.len(), *elapsed);
+            }
+        }
+        let found = found.into_iter().map(|(record, _)| record);
+        let records = found
+            .enumerate()
+            .filter_map(|(index, record)| {
+                let mut indexed = serde_json::Map::new();
+                indexed.insert("index".into(), index.into());
+                indexed.extend(record?);
+                Some(JsonValue::Object(indexed))
+            })
+            .collect();
+        json_output(py, records, pretty, jsonl_path)
+    })
+}
+
+/// Borrow a Python list of str as segments of one logical input (no concatenation).
+fn extract_segments<'py>(segments: &Bound<'py, PyList>) -> PyResult<Vec<&'py str>> {
+    let mut parts = Vec::with_capacity(segments.len());
+    for item in segments.iter() {
+        if !item.is_instance_of::<PyString>() {
+            return Err(PyValueError::new_err("segments must be a list of str"));
+        }
+        // The list keeps each segment alive for the duration of the call
+        parts.push(unsafe { py_str_as_str::<'py>(item.as_ptr()) });
+    }
+    Ok(parts)
+}
+
+/// Tokens for a match found at `start..end` of segmented input. Only the matched
+/// span is materialized (borrowed when it lies within one segment) and re-parsed.
+unsafe fn segment_match_to_py(
+    py: Python<'_>,
+    parser: &dyn ParserElement,
+    input: &Segments<'_>,
+    start: usize,
+    end: usize,
+) -> *mut pyo3::ffi::PyObject {
+    let window = input.slice(start, end);
+    let mut ctx = ParseContext::new(&window);
+    let list = match parser.parse_impl(&mut ctx, 0) {
+        Ok((_, results)) => results_to_py_list(py, &results),
+        Err(_) => pyo3::ffi::PyList_New(0),
+    };
+    list
+}
+
+/// Generic parse_segments: parse_string over a list of str segments treated as one input.
+fn generic_parse_segments<'py>(
+    py: Python<'py>,
+    parser: &dyn ParserElement,
+    segments: &Bound<'py, PyList>,
+) -> PyResult<Bound<'py, PyList>> {
+    let input = Segments::new(extract_segments(segments)?);
+    let start = if parser.skip_whitespace_before() {
+        input.skip_ws(0)
+    } else {
+        0
+    };
+    match parser.try_match_segments(&input, start) {
+        Some(end) => unsafe {
+            let list_ptr = segment_match_to_py(py, parser, &input, start, end);
+            if list_ptr.is_null() {
+                return Err(pyo3::PyErr::fetch(py));
+            }
+            let out = Bound::from_owned_ptr(py, list_ptr).cast_into_unchecked();
+            check_action_error()?;
+            Ok(out)
+        },
+        None => Err(parse_error_at(
+            &input.slice(0, input.len()),
+            start,
+            "Expected match",
+        )),
+    }
+}
+
+/// Generic search_segments: scan a list of str segments as one input and return
+/// `(tokens, start, end)` tuples with global offsets.
+fn generic_search_segments<'py>(
+    py: Python<'py>,
+    parser: &dyn ParserElement,
+    segments: &Bound<'py, PyList>,
+) -> PyResult<Bound<'py, PyList>> {
+    let input = Segments::new(extract_segments(segments)?);
+    let out = PyList::empty(py);
+    let mut loc = 0;
+    while loc < input.len() {
+        // Never start a match in the middle of a UTF-8 sequence
+        if input.byte_at(loc).is_some_and(|b| b & 0xC0 == 0x80) {
+            loc += 1;
+            continue;
+        }
+        // As in scan_string: whitespace is skipped, then the match tried there
+        let start = match parser.skip_whitespace_before() {
+            true => input.skip_ws(loc),
+            false => loc,
+        };
+        match parser.try_match_segments(&input, start) {
+            Some(end) if end > start => {
+                let tokens = unsafe {
+                    Bound::from_owned_ptr(py, segment_match_to_py(py, parser, &input, start, end))
+                };
+                check_action_error()?;
+                out.append((tokens, start, end))?;
+                loc = end;
+            }
+            _ => loc += 1,
+        }
+    }
+    Ok(out)
+}
+
+/// Predicate batches below this size are tested on the calling thread.
+const PARALLEL_MIN_INPUTS: usize = 4096;
+
+/// Stack of each worker thread: a main thread's usual 8 MiB, so the recursion limit
+/// keeps deep parses on workers from overflowing theirs too.
+const WORKER_STACK_SIZE: usize = 8 << 20;
+
+/// Spawn a scoped worker thread with WORKER_STACK_SIZE of stack. It unparks the
+/// spawning thread when done, for SignalPoll::join.
+fn spawn_worker<'scope, T: Send + 'scope>(
+    scope: &'scope std::thread::Scope<'scope, '_>,
+    f: impl FnOnce() -> T + Send + 'scope,
+) -> std::thread::ScopedJoinHandle<'scope, T> {
+    let caller = std::thread::current();
+    std::thread::Builder::new()
+        .stack_size(WORKER_STACK_SIZE)
+        .spawn_scoped(scope, move || {
+            let out = f();
+            caller.unpark();
+            out
+        })
+        .expect("failed to spawn worker thread")
+}
+
+/// How often work done with the GIL released stops to run Python signal handlers.
+const SIGNAL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Inputs SignalPoll::map matches between looks at the clock.
+const SIGNAL_POLL_INPUTS: usize = 64;
+
+/// Runs Python signal handlers from work done with the GIL released, re-acquiring
+/// it briefly every SIGNAL_POLL_INTERVAL, so Ctrl-C stops a long batch. Handlers
+/// only run on the main thread; polling from any other finds nothing. Also stops
+/// the batch once the deadline of the enclosing with_timeout passes.
+struct SignalPoll {
+    last: Instant,
+    deadline: Option<Deadline>,
+}
+
+impl SignalPoll {
+    fn new() -> Self {
+        Self {
+            last: Instant::now(),
+            deadline: DEADLINE.get(),
+        }
+    }
+
+    /// Run pending handlers if SIGNAL_POLL_INTERVAL has passed since the last poll,
+    /// returning the exception one raised (e.g. KeyboardInterrupt), or a TimeoutError
+    /// once the deadline has passed.
+    fn poll(&mut self) -> PyResult<()> {
+        if let Some(deadline) = self.deadline.filter(|d| Instant::now() >= d.at) {
+            return Err(Python::attach(|py| deadline.error(py, None)));
+        }
+        if self.last.elapsed() < SIGNAL_POLL_INTERVAL {
+            return Ok(());
+        }
+        self.last = Instant::now();
+        Python::attach(|py| py.check_signals())
+    }
+
+    /// `f` of each input in order, polling as it goes: before each input while a
+    /// deadline is running.
+    fn map<I, T>(
+        &mut self,
+        inputs: impl IntoIterator<Item = I>,
+        mut f: impl FnMut(I) -> T,
+    ) -> PyResult<Vec<T>> {
+        let mut out = Vec::new();
+        for (i, input) in inputs.into_iter().enumerate() {
+            if i % SIGNAL_POLL_INPUTS == 0 || self.deadline.is_some() {
+                self.poll()?;
+            }
+            out.push(f(input));
+        }
+        Ok(out)
+    }
+
+    /// Join workers made with spawn_worker, polling while they run. Once a handler
+    /// raises, `stop` is set so the workers give up early, and its exception is
+    /// returned when they have.
+    fn join<T>(
+        &mut self,
+        handles: Vec<std::thread::ScopedJoinHandle<'_, T>>,
+        stop: &AtomicBool,
+    ) -> PyResult<Vec<T>> {
+        let mut polled = Ok(());
+        while polled.is_ok() && !handles.iter().all(|h| h.is_finished()) {
+            let wait = self.deadline.map_or(SIGNAL_POLL_INTERVAL, |d| {
+                d.at.saturating_duration_since(Instant::now())
+                    .min(SIGNAL_POLL_INTERVAL)
+            });
+            std::thread::park_timeout(wait);
+            polled = self.poll();
+        }
+        if polled.is_err() {
+            stop.store(true, Ordering::Relaxed);
+        }
+        let parts = handles
+            .into_iter()
+            .map(|h| h.join().expect("worker thread panicked"))
+            .collect();
+        polled.map(|()| parts)
+    }
+}
+
+/// Worker threads for a parallel call: `num_threads`, or one per CPU by default.
+/// Each call spawns its own scoped threads, so calls with different counts coexist.
+fn worker_threads(num_threads: Option<usize>) -> PyResult<usize> {
+    match num_threads {
+        Some(0) => Err(PyValueError::new_err("num_threads must be positive")),
+        Some(n) => Ok(n),
+        None => Ok(std::thread::available_parallelism().map_or(1, |n| n.get())),
+    }
+}
+
+/// Literal predicates combined with AND semantics; `None` predicates always pass.
+struct StrPredicates {
+    prefix: Option<String>,
+    suffix: Option<String>,
+    contains: Option<memchr::memmem::Finder<'static>>,
+    caseless: bool,
+}
+
+impl StrPredicates {
+    fn new(
+        prefix: Option<&str>,
+        suffix: Option<&str>,
+        contains: Option<&str>,
+        caseless: bool,
+    ) -> Self {
+        let fold = |s: &str| {
+            if caseless {
+                s.to_lowercase()
+            } else {
+                s.to_string()
+            }
+        };
+        Self {
+            prefix: prefix.map(fold),
+            suffix: suffix.map(fold),
+            contains: contains
+                .map(|c| memchr::memmem::Finder::new(fold(c).as_bytes()).into_owned()),
+            caseless,
+        }
+    }
+
+    #[inline]
+    fn test(&self, s: &str) -> bool {
+        let folded;
+        let s = if self.caseless && !s.is_empty() {
+            folded = s.to_lowercase();
+            folded.as_str()
+        } else {
+            s
+        };
+        let bytes = s.as_bytes();
+        self.prefix
+            .as_ref()
+            .is_none_or(|p| bytes.starts_with(p.as_bytes()))
+            && self
+                .suffix
+                .as_ref()
+                .is_none_or(|p| bytes.ends_with(p.as_bytes()))
+            && self
+                .contains
+                .as_ref()
+                .is_none_or(|f| f.find(bytes).is_some())
+    }
+}
+
+/// `f` of every input, in order, splitting the inputs across `threads` scoped
+/// threads when there are at least `min_inputs` of them. Polls for signals meanwhile
+/// (see SignalPoll): an exception a handler raises stops the remaining inputs and is
+/// returned instead.
+fn map_in_threads<'s, T: Send>(
+    inputs: &[&'s str],
+    threads: usize,
+    min_inputs: usize,
+    f: impl Fn(&'s str) -> T + Sync,
+) -> PyResult<Vec<T>> {
+    let mut signals = SignalPoll::new();
+    if threads == 1 || inputs.len() < min_inputs.max(2) {
+        return signals.map(inputs, |s| f(s));
+    }
+    let chunk = inputs.len().div_ceil(threads);
+    let (f, stop) = (&f, &AtomicBool::new(false));
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = inputs
+            .chunks(chunk)
+            .map(|part| {
+                spawn_worker(scope, move || {
+                    part.iter()
+                        .map_while(|s| (!stop.load(Ordering::Relaxed)).then(|| f(s)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let parts = signals.join(handles, stop)?;
+        Ok(parts.into_iter().flatten().collect())
+    })
+}
+
+/// Fold every input into one accumulator, in order: each of up to `threads` scoped
+/// threads folds a contiguous run of inputs into `init()`, and the runs are merged
+/// left to right. One thread folds everything below `min_inputs`. Like
+/// map_in_threads, stops early with the exception a signal handler raises.
+fn fold_in_threads<A: Send>(
+    inputs: &[&str],
+    threads: usize,
+    min_inputs: usize,
+    init: impl Fn() -> A + Sync,
+    fold: impl Fn(&mut A, &str) + Sync,
+    merge: impl Fn(&mut A, A),
+) -> PyResult<A> {
+    let mut signals = SignalPoll::new();
+    if threads == 1 || inputs.len() < min_inputs.max(2) {
+        let mut acc = init();
+        signals.map(inputs, |s| fold(&mut acc, s))?;
+        return Ok(acc);
+    }
+    let chunk = inputs.len().div_ceil(threads);
+    let (init, fold, stop) = (&init, &fold, &AtomicBool::new(false));
+    let parts = std::thread::scope(|scope| {
+        let handles: Vec<_> = inputs
+            .chunks(chunk)
+            .map(|part| {
+                spawn_worker(scope, move || {
+                    let mut acc = init();
+                    for s in part {
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        fold(&mut acc, s);
+                    }
+                    acc
+                })
+            })
+            .collect();
+        signals.join(handles, stop)
+    })?;
+    let mut parts = parts.into_iter();
+    let mut acc = parts.next().unwrap_or_else(init);
+    for part in parts {
+        merge(&mut acc, part);
+    }
+    Ok(acc)
+}
+
+/// A parse_batch input list with its strings extracted, so they can be matched with
+/// the GIL released. Each distinct string object is matched once: `[s] * n` and
+/// cycling inputs cost one match per distinct string, and share their output.
+struct BatchInputs<'py> {
+    /// Private snapshot: nothing else can mutate it or drop its strings while detached
+    items: Bound<'py, PyList>,
+    /// Text of each distinct input object, in order of first appearance
+    distinct: Vec<InputText<'py>>,
+    /// Index of each distinct string's first input
+    firsts: Vec<usize>,
+    /// Index into `distinct` per input
+    slots: Vec<u32>,
+}
+
+impl<'py> BatchInputs<'py> {
+    fn new(inputs: &Bound<'py, PyList>) -> PyResult<Self> {
+        let items = PyList::new(inputs.py(), inputs.iter())?;
+        let mut distinct = Vec::new();
+        let mut firsts = Vec::new();
+        let mut slots = Vec::with_capacity(items.len());
+        let mut seen: FxHashMap<*mut pyo3::ffi::PyObject, u32> = FxHashMap::default();
+        let mut last: (*mut pyo3::ffi::PyObject, u32) = (std::ptr::null_mut(), 0);
+        for (i, item) in items.iter().enumerate() {
+            let ptr = item.as_ptr();
+            // Runs of one object are common enough to skip the lookup
+            if ptr != last.0 {
+                let slot = match seen.get(&ptr) {
+                    Some(&slot) => slot,
+                    None => {
+                        // The snapshot keeps the item alive as long as self
+                        let item = unsafe { Borrowed::from_ptr(items.py(), ptr) };
+                        distinct.push(list_item_text(item, i, "inputs")?);
+                        firsts.push(i);
+                        seen.insert(ptr, distinct.len() as u32 - 1);
+                        distinct.len() as u32 - 1
+                    }
+                };
+                last = (ptr, slot);
+            }
+            slots.push(last.1);
+        }
+        Ok(Self {
+            items,
+            distinct,
+            firsts,
+            slots,
+        })
+    }
+
+    /// `match_one` on each distinct string, with the GIL released; a signal
+    /// handler's exception stops it (see SignalPoll).
+    fn match_detached<'s, T: Send>(
+        &'s self,
+        match_one: impl Fn(&'s str) -> T + Sync,
+    ) -> PyResult<Vec<T>> {
+        let distinct = &self.distinct;
+        self.items
+            .py()
+            .detach(|| SignalPoll::new().map(distinct, |s| match_one(s)))
+    }
+
+    /// match_detached for the parse_batch paths. When the call is metered, each input
+    /// is recorded with the outcome and match time of its distinct string, which
+    /// repeats of one object share.
+    fn parse_detached<'s, T: Send + BatchOutcome>(
+        &'s self,
+        parse_one: impl Fn(&'s str) -> T + Sync,
+    ) -> PyResult<Vec<T>> {
+        if !batch_metered() {
+            return self.match_detached(parse_one);
+        }
+        let timed = self.match_detached(timed_parse(true, parse_one))?;
+        for &slot in &self.slots {
+            let (out, elapsed) = &timed[slot as usize];
+            let bytes = self.distinct[slot as usize].len();
+            batch_sample(out.matched(), bytes, elapsed.unwrap_or_default());
+        }
+        Ok(timed.into_iter().map(|(out, _)| out).collect())
+    }
+
+    /// Number of inputs `matches` accepts, counted with the GIL released.
+    fn count_detached(&self, matches: impl Fn(&str) -> bool + Sync) -> PyResult<usize> {
+        let (distinct, slots) = (&self.distinct, &self.slots);
+        self.items.py().detach(|| {
+            let hits = SignalPoll::new().map(distinct, |s| matches(s))?;
+            Ok(slots.iter().filter(|&&slot| hits[slot as usize]).count())
+        })
+    }
+
+    /// Flat output of single-token matches: the `(start, end)` span of each matching
+    /// input, or the input object itself when it matched whole. Misses add nothing.
+    fn span_tokens(&self, spans: &[Option<(usize, usize)>]) -> PyResult<Bound<'py, PyList>> {
+        let py = self.items.py();
+        let mut outputs = Vec::with_capacity(spans.len());
+        for (i, span) in spans.iter().enumerate() {
+            let s: &str = &self.distinct[i];
+            let item = self.items.get_item(self.firsts[i])?;
+            outputs.push(match *span {
+                // A str matched whole is its own token
+                Some((0, end)) if end == s.len() && item.is_instance_of::<PyString>() => Some(item),
+                Some((start, end)) => Some(PyString::new(py, &s[start..end]).into_any()),
+                None => None,
+            });
+        }
+        self.flattened(&outputs)
+    }
+
+    /// One output per input: the output of its distinct string.
+    fn per_input(&self, outputs: &[Bound<'py, PyAny>]) -> PyResult<Bound<'py, PyList>> {
+        PyList::new(
+            self.items.py(),
+            self.slots.iter().map(|&slot| &outputs[slot as usize]),
+        )
+    }
+
+    /// One output per input: `hit` where its distinct string matched, else `miss`.
+    fn hit_or_miss(
+        &self,
+        matched: &[bool],
+        hit: &Bound<'py, PyAny>,
+        miss: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        PyList::new(
+            self.items.py(),
+            self.slots
+                .iter()
+                .map(|&slot| if matched[slot as usize] { hit } else { miss }),
+        )
+    }
+
+    /// The outputs of every input's distinct string, concatenated in input order.
+    fn flattened<O>(&self, outputs: &[O]) -> PyResult<Bound<'py, PyList>>
+    where
+        for<'o> &'o O: IntoIterator<Item = &'o Bound<'py, PyAny>>,
+    {
+        let items: Vec<&Bound<'py, PyAny>> = self
+            .slots
+            .iter()
+            .flat_map(|&slot| &outputs[slot as usize])
+            .collect();
+        PyList::new(self.items.py(), items)
+    }
+}
+
+/// Generic parse_batch_count: matches each distinct input once, without the GIL.
+fn generic_parse_batch_count(
+    parser: &dyn ParserElement,
+    inputs: &Bound<'_, PyList>,
+) -> PyResult<usize> {
+    let batch = BatchInputs::new(inputs)?;
+    batch.count_detached(|s| parser.try_match_at(s, 0).is_some())
+}
+
+/// Generic parse_batch: parse each input and return list of result lists.
+/// Uses parse_impl to preserve multi-token results for repetition combinators.
+fn generic_parse_batch<'py>(
+    py: Python<'py>,
+    parser: &Arc<dyn ParserElement>,
+    inputs: &Bound<'py, PyList>,
+) -> PyResult<Bound<'py, PyList>> {
+    // Parse actions may have side effects or raise: run them once per item, in order
+    if has_parse_actions(parser.as_ref()) {
+        let out = PyList::empty(py);
+        let mut signals = SignalPoll::new();
+        for (i, item) in inputs.iter().enumerate() {
+            signals.poll()?;
+            let text = list_item_text(item.as_borrowed(), i, "inputs")?;
+            let mut ctx = ParseContext::new(&text);
+            let started = Instant::now();
+            let parsed = ctx.parse(parser, 0);
+            batch_sample(parsed.is_ok(), text.len(), started.elapsed());
+            let tokens = match parsed {
+                Ok((_end, results)) => unsafe {
+                    Bound::from_owned_ptr(py, results_to_py_list(py, &results))
+                },
+                Err(_) => PyList::empty(py).into_any(),
+            };
+            check_action_error()?;
+            out.append(tokens)?;
+        }
+        return Ok(out);
+    }
+    let batch = BatchInputs::new(inputs)?;
+    let parsed = batch.parse_detached(|s| {
+        let mut ctx = ParseContext::new(s);
+        ctx.parse(parser, 0).ok().map(|(_end, results)| results)
+    })?;
+    let outputs: Vec<Bound<'py, PyAny>> = parsed
+        .iter()
+        .map(|results| match results {
+            Some(results) => unsafe { Bound::from_owned_ptr(py, results_to_py_list(py, results)) },
+            None => PyList::empty(py).into_any(),
+        })
+        .collect();
+    batch.per_input(&outputs)
+}
+
+// ============================================================================
+// Forward declarations of all pyclass structs
+// ============================================================================
+
+/// Tokens of a parse: indexes like a list by position and like a dict by name
+/// (`results["key"]`), and compares equal to the plain list of its tokens.
+#[pyclass(name = "ParseResults", sequence)]
+struct PyParseResults {
+    tokens: Py<PyList>,
+    /// None when the match has no named entries
+    named: Option<Py<PyDict>>,
+}
+
+/// Caches the match spans of a grammar in a text and updates them after edits.
+#[pyclass(name = "IncrementalScanner")]
+struct PyIncrementalScanner {
+    inner: IncrementalScanner,
+}
+
+/// search_string over text fed in chunks; see StreamParser's constructor.
+#[pyclass(name = "StreamParser")]
+struct PyStreamParser {
+    inner: StreamScanner,
+    closed: bool,
+}
+
+/// Text returned by normalize(), with offsets mapping back to the original.
+#[pyclass(name = "Normalized")]
+struct PyNormalized {
+    inner: Normalized,
+}
+
+/// Iterator returned by scan_iter(): finds one match per step, from `loc` on.
+#[pyclass(name = "ScanIterator")]
+struct PyScanIterator {
+    parser: Arc<dyn ParserElement>,
+    text: Py<PyString>,
+    loc: usize,
+    end: usize,
+    overlap: bool,
+}
+
+/// Iterator returned by parse_lines(): pulls a chunk of lines from `lines` when its
+/// buffer of parsed ones runs out.
+#[pyclass(name = "LineParseIterator")]
+struct PyLineParseIterator {
+    parser: Arc<dyn ParserElement>,
+    lines: Py<PyIterator>,
+    chunk_size: usize,
+    skip_failures: bool,
+    /// Index of the next line pulled from `lines`
+    next_index: usize,
+    /// Parsed lines not yet returned, with the failure that ends the stream last
+    pending: VecDeque<PyResult<(usize, Py<PyAny>)>>,
+    done: bool,
+}
+
+/// Column returned by parse_arrow_column(). Exported through the Arrow PyCapsule
+/// interface, e.g. `pyarrow.array(column)`.
+#[pyclass(name = "ArrowColumn")]
+struct PyArrowColumn {
+    inner: ResultColumn,
+}
+
+/// Finds many literals in one pass over a text; see FastScanner's constructor.
+#[pyclass(name = "FastScanner", frozen)]
+struct PyFastScanner {
+    inner: FastScanner,
+}
+
+/// Reader of key=value records; see core::kv.
+#[pyclass(name = "KeyValueParser", frozen)]
+struct PyKeyValueParser {
+    inner: KeyValueFormat,
+}
+
+/// Grammar, files and output run together; see ParserPipeline's constructor.
+#[pyclass(name = "ParserPipeline", frozen)]
+struct PyParserPipeline {
+    parser: Arc<dyn ParserElement>,
+    source: PipelineSource,
+    sink: PipelineSink,
+    threads: usize,
+}
+
+/// Tokens of a batch as flat buffers; see parse_batch_compact.
+#[pyclass(name = "CompactResults", frozen)]
+struct PyCompactResults {
+    inner: CompactResults,
+}
+
+/// A Regex match returned with `as_match=True`: its groups and their offsets.
+#[pyclass(name = "RegexMatchResult", frozen)]
+struct PyRegexMatchResult {
+    inner: Arc<RegexCaptures>,
+}
+
+/// Set of characters from a class spec, for Word's init_chars and body_chars.
+#[pyclass(name = "CharClass", frozen)]
+struct PyCharClass {
+    /// The spec as given, with `^` prepended when negated
+    spec: String,
+    set: CharSet,
+}
+
+/// Grammar returned by compile(): the same grammar run as a flat program.
+#[pyclass(name = "CompiledGrammar", frozen)]
+struct PyCompiledGrammar {
+    inner: Arc<CompiledGrammar>,
+}
+
+/// Grammar returned by infer_pattern(), with how well it fits.
+#[pyclass(name = "InferredPattern")]
+struct PyInferredPattern {
+    element: Py<PyAny>,
+    description: String,
+    fit_score: f64,
+    generalization: Option<f64>,
+}
+
+/// Matches the string `s` exactly.
+#[pyclass(name = "Literal", extends = PyParserElement, from_py_object)]
+struct PyLiteral {
+    inner: Arc<RustLiteral>,
+    cached_pystr: Py<PyString>,
+    cached_err_msg: String,
+}
+
+impl Clone for PyLiteral {
+    fn clone(&self) -> Self {
+        Python::attach(|py| Self {
+            inner: self.inner.clone(),
+            cached_pystr: self.cached_pystr.clone_ref(py),
+            cached_err_msg: self.cached_err_msg.clone(),
+        })
+    }
+}
+
+/// A run of characters: one of `init_chars`, then any number of `body_chars`
+/// (default `init_chars`). An identifier is `Word(identchars(), identbodychars())`,
+/// also prebuilt as `common.identifier`.
+#[pyclass(name = "Word", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyWord {
+    inner: Arc<RustWord>,
+}
+
+/// Matches a regular expression. Without capture groups the match is the token;
+/// with them, each participating group is a token and named groups are results names.
+/// With `as_match`, the token is a RegexMatchResult; Suppress drops it like any
+/// token, but Combine can't join it and yields the matched text instead.
+#[pyclass(name = "Regex", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyRegex {
+    inner: Arc<RegexMatch>,
+}
+
+/// Matches `s` when not followed by an identifier character (`ident_chars`, by
+/// default those of set_default_keyword_chars()), so `if` doesn't match `iffy`.
+#[pyclass(name = "Keyword", extends = PyParserElement, from_py_object)]
+struct PyKeyword {
+    inner: Arc<RustKeyword>,
+    cached_pystr: Py<PyString>,
+}
+
+impl Clone for PyKeyword {
+    fn clone(&self) -> Self {
+        Python::attach(|py| Self {
+            inner: self.inner.clone(),
+            cached_pystr: self.cached_pystr.clone_ref(py),
+        })
+    }
+}
+
+/// Matches each of `exprs` in turn; also built with `+`.
+#[pyclass(name = "And", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyAnd {
+    inner: Arc<RustAnd>,
+}
+
+/// Matches the first of `exprs` that matches; also built with `|`.
+#[pyclass(name = "MatchFirst", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyMatchFirst {
+    inner: Arc<RustMatchFirst>,
+}
+
+/// Matches `expr` any number of times, stopping early where `stop_on` matches.
+#[pyclass(name = "ZeroOrMore", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyZeroOrMore {
+    inner: Arc<RustZeroOrMore>,
+}
+
+/// Matches `expr` one or more times, stopping early where `stop_on` matches.
+#[pyclass(name = "OneOrMore", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyOneOrMore {
+    inner: Arc<RustOneOrMore>,
+}
+
+/// Matches `expr` if it can, and the empty string if not.
+#[pyclass(name = "Optional", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyOptional {
+    inner: Arc<RustOptional>,
+}
+
+/// Matches `expr` and nests its tokens in one list token.
+#[pyclass(name = "Group", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyGroup {
+    inner: Arc<RustGroup>,
+}
+
+/// Matches `expr` and names each of its groups by the group's first token.
+#[pyclass(name = "Dict", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyDictElement {
+    inner: Arc<RustDict>,
+}
+
+/// Matches `expr` and drops its tokens.
+#[pyclass(name = "Suppress", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PySuppress {
+    inner: Arc<RustSuppress>,
+}
+
+/// Placeholder for a recursive grammar, given its element later with `<<=` or set().
+#[pyclass(name = "Forward", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyForward {
+    inner: Arc<RustForward>,
+}
+
+/// Matches `expr` and joins its tokens into one with `join_string`; with
+/// `adjacent`, no whitespace may separate them.
+#[pyclass(name = "Combine", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyCombine {
+    inner: Arc<RustCombine>,
+}
+
+/// Matches `expr` and returns the text it spanned as one token.
+#[pyclass(name = "OriginalTextFor", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyOriginalTextFor {
+    inner: Arc<RustOriginalTextFor>,
+}
+
+/// Matches `expr` and returns `[start, [tokens], end]`, its offsets in the input.
+#[pyclass(name = "Located", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyLocated {
+    inner: Arc<RustLocated>,
+}
+
+/// Matches `expr`, labelling its spans `class_name` for highlight_spans().
+#[pyclass(name = "HighlightClass", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyHighlightClass {
+    inner: Arc<RustHighlightClass>,
+}
+
+/// Matches `expr` and stores its tokens under the results name `name`.
+#[pyclass(name = "Named", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyNamed {
+    inner: Arc<RustNamed>,
+}
+
+/// Element whose tokens are passed through Python parse actions.
+#[pyclass(name = "ParseAction", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyParseAction {
+    inner: Arc<RustAction>,
+}
+
+/// Element whose tokens are converted in Rust after it matches: to_int(), to_float(),
+/// strip_quotes(), strip(), upcase(), downcase() or join().
+#[pyclass(name = "Transform", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyTransform {
+    inner: Arc<RustTransform>,
+}
+
+/// Element whose matches are rejected when a Python predicate is falsy.
+#[pyclass(name = "Condition", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyCondition {
+    inner: Arc<RustCondition>,
+}
+
+/// Matches `expr` with `chars` as the whitespace it skips.
+#[pyclass(name = "WhitespaceScope", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyWhitespaceScope {
+    inner: Arc<RustWhitespaceScope>,
+}
+
+/// Matches `expr`, reporting each attempt through the debug actions.
+#[pyclass(name = "DebugTrace", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyDebugTrace {
+    inner: Arc<RustDebugTrace>,
+}
+
+/// Matches `exprs` in any order, each at most once, and at least `n` of them. The indices of the clauses matched are named `clauses_seen`.
+#[pyclass(name = "AtLeastN", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyAtLeastN {
+    inner: Arc<RustEach>,
+}
+
+/// Matches `exprs` in any order, each at most once, and at most `n` of them. The indices of the clauses matched are named `clauses_seen`.
+#[pyclass(name = "AtMostN", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyAtMostN {
+    inner: Arc<RustEach>,
+}
+
+/// Matches `expr` exactly `count` times.
+#[pyclass(name = "Exactly", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyExactly {
+    inner: Arc<RustExactly>,
+}
+
+/// Matches `expr` between `min` and `max` times (no upper bound when None).
+#[pyclass(name = "Repeat", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyRepeat {
+    inner: Arc<RustRepeat>,
+}
+
+/// Matches `s` in any case; the token is `s` as given.
+#[pyclass(name = "CaselessLiteral", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyCaselessLiteral {
+    inner: Arc<RustCaselessLiteral>,
+}
+
+/// Matches keyword `s` in any case; the token is `s` as given.
+#[pyclass(name = "CaselessKeyword", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyCaselessKeyword {
+    inner: Arc<RustCaselessKeyword>,
+}
+
+/// Matches text differing from `match_string` in at most `max_mismatches` characters.
+#[pyclass(name = "CloseMatch", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyCloseMatch {
+    inner: Arc<RustCloseMatch>,
+}
+
+/// Start tag from make_html_tags or make_xml_tags.
+#[pyclass(name = "HtmlTag", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyHtmlTag {
+    inner: Arc<RustHtmlTag>,
+}
+
+/// End tag from make_html_tags or make_xml_tags.
+#[pyclass(name = "HtmlEndTag", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyHtmlEndTag {
+    inner: Arc<RustHtmlEndTag>,
+}
+
+/// Matches one character from `s`.
+#[pyclass(name = "Char", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyChar {
+    inner: Arc<RustChar>,
+}
+
+/// Matches at the start of the input.
+#[pyclass(name = "StringStart", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyStringStart {
+    inner: Arc<RustStringStart>,
+}
+
+/// Matches at the end of the input.
+#[pyclass(name = "StringEnd", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyStringEnd {
+    inner: Arc<RustStringEnd>,
+}
+
+/// Matches at the start of a line.
+#[pyclass(name = "LineStart", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyLineStart {
+    inner: Arc<RustLineStart>,
+}
+
+/// Matches at the end of a line; the newline is the token.
+#[pyclass(name = "LineEnd", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyLineEnd {
+    inner: Arc<RustLineEnd>,
+}
+
+/// Matches the rest of the line, up to but not including the newline.
+#[pyclass(name = "RestOfLine", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyRestOfLine {
+    inner: Arc<RustRestOfLine>,
+}
+
+/// Advances to column `colno` of the current line.
+#[pyclass(name = "GoToColumn", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyGoToColumn {
+    inner: Arc<RustGoToColumn>,
+}
+
+/// A comment; built by c_style_comment() and the other comment functions, C++
+/// style by default.
+#[pyclass(name = "Comment", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyComment {
+    inner: Arc<RustComment>,
+}
+
+/// A number, its token an int or float; see `common`.
+#[pyclass(name = "Number", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyNumber {
+    inner: Arc<RustNumber>,
+}
+
+/// An IPv6 address, also prebuilt as `common.ipv6_address`.
+#[pyclass(name = "Ipv6Address", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyIpv6Address {
+    inner: Arc<RustIpv6Address>,
+}
+
+/// Matches `expr` as a whole line: only whitespace may surround it on that line.
+#[pyclass(name = "AsLine", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyAsLine {
+    inner: Arc<RustAsLine>,
+}
+
+/// Lines of `expr` indented alike and further than the enclosing block, for
+/// indentation-based formats.
+#[pyclass(name = "IndentedBlock", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyIndentedBlock {
+    inner: Arc<RustIndentedBlock>,
+}
+
+/// Matches text between `quote_char`s, with `esc_char` escapes; `unquote` drops
+/// the quotes from the token.
+#[pyclass(name = "QuotedString", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyQuotedString {
+    inner: Arc<RustQuotedString>,
+}
+
+/// Always matches, consuming nothing.
+#[pyclass(name = "Empty", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyEmpty {
+    inner: Arc<RustEmpty>,
+}
+
+/// Never matches.
+#[pyclass(name = "NoMatch", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyNoMatch {
+    inner: Arc<RustNoMatch>,
+}
+
+/// Matches the text up to the next place `expr` matches.
+#[pyclass(name = "SkipTo", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PySkipTo {
+    inner: Arc<RustSkipTo>,
+}
+
+/// Matches the text that `expr` last matched.
+#[pyclass(name = "MatchPreviousLiteral", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyMatchPreviousLiteral {
+    inner: Arc<RustMatchPreviousLiteral>,
+}
+
+/// Matches the text that `expr` last matched, re-parsed with `expr` so its tokens
+/// must be the same.
+#[pyclass(name = "MatchPreviousExpr", extends = PyParserElement, from_py_object)]
+#[derive(Clone)]
+struct PyMatchPreviousExpr {
+    inner: Arc<RustMatchPreviousExpr>,
+}
+
+/// Base class of every element: holds the element for the methods shared by all of
+/// them (parsing, operators, results names, ...). Subclasses keep their concrete
+/// element for their own methods and fast paths.
+#[pyclass(name = "ParserElement", subclass, from_py_object)]
+#[derive(Clone)]
+struct PyParserElement {
+    inner: Arc<dyn ParserElement>,
+}
+
+/// Build element classes from their Rust struct, e.g. `PyWord { inner }`, like any
+/// other pyclass: the ParserElement base is filled in from `inner`.
+macro_rules! impl_element_subclass {
+    ($($py_type:ident),* $(,)?) => {$(
+        impl From<$py_type> for PyClassInitializer<$py_type> {
+            fn from(element: $py_type) -> Self {
+                let inner: Arc<dyn ParserElement> = element.inner.clone();
+                PyClassInitializer::from(PyParserElement { inner }).add_subclass(element)
+            }
+        }
+
+        impl $py_type {
+            /// `inner` as the generic functions take it (not every class needs it).
+            #[allow(dead_code)]
+            fn element(&self) -> Arc<dyn ParserElement> {
+                self.inner.clone()
+            }
+        }
+
+        impl<'py> IntoPyObject<'py> for $py_type {
+            type Target = $py_type;
+            type Output = Bound<'py, $py_type>;
+            type Error = PyErr;
+
+            fn into_pyobject(self, py: Python<'py>) -> PyResult<Bound<'py, $py_type>> {
+                Bound::new(py, self)
+            }
+        }
+    )*};
+}
+
+impl_element_subclass!(
+    PyLiteral,
+    PyWord,
+    PyRegex,
+    PyKeyword,
+    PyAnd,
+    PyMatchFirst,
+    PyZeroOrMore,
+    PyOneOrMore,
+    PyOptional,
+    PyGroup,
+    PyDictElement,
+    PySuppress,
+    PyForward,
+    PyCombine,
+    PyOriginalTextFor,
+    PyLocated,
+    PyHighlightClass,
+    PyNamed,
+    PyParseAction,
+    PyCondition,
+    PyTransform,
+    PyWhitespaceScope,
+    PyDebugTrace,
+    PyAtLeastN,
+    PyAtMostN,
+    PyExactly,
+    PyRepeat,
+    PyCaselessLiteral,
+    PyCaselessKeyword,
+    PyCloseMatch,
+    PyHtmlTag,
+    PyHtmlEndTag,
+    PyChar,
+    PyStringStart,
+    PyStringEnd,
+    PyLineStart,
+    PyLineEnd,
+    PyRestOfLine,
+    PyGoToColumn,
+    PyComment,
+    PyNumber,
+    PyIpv6Address,
+    PyAsLine,
+    PyIndentedBlock,
+    PyQuotedString,
+    PyEmpty,
+    PyNoMatch,
+    PySkipTo,
+    PyMatchPreviousLiteral,
+    PyMatchPreviousExpr,
+);
+
+// ============================================================================
+// Helper to extract any parser element from a PyAny
+// ============================================================================
+
+/// The element behind any element class; a plain string is treated as a Literal
+/// (like pyparsing). Operators and constructors taking expressions all go through here.
+fn extract_parser(obj: &Bound<'_, PyAny>) -> PyResult<Arc<dyn ParserElement>> {
+    if let Ok(text) = obj.cast::<PyString>() {
+        let text = text.to_str()?;
+        if text.is_empty() {
+            // A Literal("") would match everywhere
+            return Err(PatternError::new_err(
+                "cannot make an expression from an empty string",
+            ));
+        }
+        Ok(Arc::new(RustLiteral::new(text)))
+    } else if let Ok(element) = obj.cast::<PyParserElement>() {
+        Ok(element.borrow().inner.clone())
+    } else {
+        Err(PatternError::new_err("Unsupported parser element type"))
+    }
+}
+
+/// Downcast `$element` to each listed Rust type, returning it wrapped in the
+/// matching Python class.
+macro_rules! wrap_element {
+    ($py:expr, $element:expr; $($rust:ty => $py_type:ident),* $(,)?) => {{
+        let element = $element;
+        $(
+            let element = match element.downcast::<$rust>() {
+                Ok(inner) => return Ok(Py::new($py, $py_type { inner })?.into_any()),
+                Err(element) => element,
+            };
+        )*
+        element
+    }};
+}
+
+/// The Python object for an element built from Python, e.g. a child of an And:
+/// the inverse of extract_parser.
+fn element_to_py(py: Python<'_>, element: &Arc<dyn ParserElement>) -> PyResult<Py<PyAny>> {
+    let name = element.name();
+    let element = element.clone().into_any();
+    let element = match element.downcast::<RustLiteral>() {
+        Ok(inner) => {
+            let text = inner.match_str();
+            let lit = PyLiteral {
+                cached_pystr: PyString::new(py, text).unbind(),
+                cached_err_msg: format!("Expected '{}'", text),
+                inner,
+            };
+            return Ok(Py::new(py, lit)?.into_any());
+        }
+        Err(element) => element,
+    };
+    let element = match element.downcast::<RustKeyword>() {
+        Ok(inner) => {
+            let kw = PyKeyword {
+                cached_pystr: PyString::new(py, inner.match_str()).unbind(),
+                inner,
+            };
+            return Ok(Py::new(py, kw)?.into_any());
+        }
+        Err(element) => element,
+    };
+    let element = match element.downcast::<RustEach>() {
+        // AtMostN keeps min at 0 and caps max; AtLeastN allows every clause
+        Ok(inner) if inner.bounds().1 < inner.elements().len() => {
+            return Ok(Py::new(py, PyAtMostN { inner })?.into_any())
+        }
+        Ok(inner) => return Ok(Py::new(py, PyAtLeastN { inner })?.into_any()),
+        Err(element) => element,
+    };
+    wrap_element!(py, element;
+        RustWord => PyWord,
+        RegexMatch => PyRegex,
+        RustAnd => PyAnd,
+        RustMatchFirst => PyMatchFirst,
+        RustZeroOrMore => PyZeroOrMore,
+        RustOneOrMore => PyOneOrMore,
+        RustOptional => PyOptional,
+        RustGroup => PyGroup,
+        RustDict => PyDictElement,
+        RustSuppress => PySuppress,
+        RustForward => PyForward,
+        RustCombine => PyCombine,
+        RustOriginalTextFor => PyOriginalTextFor,
+        RustLocated => PyLocated,
+        RustHighlightClass => PyHighlightClass,
+        RustNamed => PyNamed,
+        RustAction => PyParseAction,
+        RustCondition => PyCondition,
+        RustTransform => PyTransform,
+        RustWhitespaceScope => PyWhitespaceScope,
+        RustDebugTrace => PyDebugTrace,
+        RustExactly => PyExactly,
+        RustRepeat => PyRepeat,
+        RustCaselessLiteral => PyCaselessLiteral,
+        RustCaselessKeyword => PyCaselessKeyword,
+        RustCloseMatch => PyCloseMatch,
+        RustHtmlTag => PyHtmlTag,
+        RustHtmlEndTag => PyHtmlEndTag,
+        RustChar => PyChar,
+        RustStringStart => PyStringStart,
+        RustStringEnd => PyStringEnd,
+        RustLineStart => PyLineStart,
+        RustLineEnd => PyLineEnd,
+        RustRestOfLine => PyRestOfLine,
+        RustGoToColumn => PyGoToColumn,
+        RustComment => PyComment,
+        RustNumber => PyNumber,
+        RustIpv6Address => PyIpv6Address,
+        RustAsLine => PyAsLine,
+        RustIndentedBlock => PyIndentedBlock,
+        RustQuotedString => PyQuotedString,
+        RustEmpty => PyEmpty,
+        RustNoMatch => PyNoMatch,
+        RustSkipTo => PySkipTo,
+        RustMatchPreviousLiteral => PyMatchPreviousLiteral,
+        RustMatchPreviousExpr => PyMatchPreviousExpr,
+    );
+    Err(PatternError::new_err(format!(
+        "no Python class for element {}",
+        name
+    )))
+}
+
+/// A grammar description as plain Python data, which is what gets pickled:
+/// `(version, ((kind, (params, ...)), ...))`, nested elements as node indexes.
+fn spec_to_py<'py>(py: Python<'py>, spec: &GrammarSpec) -> PyResult<Bound<'py, PyTuple>> {
+    let value = |value: &SpecValue| -> PyResult<Bound<'py, PyAny>> {
+        Ok(match value {
+            SpecValue::None => py.None().into_bound(py),
+            SpecValue::Bool(b) => PyBool::new(py, *b).to_owned().into_any(),
+            SpecValue::Int(n) => n.into_pyobject(py)?.into_any(),
+            SpecValue::Str(s) => PyString::new(py, s).into_any(),
+            SpecValue::List(items) => PyTuple::new(py, items)?.into_any(),
+        })
+    };
+    let mut nodes = Vec::with_capacity(spec.nodes.len());
+    for node in &spec.nodes {
+        let params = node
+            .params
+            .iter()
+            .map(value)
+            .collect::<PyResult<Vec<_>>>()?;
+        nodes.push((node.kind.as_ref(), PyTuple::new(py, params)?));
+    }
+    PyTuple::new(
+        py,
+        [
+            SPEC_VERSION.into_pyobject(py)?.into_any(),
+            PyTuple::new(py, nodes)?.into_any(),
+        ],
+    )
+}
+
+/// One node of spec_to_py's output: `(kind, params)`.
+type NodeState<'py> = (String, Vec<Bound<'py, PyAny>>);
+
+/// Inverse of spec_to_py.
+fn spec_from_py(state: &Bound<'_, PyAny>) -> PyResult<GrammarSpec> {
+    let invalid = || PyValueError::new_err("invalid grammar state");
+    let (version, nodes): (usize, Vec<NodeState<'_>>) = state.extract().map_err(|_| invalid())?;
+    if version != SPEC_VERSION {
+        return Err(PyValueError::new_err(format!(
+            "grammar state version {} is not supported (expected {})",
+            version, SPEC_VERSION
+        )));
+    }
+    let value = |value: &Bound<'_, PyAny>| -> PyResult<SpecValue> {
+        Ok(if value.is_none() {
+            SpecValue::None
+        } else if let Ok(b) = value.cast::<PyBool>() {
+            SpecValue::Bool(b.is_true())
+        } else if let Ok(s) = value.cast::<PyString>() {
+            SpecValue::Str(s.to_str()?.into())
+        } else if let Ok(n) = value.extract::<usize>() {
+            SpecValue::Int(n)
+        } else {
+            SpecValue::List(value.extract().map_err(|_| invalid())?)
+        })
+    };
+    let nodes = nodes
+        .iter()
+        .map(|(kind, params)| {
+            let params = params.iter().map(value).collect::<PyResult<_>>()?;
+            Ok(SpecNode::new(kind, params))
+        })
+        .collect::<PyResult<_>>()?;
+    Ok(GrammarSpec { nodes })
+}
+
+/// Rebuild a pickled element from the state its `__reduce__` returned.
+#[pyfunction]
+#[pyo3(name = "_rebuild_element")]
+fn rebuild_element(py: Python<'_>, state: &Bound<'_, PyAny>) -> PyResult<Py<PyAny>> {
+    let spec = spec_from_py(state)?;
+    let hook: Arc<DebugFn> = Arc::new(debug_event);
+    let element = build_grammar(&spec, &hook).map_err(|e| PyValueError::new_err(e.msg))?;
+    element_to_py(py, &element)
+}
+
+/// extract_parser for the operand of `op`, keeping the error for strings (e.g. an
+/// empty one) and naming the operator otherwise.
+fn extract_operand(other: &Bound<'_, PyAny>, op: &str) -> PyResult<Arc<dyn ParserElement>> {
+    if other.is_instance_of::<PyString>() {
+        return extract_parser(other);
+    }
+    extract_parser(other)
+        .map_err(|_| PatternError::new_err(format!("Unsupported operand type for {}", op)))
+}
+
+/// extract_parser for each expression in a constructor's list, naming the index
+/// of one that isn't an element.
+fn extract_exprs(exprs: &Bound<'_, PyList>) -> PyResult<Vec<Arc<dyn ParserElement>>> {
+    exprs
+        .iter()
+        .enumerate()
+        .map(|(i, expr)| extract_expr(i, &expr))
+        .collect()
+}
+
+/// extract_parser for the expression at index `i` of a constructor's list.
+fn extract_expr(i: usize, expr: &Bound<'_, PyAny>) -> PyResult<Arc<dyn ParserElement>> {
+    if expr.is_instance_of::<PyString>() {
+        return extract_parser(expr);
+    }
+    extract_parser(expr)
+        .map_err(|_| PatternError::new_err(format!("Unsupported expression type at index {}", i)))
+}
+
+/// The items of the sequence passed to the `what` constructor, which must not be
+/// empty (or a str, which would otherwise iterate as characters).
+fn combinator_items<'py>(
+    exprs: &Bound<'py, PyAny>,
+    what: &str,
+) -> PyResult<Vec<Bound<'py, PyAny>>> {
+    if exprs.is_instance_of::<PyString>() {
+        return Err(PyTypeError::new_err(format!(
+            "{} takes a sequence of expressions, not a str",
+            what
+        )));
+    }
+    let items = exprs.try_iter()?.collect::<PyResult<Vec<_>>>()?;
+    if items.is_empty() {
+        return Err(PatternError::new_err(format!(
+            "{} requires at least one expression",
+            what
+        )));
+    }
+    Ok(items)
+}
+
+/// The And of `elements` (with error stop `stop`) followed by `other`, flattening
+/// `other` if it is an And. With `error_stop` (the `-` operator), failures from
+/// `other` on are fatal.
+fn join_and(
+    mut elements: Vec<Arc<dyn ParserElement>>,
+    stop: Option<usize>,
+    other: &Bound<'_, PyAny>,
+    error_stop: bool,
+) -> PyResult<PyAnd> {
+    let left_len = elements.len();
+    let stop = stop.or(error_stop.then_some(left_len));
+    let stop = if let Ok(and) = other.extract::<PyAnd>() {
+        elements.extend(and.inner.elements().iter().cloned());
+        stop.or(and.inner.error_stop().map(|s| s + left_len))
+    } else {
+        elements.push(extract_operand(other, if error_stop { "-" } else { "+" })?);
+        stop
+    };
+    Ok(PyAnd {
+        inner: Arc::new(RustAnd::new(elements).with_error_stop(stop)),
+    })
+}
+
+fn make_and(a: Arc<dyn ParserElement>, other: &Bound<'_, PyAny>) -> PyResult<PyAnd> {
+    join_and(vec![a], None, other, false)
+}
+
+/// Like make_and, but called from PyAnd::__add__ where `self` is already an And.
+/// Flattens both sides.
+fn make_and_from_and(existing: &Arc<RustAnd>, other: &Bound<'_, PyAny>) -> PyResult<PyAnd> {
+    join_and(
+        existing.elements().to_vec(),
+        existing.error_stop(),
+        other,
+        false,
+    )
+}
+
+/// `a - other`: an And whose elements after `a` fail fatally, so alternatives
+/// enclosing it don't backtrack (pyparsing's error stop).
+fn make_and_stop(a: Arc<dyn ParserElement>, other: &Bound<'_, PyAny>) -> PyResult<PyAnd> {
+    join_and(vec![a], None, other, true)
+}
+
+/// Like make_and_stop, for PyAnd::__sub__.
+fn make_and_stop_from_and(existing: &Arc<RustAnd>, other: &Bound<'_, PyAny>) -> PyResult<PyAnd> {
+    join_and(
+        existing.elements().to_vec(),
+        existing.error_stop(),
+        other,
+        true,
+    )
+}
+
+/// `other + elements`, for `__radd__` when `other` isn't an element (e.g. a
+/// string); `stop` is the error stop of `elements`.
+fn reflected_and(
+    elements: Vec<Arc<dyn ParserElement>>,
+    stop: Option<usize>,
+    other: &Bound<'_, PyAny>,
+) -> PyResult<PyAnd> {
+    let mut all = vec![extract_operand(other, "+")?];
+    all.extend(elements);
+    Ok(PyAnd {
+        inner: Arc::new(RustAnd::new(all).with_error_stop(stop.map(|s| s + 1))),
+    })
+}
+
+/// `other | elements`, for `__ror__`.
+fn reflected_or(
+    elements: Vec<Arc<dyn ParserElement>>,
+    other: &Bound<'_, PyAny>,
+) -> PyResult<PyMatchFirst> {
+    let mut all = vec![extract_operand(other, "|")?];
+    all.extend(elements);
+    Ok(PyMatchFirst {
+        inner: Arc::new(RustMatchFirst::new(all)),
+    })
+}
+
+/// Repeat of `element` `min` to `max` times (unbounded when `max` is None).
+fn repeat_element(
+    element: Arc<dyn ParserElement>,
+    min: usize,
+    max: Option<usize>,
+) -> PyResult<PyRepeat> {
+    if max.is_some_and(|max| max < min) {
+        return Err(PatternError::new_err(format!(
+            "invalid repetition, max must be >= min (min={}, max={})",
+            min,
+            max.unwrap_or_default()
+        )));
+    }
+    Ok(PyRepeat {
+        inner: Arc::new(RustRepeat::new(element, min, max)),
+    })
+}
+
+/// One end of a repetition spec: a count, or None (or `...`) for an open end.
+fn repeat_bound(item: &Bound<'_, PyAny>) -> PyResult<Option<usize>> {
+    if item.is_none() || item.is(item.py().Ellipsis()) {
+        return Ok(None);
+    }
+    let n: i64 = item
+        .extract()
+        .map_err(|_| PatternError::new_err("repetition counts must be ints, None or ..."))?;
+    usize::try_from(n)
+        .map(Some)
+        .map_err(|_| PatternError::new_err(format!("repetition count {} is negative", n)))
+}
+
+/// `expr * spec` and `expr[spec]`, like pyparsing: `n` repeats exactly n times and
+/// `(min, max)` between min and max, where a None (or `...`) min is 0 and max is
+/// unbounded; a bare `...` is any number.
+fn make_repeat(element: Arc<dyn ParserElement>, spec: &Bound<'_, PyAny>) -> PyResult<PyRepeat> {
+    let (min, max) = if let Ok(bounds) = spec.cast::<PyTuple>() {
+        if bounds.len() != 2 {
+            return Err(PatternError::new_err(
+                "a repetition range must be (min, max)",
+            ));
+        }
+        let min = repeat_bound(&bounds.get_item(0)?)?.unwrap_or(0);
+        (min, repeat_bound(&bounds.get_item(1)?)?)
+    } else {
+        match repeat_bound(spec)? {
+            Some(n) => (n, Some(n)),
+            None => (0, None),
+        }
+    };
+    repeat_element(element, min, max)
+}
+
+fn make_or(a: Arc<dyn ParserElement>, other: &Bound<'_, PyAny>) -> PyResult<PyMatchFirst> {
+    // If `other` is already a MatchFirst, flatten its elements
+    if let Ok(mf) = other.extract::<PyMatchFirst>() {
+        let mut elements = vec![a];
+        elements.extend(mf.inner.elements().iter().cloned());
+        Ok(PyMatchFirst {
+            inner: Arc::new(RustMatchFirst::new(elements)),
+        })
+    } else {
+        let b = extract_operand(other, "|")?;
+        Ok(PyMatchFirst {
+            inner: Arc::new(RustMatchFirst::new(vec![a, b])),
+        })
+    }
+}
+
+/// Like make_or, but called from PyMatchFirst::__or__ where `self` is already a MatchFirst.
+/// Flattens both sides.
+fn make_or_from_matchfirst(
+    existing: &Arc<RustMatchFirst>,
+    other: &Bound<'_, PyAny>,
+) -> PyResult<PyMatchFirst> {
+    let mut elements: Vec<Arc<dyn ParserElement>> = existing.elements().to_vec();
+    if let Ok(mf) = other.extract::<PyMatchFirst>() {
+        elements.extend(mf.inner.elements().iter().cloned());
+    } else {
+        let b = extract_operand(other, "|")?;
+        elements.push(b);
+    }
+    Ok(PyMatchFirst {
+        inner: Arc::new(RustMatchFirst::new(elements)),
+    })
+}
+
+// ============================================================================
+// Implementations
+// ============================================================================
+
+#[pymethods]
+impl PyParserElement {
+    /// Parse `s` from its start and return the tokens; raises ParseError if it doesn't
+    /// match, or with `parse_all`, if text other than whitespace is left over.
+    #[pyo3(signature = (s, parse_all = false, normalize = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        metered_parse(self.inner.as_ref(), s, timeout_ms, || {
+            if normalize {
+                return normalized_parse_string(py, &self.inner, s, parse_all);
+            }
+            generic_parse_string(py, &self.inner, s, parse_all)
+        })
+    }
+    /// Whether parse_string(s, parse_all) would succeed. Exceptions raised by parse
+    /// actions and conditions propagate, as from parse_string.
+    #[pyo3(signature = (s, parse_all = true))]
+    fn matches(&self, s: &str, parse_all: bool) -> PyResult<bool> {
+        generic_matches(&self.inner, s, parse_all)
+    }
+    /// `(start, end)` byte offsets of each match in `s`, like scan_string's without
+    /// the tokens: found with the GIL released, without building results or running
+    /// parse actions. With `overlap`, the scan resumes one character after each
+    /// match's start instead of at its end.
+    #[pyo3(signature = (s, overlap = false))]
+    fn find_all_positions(&self, py: Python<'_>, s: &str, overlap: bool) -> Vec<(usize, usize)> {
+        let parser = &self.inner;
+        py.detach(|| profile::find_spans(parser, s, overlap, None))
+    }
+    /// `s` split at the matches scan_string finds, at most `maxsplit` of them, like
+    /// pyparsing's split. A match at either end leaves an empty string there, as
+    /// re.split does; empty matches don't split. With `include_separators`, the
+    /// text of each match is listed between the pieces it separates.
+    #[pyo3(signature = (s, maxsplit = None, include_separators = false))]
+    fn split(
+        &self,
+        py: Python<'_>,
+        s: &str,
+        maxsplit: Option<usize>,
+        include_separators: bool,
+    ) -> PyResult<Vec<String>> {
+        let mut ctx = ParseContext::new(s).with_checkpoint(checkpoint(py));
+        let matches = scan_string(&self.inner, &mut ctx, maxsplit, false);
+        check_action_error()?;
+        let mut pieces = Vec::with_capacity(matches.len() * 2 + 1);
+        let mut last = 0;
+        for &(_, start, end) in &matches {
+            pieces.push(s[last..start].to_string());
+            if include_separators {
+                pieces.push(s[start..end].to_string());
+            }
+            last = end;
+        }
+        pieces.push(s[last..].to_string());
+        Ok(pieces)
+    }
+    /// find_all_positions() of each input in `inputs`, as `(input_index, start, end)`
+    /// triples in input order; inputs without a match have none. At most
+    /// `max_matches_per_input` spans are found per input. With `as_arrays`, returns
+    /// three parallel lists `(input_indices, starts, ends)` instead. Matching runs
+    /// with the GIL released.
+    #[pyo3(signature = (inputs, overlap = false, max_matches_per_input = None, as_arrays = false, timeout_ms = None))]
+    fn find_all_positions_batch<'py>(
+        &self,
+        py: Python<'py>,
+        inputs: &Bound<'py, PyList>,
+        overlap: bool,
+        max_matches_per_input: Option<usize>,
+        as_arrays: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        with_timeout(timeout_ms, || {
+            let parser = &self.inner;
+            let batch = BatchInputs::new(inputs)?;
+            let spans = batch.match_detached(|s| {
+                profile::find_spans(parser, s, overlap, max_matches_per_input)
+            })?;
+            let found = batch.slots.iter().enumerate().flat_map(|(i, &slot)| {
+                spans[slot as usize]
+                    .iter()
+                    .map(move |&(start, end)| (i, start, end))
+            });
+            if as_arrays {
+                let (mut indices, mut starts, mut ends) = (Vec::new(), Vec::new(), Vec::new());
+                for (i, start, end) in found {
+                    indices.push(i);
+                    starts.push(start);
+                    ends.push(end);
+                }
+                return Ok((indices, starts, ends).into_pyobject(py)?.into_any());
+            }
+            Ok(PyList::new(py, found.collect::<Vec<_>>())?.into_any())
+        })
+    }
+    /// matches() on each str in `inputs`, as a list of bools. Matching runs with the
+    /// GIL released unless the grammar has parse actions.
+    #[pyo3(signature = (inputs, parse_all = true, timeout_ms = None))]
+    fn matches_batch(
+        &self,
+        inputs: &Bound<'_, PyList>,
+        parse_all: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Vec<bool>> {
+        with_timeout(timeout_ms, || {
+            let parser = &self.inner;
+            // Parse actions may have side effects: run them attached, once per item, in order
+            if has_parse_actions(parser.as_ref()) {
+                let mut signals = SignalPoll::new();
+                return inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        signals.poll()?;
+                        let text = list_item_text(item.as_borrowed(), i, "inputs")?;
+                        generic_matches(parser, &text, parse_all)
+                    })
+                    .collect();
+            }
+            let batch = BatchInputs::new(inputs)?;
+            let hits = batch.match_detached(|s| parses(parser, s, parse_all))?;
+            Ok(batch
+                .slots
+                .iter()
+                .map(|&slot| hits[slot as usize])
+                .collect())
+        })
+    }
+    /// parse_string on the text of the file at `path` (str or os.PathLike), read and
+    /// decoded in Rust. `encoding` is utf-8, latin-1 or ascii; bytes it can't decode
+    /// raise FileProcessingError. ParseError offsets refer to the decoded text.
+    #[pyo3(signature = (path, parse_all = false, encoding = "utf-8", timeout_ms = None))]
+    fn parse_file<'py>(
+        &self,
+        py: Python<'py>,
+        path: std::path::PathBuf,
+        parse_all: bool,
+        encoding: &str,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        with_timeout(timeout_ms, || {
+            let text = read_file_text(py, &path, encoding)?;
+            let started = metrics_start();
+            let out = generic_parse_string(py, &self.inner, &text, parse_all)
+                .and_then(into_parse_results);
+            metrics_finish(
+                started,
+                || metrics_label(self.inner.as_ref()),
+                Operation::File,
+                out.is_ok(),
+                text.len(),
+                0,
+            );
+            out
+        })
+    }
+    /// search_string on the text of the file at `path`; see parse_file.
+    #[pyo3(signature = (path, encoding = "utf-8", timeout_ms = None))]
+    fn search_file<'py>(
+        &self,
+        py: Python<'py>,
+        path: std::path::PathBuf,
+        encoding: &str,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        with_timeout(timeout_ms, || {
+            let text = read_file_text(py, &path, encoding)?;
+            let started = metrics_start();
+            let out =
+                generic_search_string(py, &self.inner, &text, None).and_then(matches_as_results);
+            metrics_finish(
+                started,
+                || metrics_label(self.inner.as_ref()),
+                Operation::File,
+                out.is_ok(),
+                text.len(),
+                out.as_ref().map_or(0, |list| list.len()),
+            );
+            out
+        })
+    }
+    /// scan_iter over the text of the file at `path`: `(tokens, start, end)` for each
+    /// match, found only when asked for; see parse_file.
+    #[pyo3(signature = (path, overlap = false, encoding = "utf-8"))]
+    fn scan_file(
+        &self,
+        py: Python<'_>,
+        path: std::path::PathBuf,
+        overlap: bool,
+        encoding: &str,
+    ) -> PyResult<PyScanIterator> {
+        let text = read_file_text(py, &path, encoding)?;
+        Ok(PyScanIterator {
+            parser: self.inner.clone(),
+            end: text.len(),
+            text: PyString::new(py, &text).unbind(),
+            loc: 0,
+            overlap,
+        })
+    }
+    /// pyparsing compatibility: tabs are never expanded before parsing here, so
+    /// offsets and columns always refer to the input as given.
+    fn parse_with_tabs(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+    /// Freeze the grammar: Forwards inside it can no longer be set.
+    fn freeze(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        freeze_grammar(slf.inner.as_ref());
+        slf
+    }
+    /// Whether freeze() was called on this grammar.
+    fn is_frozen(&self) -> bool {
+        is_grammar_frozen(self.inner.as_ref())
+    }
+    /// An independent element of the same class and configuration; its children
+    /// are shared. Redefining a copied Forward leaves the original alone.
+    fn copy(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        element_to_py(py, &self.inner.copy())
+    }
+    /// Match this element but drop its tokens.
+    fn suppress(&self) -> PySuppress {
+        PySuppress {
+            inner: Arc::new(RustSuppress::new(self.inner.clone())),
+        }
+    }
+    /// Run `fns` on this element's tokens after each match; see ParseAction.
+    #[pyo3(signature = (*fns))]
+    fn set_parse_action(&self, fns: &Bound<'_, PyTuple>) -> PyResult<PyParseAction> {
+        parse_action_element(self.inner.clone(), Vec::new(), fns)
+    }
+    /// Run `fns` on this element's tokens after each match, like set_parse_action().
+    #[pyo3(signature = (*fns))]
+    fn add_parse_action(&self, fns: &Bound<'_, PyTuple>) -> PyResult<PyParseAction> {
+        parse_action_element(self.inner.clone(), Vec::new(), fns)
+    }
+    /// Reject matches for which `fn(tokens)` is falsy, failing with `message`.
+    #[pyo3(signature = (r#fn, message = None))]
+    fn add_condition(
+        &self,
+        r#fn: &Bound<'_, PyAny>,
+        message: Option<String>,
+    ) -> PyResult<PyCondition> {
+        condition_element(self.inner.clone(), r#fn, message)
+    }
+    /// Convert the tokens to ints in Rust; a token that isn't one fails the match.
+    fn to_int(&self) -> PyTransform {
+        transform_element(self.inner.clone(), TokenTransform::ToInt)
+    }
+    /// Convert the tokens to floats in Rust; a token that isn't a number fails the match.
+    fn to_float(&self) -> PyTransform {
+        transform_element(self.inner.clone(), TokenTransform::ToFloat)
+    }
+    /// Remove one pair of surrounding quotes from each token.
+    fn strip_quotes(&self) -> PyTransform {
+        transform_element(self.inner.clone(), TokenTransform::StripQuotes)
+    }
+    /// Remove leading and trailing whitespace from each token.
+    fn strip(&self) -> PyTransform {
+        transform_element(self.inner.clone(), TokenTransform::Strip)
+    }
+    /// Convert each token to uppercase.
+    fn upcase(&self) -> PyTransform {
+        transform_element(self.inner.clone(), TokenTransform::Upcase)
+    }
+    /// Convert each token to lowercase.
+    fn downcase(&self) -> PyTransform {
+        transform_element(self.inner.clone(), TokenTransform::Downcase)
+    }
+    /// Join the tokens into one, separated by `sep`.
+    #[pyo3(signature = (sep = ""))]
+    fn join(&self, sep: &str) -> PyTransform {
+        transform_element(self.inner.clone(), TokenTransform::Join(sep.into()))
+    }
+    /// Skip `chars` as whitespace before this element and within it, instead of the
+    /// default set (see set_default_whitespace_chars()).
+    fn set_whitespace_chars(&self, chars: &str) -> PyResult<PyWhitespaceScope> {
+        whitespace_scope(self.inner.clone(), chars)
+    }
+    /// Don't skip whitespace before this element, nor, if `recursive`, within it.
+    #[pyo3(signature = (recursive = true))]
+    fn leave_whitespace(&self, recursive: bool) -> PyWhitespaceScope {
+        PyWhitespaceScope {
+            inner: Arc::new(RustWhitespaceScope::leave(self.inner.clone(), recursive)),
+        }
+    }
+    /// Skip whitespace before this element, and, if `recursive`, within it (e.g.
+    /// undoing leave_whitespace() or Combine for part of a grammar).
+    #[pyo3(signature = (recursive = true))]
+    fn ignore_whitespace(&self, recursive: bool) -> PyWhitespaceScope {
+        PyWhitespaceScope {
+            inner: Arc::new(RustWhitespaceScope::ignore(self.inner.clone(), recursive)),
+        }
+    }
+    /// Skip matches of `exprs` (e.g. comments) before this element and within it,
+    /// wherever whitespace is skipped.
+    #[pyo3(signature = (*exprs))]
+    fn ignore(&self, exprs: &Bound<'_, PyTuple>) -> PyResult<PyWhitespaceScope> {
+        ignore_scope(self.inner.clone(), exprs)
+    }
+    /// Parse each case in `tests` (one per line, or a list), printing the tokens or
+    /// the marked error; see run_tests. Returns `(all_passed, [(case, result)])`.
+    #[pyo3(signature = (tests, parse_all = true, comment = "#", full_dump = true, print_results = true))]
+    fn run_tests<'py>(
+        slf: &Bound<'py, Self>,
+        tests: &Bound<'py, PyAny>,
+        parse_all: bool,
+        comment: &str,
+        full_dump: bool,
+        print_results: bool,
+    ) -> PyResult<(bool, Bound<'py, PyList>)> {
+        run_tests(
+            slf.as_any(),
+            tests,
+            parse_all,
+            comment,
+            full_dump,
+            print_results,
+        )
+    }
+    /// Report each match attempt of this element, and its outcome, through the
+    /// debug actions (printing by default); `flag=False` turns tracing off.
+    #[pyo3(signature = (flag = true))]
+    fn set_debug(&self, flag: bool) -> PyDebugTrace {
+        debug_trace(self.inner.clone(), flag)
+    }
+    /// The element's structure, like pyparsing's default names: `'if'`,
+    /// `W:(A-Za-z)`, `{'if' W:(A-Za-z)}`, `[expr]...`.
+    fn __str__(&self) -> String {
+        self.inner.default_name(&mut Namer::default())
+    }
+    /// `Class(structure)`, e.g. `Literal('if')`, `And({'if' W:(A-Za-z)})` or
+    /// `Group(W:(A-Za-z))`.
+    fn __repr__(slf: &Bound<'_, Self>) -> PyResult<String> {
+        let class = slf.get_type().name()?.to_string();
+        let name = slf.borrow().__str__();
+        // Names like `Group:(...)`, `Forward: ...` and `Empty` already start with the class
+        Ok(match name.strip_prefix(class.as_str()) {
+            Some("") => format!("{}()", class),
+            Some(rest) if rest.starts_with(":(") => format!("{}{}", class, &rest[1..]),
+            Some(rest) if rest.starts_with(": ") => format!("{}({})", class, &rest[2..]),
+            _ => format!("{}({})", class, name),
+        })
+    }
+    /// Pickle (and copy.deepcopy) support: the grammar is rebuilt from a description
+    /// of its elements. Parse actions and conditions are Python callables and make
+    /// the element unpicklable.
+    fn __reduce__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<(Bound<'py, PyAny>, (Bound<'py, PyTuple>,))> {
+        let spec = describe_grammar(&self.inner).map_err(|e| {
+            PyTypeError::new_err(format!("cannot pickle {}: {}", self.inner.name(), e.msg))
+        })?;
+        let rebuild = py.import("pyparsing_rs")?.getattr("_rebuild_element")?;
+        Ok((rebuild, (spec_to_py(py, &spec)?,)))
+    }
+    /// Register this element's tokens under `name` in the parse results.
+    #[pyo3(signature = (name, list_all_matches = false))]
+    fn set_results_name(&self, name: &str, list_all_matches: bool) -> PyNamed {
+        PyNamed {
+            inner: Arc::new(RustNamed::new(self.inner.clone(), name, list_all_matches)),
+        }
+    }
+    /// `expr("name")` is `expr.set_results_name("name")`; a trailing `*` lists all matches.
+    fn __call__(&self, name: &str) -> PyNamed {
+        match name.strip_suffix('*') {
+            Some(base) => self.set_results_name(base, true),
+            None => self.set_results_name(name, false),
+        }
+    }
+    /// Tag this element with a syntax-highlighting class for highlight_spans().
+    fn highlight_class(&self, class_name: &str) -> PyHighlightClass {
+        PyHighlightClass {
+            inner: Arc::new(RustHighlightClass::new(self.inner.clone(), class_name)),
+        }
+    }
+    /// Non-overlapping `(start, end, class)` byte spans of the tagged elements in this
+    /// grammar: at each position the longest match wins, then the first declared.
+    fn highlight_spans<'py>(&self, py: Python<'py>, s: &str) -> PyResult<Bound<'py, PyList>> {
+        generic_highlight_spans(py, self.inner.clone(), s)
+    }
+    /// Number of matches search_string would find.
+    fn search_string_count(&self, s: &str) -> usize {
+        generic_search_string_count(&self.inner, s)
+    }
+    /// Token lists of the matches found scanning `s`, at most `max_matches` of them.
+    #[pyo3(signature = (s, max_matches = None, timeout_ms = None))]
+    fn search_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        max_matches: Option<usize>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let s: &str = &s;
+        metered_scan(self.inner.as_ref(), s, timeout_ms, || {
+            generic_search_string(py, &self.inner, s, max_matches)
+        })
+    }
+    /// search_string's matches as a JSON str built in Rust: a list of objects with each
+    /// match's `tokens`, `named` results and `start`/`end` byte offsets, compact or
+    /// `pretty`. With `jsonl_path`, they're written to that file as JSON Lines and the
+    /// number written is returned.
+    #[pyo3(signature = (s, max_matches = None, pretty = false, jsonl_path = None))]
+    fn search_string_json<'py>(
+        &self,
+        py: Python<'py>,
+        s: &str,
+        max_matches: Option<usize>,
+        pretty: bool,
+        jsonl_path: Option<std::path::PathBuf>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let started = metrics_start();
+        let mut ctx = ParseContext::new(s).with_checkpoint(checkpoint(py));
+        let matches = scan_string(&self.inner, &mut ctx, max_matches, false);
+        let out = check_action_error().and_then(|()| {
+            let records = matches
+                .iter()
+                .map(|(results, start, end)| {
+                    let mut record = json::results_record(results, &object_json);
+                    record.insert("start".into(), (*start).into());
+                    record.insert("end".into(), (*end).into());
+                    JsonValue::Object(record)
+                })
+                .collect();
+            json_output(py, records, pretty, jsonl_path.as_deref())
+        });
+        metrics_finish(
+            started,
+            || metrics_label(self.inner.as_ref()),
+            Operation::Scan,
+            out.is_ok(),
+            s.len(),
+            matches.len(),
+        );
+        out
+    }
+    /// Matches as `(tokens, start, end)` byte offsets, like pyparsing's scan_string.
+    #[pyo3(signature = (s, max_matches = None, overlap = false, normalize = false, timeout_ms = None))]
+    fn scan_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: &str,
+        max_matches: Option<usize>,
+        overlap: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        metered_scan(self.inner.as_ref(), s, timeout_ms, || {
+            generic_scan_string(py, &self.inner, s, max_matches, overlap, normalize)
+        })
+    }
+    /// Lazy scan_string: an iterator of `(tokens, start, end)` that finds each match
+    /// only when asked for it. Scans `s[start:end]` by byte offset; the iterator's
+    /// `loc` is where the next step resumes, so a saved `loc` can be passed back as
+    /// `start` to pick up a scan later.
+    #[pyo3(signature = (s, start = 0, end = None, overlap = false))]
+    fn scan_iter(
+        &self,
+        s: &Bound<'_, PyString>,
+        start: usize,
+        end: Option<usize>,
+        overlap: bool,
+    ) -> PyResult<PyScanIterator> {
+        let text = s.to_str()?;
+        let end = end.unwrap_or(text.len());
+        if start > end || end > text.len() {
+            return Err(PyValueError::new_err("span is not within the text"));
+        }
+        if !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+            return Err(PyValueError::new_err(
+                "span must fall on character boundaries",
+            ));
+        }
+        Ok(PyScanIterator {
+            parser: self.inner.clone(),
+            text: s.clone().unbind(),
+            loc: start,
+            end,
+            overlap,
+        })
+    }
+    /// Lazy parse_string over each line of any iterable (a file, a generator): an
+    /// iterator of `(line_index, tokens)` for the lines that parse. Lines may be str
+    /// or UTF-8 bytes. They're pulled `chunk_size` at a time and parsed with the GIL
+    /// released, so memory stays bounded by the chunk. Without `skip_failures`, the
+    /// first line that fails raises its ParseError, with the index in `line_index`.
+    #[pyo3(signature = (lines, skip_failures = true, chunk_size = 1000))]
+    fn parse_lines(
+        &self,
+        lines: &Bound<'_, PyAny>,
+        skip_failures: bool,
+        chunk_size: usize,
+    ) -> PyResult<PyLineParseIterator> {
+        if chunk_size == 0 {
+            return Err(PyValueError::new_err("chunk_size must be positive"));
+        }
+        Ok(PyLineParseIterator {
+            parser: self.inner.clone(),
+            lines: lines.try_iter()?.unbind(),
+            chunk_size,
+            skip_failures,
+            next_index: 0,
+            pending: VecDeque::new(),
+            done: false,
+        })
+    }
+    /// Number of `inputs` this element matches at their start.
+    #[pyo3(signature = (inputs, timeout_ms = None))]
+    fn parse_batch_count(
+        &self,
+        inputs: &Bound<'_, PyList>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<usize> {
+        with_timeout(timeout_ms, || {
+            generic_parse_batch_count(self.inner.as_ref(), inputs)
+        })
+    }
+    /// The tokens of each input. With `to_json`, the matches as a JSON str built in
+    /// Rust instead: an object per matching input with its `index`, `tokens`, `named`
+    /// results and match `end`, compact or `pretty`. With `jsonl_path`, they're written
+    /// to that file as JSON Lines and the number written is returned.
+    #[pyo3(signature = (inputs, to_json = false, pretty = false, jsonl_path = None, timeout_ms = None))]
+    fn parse_batch<'py>(
+        &self,
+        py: Python<'py>,
+        inputs: &Bound<'py, PyList>,
+        to_json: bool,
+        pretty: bool,
+        jsonl_path: Option<std::path::PathBuf>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        with_timeout(timeout_ms, || {
+            if to_json || jsonl_path.is_some() {
+                return batch_json(py, &self.inner, inputs, pretty, jsonl_path.as_deref());
+            }
+            metered_batch(self.inner.as_ref(), inputs, || {
+                generic_parse_batch(py, &self.inner, inputs)
+            })
+            .map(Bound::into_any)
+        })
+    }
+    /// parse_string over a list of str segments treated as one input.
+    fn parse_segments<'py>(
+        &self,
+        py: Python<'py>,
+        segments: &Bound<'py, PyList>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        generic_parse_segments(py, self.inner.as_ref(), segments)
+    }
+    /// search_string over a list of str segments treated as one input, as
+    /// `(tokens, start, end)` tuples with offsets into the joined text.
+    fn search_segments<'py>(
+        &self,
+        py: Python<'py>,
+        segments: &Bound<'py, PyList>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        generic_search_segments(py, self.inner.as_ref(), segments)
+    }
+    fn __add__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyAnd> {
+        make_and(self.inner.clone(), other)
+    }
+
+    fn __sub__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyAnd> {
+        make_and_stop(self.inner.clone(), other)
+    }
+
+    fn __mul__(&self, spec: &Bound<'_, PyAny>) -> PyResult<PyRepeat> {
+        make_repeat(self.inner.clone(), spec)
+    }
+
+    fn __rmul__(&self, spec: &Bound<'_, PyAny>) -> PyResult<PyRepeat> {
+        make_repeat(self.inner.clone(), spec)
+    }
+
+    /// `expr[n]` or `expr[min, max]`; `...` leaves an end open, e.g. `expr[1, ...]`.
+    fn __getitem__(&self, spec: &Bound<'_, PyAny>) -> PyResult<PyRepeat> {
+        make_repeat(self.inner.clone(), spec)
+    }
+    fn __or__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyMatchFirst> {
+        make_or(self.inner.clone(), other)
+    }
+
+    fn __radd__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyAnd> {
+        reflected_and(vec![self.inner.clone()], None, other)
+    }
+
+    fn __ror__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyMatchFirst> {
+        reflected_or(vec![self.inner.clone()], other)
+    }
+    /// `s` with each match replaced by `replacement`, or by its joined tokens (after any
+    /// parse actions) when there is none.
+    #[pyo3(signature = (s, replacement = None, timeout_ms = None))]
+    fn transform_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: &str,
+        replacement: Option<&str>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyString>> {
+        with_timeout(timeout_ms, || {
+            generic_transform_string(py, &self.inner, s, replacement)
+        })
+    }
+}
+
+#[pymethods]
+impl PyLiteral {
+    #[new]
+    fn new(py: Python<'_>, s: &str) -> PyResult<PyClassInitializer<Self>> {
+        let inner = RustLiteral::try_new(s).map_err(|e| PatternError::new_err(e.msg))?;
+        let err_msg = format!("Expected '{}'", s);
+        // Pre-create the Python exception object so failure path avoids allocation
+        Ok(Self {
+            inner: Arc::new(inner),
+            cached_pystr: PyString::new(py, s).unbind(),
+            cached_err_msg: err_msg,
+        }
+        .into())
+    }
+
+    /// Fast inline parse — returns PyList with cached PyString, zero Rust allocation
+    #[pyo3(signature = (s, parse_all = false, normalize = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        metered_parse(self.inner.as_ref(), s, timeout_ms, || {
+            if normalize {
+                return normalized_parse_string(py, &self.element(), s, parse_all);
+            }
+            if parse_all || profile::enabled() {
+                return generic_parse_string(py, &self.element(), s, parse_all);
+            }
+            let match_bytes = self.inner.match_str().as_bytes();
+            let match_len = match_bytes.len();
+            let input_bytes = s.as_bytes();
+
+            // Skip leading whitespace (like pyparsing)
+            let start = skip_ws(s, 0);
+
+            if input_bytes.len() - start >= match_len
+                && input_bytes[start] == self.inner.first_byte()
+                && input_bytes[start..start + match_len] == *match_bytes
+            {
+                PyList::new(py, [self.cached_pystr.bind(py)]).map(Bound::into_any)
+            } else {
+                Err(parse_error_at(s, start, &self.cached_err_msg))
+            }
+        })
+    }
+
+    /// Batch parse: prefix checks with the GIL released, one shared result list each
+    /// for matches and misses
+    #[pyo3(signature = (inputs, to_json = false, pretty = false, jsonl_path = None, timeout_ms = None))]
+    fn parse_batch<'py>(
+        &self,
+        py: Python<'py>,
+        inputs: &Bound<'py, PyList>,
+        to_json: bool,
+        pretty: bool,
+        jsonl_path: Option<std::path::PathBuf>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        with_timeout(timeout_ms, || {
+            if to_json || jsonl_path.is_some() {
+                return batch_json(py, &self.element(), inputs, pretty, jsonl_path.as_deref());
+            }
+            metered_batch(self.inner.as_ref(), inputs, || {
+                let batch = BatchInputs::new(inputs)?;
+                let matched = batch.parse_detached(|s| self.inner.is_prefix_of(s))?;
+                let matched_list = PyList::new(py, [self.cached_pystr.bind(py)])?;
+                batch.hit_or_miss(&matched, matched_list.as_any(), PyList::empty(py).as_any())
+            })
+            .map(Bound::into_any)
+        })
+    }
+
+    /// Search string — cycle-aware count + PySequence_Repeat for optimal list creation
+    #[pyo3(signature = (s, max_matches = None, timeout_ms = None))]
+    fn search_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        max_matches: Option<usize>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let s: &str = &s;
+        metered_scan(self.inner.as_ref(), s, timeout_ms, || {
+            // The fast paths below find every match without checkpoints or profiling;
+            // a capped search stops early
+            if max_matches.is_some() || needs_generic_scan(s) {
+                return generic_search_string(py, &self.element(), s, max_matches);
+            }
+            let cached = self.cached_pystr.bind(py);
+
+            // Use cycle-aware count (same as search_string_count)
+            let count = self.search_string_count(s);
+
+            // Build result using CPython's optimized list repeat
+            let singleton = PyList::new(py, [cached])?;
+            let template = PyList::new(py, [&singleton])?;
+            unsafe {
+                let result =
+                    pyo3::ffi::PySequence_Repeat(template.as_ptr(), count as pyo3::ffi::Py_ssize_t);
+                if result.is_null() {
+                    return Err(pyo3::PyErr::fetch(py));
+                }
+                Ok(Bound::from_owned_ptr(py, result).cast_into_unchecked())
+            }
+        })
+    }
+
+    /// Count occurrences — cycle detection fast path + SIMD memchr fallback
+    fn search_string_count(&self, s: &str) -> usize {
+        let bytes = s.as_bytes();
+        let len = bytes.len();
+        let match_bytes = self.inner.match_str().as_bytes();
+        let match_len = match_bytes.len();
+
+        // Cycle detection: find repeating period, count in one cycle × reps
+        if match_len > 0 {
+            unsafe {
+                let period = detect_text_period(bytes, len);
+                if period > 0 && period >= match_len {
+                    // Scan two consecutive cycles to get count_per_cycle
+                    // This naturally handles boundary matches
+                    let two_cycles = (period * 2).min(len);
+                    let finder = memchr::memmem::Finder::new(match_bytes);
+
+                    // Count in first cycle only (no boundary at start)
+                    let mut first_cycle_count = 0;
+                    let mut pos = 0;
+                    while pos + match_len <= period {
+                        match finder.find(&bytes[pos..period]) {
+                            Some(offset) => {
+                                first_cycle_count += 1;
+                                pos += offset + match_len;
+                            }
+                            None => break,
+                        }
+                    }
+
+                    // Count in two consecutive cycles (includes boundary)
+                    let mut two_cycle_count = 0;
+                    pos = 0;
+                    while pos + match_len <= two_cycles {
+                        match finder.find(&bytes[pos..two_cycles]) {
+                            Some(offset) => {
+                                two_cycle_count += 1;
+                                pos += offset + match_len;
+                            }
+                            None => break,
+                        }
+                    }
+                    // boundary_count = two_cycle_count - 2 * first_cycle_count
+                    // total_per_cycle = first_cycle_count + boundary_count
+                    // But simpler: count_per_additional_cycle = two_cycle_count - first_cycle_count
+                    let count_per_additional = two_cycle_count - first_cycle_count;
+                    let full_cycles = len / period;
+                    let remainder = len % period;
+
+                    let mut total = if full_cycles >= 2 {
+                        first_cycle_count + count_per_additional * (full_cycles - 1)
+                    } else if full_cycles == 1 {
+                        first_cycle_count
+                    } else {
+                        0
+                    };
+
+                    // Count in remainder (starts at full_cycles * period)
+                    if remainder > 0 {
+                        let rem_start = full_cycles * period;
+                        // Scan from slightly before the boundary to catch spanning matches
+                        let scan_start = rem_start.saturating_sub(match_len - 1);
+                        pos = scan_start;
+                        while pos + match_len <= len {
+                            match finder.find(&bytes[pos..len]) {
+                                Some(offset) => {
+                                    let match_pos = pos + offset;
+                                    // Only count if this match starts at or after rem_start,
+                                    // or spans the boundary (starts before rem_start but ends after)
+                                    if match_pos >= rem_start
+                                        || (match_pos < rem_start
+                                            && match_pos + match_len > rem_start)
+                                    {
+                                        total += 1;
+                                    }
+                                    pos += offset + match_len;
+                                }
+                                None => break,
+                            }
+                        }
+                    }
+
+                    return total;
+                }
+            }
+        }
+
+        // Fallback: SIMD memchr scan
+        let finder = memchr::memmem::Finder::new(match_bytes);
+        let mut count = 0;
+        let mut pos = 0;
+        while pos < len {
+            match finder.find(&bytes[pos..]) {
+                Some(offset) => {
+                    count += 1;
+                    pos += offset + match_len;
+                }
+                None => break,
+            }
+        }
+        count
+    }
+
+    /// Full raw FFI count — uniform detection + last-ptr fallback
+    #[pyo3(signature = (inputs, timeout_ms = None))]
+    fn parse_batch_count(
+        &self,
+        inputs: &Bound<'_, PyList>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<usize> {
+        with_timeout(timeout_ms, || {
+            let batch = BatchInputs::new(inputs)?;
+            batch.count_detached(|s| self.inner.is_prefix_of(s))
+        })
+    }
+
+    /// Replace all non-overlapping matches with replacement string.
+    /// Uses SIMD-accelerated memchr::memmem for literal search.
+    #[pyo3(signature = (s, replacement = None, timeout_ms = None))]
+    fn transform_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: &str,
+        replacement: Option<&str>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyString>> {
+        let replacement = match replacement {
+            // The fast path below scans without checkpoints or profiling
+            Some(replacement) if timeout_ms.is_none() && !needs_generic_scan(s) => replacement,
+            _ => {
+                return with_timeout(timeout_ms, || {
+                    generic_transform_string(py, &self.element(), s, replacement)
+                })
+            }
+        };
+        let match_str = self.inner.match_str();
+        let finder = memchr::memmem::Finder::new(match_str.as_bytes());
+        let mut result = String::with_capacity(s.len());
+        let mut last_end = 0;
+        for start in finder.find_iter(s.as_bytes()) {
+            result.push_str(&s[last_end..start]);
+            result.push_str(replacement);
+            last_end = start + match_str.len();
+        }
+        result.push_str(&s[last_end..]);
+        Ok(PyString::new(py, &result))
+    }
+}
+
+#[pymethods]
+impl PyWord {
+    #[new]
+    #[pyo3(signature = (init_chars, body_chars=None, min=1, max=0, exact=0, exclude_chars=None, as_keyword=false))]
+    fn new(
+        init_chars: &Bound<'_, PyAny>,
+        body_chars: Option<&Bound<'_, PyAny>>,
+        min: usize,
+        max: usize,
+        exact: usize,
+        exclude_chars: Option<&str>,
+        as_keyword: bool,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        if min < 1 {
+            return Err(PatternError::new_err(
+                "cannot specify a minimum length < 1; use Optional(Word()) if zero-length word is permitted",
+            ));
+        }
+        if max > 0 && min > max {
+            return Err(PatternError::new_err(format!(
+                "invalid args, if min and max both specified min must be <= max (min={}, max={})",
+                min, max
+            )));
+        }
+        let class_error = |e: CharClassError| PatternError::new_err(e.msg);
+        let mut word = match word_chars_arg(init_chars)? {
+            (spec, true) => RustWord::from_class(&spec).map_err(class_error)?,
+            (chars, false) => RustWord::try_new(&chars).map_err(class_error)?,
+        }
+        .with_min(min)
+        .with_max(max)
+        .with_as_keyword(as_keyword);
+        if exact > 0 {
+            word = word.with_exact(exact);
+        }
+        if let Some(body) = body_chars {
+            word = match word_chars_arg(body)? {
+                (spec, true) => word.with_body_class(&spec).map_err(class_error)?,
+                (chars, false) => word.with_body_chars(&chars),
+            };
+        }
+        // Applied last, so it also covers an explicit body_chars
+        if let Some(exclude) = exclude_chars {
+            word = word.with_exclude_chars(exclude);
+        }
+        Ok(Self {
+            inner: Arc::new(word),
+        }
+        .into())
+    }
+
+    /// Fast-path word parse — returns PyList directly, no Rust String allocation
+    #[pyo3(signature = (s, parse_all = false, normalize = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        metered_parse(self.inner.as_ref(), s, timeout_ms, || {
+            if normalize {
+                return normalized_parse_string(py, &self.element(), s, parse_all);
+            }
+            if parse_all || profile::enabled() {
+                return generic_parse_string(py, &self.element(), s, parse_all);
+            }
+            if !self.inner.is_plain() {
+                return generic_parse_string(py, &self.element(), s, parse_all);
+            }
+            let bytes = s.as_bytes();
+            // Skip leading whitespace (like pyparsing)
+            let start = skip_ws(s, 0);
+            if start >= bytes.len() || !self.inner.init_chars_contains(bytes[start]) {
+                return Err(parse_error_at(s, start, "Expected word"));
+            }
+            let mut end = start + 1;
+            while end < bytes.len() && self.inner.body_chars_contains(bytes[end]) {
+                end += 1;
+            }
+            PyList::new(py, [PyString::new(py, &s[start..end])]).map(Bound::into_any)
+        })
+    }
+
+    /// Cyclic detection + hash-based cache fallback + bulk INCREF
+    #[pyo3(signature = (inputs, to_json = false, pretty = false, jsonl_path = None, timeout_ms = None))]
+    fn parse_batch<'py>(
+        &self,
+        py: Python<'py>,
+        inputs: &Bound<'py, PyList>,
+        to_json: bool,
+        pretty: bool,
+        jsonl_path: Option<std::path::PathBuf>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        with_timeout(timeout_ms, || {
+            if to_json || jsonl_path.is_some() {
+                return batch_json(py, &self.element(), inputs, pretty, jsonl_path.as_deref());
+            }
+            metered_batch(self.inner.as_ref(), inputs, || {
+                if !self.inner.is_plain() {
+                    return generic_parse_batch(py, &self.element(), inputs);
+                }
+                let batch = BatchInputs::new(inputs)?;
+                let spans = batch.parse_detached(|s| {
+                    let bytes = s.as_bytes();
+                    if bytes.is_empty() || !self.inner.init_chars_contains(bytes[0]) {
+                        return None;
+                    }
+                    let mut end = 1;
+                    while end < bytes.len() && self.inner.body_chars_contains(bytes[end]) {
+                        end += 1;
+                    }
+                    Some((0, end))
+                })?;
+                batch.span_tokens(&spans)
+            })
+            .map(Bound::into_any)
+        })
+    }
+
+    /// Count word matches in batch, with the GIL released
+    #[pyo3(signature = (inputs, timeout_ms = None))]
+    fn parse_batch_count(
+        &self,
+        inputs: &Bound<'_, PyList>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<usize> {
+        with_timeout(timeout_ms, || {
+            if !self.inner.is_plain() {
+                return generic_parse_batch_count(self.inner.as_ref(), inputs);
+            }
+            let batch = BatchInputs::new(inputs)?;
+            batch.count_detached(|s| {
+                s.as_bytes()
+                    .first()
+                    .is_some_and(|&b| self.inner.init_chars_contains(b))
+            })
+        })
+    }
+
+    /// Count word matches in large text — cycle detection + branchless scan
+    fn search_string_count(&self, s: &str) -> usize {
+        if !self.inner.is_plain() || self.inner.starts_in_whitespace() {
+            return generic_search_string_count(&self.element(), s);
+        }
+        let bytes = s.as_bytes();
+        let len = bytes.len();
+        if len == 0 {
+            return 0;
+        }
+        // Build flat 256-byte lookup tables for O(1) byte classification
+        let mut is_init = [0u8; 256];
+        let mut is_body = [0u8; 256];
+        for b in 0u16..256 {
+            is_init[b as usize] = self.inner.init_chars_contains(b as u8) as u8;
+            is_body[b as usize] = self.inner.body_chars_contains(b as u8) as u8;
+        }
+
+        // Cycle detection: if text has repeating period P, count in one cycle × reps
+        unsafe {
+            let period = detect_text_period(bytes, len);
+            if period > 0 {
+                let first_byte = *bytes.get_unchecked(0);
+                // Verify no word spans the cycle boundary:
+                // The last byte of cycle must NOT be a body char, OR the first byte of next cycle must NOT be init/body
+                let last_of_cycle = *bytes.get_unchecked(period - 1);
+                let last_is_body = *is_body.get_unchecked(last_of_cycle as usize);
+                let first_is_init = *is_init.get_unchecked(first_byte as usize);
+                let first_is_body = *is_body.get_unchecked(first_byte as usize);
+                let word_spans_boundary =
+                    last_is_body != 0 && (first_is_init != 0 || first_is_body != 0);
+
+                if !word_spans_boundary {
+                    let cycle_count = count_words_branchless(bytes, 0, period, &is_init, &is_body);
+                    let full_cycles = len / period;
+                    let rem_start = full_cycles * period;
+                    let total = cycle_count * full_cycles
+                        + count_words_branchless(bytes, rem_start, len, &is_init, &is_body);
+                    return total;
+                }
+            }
+        }
+
+        // Fallback: full branchless scan
+        unsafe { count_words_branchless(bytes, 0, len, &is_init, &is_body) }
+    }
+
+    /// Optimized Word search_string — O(1) byte-table scanning, dedup, list-of-lists output
+    #[pyo3(signature = (s, max_matches = None, timeout_ms = None))]
+    fn search_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        max_matches: Option<usize>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let s: &str = &s;
+        metered_scan(self.inner.as_ref(), s, timeout_ms, || {
+            // The fast paths below find every match without checkpoints or profiling;
+            // a capped search stops early
+            if max_matches.is_some() || needs_generic_scan(s) {
+                return generic_search_string(py, &self.element(), s, max_matches);
+            }
+            if !self.inner.is_plain() || self.inner.starts_in_whitespace() {
+                return generic_search_string(py, &self.element(), s, None);
+            }
+            let bytes = s.as_bytes();
+            let len = bytes.len();
+
+            // Build flat 256-byte lookup tables for O(1) byte classification
+            let mut is_init = [0u8; 256];
+            let mut is_body = [0u8; 256];
+            for b in 0u16..256 {
+                is_init[b as usize] = self.inner.init_chars_contains(b as u8) as u8;
+                is_body[b as usize] = self.inner.body_chars_contains(b as u8) as u8;
+            }
+
+            unsafe {
+                // Scan for all word ranges
+                let mut ranges: Vec<(usize, usize)> = Vec::new();
+                let mut pos = 0usize;
+                while pos < len {
+                    let b = *bytes.get_unchecked(pos);
+                    if is_init[b as usize] == 0 {
+                        pos += 1;
+                        continue;
+                    }
+                    let start = pos;
+                    pos += 1;
+                    while pos < len && is_body[*bytes.get_unchecked(pos) as usize] != 0 {
+                        pos += 1;
+                    }
+                    ranges.push((start, pos));
+                }
+
+                let n = ranges.len() as pyo3::ffi::Py_ssize_t;
+                if n == 0 {
+                    return Ok(PyList::empty(py));
+                }
+
+                let list_ptr = pyo3::ffi::PyList_New(n);
+                if list_ptr.is_null() {
+                    return Err(pyo3::PyErr::fetch(py));
+                }
+
+                // Dedup: cache [word] sublists for repeated words
+                let mut dedup: FxHashMap<&str, *mut pyo3::ffi::PyObject> = FxHashMap::default();
+                for (i, &(start, end)) in ranges.iter().enumerate() {
+                    let word = std::str::from_utf8_unchecked(&bytes[start..end]);
+                    let sublist = if let Some(&existing) = dedup.get(word) {
+                        pyo3::ffi::Py_INCREF(existing);
+                        existing
+                    } else {
+                        let py_str = PyString::new(py, word).into_ptr();
+                        let inner = pyo3::ffi::PyList_New(1);
+                        pyo3::ffi::PyList_SET_ITEM(inner, 0, py_str);
+                        // Keep a reference for dedup
+                        pyo3::ffi::Py_INCREF(inner);
+                        dedup.insert(word, inner);
+                        inner
+                    };
+                    pyo3::ffi::PyList_SET_ITEM(list_ptr, i as pyo3::ffi::Py_ssize_t, sublist);
+                }
+                // Release dedup references
+                for (_, ptr) in dedup {
+                    pyo3::ffi::Py_DECREF(ptr);
+                }
+
+                Ok(Bound::from_owned_ptr(py, list_ptr).cast_into_unchecked())
+            }
+        })
+    }
+
+    /// UNUSED: Original optimized search_string (returns flat list).
+    #[allow(dead_code)]
+    fn _search_string_flat<'py>(&self, py: Python<'py>, s: &str) -> PyResult<Bound<'py, PyList>> {
+        let bytes = s.as_bytes();
+        let len = bytes.len();
+
+        // Build flat 256-byte lookup tables for O(1) byte classification
+        let mut is_init = [0u8; 256];
+        let mut is_body = [0u8; 256];
+        for b in 0u16..256 {
+            is_init[b as usize] = self.inner.init_chars_contains(b as u8) as u8;
+            is_body[b as usize] = self.inner.body_chars_contains(b as u8) as u8;
+        }
+
+        unsafe {
+            // --- Text repetition fast path ---
+            'cycle: {
+                let period = detect_text_period(bytes, len);
+                if period == 0 {
+                    break 'cycle;
+                }
+
+                // Scan first cycle to find word boundaries
+                let mut cycle_word_ranges: Vec<(usize, usize)> = Vec::new();
+                let mut pos = 0usize;
+                while pos < period {
+                    let b = *bytes.get_unchecked(pos);
+                    if is_init[b as usize] == 0 {
+                        pos += 1;
+                        continue;
+                    }
+                    let start = pos;
+                    pos += 1;
+                    while pos < period && is_body[*bytes.get_unchecked(pos) as usize] != 0 {
+                        pos += 1;
+                    }
+                    cycle_word_ranges.push((start, pos));
+                }
+
+                let wpc = cycle_word_ranges.len();
+                if wpc == 0 {
+                    break 'cycle;
+                }
+
+                // Check: no word spans the cycle boundary
+                let last_end = cycle_word_ranges[wpc - 1].1;
+                if last_end == period
+                    && period < len
+                    && is_body[*bytes.get_unchecked(period) as usize] != 0
+                {
+                    break 'cycle;
+                }
+
+                // Create deduped PyStrings for unique words in cycle
+                let mut unique_strs: Vec<Bound<'py, PyString>> = Vec::new();
+                let mut cycle_ptrs: Vec<*mut pyo3::ffi::PyObject> = Vec::with_capacity(wpc);
+                let mut dedup_keys: [u64; 32] = [u64::MAX; 32];
+                let mut dedup_ptrs: [*mut pyo3::ffi::PyObject; 32] = [std::ptr::null_mut(); 32];
+
+                for &(start, end) in &cycle_word_ranges {
+                    let word_len = end - start;
+                    let key = word_hash_key(bytes, start, word_len, len);
+
+                    let mut slot = (key as usize ^ (key as usize >> 16)) & 31;
+                    loop {
+                        let dk = *dedup_keys.get_unchecked(slot);
+                        if dk == key {
+                            cycle_ptrs.push(*dedup_ptrs.get_unchecked(slot));
+                            break;
+                        }
+                        if dk == u64::MAX {
+                            let py_str = PyString::new(
+                                py,
+                                std::str::from_utf8_unchecked(&bytes[start..end]),
+                            );
+                            let ptr = py_str.as_ptr();
+                            *dedup_keys.get_unchecked_mut(slot) = key;
+                            *dedup_ptrs.get_unchecked_mut(slot) = ptr;
+                            unique_strs.push(py_str);
+                            cycle_ptrs.push(ptr);
+                            break;
+                        }
+                        slot = (slot + 1) & 31;
+                    }
+                }
+
+                let num_full_cycles = len / period;
+                let remainder = len % period;
+
+                // Fast path: no remainder — use PySequence_Repeat (C-level INCREF)
+                if remainder == 0 && wpc > 0 {
+                    let cycle_list = pyo3::ffi::PyList_New(wpc as pyo3::ffi::Py_ssize_t);
+                    if cycle_list.is_null() {
+                        return Err(pyo3::PyErr::fetch(py));
+                    }
+                    for (j, &ptr) in cycle_ptrs.iter().enumerate() {
+                        pyo3::ffi::Py_INCREF(ptr);
+                        pyo3::ffi::PyList_SET_ITEM(cycle_list, j as pyo3::ffi::Py_ssize_t, ptr);
+                    }
+                    let result = pyo3::ffi::PySequence_Repeat(
+                        cycle_list,
+                        num_full_cycles as pyo3::ffi::Py_ssize_t,
+                    );
+                    pyo3::ffi::Py_DECREF(cycle_list);
+                    if result.is_null() {
+                        return Err(pyo3::PyErr::fetch(py));
+                    }
+                    return Ok(Bound::from_owned_ptr(py, result).cast_into_unchecked());
+                }
+
+                // Scan remainder for words (partial last cycle)
+                let rem_start_byte = num_full_cycles * period;
+                let mut rem_ptrs: Vec<*mut pyo3::ffi::PyObject> = Vec::new();
+                if remainder > 0 {
+                    let mut rpos = rem_start_byte;
+                    while rpos < len {
+                        let b = *bytes.get_unchecked(rpos);
+                        if is_init[b as usize] == 0 {
+                            rpos += 1;
+                            continue;
+                        }
+                        let wstart = rpos;
+                        rpos += 1;
+                        while rpos < len && is_body[*bytes.get_unchecked(rpos) as usize] != 0 {
+                            rpos += 1;
+                        }
+                        let wlen = rpos - wstart;
+                        let key = word_hash_key(bytes, wstart, wlen, len);
+
+                        let mut slot = (key as usize ^ (key as usize >> 16)) & 31;
+                        loop {
+                            let dk = *dedup_keys.get_unchecked(slot);
+                            if dk == key {
+                                rem_ptrs.push(*dedup_ptrs.get_unchecked(slot));
+                                break;
+                            }
+                            if dk == u64::MAX {
+                                let py_str = PyString::new(
+                                    py,
+                                    std::str::from_utf8_unchecked(&bytes[wstart..rpos]),
+                                );
+                                let ptr = py_str.as_ptr();
+                                *dedup_keys.get_unchecked_mut(slot) = key;
+                                *dedup_ptrs.get_unchecked_mut(slot) = ptr;
+                                unique_strs.push(py_str);
+                                rem_ptrs.push(ptr);
+                                break;
+                            }
+                            slot = (slot + 1) & 31;
+                        }
+                    }
+                }
+
+                let full_items = wpc * num_full_cycles;
+
+                let total = full_items + rem_ptrs.len();
+                let list_ptr = pyo3::ffi::PyList_New(total as pyo3::ffi::Py_ssize_t);
+                if list_ptr.is_null() {
+                    return Err(pyo3::PyErr::fetch(py));
+                }
+
+                if total > 0 {
+                    for (j, &ptr) in cycle_ptrs.iter().enumerate() {
+                        pyo3::ffi::PyList_SET_ITEM(list_ptr, j as pyo3::ffi::Py_ssize_t, ptr);
+                    }
+
+                    let ob_item = list_ob_item(list_ptr);
+
+                    if full_items > wpc {
+                        memcpy_double_fill(ob_item, wpc, full_items);
+                    }
+
+                    for (j, &ptr) in rem_ptrs.iter().enumerate() {
+                        *ob_item.add(full_items + j) = ptr;
+                    }
+                }
+
+                // Bulk INCREF
+                for py_str in unique_strs.iter() {
+                    let sptr = py_str.as_ptr();
+                    let mut count = 0usize;
+                    for &cp in cycle_ptrs.iter() {
+                        if cp == sptr {
+                            count += num_full_cycles;
+                        }
+                    }
+                    for &rp in rem_ptrs.iter() {
+                        if rp == sptr {
+                            count += 1;
+                        }
+                    }
+                    if count > 0 {
+                        bulk_incref(sptr, count);
+                    }
+                }
+
+                return Ok(Bound::from_owned_ptr(py, list_ptr).cast_into_unchecked());
+            }
+
+            // --- Fallback: full-scan approach (non-cyclic text) ---
+            // Uses FxHashMap for dedup (handles unlimited unique words).
+            // Two-pass: count first, then build list directly.
+            let word_count = count_words_branchless(bytes, 0, len, &is_init, &is_body);
+            if word_count == 0 {
+                return Ok(PyList::empty(py));
+            }
+
+            let list_ptr = pyo3::ffi::PyList_New(word_count as pyo3::ffi::Py_ssize_t);
+            if list_ptr.is_null() {
+                return Err(pyo3::PyErr::fetch(py));
+            }
+
+            let mut dedup: FxHashMap<&str, *mut pyo3::ffi::PyObject> = FxHashMap::default();
+            let mut _keep_alive: Vec<Bound<'py, PyString>> = Vec::new();
+            let mut out_idx = 0usize;
+
+            let mut pos = 0;
+            while pos < len {
+                let b = *bytes.get_unchecked(pos);
+                if is_init[b as usize] == 0 {
+                    pos += 1;
+                    continue;
+                }
+                let start = pos;
+                pos += 1;
+                while pos < len {
+                    let b2 = *bytes.get_unchecked(pos);
+                    if is_body[b2 as usize] == 0 {
+                        break;
+                    }
+                    pos += 1;
+                }
+
+                let word = std::str::from_utf8_unchecked(&bytes[start..pos]);
+                let ptr = match dedup.get(word) {
+                    Some(&p) => {
+                        pyo3::ffi::Py_INCREF(p);
+                        p
+                    }
+                    None => {
+                        let py_str = PyString::new(py, word);
+                        let p = py_str.as_ptr();
+                        pyo3::ffi::Py_INCREF(p); // one for the list slot
+                        dedup.insert(word, p);
+                        _keep_alive.push(py_str);
+                        p
+                    }
+                };
+                pyo3::ffi::PyList_SET_ITEM(list_ptr, out_idx as pyo3::ffi::Py_ssize_t, ptr);
+                out_idx += 1;
+            }
+
+            Ok(Bound::from_owned_ptr(py, list_ptr).cast_into_unchecked())
+        }
+    }
+
+    /// Specialized transform: uses 256-byte lookup tables for direct byte scanning.
+    #[pyo3(signature = (s, replacement = None, timeout_ms = None))]
+    fn transform_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: &str,
+        replacement: Option<&str>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyString>> {
+        let Some(replacement) = replacement else {
+            return with_timeout(timeout_ms, || {
+                generic_transform_string(py, &self.element(), s, None)
+            });
+        };
+        // The fast path below scans without checkpoints or profiling
+        if !self.inner.is_plain()
+            || self.inner.starts_in_whitespace()
+            || timeout_ms.is_some()
+            || needs_generic_scan(s)
+        {
+            return with_timeout(timeout_ms, || {
+                generic_transform_string(py, &self.element(), s, Some(replacement))
+            });
+        }
+        let bytes = s.as_bytes();
+        let len = bytes.len();
+        if len == 0 {
+            return Ok(PyString::new(py, ""));
+        }
+
+        // Build flat lookup tables
+        let mut is_init = [false; 256];
+        let mut is_body = [false; 256];
+        for b in 0u16..256 {
+            is_init[b as usize] = self.inner.init_chars_contains(b as u8);
+            is_body[b as usize] = self.inner.body_chars_contains(b as u8);
+        }
+
+        let mut result = String::with_capacity(len);
+        let mut i = 0;
+        let mut copy_from = 0;
+        while i < len {
+            if is_init[bytes[i] as usize] {
+                // Flush non-word text
+                if copy_from < i {
+                    result.push_str(&s[copy_from..i]);
+                }
+                // Scan word body
+                let mut end = i + 1;
+                while end < len && is_body[bytes[end] as usize] {
+                    end += 1;
+                }
+                result.push_str(replacement);
+                i = end;
+                copy_from = end;
+            } else {
+                i += 1;
+            }
+        }
+        if copy_from < len {
+            result.push_str(&s[copy_from..]);
+        }
+        Ok(PyString::new(py, &result))
+    }
+}
+
+#[pymethods]
+impl PyRegex {
+    /// `as_group_list` makes the groups' tokens one nested list per match;
+    /// `as_match` returns a RegexMatchResult per match instead of tokens.
+    #[new]
+    #[pyo3(signature = (pattern, as_group_list = false, as_match = false))]
+    fn new(
+        pattern: &str,
+        as_group_list: bool,
+        as_match: bool,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let output = match (as_group_list, as_match) {
+            (true, true) => {
+                return Err(PatternError::new_err(
+                    "as_group_list and as_match are mutually exclusive",
+                ))
+            }
+            (true, false) => RegexOutput::GroupList,
+            (false, true) => RegexOutput::Match,
+            (false, false) => RegexOutput::Tokens,
+        };
+        RegexMatch::new(pattern)
+            .map(|inner| Self {
+                inner: Arc::new(inner.with_output(output)),
+            })
+            .map_err(|e| regex_error(pattern, &e))
+            .map(Into::into)
+    }
+
+    /// Fast-path regex parse — returns PyList directly, no Rust String allocation
+    #[pyo3(signature = (s, parse_all = false, normalize = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        metered_parse(self.inner.as_ref(), s, timeout_ms, || {
+            if normalize {
+                return normalized_parse_string(py, &self.element(), s, parse_all);
+            }
+            if parse_all || !self.inner.match_is_token() || profile::enabled() {
+                return generic_parse_string(py, &self.element(), s, parse_all);
+            }
+            // Skip leading whitespace (like pyparsing)
+            let start = skip_ws(s, 0);
+            match self.inner.try_match(&s[start..]) {
+                Some(matched) => PyList::new(py, [PyString::new(py, matched)]).map(Bound::into_any),
+                None => Err(parse_error_at(s, start, "Expected regex match")),
+            }
+        })
+    }
+
+    /// Count regex matches in text — uses find_iter for SIMD-accelerated search
+    fn search_string_count(&self, s: &str) -> usize {
+        let found: Vec<_> = self.inner.find_iter(s).collect();
+        if found
+            .iter()
+            .any(|m| scans_differently(s, m.start(), m.end()))
+        {
+            return generic_search_string_count(&self.element(), s);
+        }
+        found.len()
+    }
+
+    /// Optimized regex search — uses find_iter for SIMD-accelerated scanning
+    #[pyo3(signature = (s, max_matches = None, timeout_ms = None))]
+    fn search_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        max_matches: Option<usize>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let s: &str = &s;
+        metered_scan(self.inner.as_ref(), s, timeout_ms, || {
+            // The fast paths below find every match without checkpoints or profiling;
+            // a capped search stops early
+            if max_matches.is_some() || needs_generic_scan(s) {
+                return generic_search_string(py, &self.element(), s, max_matches);
+            }
+            if !self.inner.match_is_token() {
+                return generic_search_string(py, &self.element(), s, None);
+            }
+            unsafe {
+                // Collect match slices via find_iter (avoids position-by-position scanning)
+                let found: Vec<_> = self.inner.find_iter(s).collect();
+                if found
+                    .iter()
+                    .any(|m| scans_differently(s, m.start(), m.end()))
+                {
+                    return generic_search_string(py, &self.element(), s, None);
+                }
+                let matches: Vec<&str> = found.iter().map(|m| m.as_str()).collect();
+                let n = matches.len() as pyo3::ffi::Py_ssize_t;
+                if n == 0 {
+                    return Ok(PyList::empty(py));
+                }
+
+                let list_ptr = pyo3::ffi::PyList_New(n);
+                if list_ptr.is_null() {
+                    return Err(pyo3::PyErr::fetch(py));
+                }
+
+                let mut dedup: FxHashMap<&str, *mut pyo3::ffi::PyObject> = FxHashMap::default();
+                for (i, &matched) in matches.iter().enumerate() {
+                    let py_str = if let Some(&existing) = dedup.get(matched) {
+                        pyo3::ffi::Py_INCREF(existing);
+                        existing
+                    } else {
+                        let new_str = PyString::new(py, matched).into_ptr();
+                        dedup.insert(matched, new_str);
+                        pyo3::ffi::Py_INCREF(new_str);
+                        new_str
+                    };
+                    let inner = pyo3::ffi::PyList_New(1);
+                    pyo3::ffi::PyList_SET_ITEM(inner, 0, py_str);
+                    pyo3::ffi::PyList_SET_ITEM(list_ptr, i as pyo3::ffi::Py_ssize_t, inner);
+                }
+                for (_, ptr) in dedup {
+                    pyo3::ffi::Py_DECREF(ptr);
+                }
+
+                Ok(Bound::from_owned_ptr(py, list_ptr).cast_into_unchecked())
+            }
+        })
+    }
+
+    /// Cyclic detection + hash-based cache fallback + bulk INCREF
+    #[pyo3(signature = (inputs, to_json = false, pretty = false, jsonl_path = None, timeout_ms = None))]
+    fn parse_batch<'py>(
+        &self,
+        py: Python<'py>,
+        inputs: &Bound<'py, PyList>,
+        to_json: bool,
+        pretty: bool,
+        jsonl_path: Option<std::path::PathBuf>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        with_timeout(timeout_ms, || {
+            if to_json || jsonl_path.is_some() {
+                return batch_json(py, &self.element(), inputs, pretty, jsonl_path.as_deref());
+            }
+            metered_batch(self.inner.as_ref(), inputs, || {
+                if !self.inner.match_is_token() {
+                    return generic_parse_batch(py, &self.element(), inputs);
+                }
+                let batch = BatchInputs::new(inputs)?;
+                let spans = batch.parse_detached(|s| {
+                    let matched = self.inner.try_match(s)?;
+                    let start = matched.as_ptr() as usize - s.as_ptr() as usize;
+                    Some((start, start + matched.len()))
+                })?;
+                batch.span_tokens(&spans)
+            })
+            .map(Bound::into_any)
+        })
+    }
+
+    /// Count regex matches in batch, with the GIL released
+    #[pyo3(signature = (inputs, timeout_ms = None))]
+    fn parse_batch_count(
+        &self,
+        inputs: &Bound<'_, PyList>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<usize> {
+        with_timeout(timeout_ms, || {
+            let batch = BatchInputs::new(inputs)?;
+            batch.count_detached(|s| self.inner.try_match(s).is_some())
+        })
+    }
+
+    /// Specialized: uses regex replace_all for efficient in-engine replacement.
+    #[pyo3(signature = (s, replacement = None, timeout_ms = None))]
+    fn transform_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: &str,
+        replacement: Option<&str>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyString>> {
+        let Some(replacement) = replacement else {
+            return with_timeout(timeout_ms, || {
+                generic_transform_string(py, &self.element(), s, None)
+            });
+        };
+        // find_iter below runs without checkpoints or profiling
+        if timeout_ms.is_some() || needs_generic_scan(s) {
+            return with_timeout(timeout_ms, || {
+                generic_transform_string(py, &self.element(), s, Some(replacement))
+            });
+        }
+        // Use regex's replace_all with NoExpand for literal replacement
+        let result = self.inner.find_iter(s).collect::<Vec<_>>();
+        if result
+            .iter()
+            .any(|m| scans_differently(s, m.start(), m.end()))
+        {
+            return with_timeout(timeout_ms, || {
+                generic_transform_string(py, &self.element(), s, Some(replacement))
+            });
+        }
+        if result.is_empty() {
+            return Ok(PyString::new(py, s));
+        }
+        let mut out = String::with_capacity(s.len());
+        let mut last_end = 0;
+        for m in &result {
+            out.push_str(&s[last_end..m.start()]);
+            out.push_str(replacement);
+            last_end = m.end();
+        }
+        out.push_str(&s[last_end..]);
+        Ok(PyString::new(py, &out))
+    }
+}
+
+#[pymethods]
+impl PyKeyword {
+    /// `ident_chars` (a class spec like `A-Za-z0-9_-`, or a CharClass) are the
+    /// characters that may not precede or follow the keyword, by default those set
+    /// with set_default_keyword_chars(). `caseless` matches in any ASCII case and
+    /// returns the keyword as given.
+    #[new]
+    #[pyo3(signature = (s, ident_chars = None, caseless = false))]
+    fn new(
+        py: Python<'_>,
+        s: &str,
+        ident_chars: Option<&Bound<'_, PyAny>>,
+        caseless: bool,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let mut keyword = RustKeyword::try_new(s)
+            .map_err(|e| PatternError::new_err(e.msg))?
+            .with_caseless(caseless);
+        if let Some(chars) = ident_chars {
+            keyword = keyword
+                .with_ident_chars(&keyword_chars_arg(chars)?)
+                .map_err(|e| PatternError::new_err(e.msg))?;
+        }
+        Ok(Self {
+            inner: Arc::new(keyword),
+            cached_pystr: PyString::new(py, s).unbind(),
+        }
+        .into())
+    }
+
+    /// The identifier characters, as a class spec.
+    #[getter]
+    fn ident_chars(&self) -> &str {
+        self.inner.ident_chars()
+    }
+
+    /// Whether the keyword matches in any case.
+    #[getter]
+    fn caseless(&self) -> bool {
+        self.inner.is_caseless()
+    }
+
+    /// Keyword.set_default_keyword_chars(): see the module function.
+    #[staticmethod]
+    #[pyo3(signature = (chars = None))]
+    fn set_default_keyword_chars(chars: Option<&Bound<'_, PyAny>>) -> PyResult<String> {
+        set_default_keyword_chars_py(chars)
+    }
+
+    /// Fast keyword parse — uses try_match_at + cached PyString, zero allocation
+    #[pyo3(signature = (s, parse_all = false, normalize = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        metered_parse(self.inner.as_ref(), s, timeout_ms, || {
+            if normalize {
+                return normalized_parse_string(py, &self.element(), s, parse_all);
+            }
+            if parse_all || profile::enabled() {
+                return generic_parse_string(py, &self.element(), s, parse_all);
+            }
+            // Skip leading whitespace (like pyparsing)
+            let start = skip_ws(s, 0);
+            match self.inner.try_match_at(s, start) {
+                Some(_end) => PyList::new(py, [self.cached_pystr.bind(py)]).map(Bound::into_any),
+                None => Err(parse_error_at(s, start, "Expected keyword")),
+            }
+        })
+    }
+
+    /// Occurrences found with memmem, checking the boundary only at candidates
+    fn search_string_count(&self, s: &str) -> usize {
+        self.inner.find_spans(s, false, None).len()
+    }
+
+    /// Search string — count + PySequence_Repeat (same pattern as Literal)
+    #[pyo3(signature = (s, max_matches = None, timeout_ms = None))]
+    fn search_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        max_matches: Option<usize>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let s: &str = &s;
+        metered_scan(self.inner.as_ref(), s, timeout_ms, || {
+            // The fast paths below find every match without checkpoints or profiling;
+            // a capped search stops early
+            if max_matches.is_some() || needs_generic_scan(s) {
+                return generic_search_string(py, &self.element(), s, max_matches);
+            }
+            let cached = self.cached_pystr.bind(py);
+            let count = self.search_string_count(s);
+            if count == 0 {
+                return Ok(PyList::empty(py));
+            }
+            let singleton = PyList::new(py, [cached])?;
+            let template = PyList::new(py, [&singleton])?;
+            unsafe {
+                let result =
+                    pyo3::ffi::PySequence_Repeat(template.as_ptr(), count as pyo3::ffi::Py_ssize_t);
+                if result.is_null() {
+                    return Err(pyo3::PyErr::fetch(py));
+                }
+                Ok(Bound::from_owned_ptr(py, result).cast_into_unchecked())
+            }
+        })
+    }
+
+    /// Number of `inputs` the keyword matches at their start.
+    #[pyo3(signature = (inputs, timeout_ms = None))]
+    fn parse_batch_count(
+        &self,
+        inputs: &Bound<'_, PyList>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<usize> {
+        with_timeout(timeout_ms, || {
+            let batch = BatchInputs::new(inputs)?;
+            batch.count_detached(|s| self.inner.try_match_at(s, 0).is_some())
+        })
+    }
+
+    /// Specialized parse_batch: matching with the GIL released, one shared result
+    /// list each for matches and misses
+    #[pyo3(signature = (inputs, to_json = false, pretty = false, jsonl_path = None, timeout_ms = None))]
+    fn parse_batch<'py>(
+        &self,
+        py: Python<'py>,
+        inputs: &Bound<'py, PyList>,
+        to_json: bool,
+        pretty: bool,
+        jsonl_path: Option<std::path::PathBuf>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        with_timeout(timeout_ms, || {
+            if to_json || jsonl_path.is_some() {
+                return batch_json(py, &self.element(), inputs, pretty, jsonl_path.as_deref());
+            }
+            metered_batch(self.inner.as_ref(), inputs, || {
+                let batch = BatchInputs::new(inputs)?;
+                let matched = batch.parse_detached(|s| self.inner.try_match_at(s, 0).is_some())?;
+                let matched_list = PyList::new(py, [self.cached_pystr.bind(py)])?;
+                batch.hit_or_miss(&matched, matched_list.as_any(), PyList::empty(py).as_any())
+            })
+            .map(Bound::into_any)
+        })
+    }
+}
+
+#[pymethods]
+impl PyAnd {
+    /// `exprs` (elements or strings) in sequence, like chaining them with `+`;
+    /// nested Ands are flattened into this one, keeping their error stops.
+    #[new]
+    fn new(exprs: &Bound<'_, PyAny>) -> PyResult<PyClassInitializer<Self>> {
+        let mut elements = Vec::new();
+        let mut stop = None;
+        for (i, expr) in combinator_items(exprs, "And")?.iter().enumerate() {
+            match expr.extract::<PyAnd>() {
+                Ok(nested) => {
+                    let offset = elements.len();
+                    stop = stop.or(nested.inner.error_stop().map(|s| s + offset));
+                    elements.extend(nested.inner.elements().iter().cloned());
+                }
+                Err(_) => elements.push(extract_expr(i, expr)?),
+            }
+        }
+        Ok(Self {
+            inner: Arc::new(RustAnd::new(elements).with_error_stop(stop)),
+        }
+        .into())
+    }
+
+    /// Parse using parse_impl for correct multi-token handling.
+    /// Uses try_match_at fast path for Normal elements, parse_impl for Complex/Suppress/Group.
+    #[pyo3(signature = (s, parse_all = false, normalize = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        metered_parse(self.inner.as_ref(), s, timeout_ms, || {
+            if normalize {
+                return normalized_parse_string(py, &self.element(), s, parse_all);
+            }
+            if parse_all || profile::enabled() {
+                return generic_parse_string(py, &self.element(), s, parse_all);
+            }
+            // Back-references need the spans recorded by And::parse_impl, the
+            // token-only fast path would drop results names, and it reports a
+            // failure past an error stop as a plain mismatch
+            if self.inner.has_backref()
+                || self.inner.has_results_names()
+                || self.inner.has_parse_actions()
+                || self.inner.error_stop().is_some()
+            {
+                return generic_parse_string(py, &self.element(), s, parse_all);
+            }
+            let elements = self.inner.elements();
+            unsafe {
+                let mut tokens: Vec<*mut pyo3::ffi::PyObject> = Vec::with_capacity(elements.len());
+                let mut ctx = ParseContext::new(s);
+                // Skip leading whitespace (like pyparsing)
+                let mut pos = skip_ws(s, 0);
+
+                for elem in elements.iter() {
+                    // Skip whitespace before each element
+                    if elem.skip_whitespace_before() {
+                        pos = skip_ws(s, pos);
+                    }
+                    match elem.parser_kind() {
+                        ParserKind::Normal => {
+                            // Fast path: try_match_at + string slice (one token)
+                            match elem.try_match_at(s, pos) {
+                                Some(end) => {
+                                    // Normal elements always yield their span, even when empty
+                                    tokens.push(PyString::new(py, &s[pos..end]).into_ptr());
+                                    pos = end;
+                                }
+                                None => {
+                                    for &ptr in &tokens {
+                                        pyo3::ffi::Py_DECREF(ptr);
+                                    }
+                                    return Err(parse_error_at(s, pos, "Expected match"));
+                                }
+                            }
+                        }
+                        ParserKind::Suppress => {
+                            // Suppress: advance position but produce no tokens
+                            match elem.try_match_at(s, pos) {
+                                Some(end) => pos = end,
+                                None => {
+                                    for &ptr in &tokens {
+                                        pyo3::ffi::Py_DECREF(ptr);
+                                    }
+                                    return Err(parse_error_at(s, pos, "Expected match"));
+                                }
+                            }
+                        }
+                        ParserKind::Group | ParserKind::Complex => {
+                            // Group: creates nested list; Complex: may contain groups
+                            match elem.parse_impl(&mut ctx, pos) {
+                                // Names registered inside (e.g. by Regex groups or Dict)
+                                // need the full ParseResults
+                                Ok((_, res)) if !res.named().is_empty() => {
+                                    for &ptr in &tokens {
+                                        pyo3::ffi::Py_DECREF(ptr);
+                                    }
+                                    return generic_parse_string(py, &self.element(), s, false);
+                                }
+                                Ok((new_pos, res)) => {
+                                    for item in res.items() {
+                                        tokens.push(result_item_to_py(py, item));
+                                    }
+                                    pos = new_pos;
+                                }
+                                Err(e) => {
+                                    for &ptr in &tokens {
+                                        pyo3::ffi::Py_DECREF(ptr);
+                                    }
+                                    return Err(parse_error(s, &e));
+                                }
+                            }
+                        }
+                    }
+                }
+
+                let n = tokens.len() as pyo3::ffi::Py_ssize_t;
+                let list_ptr = pyo3::ffi::PyList_New(n);
+                if list_ptr.is_null() {
+                    for &ptr in &tokens {
+                        pyo3::ffi::Py_DECREF(ptr);
+                    }
+                    return Err(pyo3::PyErr::fetch(py));
+                }
+                for (i, &ptr) in tokens.iter().enumerate() {
+                    pyo3::ffi::PyList_SET_ITEM(list_ptr, i as pyo3::ffi::Py_ssize_t, ptr);
+                }
+                Ok(Bound::from_owned_ptr(py, list_ptr).cast_into_unchecked())
+            }
+        })
+    }
+
+    /// Cyclic detection + hash-based pointer cache count
+    #[pyo3(signature = (inputs, timeout_ms = None))]
+    fn parse_batch_count(
+        &self,
+        inputs: &Bound<'_, PyList>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<usize> {
+        with_timeout(timeout_ms, || {
+            let batch = BatchInputs::new(inputs)?;
+            batch.count_detached(|s| self.inner.try_match_at(s, 0).is_some())
+        })
+    }
+
+    /// The tokens of each input. With `to_json`, the matches as a JSON str built in
+    /// Rust instead: an object per matching input with its `index`, `tokens`, `named`
+    /// results and match `end`, compact or `pretty`. With `jsonl_path`, they're written
+    /// to that file as JSON Lines and the number written is returned.
+    #[pyo3(signature = (inputs, to_json = false, pretty = false, jsonl_path = None, timeout_ms = None))]
+    fn parse_batch<'py>(
+        &self,
+        py: Python<'py>,
+        inputs: &Bound<'py, PyList>,
+        to_json: bool,
+        pretty: bool,
+        jsonl_path: Option<std::path::PathBuf>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        with_timeout(timeout_ms, || {
+            if to_json || jsonl_path.is_some() {
+                return batch_json(py, &self.element(), inputs, pretty, jsonl_path.as_deref());
+            }
+            metered_batch(self.inner.as_ref(), inputs, || {
+                generic_parse_batch(py, &self.element(), inputs)
+            })
+            .map(Bound::into_any)
+        })
+    }
+
+    fn __add__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyAnd> {
+        make_and_from_and(&self.inner, other)
+    }
+
+    fn __sub__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyAnd> {
+        make_and_stop_from_and(&self.inner, other)
+    }
+
+    fn __radd__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyAnd> {
+        reflected_and(
+            self.inner.elements().to_vec(),
+            self.inner.error_stop(),
+            other,
+        )
+    }
+
+    /// The And's elements; chained operators build one flat And.
+    #[getter]
+    fn exprs(&self, py: Python<'_>) -> PyResult<Vec<Py<PyAny>>> {
+        self.inner
+            .elements()
+            .iter()
+            .map(|elem| element_to_py(py, elem))
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.elements().len()
+    }
+}
+
+#[pymethods]
+impl PyMatchFirst {
+    /// The first of `exprs` (elements or strings) that matches; nested MatchFirsts
+    /// are flattened into this one.
+    #[new]
+    fn new(exprs: &Bound<'_, PyAny>) -> PyResult<PyClassInitializer<Self>> {
+        let mut elements = Vec::new();
+        for (i, expr) in combinator_items(exprs, "MatchFirst")?.iter().enumerate() {
+            match expr.extract::<PyMatchFirst>() {
+                Ok(nested) => elements.extend(nested.inner.elements().iter().cloned()),
+                Err(_) => elements.push(extract_expr(i, expr)?),
+            }
+        }
+        Ok(Self {
+            inner: Arc::new(RustMatchFirst::new(elements)),
+        }
+        .into())
+    }
+
+    /// Like ParserElement.parse_string.
+    #[pyo3(signature = (s, parse_all = false, normalize = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        metered_parse(self.inner.as_ref(), s, timeout_ms, || {
+            if normalize {
+                return normalized_parse_string(py, &self.element(), s, parse_all);
+            }
+            if parse_all || profile::enabled() {
+                return generic_parse_string(py, &self.element(), s, parse_all);
+            }
+            // Skip leading whitespace (like pyparsing)
+            let start = skip_ws(s, 0);
+            let mut ctx = ParseContext::new(s);
+            let mut failure = FurthestFailure::default();
+            for elem in self.inner.elements() {
+                match elem.parse_impl(&mut ctx, start) {
+                    Ok((_end, results)) => {
+                        check_action_error()?;
+                        return results_to_py_object(py, &results);
+                    }
+                    Err(e) if e.fatal => return Err(parse_error(s, &e)),
+                    Err(e) => failure.record(e),
+                }
+            }
+            Err(parse_error(s, &failure.into_error(start)))
+        })
+    }
+
+    /// Up to `max_parses` distinct parses of `s` that differ in the alternatives taken,
+    /// best first, as `(tokens, end, score, choices)`. `choices` lists the alternative
+    /// index taken at each MatchFirst on the parse path, starting with this one; the
+    /// score favours longer matches and fewer skipped Optionals.
+    #[pyo3(signature = (s, max_parses = 5))]
+    fn parse_all_alternatives<'py>(
+        &self,
+        py: Python<'py>,
+        s: &str,
+        max_parses: usize,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let found = parse_all_alternatives(self.inner.as_ref(), s, max_parses);
+        check_action_error()?;
+        let out = PyList::empty(py);
+        for alt in &found {
+            out.append((
+                results_to_py_object(py, &alt.results)?,
+                alt.end,
+                alt.score(),
+                alt.choices.clone(),
+            ))?;
+        }
+        Ok(out)
+    }
+
+    fn __or__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyMatchFirst> {
+        make_or_from_matchfirst(&self.inner, other)
+    }
+
+    fn __ror__(&self, other: &Bound<'_, PyAny>) -> PyResult<PyMatchFirst> {
+        reflected_or(self.inner.elements().to_vec(), other)
+    }
+
+    /// The MatchFirst's elements; chained operators build one flat MatchFirst.
+    #[getter]
+    fn exprs(&self, py: Python<'_>) -> PyResult<Vec<Py<PyAny>>> {
+        self.inner
+            .elements()
+            .iter()
+            .map(|elem| element_to_py(py, elem))
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.elements().len()
+    }
+}
+
+/// Generate the `#[pymethods]` impl for thin wrapper parser types: just the
+/// constructor, as every other method comes from ParserElement.
+macro_rules! impl_thin_parser_wrapper {
+    ($py_type:ident, $rust_type:ident) => {
+        impl_thin_parser_wrapper!(
+            $py_type,
+            #[new]
+            fn new(expr: &Bound<'_, PyAny>) -> PyResult<PyClassInitializer<Self>> {
+                let inner = extract_parser(expr)?;
+                Ok(Self {
+                    inner: Arc::new($rust_type::new(inner)),
+                }
+                .into())
+            }
+        );
+    };
+    ($py_type:ident, $new:item) => {
+        #[pymethods]
+        impl $py_type {
+            $new
+        }
+    };
+}
+
+impl_thin_parser_wrapper!(
+    PyZeroOrMore,
+    /// Match `expr` any number of times, stopping before a match of `stop_on`.
+    #[new]
+    #[pyo3(signature = (expr, stop_on = None))]
+    fn new(
+        expr: &Bound<'_, PyAny>,
+        stop_on: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let stop_on = stop_on.map(extract_parser).transpose()?;
+        Ok(Self {
+            inner: Arc::new(RustZeroOrMore::new(extract_parser(expr)?).with_stop_on(stop_on)),
+        }
+        .into())
+    }
+);
+impl_thin_parser_wrapper!(
+    PyOneOrMore,
+    /// Match `expr` at least once, stopping before a match of `stop_on`.
+    #[new]
+    #[pyo3(signature = (expr, stop_on = None))]
+    fn new(
+        expr: &Bound<'_, PyAny>,
+        stop_on: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let stop_on = stop_on.map(extract_parser).transpose()?;
+        Ok(Self {
+            inner: Arc::new(RustOneOrMore::new(extract_parser(expr)?).with_stop_on(stop_on)),
+        }
+        .into())
+    }
+);
+impl_thin_parser_wrapper!(
+    PyCombine,
+    /// Join the tokens of `expr` into one string with `join_string` between them.
+    /// `adjacent` allows no whitespace between its elements; with it False they
+    /// skip whitespace as usual and only their tokens are joined.
+    #[new]
+    #[pyo3(signature = (expr, join_string = "", adjacent = true))]
+    fn new(
+        expr: &Bound<'_, PyAny>,
+        join_string: &str,
+        adjacent: bool,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let combine = RustCombine::new(extract_parser(expr)?)
+            .with_join_string(join_string)
+            .with_adjacent(adjacent);
+        Ok(Self {
+            inner: Arc::new(combine),
+        }
+        .into())
+    }
+);
+impl_thin_parser_wrapper!(PyOriginalTextFor, RustOriginalTextFor);
+impl_thin_parser_wrapper!(PyAsLine, RustAsLine);
+impl_thin_parser_wrapper!(
+    PyIndentedBlock,
+    /// One or more matches of `expr` starting in the same column, further right
+    /// than the enclosing block's lines; the block ends at the first line indented
+    /// less. With `recursive`, lines indented further form a nested block of `expr`.
+    /// The block's tokens are one group unless `grouped` is False.
+    #[new]
+    #[pyo3(signature = (expr, *, recursive = false, grouped = true))]
+    fn new(
+        expr: &Bound<'_, PyAny>,
+        recursive: bool,
+        grouped: bool,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let block = RustIndentedBlock::new(extract_parser(expr)?)
+            .with_recursive(recursive)
+            .with_grouped(grouped);
+        Ok(Self {
+            inner: Arc::new(block),
+        }
+        .into())
+    }
+);
+
+// PyGroup — custom implementation: wraps inner result in a nested list
+#[pymethods]
+impl PyGroup {
+    #[new]
+    fn new(expr: &Bound<'_, PyAny>) -> PyResult<PyClassInitializer<Self>> {
+        let inner = extract_parser(expr)?;
+        Ok(Self {
+            inner: Arc::new(RustGroup::new(inner)),
+        }
+        .into())
+    }
+    /// Like ParserElement.parse_string.
+    #[pyo3(signature = (s, parse_all = false, normalize = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        metered_parse(self.inner.as_ref(), s, timeout_ms, || {
+            if normalize {
+                return normalized_parse_string(py, &self.element(), s, parse_all);
+            }
+            // Group's parse_string uses parse_impl which wraps in from_group
+            // results_to_py_list handles the Group variant recursively
+            generic_parse_string(py, &self.element(), s, parse_all)
+        })
+    }
+}
+
+// PyHighlightClass — transparent wrapper tagging its element for highlight_spans()
+#[pymethods]
+impl PyHighlightClass {
+    #[new]
+    fn new(expr: &Bound<'_, PyAny>, class_name: &str) -> PyResult<PyClassInitializer<Self>> {
+        let inner = extract_parser(expr)?;
+        Ok(Self {
+            inner: Arc::new(RustHighlightClass::new(inner, class_name)),
+        }
+        .into())
+    }
+    /// The label given to this element's spans.
+    #[getter]
+    fn class_name(&self) -> &str {
+        self.inner.highlight_class().unwrap()
+    }
+}
+
+// PyNamed — registers its element's tokens under a results name
+#[pymethods]
+impl PyNamed {
+    #[new]
+    #[pyo3(signature = (expr, name, list_all_matches = false))]
+    fn new(
+        expr: &Bound<'_, PyAny>,
+        name: &str,
+        list_all_matches: bool,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let inner = extract_parser(expr)?;
+        Ok(Self {
+            inner: Arc::new(RustNamed::new(inner, name, list_all_matches)),
+        }
+        .into())
+    }
+    /// The results name the tokens are stored under.
+    #[getter]
+    fn name(&self) -> &str {
+        self.inner.results_name().unwrap()
+    }
+}
+
+// PyParseAction — runs Python callables on its element's tokens
+#[pymethods]
+impl PyParseAction {
+    #[new]
+    #[pyo3(signature = (expr, *fns))]
+    fn new(
+        expr: &Bound<'_, PyAny>,
+        fns: &Bound<'_, PyTuple>,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        parse_action_element(extract_parser(expr)?, Vec::new(), fns).map(Into::into)
+    }
+    /// Replace the actions, keeping the element they run on.
+    #[pyo3(signature = (*fns))]
+    fn set_parse_action(&self, fns: &Bound<'_, PyTuple>) -> PyResult<PyParseAction> {
+        parse_action_element(self.inner.element().clone(), Vec::new(), fns)
+    }
+    /// Run `fns` after the existing actions.
+    #[pyo3(signature = (*fns))]
+    fn add_parse_action(&self, fns: &Bound<'_, PyTuple>) -> PyResult<PyParseAction> {
+        parse_action_element(
+            self.inner.element().clone(),
+            self.inner.actions().to_vec(),
+            fns,
+        )
+    }
+}
+
+// PyTransform — tokens converted in Rust
+#[pymethods]
+impl PyTransform {
+    /// `transform` is "to_int", "to_float", "strip_quotes", "strip", "upcase",
+    /// "downcase" or "join" (with `sep`).
+    #[new]
+    #[pyo3(signature = (expr, transform, sep = ""))]
+    fn new(
+        expr: &Bound<'_, PyAny>,
+        transform: &str,
+        sep: &str,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let transform = TokenTransform::from_name(transform, sep).ok_or_else(|| {
+            PatternError::new_err(format!("unknown token transform '{}'", transform))
+        })?;
+        Ok(transform_element(extract_parser(expr)?, transform).into())
+    }
+}
+
+fn transform_element(element: Arc<dyn ParserElement>, transform: TokenTransform) -> PyTransform {
+    PyTransform {
+        inner: Arc::new(RustTransform::new(element, transform)),
+    }
+}
+
+// PyCondition — rejects matches failing a Python predicate
+#[pymethods]
+impl PyCondition {
+    #[new]
+    #[pyo3(signature = (expr, r#fn, message = None))]
+    fn new(
+        expr: &Bound<'_, PyAny>,
+        r#fn: &Bound<'_, PyAny>,
+        message: Option<String>,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        condition_element(extract_parser(expr)?, r#fn, message).map(Into::into)
+    }
+}
+
+// PyWhitespaceScope — element with its own whitespace characters
+#[pymethods]
+impl PyWhitespaceScope {
+    #[new]
+    fn new(expr: &Bound<'_, PyAny>, chars: &str) -> PyResult<PyClassInitializer<Self>> {
+        whitespace_scope(extract_parser(expr)?, chars).map(Into::into)
+    }
+}
+
+// PyDebugTrace — reports match attempts to the debug actions
+#[pymethods]
+impl PyDebugTrace {
+    #[new]
+    #[pyo3(signature = (expr, flag = true))]
+    fn new(expr: &Bound<'_, PyAny>, flag: bool) -> PyResult<PyClassInitializer<Self>> {
+        Ok(debug_trace(extract_parser(expr)?, flag).into())
+    }
+    /// Report each match attempt of this element, and its outcome, through the
+    /// debug actions (printing by default); `flag=False` turns tracing off.
+    #[pyo3(signature = (flag = true))]
+    fn set_debug(&self, flag: bool) -> PyDebugTrace {
+        debug_trace(self.inner.element().clone(), flag)
+    }
+}
+
+// ============================================================================
+// ParseResults — list-like tokens plus dict-like named entries
+// ============================================================================
+
+#[pymethods]
+impl PyParseResults {
+    fn __len__(&self, py: Python<'_>) -> usize {
+        self.tokens.bind(py).len()
+    }
+    /// `results[i]` / `results[i:j]` index the tokens; `results["name"]` looks up a name.
+    fn __getitem__<'py>(
+        &self,
+        py: Python<'py>,
+        key: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        if let Ok(name) = key.cast::<PyString>() {
+            return self
+                .named_dict(py)
+                .and_then(|named| named.get_item(name).transpose())
+                .unwrap_or_else(|| Err(PyKeyError::new_err(name.to_string())));
+        }
+        self.tokens.bind(py).as_any().get_item(key)
+    }
+    /// True for a result name or for one of the tokens.
+    fn __contains__(&self, py: Python<'_>, item: &Bound<'_, PyAny>) -> PyResult<bool> {
+        if let Some(named) = self.named_dict(py) {
+            if item.is_instance_of::<PyString>() && named.contains(item)? {
+                return Ok(true);
+            }
+        }
+        self.tokens.bind(py).contains(item)
+    }
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(self.tokens.bind(py).as_any().try_iter()?.into_any())
+    }
+    /// Equal to another ParseResults or a plain list with the same tokens.
+    fn __eq__(&self, py: Python<'_>, other: &Bound<'_, PyAny>) -> PyResult<bool> {
+        if let Ok(other) = other.cast::<PyParseResults>() {
+            return self.tokens.bind(py).eq(other.borrow().tokens.bind(py));
+        }
+        self.tokens.bind(py).as_any().eq(other)
+    }
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let named = match self.named_dict(py) {
+            Some(named) => named.repr()?.to_string(),
+            None => "{}".to_string(),
+        };
+        Ok(format!(
+            "ParseResults({}, {})",
+            self.tokens.bind(py).repr()?,
+            named
+        ))
+    }
+    /// The tokens alone, printed like the list they compare equal to.
+    fn __str__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(self.tokens.bind(py).repr()?.to_string())
+    }
+    /// The token stored under `name`, or `default`.
+    #[pyo3(signature = (name, default = None))]
+    fn get<'py>(
+        &self,
+        py: Python<'py>,
+        name: &str,
+        default: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        match self.named_dict(py) {
+            Some(named) => Ok(named.get_item(name)?.or(default)),
+            None => Ok(default),
+        }
+    }
+    /// The results names set.
+    fn keys<'py>(&self, py: Python<'py>) -> Bound<'py, PyList> {
+        match self.named_dict(py) {
+            Some(named) => named.keys(),
+            None => PyList::empty(py),
+        }
+    }
+    /// The tokens as a plain list.
+    fn as_list<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        PyList::new(py, self.tokens.bind(py).iter())
+    }
+    /// The named tokens as a dict. Nested results become dicts when they have named
+    /// entries and lists otherwise, at any depth (e.g. a list of dicts for `entry*`).
+    fn as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        if let Some(named) = self.named_dict(py) {
+            for (name, value) in named.iter() {
+                dict.set_item(name, PyParseResults::plain_value(py, &value)?)?;
+            }
+        }
+        Ok(dict)
+    }
+    /// The tokens followed by one `- name: value` line per named entry (sorted by name),
+    /// nested results indented below their name.
+    #[pyo3(signature = (indent = ""))]
+    fn dump(&self, py: Python<'_>, indent: &str) -> PyResult<String> {
+        let mut out = self.tokens.bind(py).repr()?.to_string();
+        let Some(named) = self.named_dict(py) else {
+            return Ok(out);
+        };
+        let mut entries = named
+            .iter()
+            .map(|(name, value)| Ok((name.str()?.to_string(), value)))
+            .collect::<PyResult<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, value) in entries {
+            out.push_str(&format!("\n{indent}- {name}: "));
+            match value.cast::<PyParseResults>() {
+                Ok(nested) => out.push_str(&nested.borrow().dump(py, &format!("{indent}  "))?),
+                Err(_) => out.push_str(&value.repr()?.to_string()),
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl PyParseResults {
+    fn named_dict<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyDict>> {
+        self.named.as_ref().map(|named| named.bind(py).clone())
+    }
+
+    /// `value` with every ParseResults in it replaced as as_dict does.
+    fn plain_value<'py>(py: Python<'py>, value: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+        if let Ok(nested) = value.cast::<PyParseResults>() {
+            let nested = nested.borrow();
+            if nested.named.is_some() {
+                return nested.as_dict(py).map(Bound::into_any);
+            }
+            return Self::plain_list(py, nested.tokens.bind(py));
+        }
+        match value.cast::<PyList>() {
+            Ok(list) => Self::plain_list(py, list),
+            Err(_) => Ok(value.clone()),
+        }
+    }
+
+    fn plain_list<'py>(py: Python<'py>, list: &Bound<'py, PyList>) -> PyResult<Bound<'py, PyAny>> {
+        let items = list
+            .iter()
+            .map(|item| Self::plain_value(py, &item))
+            .collect::<PyResult<Vec<_>>>()?;
+        PyList::new(py, items).map(Bound::into_any)
+    }
+}
+
+// ============================================================================
+// Wrappers whose results carry names (Dict, Located): parse_string returns ParseResults
+// ============================================================================
+
+macro_rules! impl_named_results_wrapper {
+    ($py_type:ident, $rust_type:ident) => {
+        #[pymethods]
+        impl $py_type {
+            #[new]
+            fn new(expr: &Bound<'_, PyAny>) -> PyResult<PyClassInitializer<Self>> {
+                let inner = extract_parser(expr)?;
+                Ok(Self {
+                    inner: Arc::new($rust_type::new(inner)),
+                }
+                .into())
+            }
+            /// Parse `s` from its start into ParseResults, with the names set inside.
+            fn parse_string<'py>(
+                &self,
+                py: Python<'py>,
+                s: InputText<'_>,
+            ) -> PyResult<Bound<'py, PyAny>> {
+                let s: &str = &s;
+                metered_parse(self.inner.as_ref(), s, None, || {
+                    match self.inner.parse_string(s) {
+                        Ok(results) => {
+                            check_action_error()?;
+                            results_to_py_object(py, &results)
+                        }
+                        Err(e) => Err(parse_error(s, &e)),
+                    }
+                })
+            }
+        }
+    };
+}
+
+impl_named_results_wrapper!(PyDictElement, RustDict);
+impl_named_results_wrapper!(PyLocated, RustLocated);
+
+// ============================================================================
+
+// ============================================================================
+
+// PyOptional — specialized: never raises exceptions, avoids ParseResults on no-match
+// ============================================================================
+
+#[pymethods]
+impl PyOptional {
+    #[new]
+    fn new(expr: &Bound<'_, PyAny>) -> PyResult<PyClassInitializer<Self>> {
+        let inner = extract_parser(expr)?;
+        Ok(Self {
+            inner: Arc::new(RustOptional::new(inner)),
+        }
+        .into())
+    }
+    /// Like ParserElement.parse_string.
+    #[pyo3(signature = (s, parse_all = false, normalize = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        metered_parse(self.inner.as_ref(), s, timeout_ms, || {
+            if normalize {
+                return normalized_parse_string(py, &self.element(), s, parse_all);
+            }
+            if parse_all || profile::enabled() {
+                return generic_parse_string(py, &self.element(), s, parse_all);
+            }
+            // Optional always succeeds. Use try_match_at to check cheaply.
+            // If match at 0 returns 0 (no advancement), inner didn't match → return empty list.
+            let end = self.inner.try_match_at(s, 0).unwrap_or(0);
+            if end == 0 {
+                return Ok(PyList::empty(py).into_any());
+            }
+            // Inner matched — do full parse to get tokens
+            generic_parse_string(py, &self.element(), s, parse_all)
+        })
+    }
+}
+
+// ============================================================================
+// PySuppress — specialized: always returns empty list on success
+// ============================================================================
+
+#[pymethods]
+impl PySuppress {
+    #[new]
+    /// Suppress any element; a plain string is treated as a Literal.
+    fn new(expr: &Bound<'_, PyAny>) -> PyResult<PyClassInitializer<Self>> {
+        Ok(Self {
+            inner: Arc::new(RustSuppress::new(extract_parser(expr)?)),
+        }
+        .into())
+    }
+    /// Like ParserElement.parse_string.
+    #[pyo3(signature = (s, parse_all = false, normalize = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        normalize: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        metered_parse(self.inner.as_ref(), s, timeout_ms, || {
+            if normalize {
+                return normalized_parse_string(py, &self.element(), s, parse_all);
+            }
+            if parse_all || profile::enabled() {
+                return generic_parse_string(py, &self.element(), s, parse_all);
+            }
+            // Skip leading whitespace (like pyparsing), then suppress always returns empty tokens.
+            let start = skip_ws(s, 0);
+            if self.inner.try_match_at(s, start).is_some() {
+                Ok(PyList::empty(py).into_any())
+            } else {
+                Err(parse_error_at(s, start, "No match (suppressed)"))
+            }
+        })
+    }
+    /// Like ParserElement.parse_batch.
+    #[pyo3(signature = (inputs, to_json = false, pretty = false, jsonl_path = None, timeout_ms = None))]
+    fn parse_batch<'py>(
+        &self,
+        py: Python<'py>,
+        inputs: &Bound<'py, PyList>,
+        to_json: bool,
+        pretty: bool,
+        jsonl_path: Option<std::path::PathBuf>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        with_timeout(timeout_ms, || {
+            if to_json || jsonl_path.is_some() {
+                return batch_json(py, &self.element(), inputs, pretty, jsonl_path.as_deref());
+            }
+            metered_batch(self.inner.as_ref(), inputs, || {
+                // Suppress parse_batch: each result is an empty list, shared by the matches
+                let batch = BatchInputs::new(inputs)?;
+                let matched = batch.parse_detached(|s| self.inner.try_match_at(s, 0).is_some())?;
+                let shared = PyList::empty(py).into_any();
+                let outputs: Vec<Bound<'py, PyAny>> = matched
+                    .iter()
+                    .map(|&m| match m {
+                        true => shared.clone(),
+                        false => PyList::empty(py).into_any(),
+                    })
+                    .collect();
+                batch.per_input(&outputs)
+            })
+            .map(Bound::into_any)
+        })
+    }
+}
+
+// ============================================================================
+// PyForward — placeholder for recursive grammar definitions
+// ============================================================================
+
+#[pymethods]
+impl PyForward {
+    #[new]
+    fn new() -> PyClassInitializer<Self> {
+        Self {
+            inner: Arc::new(RustForward::new()),
+        }
+        .into()
+    }
+
+    /// Set the inner parser expression (equivalent to <<= in pyparsing).
+    /// Fails once the grammar has been frozen.
+    fn set(&self, expr: &Bound<'_, PyAny>) -> PyResult<()> {
+        let parser = extract_parser(expr)?;
+        self.inner
+            .set(parser)
+            .map_err(|e| PyparsingError::new_err(e.to_string()))
+    }
+
+    /// Python <<= operator support.
+    fn __ilshift__(&self, expr: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.set(expr)
+    }
+}
+
+// ============================================================================
+// PyExactly — takes expr + count, can't use thin wrapper macro
+// ============================================================================
+
+#[pymethods]
+impl PyExactly {
+    #[new]
+    fn new(expr: &Bound<'_, PyAny>, count: usize) -> PyResult<PyClassInitializer<Self>> {
+        let inner = extract_parser(expr)?;
+        Ok(Self {
+            inner: Arc::new(RustExactly::new(inner, count)),
+        }
+        .into())
+    }
+}
+
+// ============================================================================
+// PyRepeat — bounded repetition, from Repeat(expr, min, max), `*` and `expr[min, max]`
+// ============================================================================
+
+#[pymethods]
+impl PyRepeat {
+    #[new]
+    #[pyo3(signature = (expr, min = 0, max = None))]
+    fn new(
+        expr: &Bound<'_, PyAny>,
+        min: usize,
+        max: Option<usize>,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        repeat_element(extract_parser(expr)?, min, max).map(Into::into)
+    }
+}
+
+// ============================================================================
+// Bounded unordered clauses: AtLeastN, AtMostN
+// ============================================================================
+
+macro_rules! impl_bounded_each_parser {
+    ($py_type:ident, $bounds:expr) => {
+        #[pymethods]
+        impl $py_type {
+            #[new]
+            fn new(exprs: &Bound<'_, PyList>, n: usize) -> PyResult<PyClassInitializer<Self>> {
+                let elements = extract_exprs(exprs)?;
+                let bounds: fn(usize, usize) -> (usize, usize) = $bounds;
+                let (min, max) = bounds(n, elements.len());
+                Ok(Self {
+                    inner: Arc::new(RustEach::with_bounds(elements, min, max)),
+                }
+                .into())
+            }
+            /// Indices of the clauses present in `s`, in the order they appeared.
+            fn clauses_seen(&self, s: &str) -> PyResult<Vec<usize>> {
+                self.inner.clauses_seen(s).map_err(|e| parse_error(s, &e))
+            }
+        }
+    };
+}
+
+impl_bounded_each_parser!(PyAtLeastN, |n, len| (n, len));
+impl_bounded_each_parser!(PyAtMostN, |n, _len| (0, n));
+
+// ============================================================================
+// String-arg constructors: CaselessLiteral, CaselessKeyword, Char
+// ============================================================================
+
+macro_rules! impl_string_arg_parser {
+    ($py_type:ident, $rust_type:ident) => {
+        #[pymethods]
+        impl $py_type {
+            #[new]
+            fn new(s: &str) -> PyClassInitializer<Self> {
+                Self {
+                    inner: Arc::new($rust_type::new(s)),
+                }
+                .into()
+            }
+        }
+    };
+    // Types that reject an empty string
+    ($py_type:ident, $rust_type:ident, non_empty) => {
+        #[pymethods]
+        impl $py_type {
+            #[new]
+            fn new(s: &str) -> PyResult<PyClassInitializer<Self>> {
+                let inner = $rust_type::try_new(s).map_err(|e| PatternError::new_err(e.msg))?;
+                Ok(Self {
+                    inner: Arc::new(inner),
+                }
+                .into())
+            }
+        }
+    };
+}
+
+impl_string_arg_parser!(PyCaselessLiteral, RustCaselessLiteral, non_empty);
+impl_string_arg_parser!(PyCaselessKeyword, RustCaselessKeyword, non_empty);
+impl_string_arg_parser!(PyChar, RustChar);
+
+// ============================================================================
+// CloseMatch — match string plus a mismatch budget
+// ============================================================================
+
+#[pymethods]
+impl PyCloseMatch {
+    /// Text as long as `match_string` differing from it in at most `max_mismatches`
+    /// characters. The token is the text found; results name `original` (the match
+    /// string) and `mismatches` (positions that differ).
+    #[new]
+    #[pyo3(signature = (match_string, max_mismatches = 1, caseless = false))]
+    fn new(
+        match_string: &str,
+        max_mismatches: usize,
+        caseless: bool,
+    ) -> PyResult<PyClassInitializer<Self>> {
+        let inner = RustCloseMatch::try_new(match_string, max_mismatches)
+            .map_err(|e| PatternError::new_err(e.msg))?
+            .with_caseless(caseless);
+        Ok(Self {
+            inner: Arc::new(inner),
+        }
+        .into())
+    }
+
+    /// The text matches are compared with.
+    #[getter]
+    fn match_string(&self) -> &str {
+        self.inner.match_str()
+    }
+
+    /// How many characters may differ from `match_string`.
+    #[getter]
+    fn max_mismatches(&self) -> usize {
+        self.inner.max_mismatches()
+    }
+}
+
+// ============================================================================
+// HTML and XML tags — make_html_tags, make_xml_tags and with_attribute
+// ============================================================================
+
+#[pymethods]
+impl PyHtmlTag {
+    /// `<tag ...>` or `<tag .../>`. The token is the tag's text; results name `tag`,
+    /// each attribute and `empty` (whether it self-closes).
+    #[new]
+    #[pyo3(signature = (tag, xml = false))]
+    fn new(tag: &str, xml: bool) -> PyResult<PyClassInitializer<Self>> {
+        Ok(Self {
+            inner: Arc::new(html_tags(tag, xml)?.0),
+        }
+        .into())
+    }
+
+    /// The tag name, as given.
+    #[getter]
+    fn tag(&self) -> &str {
+        self.inner.tag()
+    }
+}
+
+#[pymethods]
+impl PyHtmlEndTag {
+    #[new]
+    #[pyo3(signature = (tag, xml = false))]
+    fn new(tag: &str, xml: bool) -> PyResult<PyClassInitializer<Self>> {
+        Ok(Self {
+            inner: Arc::new(html_tags(tag, xml)?.1),
+        }
+        .into())
+    }
+}
+
+/// Start and end tag elements for `tag`, which must be a tag name.
+fn html_tags(tag: &str, xml: bool) -> PyResult<(RustHtmlTag, RustHtmlEndTag)> {
+    let valid = |c: char| !c.is_whitespace() && !"=/<>\"'".contains(c);
+    if tag.is_empty() || !tag.chars().all(valid) {
+        return Err(PatternError::new_err(format!("invalid tag name {:?}", tag)));
+    }
+    Ok(match xml {
+        true => (RustHtmlTag::xml(tag), RustHtmlEndTag::xml(tag)),
+        false => (RustHtmlTag::html(tag), RustHtmlEndTag::html(tag)),
+    })
+}
+
+/// `(start, end)` elements for an HTML tag. The start tag matches the name in any
+/// case, takes attributes in any order, quoted or not, and lowercases their names.
+#[pyfunction]
+fn make_html_tags(tag: &str) -> PyResult<(PyHtmlTag, PyHtmlEndTag)> {
+    let (start, end) = html_tags(tag, false)?;
+    Ok((
+        PyHtmlTag {
+            inner: Arc::new(start),
+        },
+        PyHtmlEndTag {
+            inner: Arc::new(end),
+        },
+    ))
+}
+
+/// `(start, end)` elements for an XML tag: like make_html_tags, but names are
+/// case-sensitive and attribute values must be quoted.
+#[pyfunction]
+fn make_xml_tags(tag: &str) -> PyResult<(PyHtmlTag, PyHtmlEndTag)> {
+    let (start, end) = html_tags(tag, true)?;
+    Ok((
+        PyHtmlTag {
+            inner: Arc::new(start),
+        },
+        PyHtmlEndTag {
+            inner: Arc::new(end),
+        },
+    ))
+}
+
+/// The `ANY_VALUE` sentinel: with_attribute only requires the attribute be present.
+#[pyclass(name = "AnyValue", frozen)]
+struct PyAnyValue;
+
+#[pymethods]
+impl PyAnyValue {
+    fn __repr__(&self) -> &'static str {
+        "ANY_VALUE"
+    }
+}
+
+/// Start-tag filter from with_attribute, checked in Rust when passed to
+/// add_condition or add_parse_action.
+#[pyclass(name = "AttributeFilter", frozen)]
+struct PyAttributeFilter {
+    inner: Arc<AttributeFilter>,
+}
+
+#[pymethods]
+impl PyAttributeFilter {
+    fn __repr__(&self) -> String {
+        format!("with_attribute({})", self.inner.message())
+    }
+}
+
+/// Filter for start tags whose attributes have the given values, from
+/// `(name, value)` pairs and keywords; `ANY_VALUE` only requires presence. Pass it
+/// to add_condition or add_parse_action.
+#[pyfunction]
+#[pyo3(signature = (*args, **kwargs))]
+fn with_attribute(
+    args: &Bound<'_, PyTuple>,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyAttributeFilter> {
+    let mut pairs: Vec<(String, Bound<'_, PyAny>)> = Vec::new();
+    for arg in args.iter() {
+        pairs.push(arg.extract()?);
+    }
+    if let Some(kwargs) = kwargs {
+        for (name, value) in kwargs.iter() {
+            pairs.push((name.extract()?, value));
+        }
+    }
+    let mut required = Vec::with_capacity(pairs.len());
+    for (name, value) in pairs {
+        let value = match value.is_instance_of::<PyAnyValue>() {
+            true => None,
+            false => Some(value.extract::<String>().map_err(|_| {
+                PyTypeError::new_err(format!("attribute {} must be a str or ANY_VALUE", name))
+            })?),
+        };
+        required.push((name.into(), value.map(Into::into)));
+    }
+    Ok(PyAttributeFilter {
+        inner: Arc::new(AttributeFilter::new(required)),
+    })
+}
+
+// ============================================================================
+// No-arg constructors: positional anchors (StringStart, StringEnd, etc.)
+// ============================================================================
+
+macro_rules! impl_noarg_parser {
+    ($py_type:ident, $rust_expr:expr) => {
+        #[pymethods]
+        impl $py_type {
+            #[new]
+            fn new() -> PyClassInitializer<Self> {
+                Self {
+                    inner: Arc::new($rust_expr),
+                }
+                .into()
+            }
+        }
+    };
+}
+
+impl_noarg_parser!(PyStringStart, RustStringStart);
+impl_noarg_parser!(PyStringEnd, RustStringEnd);
+impl_noarg_parser!(PyLineStart, RustLineStart);
+impl_noarg_parser!(PyLineEnd, RustLineEnd);
+impl_noarg_parser!(PyRestOfLine, RustRestOfLine::new());
+impl_noarg_parser!(PyComment, RustComment::cpp_style());
+impl_noarg_parser!(PyNumber, RustNumber::new(NumberKind::Number));
+impl_noarg_parser!(PyIpv6Address, RustIpv6Address::new());
+impl_noarg_parser!(PyEmpty, RustEmpty);
+impl_noarg_parser!(PyNoMatch, RustNoMatch);
+
+// ============================================================================
+// GoToColumn — takes a column number
+// ============================================================================
+
+#[pymethods]
+impl PyGoToColumn {
+    /// Advance to column `colno` (1-based, in characters) of the current line; the
+    /// text passed over is the token unless `suppress` is set.
+    #[new]
+    #[pyo3(signature = (colno, suppress = false))]
+    fn new(colno: usize, suppress: bool) -> PyResult<PyClassInitializer<Self>> {
+        if colno < 1 {
+            return Err(PatternError::new_err("GoToColumn columns start at 1"));
+        }
+        let column = RustGoToColumn::new(colno);
+        Ok(Self {
+            inner: Arc::new(match suppress {
+                true => column.suppressed(),
+                false => column,
+            }),
+        }
+        .into())
+    }
+
+    /// The column advanced to, 1-based.
+    #[getter]
+    fn colno(&self) -> usize {
+        self.inner.column()
+    }
+}
+
+// ============================================================================
+// SkipTo — takes a parser element target
+// ============================================================================
+
+impl_thin_parser_wrapper!(PySkipTo, RustSkipTo);
+
+// ============================================================================
+// Back-references — match what an earlier element in the sequence matched
+// ============================================================================
+
+impl_thin_parser_wrapper!(PyMatchPreviousLiteral, RustMatchPreviousLiteral);
+impl_thin_parser_wrapper!(PyMatchPreviousExpr, RustMatchPreviousExpr);
+
+// ============================================================================
+// QuotedString — custom constructor with optional params
+// ============================================================================
+
+#[pymethods]
+impl PyQuotedString {
+    #[new]
+    #[pyo3(signature = (quote_char="\"", esc_char=None, multiline=false, unquote=true))]
+    fn new(
+        quote_char: &str,
+        esc_char: Option<&str>,
+        multiline: bool,
+        unquote: bool,
+    ) -> PyClassInitializer<Self> {
+        Self {
+            inner: Arc::new(RustQuotedString::new(
+                quote_char, esc_char, multiline, unquote,
+            )),
+        }
+        .into()
+    }
+}
+
+// Character set constants
+/// The ASCII letters, a-z and A-Z.
+#[pyfunction]
+fn alphas() -> &'static str {
+    builder::ALPHAS
+}
+
+/// The ASCII letters and digits.
+#[pyfunction]
+fn alphanums() -> &'static str {
+    builder::ALPHANUMS
+}
+
+/// The digits 0-9.
+#[pyfunction]
+fn nums() -> &'static str {
+    builder::NUMS
+}
+
+/// The printable ASCII characters other than space.
+#[pyfunction]
+fn printables() -> &'static str {
+    builder::PRINTABLES
+}
+
+/// Characters that may start an identifier: letters and `_`.
+#[pyfunction]
+fn identchars() -> &'static str {
+    builder::IDENTCHARS
+}
+
+/// Characters that may continue an identifier: letters, digits and `_`.
+#[pyfunction]
+fn identbodychars() -> &'static str {
+    builder::IDENTBODYCHARS
+}
+
+/// The hexadecimal digits, in both cases.
+#[pyfunction]
+fn hexnums() -> &'static str {
+    "0123456789abcdefABCDEF"
+}
+
+/// The uppercase ASCII letters.
+#[pyfunction]
+fn alphas_upper() -> &'static str {
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+}
+
+/// The lowercase ASCII letters.
+#[pyfunction]
+fn alphas_lower() -> &'static str {
+    "abcdefghijklmnopqrstuvwxyz"
+}
+
+/// The characters of a bracketed class like `[a-zA-Z0-9_]`, for Word and Char:
+/// pyparsing's srange. Negated classes raise PatternError; use a CharClass instead.
+#[pyfunction]
+fn srange(spec: &str) -> PyResult<String> {
+    chars::srange(spec).map_err(|e| PatternError::new_err(e.msg))
+}
+
+/// A `unicode` namespace member, e.g. `unicode.Greek`: character strings for Word
+/// and Char drawn from a script's code point ranges.
+#[pyclass(name = "UnicodeSet", frozen)]
+struct PyUnicodeSet {
+    inner: &'static UnicodeSet,
+}
+
+#[pymethods]
+impl PyUnicodeSet {
+    /// The letters of this set.
+    #[getter]
+    fn alphas(&self) -> String {
+        self.inner.alphas()
+    }
+
+    /// The digits of this set.
+    #[getter]
+    fn nums(&self) -> String {
+        self.inner.nums()
+    }
+
+    /// The letters and digits of this set.
+    #[getter]
+    fn alphanums(&self) -> String {
+        self.inner.alphanums()
+    }
+
+    /// Characters of this set that may start an identifier.
+    #[getter]
+    fn identchars(&self) -> String {
+        self.inner.identchars()
+    }
+
+    /// Characters of this set that may continue an identifier.
+    #[getter]
+    fn identbodychars(&self) -> String {
+        self.inner.identbodychars()
+    }
+
+    /// The printable characters of this set other than whitespace.
+    #[getter]
+    fn printables(&self) -> String {
+        self.inner.printables()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("unicode.{}", self.inner.name)
+    }
+}
+
+/// Create a MatchFirst from a space-separated string of literal alternatives.
+/// Equivalent to pyparsing.one_of("+ - * /").
+#[pyfunction]
+fn one_of(strs: &str) -> PyResult<PyMatchFirst> {
+    let mut elements: Vec<Arc<dyn ParserElement>> = Vec::new();
+    for s in strs.split_whitespace() {
+        elements.push(Arc::new(RustLiteral::new(s)));
+    }
+    if elements.is_empty() {
+        return Err(PatternError::new_err("one_of requires at least one string"));
+    }
+    Ok(PyMatchFirst {
+        inner: Arc::new(RustMatchFirst::new(elements)),
+    })
+}
+
+/// Create an element capturing everything up to (not including) the next newline.
+/// With `strip=True`, surrounding spaces and tabs are removed from the token.
+/// Equivalent to pyparsing's rest_of_line.
+#[pyfunction]
+#[pyo3(signature = (strip = false))]
+fn rest_of_line(strip: bool) -> PyRestOfLine {
+    let rol = if strip {
+        RustRestOfLine::stripped()
+    } else {
+        RustRestOfLine::new()
+    };
+    PyRestOfLine {
+        inner: Arc::new(rol),
+    }
+}
+
+/// Generate random strings that `grammar` parses in full, reproducibly for a given seed.
+/// Returns one string, or a list of `count` strings. Supports literals, Word, Char,
+/// combinators and repetition; Regex and other open-ended elements are refused.
+#[pyfunction]
+#[pyo3(signature = (grammar, rng_seed = 0, max_depth = 16, count = None))]
+fn generate<'py>(
+    py: Python<'py>,
+    grammar: &Bound<'py, PyAny>,
+    rng_seed: u64,
+    max_depth: usize,
+    count: Option<usize>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let parser = extract_parser(grammar)?;
+    let mut gen = Generator::new(rng_seed, max_depth);
+    let mut sample =
+        || generate_sample(parser.as_ref(), &mut gen).map_err(|e| PyparsingError::new_err(e.msg));
+    match count {
+        None => Ok(PyString::new(py, &sample()?).into_any()),
+        Some(n) => {
+            let samples = (0..n)
+                .map(|_| sample())
+                .collect::<PyResult<Vec<String>>>()?;
+            Ok(PyList::new(py, samples)?.into_any())
+        }
+    }
+}
+
+/// Lower `grammar` into a flat instruction program that parses like it without
+/// recursing through the element tree. Supports And, MatchFirst, Optional,
+/// ZeroOrMore, Literal, Word, Keyword, Regex without groups, Suppress and Group;
+/// anything else (results names and parse actions included) is refused.
+#[pyfunction]
+fn compile(grammar: &Bound<'_, PyAny>) -> PyResult<PyCompiledGrammar> {
+    let parser = extract_parser(grammar)?;
+    let compiled = CompiledGrammar::compile(&parser).map_err(|e| PyparsingError::new_err(e.msg))?;
+    Ok(PyCompiledGrammar {
+        inner: Arc::new(compiled),
+    })
+}
+
+/// `/* ... */` comment, possibly spanning lines. Equivalent to pyparsing.c_style_comment.
+#[pyfunction]
+fn c_style_comment() -> PyComment {
+    PyComment {
+        inner: Arc::new(RustComment::c_style()),
+    }
+}
+
+/// `/* ... */` or `// ...` comment. Equivalent to pyparsing.cpp_style_comment.
+#[pyfunction]
+fn cpp_style_comment() -> PyComment {
+    PyComment {
+        inner: Arc::new(RustComment::cpp_style()),
+    }
+}
+
+/// `# ...` comment up to the end of the line. Equivalent to pyparsing.python_style_comment.
+#[pyfunction]
+fn python_style_comment() -> PyComment {
+    PyComment {
+        inner: Arc::new(RustComment::python_style()),
+    }
+}
+
+/// `<!-- ... -->` comment. Equivalent to pyparsing.html_comment.
+#[pyfunction]
+fn html_comment() -> PyComment {
+    PyComment {
+        inner: Arc::new(RustComment::html()),
+    }
+}
+
+/// Require `grammar` to match a whole line: only blanks may surround it before the
+/// newline (CRLF tolerated), and the newline is consumed.
+/// Like pyparsing's `LineStart() + grammar + LineEnd()`, but confined to one line.
+#[pyfunction]
+fn as_line(grammar: &Bound<'_, PyAny>) -> PyResult<PyAsLine> {
+    Ok(PyAsLine {
+        inner: Arc::new(RustAsLine::new(extract_parser(grammar)?)),
+    })
+}
+
+/// Wrap `expr` so its result is the original input text it matched, whitespace included.
+/// With `as_string=False`, the result is the match's start and end offsets instead.
+/// Equivalent to pyparsing.original_text_for(expr, as_string).
+#[pyfunction]
+#[pyo3(signature = (expr, as_string = true))]
+fn original_text_for(expr: &Bound<'_, PyAny>, as_string: bool) -> PyResult<PyOriginalTextFor> {
+    let inner = extract_parser(expr)?;
+    let otf = if as_string {
+        RustOriginalTextFor::new(inner)
+    } else {
+        RustOriginalTextFor::with_locations(inner)
+    };
+    Ok(PyOriginalTextFor {
+        inner: Arc::new(otf),
+    })
+}
+
+/// Create an element that matches the exact text `expr` most recently matched.
+/// Equivalent to pyparsing.match_previous_literal(expr).
+#[pyfunction]
+fn match_previous_literal(expr: &Bound<'_, PyAny>) -> PyResult<PyMatchPreviousLiteral> {
+    Ok(PyMatchPreviousLiteral {
+        inner: Arc::new(RustMatchPreviousLiteral::new(extract_parser(expr)?)),
+    })
+}
+
+/// Create an element that re-parses `expr` and requires the same tokens as its last match.
+/// Equivalent to pyparsing.match_previous_expr(expr).
+#[pyfunction]
+fn match_previous_expr(expr: &Bound<'_, PyAny>) -> PyResult<PyMatchPreviousExpr> {
+    Ok(PyMatchPreviousExpr {
+        inner: Arc::new(RustMatchPreviousExpr::new(extract_parser(expr)?)),
+    })
+}
+
+/// Test each string for a literal `prefix`, `suffix` and/or `contains` substring,
+/// combined with AND; predicates left as `None` are ignored. `caseless` compares
+/// lowercased text. Runs across `num_threads` threads (default: one per CPU) with the
+/// GIL released; 1 tests every string on the calling thread. Returns a list of bool.
+#[pyfunction]
+#[pyo3(signature = (strings, prefix = None, suffix = None, contains = None, caseless = false, num_threads = None))]
+fn batch_str_predicates<'py>(
+    py: Python<'py>,
+    strings: &Bound<'py, PyList>,
+    prefix: Option<&str>,
+    suffix: Option<&str>,
+    contains: Option<&str>,
+    caseless: bool,
+    num_threads: Option<usize>,
+) -> PyResult<Bound<'py, PyList>> {
+    let threads = worker_threads(num_threads)?;
+    let text_list = TextList::new(strings, "strings")?;
+    let inputs = text_list.strs();
+    let preds = StrPredicates::new(prefix, suffix, contains, caseless);
+    let flags =
+        py.detach(|| map_in_threads(&inputs, threads, PARALLEL_MIN_INPUTS, |s| preds.test(s)))?;
+    PyList::new(py, flags)
+}
+
+/// Test each string against every regex in `patterns` in one pass. Returns per
+/// string the indices of the patterns that match anywhere in it, or with `spans`
+/// `(index, start, end)` of each matching pattern's first match, in byte offsets.
+/// All patterns are compiled up front; a PatternError lists every one that fails.
+/// Runs across `num_threads` threads (default: one per CPU) with the GIL released.
+#[pyfunction]
+#[pyo3(signature = (patterns, strings, spans = false, num_threads = None))]
+fn batch_regex_set<'py>(
+    py: Python<'py>,
+    patterns: Vec<String>,
+    strings: &Bound<'py, PyList>,
+    spans: bool,
+    num_threads: Option<usize>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let threads = worker_threads(num_threads)?;
+    let set = PatternSet::new(&patterns).map_err(|e| PatternError::new_err(e.msg))?;
+    let text_list = TextList::new(strings, "strings")?;
+    let inputs = text_list.strs();
+    if spans {
+        let found = py.detach(|| {
+            map_in_threads(&inputs, threads, PARALLEL_MIN_INPUTS, |s| {
+                set.first_matches(s)
+            })
+        })?;
+        return Ok(found.into_pyobject(py)?.into_any());
+    }
+    let found =
+        py.detach(|| map_in_threads(&inputs, threads, PARALLEL_MIN_INPUTS, |s| set.matching(s)))?;
+    Ok(found.into_pyobject(py)?.into_any())
+}
+
+/// Text of item `index` of a stream: a str, or a UTF-8 bytes-like object.
+fn stream_item_text<'a>(item: &'a Bound<'_, PyAny>, index: usize) -> PyResult<InputText<'a>> {
+    input_text(item.as_borrowed(), Some(index))?
+        .ok_or_else(|| PyValueError::new_err("inputs must be str or bytes-like objects"))
+}
+
+/// Count the items of any iterable (a generator, a file, a database cursor) that
+/// `grammar` matches at the start, like parse_batch_count, pulling `chunk_size`
+/// items at a time and matching each chunk across `num_threads` threads with the
+/// GIL released, so memory stays bounded by the chunk. Items may be str or UTF-8
+/// bytes. `grammar` may be a list of grammars, counted separately. Returns a dict
+/// with `inputs`, `matches` (items matched by any grammar) and, for a list,
+/// `per_pattern`.
+#[pyfunction]
+#[pyo3(signature = (grammar, inputs, chunk_size = 10000, num_threads = None))]
+fn stream_batch_count<'py>(
+    py: Python<'py>,
+    grammar: &Bound<'py, PyAny>,
+    inputs: &Bound<'py, PyAny>,
+    chunk_size: usize,
+    num_threads: Option<usize>,
+) -> PyResult<Bound<'py, PyDict>> {
+    if chunk_size == 0 {
+        return Err(PyValueError::new_err("chunk_size must be positive"));
+    }
+    let threads = worker_threads(num_threads)?;
+    let parsers = match grammar.cast::<PyList>() {
+        Ok(list) => extract_exprs(list)?,
+        Err(_) => vec![extract_parser(grammar)?],
+    };
+    let mut items = inputs.try_iter()?;
+    let mut total = 0;
+    let mut matches = 0;
+    let mut per_pattern = vec![0; parsers.len()];
+    loop {
+        // Owned references keep every chunk string alive while detached
+        let mut chunk: Vec<Bound<'py, PyAny>> = Vec::with_capacity(chunk_size);
+        for item in items.by_ref().take(chunk_size) {
+            chunk.push(item?);
+        }
+        if chunk.is_empty() {
+            break;
+        }
+        let chunk_texts = chunk
+            .iter()
+            .enumerate()
+            .map(|(i, item)| stream_item_text(item, total + i))
+            .collect::<PyResult<Vec<_>>>()?;
+        let texts: Vec<&str> = chunk_texts.iter().map(|t| &**t).collect();
+        let hits = py.detach(|| {
+            map_in_threads(&texts, threads, PARALLEL_MIN_INPUTS, |s| {
+                parsers
+                    .iter()
+                    .map(|p| p.try_match_at(s, 0).is_some())
+                    .collect::<Vec<_>>()
+            })
+        })?;
+        for hit in &hits {
+            matches += hit.contains(&true) as usize;
+            for (count, &matched) in per_pattern.iter_mut().zip(hit) {
+                *count += matched as usize;
+            }
+        }
+        total += chunk.len();
+    }
+    let out = PyDict::new(py);
+    out.set_item("inputs", total)?;
+    out.set_item("matches", matches)?;
+    if grammar.is_instance_of::<PyList>() {
+        out.set_item("per_pattern", per_pattern)?;
+    }
+    Ok(out)
+}
+
+/// Match statistics of `grammar` (or of each grammar in a list) over `inputs`, with
+/// matches found like search_string_count. Computed in one pass with running totals
+/// across `num_threads` threads (default: one per CPU) with the GIL released.
+/// Returns a dict:
+/// - `inputs`, `matches` and `inputs_without_matches`: counts;
+/// - `per_pattern`: matches of each grammar (one entry for a single grammar);
+/// - `per_input`: matches of all grammars in each input;
+/// - `match_length`: `min`, `max` and `mean` length in characters, None without matches;
+/// - `start_positions`: `bin_edges` (0, 1, 2, 4, 8, ...) and `counts` of matches whose
+///   start byte offset is at least the edge and below the next one.
+#[pyfunction]
+#[pyo3(signature = (grammar, inputs, num_threads = None))]
+fn aggregate_stats<'py>(
+    py: Python<'py>,
+    grammar: &Bound<'py, PyAny>,
+    inputs: &Bound<'py, PyList>,
+    num_threads: Option<usize>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let threads = worker_threads(num_threads)?;
+    let parsers = match grammar.cast::<PyList>() {
+        Ok(list) => extract_exprs(list)?,
+        Err(_) => vec![extract_parser(grammar)?],
+    };
+    let text_list = TextList::new(inputs, "inputs")?;
+    let texts = text_list.strs();
+    let stats = py.detach(|| {
+        fold_in_threads(
+            &texts,
+            threads,
+            PARALLEL_MIN_INPUTS,
+            || MatchStats::new(parsers.len()),
+            |stats, s| stats.add_input(&parsers, s),
+            MatchStats::merge,
+        )
+    })?;
+    let out = PyDict::new(py);
+    out.set_item("inputs", stats.inputs())?;
+    out.set_item("matches", stats.matches())?;
+    out.set_item("inputs_without_matches", stats.inputs_without_matches())?;
+    out.set_item("per_pattern", stats.per_pattern())?;
+    out.set_item("per_input", stats.per_input())?;
+    match stats.match_length() {
+        Some((min, max, mean)) => {
+            let length = PyDict::new(py);
+            length.set_item("min", min)?;
+            length.set_item("max", max)?;
+            length.set_item("mean", mean)?;
+            out.set_item("match_length", length)?;
+        }
+        None => out.set_item("match_length", py.None())?,
+    }
+    let starts = PyDict::new(py);
+    starts.set_item("bin_edges", stats.start_bin_edges())?;
+    starts.set_item("counts", stats.start_bins())?;
+    out.set_item("start_positions", starts)?;
+    Ok(out)
+}
+
+/// Number of matches of `grammar` (an element, or a str found literally) in each
+/// input, found like find_all_positions: with `overlap`, counting resumes one
+/// character after each match's start. Matching runs across `num_threads` threads
+/// (default: one per CPU) with the GIL released. Returns a list of counts, one per
+/// input; with `total`, only their sum; with `as_numpy`, a NumPy int64 array built
+/// from one buffer instead of a list (NumPy must be installed).
+#[pyfunction]
+#[pyo3(signature = (grammar, inputs, overlap = false, total = false, as_numpy = false, num_threads = None))]
+fn batch_count_matches<'py>(
+    py: Python<'py>,
+    grammar: &Bound<'py, PyAny>,
+    inputs: &Bound<'py, PyList>,
+    overlap: bool,
+    total: bool,
+    as_numpy: bool,
+    num_threads: Option<usize>,
+) -> PyResult<Bound<'py, PyAny>> {
+    if total && as_numpy {
+        return Err(PyValueError::new_err("pass total or as_numpy, not both"));
+    }
+    let threads = worker_threads(num_threads)?;
+    let parser = extract_parser(grammar)?;
+    let parser = &parser;
+    let text_list = TextList::new(inputs, "inputs")?;
+    let texts = text_list.strs();
+    let count = |s: &str| profile::find_spans(parser, s, overlap, None).len();
+    if total {
+        let sum = py.detach(|| {
+            fold_in_threads(
+                &texts,
+                threads,
+                PARALLEL_MIN_INPUTS,
+                || 0,
+                |sum, s| *sum += count(s),
+                |sum, part| *sum += part,
+            )
+        })?;
+        return Ok(sum.into_pyobject(py)?.into_any());
+    }
+    let counts = py.detach(|| map_in_threads(&texts, threads, PARALLEL_MIN_INPUTS, count))?;
+    match as_numpy {
+        true => numpy_int64(py, &counts),
+        false => Ok(counts.into_pyobject(py)?.into_any()),
+    }
+}
+
+/// `values` as a NumPy int64 array, built from one buffer rather than a list.
+fn numpy_int64<'py>(py: Python<'py>, values: &[usize]) -> PyResult<Bound<'py, PyAny>> {
+    let raw: Vec<u8> = values
+        .iter()
+        .flat_map(|&n| (n as i64).to_ne_bytes())
+        .collect();
+    py.import("numpy")?
+        .getattr("frombuffer")?
+        .call1((PyByteArray::new(py, &raw), "int64"))
+}
+
+/// Occurrences of a set of patterns in each input, as `(input_index, pattern_index,
+/// start, end)` records in input order, then by start and end. `patterns` is a list of str
+/// (found literally, all in one Aho-Corasick pass) or elements, found with
+/// try_match_at like find_all_positions. By default leftmost-longest without
+/// overlaps: the match starting first wins, then the longest, then the pattern
+/// listed first. With `report_all`, every pattern's matches at every offset. With
+/// `as_numpy`, four NumPy int64 arrays `(input_index, pattern_index, start, end)`
+/// instead of a list. Runs across `num_threads` threads (default: one per CPU)
+/// with the GIL released.
+#[pyfunction]
+#[pyo3(signature = (patterns, inputs, report_all = false, as_numpy = false, num_threads = None))]
+fn multi_match_indices<'py>(
+    py: Python<'py>,
+    patterns: &Bound<'py, PyList>,
+    inputs: &Bound<'py, PyList>,
+    report_all: bool,
+    as_numpy: bool,
+    num_threads: Option<usize>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let threads = worker_threads(num_threads)?;
+    let literals: Option<Vec<String>> = patterns
+        .iter()
+        .map(|p| p.cast::<PyString>().ok().map(|s| s.to_string()))
+        .collect();
+    let text_list = TextList::new(inputs, "inputs")?;
+    let texts = text_list.strs();
+    let found = match literals {
+        Some(literals) => {
+            let options = ScannerOptions {
+                overlapping: report_all,
+                ..ScannerOptions::default()
+            };
+            let scanner =
+                FastScanner::new(&literals, options).map_err(|e| PatternError::new_err(e.msg))?;
+            py.detach(|| map_in_threads(&texts, threads, PARALLEL_MIN_INPUTS, |s| scanner.scan(s)))?
+        }
+        None => {
+            let parsers = extract_exprs(patterns)?;
+            if parsers.is_empty() {
+                return Err(PatternError::new_err("patterns must not be empty"));
+            }
+            py.detach(|| {
+                map_in_threads(&texts, threads, PARALLEL_MIN_INPUTS, |s| {
+                    scan_elements(&parsers, s, report_all)
+                })
+            })?
+        }
+    };
+    let records = found.iter().enumerate().flat_map(|(i, matches)| {
+        matches
+            .iter()
+            .map(move |&(pattern, start, end)| (i, pattern, start, end))
+    });
+    if !as_numpy {
+        return Ok(PyList::new(py, records.collect::<Vec<_>>())?.into_any());
+    }
+    let mut columns: [Vec<usize>; 4] = Default::default();
+    for (i, pattern, start, end) in records {
+        for (column, value) in columns.iter_mut().zip([i, pattern, start, end]) {
+            column.push(value);
+        }
+    }
+    let arrays = columns
+        .iter()
+        .map(|column| numpy_int64(py, column))
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(PyTuple::new(py, arrays)?.into_any())
+}
+
+/// Histogram of the lengths in characters of each input, or with `grammar` of each
+/// match of it in the inputs (found like search_string_count). Bins are `bin_width`
+/// wide from `min`; give `num_bins` or `max` for the top of the range, or both to
+/// derive the width (default 1). Values below `min` count as underflow, values at or
+/// above the top as overflow. Lengths are counted straight into the bins across
+/// `num_threads` threads (default: one per CPU) with the GIL released. Returns a
+/// dict of `bin_edges` (floats, `num_bins + 1`), `counts`, `underflow`, `overflow`
+/// and `total`, e.g. for `matplotlib.pyplot.stairs(counts, bin_edges)`.
+#[pyfunction]
+#[pyo3(signature = (inputs, grammar = None, bin_width = None, num_bins = None, min = 0.0, max = None, num_threads = None))]
+fn length_histogram<'py>(
+    inputs: &Bound<'py, PyList>,
+    grammar: Option<&Bound<'py, PyAny>>,
+    bin_width: Option<f64>,
+    num_bins: Option<usize>,
+    min: f64,
+    max: Option<f64>,
+    num_threads: Option<usize>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let py = inputs.py();
+    let threads = worker_threads(num_threads)?;
+    if bin_width.is_some_and(|w| !(w > 0.0 && w.is_finite())) {
+        return Err(PyValueError::new_err("bin_width must be positive"));
+    }
+    if num_bins == Some(0) {
+        return Err(PyValueError::new_err("num_bins must be positive"));
+    }
+    if !min.is_finite() || max.is_some_and(|max| !(max > min && max.is_finite())) {
+        return Err(PyValueError::new_err("max must be greater than min"));
+    }
+    let (bin_width, num_bins) = match (bin_width, num_bins, max) {
+        (None, Some(n), Some(max)) => ((max - min) / n as f64, n),
+        (Some(w), Some(n), Some(max)) => {
+            if (min + n as f64 * w - max).abs() > w * 1e-9 {
+                return Err(PyValueError::new_err(
+                    "bin_width, num_bins and max disagree: max must be min + num_bins * bin_width",
+                ));
+            }
+            (w, n)
+        }
+        (w, Some(n), None) => (w.unwrap_or(1.0), n),
+        (w, None, Some(max)) => {
+            let w = w.unwrap_or(1.0);
+            (w, ((max - min) / w).ceil() as usize)
+        }
+        (_, None, None) => return Err(PyValueError::new_err("give num_bins or max")),
+    };
+    let parser = grammar.map(extract_parser).transpose()?;
+    let text_list = TextList::new(inputs, "inputs")?;
+    let texts = text_list.strs();
+    let histogram = py.detach(|| {
+        fold_in_threads(
+            &texts,
+            threads,
+            PARALLEL_MIN_INPUTS,
+            || Histogram::new(min, bin_width, num_bins),
+            |histogram, s| match &parser {
+                Some(parser) => for_each_match(parser.as_ref(), s, |start, end| {
+                    histogram.add(char_len(s, start, end))
+                }),
+                None => histogram.add(s.chars().count()),
+            },
+            Histogram::merge,
+        )
+    })?;
+    let counts = histogram.counts();
+    let out = PyDict::new(py);
+    out.set_item("bin_edges", histogram.edges())?;
+    out.set_item("counts", counts)?;
+    out.set_item("underflow", histogram.underflow())?;
+    out.set_item("overflow", histogram.overflow())?;
+    out.set_item(
+        "total",
+        counts.iter().sum::<usize>() + histogram.underflow() + histogram.overflow(),
+    )?;
+    Ok(out)
+}
+
+/// Import a string column through the Arrow PyCapsule interface, preferring
+/// `__arrow_c_stream__` (chunked columns) over `__arrow_c_array__`.
+fn import_string_column(column: &Bound<'_, PyAny>) -> PyResult<StringColumn> {
+    let arrow_error = |e: ArrowError| PyValueError::new_err(e.msg);
+    if column.hasattr("__arrow_c_stream__")? {
+        let capsule = column.call_method0("__arrow_c_stream__")?;
+        let stream = capsule
+            .cast::<PyCapsule>()?
+            .pointer_checked(Some(c"arrow_array_stream"))?;
+        let stream = unsafe { ArrowArrayStream::take(stream.as_ptr().cast()) };
+        return StringColumn::from_stream(stream).map_err(arrow_error);
+    }
+    if column.hasattr("__arrow_c_array__")? {
+        let (schema, array): (Bound<'_, PyAny>, Bound<'_, PyAny>) =
+            column.call_method0("__arrow_c_array__")?.extract()?;
+        let schema = schema
+            .cast::<PyCapsule>()?
+            .pointer_checked(Some(c"arrow_schema"))?;
+        let array = array
+            .cast::<PyCapsule>()?
+            .pointer_checked(Some(c"arrow_array"))?;
+        let (schema, array) = unsafe {
+            (
+                ArrowSchema::take(schema.as_ptr().cast()),
+                ArrowArray::take(array.as_ptr().cast()),
+            )
+        };
+        return StringColumn::from_array(schema, array).map_err(arrow_error);
+    }
+    Err(PyTypeError::new_err(
+        "column must support the Arrow PyCapsule interface (__arrow_c_stream__ or __arrow_c_array__)",
+    ))
+}
+
+/// Tokens as strings for an Arrow column: groups flattened, other values through str().
+fn push_token_strings<'a>(
+    py: Python<'_>,
+    items: &'a [ParseResultItem],
+    out: &mut Vec<Cow<'a, str>>,
+) -> PyResult<()> {
+    for item in items {
+        match item {
+            ParseResultItem::Token(t) => out.push(Cow::Borrowed(t)),
+            ParseResultItem::Group(inner) => push_token_strings(py, inner.items(), out)?,
+            ParseResultItem::Int(v) => out.push(Cow::Owned(v.to_string())),
+            ParseResultItem::Float(v) => {
+                out.push(Cow::Owned(PyFloat::new(py, *v).str()?.to_str()?.to_owned()))
+            }
+            ParseResultItem::Object(value) => {
+                if let Some(obj) = value.0.downcast_ref::<Py<PyAny>>() {
+                    out.push(Cow::Owned(obj.bind(py).str()?.to_str()?.to_owned()));
+                } else if let Some(captures) = value.0.downcast_ref::<RegexCaptures>() {
+                    out.push(Cow::Borrowed(&captures.text));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse every row of an Arrow string column (anything with `__arrow_c_stream__` or
+/// `__arrow_c_array__`, e.g. a pyarrow Array or a table column) like parse_string,
+/// reading the strings in place from the Arrow buffers. Returns an ArrowColumn of
+/// each row's first token, or with `all_tokens` a list of all its tokens; rows that
+/// are null or don't match (or have no tokens, for the first token) are null.
+#[pyfunction]
+#[pyo3(signature = (grammar, column, all_tokens = false))]
+fn parse_arrow_column(
+    py: Python<'_>,
+    grammar: &Bound<'_, PyAny>,
+    column: &Bound<'_, PyAny>,
+    all_tokens: bool,
+) -> PyResult<PyArrowColumn> {
+    let parser = extract_parser(grammar)?;
+    let input = import_string_column(column)?;
+    let rows = input.rows().map_err(|e| PyValueError::new_err(e.msg))?;
+    let parse = |s| {
+        let mut ctx = ParseContext::new(s);
+        let loc = skip_ws(s, 0);
+        ctx.parse(&parser, loc).ok().map(|(_end, results)| results)
+    };
+    // Parse actions may have side effects or raise: run them attached, in order
+    let parsed: Vec<Option<ParseResults>> = if has_parse_actions(parser.as_ref()) {
+        let mut parsed = Vec::with_capacity(rows.len());
+        for row in &rows {
+            parsed.push(row.and_then(parse));
+            check_action_error()?;
+        }
+        parsed
+    } else {
+        py.detach(|| rows.iter().map(|row| row.and_then(parse)).collect())
+    };
+    let mut out = match all_tokens {
+        true => ResultColumn::lists(),
+        false => ResultColumn::strings(),
+    };
+    let mut tokens = Vec::new();
+    for results in &parsed {
+        let Some(results) = results else {
+            out.push_null();
+            continue;
+        };
+        tokens.clear();
+        push_token_strings(py, results.items(), &mut tokens)?;
+        out.push_tokens(&tokens);
+    }
+    Ok(PyArrowColumn { inner: out })
+}
+
+/// Parse each string like parse_string and return its tokens as a CompactResults:
+/// one buffer of all token text plus int64 arrays of each token's input index and
+/// span in that buffer, instead of a list per input and a str per token. Groups are
+/// flattened and non-string tokens converted with str(); inputs that don't match
+/// have no tokens. Parsing runs across `num_threads` threads (default: one per
+/// CPU) with the GIL released, unless the grammar has parse actions.
+#[pyfunction]
+#[pyo3(signature = (grammar, inputs, num_threads = None))]
+fn parse_batch_compact(
+    py: Python<'_>,
+    grammar: &Bound<'_, PyAny>,
+    inputs: &Bound<'_, PyList>,
+    num_threads: Option<usize>,
+) -> PyResult<PyCompactResults> {
+    let threads = worker_threads(num_threads)?;
+    let parser = extract_parser(grammar)?;
+    let text_list = TextList::new(inputs, "inputs")?;
+    let texts = text_list.strs();
+    let parse = |s| {
+        let mut ctx = ParseContext::new(s);
+        ctx.parse(&parser, skip_ws(s, 0))
+            .ok()
+            .map(|(_end, results)| results)
+    };
+    // Parse actions may have side effects or raise: run them attached, in order
+    let parsed: Vec<Option<ParseResults>> = if has_parse_actions(parser.as_ref()) {
+        let mut parsed = Vec::with_capacity(texts.len());
+        for text in &texts {
+            parsed.push(parse(text));
+            check_action_error()?;
+        }
+        parsed
+    } else {
+        py.detach(|| map_in_threads(&texts, threads, PARALLEL_MIN_INPUTS, parse))?
+    };
+    let mut out = CompactResults::new();
+    let mut tokens = Vec::new();
+    for results in &parsed {
+        tokens.clear();
+        if let Some(results) = results {
+            push_token_strings(py, results.items(), &mut tokens)?;
+        }
+        out.push_input(&tokens);
+    }
+    Ok(PyCompactResults { inner: out })
+}
+
+/// Rebuild CompactResults from the dict returned by its buffers(), e.g. after
+/// sending it to another process. The buffers are checked before use.
+#[pyfunction]
+fn decode_compact(buffers: &Bound<'_, PyDict>) -> PyResult<PyCompactResults> {
+    let get = |key: &str| -> PyResult<Bound<'_, PyAny>> {
+        buffers
+            .get_item(key)?
+            .ok_or_else(|| PyValueError::new_err(format!("buffers has no '{}'", key)))
+    };
+    let int64s = |key: &str| -> PyResult<Vec<i64>> {
+        let raw: Vec<u8> = get(key)?.cast::<PyBytes>()?.as_bytes().to_vec();
+        if !raw.len().is_multiple_of(8) {
+            return Err(PyValueError::new_err(format!(
+                "'{}' is not a whole number of int64 values",
+                key
+            )));
+        }
+        Ok(raw
+            .chunks_exact(8)
+            .map(|b| i64::from_ne_bytes(b.try_into().expect("8-byte chunk")))
+            .collect())
+    };
+    let inner = CompactResults::from_parts(
+        get("inputs")?.extract()?,
+        get("data")?.cast::<PyBytes>()?.as_bytes().to_vec(),
+        int64s("input_index")?,
+        int64s("token_start")?,
+        int64s("token_end")?,
+    )
+    .map_err(|e| PyValueError::new_err(e.msg))?;
+    Ok(PyCompactResults { inner })
+}
+
+/// Estimate how often `grammar` matches in a corpus by scanning a reproducible random
+/// `fraction` of it. `source` is either the text itself (sampled by line) or a list of
+/// file paths (sampled as `window`-byte regions aligned to line boundaries; only those
+/// regions are read). Returns a dict with the estimate, a 95% interval and examples.
+#[pyfunction]
+#[pyo3(signature = (source, grammar, fraction = 0.01, seed = 42, window = 65536, max_examples = 5))]
+fn sample_scan<'py>(
+    py: Python<'py>,
+    source: &Bound<'py, PyAny>,
+    grammar: &Bound<'py, PyAny>,
+    fraction: f64,
+    seed: u64,
+    window: usize,
+    max_examples: usize,
+) -> PyResult<Bound<'py, PyDict>> {
+    if !(fraction > 0.0 && fraction <= 1.0) {
+        return Err(PyValueError::new_err("fraction must be in (0, 1]"));
+    }
+    let parser = extract_parser(grammar)?;
+    let report = if let Ok(text) = source.cast::<PyString>() {
+        let text = text.to_str()?;
+        py.detach(|| sample_text(parser.as_ref(), text, fraction, seed, max_examples))
+    } else {
+        let paths: Vec<std::path::PathBuf> = source.extract().map_err(|_| {
+            PyValueError::new_err("source must be a str of text or a list of file paths")
+        })?;
+        py.detach(|| {
+            sample_files(
+                parser.as_ref(),
+                &paths,
+                window,
+                fraction,
+                seed,
+                max_examples,
+            )
+        })
+        .map_err(|e| file_error(&e))?
+    };
+    let out = PyDict::new(py);
+    out.set_item("estimate", report.estimate)?;
+    out.set_item("ci_low", report.ci_low)?;
+    out.set_item("ci_high", report.ci_high)?;
+    out.set_item("matches_sampled", report.matches_sampled)?;
+    out.set_item("units_sampled", report.units_sampled)?;
+    out.set_item("units_total", report.units_total)?;
+    out.set_item("examples", report.examples)?;
+    Ok(out)
+}
+
+/// Replacement for a transform_multi rule: fixed text or a callable given the matched text.
+enum Replacement {
+    Text(String),
+    Call(Py<PyAny>),
+}
+
+/// transform_multi rules: `parsers[i]` is replaced according to `replacements[i]`.
+struct ReplaceRules {
+    parsers: Vec<Arc<dyn ParserElement>>,
+    replacements: Vec<Replacement>,
+}
+
+/// Split `[(grammar, replacement), ...]` into parsers and replacements.
+fn extract_rules(rules: &Bound<'_, PyList>) -> PyResult<ReplaceRules> {
+    let mut parsers = Vec::with_capacity(rules.len());
+    let mut replacements = Vec::with_capacity(rules.len());
+    for rule in rules.iter() {
+        let (grammar, replacement): (Bound<'_, PyAny>, Bound<'_, PyAny>) =
+            rule.extract().map_err(|_| {
+                PyValueError::new_err("rules must be a list of (grammar, replacement) pairs")
+            })?;
+        parsers.push(extract_parser(&grammar)?);
+        replacements.push(if let Ok(text) = replacement.cast::<PyString>() {
+            Replacement::Text(text.to_str()?.to_string())
+        } else if replacement.is_callable() {
+            Replacement::Call(replacement.unbind())
+        } else {
+            return Err(PyValueError::new_err(
+                "replacement must be a str or a callable taking the matched text",
+            ));
+        });
+    }
+    Ok(ReplaceRules {
+        parsers,
+        replacements,
+    })
+}
+
+/// Append `text` to `out` with each match replaced; returns the number of replacements.
+fn apply_rules(
+    py: Python<'_>,
+    rules: &ReplaceRules,
+    text: &str,
+    out: &mut String,
+) -> PyResult<usize> {
+    let matches = py.detach(|| scan_rules(&rules.parsers, text));
+    let mut copied = 0;
+    for m in &matches {
+        out.push_str(&text[copied..m.start]);
+        match &rules.replacements[m.rule] {
+            Replacement::Text(r) => out.push_str(r),
+            Replacement::Call(f) => {
+                let r = f.bind(py).call1((&text[m.start..m.end],))?;
+                out.push_str(r.cast::<PyString>()?.to_str()?);
+            }
+        }
+        copied = m.end;
+    }
+    out.push_str(&text[copied..]);
+    Ok(matches.len())
+}
+
+/// Replace matches of several grammars in one pass. `rules` is an ordered list of
+/// `(grammar, replacement)` where replacement is a str or a callable given the
+/// matched text. The earliest match wins, then the longest, then the earlier rule;
+/// replaced text is never rematched. Everything else is copied through unchanged.
+#[pyfunction]
+fn transform_multi<'py>(
+    py: Python<'py>,
+    text: &str,
+    rules: &Bound<'py, PyList>,
+) -> PyResult<Bound<'py, PyString>> {
+    let rules = extract_rules(rules)?;
+    let mut out = String::with_capacity(text.len());
+    apply_rules(py, &rules, text, &mut out)?;
+    Ok(PyString::new(py, &out))
+}
+
+/// Replace matches of `pattern` in each string. `pattern` is a str (found
+/// literally), a Regex element (its capture groups usable in the template) or any
+/// other element (found like transform_multi). `replacement` is a template: `$0` is
+/// the matched text, `$1`, `$2`, ... (or `${1}`) a Regex's groups, `$$` a literal `$`.
+/// At most `max_replacements` per string, or only the first with `first_only`.
+/// Runs across `num_threads` threads (default: one per CPU) with the GIL released.
+/// Returns `(new_strings, counts)`; strings with no match are returned as given.
+#[pyfunction]
+#[pyo3(signature = (strings, pattern, replacement, max_replacements = None, first_only = false, num_threads = None))]
+fn batch_replace<'py>(
+    py: Python<'py>,
+    strings: &Bound<'py, PyList>,
+    pattern: &Bound<'py, PyAny>,
+    replacement: &str,
+    max_replacements: Option<usize>,
+    first_only: bool,
+    num_threads: Option<usize>,
+) -> PyResult<(Bound<'py, PyList>, Vec<usize>)> {
+    let threads = worker_threads(num_threads)?;
+    let pattern = if let Ok(text) = pattern.cast::<PyString>() {
+        let text = text.to_str()?;
+        if text.is_empty() {
+            return Err(PatternError::new_err("cannot replace an empty string"));
+        }
+        let literal = regex::Regex::new(&regex::escape(text))
+            .map_err(|e| PatternError::new_err(e.to_string()))?;
+        ReplacePattern::Regex(literal)
+    } else {
+        let parser = extract_parser(pattern)?;
+        match parser.clone().into_any().downcast::<RegexMatch>() {
+            Ok(regex) => ReplacePattern::Regex(regex.search_regex().clone()),
+            Err(_) => ReplacePattern::Element(parser),
+        }
+    };
+    let limit = match first_only {
+        true => Some(max_replacements.map_or(1, |max| max.min(1))),
+        false => max_replacements,
+    };
+    let replacer = Replacer::new(pattern, Template::parse(replacement), limit)
+        .map_err(|e| PatternError::new_err(e.msg))?;
+    let text_list = TextList::new(strings, "strings")?;
+    let inputs = text_list.strs();
+    let replaced = py.detach(|| {
+        map_in_threads(&inputs, threads, PARALLEL_MIN_INPUTS, |s| {
+            replacer.replace(s)
+        })
+    })?;
+    let mut counts = Vec::with_capacity(replaced.len());
+    let out = PyList::empty(py);
+    for ((item, s), result) in text_list.snapshot.iter().zip(&inputs).zip(replaced) {
+        match result {
+            Some((text, count)) => {
+                out.append(PyString::new(py, &text))?;
+                counts.push(count);
+            }
+            // An unchanged str is returned as is; bytes-like inputs become str
+            None if item.is_instance_of::<PyString>() => {
+                out.append(item)?;
+                counts.push(0);
+            }
+            None => {
+                out.append(PyString::new(py, s))?;
+                counts.push(0);
+            }
+        }
+    }
+    Ok((out, counts))
+}
+
+/// transform_multi over a file, streamed to `output_path` in blocks of whole lines of
+/// about `block_size` bytes; matches must not span lines. Returns the number of
+/// replacements made.
+#[pyfunction]
+#[pyo3(signature = (input_path, output_path, rules, block_size = 1048576))]
+fn transform_multi_file(
+    py: Python<'_>,
+    input_path: std::path::PathBuf,
+    output_path: std::path::PathBuf,
+    rules: &Bound<'_, PyList>,
+    block_size: usize,
+) -> PyResult<usize> {
+    let started = metrics_start();
+    let mut bytes = 0;
+    let out = stream_transform_multi(py, &input_path, &output_path, rules, block_size, &mut bytes);
+    let replaced = *out.as_ref().unwrap_or(&0);
+    metrics_finish(
+        started,
+        || Cow::Borrowed("transform_multi_file"),
+        Operation::File,
+        out.is_ok(),
+        bytes,
+        replaced,
+    );
+    out
+}
+
+/// Body of transform_multi_file, counting the bytes read into `bytes`.
+fn stream_transform_multi(
+    py: Python<'_>,
+    input_path: &std::path::Path,
+    output_path: &std::path::Path,
+    rules: &Bound<'_, PyList>,
+    block_size: usize,
+    bytes: &mut usize,
+) -> PyResult<usize> {
+    use std::io::{BufRead, Write};
+    let rules = extract_rules(rules)?;
+    let input =
+        std::fs::File::open(input_path).map_err(|e| file_error(&FileError::new(input_path, e)))?;
+    let output = std::fs::File::create(output_path)
+        .map_err(|e| file_error(&FileError::new(output_path, e)))?;
+    let mut reader = std::io::BufReader::new(input);
+    let mut writer = std::io::BufWriter::new(output);
+    let mut block = String::new();
+    let mut out = String::new();
+    let mut lineno = 0;
+    let mut replaced = 0;
+    loop {
+        block.clear();
+        let mut at_eof = false;
+        while block.len() < block_size.max(1) {
+            match reader.read_line(&mut block) {
+                Ok(0) => {
+                    at_eof = true;
+                    break;
+                }
+                Ok(_) => lineno += 1,
+                Err(e) => {
+                    let mut err = FileError::new(input_path, e);
+                    err.line = Some(lineno + 1);
+                    return Err(file_error(&err));
+                }
+            }
+        }
+        *bytes += block.len();
+        out.clear();
+        replaced += apply_rules(py, &rules, &block, &mut out)?;
+        writer
+            .write_all(out.as_bytes())
+            .map_err(|e| file_error(&FileError::new(output_path, e)))?;
+        if at_eof {
+            break;
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| file_error(&FileError::new(output_path, e)))?;
+    Ok(replaced)
+}
+
+/// Call `callback` with lists of up to `batch_size` `(line_number, tokens)` pairs for
+/// the lines of the file at `path` that `grammar` matches somewhere in: 1-based line
+/// numbers, the tokens of the first match in the line. Lines are read and matched with
+/// the GIL released between callbacks, so memory stays bounded by `batch_size` however
+/// large the file. Stops early when the callback returns False; exceptions it raises
+/// propagate. Returns the number of matching lines passed to the callback. With
+/// `timeout_ms`, raises TimeoutError between batches once that many milliseconds
+/// have passed.
+#[pyfunction]
+#[pyo3(signature = (path, grammar, callback, batch_size = 10000, timeout_ms = None))]
+fn process_file_streaming(
+    py: Python<'_>,
+    path: std::path::PathBuf,
+    grammar: &Bound<'_, PyAny>,
+    callback: &Bound<'_, PyAny>,
+    batch_size: usize,
+    timeout_ms: Option<u64>,
+) -> PyResult<usize> {
+    if batch_size == 0 {
+        return Err(PyValueError::new_err("batch_size must be positive"));
+    }
+    let parser = extract_parser(grammar)?;
+    let started = metrics_start();
+    let mut bytes = 0;
+    let out = with_timeout(timeout_ms, || {
+        stream_file_matches(py, &path, &parser, callback, batch_size, &mut bytes)
+    });
+    let delivered = *out.as_ref().unwrap_or(&0);
+    metrics_finish(
+        started,
+        || metrics_label(parser.as_ref()),
+        Operation::File,
+        out.is_ok(),
+        bytes,
+        delivered,
+    );
+    out
+}
+
+/// Match `grammar` against column `column_index` of each record of the CSV file at
+/// `path`, like parse_string on the field's text. Fields follow RFC 4180 quoting, so
+/// quoted fields may hold delimiters, line breaks and doubled quotes. Returns
+/// `(row, value, tokens)` for the matching fields, `row` counting data records from 0
+/// (the header, if any, is skipped). Records without that column are skipped.
+#[pyfunction]
+#[pyo3(signature = (path, column_index, grammar, has_header = true, delimiter = ","))]
+fn process_csv_field<'py>(
+    py: Python<'py>,
+    path: std::path::PathBuf,
+    column_index: usize,
+    grammar: &Bound<'py, PyAny>,
+    has_header: bool,
+    delimiter: &str,
+) -> PyResult<Bound<'py, PyList>> {
+    let mut chars = delimiter.chars();
+    let delimiter = match (chars.next(), chars.next()) {
+        (Some(c), None) if !matches!(c, '"' | '\r' | '\n') => c,
+        _ => {
+            return Err(PyValueError::new_err(
+                "delimiter must be a single character other than a quote or line break",
+            ))
+        }
+    };
+    let parser = extract_parser(grammar)?;
+    let started = metrics_start();
+    let read_fields = || -> Result<Vec<(usize, String, ParseResults<'static>)>, FileError> {
+        let input = std::fs::File::open(&path).map_err(|e| FileError::new(&path, e))?;
+        let mut reader = CsvReader::new(std::io::BufReader::new(input), delimiter);
+        let mut fields = Vec::new();
+        let mut matches = Vec::new();
+        let mut row = 0;
+        let mut skip_header = has_header;
+        loop {
+            match reader.read_record(&mut fields) {
+                Ok(true) => {}
+                Ok(false) => return Ok(matches),
+                Err(e) => {
+                    let mut err = FileError::new(&path, e);
+                    err.line = Some(reader.line_number());
+                    return Err(err);
+                }
+            }
+            if std::mem::take(&mut skip_header) {
+                continue;
+            }
+            if let Some(value) = fields.get(column_index) {
+                let mut ctx = ParseContext::new(value);
+                if let Ok((_, results)) = parser.parse_impl(&mut ctx, skip_ws(value, 0)) {
+                    matches.push((row, value.clone(), results.into_owned()));
+                }
+            }
+            row += 1;
+        }
+    };
+    // Parse actions may have side effects or raise: run them attached
+    let read = match has_parse_actions(parser.as_ref()) {
+        true => read_fields(),
+        false => py.detach(read_fields),
+    };
+    let out = check_action_error()
+        .and_then(|()| read.map_err(|e| file_error(&e)))
+        .and_then(|matches| {
+            let items = matches
+                .iter()
+                .map(|(row, value, results)| Ok((*row, value, results_to_py_results(py, results)?)))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)
+        });
+    let bytes = std::fs::metadata(&path).map_or(0, |m| m.len() as usize);
+    metrics_finish(
+        started,
+        || metrics_label(parser.as_ref()),
+        Operation::File,
+        out.is_ok(),
+        bytes,
+        out.as_ref().map_or(0, |list| list.len()),
+    );
+    out
+}
+
+/// The matching lines of process_file_streaming as one list, with the file split into
+/// line-aligned chunks matched on `num_threads` threads (default: one per CPU):
+/// `num_chunks` chunks (default: one per thread), or chunks of about `chunk_size`
+/// bytes. Each split point moves forward to just past the next newline, so no line is
+/// split or matched twice and the result is the same as a single-threaded scan. With
+/// `num_threads=1`, or a grammar with parse actions, chunks run in order on the
+/// calling thread. With `to_json` or `jsonl_path`, the matches are returned or written
+/// as JSON like parse_batch's, each with its `line`, `tokens` and `named` results.
+/// With `timeout_ms`, raises TimeoutError at the first chunk started after that many
+/// milliseconds.
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    grammar,
+    num_chunks = None,
+    chunk_size = None,
+    num_threads = None,
+    to_json = false,
+    pretty = false,
+    jsonl_path = None,
+    timeout_ms = None
+))]
+#[allow(clippy::too_many_arguments)]
+fn split_file_process<'py>(
+    py: Python<'py>,
+    path: std::path::PathBuf,
+    grammar: &Bound<'py, PyAny>,
+    num_chunks: Option<usize>,
+    chunk_size: Option<usize>,
+    num_threads: Option<usize>,
+    to_json: bool,
+    pretty: bool,
+    jsonl_path: Option<std::path::PathBuf>,
+    timeout_ms: Option<u64>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let threads = worker_threads(num_threads)?;
+    let parser = extract_parser(grammar)?;
+    let parser = &parser;
+    let len = std::fs::metadata(&path)
+        .map_err(|e| file_error(&FileError::new(&path, e)))?
+        .len() as usize;
+    let num_chunks = match (num_chunks, chunk_size) {
+        (Some(_), Some(_)) => {
+            return Err(PyValueError::new_err(
+                "pass num_chunks or chunk_size, not both",
+            ))
+        }
+        (Some(0), None) | (None, Some(0)) => {
+            return Err(PyValueError::new_err(
+                "num_chunks and chunk_size must be positive",
+            ))
+        }
+        (Some(n), None) => n,
+        (None, Some(size)) => len.div_ceil(size),
+        (None, None) => threads,
+    };
+    // Parse actions may have side effects or raise: run them attached, in order
+    let attached = has_parse_actions(parser.as_ref());
+    let started = metrics_start();
+    let run = || -> Result<Vec<(usize, ParseResults)>, FileError> {
+        let chunks = line_aligned_chunks(&path, num_chunks)?;
+        let workers = match attached {
+            true => 1,
+            false => threads.min(chunks.len()).max(1),
+        };
+        let mut signals = SignalPoll::new();
+        let per_chunk: PyResult<Vec<Result<ChunkMatches, FileError>>> = if workers == 1 {
+            signals.map(&chunks, |&c| match_chunk_lines(&path, c, parser))
+        } else {
+            let stop = &AtomicBool::new(false);
+            std::thread::scope(|scope| {
+                // Worker w takes chunks w, w + workers, ...
+                let handles: Vec<_> = (0..workers)
+                    .map(|w| {
+                        let (chunks, path) = (&chunks, &path);
+                        spawn_worker(scope, move || {
+                            let mine = chunks.iter().skip(w).step_by(workers);
+                            mine.map_while(|&c| {
+                                (!stop.load(Ordering::Relaxed))
+                                    .then(|| match_chunk_lines(path, c, parser))
+                            })
+                            .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect();
+                let mut parts: Vec<_> = signals
+                    .join(handles, stop)?
+                    .into_iter()
+                    .map(Vec::into_iter)
+                    .collect();
+                Ok((0..chunks.len())
+                    .filter_map(|i| parts[i % workers].next())
+                    .collect())
+            })
+        };
+        // Interrupted: check_action_error raises the handler's exception
+        let per_chunk = match per_chunk {
+            Ok(per_chunk) => per_chunk,
+            Err(err) => {
+                stash_action_error(err);
+                Vec::new()
+            }
+        };
+        let mut matches = Vec::new();
+        let mut lines_before = 0;
+        for chunk in per_chunk {
+            let chunk = chunk.map_err(|mut e| {
+                e.line = e.line.map(|line| line + lines_before);
+                e
+            })?;
+            let found = chunk.matches.into_iter();
+            matches.extend(found.map(|(line, results)| (line + lines_before, results)));
+            lines_before += chunk.lines;
+        }
+        Ok(matches)
+    };
+    let read = with_timeout(timeout_ms, || match attached {
+        true => run(),
+        false => py.detach(run),
+    });
+    let matches = check_action_error().and_then(|()| read.map_err(|e| file_error(&e)));
+    let found = matches.as_ref().map_or(0, Vec::len);
+    let out = matches.and_then(|matches| {
+        if to_json || jsonl_path.is_some() {
+            let records = matches
+                .iter()
+                .map(|(line, results)| {
+                    let mut record = serde_json::Map::new();
+                    record.insert("line".into(), (*line).into());
+                    record.extend(json::results_record(results, &object_json));
+                    JsonValue::Object(record)
+                })
+                .collect();
+            return json_output(py, records, pretty, jsonl_path.as_deref());
+        }
+        let items = matches
+            .iter()
+            .map(|(line, results)| Ok((*line, results_to_py_results(py, results)?)))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(PyList::new(py, items)?.into_any())
+    });
+    metrics_finish(
+        started,
+        || metrics_label(parser.as_ref()),
+        Operation::File,
+        out.is_ok(),
+        len,
+        found,
+    );
+    out
+}
+
+/// Wildcard patterns from a str or a list of str; None is no patterns.
+fn glob_list(patterns: Option<&Bound<'_, PyAny>>, what: &str) -> PyResult<Vec<Glob>> {
+    let Some(patterns) = patterns else {
+        return Ok(Vec::new());
+    };
+    let patterns: Vec<String> = match patterns.cast::<PyString>() {
+        Ok(one) => vec![one.to_str()?.to_string()],
+        Err(_) => patterns.extract().map_err(|_| {
+            PyValueError::new_err(format!("{} must be a str or a list of str", what))
+        })?,
+    };
+    patterns
+        .iter()
+        .map(|p| Glob::new(p).map_err(|e| PatternError::new_err(e.to_string())))
+        .collect()
+}
+
+/// Files matched at a time by scan_directory, per worker thread: after each wave the
+/// hits are counted against `max_results`, so no more than a wave is scanned past it.
+const SCAN_WAVE_PER_WORKER: usize = 8;
+
+/// Match `grammar` against each line of the files under `root`, returning
+/// `{path: [(line_number, tokens), ...]}` for the files with a matching line, in
+/// walk order: depth first, names sorted. 1-based line numbers; the tokens of the
+/// first match in the line, as in process_file_streaming. `include` and `exclude`
+/// are fnmatch-style patterns (a str or a list) matched against each path as
+/// `root` joined with the relative path; excluded directories aren't entered.
+/// Files with a NUL byte or that aren't UTF-8 are skipped as binary. Symlinks are
+/// skipped unless `follow_symlinks`; when followed, each directory is entered only
+/// once. `max_depth` limits how many levels below `root` are entered, and
+/// `max_results` caps the total number of lines returned. Files are matched across
+/// `num_threads` threads (default: one per CPU) with the GIL released. With
+/// `timeout_ms`, raises TimeoutError at the first file started after that many
+/// milliseconds.
+#[pyfunction]
+#[pyo3(signature = (
+    root,
+    grammar,
+    include = None,
+    exclude = None,
+    follow_symlinks = false,
+    max_depth = None,
+    max_results = None,
+    num_threads = None,
+    timeout_ms = None
+))]
+#[allow(clippy::too_many_arguments)]
+fn scan_directory<'py>(
+    py: Python<'py>,
+    root: std::path::PathBuf,
+    grammar: &Bound<'py, PyAny>,
+    include: Option<&Bound<'py, PyAny>>,
+    exclude: Option<&Bound<'py, PyAny>>,
+    follow_symlinks: bool,
+    max_depth: Option<usize>,
+    max_results: Option<usize>,
+    num_threads: Option<usize>,
+    timeout_ms: Option<u64>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let threads = worker_threads(num_threads)?;
+    let parser = extract_parser(grammar)?;
+    let parser = &parser;
+    let options = WalkOptions {
+        include: glob_list(include, "include")?,
+        exclude: glob_list(exclude, "exclude")?,
+        follow_symlinks,
+        max_depth,
+    };
+    // Parse actions may have side effects or raise: run them attached, in order
+    let attached = has_parse_actions(parser.as_ref());
+    let (workers, wave_size) = match attached {
+        true => (1, 1),
+        false => (threads, threads * SCAN_WAVE_PER_WORKER),
+    };
+    let started = metrics_start();
+    let mut read = ReadCount::default();
+    let mut run = || -> Result<Vec<(std::path::PathBuf, LineHits)>, FileError> {
+        let files = walk_files(&root, &options)?;
+        let mut hits = Vec::new();
+        let mut remaining = max_results;
+        scan_file_waves(
+            &files,
+            parser,
+            workers,
+            wave_size,
+            max_results,
+            &mut read,
+            |wave, per_file| {
+                for (path, found) in wave.iter().zip(per_file) {
+                    let Some(mut found) = found? else {
+                        continue;
+                    };
+                    if let Some(left) = remaining.as_mut() {
+                        found.truncate(*left);
+                        *left -= found.len();
+                    }
+                    if !found.is_empty() {
+                        hits.push((path.clone(), found));
+                    }
+                }
+                Ok(remaining)
+            },
+        )?;
+        Ok(hits)
+    };
+    let scanned = with_timeout(timeout_ms, || match attached {
+        true => run(),
+        false => py.detach(run),
+    });
+    let hits = check_action_error().and_then(|()| scanned.map_err(|e| file_error(&e)));
+    let found = hits
+        .as_ref()
+        .map_or(0, |hits| hits.iter().map(|(_, found)| found.len()).sum());
+    let out = hits.and_then(|hits| {
+        let out = PyDict::new(py);
+        for (path, found) in &hits {
+            let items = found
+                .iter()
+                .map(|(line, results)| Ok((*line, results_to_py_results(py, results)?)))
+                .collect::<PyResult<Vec<_>>>()?;
+            out.set_item(path.to_string_lossy(), PyList::new(py, items)?)?;
+        }
+        Ok(out)
+    });
+    metrics_finish(
+        started,
+        || metrics_label(parser.as_ref()),
+        Operation::File,
+        out.is_ok(),
+        read.bytes,
+        found,
+    );
+    out
+}
+
+/// What match_file_lines found in one file.
+type FileLines = Result<Option<LineHits>, FileError>;
+
+/// Match `parser` against the lines of `files`, a wave of `wave_size` files at a
+/// time split across `workers` threads, adding what was read to `read`. Each wave's
+/// results go to `per_wave` in file order, which returns how many more matching
+/// lines are wanted: `limit` to start with, None for no limit, and Some(0) stops.
+/// An exception from a signal handler (see SignalPoll) also stops, and is left for
+/// check_action_error.
+fn scan_file_waves<E>(
+    files: &[std::path::PathBuf],
+    parser: &Arc<dyn ParserElement>,
+    workers: usize,
+    wave_size: usize,
+    limit: Option<usize>,
+    read: &mut ReadCount,
+    mut per_wave: impl FnMut(&[std::path::PathBuf], Vec<FileLines>) -> Result<Option<usize>, E>,
+) -> Result<(), E> {
+    let mut remaining = limit;
+    let mut signals = SignalPoll::new();
+    for wave in files.chunks(wave_size) {
+        if remaining == Some(0) {
+            break;
+        }
+        let scan = |path: &std::path::PathBuf| {
+            let mut count = ReadCount::default();
+            let found = match_file_lines(path, parser, remaining, &mut count);
+            (found, count)
+        };
+        let stop = &AtomicBool::new(false);
+        let per_file = match workers.min(wave.len()) {
+            1 => signals.map(wave, scan),
+            n => std::thread::scope(|scope| {
+                // Worker w takes files w, w + n, ...
+                let handles: Vec<_> = (0..n)
+                    .map(|w| {
+                        let scan = &scan;
+                        spawn_worker(scope, move || {
+                            let mine = wave.iter().skip(w).step_by(n);
+                            mine.map_while(|path| {
+                                (!stop.load(Ordering::Relaxed)).then(|| scan(path))
+                            })
+                            .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect();
+                let mut parts: Vec<_> = signals
+                    .join(handles, stop)?
+                    .into_iter()
+                    .map(Vec::into_iter)
+                    .collect();
+                Ok((0..wave.len())
+                    .filter_map(|i| parts[i % n].next())
+                    .collect::<Vec<_>>())
+            }),
+        };
+        let per_file = match per_file {
+            Ok(per_file) => per_file,
+            Err(err) => {
+                stash_action_error(err);
+                break;
+            }
+        };
+        let per_file = per_file
+            .into_iter()
+            .map(|(found, count)| {
+                read.bytes += count.bytes;
+                read.lines += count.lines;
+                found
+            })
+            .collect();
+        remaining = per_wave(wave, per_file)?;
+    }
+    Ok(())
+}
+
+/// Matching lines of a file, read one batch at a time.
+struct LineMatches {
+    reader: std::io::BufReader<std::fs::File>,
+    line: String,
+    lineno: usize,
+}
+
+impl LineMatches {
+    /// Read lines until `batch_size` of them match or the file ends, counting the
+    /// bytes read into `bytes`. The bool is whether the file ended.
+    fn next_batch(
+        &mut self,
+        path: &std::path::Path,
+        parser: &Arc<dyn ParserElement>,
+        batch_size: usize,
+        bytes: &mut usize,
+    ) -> Result<(Vec<(usize, ParseResults<'static>)>, bool), FileError> {
+        use std::io::BufRead;
+        let mut batch = Vec::new();
+        while batch.len() < batch_size {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return Ok((batch, true)),
+                Ok(n) => *bytes += n,
+                Err(e) => {
+                    let mut err = FileError::new(path, e);
+                    err.line = Some(self.lineno + 1);
+                    return Err(err);
+                }
+            }
+            self.lineno += 1;
+            if let Some(results) = first_match_in_line(parser, &self.line) {
+                batch.push((self.lineno, results.into_owned()));
+            }
+        }
+        Ok((batch, false))
+    }
+}
+
+/// Body of process_file_streaming, counting the bytes read into `bytes`.
+fn stream_file_matches(
+    py: Python<'_>,
+    path: &std::path::Path,
+    parser: &Arc<dyn ParserElement>,
+    callback: &Bound<'_, PyAny>,
+    batch_size: usize,
+    bytes: &mut usize,
+) -> PyResult<usize> {
+    let input = std::fs::File::open(path).map_err(|e| file_error(&FileError::new(path, e)))?;
+    let mut lines = LineMatches {
+        reader: std::io::BufReader::new(input),
+        line: String::new(),
+        lineno: 0,
+    };
+    // Parse actions may have side effects or raise: run them attached
+    let attached = has_parse_actions(parser.as_ref());
+    let mut delivered = 0;
+    loop {
+        let read = match attached {
+            true => lines.next_batch(path, parser, batch_size, bytes),
+            false => py.detach(|| lines.next_batch(path, parser, batch_size, bytes)),
+        };
+        check_action_error()?;
+        let (batch, at_eof) = read.map_err(|e| file_error(&e))?;
+        py.check_signals()?;
+        if let Some(deadline) = DEADLINE.get().filter(|d| Instant::now() >= d.at) {
+            return Err(deadline.error(py, None));
+        }
+        if !batch.is_empty() {
+            delivered += batch.len();
+            let items = batch
+                .iter()
+                .map(|(lineno, results)| Ok((*lineno, results_to_py_results(py, results)?)))
+                .collect::<PyResult<Vec<_>>>()?;
+            let keep_going = callback.call1((PyList::new(py, items)?,))?;
+            if keep_going.cast::<PyBool>().is_ok_and(|b| !b.is_true()) {
+                break;
+            }
+        }
+        if at_eof {
+            break;
+        }
+    }
+    Ok(delivered)
+}
+
+#[pymethods]
+impl PyIncrementalScanner {
+    /// Scan with `grammar` like scan_string. Edits re-scan from `context_lines`
+    /// non-blank lines before the edited region; raise it for matches that span
+    /// many lines.
+    #[new]
+    #[pyo3(signature = (grammar, context_lines = 1))]
+    fn new(grammar: &Bound<'_, PyAny>, context_lines: usize) -> PyResult<Self> {
+        Ok(Self {
+            inner: IncrementalScanner::new(extract_parser(grammar)?, context_lines),
+        })
+    }
+
+    /// Replace the text and scan it from scratch.
+    fn set_text(&mut self, py: Python<'_>, text: &str) {
+        let text = text.to_string();
+        py.detach(|| self.inner.set_text(text));
+    }
+
+    /// Replace `old_len` bytes at byte offset `start` with `new_text`, re-scanning
+    /// around the edit. Returns `(removed, added)` lists of `(start, end)` spans:
+    /// removed in offsets before the edit, added in offsets after it. Spans that only
+    /// moved with the surrounding text are not reported.
+    fn apply_edit(
+        &mut self,
+        py: Python<'_>,
+        start: usize,
+        old_len: usize,
+        new_text: &str,
+    ) -> PyResult<(Vec<Span>, Vec<Span>)> {
+        let delta = py
+            .detach(|| self.inner.apply_edit(start, old_len, new_text))
+            .map_err(|e| PyValueError::new_err(e.msg))?;
+        Ok((delta.removed, delta.added))
+    }
+
+    /// Current match spans as `(start, end)` byte offsets.
+    fn spans(&self) -> Vec<Span> {
+        self.inner.spans().to_vec()
+    }
+
+    /// The current text.
+    #[getter]
+    fn text(&self) -> &str {
+        self.inner.text()
+    }
+}
+
+/// Matches found in a stream, as `(tokens, start, end)` tuples.
+type PyStreamMatches<'py> = Vec<(Bound<'py, PyParseResults>, usize, usize)>;
+
+fn stream_matches<'py>(py: Python<'py>, found: Vec<StreamMatch>) -> PyResult<PyStreamMatches<'py>> {
+    check_action_error()?;
+    found
+        .iter()
+        .map(|(results, start, end)| Ok((results_to_py_results(py, results)?, *start, *end)))
+        .collect()
+}
+
+#[pymethods]
+impl PyStreamParser {
+    /// Scan text that arrives in chunks with `grammar`, like search_string over all
+    /// of it. A match is only returned once no more input could change it: an
+    /// attempt at a position, matching or not, that looked as far as the end of the
+    /// text fed so far waits for the next chunk, and so does everything after it.
+    /// So `Word(alphas())` ending at the end of a chunk waits, and so does
+    /// `"ab" + Optional("cde")` with only "abcd" fed. Elements that match through a
+    /// window of text (Regex, QuotedString, ...) can't tell how far they looked and
+    /// wait for close(). Once more than `max_buffer` bytes are held from a position,
+    /// its attempt is decided on the text so far instead, bounding the buffer on
+    /// input that never settles (default 1 MiB). Parse actions see the buffered text, and offsets
+    /// within it.
+    #[new]
+    #[pyo3(signature = (grammar, max_buffer = 1048576))]
+    fn new(grammar: &Bound<'_, PyAny>, max_buffer: usize) -> PyResult<Self> {
+        if max_buffer == 0 {
+            return Err(PyValueError::new_err("max_buffer must be at least 1"));
+        }
+        Ok(Self {
+            inner: StreamScanner::new(extract_parser(grammar)?, max_buffer),
+            closed: false,
+        })
+    }
+
+    /// Add `chunk` (str, or UTF-8 bytes, bytearray or memoryview, which may end
+    /// partway through a character) and return the matches it settles as
+    /// `(tokens, start, end)`, with byte offsets into the whole stream.
+    fn feed<'py>(
+        &mut self,
+        py: Python<'py>,
+        chunk: &Bound<'py, PyAny>,
+    ) -> PyResult<PyStreamMatches<'py>> {
+        if self.closed {
+            return Err(PyValueError::new_err("StreamParser is closed"));
+        }
+        let found = match chunk.cast::<PyString>() {
+            Ok(text) => {
+                if self.inner.has_partial_char() {
+                    return Err(PyValueError::new_err(
+                        "a str chunk can't follow bytes that end partway through a character",
+                    ));
+                }
+                self.inner.feed(text.to_str()?)
+            }
+            Err(_) => {
+                let bytes = PyBuffer::<u8>::get(chunk)
+                    .and_then(|buf| buf.to_vec(py))
+                    .map_err(|_| {
+                        PyTypeError::new_err("chunk must be a str or a bytes-like object")
+                    })?;
+                self.inner.feed_bytes(&bytes).map_err(|e| {
+                    PyValueError::new_err(format!(
+                        "stream is not valid UTF-8: invalid byte sequence at offset {}",
+                        e.offset
+                    ))
+                })?
+            }
+        };
+        stream_matches(py, found)
+    }
+
+    /// End the stream: return `(matches, leftover)`, the matches still held now
+    /// that no more input comes, and whether text after the last match was left
+    /// unmatched (whitespace aside) or a character was left unfinished.
+    fn close<'py>(&mut self, py: Python<'py>) -> PyResult<(PyStreamMatches<'py>, bool)> {
+        if self.closed {
+            return Err(PyValueError::new_err("StreamParser is closed"));
+        }
+        self.closed = true;
+        let (found, leftover) = self.inner.finish();
+        Ok((stream_matches(py, found)?, leftover))
+    }
+
+    /// Bytes held for matches still to be decided.
+    #[getter]
+    fn buffered(&self) -> usize {
+        self.inner.buffered()
+    }
+
+    /// Stream byte offset the next attempt starts at.
+    #[getter]
+    fn position(&self) -> usize {
+        self.inner.position()
+    }
+
+    /// Whether close() was called.
+    #[getter]
+    fn closed(&self) -> bool {
+        self.closed
+    }
+}
+
+#[pymethods]
+impl PyInferredPattern {
+    /// The inferred grammar element.
+    #[getter]
+    fn element(&self, py: Python<'_>) -> Py<PyAny> {
+        self.element.clone_ref(py)
+    }
+
+    /// The grammar as a Python expression.
+    #[getter]
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Fraction of the samples the grammar matches entirely (always 1.0).
+    #[getter]
+    fn fit_score(&self) -> f64 {
+        self.fit_score
+    }
+
+    /// Fraction of the held-out samples the grammar matches entirely, if given.
+    #[getter]
+    fn generalization(&self) -> Option<f64> {
+        self.generalization
+    }
+
+    fn __repr__(&self) -> String {
+        format!("InferredPattern({})", self.description)
+    }
+}
+
+#[pymethods]
+impl PyNormalized {
+    /// The normalized text.
+    #[getter]
+    fn text(&self) -> &str {
+        &self.inner.text
+    }
+
+    /// Byte offset in the original text of normalized byte offset `loc`. Offsets
+    /// inside a collapsed or composed run map to its start.
+    fn to_original_offset(&self, loc: usize) -> PyResult<usize> {
+        self.inner
+            .to_original_offset(loc)
+            .ok_or_else(|| PyValueError::new_err("loc is past the end of the text"))
+    }
+
+    /// Original `(start, end)` of the normalized span `start..end`, leaving out
+    /// characters removed around it.
+    fn to_original_span(&self, start: usize, end: usize) -> PyResult<Span> {
+        self.inner
+            .to_original_span((start, end))
+            .ok_or_else(|| PyValueError::new_err("span is not within the text"))
+    }
+
+    fn __str__(&self) -> &str {
+        &self.inner.text
+    }
+}
+
+#[pymethods]
+impl PyArrowColumn {
+    /// Number of rows that failed to parse.
+    #[getter]
+    fn null_count(&self) -> usize {
+        self.inner.null_count()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Rows as Python values: None, a str, or a list of str.
+    fn to_pylist<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        let out = PyList::empty(py);
+        for i in 0..self.inner.len() {
+            match self.inner.row(i) {
+                ResultRow::Null => out.append(py.None())?,
+                ResultRow::String(s) => out.append(s)?,
+                ResultRow::List(tokens) => out.append(tokens)?,
+            }
+        }
+        Ok(out)
+    }
+
+    /// Export a copy of the column as `(schema, array)` capsules. The column's own
+    /// type is always used, whatever `requested_schema` asks for.
+    #[pyo3(signature = (requested_schema = None))]
+    fn __arrow_c_array__<'py>(
+        &self,
+        py: Python<'py>,
+        requested_schema: Option<&Bound<'py, PyAny>>,
+    ) -> PyResult<(Bound<'py, PyCapsule>, Bound<'py, PyCapsule>)> {
+        let _ = requested_schema;
+        let (schema, array) = self.inner.export();
+        Ok((
+            PyCapsule::new(py, schema, Some(c"arrow_schema".to_owned()))?,
+            PyCapsule::new(py, array, Some(c"arrow_array".to_owned()))?,
+        ))
+    }
+}
+
+#[pymethods]
+impl PyFastScanner {
+    /// Scanner for `literals`, matched leftmost-longest without overlaps unless
+    /// `overlapping`. `caseless` compares ASCII letters case-insensitively;
+    /// `whole_words` only reports matches with no `[A-Za-z0-9_]` character on either
+    /// side, like Keyword.
+    #[new]
+    #[pyo3(signature = (literals, caseless = false, overlapping = false, whole_words = false))]
+    fn new(
+        literals: Vec<String>,
+        caseless: bool,
+        overlapping: bool,
+        whole_words: bool,
+    ) -> PyResult<Self> {
+        let options = ScannerOptions {
+            caseless,
+            overlapping,
+            whole_words,
+        };
+        let inner =
+            FastScanner::new(&literals, options).map_err(|e| PatternError::new_err(e.msg))?;
+        Ok(Self { inner })
+    }
+
+    /// Number of literals.
+    fn __len__(&self) -> usize {
+        self.inner.literal_count()
+    }
+
+    /// Matches as `(literal_index, start, end)` byte offsets, ordered by position.
+    fn scan(&self, py: Python<'_>, text: &str) -> Vec<LiteralMatch> {
+        py.detach(|| self.inner.scan(text))
+    }
+
+    /// Number of matches scan() would return.
+    fn count(&self, py: Python<'_>, text: &str) -> usize {
+        py.detach(|| self.inner.count(text))
+    }
+
+    /// scan() of each text, across `num_threads` threads (default: one per CPU) with
+    /// the GIL released. Returns a list of match lists. Given one text (str or
+    /// UTF-8 bytes-like, e.g. an mmap) instead of a list, splits it into chunks at
+    /// line ends, scans them in parallel and returns what scan() would: chunks read
+    /// one literal's length into the next, so matches across a boundary are found
+    /// once.
+    #[pyo3(signature = (texts, num_threads = None))]
+    fn parallel_scan<'py>(
+        &self,
+        py: Python<'py>,
+        texts: &Bound<'py, PyAny>,
+        num_threads: Option<usize>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let threads = worker_threads(num_threads)?;
+        let Ok(texts) = texts.cast::<PyList>() else {
+            let text: InputText<'_> = texts.extract()?;
+            let found = py.detach(|| self.scan_in_chunks(&text, threads));
+            return Ok(found.into_pyobject(py)?.into_any());
+        };
+        let text_list = TextList::new(texts, "texts")?;
+        let inputs = text_list.strs();
+        let found =
+            py.detach(|| map_in_threads(&inputs, threads, 2, |text| self.inner.scan(text)))?;
+        Ok(found.into_pyobject(py)?.into_any())
+    }
+}
+
+/// Smallest chunk parallel_scan splits one text into.
+const MIN_SCAN_CHUNK: usize = 64 * 1024;
+
+impl PyFastScanner {
+    /// scan() of `text` as chunks matched across up to `threads` threads.
+    fn scan_in_chunks(&self, text: &str, threads: usize) -> Vec<LiteralMatch> {
+        let scanner = &self.inner;
+        let bounds = scanner.chunk_bounds(text, threads.min(text.len() / MIN_SCAN_CHUNK));
+        if bounds.len() < 2 {
+            return scanner.scan(text);
+        }
+        let chunks = std::thread::scope(|scope| {
+            let handles: Vec<_> = bounds
+                .iter()
+                .map(|&b| spawn_worker(scope, move || scanner.scan_chunk(text, b)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("scan worker panicked"))
+                .collect()
+        });
+        scanner.merge_chunks(text, &bounds, chunks)
+    }
+}
+
+/// Key characters of a KeyValueParser by default: ASCII letters and digits, `_.-`.
+const DEFAULT_KEY_CHARS: &str = "A-Za-z0-9_.\\-";
+
+#[pymethods]
+impl PyKeyValueParser {
+    /// Reader of `key=value` records such as `ts=1 level=error msg="boom"`. Pairs are
+    /// separated by whitespace (`separator=None`) or by a character, with optional
+    /// whitespace around it; `assign` joins a key to its value. Keys are runs of
+    /// `key_chars` (a str of characters or a CharClass; default letters, digits and
+    /// `_.-`). A value runs to the next separator, or is double-quoted with `\"`,
+    /// `\\`, `\n`, `\t` and `\r` escapes. Pairs that can't be read are left out, or
+    /// raise ParseError when `skip_malformed` is False.
+    #[new]
+    #[pyo3(signature = (separator = None, assign = '=', key_chars = None, skip_malformed = true))]
+    fn new(
+        separator: Option<char>,
+        assign: char,
+        key_chars: Option<&Bound<'_, PyAny>>,
+        skip_malformed: bool,
+    ) -> PyResult<Self> {
+        let key_chars = match key_chars {
+            Some(arg) => match word_chars_arg(arg)? {
+                (spec, true) => CharSet::from_class(&spec),
+                (chars, false) => Ok(CharSet::from_chars(&chars)),
+            },
+            None => CharSet::from_class(DEFAULT_KEY_CHARS),
+        }
+        .map_err(|e| PatternError::new_err(e.msg))?;
+        if key_chars.is_empty() {
+            return Err(PatternError::new_err("key_chars must not be empty"));
+        }
+        for (what, c) in [
+            Some(("assign", assign)),
+            separator.map(|c| ("separator", c)),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if c == '"' || c.is_whitespace() || key_chars.contains_char(c) {
+                return Err(PyValueError::new_err(format!(
+                    "{} {:?} can't be a quote, whitespace or a key character",
+                    what, c
+                )));
+            }
+        }
+        if separator == Some(assign) {
+            return Err(PyValueError::new_err("separator and assign must differ"));
+        }
+        Ok(Self {
+            inner: KeyValueFormat {
+                separator: separator.map_or(PairSeparator::Whitespace, PairSeparator::Char),
+                assign,
+                key_chars,
+                skip_malformed,
+            },
+        })
+    }
+
+    /// The pairs of `s` (str or UTF-8 bytes-like) as a dict; a key given twice
+    /// keeps its last value.
+    fn parse_line<'py>(&self, py: Python<'py>, s: InputText<'_>) -> PyResult<Bound<'py, PyDict>> {
+        let s: &str = &s;
+        let pairs = py
+            .detach(|| self.inner.parse_record(s))
+            .map_err(|e| build_parse_error(s, e.offset, &e.msg, false))?;
+        let out = PyDict::new(py);
+        for (key, value) in pairs {
+            out.set_item(key, &*value)?;
+        }
+        Ok(out)
+    }
+
+    /// parse_line() of each line, across `num_threads` threads (default: one per
+    /// CPU) with the GIL released. Returns a list of dicts, or with `as_arrays`
+    /// three parallel lists `(line_index, keys, values)` with one entry per pair.
+    /// With `skip_malformed=False` the first line that fails raises its ParseError,
+    /// with the index in `line_index`.
+    #[pyo3(signature = (lines, as_arrays = false, num_threads = None))]
+    fn parse_batch<'py>(
+        &self,
+        py: Python<'py>,
+        lines: &Bound<'py, PyList>,
+        as_arrays: bool,
+        num_threads: Option<usize>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let threads = worker_threads(num_threads)?;
+        let text_list = TextList::new(lines, "lines")?;
+        let inputs = text_list.strs();
+        let records = py.detach(|| {
+            map_in_threads(&inputs, threads, PARALLEL_MIN_INPUTS, |s| {
+                self.inner.parse_record(s)
+            })
+        })?;
+        // Keys repeat from line to line: one str object per distinct key
+        let mut keys: FxHashMap<&str, Bound<'py, PyString>> = FxHashMap::default();
+        let mut key = |k| {
+            keys.entry(k)
+                .or_insert_with(|| PyString::new(py, k))
+                .clone()
+        };
+        let (out, mut index, mut all_keys, mut values) =
+            (PyList::empty(py), Vec::new(), Vec::new(), Vec::new());
+        for (i, (record, s)) in records.into_iter().zip(&inputs).enumerate() {
+            let pairs = record.map_err(|e| {
+                let err = build_parse_error(s, e.offset, &e.msg, false);
+                match err.value(py).setattr("line_index", i) {
+                    Ok(()) => err,
+                    Err(e) => e,
+                }
+            })?;
+            if as_arrays {
+                for (k, value) in pairs {
+                    index.push(i);
+                    all_keys.push(key(k));
+                    values.push(PyString::new(py, &value));
+                }
+                continue;
+            }
+            let dict = PyDict::new(py);
+            for (k, value) in pairs {
+                dict.set_item(key(k), &*value)?;
+            }
+            out.append(dict)?;
+        }
+        match as_arrays {
+            true => Ok((index, all_keys, values).into_pyobject(py)?.into_any()),
+            false => Ok(out.into_any()),
+        }
+    }
+}
+
+/// Where a ParserPipeline's files come from.
+enum PipelineSource {
+    /// These files, in this order.
+    Files(Vec<std::path::PathBuf>),
+    /// The files walk_files finds under the root.
+    Walk(std::path::PathBuf, WalkOptions),
+}
+
+impl PipelineSource {
+    fn files(&self) -> Result<Vec<std::path::PathBuf>, FileError> {
+        match self {
+            Self::Files(files) => Ok(files.clone()),
+            Self::Walk(root, options) => walk_files(root, options),
+        }
+    }
+}
+
+/// What a ParserPipeline does with its matches.
+enum PipelineSink {
+    Return,
+    Jsonl(std::path::PathBuf),
+    Count,
+}
+
+/// A glob source split into the directory to walk (the components before the
+/// first wildcard, or `.`) and the pattern to match the walked paths against.
+fn glob_source(pattern: &str) -> PyResult<(std::path::PathBuf, Glob)> {
+    let mut root = std::path::PathBuf::new();
+    for part in std::path::Path::new(pattern).components() {
+        if part.as_os_str().to_string_lossy().contains(['*', '?', '[']) {
+            break;
+        }
+        root.push(part);
+    }
+    let pattern = match root.as_os_str().is_empty() {
+        true => {
+            root.push(".");
+            format!(".{}{}", std::path::MAIN_SEPARATOR, pattern)
+        }
+        false => pattern.to_string(),
+    };
+    let glob = Glob::new(&pattern).map_err(|e| PatternError::new_err(e.to_string()))?;
+    Ok((root, glob))
+}
+
+/// `parser` wrapped in each of `transforms` in turn: names as in Transform, or
+/// `("join", sep)`.
+fn with_transforms(
+    mut parser: Arc<dyn ParserElement>,
+    transforms: Option<&Bound<'_, PyList>>,
+) -> PyResult<Arc<dyn ParserElement>> {
+    for item in transforms.iter().flat_map(|list| list.iter()) {
+        let (name, sep) = match item.extract::<String>() {
+            Ok(name) => (name, String::new()),
+            Err(_) => item.extract::<(String, String)>().map_err(|_| {
+                PyValueError::new_err("transforms must be names or (name, sep) tuples")
+            })?,
+        };
+        let transform = TokenTransform::from_name(&name, &sep)
+            .ok_or_else(|| PatternError::new_err(format!("unknown token transform '{}'", name)))?;
+        parser = Arc::new(RustTransform::new(parser, transform));
+    }
+    Ok(parser)
+}
+
+/// A matching line as a JSON Lines record: its `path`, 1-based `line`, `tokens`
+/// and `named` results.
+fn pipeline_record(path: &std::path::Path, line: usize, results: &ParseResults) -> JsonValue {
+    let mut record = serde_json::Map::new();
+    record.insert("path".into(), path.to_string_lossy().into());
+    record.insert("line".into(), line.into());
+    record.extend(json::results_record(results, &object_json));
+    JsonValue::Object(record)
+}
+
+/// Counts for ParserPipeline.run's summary.
+#[derive(Default)]
+struct PipelineTotals {
+    read: ReadCount,
+    files: usize,
+    matches: usize,
+    skipped: usize,
+    errors: usize,
+}
+
+#[pymethods]
+impl PyParserPipeline {
+    /// Match `grammar` (an element, or a str matched literally) against each line of
+    /// a set of files and send the matches to a sink, all in Rust. `source` is a
+    /// list of file paths, a directory (walked as in scan_directory, with
+    /// `include`, `exclude`, `follow_symlinks` and `max_depth`) or a glob such as
+    /// `logs/*.log`, walked from the directory before its first wildcard; as in
+    /// fnmatch, `*` also matches across directories. `transforms` are token
+    /// transforms applied to each match in order: "to_int", "to_float",
+    /// "strip_quotes", "strip", "upcase", "downcase", "join" or `("join", sep)`.
+    /// `sink` is "return" (the matches come back from run()), "jsonl" (written to
+    /// `jsonl_path` as they are found) or "count" (only counted). Files are matched
+    /// across `num_threads` threads (default: one per CPU) with the GIL released, a
+    /// few files per thread at a time, so a "jsonl" or "count" run holds only the
+    /// matches of the files in flight.
+    #[new]
+    #[pyo3(signature = (
+        grammar,
+        source,
+        include = None,
+        exclude = None,
+        transforms = None,
+        sink = "return",
+        jsonl_path = None,
+        follow_symlinks = false,
+        max_depth = None,
+        num_threads = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        grammar: &Bound<'_, PyAny>,
+        source: &Bound<'_, PyAny>,
+        include: Option<&Bound<'_, PyAny>>,
+        exclude: Option<&Bound<'_, PyAny>>,
+        transforms: Option<&Bound<'_, PyList>>,
+        sink: &str,
+        jsonl_path: Option<std::path::PathBuf>,
+        follow_symlinks: bool,
+        max_depth: Option<usize>,
+        num_threads: Option<usize>,
+    ) -> PyResult<Self> {
+        let threads = worker_threads(num_threads)?;
+        let parser = with_transforms(extract_parser(grammar)?, transforms)?;
+        let mut options = WalkOptions {
+            include: glob_list(include, "include")?,
+            exclude: glob_list(exclude, "exclude")?,
+            follow_symlinks,
+            max_depth,
+        };
+        let source = match source.cast::<PyList>() {
+            Ok(list) => {
+                if !options.include.is_empty() || !options.exclude.is_empty() {
+                    return Err(PyValueError::new_err(
+                        "include and exclude apply to a directory or glob source",
+                    ));
+                }
+                let files = list.extract().map_err(|_| {
+                    PyValueError::new_err("source must be a path or a list of paths")
+                })?;
+                PipelineSource::Files(files)
+            }
+            Err(_) => {
+                let path: std::path::PathBuf = source.extract().map_err(|_| {
+                    PyValueError::new_err("source must be a path or a list of paths")
+                })?;
+                let text = path.to_string_lossy();
+                if path.exists() || !text.contains(['*', '?', '[']) {
+                    PipelineSource::Walk(path, options)
+                } else {
+                    if !options.include.is_empty() {
+                        return Err(PyValueError::new_err(
+                            "include can't be combined with a glob source",
+                        ));
+                    }
+                    let (root, glob) = glob_source(&text)?;
+                    options.include.push(glob);
+                    PipelineSource::Walk(root, options)
+                }
+            }
+        };
+        let sink = match (sink, jsonl_path) {
+            ("return", None) => PipelineSink::Return,
+            ("count", None) => PipelineSink::Count,
+            ("jsonl", Some(path)) => PipelineSink::Jsonl(path),
+            ("jsonl", None) => return Err(PyValueError::new_err("sink 'jsonl' needs jsonl_path")),
+            ("return" | "count", Some(_)) => {
+                return Err(PyValueError::new_err("jsonl_path is only for sink 'jsonl'"))
+            }
+            (other, _) => {
+                return Err(PyValueError::new_err(format!(
+                    "sink must be 'return', 'jsonl' or 'count', not '{}'",
+                    other
+                )))
+            }
+        };
+        Ok(Self {
+            parser,
+            source,
+            sink,
+            threads,
+        })
+    }
+    /// The files run() would read, in order.
+    fn files(&self, py: Python<'_>) -> PyResult<Vec<String>> {
+        let files = py
+            .detach(|| self.source.files())
+            .map_err(|e| file_error(&e))?;
+        Ok(files
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect())
+    }
+    /// The tokens of the first match in `line`, after the transforms, or None; what
+    /// run() finds in each line of a file.
+    fn match_line<'py>(
+        &self,
+        py: Python<'py>,
+        line: &str,
+    ) -> PyResult<Option<Bound<'py, PyParseResults>>> {
+        let found = first_match_in_line(&self.parser, line);
+        check_action_error()?;
+        found
+            .map(|results| results_to_py_results(py, &results))
+            .transpose()
+    }
+    /// Read every file and send its matching lines to the sink. Returns a summary:
+    /// `files` (text files read), `lines` (lines read), `matches`, `skipped` (files
+    /// with a NUL byte or that aren't UTF-8) and `errors` (files that couldn't be
+    /// read). With sink "return" it also has `results`: `(path, line, tokens)` for
+    /// each matching line, with 1-based line numbers.
+    fn run<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let parser = &self.parser;
+        // Parse actions may have side effects or raise: run them attached, in order
+        let attached = has_parse_actions(parser.as_ref());
+        let (workers, wave_size) = match attached {
+            true => (1, 1),
+            false => (self.threads, self.threads * SCAN_WAVE_PER_WORKER),
+        };
+        let started = metrics_start();
+        let mut totals = PipelineTotals::default();
+        let mut kept = Vec::new();
+        let mut run = || -> Result<(), FileError> {
+            let files = self.source.files()?;
+            let mut jsonl = match &self.sink {
+                PipelineSink::Jsonl(path) => Some(json::JsonlWriter::create(path)?),
+                PipelineSink::Return | PipelineSink::Count => None,
+            };
+            scan_file_waves(
+                &files,
+                parser,
+                workers,
+                wave_size,
+                None,
+                &mut totals.read,
+                |wave, per_file| {
+                    let mut records = Vec::new();
+                    for (path, found) in wave.iter().zip(per_file) {
+                        let found = match found {
+                            Ok(Some(found)) => found,
+                            Ok(None) => {
+                                totals.skipped += 1;
+                                continue;
+                            }
+                            Err(_) => {
+                                totals.errors += 1;
+                                continue;
+                            }
+                        };
+                        totals.files += 1;
+                        totals.matches += found.len();
+                        match &self.sink {
+                            PipelineSink::Return => kept.push((path.clone(), found)),
+                            PipelineSink::Jsonl(_) => records.extend(
+                                found
+                                    .iter()
+                                    .map(|(line, results)| pipeline_record(path, *line, results)),
+                            ),
+                            PipelineSink::Count => {}
+                        }
+                    }
+                    if let Some(out) = jsonl.as_mut() {
+                        out.write(&records)?;
+                    }
+                    Ok(None)
+                },
+            )?;
+            if let Some(out) = jsonl {
+                out.finish()?;
+            }
+            Ok(())
+        };
+        let ran = match attached {
+            true => run(),
+            false => py.detach(run),
+        };
+        let out = check_action_error()
+            .and_then(|()| ran.map_err(|e| file_error(&e)))
+            .and_then(|()| {
+                let summary = PyDict::new(py);
+                summary.set_item("files", totals.files)?;
+                summary.set_item("lines", totals.read.lines)?;
+                summary.set_item("matches", totals.matches)?;
+                summary.set_item("skipped", totals.skipped)?;
+                summary.set_item("errors", totals.errors)?;
+                if let PipelineSink::Return = self.sink {
+                    let results = PyList::empty(py);
+                    for (path, found) in &kept {
+                        let path = path.to_string_lossy();
+                        for (line, tokens) in found {
+                            results.append((&path, *line, results_to_py_results(py, tokens)?))?;
+                        }
+                    }
+                    summary.set_item("results", results)?;
+                }
+                Ok(summary)
+            });
+        metrics_finish(
+            started,
+            || metrics_label(parser.as_ref()),
+            Operation::File,
+            out.is_ok(),
+            totals.read.bytes,
+            totals.matches,
+        );
+        out
+    }
+}
+
+#[pymethods]
+impl PyCompactResults {
+    /// Number of inputs.
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Tokens of input `i` as a list of str, built on access.
+    fn __getitem__(&self, i: isize) -> PyResult<Vec<&str>> {
+        let len = self.inner.len() as isize;
+        let index = if i < 0 { i + len } else { i };
+        if !(0..len).contains(&index) {
+            return Err(PyIndexError::new_err("CompactResults index out of range"));
+        }
+        Ok(self.inner.row(index as usize))
+    }
+
+    /// Total number of tokens across all rows.
+    #[getter]
+    fn token_count(&self) -> usize {
+        self.inner.token_count()
+    }
+
+    /// Every input's tokens, as a list of lists of str.
+    fn to_list(&self) -> Vec<Vec<&str>> {
+        (0..self.inner.len()).map(|i| self.inner.row(i)).collect()
+    }
+
+    /// The results as a dict of `inputs` (the input count), `data` (all token text,
+    /// UTF-8) and `input_index`, `token_start`, `token_end` (bytes of native-endian
+    /// int64, one per token, e.g. for `numpy.frombuffer(b, dtype=numpy.int64)` or
+    /// `memoryview(b).cast("q")`). Token `k` is `data[token_start[k]:token_end[k]]`.
+    fn buffers<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let int64s = |values: &[i64]| {
+            let raw: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+            PyBytes::new(py, &raw)
+        };
+        let out = PyDict::new(py);
+        out.set_item("inputs", self.inner.len())?;
+        out.set_item("data", PyBytes::new(py, self.inner.data()))?;
+        out.set_item("input_index", int64s(self.inner.input_index()))?;
+        out.set_item("token_start", int64s(self.inner.token_start()))?;
+        out.set_item("token_end", int64s(self.inner.token_end()))?;
+        Ok(out)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "CompactResults({} inputs, {} tokens)",
+            self.inner.len(),
+            self.inner.token_count()
+        )
+    }
+}
+
+#[pymethods]
+impl PyRegexMatchResult {
+    /// Text of group `n` (an index or a name; 0 is the whole match), or None if the
+    /// group didn't participate. Several groups give a tuple, like re.Match.group().
+    #[pyo3(signature = (*groups))]
+    fn group<'py>(
+        &self,
+        py: Python<'py>,
+        groups: &Bound<'py, PyTuple>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        match groups.len() {
+            0 => Ok(PyString::new(py, &self.inner.text).into_any()),
+            1 => self.group_text(py, &groups.get_item(0)?),
+            _ => {
+                let texts = groups
+                    .iter()
+                    .map(|g| self.group_text(py, &g))
+                    .collect::<PyResult<Vec<_>>>()?;
+                Ok(PyTuple::new(py, texts)?.into_any())
+            }
+        }
+    }
+
+    fn __getitem__<'py>(
+        &self,
+        py: Python<'py>,
+        n: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.group_text(py, n)
+    }
+
+    /// Text of every group after the whole match, `default` for those that didn't
+    /// participate.
+    #[pyo3(signature = (default = None))]
+    fn groups<'py>(
+        &self,
+        py: Python<'py>,
+        default: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyTuple>> {
+        let default = default.unwrap_or_else(|| py.None().into_bound(py));
+        let texts = (1..self.inner.spans.len()).map(|i| match self.inner.group(i) {
+            Some(text) => PyString::new(py, text).into_any(),
+            None => default.clone(),
+        });
+        PyTuple::new(py, texts)
+    }
+
+    /// `{name: text}` for the named groups.
+    #[pyo3(signature = (default = None))]
+    fn groupdict<'py>(
+        &self,
+        py: Python<'py>,
+        default: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let out = PyDict::new(py);
+        for (i, name) in self.inner.names.iter().enumerate() {
+            if let Some(name) = name {
+                match self.inner.group(i) {
+                    Some(text) => out.set_item(&**name, text)?,
+                    None => out.set_item(&**name, &default)?,
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Byte offset in the input where group `n` starts, or -1 if it didn't participate.
+    #[pyo3(signature = (n = None))]
+    fn start(&self, n: Option<&Bound<'_, PyAny>>) -> PyResult<isize> {
+        Ok(self.span(n)?.0)
+    }
+
+    /// Byte offset in the input where group `n` ends, or -1 if it didn't participate.
+    #[pyo3(signature = (n = None))]
+    fn end(&self, n: Option<&Bound<'_, PyAny>>) -> PyResult<isize> {
+        Ok(self.span(n)?.1)
+    }
+
+    /// `(start(n), end(n))`.
+    #[pyo3(signature = (n = None))]
+    fn span(&self, n: Option<&Bound<'_, PyAny>>) -> PyResult<(isize, isize)> {
+        let index = match n {
+            Some(n) => self.group_index(n)?,
+            None => 0,
+        };
+        Ok(self
+            .inner
+            .span(index)
+            .map_or((-1, -1), |(start, end)| (start as isize, end as isize)))
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let (start, end) = self.span(None)?;
+        let text = PyString::new(py, &self.inner.text).repr()?;
+        Ok(format!(
+            "<RegexMatchResult span=({}, {}), match={}>",
+            start, end, text
+        ))
+    }
+}
+
+impl PyRegexMatchResult {
+    /// Index of group `n`, given as an index or a name.
+    fn group_index(&self, n: &Bound<'_, PyAny>) -> PyResult<usize> {
+        let index = match n.cast::<PyString>() {
+            Ok(name) => self.inner.index_of(name.to_str()?),
+            Err(_) => Some(n.extract::<usize>()?),
+        };
+        index
+            .filter(|&i| i < self.inner.spans.len())
+            .ok_or_else(|| PyIndexError::new_err("no such group"))
+    }
+
+    fn group_text<'py>(
+        &self,
+        py: Python<'py>,
+        n: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let text = self.inner.group(self.group_index(n)?);
+        Ok(match text {
+            Some(text) => PyString::new(py, text).into_any(),
+            None => py.None().into_bound(py),
+        })
+    }
+}
+
+#[pymethods]
+impl PyCharClass {
+    /// Characters matched by `spec`, a regex character class without the brackets:
+    /// single characters, ranges (`a-zA-Z0-9_`), escapes (`\\d`, `\\w`) and Unicode
+    /// categories or scripts (`\\p{L}`, `\\p{Nd}`, `\\p{Greek}`). A leading `^` or
+    /// `negate` matches every other character instead.
+    #[new]
+    #[pyo3(signature = (spec, negate = false))]
+    fn new(spec: &str, negate: bool) -> PyResult<Self> {
+        let spec = match negate {
+            true => format!("^{}", spec),
+            false => spec.to_string(),
+        };
+        let set = CharSet::from_class(&spec).map_err(|e| PatternError::new_err(e.msg))?;
+        Ok(Self { spec, set })
+    }
+
+    /// The spec as given, with `^` prepended when negated.
+    #[getter]
+    fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    fn __contains__(&self, c: &str) -> PyResult<bool> {
+        let mut chars = c.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(self.set.contains_char(c)),
+            _ => Err(PyValueError::new_err(
+                "CharClass membership needs a single character",
+            )),
+        }
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(format!(
+            "CharClass({})",
+            PyString::new(py, &self.spec).repr()?
+        ))
+    }
+}
+
+/// A Word character argument: a plain string of characters, or a CharClass, whose
+/// spec is returned with `true`.
+fn word_chars_arg(arg: &Bound<'_, PyAny>) -> PyResult<(String, bool)> {
+    if let Ok(class) = arg.cast::<PyCharClass>() {
+        return Ok((class.get().spec.clone(), true));
+    }
+    match arg.extract::<String>() {
+        Ok(chars) => Ok((chars, false)),
+        Err(_) => Err(PyTypeError::new_err(
+            "Word characters must be a str or CharClass",
+        )),
+    }
+}
+
+#[pymethods]
+impl PyCompiledGrammar {
+    /// The grammar this was compiled from.
+    #[getter]
+    fn grammar(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        element_to_py(py, self.inner.source())
+    }
+
+    /// Number of instructions in the program.
+    fn __len__(&self) -> usize {
+        self.inner.instruction_count()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "CompiledGrammar({}, {} instructions)",
+            self.inner.source().name(),
+            self.inner.instruction_count()
+        )
+    }
+
+    /// Like ParserElement.parse_string; a mismatch raises the same ParseError.
+    #[pyo3(signature = (s, parse_all = false, timeout_ms = None))]
+    fn parse_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        parse_all: bool,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let s: &str = &s;
+        let source = self.inner.source();
+        metered_parse(source.as_ref(), s, timeout_ms, || {
+            let mut ctx = ParseContext::new(s).with_checkpoint(checkpoint(py));
+            let parsed = self
+                .inner
+                .parse_at(&mut ctx, skip_ws(s, 0))
+                .and_then(|(end, results)| {
+                    if parse_all {
+                        expect_end(s, end, &[])?;
+                    }
+                    Ok(results)
+                });
+            check_action_error()?;
+            match parsed {
+                Ok(results) => results_to_py_object(py, &results),
+                Err(e) => Err(parse_error(s, &e)),
+            }
+        })
+    }
+
+    /// Like ParserElement.parse_batch, matching with the GIL released.
+    #[pyo3(signature = (inputs, to_json = false, pretty = false, jsonl_path = None, timeout_ms = None))]
+    fn parse_batch<'py>(
+        &self,
+        py: Python<'py>,
+        inputs: &Bound<'py, PyList>,
+        to_json: bool,
+        pretty: bool,
+        jsonl_path: Option<std::path::PathBuf>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        with_timeout(timeout_ms, || {
+            if to_json || jsonl_path.is_some() {
+                return batch_json(
+                    py,
+                    self.inner.source(),
+                    inputs,
+                    pretty,
+                    jsonl_path.as_deref(),
+                );
+            }
+            metered_batch(self.inner.source().as_ref(), inputs, || {
+                let batch = BatchInputs::new(inputs)?;
+                let parsed = batch.parse_detached(|s| {
+                    let mut ctx = ParseContext::new(s);
+                    self.inner
+                        .match_at(&mut ctx, 0)
+                        .map(|(_end, results)| results)
+                })?;
+                let outputs: Vec<Bound<'py, PyAny>> = parsed
+                    .iter()
+                    .map(|results| match results {
+                        Some(results) => unsafe {
+                            Bound::from_owned_ptr(py, results_to_py_list(py, results))
+                        },
+                        None => PyList::empty(py).into_any(),
+                    })
+                    .collect();
+                batch.per_input(&outputs)
+            })
+            .map(Bound::into_any)
+        })
+    }
+
+    /// Like ParserElement.search_string.
+    #[pyo3(signature = (s, max_matches = None, timeout_ms = None))]
+    fn search_string<'py>(
+        &self,
+        py: Python<'py>,
+        s: InputText<'_>,
+        max_matches: Option<usize>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let s: &str = &s;
+        metered_scan(self.inner.source().as_ref(), s, timeout_ms, || {
+            let mut ctx = ParseContext::new(s).with_checkpoint(checkpoint(py));
+            let found = self.inner.search(&mut ctx, max_matches);
+            check_action_error()?;
+            let out = PyList::empty(py);
+            for results in &found {
+                out.append(results_to_py_results(py, results)?)?;
+            }
+            Ok(out)
+        })
+    }
+}
+
+#[pymethods]
+impl PyScanIterator {
+    /// Byte offset the next match is searched from.
+    #[getter]
+    fn loc(&self) -> usize {
+        self.loc
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(
+        &mut self,
+        py: Python<'py>,
+    ) -> PyResult<Option<(Bound<'py, PyParseResults>, usize, usize)>> {
+        let text = self.text.bind(py).to_str()?;
+        let mut ctx = ParseContext::new(&text[..self.end]).with_checkpoint(checkpoint(py));
+        let found = scan_next(&self.parser, &mut ctx, self.loc, self.overlap);
+        check_action_error()?;
+        let Some(((results, start, end), next)) = found else {
+            self.loc = self.end;
+            return Ok(None);
+        };
+        self.loc = next;
+        Ok(Some((results_to_py_results(py, &results)?, start, end)))
+    }
+}
+
+/// parse_string's parse of one line, as parse_lines runs it.
+fn parse_line<'a>(
+    parser: &dyn ParserElement,
+    mut ctx: ParseContext<'a>,
+) -> Result<ParseResults<'a>, ParseException> {
+    let start = skip_ws(ctx.input(), 0);
+    parser
+        .parse_impl(&mut ctx, start)
+        .map(|(_end, results)| results)
+        .map_err(|e| ctx.with_furthest_failure(e))
+}
+
+impl PyLineParseIterator {
+    /// Pull and parse the next chunk of lines into `pending`. An error pulling or
+    /// decoding a line is queued after the lines before it, and ends the stream.
+    fn fill(&mut self, py: Python<'_>) -> PyResult<()> {
+        let mut lines = self.lines.bind(py).clone();
+        // Owned references keep every chunk string alive while detached
+        let mut chunk: Vec<Bound<'_, PyAny>> = Vec::with_capacity(self.chunk_size);
+        let mut stop = None;
+        for line in lines.by_ref().take(self.chunk_size) {
+            match line {
+                Ok(line) => chunk.push(line),
+                Err(e) => {
+                    stop = Some(e);
+                    break;
+                }
+            }
+        }
+        let mut chunk_texts = Vec::with_capacity(chunk.len());
+        for (i, line) in chunk.iter().enumerate() {
+            match stream_item_text(line, self.next_index + i) {
+                Ok(text) => chunk_texts.push(text),
+                Err(e) => {
+                    stop = Some(e);
+                    break;
+                }
+            }
+        }
+        let texts: Vec<&str> = chunk_texts.iter().map(|t| &**t).collect();
+        if chunk.is_empty() {
+            self.done = true;
+        }
+        let first = self.next_index;
+        self.next_index += chunk.len();
+        let parser = self.parser.clone();
+        // Parse actions may have side effects or raise: run them with the GIL, in order
+        if has_parse_actions(parser.as_ref()) {
+            for (i, s) in texts.iter().enumerate() {
+                let ctx = ParseContext::new(s)
+                    .with_checkpoint(checkpoint(py))
+                    .tracking_furthest_failure();
+                if !self.push(py, first + i, s, parse_line(parser.as_ref(), ctx))? {
+                    return Ok(());
+                }
+            }
+        } else {
+            let skip_failures = self.skip_failures;
+            let parsed = py.detach(|| {
+                let mut parsed = Vec::with_capacity(texts.len());
+                for s in &texts {
+                    let ctx = ParseContext::new(s).tracking_furthest_failure();
+                    let result = parse_line(parser.as_ref(), ctx);
+                    let failed = result.is_err();
+                    parsed.push(result);
+                    if failed && !skip_failures {
+                        break;
+                    }
+                }
+                parsed
+            });
+            for (i, (s, result)) in texts.iter().zip(parsed).enumerate() {
+                if !self.push(py, first + i, s, result)? {
+                    return Ok(());
+                }
+            }
+        }
+        if let Some(e) = stop {
+            self.pending.push_back(Err(e));
+            self.done = true;
+        }
+        Ok(())
+    }
+
+    /// Queue the outcome of line `index`; false once it ends the stream.
+    fn push(
+        &mut self,
+        py: Python<'_>,
+        index: usize,
+        s: &str,
+        result: Result<ParseResults<'_>, ParseException>,
+    ) -> PyResult<bool> {
+        let outcome = match result {
+            Ok(results) => check_action_error()
+                .and_then(|()| results_to_py_object(py, &results))
+                .map(|tokens| (index, tokens.unbind())),
+            Err(e) => match check_action_error() {
+                Err(err) => Err(err),
+                Ok(()) if self.skip_failures => return Ok(true),
+                Ok(()) => {
+                    let err = build_parse_error(s, e.loc, &e.msg, e.fatal);
+                    err.value(py).setattr("line_index", index)?;
+                    Err(err)
+                }
+            },
+        };
+        let failed = outcome.is_err();
+        self.pending.push_back(outcome);
+        if failed {
+            self.done = true;
+        }
+        Ok(!failed)
+    }
+}
+
+#[pymethods]
+impl PyLineParseIterator {
+    /// Lines pulled from the iterable so far, parsed or buffered.
+    #[getter]
+    fn lines_read(&self) -> usize {
+        self.next_index
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<(usize, Py<PyAny>)>> {
+        loop {
+            if let Some(outcome) = self.pending.pop_front() {
+                return outcome.map(Some);
+            }
+            if self.done {
+                return Ok(None);
+            }
+            self.fill(py)?;
+        }
+    }
+}
+
+/// Every public name of the module as a list of dicts: name, kind ("function",
+/// "class", "exception", "alias", "constant" or "module"), signature, base,
+/// summary and members, the methods and properties a class defines as dicts of
+/// name, kind ("method", "staticmethod" or "property"), signature and summary.
+#[pyfunction]
+fn api_summary(py: Python<'_>) -> PyResult<Bound<'_, PyList>> {
+    let list = PyList::empty(py);
+    for entry in api::API {
+        let members = PyList::empty(py);
+        for member in entry.members {
+            let dict = PyDict::new(py);
+            dict.set_item("name", member.name)?;
+            dict.set_item("kind", member.kind.as_str())?;
+            dict.set_item("signature", member.signature)?;
+            dict.set_item("summary", member.summary)?;
+            members.append(dict)?;
+        }
+        let dict = PyDict::new(py);
+        dict.set_item("name", entry.name)?;
+        dict.set_item("kind", entry.kind.as_str())?;
+        dict.set_item("signature", entry.signature)?;
+        dict.set_item("base", entry.base)?;
+        dict.set_item("summary", entry.summary)?;
+        dict.set_item("members", members)?;
+        list.append(dict)?;
+    }
+    Ok(list)
+}
+
+/// pyparsing_common.comma_separated_list: fields separated by commas, each a quoted
+/// string (quotes kept) or any other text up to the next comma or line end, with
+/// surrounding whitespace dropped. An empty field, including one after a trailing
+/// comma, is an empty string.
+fn comma_separated_list() -> PyResult<Arc<dyn ParserElement>> {
+    let quoted = |q| builder::Expr::new(RustQuotedString::new(q, Some("\\"), false, false));
+    let bare = builder::regex(r"(?:[^,\s](?:[^,\n]*[^,\s])?)?")
+        .map_err(|e| PatternError::new_err(e.to_string()))?;
+    let field = quoted("\"") | quoted("'") | bare;
+    let rest = (builder::lit(",").suppress() + field.clone()).zero_or_more();
+    Ok((field + rest).into_element())
+}
+
+/// The summary the API table gives submodule `name`.
+fn module_summary(name: &str) -> &'static str {
+    api::entry(name).map_or("", |e| e.summary)
+}
+
+/// A fast pyparsing-compatible parser library.
+#[pymodule]
+fn pyparsing_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("PyparsingError", m.py().get_type::<PyparsingError>())?;
+    m.add("ParseError", m.py().get_type::<ParseError>())?;
+    // pyparsing's name for it
+    m.add("ParseException", m.py().get_type::<ParseError>())?;
+    m.add("ParseFatalError", m.py().get_type::<ParseFatalError>())?;
+    m.add("ParseFatalException", m.py().get_type::<ParseFatalError>())?;
+    add_parse_error_methods(m)?;
+    m.add("PatternError", m.py().get_type::<PatternError>())?;
+    m.add(
+        "FileProcessingError",
+        m.py().get_type::<FileProcessingError>(),
+    )?;
+
+    m.add_class::<PyParserElement>()?;
+    m.add_class::<PyLiteral>()?;
+    m.add_class::<PyKeyword>()?;
+    m.add_class::<PyWord>()?;
+    m.add_class::<PyRegex>()?;
+    m.add_class::<PyAnd>()?;
+    m.add_class::<PyMatchFirst>()?;
+    m.add_class::<PyZeroOrMore>()?;
+    m.add_class::<PyOneOrMore>()?;
+    m.add_class::<PyOptional>()?;
+    m.add_class::<PyGroup>()?;
+    m.add_class::<PyDictElement>()?;
+    m.add_class::<PyParseResults>()?;
+    m.add_class::<PySuppress>()?;
+    m.add_class::<PyForward>()?;
+    m.add_class::<PyCombine>()?;
+    m.add_class::<PyOriginalTextFor>()?;
+    m.add_class::<PyLocated>()?;
+    m.add_class::<PyHighlightClass>()?;
+    m.add_class::<PyNamed>()?;
+    m.add_class::<PyParseAction>()?;
+    m.add_class::<PyCondition>()?;
+    m.add_class::<PyTransform>()?;
+    m.add_class::<PyWhitespaceScope>()?;
+    m.add_class::<PyDebugTrace>()?;
+    m.add_class::<PyExactly>()?;
+    m.add_class::<PyRepeat>()?;
+    m.add_class::<PyAtLeastN>()?;
+    m.add_class::<PyAtMostN>()?;
+    m.add_class::<PyCaselessLiteral>()?;
+    m.add_class::<PyCaselessKeyword>()?;
+    m.add_class::<PyCloseMatch>()?;
+    m.add_class::<PyHtmlTag>()?;
+    m.add_class::<PyHtmlEndTag>()?;
+    m.add_class::<PyAttributeFilter>()?;
+    m.add_class::<PyChar>()?;
+    m.add_class::<PyStringStart>()?;
+    m.add_class::<PyStringEnd>()?;
+    m.add_class::<PyLineStart>()?;
+    m.add_class::<PyLineEnd>()?;
+    m.add_class::<PyRestOfLine>()?;
+    m.add_class::<PyGoToColumn>()?;
+    m.add_class::<PyAsLine>()?;
+    m.add_class::<PyIndentedBlock>()?;
+    m.add_class::<PyComment>()?;
+    m.add_class::<PyNumber>()?;
+    m.add_class::<PyIpv6Address>()?;
+    m.add_class::<PyQuotedString>()?;
+    m.add_class::<PyEmpty>()?;
+    m.add_class::<PyNoMatch>()?;
+    m.add_class::<PySkipTo>()?;
+    m.add_class::<PyMatchPreviousLiteral>()?;
+    m.add_class::<PyMatchPreviousExpr>()?;
+    m.add_class::<PyIncrementalScanner>()?;
+    m.add_class::<PyStreamParser>()?;
+    m.add_class::<PyNormalized>()?;
+    m.add_class::<PyScanIterator>()?;
+    m.add_class::<PyLineParseIterator>()?;
+    m.add_class::<PyArrowColumn>()?;
+    m.add_class::<PyCharClass>()?;
+    m.add_class::<PyRegexMatchResult>()?;
+    m.add_class::<PyUnicodeSet>()?;
+    m.add_class::<PyCompactResults>()?;
+    m.add_class::<PyCompiledGrammar>()?;
+    m.add_class::<PyFastScanner>()?;
+    m.add_class::<PyKeyValueParser>()?;
+    m.add_class::<PyParserPipeline>()?;
+    m.add_class::<PyInferredPattern>()?;
+
+    m.add_function(wrap_pyfunction!(alphas, m)?)?;
+    m.add_function(wrap_pyfunction!(alphanums, m)?)?;
+    m.add_function(wrap_pyfunction!(nums, m)?)?;
+    m.add_function(wrap_pyfunction!(printables, m)?)?;
+    m.add_function(wrap_pyfunction!(hexnums, m)?)?;
+    m.add_function(wrap_pyfunction!(alphas_upper, m)?)?;
+    m.add_function(wrap_pyfunction!(alphas_lower, m)?)?;
+    m.add_function(wrap_pyfunction!(srange, m)?)?;
+    m.add_function(wrap_pyfunction!(make_html_tags, m)?)?;
+    m.add_function(wrap_pyfunction!(make_xml_tags, m)?)?;
+    m.add_function(wrap_pyfunction!(with_attribute, m)?)?;
+    m.add("ANY_VALUE", Py::new(m.py(), PyAnyValue)?)?;
+    m.add_function(wrap_pyfunction!(identchars, m)?)?;
+    m.add_function(wrap_pyfunction!(identbodychars, m)?)?;
+    m.add_function(wrap_pyfunction!(one_of, m)?)?;
+    m.add_function(wrap_pyfunction!(rest_of_line, m)?)?;
+    m.add_function(wrap_pyfunction!(as_line, m)?)?;
+    m.add_function(wrap_pyfunction!(lineno_py, m)?)?;
+    m.add_function(wrap_pyfunction!(col_py, m)?)?;
+    m.add_function(wrap_pyfunction!(line_py, m)?)?;
+    m.add_function(wrap_pyfunction!(generate, m)?)?;
+    m.add_function(wrap_pyfunction!(compile, m)?)?;
+    m.add_function(wrap_pyfunction!(c_style_comment, m)?)?;
+    m.add_function(wrap_pyfunction!(cpp_style_comment, m)?)?;
+    m.add_function(wrap_pyfunction!(python_style_comment, m)?)?;
+    m.add_function(wrap_pyfunction!(html_comment, m)?)?;
+    m.add_function(wrap_pyfunction!(original_text_for, m)?)?;
+    m.add_function(wrap_pyfunction!(match_previous_literal, m)?)?;
+    m.add_function(wrap_pyfunction!(match_previous_expr, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_str_predicates, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_regex_set, m)?)?;
+    m.add_function(wrap_pyfunction!(stream_batch_count, m)?)?;
+    m.add_function(wrap_pyfunction!(aggregate_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_count_matches, m)?)?;
+    m.add_function(wrap_pyfunction!(multi_match_indices, m)?)?;
+    m.add_function(wrap_pyfunction!(length_histogram, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_batch_compact, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_compact, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_arrow_column, m)?)?;
+    m.add_function(wrap_pyfunction!(sample_scan, m)?)?;
+    m.add_function(wrap_pyfunction!(transform_multi, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_replace, m)?)?;
+    m.add_function(wrap_pyfunction!(set_checkpoint_interval, m)?)?;
+    m.add_function(wrap_pyfunction!(enable_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics_snapshot, m)?)?;
+    m.add_function(wrap_pyfunction!(reset_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(enable_profiling, m)?)?;
+    m.add_function(wrap_pyfunction!(get_profile_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(reset_profile_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(enable_packrat, m)?)?;
+    m.add_function(wrap_pyfunction!(disable_packrat, m)?)?;
+    m.add_function(wrap_pyfunction!(packrat_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(enable_left_recursion, m)?)?;
+    m.add_function(wrap_pyfunction!(disable_left_recursion, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize_py, m)?)?;
+    m.add_function(wrap_pyfunction!(infer_pattern, m)?)?;
+    m.add_function(wrap_pyfunction!(set_debug_actions, m)?)?;
+    m.add_function(wrap_pyfunction!(rebuild_element, m)?)?;
+    m.add_function(wrap_pyfunction!(set_default_whitespace_chars, m)?)?;
+    m.add_function(wrap_pyfunction!(set_recursion_limit_py, m)?)?;
+    m.add_function(wrap_pyfunction!(set_regex_limits, m)?)?;
+    m.add_function(wrap_pyfunction!(regex_cache_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(clear_regex_cache, m)?)?;
+    m.add_function(wrap_pyfunction!(set_default_keyword_chars_py, m)?)?;
+    m.add_function(wrap_pyfunction!(transform_multi_file, m)?)?;
+    m.add_function(wrap_pyfunction!(process_file_streaming, m)?)?;
+    m.add_function(wrap_pyfunction!(process_csv_field, m)?)?;
+    m.add_function(wrap_pyfunction!(split_file_process, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_directory, m)?)?;
+    m.add_function(wrap_pyfunction!(api_summary, m)?)?;
+
+    // Prebuilt elements, like pyparsing.pyparsing_common
+    let common = PyModule::new(m.py(), "common")?;
+    for (name, kind) in [
+        ("integer", NumberKind::Integer),
+        ("signed_integer", NumberKind::SignedInteger),
+        ("real", NumberKind::Real),
+        ("sci_real", NumberKind::SciReal),
+        ("number", NumberKind::Number),
+        ("fraction", NumberKind::Fraction),
+    ] {
+        let elem = PyNumber {
+            inner: Arc::new(RustNumber::new(kind)),
+        };
+        common.add(name, Py::new(m.py(), elem)?)?;
+    }
+    for (name, pattern) in [
+        (
+            "ipv4_address",
+            r"(?:25[0-5]|2[0-4][0-9]|1?[0-9]{1,2})(?:\.(?:25[0-5]|2[0-4][0-9]|1?[0-9]{1,2})){3}",
+        ),
+        (
+            "mac_address",
+            r"[0-9a-fA-F]{2}(?::[0-9a-fA-F]{2}){5}|[0-9a-fA-F]{2}(?:-[0-9a-fA-F]{2}){5}|[0-9a-fA-F]{2}(?:\.[0-9a-fA-F]{2}){5}",
+        ),
+        (
+            "uuid",
+            r"[0-9a-fA-F]{8}(?:-[0-9a-fA-F]{4}){3}-[0-9a-fA-F]{12}",
+        ),
+        ("iso8601_date", r"\d{4}(?:-\d\d(?:-\d\d)?)?"),
+        (
+            "iso8601_datetime",
+            r"\d{4}-\d\d-\d\d[T ]\d\d:\d\d(?::\d\d(?:\.\d*)?)?(?:Z|[+-]\d\d:?\d\d)?",
+        ),
+    ] {
+        let regex = RegexMatch::new(pattern).map_err(|e| regex_error(pattern, &e))?;
+        let elem = PyRegex {
+            inner: Arc::new(regex),
+        };
+        common.add(name, Py::new(m.py(), elem)?)?;
+    }
+    let ipv6 = PyIpv6Address {
+        inner: Arc::new(RustIpv6Address::new()),
+    };
+    common.add("ipv6_address", Py::new(m.py(), ipv6)?)?;
+    let identifier = PyWord {
+        inner: Arc::new(
+            RustWord::new(builder::IDENTCHARS).with_body_chars(builder::IDENTBODYCHARS),
+        ),
+    };
+    common.add("identifier", Py::new(m.py(), identifier)?)?;
+    common.add(
+        "comma_separated_list",
+        element_to_py(m.py(), &comma_separated_list()?)?,
+    )?;
+    common.add("__doc__", module_summary("common"))?;
+    m.add_submodule(&common)?;
+
+    // Character strings of Unicode scripts, like pyparsing.pyparsing_unicode
+    let unicode = PyModule::new(m.py(), "unicode")?;
+    for set in UNICODE_SETS {
+        unicode.add(set.name, PyUnicodeSet { inner: set })?;
+    }
+    unicode.add("__doc__", module_summary("unicode"))?;
+    m.add_submodule(&unicode)?;
+
+    m.add("__version__", "0.2.0")?;
+    m.add(
+        "__all__",
+        api::API.iter().map(|e| e.name).collect::<Vec<_>>(),
+    )?;
+    Ok(())
+}
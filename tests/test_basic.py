@@ -35,6 +35,64 @@ class TestWord:
         result = word.parse_string("axxx")
         assert result == ["axxx"]
 
+    def test_word_exact_truncates_longer_run(self):
+        year = pp.Word(pp.nums(), exact=4)
+        assert year.parse_string("123456") == ["1234"]
+        assert year.search_string("1999 20001 12") == [["1999"], ["2000"]]
+        assert year.parse_batch(["20240", "24"]) == [["2024"], []]
+        with pytest.raises(ValueError):
+            year.parse_string("123")
+
+    def test_word_min_rejects_short_run(self):
+        word = pp.Word(pp.alphas(), min=3)
+        with pytest.raises(ValueError):
+            word.parse_string("ab")
+        assert word.search_string_count("ab abc abcd") == 2
+
+    def test_word_max_counts_characters(self):
+        assert pp.Word(pp.hexnums(), min=2, max=8).parse_string("deadbeefcafe") == ["deadbeef"]
+        assert pp.Word(pp.alphas(), pp.nums(), max=3).parse_string("a12345") == ["a12"]
+        assert pp.Word(pp.alphas() + "é", max=2).parse_string("ééé") == ["éé"]
+
+    def test_word_exclude_chars(self):
+        token = pp.Word(pp.printables(), exclude_chars="=,")
+        assert token.parse_string("key=value,next") == ["key"]
+        assert token.search_string("key=value,next") == [["key"], ["value"], ["next"]]
+        pair = token + pp.Suppress(pp.Literal("=")) + token
+        assert pair.parse_string("key=value,next") == ["key", "value"]
+
+    def test_word_exclude_applies_to_body_chars(self):
+        word = pp.Word("ab", "ab,é", exclude_chars=",é")
+        assert word.parse_string("abé,b") == ["ab"]
+        assert pp.Word(pp.alphas() + "é", exclude_chars="é").search_string("caféx") == [["caf"], ["x"]]
+
+    def test_word_as_keyword(self):
+        digits = pp.Word(pp.nums(), max=2, as_keyword=True)
+        assert digits.search_string("12345 67 8") == [["67"], ["8"]]
+        with pytest.raises(ValueError):
+            digits.parse_string("123")
+        word = pp.Word(pp.alphas(), pp.alphanums(), as_keyword=True)
+        assert word.search_string("x1y2 z") == [["x1y2"], ["z"]]
+        assert pp.Word(pp.alphas(), as_keyword=True).matches("abc")
+
+    def test_word_mixed_ascii_and_unicode_chars(self):
+        word = pp.Word("αβγ" + pp.alphas(), pp.alphanums() + "é_")
+        assert word.parse_string("βaé9_ x") == ["βaé9_"]
+        assert word.search_string("1 αbé, γ; é") == [["αbé"], ["γ"]]
+        assert word.scan_string("x αé") == [(["x"], 0, 1), (["αé"], 2, 6)]
+        assert pp.Word("日本", max=2, as_keyword=True).search_string("日本 日本日 本") == [["日本"], ["本"]]
+        assert pp.Word("é", max=1).search_string("ééé") == [["é"], ["é"], ["é"]]
+        # Latin-1 characters match whole, never by a single UTF-8 byte
+        assert pp.Word("é").search_string("aéé 陀 é") == [["éé"], ["é"]]
+        assert pp.Word(pp.alphas()).search_string("naïve") == [["na"], ["ve"]]
+        assert pp.Regex("[éa]").search_string("é a") == [["é"], ["a"]]
+
+    def test_word_invalid_lengths(self):
+        with pytest.raises(ValueError):
+            pp.Word(pp.nums(), min=0)
+        with pytest.raises(ValueError):
+            pp.Word(pp.nums(), min=5, max=2)
+
 class TestRegex:
     def test_regex_digits(self):
         regex = pp.Regex(r"\d+")
@@ -57,6 +115,70 @@ class TestKeyword:
         with pytest.raises(ValueError):
             kw.parse_string("ifx")  # Should fail - "ifx" is not "if"
 
+    def test_keyword_ident_chars(self):
+        kw = pp.Keyword("color", ident_chars="A-Za-z0-9_-")
+        assert kw.ident_chars == "A-Za-z0-9_-"
+        assert kw.parse_string("color: red") == ["color"]
+        with pytest.raises(ValueError):
+            kw.parse_string("color-scheme")
+        assert pp.Keyword("color").parse_string("color-scheme") == ["color"]
+        assert pp.Keyword("if", ident_chars=pp.CharClass(r"\p{L}")).parse_string("if1") == ["if"]
+        with pytest.raises(ValueError):
+            pp.Keyword("if", ident_chars=pp.CharClass(r"\p{L}")).parse_string("ifé")
+
+    def test_keyword_caseless(self):
+        kw = pp.Keyword("select", caseless=True)
+        assert kw.caseless
+        assert kw.parse_string("SeLeCt *") == ["select"]
+        assert kw.search_string("SELECT a; select b") == [["select"], ["select"]]
+        with pytest.raises(ValueError):
+            kw.parse_string("selected")
+
+    def test_keyword_caseless_in_sequence(self):
+        # The token is the keyword as given on every path, not the text matched
+        grammar = pp.Literal("a") + pp.Keyword("if", caseless=True)
+        assert grammar.parse_string("a IF") == ["a", "if"]
+        assert grammar.search_string("a If") == [["a", "if"]]
+        assert pp.Group(grammar).parse_string("a iF") == [["a", "if"]]
+        compiled = pp.compile(grammar)
+        assert compiled.parse_string("a IF") == ["a", "if"]
+        assert compiled.search_string("a iF a If") == [["a", "if"], ["a", "if"]]
+
+    def test_keyword_leading_boundary(self):
+        kw = pp.Keyword("end")
+        assert kw.scan_string("veryend end") == [(["end"], 8, 11)]
+        assert kw.search_string("_end 2end end_ end2 (end)") == [["end"]]
+        assert kw.find_all_positions("x_end 9end end") == [(11, 14)]
+        assert pp.SkipTo(kw).parse_string("backend end") == ["backend "]
+        statement = kw + ";"
+        assert statement.search_string("legend; end;") == [["end", ";"]]
+        keywords = pp.MatchFirst([pp.Keyword(w) for w in
+                                  ["begin", "end", "if", "then", "else", "do", "od", "fi"]])
+        assert keywords.search_string("weekend ifdo do_ 1do fi") == [["fi"]]
+        caseless = pp.Keyword("END", caseless=True)
+        assert caseless.search_string("WeekEnd End") == [["END"]]
+        letters = pp.Keyword("if", ident_chars=pp.CharClass(r"\p{L}"))
+        assert letters.search_string("éif 1if") == [["if"]]
+        stream = pp.StreamParser(kw)
+        assert stream.feed("very") == []
+        assert [(start, end) for _, start, end in stream.feed("end end ")] == [(8, 11)]
+
+    def test_default_keyword_chars(self):
+        import pickle
+        previous = pp.set_default_keyword_chars("A-Za-z0-9_$")
+        try:
+            assert previous == "A-Za-z0-9_"
+            kw = pp.Keyword("if")
+            with pytest.raises(ValueError):
+                kw.parse_string("if$")
+            assert pp.Keyword.set_default_keyword_chars() == "A-Za-z0-9_$"
+            # Keywords keep the characters they were created with, also when pickled
+            with pytest.raises(ValueError):
+                pickle.loads(pickle.dumps(kw)).parse_string("if$")
+            assert pp.Keyword("if").parse_string("if$") == ["if"]
+        finally:
+            pp.set_default_keyword_chars(previous)
+
 class TestTransformString:
     def test_literal_transform(self):
         lit = pp.Literal("fox")
@@ -83,6 +205,278 @@ class TestTransformString:
         result = regex.transform_string("foo 123 bar 456", "NUM")
         assert result == "foo NUM bar NUM"
 
+    def test_transform_with_parse_action(self):
+        upper = pp.Word(pp.alphas()).set_parse_action(lambda t: t[0].upper())
+        assert upper.transform_string("  hello, world!\n") == "  HELLO, WORLD!\n"
+        num = pp.Regex(r"\d+").set_parse_action(lambda t: int(t[0]) * 2)
+        assert num.transform_string("a1 b20\tc") == "a2 b40\tc"
+
+    def test_transform_joins_tokens(self):
+        pair = pp.Word(pp.alphas()) + pp.Literal("=").suppress() + pp.Word(pp.nums())
+        assert pair.transform_string("x = 1; yy=22") == "x1; yy22"
+        assert pair.transform_string("x = 1; yy=22", "P") == "P; P"
+        assert pp.Group(pair).transform_string("a=1 b=2") == "a1 b2"
+
+    def test_transform_without_replacement_keeps_text(self):
+        for elem in (pp.Literal("fox"), pp.Word(pp.alphas()), pp.Regex(r"\w+")):
+            assert elem.transform_string("the  fox\tran") == "the  fox\tran"
+
+class TestScanString:
+    def test_offsets(self):
+        word = pp.Word(pp.alphas())
+        assert word.scan_string("ab 12 cde") == [(["ab"], 0, 2), (["cde"], 6, 9)]
+
+    def test_start_skips_leading_whitespace(self):
+        pair = pp.Word(pp.alphas()) + pp.Word(pp.nums())
+        assert pair.scan_string("x 1;  yy 22") == [(["x", "1"], 0, 3), (["yy", "22"], 6, 11)]
+
+    def test_max_matches(self):
+        assert pp.Word(pp.nums()).scan_string("1 2 3 4", max_matches=2) == [(["1"], 0, 1), (["2"], 2, 3)]
+        assert pp.Word(pp.nums()).scan_string("1 2", max_matches=0) == []
+
+    def test_overlap(self):
+        word = pp.Word(pp.alphas())
+        assert word.scan_string("abc", overlap=True) == [(["abc"], 0, 3), (["bc"], 1, 3), (["c"], 2, 3)]
+        assert pp.Literal("aa").scan_string("aaa", overlap=True) == [(["aa"], 0, 2), (["aa"], 1, 3)]
+
+    def test_byte_offsets_and_actions(self):
+        num = pp.Word(pp.nums()).set_parse_action(lambda t: int(t[0]))
+        text = "é 42"
+        ((tokens, start, end),) = num.scan_string(text)
+        assert tokens == [42]
+        assert text.encode()[start:end] == b"42"
+        assert pp.Word(pp.alphas()).scan_string("héllo", overlap=True)[1] == (["llo"], 3, 6)
+
+    def test_zero_length_matches_skipped(self):
+        # The Optional matches nothing at "b" and past the whitespace before it
+        g = pp.Optional(pp.OneOrMore(pp.Optional(pp.Word(pp.nums()))))
+        assert g.scan_string("1 b") == [(["1"], 0, 1)]
+        assert g.scan_string("1 b  2", max_matches=5) == [(["1"], 0, 1), (["2"], 5, 6)]
+        assert pp.Optional(pp.Literal("x")).scan_string("a x") == [(["x"], 2, 3)]
+
+class TestFindAllPositions:
+    def test_agrees_with_search_and_scan(self):
+        ident = pp.Word(pp.alphas(), pp.alphanums())
+        call = pp.Group(ident + pp.Suppress("(") + pp.Optional(ident) + pp.Suppress(")"))
+        text = "f(x) + g() * h(y, z) -  k (w)\n" * 20
+        for elem in (call, ident, pp.Regex(r"\d+|[a-z]"), pp.Literal("(") + ident):
+            positions = elem.find_all_positions(text)
+            assert positions == [(start, end) for _, start, end in elem.scan_string(text)]
+            assert len(positions) == len(elem.search_string(text))
+            assert [text[a:b] for a, b in positions[:2]] == [
+                text[a:b] for _, a, b in elem.scan_string(text, max_matches=2)
+            ]
+
+    def test_zero_length_matches_skipped(self):
+        grammars = [
+            pp.Optional(pp.OneOrMore(pp.Optional(pp.Word(pp.nums())))),
+            pp.Regex("x*"),
+            pp.Regex(r"\s*x"),
+            pp.ZeroOrMore(pp.Literal("x")),
+            pp.Group(pp.Optional(pp.Literal("x"))),
+            pp.Optional(pp.Literal("x")) + pp.Optional(pp.Literal("y")),
+        ]
+        for elem in grammars:
+            for text in ("1 b  x\n 2", "axxb x", "", "  ", " b x  y"):
+                scanned = elem.scan_string(text)
+                assert all(start < end for _, start, end in scanned), (elem, text)
+                assert elem.find_all_positions(text) == [(a, b) for _, a, b in scanned], (elem, text)
+                assert elem.search_string(text) == [tokens for tokens, _, _ in scanned], (elem, text)
+                assert elem.search_string_count(text) == len(scanned), (elem, text)
+        assert pp.Optional(pp.OneOrMore(pp.Optional(pp.Word(pp.nums())))).find_all_positions("1 b") == [(0, 1)]
+
+    def test_overlap_and_offsets(self):
+        word = pp.Word(pp.alphas())
+        assert word.find_all_positions("abc de") == [(0, 3), (4, 6)]
+        assert word.find_all_positions("abc", overlap=True) == [(0, 3), (1, 3), (2, 3)]
+        assert pp.Literal("aa").find_all_positions("aaa", overlap=True) == [(0, 2), (1, 3)]
+        assert word.find_all_positions("héllo", overlap=True)[1] == (3, 6)
+        assert (pp.Word(pp.alphas()) + pp.Word(pp.nums())).find_all_positions("x 1;  yy 22") == [
+            (0, 3), (6, 11),
+        ]
+        assert pp.Empty().find_all_positions("abc") == []
+
+    def test_batch(self):
+        ident = pp.Word(pp.alphas(), pp.alphanums())
+        inputs = ["f(x) g()", "", "123", "aa aa", b"k(w)", "aa aa"]
+        for elem in (ident, pp.Literal("aa"), pp.Keyword("aa"), ident + pp.Literal("(")):
+            for overlap in (False, True):
+                for cap in (None, 0, 1, 2):
+                    expected = [
+                        (i, a, b)
+                        for i, s in enumerate(inputs)
+                        for a, b in elem.find_all_positions(s if isinstance(s, str) else s.decode(), overlap=overlap)[:cap]
+                    ]
+                    found = elem.find_all_positions_batch(inputs, overlap=overlap, max_matches_per_input=cap)
+                    assert found == expected, (elem, overlap, cap)
+                    arrays = elem.find_all_positions_batch(
+                        inputs, overlap=overlap, max_matches_per_input=cap, as_arrays=True
+                    )
+                    assert arrays == tuple([span[k] for span in expected] for k in range(3))
+        assert pp.Literal("aa").find_all_positions_batch(["aaa"], overlap=True) == [(0, 0, 2), (0, 1, 3)]
+
+    def test_parse_actions_do_not_run(self):
+        seen = []
+        num = pp.Word(pp.nums()).add_parse_action(lambda t: seen.append(t[0]))
+        assert num.find_all_positions("1 22 333") == [(0, 1), (2, 4), (5, 8)]
+        assert seen == []
+
+class TestSplit:
+    def test_like_re_split(self):
+        comma = pp.Literal(",")
+        assert comma.split("a,b,,c") == ["a", "b", "", "c"]
+        assert comma.split(",a,b,") == ["", "a", "b", ""]
+        assert comma.split("abc") == ["abc"]
+        assert comma.split("") == [""]
+        punct = pp.Word(".,;!?")
+        assert punct.split("wait... what?! yes;no") == ["wait", " what", " yes", "no"]
+
+    def test_maxsplit_and_separators(self):
+        punct = pp.Word(".,;!?")
+        assert punct.split("a, b; c.", maxsplit=1) == ["a", " b; c."]
+        assert punct.split("a, b; c.", maxsplit=0) == ["a, b; c."]
+        assert punct.split("a,b;;c", include_separators=True) == ["a", ",", "b", ";;", "c"]
+        sep = pp.Suppress(pp.Literal("->"))
+        assert sep.split("x->y", include_separators=True) == ["x", "->", "y"]
+
+    def test_csv_separators(self):
+        # A comma not followed by a digit, so "1,234" stays one field
+        sep = pp.Literal(",").add_condition(lambda s, loc, t: not s[loc + 1:loc + 2].isdigit())
+        assert sep.split("price 1,234,blue,red") == ["price 1,234", "blue", "red"]
+        # A comma outside double quotes
+        unquoted = pp.Literal(",").add_condition(lambda s, loc, t: s.count('"', 0, loc) % 2 == 0)
+        assert unquoted.split('a,"b,c",d') == ["a", '"b,c"', "d"]
+
+    def test_empty_matches_do_not_split(self):
+        assert pp.Empty().split("abc") == ["abc"]
+        assert pp.Optional(",").split("a,b") == ["a", "b"]
+        assert pp.ZeroOrMore(pp.Literal("-")).split("a--b") == ["a", "b"]
+        # try_match_at sees a match past the whitespace, parse_impl an empty one
+        g = pp.Optional(pp.OneOrMore(pp.Optional(pp.Word(pp.nums()))))
+        assert g.split("1 b  x\n 2") == ["", " b  x\n ", ""]
+        assert g.split("1 b  x\n 2", maxsplit=1) == ["", " b  x\n 2"]
+
+class TestScanIter:
+    def test_matches_scan_string(self):
+        pair = pp.Word(pp.alphas()) + pp.Word(pp.nums())
+        text = "x 1;  yy 22 é z 3"
+        assert list(pair.scan_iter(text)) == [tuple(m) for m in pair.scan_string(text)]
+        assert list(pp.Literal("aa").scan_iter("aaa", overlap=True)) == [(["aa"], 0, 2), (["aa"], 1, 3)]
+
+    def test_lazy_and_resumable(self):
+        import itertools
+        word = pp.Word(pp.alphas())
+        it = word.scan_iter("ab cd ef gh")
+        assert iter(it) is it
+        assert list(itertools.islice(it, 2)) == [(["ab"], 0, 2), (["cd"], 3, 5)]
+        assert it.loc == 5
+        assert list(word.scan_iter("ab cd ef gh", it.loc)) == [(["ef"], 6, 8), (["gh"], 9, 11)]
+        assert next(it) == (["ef"], 6, 8)
+        assert list(it) == [(["gh"], 9, 11)]
+        assert next(it, None) is None
+
+    def test_start_and_end(self):
+        word = pp.Word(pp.alphas())
+        assert list(word.scan_iter("ab cd ef", 3, 7)) == [(["cd"], 3, 5), (["e"], 6, 7)]
+        assert list(word.scan_iter("ab", 2)) == []
+        for start, end in ((3, 2), (0, 9)):
+            with pytest.raises(ValueError):
+                word.scan_iter("ab cd", start, end)
+        with pytest.raises(ValueError):
+            word.scan_iter("éa", 1)
+
+    def test_action_errors_raise_from_next(self):
+        def fail(tokens):
+            raise RuntimeError("boom")
+        it = pp.Word(pp.nums()).set_parse_action(fail).scan_iter("a 1")
+        with pytest.raises(RuntimeError):
+            next(it)
+
+class TestParseAll:
+    def test_trailing_text_rejected(self):
+        assert pp.Literal("foo").parse_string("foobar extra") == ["foo"]
+        with pytest.raises(pp.ParseError) as exc:
+            pp.Literal("foo").parse_string("foobar extra", parse_all=True)
+        assert exc.value.loc == 3
+        assert "found 'bar extra'" in exc.value.msg
+
+    def test_trailing_whitespace_allowed(self):
+        assert pp.Literal("foo").parse_string("  foo \n\t", parse_all=True) == ["foo"]
+
+    def test_every_element_accepts_parse_all(self):
+        word = pp.Word(pp.alphas())
+        for elem, text in (
+            (pp.Literal("a"), "a"), (word, "ab"), (pp.Regex("a+"), "aa"), (pp.Keyword("a"), "a"),
+            (word + word, "a b"), (word | pp.Literal("1"), "a"), (pp.Group(word), "a"),
+            (pp.Optional(word), "a"), (pp.ZeroOrMore(word), "a b"), (pp.OneOrMore(word), "a b"),
+            (pp.Suppress(word), "a"), (word("name"), "a"),
+        ):
+            elem.parse_string(text + " ", parse_all=True)
+            with pytest.raises(pp.ParseError, match="Expected end of text"):
+                elem.parse_string(text + " ;", parse_all=True)
+
+    def test_long_leftover_is_truncated(self):
+        with pytest.raises(pp.ParseError) as exc:
+            pp.Word(pp.nums()).parse_string("12 " + "x" * 100, parse_all=True)
+        assert exc.value.msg == "Expected end of text, found '" + "x" * 20 + "...'"
+        assert exc.value.col == 4
+
+class TestMatches:
+    def parses(self, elem, text, parse_all):
+        try:
+            elem.parse_string(text, parse_all=parse_all)
+            return True
+        except pp.ParseError:
+            return False
+
+    def test_agrees_with_parse_string(self):
+        word = pp.Word(pp.alphas())
+        even = pp.Word(pp.nums()).add_condition(lambda t: int(t[0]) % 2 == 0)
+
+        def reject_x(t):
+            if t[0] == "x":
+                raise pp.ParseError("no x")
+
+        elems = [
+            pp.Literal("a"), word, pp.Regex(r"\d+"), pp.Keyword("if"), word + pp.Literal("="),
+            pp.StringStart(), pp.Empty(), pp.Optional(word), pp.OneOrMore(word), even,
+            word.add_parse_action(reject_x), pp.match_previous_literal(word) + word,
+            pp.OneOrMore(word).ignore(pp.python_style_comment()),
+        ]
+        texts = ["", "a", " a ", "ab", "a b", "x", "12", "7", "if", "iffy", "a =", "a # c", ";"]
+        for elem in elems:
+            for text in texts:
+                for parse_all in (True, False):
+                    expected = self.parses(elem, text, parse_all)
+                    assert elem.matches(text, parse_all=parse_all) == expected, (elem, text)
+
+    def test_action_errors_raise(self):
+        def boom(t):
+            raise RuntimeError("boom")
+
+        elem = pp.Word(pp.alphas()).add_parse_action(boom)
+        with pytest.raises(RuntimeError, match="boom"):
+            elem.matches("abc")
+        with pytest.raises(RuntimeError, match="boom"):
+            elem.matches_batch(["1", "abc"])
+        assert elem.matches_batch(["1", "2"]) == [False, False]
+        failing = pp.Word(pp.alphas()).add_condition(lambda t: 1 / 0)
+        with pytest.raises(ZeroDivisionError):
+            failing.matches("abc")
+        assert not failing.matches("123")
+
+    def test_matches_batch(self):
+        num = pp.Word(pp.nums())
+        inputs = ["12", " 3 ", "4x", "", "12"] * 3
+        assert num.matches_batch(inputs) == [True, True, False, False, True] * 3
+        assert num.matches_batch(inputs, parse_all=False) == [True, True, True, False, True] * 3
+        seen = []
+        tracked = num.add_parse_action(lambda t: seen.append(t[0]))
+        assert tracked.matches_batch(["1", "x", "1"]) == [True, False, True]
+        assert seen == ["1", "1"]
+        with pytest.raises(ValueError):
+            num.matches_batch(["1", 2])
+
 class TestOneOf:
     def test_one_of_basic(self):
         expr = pp.one_of("+ - * /")
@@ -156,7 +550,8 @@ class TestChar:
 class TestPositionalAnchors:
     def test_string_start(self):
         ss = pp.StringStart()
-        assert ss.matches("hello")
+        assert ss.matches("hello", parse_all=False)
+        assert not ss.matches("hello")
         expr = ss + pp.Literal("hello")
         assert expr.parse_string("hello") == ["hello"]
 
@@ -169,7 +564,7 @@ class TestPositionalAnchors:
 
     def test_line_start(self):
         ls = pp.LineStart()
-        assert ls.matches("hello")
+        assert ls.matches("hello", parse_all=False)
 
     def test_line_end(self):
         le = pp.LineEnd()
@@ -184,6 +579,275 @@ class TestPositionalAnchors:
         rol = pp.rest_of_line()
         assert rol.parse_string("") == [""]
 
+    def test_rest_of_line_log_search(self):
+        log = "INFO start\nERROR  disk full: /var\nWARN slow\nERROR timeout after 30s\n"
+        expr = pp.Keyword("ERROR") + pp.rest_of_line()
+        assert expr.search_string(log) == [
+            ["ERROR", "  disk full: /var"],
+            ["ERROR", " timeout after 30s"],
+        ]
+
+    def test_rest_of_line_strip(self):
+        expr = pp.Keyword("key") + pp.rest_of_line(strip=True)
+        assert expr.parse_string("key   value here  \nnext") == ["key", "value here"]
+
+class TestComments:
+    def test_c_style_multiline(self):
+        cmt = pp.c_style_comment()
+        assert cmt.parse_string("/* a\n b */ x") == ["/* a\n b */"]
+
+    def test_c_style_ends_at_first_close(self):
+        cmt = pp.c_style_comment()
+        assert cmt.parse_string("/* a /* b */ c */") == ["/* a /* b */"]
+
+    def test_c_style_unterminated(self):
+        cmt = pp.c_style_comment()
+        with pytest.raises(ValueError, match="Unterminated comment"):
+            cmt.parse_string("/* never closed")
+
+    def test_cpp_style_both_forms(self):
+        cmt = pp.cpp_style_comment()
+        assert cmt.parse_string("// line\nnext") == ["// line"]
+        assert cmt.parse_string("/* block */") == ["/* block */"]
+
+    def test_python_style_search(self):
+        cmt = pp.python_style_comment()
+        text = "x = 1  # one\ny = 2\n# two\n"
+        assert cmt.search_string(text) == [["# one"], ["# two"]]
+
+    def test_html_comment(self):
+        cmt = pp.html_comment()
+        assert cmt.parse_string("<!-- note -->") == ["<!-- note -->"]
+        with pytest.raises(ValueError):
+            cmt.parse_string("<!-- open")
+
+class TestCommonNumbers:
+    def test_number_typed_values(self):
+        result = pp.OneOrMore(pp.common.number).parse_string("3.14 -2 1e-5")
+        assert result == [3.14, -2, 1e-5]
+        assert [type(v) for v in result] == [float, int, float]
+
+    def test_integer_forms(self):
+        assert pp.common.integer.parse_string("42") == [42]
+        assert pp.common.signed_integer.parse_string("-17") == [-17]
+        with pytest.raises(ValueError):
+            pp.common.integer.parse_string("-17")
+
+    def test_real_forms(self):
+        assert pp.common.real.parse_string("2.5") == [2.5]
+        assert pp.common.real.parse_string(".5") == [0.5]
+        with pytest.raises(ValueError):
+            pp.common.real.parse_string("7")
+        assert pp.common.sci_real.parse_string("6E3") == [6000.0]
+        assert pp.common.sci_real.parse_string("-1.5e-2") == [-0.015]
+
+    def test_fraction(self):
+        assert pp.common.fraction.parse_string("3/4") == [0.75]
+        assert pp.common.fraction.parse_string("-1 / 2") == [-0.5]
+
+    def test_zero_denominator_and_oversized_integer_rejected(self):
+        fraction = pp.common.fraction
+        with pytest.raises(pp.ParseError, match="zero denominator"):
+            fraction.parse_string("1/0")
+        # Every way of matching agrees with parse_string
+        assert not fraction.matches("1/0")
+        assert fraction.search_string_count("1/0 3/4") == 1
+        assert fraction.find_all_positions("1/0 3/4") == [(4, 7)]
+        with pytest.raises(pp.ParseError, match="64 bits"):
+            pp.common.integer.parse_string("99999999999999999999")
+        assert not pp.common.signed_integer.matches("-99999999999999999999")
+        assert pp.common.integer.parse_string("9223372036854775807") == [9223372036854775807]
+
+    def test_number_in_sequence_and_search(self):
+        expr = pp.Literal("x") + pp.Literal("=") + pp.common.number
+        assert expr.parse_string("x = 10") == ["x", "=", 10]
+        assert pp.common.number.search_string("a 1 b 2.5") == [[1], [2.5]]
+
+class TestCommonNetworkAndDates:
+    def full_match(self, elem, text):
+        try:
+            return elem.parse_string(text) == [text]
+        except ValueError:
+            return False
+
+    def test_ipv4_table(self):
+        for addr in ["0.0.0.0", "10.0.0.1", "192.168.1.255", "255.255.255.255"]:
+            assert self.full_match(pp.common.ipv4_address, addr), addr
+        for addr in ["256.1.1.1", "1.2.3", "1.2.3.4.5x", "a.b.c.d"]:
+            assert not self.full_match(pp.common.ipv4_address, addr), addr
+
+    def test_ipv6_table(self):
+        valid = [
+            "::", "::1", "fe80::1", "2001:db8::8a2e:370:7334",
+            "1:2:3:4:5:6:7:8", "::ffff:192.0.2.1", "64:ff9b::192.0.2.33", "1:2:3:4:5:6:7::",
+        ]
+        invalid = [
+            "1::2::3", "1:2:3:4:5:6:7", "12345::1", "1:2:3:4:5:6:7:8:9",
+            "g::1", ":1:2", "1:2:3:4:5:6:7:8::", "::1.2.3.256",
+        ]
+        for addr in valid:
+            assert self.full_match(pp.common.ipv6_address, addr), addr
+        for addr in invalid:
+            assert not self.full_match(pp.common.ipv6_address, addr), addr
+
+    def test_ip_search_in_log(self):
+        log = "conn from 10.0.0.1 to fe80::1 via 192.168.1.7"
+        assert pp.common.ipv4_address.search_string(log) == [["10.0.0.1"], ["192.168.1.7"]]
+        assert pp.common.ipv6_address.search_string(log) == [["fe80::1"]]
+
+    def test_mac_and_uuid(self):
+        assert self.full_match(pp.common.mac_address, "00:1A:2b:3c:4d:5e")
+        assert self.full_match(pp.common.mac_address, "00-1a-2b-3c-4d-5e")
+        assert not self.full_match(pp.common.mac_address, "00:1A-2b:3c:4d:5e")
+        assert self.full_match(pp.common.uuid, "123e4567-e89b-12d3-a456-426614174000")
+        assert not self.full_match(pp.common.uuid, "123e4567-e89b-12d3-a456")
+
+    def test_iso8601(self):
+        for text in ["1999", "1999-12", "1999-12-31"]:
+            assert self.full_match(pp.common.iso8601_date, text), text
+        for text in ["1999-12-31T23:59", "1999-12-31 23:59:59", "1999-12-31T23:59:59.999Z",
+                     "1999-12-31T23:59:59+05:30"]:
+            assert self.full_match(pp.common.iso8601_datetime, text), text
+        assert not self.full_match(pp.common.iso8601_datetime, "1999-12-31")
+
+class TestCommaSeparatedList:
+    def test_messy_lines(self):
+        csl = pp.common.comma_separated_list
+        assert csl.parse_string("a, b ,c") == ["a", "b", "c"]
+        line = 'Smith, "Jones, Jr.", 42 Main St. ,  \'N/A\', 3.5 kg'
+        assert csl.parse_string(line) == ["Smith", '"Jones, Jr."', "42 Main St.", "'N/A'", "3.5 kg"]
+        assert csl.parse_string('"unterminated, x') == ['"unterminated', "x"]
+
+    def test_empty_fields_and_trailing_comma(self):
+        csl = pp.common.comma_separated_list
+        assert csl.parse_string("a,,b") == ["a", "", "b"]
+        assert csl.parse_string("a,b,") == ["a", "b", ""]
+        assert csl.parse_string("") == [""]
+
+    def test_one_line_at_a_time(self):
+        csl = pp.common.comma_separated_list
+        assert csl.parse_string("a, b\nc, d") == ["a", "b"]
+        assert csl.search_string("x,y\n1, 2") == [["x", "y"], ["1", "2"]]
+
+class TestAsLine:
+    def grammar(self):
+        return pp.as_line(pp.Keyword("GET") + pp.Word(pp.alphanums() + "/"))
+
+    def test_exact_line_with_trailing_spaces(self):
+        assert self.grammar().parse_string("GET /index  ") == ["GET", "/index"]
+        assert self.grammar().parse_string("GET /index\r\n") == ["GET", "/index"]
+
+    def test_partial_line_rejected(self):
+        with pytest.raises(ValueError, match="end of line"):
+            self.grammar().parse_string("GET /index extra")
+
+    def test_search_only_whole_lines(self):
+        log = "GET /a\r\nGET /b trailing\n  GET /c\nxx GET /d\n"
+        assert self.grammar().search_string(log) == [["GET", "/a"], ["GET", "/c"]]
+
+    def test_does_not_span_lines(self):
+        expr = pp.as_line(pp.Word(pp.alphas()) + pp.Word(pp.alphas()))
+        with pytest.raises(ValueError):
+            expr.parse_string("abc\nxyz")
+
+class TestIndentedBlock:
+    def grammar(self):
+        stmt = pp.Forward()
+        ident = pp.Word(pp.alphas(), pp.alphanums() + "_")
+        assign = pp.Group(ident + pp.Suppress("=") + pp.Word(pp.nums()))
+        section = pp.Group(ident + pp.Suppress(":") + pp.IndentedBlock(stmt))
+        stmt <<= section | assign
+        return pp.IndentedBlock(stmt, grouped=False)
+
+    def test_two_level_nested_document(self):
+        text = (
+            "server:\n"
+            "    host = 1\n"
+            "    limits:\n"
+            "        cpu = 2\n"
+            "\n"
+            "        mem = 3\n"
+            "    port = 4\n"
+            "debug = 0\n"
+        )
+        assert self.grammar().parse_string(text, parse_all=True) == [
+            ["server", [["host", "1"], ["limits", [["cpu", "2"], ["mem", "3"]]], ["port", "4"]]],
+            ["debug", "0"],
+        ]
+
+    def test_dedent_ends_block(self):
+        block = pp.IndentedBlock(pp.Word(pp.alphas()))
+        assert block.parse_string("  a\n  b\nc") == [["a", "b"]]
+        assert block.parse_string("  a\n    b") == [["a"]]
+
+    def test_nested_block_must_indent_past_parent(self):
+        with pytest.raises(pp.ParseException):
+            self.grammar().parse_string("server:\nhost = 1", parse_all=True)
+        # Lines of one block must line up
+        with pytest.raises(pp.ParseException):
+            self.grammar().parse_string("a:\n    x = 1\n  y = 2", parse_all=True)
+
+    def test_recursive_and_ungrouped(self):
+        word = pp.Word(pp.alphas())
+        tree = pp.IndentedBlock(word, recursive=True)
+        text = "a\nb\n  c\n  d\n    e\nf"
+        assert tree.parse_string(text, parse_all=True) == [["a", "b", ["c", "d", ["e"]], "f"]]
+        flat = pp.IndentedBlock(word, recursive=True, grouped=False)
+        assert flat.parse_string(text) == ["a", "b", "c", "d", "e", "f"]
+        with pytest.raises(TypeError):
+            pp.IndentedBlock(word, True)
+
+    def test_copy_and_str(self):
+        grammar = pp.IndentedBlock(pp.Word(pp.alphas()), recursive=True)
+        clone = grammar.copy()
+        assert clone.parse_string("a\n  b") == [["a", ["b"]]]
+        assert str(grammar) == "IndentedBlock:(W:(ABCDEFGH...))"
+
+class TestDict:
+    def grammar(self):
+        key = pp.Word(pp.alphas())
+        value = pp.Word(pp.nums())
+        return pp.Dict(pp.OneOrMore(pp.Group(key + pp.Suppress(pp.Literal("=")) + value)))
+
+    def test_keys_map_to_values(self):
+        result = self.grammar().parse_string("width=80 height=24")
+        assert result["width"] == "80"
+        assert result["height"] == "24"
+        assert result.keys() == ["width", "height"]
+        assert "depth" not in result
+
+    def test_tokens_unchanged(self):
+        result = self.grammar().parse_string("a=1 b=2")
+        assert result == [["a", "1"], ["b", "2"]]
+        assert result[1] == ["b", "2"]
+        assert len(result) == 2
+
+    def test_multi_token_value(self):
+        row = pp.Group(pp.Word(pp.alphas()) + pp.OneOrMore(pp.Word(pp.nums())))
+        result = pp.Dict(pp.OneOrMore(row)).parse_string("x 1 2 3 y 4")
+        assert result["x"] == ["1", "2", "3"]
+        assert result["y"] == "4"
+
+    def test_missing_key(self):
+        with pytest.raises(KeyError):
+            self.grammar().parse_string("a=1")["b"]
+
+class TestLocated:
+    def test_offsets_exclude_leading_whitespace(self):
+        result = pp.Located(pp.Word(pp.alphas())).parse_string("   abc")
+        assert result == [3, ["abc"], 6]
+        assert result["locn_start"] == 3
+        assert result["value"] == ["abc"]
+        assert result["locn_end"] == 6
+
+    def test_search_reports_each_occurrence(self):
+        expr = pp.Located(pp.Word(pp.alphas()) + pp.Word(pp.nums()))
+        assert expr.search_string("  ab 12  cd 3") == [[2, ["ab", "12"], 7], [9, ["cd", "3"], 13]]
+
+    def test_inside_sequence(self):
+        expr = pp.Literal("x") + pp.Located(pp.Word(pp.alphas()))
+        assert expr.parse_string("x   yy") == ["x", 4, ["yy"], 6]
+
 class TestQuotedString:
     def test_double_quoted(self):
         qs = pp.QuotedString('"')
@@ -214,7 +878,8 @@ class TestQuotedString:
 class TestEmpty:
     def test_empty_matches(self):
         empty = pp.Empty()
-        assert empty.matches("anything")
+        assert empty.matches("anything", parse_all=False)
+        assert empty.matches("  ")
         assert empty.parse_string("hello") == []
 
 class TestNoMatch:
@@ -253,5 +918,59 @@ class TestCharHelpers:
     def test_alphas_lower(self):
         assert pp.alphas_lower() == "abcdefghijklmnopqrstuvwxyz"
 
+    def test_identchars(self):
+        assert pp.identchars() == pp.alphas() + "_"
+        assert pp.identbodychars() == pp.alphanums() + "_"
+        ident = pp.Word(pp.identchars(), pp.identbodychars())
+        assert ident.parse_string("_tmp2 = 1") == ["_tmp2"]
+
+    def test_common_identifier(self):
+        ident = pp.common.identifier
+        assert ident.parse_string("var_1 x") == ["var_1"]
+        assert ident.parse_string("__init__") == ["__init__"]
+        with pytest.raises(ValueError):
+            ident.parse_string("1abc")
+        assert ident.search_string("9lives cat9 _") == [["lives"], ["cat9"], ["_"]]
+        # No NotAny here: keywords are kept out with a condition or by trying them first
+        keywords = {"if", "else"}
+        name = ident.add_condition(lambda t: t[0] not in keywords)
+        assert name.parse_string("iffy") == ["iffy"]
+        with pytest.raises(ValueError):
+            name.parse_string("if")
+        stmt = pp.Keyword("if") | ident
+        assert stmt.search_string("if ifx") == [["if"], ["ifx"]]
+
+    def test_srange(self):
+        assert pp.srange("[a-fA-F0-9]") == "abcdefABCDEF0123456789"
+        assert pp.srange("[-+0-2]") == "-+012"
+        assert pp.srange("[a-c-]") == "abc-"
+        assert pp.srange(r"[\t\n\\\]\x41-\x43]") == "\t\n\\]ABC"
+        assert pp.srange("[α-ε]") == "αβγδε"
+        assert pp.srange(r"[α\U0001F600]") == "α\U0001F600"
+        assert pp.Word(pp.srange("[a-fA-F0-9]")).search_string("0xDEAD zz beef") == [
+            ["0"], ["DEAD"], ["beef"]]
+
+    def test_unicode_sets(self):
+        greek = pp.unicode.Greek
+        assert "α" in greek.alphas and "Ω" in greek.alphas and "a" not in greek.alphas
+        assert pp.Word(greek.alphas).parse_string("αβγ δ") == ["αβγ"]
+        latin_greek = pp.Word(pp.unicode.Latin1.alphas + greek.alphas)
+        assert latin_greek.parse_string("café αβγ") == ["café"]
+        assert latin_greek.parse_string("abéαβ") == ["abéαβ"]
+        assert pp.unicode.Latin1.nums == "0123456789"
+        assert pp.unicode.Cyrillic.alphanums.startswith("ЀЁ")
+        assert "中" in pp.unicode.CJK.alphas and "한" in pp.unicode.CJK.alphas
+        assert "_" in pp.unicode.Latin1.identchars and "1" not in pp.unicode.Latin1.identchars
+        assert "1" in pp.unicode.Latin1.identbodychars
+        assert " " not in pp.unicode.Latin1.printables and "~" in pp.unicode.Latin1.printables
+        assert len(pp.unicode.BasicMultilingualPlane.alphas) > 40000
+        ident = pp.Word(pp.unicode.Cyrillic.identchars, pp.unicode.Cyrillic.identbodychars)
+        assert ident.parse_string("привет") == ["привет"]
+
+    def test_srange_errors(self):
+        for spec in ["[^a-z]", "a-z", "[z-a]", r"[\x4]", "[a\\]"]:
+            with pytest.raises(pp.PatternError):
+                pp.srange(spec)
+
 if __name__ == "__main__":
     pytest.main([__file__, "-v"])
@@ -1,5 +1,11 @@
 #!/usr/bin/env python3
 """Test combinators for pyparsing_rs."""
+import contextlib
+import copy
+import io
+import itertools
+import pickle
+
 import pytest
 import pyparsing_rs as pp
 
@@ -18,6 +24,56 @@ class TestAnd:
         with pytest.raises(ValueError):
             combined.parse_string("hello there")
 
+    def test_and_skips_whitespace_between_elements(self):
+        combined = pp.Keyword("let") + pp.Word(pp.alphas()) + pp.Literal("=") + pp.Regex(r"\d+")
+        for text in ["let x=1", "let  x \t= \n 1"]:
+            assert combined.parse_string(text) == ["let", "x", "=", "1"]
+        assert combined.search_string("a let x = 1; let y=2") == [
+            ["let", "x", "=", "1"],
+            ["let", "y", "=", "2"],
+        ]
+        glued = pp.Combine(pp.Literal("hello") + pp.Literal("world"))
+        assert glued.parse_string("helloworld") == ["helloworld"]
+        with pytest.raises(ValueError):
+            glued.parse_string("hello world")
+
+    def test_chained_and_is_flat(self):
+        num = pp.Word(pp.nums())
+        chained = pp.Literal("a") + num + pp.Literal("b") + pp.Keyword("if") + num
+        assert len(chained) == 5
+        assert [type(e).__name__ for e in chained.exprs] == [
+            "Literal", "Word", "Literal", "Keyword", "Word",
+        ]
+        assert chained.parse_string("a 1 b if 2") == ["a", "1", "b", "if", "2"]
+        nested = (pp.Literal("a") + num) + (pp.Literal("b") + (pp.Keyword("if") + num))
+        assert len(nested) == 5
+        assert nested.parse_string("a 1 b if 2") == ["a", "1", "b", "if", "2"]
+
+    def test_exprs_keep_their_class(self):
+        for name, sample in operand_samples().items():
+            if name != "And":
+                assert type((pp.Empty() + sample).exprs[1]) is type(sample), name
+
+    def test_and_from_list(self):
+        parts = [pp.Keyword("let"), pp.Word(pp.alphas()), "=", pp.Regex(r"\d+")]
+        assign = pp.And(parts)
+        assert assign.parse_string("let x = 42") == ["let", "x", "=", "42"]
+        nested = pp.And([pp.And(["a", "b"]), pp.Group(pp.Literal("c")), "d"])
+        assert len(nested) == 4
+        assert nested.parse_string("a b c d") == ["a", "b", ["c"], "d"]
+        assert pp.And(iter(["x", "y"])).parse_string("x y") == ["x", "y"]
+        stopped = pp.And([pp.Literal("x") - pp.Literal("y"), "z"])
+        with pytest.raises(pp.ParseFatalError):
+            (stopped | pp.Literal("x")).parse_string("x q")
+
+    def test_and_rejects_empty_and_bad_items(self):
+        with pytest.raises(ValueError, match="at least one"):
+            pp.And([])
+        with pytest.raises(TypeError):
+            pp.And("ab")
+        with pytest.raises(pp.PatternError, match="index 1"):
+            pp.And(["a", 3])
+
 class TestMatchFirst:
     def test_match_first_first_wins(self):
         lit1 = pp.Literal("hello")
@@ -33,6 +89,65 @@ class TestMatchFirst:
         result = combined.parse_string("goodbye")
         assert result == ["goodbye"]
 
+    def test_chained_match_first_is_flat(self):
+        chained = pp.Literal("a") | pp.Literal("b") | pp.Word(pp.nums()) | (pp.Literal("c") | pp.Literal("d"))
+        assert len(chained) == 5
+        assert [e.parse_string(t) for e, t in zip(chained.exprs, "ab1cd")] == [
+            ["a"], ["b"], ["1"], ["c"], ["d"],
+        ]
+        grouped = pp.MatchFirst([pp.Literal("x")]) | (pp.Literal("y") + pp.Literal("z"))
+        assert len(grouped) == 2
+        assert type(grouped.exprs[1]) is pp.And
+
+    def test_match_first_reports_furthest_failure(self):
+        ident = pp.Word(pp.alphas())
+        stmt = pp.MatchFirst([
+            pp.Keyword("if") + pp.Literal("(") + ident + pp.Literal(")"),
+            pp.Keyword("while") + ident,
+            pp.Literal("x") + pp.Literal("="),
+        ])
+        with pytest.raises(pp.ParseError) as exc:
+            stmt.parse_string("if (a b")
+        assert exc.value.loc == 6
+        assert exc.value.col == 7
+        assert "Expected ')'" in str(exc.value)
+
+    def test_match_first_combines_tied_failures(self):
+        expr = pp.Literal("if") | pp.Literal("while") | pp.Word(pp.nums())
+        with pytest.raises(pp.ParseError) as exc:
+            expr.parse_string("abc")
+        assert str(exc.value).startswith("Expected 'if' | 'while' | W:(01234567...)")
+
+    def test_match_first_from_list(self):
+        words = ["kw%d" % i for i in range(50)]
+        keywords = pp.MatchFirst([pp.Keyword(w) for w in words])
+        assert len(keywords) == 50
+        assert keywords.parse_string("kw37") == ["kw37"]
+        assert keywords.search_string("kw1 kw10x kw49") == [["kw1"], ["kw49"]]
+        with pytest.raises(pp.ParseError):
+            keywords.parse_string("kw50")
+        mixed = pp.MatchFirst((pp.Regex(r"\d+"), "+", pp.Group(pp.Literal("(") + ")")))
+        assert mixed.parse_string("()") == [["(", ")"]]
+        assert mixed.parse_string("+") == ["+"]
+
+    def test_match_first_flattens_and_rejects_empty(self):
+        nested = pp.MatchFirst([pp.MatchFirst(["a", "b"]), "c", pp.Literal("d") | "e"])
+        assert len(nested) == 5
+        assert nested.parse_string("e") == ["e"]
+        with pytest.raises(ValueError, match="at least one"):
+            pp.MatchFirst([])
+        with pytest.raises(TypeError):
+            pp.MatchFirst("ab")
+
+    def test_nested_match_first_reports_furthest_failure(self):
+        args = pp.Literal("(") + pp.Word(pp.nums()) + pp.Literal(")")
+        call = pp.Word(pp.alphas()) + args
+        expr = pp.Literal("(") + pp.MatchFirst([call, pp.Word(pp.nums())])
+        with pytest.raises(pp.ParseError) as exc:
+            expr.parse_string("(f(1 x")
+        assert exc.value.loc == 5
+        assert "Expected ')'" in str(exc.value)
+
 class TestZeroOrMore:
     def test_zero_or_more_multiple(self):
         lit = pp.Literal("a")
@@ -46,6 +161,26 @@ class TestZeroOrMore:
         result = many.parse_string("bbbb")
         assert result == []
 
+    def test_zero_or_more_stop_on(self):
+        end = pp.Keyword("END")
+        block = pp.Keyword("BEGIN") + pp.Group(pp.ZeroOrMore(pp.Word(pp.alphas()), stop_on=end)) + end
+        assert block.parse_string("BEGIN x y END", parse_all=True) == ["BEGIN", ["x", "y"], "END"]
+        assert block.parse_string("BEGIN END") == ["BEGIN", [], "END"]
+        assert block.search_string("q BEGIN x END r BEGIN END") == [
+            ["BEGIN", ["x"], "END"],
+            ["BEGIN", [], "END"],
+        ]
+
+    def test_zero_or_more_composite(self):
+        pair = pp.Group(pp.Word(pp.alphas()) + pp.Literal(":") + pp.Word(pp.nums()))
+        many = pp.ZeroOrMore(pair)
+        assert many.parse_string("a:1 b : 22") == [["a", ":", "1"], ["b", ":", "22"]]
+        assert many.parse_string("") == []
+        assert many.parse_string("a: x") == []
+        alternation = pp.ZeroOrMore(pp.Regex(r"\d+") | pp.Keyword("and") | "&")
+        assert alternation.parse_string("1 and 2 & 3 or") == ["1", "and", "2", "&", "3"]
+        assert alternation.parse_string("or") == []
+
 class TestOneOrMore:
     def test_one_or_more_multiple(self):
         lit = pp.Literal("a")
@@ -59,6 +194,28 @@ class TestOneOrMore:
         with pytest.raises(ValueError):
             many.parse_string("bbbb")
 
+    def test_one_or_more_stop_on(self):
+        # Without stop_on, the statement Word would consume END
+        stmt = pp.Word(pp.alphas())
+        end = pp.Keyword("END")
+        body = pp.OneOrMore(stmt, stop_on=end)
+        block = pp.Keyword("BEGIN") + body + end
+        assert block.parse_string("BEGIN a b  c END", parse_all=True) == ["BEGIN", "a", "b", "c", "END"]
+        assert block.matches("BEGIN a END")
+        with pytest.raises(ValueError):
+            (pp.Keyword("BEGIN") + pp.OneOrMore(stmt) + end).parse_string("BEGIN a END")
+        with pytest.raises(ValueError):
+            block.parse_string("BEGIN END")
+        assert body.search_string("a b END") == [["a", "b"], ["ND"]]
+
+    def test_one_or_more_composite(self):
+        item = pp.Suppress(",") + pp.Word(pp.alphas())
+        assert pp.OneOrMore(item).parse_string(", a , b") == ["a", "b"]
+        nested = pp.OneOrMore(pp.Group(pp.OneOrMore("x") + ";"))
+        assert nested.parse_string("x x ; x ;") == [["x", "x", ";"], ["x", ";"]]
+        with pytest.raises(pp.ParseError):
+            pp.OneOrMore(pp.Group(pp.Literal("a") + "b")).parse_string("a c")
+
 class TestOptional:
     def test_optional_present(self):
         lit = pp.Literal("a")
@@ -72,6 +229,15 @@ class TestOptional:
         result = opt.parse_string("b")
         assert result == []
 
+    def test_optional_composite(self):
+        sign = pp.Optional(pp.Literal("+") | "-")
+        number = sign + pp.Word(pp.nums())
+        assert number.parse_string("-12") == ["-", "12"]
+        assert number.parse_string("12") == ["12"]
+        suffix = pp.Optional(pp.Group(pp.Keyword("as") + pp.Word(pp.alphas())))
+        assert (pp.Word(pp.alphas()) + suffix).parse_string("x as y") == ["x", ["as", "y"]]
+        assert pp.Optional("kw").parse_string("kw") == ["kw"]
+
 class TestSuppress:
     def test_suppress_no_output(self):
         lit = pp.Literal("hello")
@@ -131,6 +297,43 @@ class TestForward:
         result = fwd.transform_string("hello world hello", "hi")
         assert result == "hi world hi"
 
+class TestFreeze:
+    def nested(self):
+        expr = pp.Forward()
+        expr <<= pp.Word(pp.nums()) | pp.Group(pp.Suppress(pp.Literal("(")) + expr + pp.Suppress(pp.Literal(")")))
+        return expr
+
+    def test_set_after_freeze_raises(self):
+        fwd = pp.Forward()
+        fwd.set(pp.Literal("a"))
+        fwd.freeze()
+        with pytest.raises(ValueError, match="frozen"):
+            fwd.set(pp.Literal("b"))
+        with pytest.raises(ValueError, match="frozen"):
+            fwd <<= pp.Literal("b")
+        assert fwd.parse_string("a") == ["a"]
+
+    def test_freezing_parent_freezes_nested_forward(self):
+        inner = pp.Forward()
+        inner.set(pp.Literal("x"))
+        grammar = pp.Literal("(") + inner + pp.Literal(")")
+        assert not grammar.is_frozen()
+        assert grammar.freeze() is grammar
+        assert grammar.is_frozen() and inner.is_frozen()
+        with pytest.raises(ValueError):
+            inner.set(pp.Literal("y"))
+
+    def test_recursive_grammar_freezes(self):
+        expr = self.nested().freeze()
+        assert expr.is_frozen()
+        assert expr.parse_string("((7))") == [[["7"]]]
+
+    def test_frozen_batch_is_deterministic(self):
+        expr = self.nested().freeze()
+        inputs = ["1", "(2)", "((3))", "(4", "x"] * 200
+        first = expr.parse_batch(inputs)
+        assert all(expr.parse_batch(inputs) == first for _ in range(5))
+
 class TestCombine:
     def test_combine_basic(self):
         word = pp.Word(pp.alphas())
@@ -161,6 +364,74 @@ class TestCombine:
         count = expr.search_string_count("foo-1 bar-2 baz-3")
         assert count == 3
 
+    def test_combine_decimal_numbers(self):
+        number = pp.Combine(pp.Word(pp.nums()) + "." + pp.Word(pp.nums()))
+        assert number.parse_string("3.14") == ["3.14"]
+        assert number.search_string("pi 3.14, e 2.72, not 1 . 5") == [["3.14"], ["2.72"]]
+        with pytest.raises(pp.ParseException):
+            number.parse_string("3 .14")
+
+    def test_combine_dotted_identifiers(self):
+        ident = pp.Word(pp.alphas(), pp.alphanums())
+        dotted = pp.Combine(ident + pp.ZeroOrMore("." + ident))
+        assert dotted.parse_string("os.path.join") == ["os.path.join"]
+        assert dotted.parse_string("os .path", parse_all=False) == ["os"]
+        # Not adjacent: whitespace between parts is allowed but not kept
+        loose = pp.Combine(ident + pp.ZeroOrMore("." + ident), adjacent=False)
+        assert loose.parse_string("os . path .join") == ["os.path.join"]
+        # The dots dropped, then put back by join_string
+        parts = pp.Combine(
+            ident + pp.ZeroOrMore(pp.Suppress(".") + ident), join_string="::", adjacent=False
+        )
+        assert parts.parse_string("a.b . c") == ["a::b::c"]
+
+    def test_combine_with_suppress(self):
+        digits = pp.Word(pp.nums())
+        grouped = pp.Combine(digits + pp.ZeroOrMore(pp.Suppress(",") + digits))
+        assert grouped.parse_string("1,234,567") == ["1234567"]
+        assert grouped.search_string("cost 1,500 and 20") == [["1500"], ["20"]]
+        assert grouped.parse_batch(["1,000", "7"]) == [["1000"], ["7"]]
+
+    def test_combine_join_string(self):
+        word = pp.Word(pp.alphas())
+        spaced = pp.Combine(word + word + word, join_string="-", adjacent=False)
+        assert spaced.parse_string("a  b c") == ["a-b-c"]
+        # Adjacent with a join string: the text is split at the tokens
+        glued = pp.Combine(pp.Word(pp.nums()) + pp.Word(pp.alphas()), join_string="_")
+        assert glued.parse_string("12ab") == ["12_ab"]
+        with pytest.raises(pp.ParseException):
+            glued.parse_string("12 ab")
+
+    def test_combine_keeps_named_results_and_pickles(self):
+        num = pp.Word(pp.nums())
+        expr = pp.Combine(num("int") + pp.Suppress(".") + num("frac"), join_string="|")
+        result = expr.parse_string("3.25")
+        assert result == ["3|25"]
+        assert (result["int"], result["frac"]) == ("3", "25")
+        assert pickle.loads(pickle.dumps(expr)).parse_string("1.5") == ["1|5"]
+        loose = pickle.loads(pickle.dumps(pp.Combine(num + num, adjacent=False)))
+        assert loose.parse_string("1 5") == ["15"]
+
+class TestGroup:
+    def test_group_nests_tokens(self):
+        expr = pp.Group(pp.Word(pp.alphas()) + pp.Word(pp.nums()))
+        assert expr.parse_string("ab 12") == [["ab", "12"]]
+
+    def test_two_levels_under_zero_or_more(self):
+        pair = pp.Group(pp.Word(pp.alphas()) + pp.Word(pp.nums()))
+        expr = pp.ZeroOrMore(pp.Group(pair + pp.Word(pp.alphas())))
+        text = "a 1 x b 2 y"
+        expected = [[["a", "1"], "x"], [["b", "2"], "y"]]
+        assert expr.parse_string(text) == expected
+        assert expr.parse_batch([text, "c 3 z"]) == [expected, [[["c", "3"], "z"]]]
+        assert expr.search_string("a 1 x") == [[[["a", "1"], "x"]]]
+
+    def test_combine_and_suppress_of_groups(self):
+        word, num = pp.Word(pp.alphas()), pp.Word(pp.nums())
+        assert pp.Combine(pp.Group(word + pp.Group(num))).parse_string("a1") == ["a1"]
+        assert pp.Suppress(pp.Group(word)).parse_string("a") == []
+        assert pp.Group(word + pp.Suppress(num)).parse_string("a 1") == [["a"]]
+
 class TestExactly:
     def test_exactly_match(self):
         lit = pp.Literal("a")
@@ -192,5 +463,793 @@ class TestExactly:
         count = expr.search_string_count("aaabaaabaa")
         assert count == 2
 
+
+class TestRepeat:
+    def test_mul_exact_count(self):
+        expr = pp.Word(pp.nums(), exact=2) * 3
+        assert expr.parse_string("12 34 56 78") == ["12", "34", "56"]
+        with pytest.raises(ValueError):
+            expr.parse_string("12 34 56 78", parse_all=True)
+
+    def test_mul_range(self):
+        expr = pp.Literal("a") * (2, 3)
+        assert expr.parse_string("aaaa") == ["a", "a", "a"]
+        assert expr.parse_string("aa") == ["a", "a"]
+        with pytest.raises(ValueError):
+            expr.parse_string("ab")
+
+    def test_mul_open_ended(self):
+        expr = pp.Literal("a") * (1, None)
+        assert expr.parse_string("aaaaa") == ["a"] * 5
+        assert (pp.Literal("a") * (None, 2)).parse_string("b") == []
+
+    def test_rmul(self):
+        assert (2 * pp.Literal("a")).parse_string("aaa") == ["a", "a"]
+
+    def test_getitem(self):
+        word = pp.Word(pp.alphas())
+        assert word[2].parse_string("x y z") == ["x", "y"]
+        assert word[1, ...].parse_string("x y z") == ["x", "y", "z"]
+        assert word[...].parse_string("") == []
+
+    def test_repeat_class(self):
+        expr = pp.Repeat(pp.Literal("a"), 1, 2)
+        assert expr.parse_string("aaa") == ["a", "a"]
+
+    def test_invalid_bounds(self):
+        with pytest.raises(ValueError):
+            pp.Literal("a") * (3, 1)
+        with pytest.raises(ValueError):
+            pp.Literal("a") * -1
+
+
+class TestOriginalTextFor:
+    def test_original_text_keeps_whitespace(self):
+        call = pp.Word(pp.alphas()) + pp.Literal("(") + pp.Word(pp.alphas()) + pp.Literal(")")
+        expr = pp.original_text_for(call)
+        assert expr.parse_string("foo (  bar )") == ["foo (  bar )"]
+
+    def test_original_text_in_group_and_suppress(self):
+        body = pp.original_text_for(pp.Literal("{") + pp.Word(pp.alphas()) + pp.Literal("}"))
+        expr = pp.Suppress(pp.Keyword("fn")) + pp.Group(body)
+        assert expr.parse_string("fn { x }") == [["{ x }"]]
+
+    def test_original_text_locations(self):
+        expr = pp.Literal("a") + pp.original_text_for(
+            pp.Word(pp.nums()) + pp.Word(pp.nums()), as_string=False
+        )
+        assert expr.parse_string("a 12  34") == ["a", "2", "8"]
+
+    def test_original_text_mismatch(self):
+        expr = pp.original_text_for(pp.Literal("a") + pp.Literal("b"))
+        with pytest.raises(ValueError):
+            expr.parse_string("a c")
+
+
+class TestAtLeastN:
+    def clauses(self):
+        return [
+            pp.Keyword("color") + pp.Word(pp.alphas()),
+            pp.Keyword("size") + pp.Word(pp.nums()),
+            pp.Keyword("shape") + pp.Word(pp.alphas()),
+            pp.Keyword("weight") + pp.Word(pp.nums()),
+            pp.Keyword("label") + pp.QuotedString('"'),
+        ]
+
+    def test_two_clauses_any_order(self):
+        expr = pp.AtLeastN(self.clauses(), 2)
+        assert expr.parse_string("size 10 color red") == ["size", "10", "color", "red"]
+        assert expr.clauses_seen("size 10 color red") == [1, 0]
+        assert expr.parse_string("size 10 color red")["clauses_seen"] == [1, 0]
+
+    def test_three_clauses_scrambled(self):
+        expr = pp.AtLeastN(self.clauses(), 2)
+        result = expr.parse_string('label "x" weight 3 shape box')
+        assert result == ["label", "x", "weight", "3", "shape", "box"]
+        assert expr.clauses_seen('label "x" weight 3 shape box') == [4, 3, 2]
+
+    def test_under_threshold_lists_missing(self):
+        expr = pp.AtLeastN(self.clauses(), 2)
+        with pytest.raises(ValueError) as exc:
+            expr.parse_string("weight 5")
+        msg = str(exc.value)
+        assert "at least 2 of 5" in msg
+        for kw in ("color", "size", "shape", "label"):
+            assert f"keyword '{kw}'" in msg
+        assert "'weight'" not in msg
+
+    def test_each_clause_at_most_once(self):
+        expr = pp.AtLeastN(self.clauses(), 2)
+        with pytest.raises(ValueError):
+            expr.parse_string("size 1 size 2")
+
+    def test_at_most_n(self):
+        expr = pp.AtMostN(self.clauses(), 2)
+        assert expr.parse_string("shape box") == ["shape", "box"]
+        assert expr.parse_string("") == []
+        with pytest.raises(ValueError):
+            expr.parse_string("shape box size 1 color red")
+
+
+class TestMatchPrevious:
+    def test_match_previous_literal_repeated_word(self):
+        word = pp.Word(pp.alphas())
+        repeated = word + pp.match_previous_literal(word)
+        assert repeated.parse_string("foo foo") == ["foo", "foo"]
+
+    def test_match_previous_literal_different_word(self):
+        word = pp.Word(pp.alphas())
+        repeated = word + pp.match_previous_literal(word)
+        with pytest.raises(ValueError):
+            repeated.parse_string("foo bar")
+
+    def test_match_previous_literal_search(self):
+        word = pp.Word(pp.alphas())
+        repeated = word + pp.match_previous_literal(word)
+        assert repeated.search_string("the cat cat sat") == [["cat", "cat"]]
+
+    def test_match_previous_expr_same_tokens(self):
+        num = pp.Word(pp.nums())
+        pair = num + pp.Literal(":") + pp.match_previous_expr(num)
+        assert pair.parse_string("12:12") == ["12", ":", "12"]
+
+    def test_match_previous_expr_requires_whole_tokens(self):
+        num = pp.Word(pp.nums())
+        pair = num + pp.Literal(":") + pp.match_previous_expr(num)
+        # match_previous_literal would accept the "12" prefix of "123"
+        with pytest.raises(ValueError):
+            pair.parse_string("12:123")
+
+    def test_reference_matched_inside_a_wrapper(self):
+        word = pp.Word(pp.alphas())
+        grouped = pp.Group(word) + pp.match_previous_literal(word)
+        assert grouped.parse_string("ab ab") == [["ab"], "ab"]
+        named = word("tag") + pp.match_previous_literal(word)
+        assert named.parse_string("ab ab").as_dict() == {"tag": "ab"}
+        assert named.search_string("x ab ab y") == [["ab", "ab"]]
+        with pytest.raises(ValueError):
+            named.parse_string("ab cd")
+
+    def test_reference_matched_in_a_nested_sequence(self):
+        word = pp.Word(pp.alphas())
+        nested = pp.Group(word + "=") + pp.match_previous_expr(word)
+        assert nested.parse_string("ab = ab") == [["ab", "="], "ab"]
+        assert not nested.matches("ab = cd")
+        deeper = pp.Group(pp.Optional("*") + pp.Group(word)) + ":" + pp.match_previous_literal(word)
+        assert deeper.parse_string("* ab : ab") == [["*", ["ab"]], ":", "ab"]
+
+class TestResultsNames:
+    def test_call_shorthand(self):
+        expr = pp.Word(pp.alphas())("key") + pp.Literal("=") + pp.Word(pp.nums())("val")
+        res = expr.parse_string("x=1")
+        assert res == ["x", "=", "1"]
+        assert res["key"] == "x"
+        assert res["val"] == "1"
+        assert "key" in res and "other" not in res
+
+    def test_set_results_name(self):
+        expr = pp.Word(pp.alphas()).set_results_name("word") + pp.Word(pp.nums())
+        assert expr.parse_string("abc 12").as_dict() == {"word": "abc"}
+
+    def test_names_stay_inside_groups(self):
+        pair = pp.Group(pp.Word(pp.alphas())("key") + pp.Suppress(pp.Literal("=")) + pp.Word(pp.nums())("val"))
+        res = pp.OneOrMore(pair).parse_string("a=1 b=2")
+        assert res == [["a", "1"], ["b", "2"]]
+        assert (res[0]["key"], res[0]["val"]) == ("a", "1")
+        assert res[1].as_dict() == {"key": "b", "val": "2"}
+        assert "key" not in res and res.keys() == []
+        # A name on the group itself is the parent's; the last match wins
+        named = pp.OneOrMore(pair("pair")).parse_string("a=1 b=2")
+        assert named.keys() == ["pair"] and named["pair"]["key"] == "b"
+        nested = pp.Group(pp.Word(pp.alphas())("outer") + pp.Group(pp.Word(pp.nums())("inner")))
+        [group] = nested.parse_string("x 1")
+        assert group.keys() == ["outer"] and group[1]["inner"] == "1"
+
+    def test_repeated_names_last_wins(self):
+        item = pp.Word(pp.alphas())("item")
+        res = pp.ZeroOrMore(item).parse_string("a b c")
+        assert res["item"] == "c" and res.get("item") == "c"
+        assert res.as_dict() == {"item": "c"}
+
+    def test_list_all_matches(self):
+        nums = pp.OneOrMore(pp.Word(pp.nums()).set_results_name("n", list_all_matches=True))
+        assert nums.parse_string("1 2 3")["n"] == ["1", "2", "3"]
+        assert pp.OneOrMore(pp.Word(pp.nums())("n*")).parse_string("4 5")["n"] == ["4", "5"]
+        # Each match of a multi-token value is kept whole
+        pairs = pp.OneOrMore((pp.Word(pp.alphas()) + pp.Word(pp.nums()))("p*")).parse_string("a 1 b 2")
+        assert pairs["p"] == [["a", "1"], ["b", "2"]]
+
+    def test_list_all_matches_and_plain_entries(self):
+        word, num = pp.Word(pp.alphas()), pp.Word(pp.nums())
+        res = (pp.OneOrMore(word("x*")) + num("x")).parse_string("a b 1")
+        assert res["x"] == "1"
+        res = (num("x") + pp.OneOrMore(word("x*"))).parse_string("1 a b")
+        assert res["x"] == ["a", "b"]
+        res = (word("first") + pp.OneOrMore(num("n*")) + word("last")).parse_string("a 1 2 z")
+        assert res.keys() == ["first", "n", "last"]
+        assert res.as_dict() == {"first": "a", "n": ["1", "2"], "last": "z"}
+        assert res.get("n") == ["1", "2"] and res.get("missing", 0) == 0
+
+    def test_as_dict_converts_nested_results(self):
+        word, num = pp.Word(pp.alphas()), pp.Word(pp.nums())
+        entry = pp.Group(word("k") + num("v"))
+        res = pp.OneOrMore(entry("entry*")).parse_string("a 1 b 2")
+        assert res.as_dict() == {"entry": [{"k": "a", "v": "1"}, {"k": "b", "v": "2"}]}
+        res = pp.Group(word("k") + pp.Group(num + num)("pair"))("g").parse_string("a 1 2")
+        d = res.as_dict()
+        assert d == {"g": {"k": "a", "pair": ["1", "2"]}}
+        assert type(d["g"]) is dict and type(d["g"]["pair"]) is list
+
+    def test_names_on_empty_matches_left_out(self):
+        word, num = pp.Word(pp.alphas()), pp.Word(pp.nums())
+        res = (pp.Optional(word)("o") + num("n")).parse_string("1")
+        assert res.as_dict() == {"n": "1"} and "o" not in res
+        res = (pp.Empty()("e") + num("n")).parse_string("1")
+        assert res.as_dict() == {"n": "1"} and res.keys() == ["n"]
+        res = (pp.ZeroOrMore(word)("z") + num("n")).parse_string("1")
+        assert res.as_dict() == {"n": "1"}
+        res = (pp.ZeroOrMore(word)("z*") + num("n")).parse_string("1")
+        assert res.as_dict() == {"n": "1"}
+        # A match with tokens is still named, and an empty group is a token
+        assert (pp.Optional(word)("o") + num("n")).parse_string("a 1").as_dict() == {"o": "a", "n": "1"}
+        assert "g" in pp.Group(pp.Optional(word))("g").parse_string("")
+
+    def test_multi_token_value_and_unnamed_results(self):
+        expr = (pp.Word(pp.alphas()) + pp.Word(pp.nums()))("pair")
+        assert expr.parse_string("ab 12")["pair"] == ["ab", "12"]
+        assert (pp.Word(pp.alphas()) + pp.Word(pp.nums())).parse_string("ab 12") == ["ab", "12"]
+
+    def test_named_element_matches_like_inner(self):
+        named = pp.Word(pp.nums())("n")
+        assert named.name == "n"
+        assert named.search_string("a 1 b 22") == [["1"], ["22"]]
+
+class TestParseResultsObject:
+    def test_returned_from_every_entry_point(self):
+        word = pp.Word(pp.alphas())
+        for expr in (word, pp.Literal("ab"), word + word, pp.Optional(word), pp.Suppress(word), pp.Group(word)):
+            assert isinstance(expr.parse_string("ab cd"), pp.ParseResults)
+            assert all(isinstance(r, pp.ParseResults) for r in expr.search_string("ab cd"))
+            assert all(isinstance(r, pp.ParseResults) for r, _, _ in expr.scan_string("ab cd"))
+
+    def test_behaves_like_token_list(self):
+        res = (pp.Word(pp.alphas()) + pp.Word(pp.nums())).parse_string("ab 12")
+        assert res == ["ab", "12"] and ["ab", "12"] == res
+        assert res != ["ab"]
+        assert len(res) == 2 and list(res) == ["ab", "12"]
+        assert res[0] == "ab" and res[-1] == "12" and res[1:] == ["12"]
+        assert "ab" in res
+        assert str(res) == "['ab', '12']"
+        assert res.as_list() == ["ab", "12"] and type(res.as_list()) is list
+        assert res.keys() == [] and res.as_dict() == {}
+        with pytest.raises(KeyError):
+            res["missing"]
+
+    def test_named_access(self):
+        expr = pp.Word(pp.alphas())("key") + pp.Word(pp.nums())("val")
+        res = expr.parse_string("x 1")
+        assert res.get("key") == "x"
+        assert res.get("other") is None and res.get("other", 0) == 0
+        assert res.keys() == ["key", "val"]
+        assert res.as_dict() == {"key": "x", "val": "1"}
+        assert repr(res) == "ParseResults(['x', '1'], {'key': 'x', 'val': '1'})"
+
+    def test_search_string_keeps_names(self):
+        expr = pp.Word(pp.alphas())("key") + pp.Word(pp.nums())("val")
+        found = expr.search_string("a 1 b 2")
+        assert found == [["a", "1"], ["b", "2"]]
+        assert [r["key"] for r in found] == ["a", "b"]
+
+    def test_dump(self):
+        expr = pp.Word(pp.nums())("val") + pp.Word(pp.alphas())("key")
+        assert expr.parse_string("1 x").dump() == "['1', 'x']\n- key: 'x'\n- val: '1'"
+        assert pp.Word(pp.alphas()).parse_string("ab").dump() == "['ab']"
+
+class TestParseActions:
+    def test_action_replaces_tokens(self):
+        num = pp.Word(pp.nums()).set_parse_action(lambda t: int(t[0]))
+        assert (num + pp.Literal("+") + num).parse_string("1 + 22") == [1, "+", 22]
+        assert num.search_string("a 1 b 22") == [[1], [22]]
+
+    def test_signature_detection(self):
+        seen = []
+        word = pp.Word(pp.alphas())
+        word.set_parse_action(lambda s, loc, t: seen.append((s, loc, t))).parse_string("  ab")
+        word.set_parse_action(lambda loc, t: seen.append((loc, t))).parse_string("cd")
+        word.set_parse_action(lambda: seen.append("none")).parse_string("ef")
+        assert seen == [("  ab", 2, ["ab"]), (0, ["cd"]), "none"]
+
+    def test_return_values(self):
+        word = pp.Word(pp.alphas())
+        assert word.set_parse_action(lambda t: None).parse_string("ab") == ["ab"]
+        assert word.set_parse_action(lambda t: [t[0], t[0].upper()]).parse_string("ab") == ["ab", "AB"]
+        node = object()
+        assert word.set_parse_action(lambda t: node).parse_string("ab")[0] is node
+
+    def test_add_parse_action_chains(self):
+        num = pp.Word(pp.nums()).set_parse_action(lambda t: int(t[0]))
+        assert num.add_parse_action(lambda t: t[0] * 10).parse_string("4") == [40]
+        assert num.set_parse_action(lambda t: "x").parse_string("4") == ["x"]
+
+    def test_parse_error_rejects_match(self):
+        def small(t):
+            if int(t[0]) > 255:
+                raise pp.ParseError("too big")
+        octet = pp.Word(pp.nums()).set_parse_action(small)
+        assert (octet | pp.Word(pp.alphanums())).parse_string("300") == ["300"]
+        with pytest.raises(pp.ParseError):
+            octet.parse_string("300")
+
+    def test_other_exceptions_propagate(self):
+        def boom(t):
+            raise KeyError("boom")
+        failing = pp.Word(pp.nums()).set_parse_action(boom)
+        for call in (
+            lambda: failing.parse_string("1"),
+            lambda: (failing | pp.Word(pp.alphanums())).parse_string("1"),
+            lambda: (pp.Literal("a") + failing).parse_string("a 1"),
+            lambda: failing.search_string("x 1"),
+            lambda: failing.parse_batch(["1"]),
+        ):
+            with pytest.raises(KeyError):
+                call()
+        # Nothing is left pending for the next parse
+        assert pp.Word(pp.nums()).parse_string("1") == ["1"]
+
+    def test_batch_runs_actions_per_item(self):
+        calls = []
+        num = pp.Word(pp.nums()).set_parse_action(lambda t: calls.append(t[0]) or int(t[0]))
+        assert num.parse_batch(["1", "x", "1"]) == [[1], [], [1]]
+        assert calls == ["1", "1"]
+
+class TestConditions:
+    def test_octet_condition(self):
+        octet = pp.Word(pp.nums()).add_condition(lambda t: int(t[0]) <= 255, message="octet > 255")
+        assert octet.parse_string("255") == ["255"]
+        with pytest.raises(pp.ParseError) as exc:
+            octet.parse_string("  256")
+        assert exc.value.msg == "octet > 255"
+        assert exc.value.loc == 2
+
+    def test_default_message(self):
+        with pytest.raises(pp.ParseError, match="failed user-defined condition"):
+            pp.Word(pp.nums()).add_condition(lambda t: False).parse_string("1")
+
+    def test_match_first_falls_through(self):
+        octet = pp.Word(pp.nums(), as_keyword=True).add_condition(lambda t: int(t[0]) <= 255)
+        expr = octet | pp.Word(pp.alphanums())
+        assert expr.parse_string("300") == ["300"]
+        assert expr.matches("300")
+        # Match-only scans honour the condition too
+        assert octet.search_string("1 300 20") == [["1"], ["20"]]
+        assert octet.search_string_count("1 300 20") == 2
+
+    def test_failed_branch_keeps_committed_results(self):
+        even = pp.Word(pp.nums()).add_condition(lambda t: int(t[0]) % 2 == 0)
+        pair = pp.Literal("a") + pp.MatchFirst(
+            [even + pp.Literal("!"), pp.Word(pp.nums()) + pp.Literal("?")]
+        )
+        assert pair.parse_string("a 3 ?") == ["a", "3", "?"]
+        assert pair.parse_string("a 4 !") == ["a", "4", "!"]
+        seq = pp.Group(pp.Literal("x") + even) | pp.Group(pp.Literal("x") + pp.Word(pp.nums()))
+        assert seq.parse_string("x 3") == [["x", "3"]]
+
+    def test_conditions_chain(self):
+        num = pp.Word(pp.nums()).add_condition(lambda t: int(t[0]) > 1).add_condition(lambda t: int(t[0]) < 9)
+        assert num.search_string("1 5 9") == [["5"]]
+
+    def test_condition_exceptions_propagate(self):
+        def boom(t):
+            raise KeyError("boom")
+        with pytest.raises(KeyError):
+            pp.Word(pp.nums()).add_condition(boom).parse_string("1")
+        with pytest.raises(ValueError):
+            pp.Word(pp.nums()).add_condition(42)
+
+class TestNativeTransforms:
+    def test_to_int_and_float(self):
+        ints = pp.OneOrMore(pp.Word(pp.nums()).to_int())
+        result = ints.parse_string("1 22 333")
+        assert result == [1, 22, 333]
+        assert all(type(v) is int for v in result)
+        num = pp.Word(pp.nums() + ".e-").to_float()
+        assert num.parse_string("1.5e3") == [1500.0]
+        assert pp.common.integer.to_float().parse_string("7") == [7.0]
+
+    def test_bad_conversion_fails_at_token(self):
+        expr = pp.Word(pp.alphas()) + pp.Word(pp.nums() + ".").to_float()
+        with pytest.raises(pp.ParseError) as exc:
+            expr.parse_string("x 1.2.3")
+        assert exc.value.loc == 2
+        with pytest.raises(pp.ParseError, match="integer"):
+            pp.Word(pp.nums()).to_int().parse_string("99999999999999999999999")
+        # Alternatives move on when the conversion fails
+        value = pp.Word(pp.nums()).to_int() | pp.Word(pp.nums())
+        assert value.parse_string("99999999999999999999999") == ["99999999999999999999999"]
+
+    def test_text_transforms(self):
+        quoted = pp.Regex("'[^']*'") | pp.Regex('"[^"]*"')
+        assert quoted.strip_quotes().parse_string("'a b'") == ["a b"]
+        assert quoted.strip_quotes().parse_string('"c"') == ["c"]
+        assert pp.Word(pp.alphas()).upcase().parse_string("abc") == ["ABC"]
+        assert pp.Word(pp.alphas()).downcase().parse_string("AbC") == ["abc"]
+        assert pp.Regex(r"[^,]+").strip().parse_string("  x y  ,") == ["x y"]
+        words = pp.OneOrMore(pp.Word(pp.alphas()))
+        assert words.join().parse_string("a b c") == ["abc"]
+        assert words.join("-").parse_string("a b c") == ["a-b-c"]
+
+    def test_named_values_transformed(self):
+        result = pp.Word(pp.nums())("n").to_int().parse_string("12")
+        assert result["n"] == 12
+        result = (pp.Literal("x") + pp.Word(pp.alphas())("name")).upcase().parse_string("x y")
+        assert result == ["X", "Y"] and result["name"] == "Y"
+
+    def test_batch_and_pickle(self):
+        import pickle
+        num = pp.Word(pp.nums()).to_int()
+        assert num.parse_batch(["1", "22"]) == [[1], [22]]
+        assert pickle.loads(pickle.dumps(num)).parse_string("5") == [5]
+        assert pp.Transform(pp.Word(pp.alphas()), "join", sep="+").parse_string("ab") == ["ab"]
+        with pytest.raises(pp.PatternError):
+            pp.Transform(pp.Word(pp.alphas()), "reverse")
+
+class TestParseAllAlternatives:
+    def date_grammar(self):
+        n2 = lambda: pp.Word(pp.nums(), exact=2)
+        sl = pp.Literal("/")
+        return pp.MatchFirst([
+            pp.Group(n2()("month") + sl + n2()("day") + sl + n2()("year")),
+            pp.Group(n2()("day") + sl + n2()("month") + sl + n2()("year")),
+            pp.Group(n2()("year") + sl + n2()("month") + sl + n2()("day")),
+        ])
+
+    def test_ambiguous_date_ranked_deterministically(self):
+        parses = self.date_grammar().parse_all_alternatives("01/02/03")
+        assert [choices for _, _, _, choices in parses] == [[0], [1], [2]]
+        assert [r[0]["year"] for r, _, _, _ in parses] == ["03", "03", "01"]
+        assert all(end == 8 and score == 8 for _, end, score, _ in parses)
+        assert self.date_grammar().parse_all_alternatives("01/02/03") == parses
+
+    def test_max_parses_bounds_results(self):
+        parses = self.date_grammar().parse_all_alternatives("01/02/03", max_parses=2)
+        assert [choices for _, _, _, choices in parses] == [[0], [1]]
+
+    def test_unambiguous_input_has_one_parse(self):
+        grammar = pp.MatchFirst([
+            pp.Word(pp.nums(), exact=4) + pp.Literal("-") + pp.Word(pp.nums(), exact=2),
+            pp.Word(pp.nums(), exact=2) + pp.Literal("/") + pp.Word(pp.nums(), exact=2),
+        ])
+        assert grammar.parse_all_alternatives("2024-05") == [(["2024", "-", "05"], 7, 7, [0])]
+        assert grammar.parse_all_alternatives("nope") == []
+
+    def test_optional_skips_lower_score(self):
+        grammar = pp.MatchFirst([
+            pp.Word(pp.alphas()) + pp.Optional(pp.Word(pp.nums())),
+            pp.Word(pp.alphanums()),
+        ])
+        assert grammar.parse_all_alternatives("ab") == [(["ab"], 2, 2, [1]), (["ab"], 2, 1, [0])]
+
+    def test_nested_choice_points(self):
+        sign = pp.MatchFirst([pp.Literal("+"), pp.Literal("+")])
+        grammar = pp.MatchFirst([sign + pp.Word(pp.nums()), pp.Word(pp.printables())])
+        parses = grammar.parse_all_alternatives("+1")
+        assert [choices for _, _, _, choices in parses] == [[0, 0], [0, 1], [1]]
+
+class TestSuppressMethod:
+    def test_key_value(self):
+        expr = pp.Word(pp.alphas()) + pp.Literal("=").suppress() + pp.Word(pp.nums())
+        assert expr.parse_string("x = 42") == ["x", "42"]
+
+    def test_every_element_has_suppress(self):
+        word = pp.Word(pp.alphas())
+        for elem, text in (
+            (pp.Literal("a"), "a"), (word, "ab"), (pp.Regex("a+"), "aa"), (pp.Keyword("a"), "a"),
+            (word + word, "a b"), (pp.MatchFirst([word]), "a"), (pp.Group(word), "a"),
+            (pp.Optional(word), "a"), (pp.ZeroOrMore(word), "a b"), (pp.OneOrMore(word), "a b"),
+        ):
+            sup = elem.suppress()
+            assert isinstance(sup, pp.Suppress)
+            assert (sup + pp.Literal("!")).parse_string(text + " !") == ["!"]
+
+    def test_suppress_accepts_any_element_and_strings(self):
+        assert pp.Suppress(pp.Group(pp.Word(pp.nums()))).parse_string("12") == []
+        assert (pp.Suppress("(") + pp.Word(pp.nums()) + pp.Suppress(")")).parse_string("(7)") == ["7"]
+
+
+def operand_samples():
+    """One instance of every exported element class."""
+    lit = pp.Literal("a")
+    word = pp.Word(pp.alphas())
+    fwd = pp.Forward()
+    fwd <<= lit
+    return {
+        "Literal": lit,
+        "Word": word,
+        "Regex": pp.Regex(r"\d+"),
+        "Keyword": pp.Keyword("if"),
+        "CaselessLiteral": pp.CaselessLiteral("a"),
+        "CaselessKeyword": pp.CaselessKeyword("if"),
+        "Char": pp.Char("abc"),
+        "And": lit + word,
+        "MatchFirst": pp.MatchFirst([lit, word]),
+        "Group": pp.Group(word),
+        "Dict": pp.Dict(pp.Group(word + word)),
+        "Suppress": pp.Suppress(lit),
+        "ZeroOrMore": pp.ZeroOrMore(word),
+        "OneOrMore": pp.OneOrMore(word),
+        "Optional": pp.Optional(word),
+        "Forward": fwd,
+        "Combine": pp.Combine(lit + word),
+        "OriginalTextFor": pp.OriginalTextFor(lit + word),
+        "Located": pp.Located(word),
+        "AsLine": pp.AsLine(word),
+        "IndentedBlock": pp.IndentedBlock(word),
+        "AtLeastN": pp.AtLeastN([lit, word], 1),
+        "AtMostN": pp.AtMostN([lit, word], 1),
+        "Exactly": pp.Exactly(lit, 2),
+        "Repeat": pp.Repeat(lit, 1, 2),
+        "StringStart": pp.StringStart(),
+        "StringEnd": pp.StringEnd(),
+        "LineStart": pp.LineStart(),
+        "LineEnd": pp.LineEnd(),
+        "RestOfLine": pp.RestOfLine(),
+        "Empty": pp.Empty(),
+        "NoMatch": pp.NoMatch(),
+        "SkipTo": pp.SkipTo(lit),
+        "QuotedString": pp.QuotedString('"'),
+        "MatchPreviousLiteral": pp.MatchPreviousLiteral(word),
+        "MatchPreviousExpr": pp.MatchPreviousExpr(word),
+        "Named": word("name"),
+        "HighlightClass": word.highlight_class("kw"),
+        "Comment": pp.c_style_comment(),
+        "Number": pp.Number(),
+        "Ipv6Address": pp.Ipv6Address(),
+        "ParseAction": word.set_parse_action(lambda t: t),
+        "Condition": word.add_condition(lambda t: True),
+        "DebugTrace": word.set_debug(),
+        "WhitespaceScope": word.leave_whitespace(),
+    }
+
+
+class TestOperatorMatrix:
+    def test_every_pair_composes_with_add(self):
+        samples = operand_samples()
+        for (ln, left), (rn, right) in itertools.product(samples.items(), repeat=2):
+            assert isinstance(left + right, pp.And), (ln, rn)
+
+    def test_every_pair_composes_with_or(self):
+        samples = operand_samples()
+        for (ln, left), (rn, right) in itertools.product(samples.items(), repeat=2):
+            assert isinstance(left | right, pp.MatchFirst), (ln, rn)
+
+    def test_mixed_operands_parse(self):
+        expr = pp.Literal("if") + pp.Optional(pp.Literal("not")) + pp.Keyword("done")
+        assert expr.parse_string("if not done") == ["if", "not", "done"]
+        assert expr.parse_string("if done") == ["if", "done"]
+        alt = (pp.Literal("x") + pp.Literal("y")) | pp.Group(pp.Word(pp.nums()))
+        assert alt.parse_string("12") == [["12"]]
+
+    def test_string_operands_are_literals(self):
+        expr = pp.Word(pp.alphas()) + "=" + pp.Word(pp.nums())
+        assert expr.parse_string("x = 1") == ["x", "=", "1"]
+        assert (pp.Keyword("on") | "off").parse_string("off") == ["off"]
+        assert pp.Group("a").parse_string("a") == [["a"]]
+
+    def test_reflected_string_operands(self):
+        num = pp.Word(pp.nums())
+        parens = "(" + num + ")"
+        assert len(parens) == 3
+        assert parens.parse_string("( 3 )") == ["(", "3", ")"]
+        assert ("on" | pp.Keyword("off")).parse_string("on") == ["on"]
+        assert len("a" | pp.MatchFirst(["b", "c"])) == 3
+        guarded = "(" + (num - ")")
+        with pytest.raises(pp.ParseFatalError):
+            pp.MatchFirst([guarded, num]).parse_string("(1")
+
+    def test_string_constructor_arguments(self):
+        assert pp.MatchFirst(["a", "b"]).parse_string("b") == ["b"]
+        assert pp.Optional("a").parse_string("b") == []
+        assert pp.OneOrMore("a").parse_string("a a") == ["a", "a"]
+        assert pp.ZeroOrMore("a", stop_on="b").parse_string("a a b") == ["a", "a"]
+
+    def test_empty_string_operand(self):
+        word = pp.Word(pp.alphas())
+        for build in [
+            lambda: word + "",
+            lambda: "" + word,
+            lambda: word | "",
+            lambda: "" | word,
+            lambda: word - "",
+            lambda: pp.MatchFirst(["a", ""]),
+            lambda: pp.Group(""),
+            lambda: pp.Suppress(""),
+            lambda: pp.ZeroOrMore(""),
+        ]:
+            with pytest.raises(ValueError, match="empty string"):
+                build()
+
+    def test_unsupported_operand(self):
+        with pytest.raises(ValueError):
+            pp.Literal("a") + 1
+        with pytest.raises(ValueError):
+            pp.Literal("a") | None
+
+
+class TestParserElementBase:
+    def test_every_element_is_a_parser_element(self):
+        for name, sample in operand_samples().items():
+            assert isinstance(sample, pp.ParserElement), name
+            assert type(sample).__name__ == name
+
+    def test_shared_methods(self):
+        for name, sample in operand_samples().items():
+            for method in ["parse_string", "search_string", "parse_batch", "scan_string", "scan_iter",
+                           "set_results_name", "suppress", "set_parse_action"]:
+                assert callable(getattr(sample, method)), (name, method)
+        skip = pp.SkipTo(pp.Literal(";"))
+        assert skip.search_string("a;b;") == [["a"], ["b"]]
+        assert skip("body").parse_string("x y;")["body"] == "x y"
+
+    def test_search_string_max_matches(self):
+        text = "a aa 1 a, b a"
+        for name, sample in operand_samples().items():
+            # The DebugTrace sample prints each attempt
+            with contextlib.redirect_stdout(io.StringIO()):
+                found = sample.search_string(text)
+                for n in range(len(found) + 2):
+                    assert sample.search_string(text, max_matches=n) == found[:n], name
+
+    def test_generic_code_over_elements(self):
+        def names(expr):
+            return [type(e).__name__ for e in expr.exprs if isinstance(e, pp.ParserElement)]
+        expr = pp.Literal("a") + pp.Optional("b") + pp.Group(pp.Word(pp.nums()))
+        assert names(expr) == ["Literal", "Optional", "Group"]
+
+    def test_base_is_not_constructible(self):
+        with pytest.raises(TypeError):
+            pp.ParserElement()
+
+
+def outcome(expr, text):
+    try:
+        return expr.parse_string(text)
+    except ValueError as e:
+        return str(e)
+
+
+class TestPickle:
+    INPUTS = ["a", "abc 12", "if x", "aa", '"q"', "/* c */", "1.5", "::1", ""]
+
+    def test_every_element_round_trips(self):
+        for name, sample in operand_samples().items():
+            if name in ("ParseAction", "Condition"):
+                continue
+            for clone in (pickle.loads(pickle.dumps(sample)), copy.deepcopy(sample)):
+                assert type(clone) is type(sample), name
+                # The DebugTrace sample prints each attempt
+                with contextlib.redirect_stdout(io.StringIO()):
+                    for text in self.INPUTS:
+                        assert outcome(clone, text) == outcome(sample, text), (name, text)
+
+    def test_parameters_survive(self):
+        word = pp.Word(pp.alphas(), pp.alphanums(), min=2, max=4, exclude_chars="x")
+        expr = word("key") + pp.Suppress("=") + pp.Regex(r"\d+")("val")
+        clone = pickle.loads(pickle.dumps(expr))
+        res = clone.parse_string("ab1=12")
+        assert res == ["ab1", "12"] and res["key"] == "ab1" and res["val"] == "12"
+        for text in ["a=1", "abcde=1", "ax=1"]:
+            assert outcome(clone, text) == outcome(expr, text)
+        quoted = pp.QuotedString("'", esc_char="\\", unquote=True)
+        assert pickle.loads(pickle.dumps(quoted)).parse_string("'a\\'b'") == ["a'b"]
+
+    def test_recursive_grammar(self):
+        expr = pp.Forward()
+        expr <<= pp.Word(pp.nums()) | pp.Group(pp.Suppress("(") + expr + pp.Suppress(")"))
+        expr.freeze()
+        clone = pickle.loads(pickle.dumps(expr))
+        assert clone.parse_string("((1))") == expr.parse_string("((1))")
+        with pytest.raises(ValueError, match="frozen"):
+            clone <<= pp.Literal("x")
+
+    def test_shared_subexpressions_stay_shared(self):
+        word = pp.Word(pp.alphas())
+        _, (state,) = (word + word).__reduce__()
+        version, nodes = state
+        assert [kind for kind, _ in nodes] == ["And", "Word"]
+        assert nodes[0][1][0] == (1, 1)
+        clone = pickle.loads(pickle.dumps(word + word))
+        assert clone.parse_string("a b") == ["a", "b"]
+
+    def test_callbacks_refuse_to_pickle(self):
+        word = pp.Word(pp.nums())
+        with pytest.raises(TypeError, match="parse actions"):
+            pickle.dumps(word.set_parse_action(lambda t: int(t[0])) + word)
+        with pytest.raises(TypeError, match="conditions"):
+            copy.deepcopy(word.add_condition(lambda t: True))
+
+    def test_bad_state_is_rejected(self):
+        with pytest.raises(ValueError, match="version"):
+            pp._rebuild_element((99, ()))
+        with pytest.raises(ValueError, match="unknown element kind"):
+            pp._rebuild_element((1, (("Nope", ()),)))
+
+class TestCopy:
+    def test_every_element_copies(self):
+        for name, sample in operand_samples().items():
+            clone = sample.copy()
+            assert type(clone) is type(sample) and clone is not sample, name
+            # The DebugTrace sample prints each attempt
+            with contextlib.redirect_stdout(io.StringIO()):
+                for text in TestPickle.INPUTS:
+                    assert outcome(clone, text) == outcome(sample, text), (name, text)
+
+    def test_copies_take_different_results_names(self):
+        word = pp.Word(pp.nums())
+        expr = word.copy()("start") + pp.Suppress("-") + word.copy()("end")
+        res = expr.parse_string("10-20")
+        assert res == ["10", "20"]
+        assert res["start"] == "10" and res["end"] == "20"
+        assert "start" not in word.parse_string("5")
+
+    def test_configuring_a_copy_leaves_the_original(self):
+        integer = pp.Word(pp.nums())
+        as_int = integer.copy().set_parse_action(lambda t: int(t[0]))
+        assert as_int.parse_string("42") == [42]
+        assert integer.parse_string("42") == ["42"]
+
+    def test_copied_forward_is_redefined_separately(self):
+        expr = pp.Forward()
+        expr <<= pp.Literal("a")
+        clone = expr.copy()
+        assert clone.parse_string("a") == ["a"]
+        clone <<= pp.Literal("b")
+        assert clone.parse_string("b") == ["b"]
+        assert expr.parse_string("a") == ["a"]
+        with pytest.raises(ValueError):
+            expr.parse_string("b")
+
+    def test_copy_is_a_distinct_element(self):
+        # Backreferences follow the element they were built from, not its copies
+        word = pp.Word(pp.alphas())
+        clone = word.copy()
+        expr = word + clone + pp.MatchPreviousLiteral(word)
+        assert expr.parse_string("ab cd ab") == ["ab", "cd", "ab"]
+        with pytest.raises(ValueError):
+            expr.parse_string("ab cd cd")
+
+
+class TestRepr:
+    def test_every_element_describes_its_structure(self):
+        for name, sample in operand_samples().items():
+            assert repr(sample).startswith(name + "("), name
+            assert "object at" not in repr(sample) and str(sample), name
+
+    def test_pyparsing_style_names(self):
+        lit = pp.Literal("if")
+        word = pp.Word("abc")
+        assert str(lit) == "'if'" and repr(lit) == "Literal('if')"
+        assert str(word) == "W:(abc...)"
+        assert str(lit + word) == "{'if' W:(abc...)}"
+        assert str(lit | word | pp.Literal("x")) == "{'if' | W:(abc...) | 'x'}"
+        assert str(pp.ZeroOrMore(word)) == "[W:(abc...)]..."
+        assert str(pp.OneOrMore(word)) == "{W:(abc...)}..."
+        assert str(pp.Optional(lit)) == "['if']"
+        assert str(pp.Group(word)) == "Group:(W:(abc...))"
+        assert repr(pp.Group(word)) == "Group(W:(abc...))"
+        assert repr(pp.StringEnd()) == "StringEnd()"
+        assert str(word("key")) == "W:(abc...)"
+
+    def test_recursive_forward_terminates(self):
+        expr = pp.Forward()
+        assert str(expr) == "Forward: None"
+        expr <<= pp.Literal("(") + pp.Optional(expr) + pp.Literal(")")
+        assert str(expr) == "Forward: {'(' [Forward: ...] ')'}"
+        assert repr(expr) == "Forward({'(' [Forward: ...] ')'})"
+        a, b = pp.Forward(), pp.Forward()
+        a <<= pp.Literal("a") + b
+        b <<= pp.Literal("b") | a
+        assert str(a) == "Forward: {'a' Forward: {'b' | Forward: ...}}"
+
+
 if __name__ == "__main__":
     pytest.main([__file__, "-v"])
@@ -6,6 +6,14 @@ Tests cover: empty strings, unicode, long inputs, nested combinators,
 boundary conditions, operator overloading, search_string correctness,
 batch operations, and cross-validation against pyparsing.
 """
+import contextlib
+import ctypes
+import io
+import signal
+import sys
+import threading
+import time
+
 import pytest
 import pyparsing_rs as pp
 
@@ -29,6 +37,21 @@ class TestEmptyStringHandling:
         with pytest.raises(ValueError):
             word.parse_string("")
 
+    def test_empty_match_string_rejected(self):
+        """Literal and Keyword variants can't be built from an empty string."""
+        for cls in (pp.Literal, pp.Keyword, pp.CaselessLiteral, pp.CaselessKeyword):
+            with pytest.raises(pp.PatternError, match="non-empty match string"):
+                cls("")
+        with pytest.raises(ValueError):
+            pp.Keyword("", caseless=True)
+
+    def test_empty_word_chars_rejected(self):
+        """Word needs at least one initial character."""
+        with pytest.raises(ValueError, match="non-empty character set"):
+            pp.Word("")
+        with pytest.raises(ValueError, match="non-empty character set"):
+            pp.Word("", "abc")
+
     def test_regex_star_parse_empty_string(self):
         """Regex r'.*' on empty string should produce a zero-length match."""
         regex = pp.Regex(r".*")
@@ -302,7 +325,7 @@ class TestNestedCombinators:
         outer = pp.ZeroOrMore(inner)
         result = outer.parse_string("aaa")
         # The exact behavior may vary. Just verify it doesn't crash or hang.
-        assert isinstance(result, list)
+        assert isinstance(result, pp.ParseResults)
 
     def test_optional_nested(self):
         """Optional(Optional(x)) should behave like Optional(x)."""
@@ -442,6 +465,33 @@ class TestBoundaryConditions:
         assert parser.matches("hello there") is False
         assert parser.matches("") is False
 
+    def test_matches_agrees_with_parse_string(self):
+        """matches() succeeds exactly where parse_string() does."""
+        def tagged(toks):
+            return ["tag"]
+
+        grammars = [
+            pp.OneOrMore(pp.Optional(pp.Literal("x"))),
+            pp.OneOrMore(pp.Empty()),
+            pp.Group(pp.OneOrMore(pp.Optional(pp.Literal("x")))),
+            pp.Literal("a") + pp.OneOrMore(pp.Optional(pp.Literal("x"))),
+            pp.ZeroOrMore(pp.Word(pp.nums())),
+            pp.Optional(pp.OneOrMore(pp.Optional(pp.Word(pp.nums())))),
+            pp.Word(pp.nums()) | pp.Empty(),
+            pp.Literal("x").add_parse_action(tagged) + pp.Optional(pp.Literal("y")),
+        ]
+        inputs = ["", "x", "y", " ", "xx", "x y", "a", "a x", "1 2", " 1"]
+        for grammar in grammars:
+            for text in inputs:
+                for parse_all in (False, True):
+                    try:
+                        grammar.parse_string(text, parse_all=parse_all)
+                        parsed = True
+                    except pp.ParseException:
+                        parsed = False
+                    assert grammar.matches(text, parse_all=parse_all) is parsed, (
+                        grammar, text, parse_all)
+
 
 # ============================================================================
 # f. Operator overloading
@@ -617,8 +667,8 @@ class TestSearchStringCorrectness:
         regex = pp.Regex(r"\d+")
         text = "a1b22c333"
         result = regex.search_string(text)
-        # Each result is a list containing the match
-        values = [r[0] if isinstance(r, list) else r for r in result]
+        # Each result is a ParseResults containing the match
+        values = [r[0] if isinstance(r, pp.ParseResults) else r for r in result]
         assert "1" in values
         assert "22" in values
         assert "333" in values
@@ -647,6 +697,18 @@ class TestSearchStringCorrectness:
         results = regex.search_string(text)
         assert count == len(results)
 
+    def test_max_matches_on_fast_paths(self):
+        """Capped searches agree with the uncapped fast paths."""
+        text = "x1 yy22 zzz333 " * 5
+        for elem in (
+            pp.Literal("yy"), pp.Word(pp.alphas()), pp.Regex(r"\d+"), pp.Keyword("yy"),
+            pp.Word(pp.alphas()) + pp.Word(pp.nums()),
+            pp.compile(pp.Word(pp.alphas()) + pp.Word(pp.nums())),
+        ):
+            found = elem.search_string(text)
+            assert elem.search_string(text, max_matches=4) == found[:4]
+            assert elem.search_string(text, max_matches=0) == []
+
 
 # ============================================================================
 # h. Batch operations
@@ -762,6 +824,62 @@ class TestBatchOperations:
         assert len(result) == 1000
         assert all(r == [] for r in result)
 
+    def test_and_parse_batch_agrees_with_parse_string(self):
+        num = pp.Word(pp.nums())
+        grammars = [
+            pp.Word(pp.alphas()) + pp.Word(pp.nums()),
+            pp.Word(pp.alphas()) + num.copy().set_parse_action(lambda t: int(t[0])),
+            pp.Literal("x") + pp.OneOrMore(num),
+            pp.Literal("a") + pp.Word(pp.alphas()).set_whitespace_chars(" "),
+            pp.Literal("a") + pp.Keyword("if", caseless=True),
+        ]
+        inputs = ["ab 12", "  x 1 2", "x", "a b", "a IF", "ab12", "", "1 a"]
+        for grammar in grammars:
+            expected = []
+            for s in inputs:
+                try:
+                    expected.append(grammar.parse_string(s))
+                except pp.ParseError:
+                    expected.append([])
+            assert grammar.parse_batch(inputs) == expected
+            assert grammar.parse_batch_count(inputs) == sum(1 for e in expected if e)
+        assert (pp.Word(pp.alphas()) + pp.Word(pp.nums())).parse_batch(["ab 12"]) == [["ab", "12"]]
+
+    def test_parse_batch_rejects_non_str_inputs(self):
+        with pytest.raises(ValueError, match="list of str"):
+            pp.Literal("a").parse_batch(["a", 1])
+        with pytest.raises(ValueError, match="list of str"):
+            pp.Word(pp.alphas()).parse_batch_count([1.5])
+
+    def test_parse_batch_releases_the_gil(self):
+        """A background thread keeps running while a large batch is matched."""
+        expr = pp.Suppress(pp.OneOrMore(pp.Word(pp.alphas())))
+        inputs = ["word " * 2000 + str(i) for i in range(300)]
+        ticks = [0]
+        start, stop = threading.Event(), threading.Event()
+
+        def spin():
+            start.wait()
+            while not stop.is_set():
+                ticks[0] += 1
+
+        interval = sys.getswitchinterval()
+        # Too long for the interpreter to preempt the batch: the spinner only runs
+        # if the batch releases the GIL
+        sys.setswitchinterval(0.5)
+        thread = threading.Thread(target=spin)
+        thread.start()
+        try:
+            start.set()
+            result = expr.parse_batch(inputs)
+            during = ticks[0]
+        finally:
+            stop.set()
+            thread.join()
+            sys.setswitchinterval(interval)
+        assert result == [[]] * len(inputs)
+        assert during > 0
+
 
 # ============================================================================
 # i. Cross-validation against pyparsing
@@ -928,10 +1046,48 @@ class TestStressAndCornerCases:
         assert result == ["a+b*c?"]
 
     def test_regex_special_groups(self):
-        """Regex with groups should return the full match."""
+        """Regex with groups should return one token per group."""
         regex = pp.Regex(r"(\d+)-(\d+)")
         result = regex.parse_string("123-456")
-        assert result == ["123-456"]
+        assert result == ["123", "456"]
+
+    def test_regex_named_groups(self):
+        regex = pp.Regex(r"(?P<key>\w+)=(?P<val>\d+)")
+        result = regex.parse_string("a=1")
+        assert result == ["a", "1"]
+        assert (result["key"], result["val"]) == ("a", "1")
+        # Non-participating groups are skipped; non-capturing groups give the whole match
+        assert pp.Regex(r"(x)?(\d+)").parse_string("42") == ["42"]
+        assert pp.Regex(r"(?:\d+)-(?:\d+)").parse_string("1-2") == ["1-2"]
+        assert regex.search_string("a=1 b=22") == [["a", "1"], ["b", "22"]]
+        assert regex.parse_batch(["a=1", "x"]) == [["a", "1"], []]
+        assert (pp.Word(pp.alphas()) + regex).parse_string("set w=80")["val"] == "80"
+
+    def test_regex_as_group_list(self):
+        pair = pp.Regex(r"(\w+)=(\d+)", as_group_list=True)
+        assert pair.parse_string("a=1") == [["a", "1"]]
+        assert pair.search_string("a=1 b=22") == [[["a", "1"]], [["b", "22"]]]
+        assert (pp.Literal("set") + pair).parse_string("set w=80") == ["set", ["w", "80"]]
+        with pytest.raises(pp.PatternError):
+            pp.Regex("(a)", as_group_list=True, as_match=True)
+
+    def test_regex_as_match(self):
+        import pickle
+        pair = pp.Regex(r"(?P<key>\w+)=(?P<val>\d+)(;)?", as_match=True)
+        [m] = (pp.Literal("set") + pair).parse_string("set w=80")[1:]
+        assert (m.group(), m.group(1), m.group("val"), m[2]) == ("w=80", "w", "80", "80")
+        assert m.group(1, 2) == ("w", "80")
+        assert m.groups() == ("w", "80", None) and m.groups("") == ("w", "80", "")
+        assert m.groupdict() == {"key": "w", "val": "80"}
+        assert (m.start(), m.end(), m.span(2), m.span(3)) == (4, 8, (6, 8), (-1, -1))
+        with pytest.raises(IndexError):
+            m.group(9)
+        [[m1], [m2]] = pair.search_string("a=1; b=2")
+        assert (m1.group(0), m2.span()) == ("a=1;", (5, 8))
+        assert pair.suppress().parse_string("a=1") == []
+        assert pp.Combine(pair).parse_string("a=1") == ["a=1"]
+        assert pair.transform_string("x a=1 y") == "x a=1 y"
+        assert pickle.loads(pickle.dumps(pair)).parse_string("a=1")[0].group("key") == "a"
 
     def test_search_string_count_consistency_literal(self):
         """Verify count consistency across different string sizes."""
@@ -1034,5 +1190,3819 @@ class TestStressAndCornerCases:
         assert count == 3
 
 
+# ============================================================================
+# k. Segmented (rope-like) inputs
+# ============================================================================
+
+class TestSegmentedInput:
+    """parse_segments / search_segments treat a list of strings as one input."""
+
+    def test_literal_spanning_boundary(self):
+        lit = pp.Literal("hello")
+        result = lit.search_segments(["say he", "llo there, hel", "lo"])
+        assert result == [(["hello"], 4, 9), (["hello"], 17, 22)]
+
+    def test_parse_segments(self):
+        parser = pp.Keyword("let") + pp.Word(pp.alphas())
+        assert parser.parse_segments(["le", "t x", "yz"]) == ["let", "xyz"]
+        with pytest.raises(ValueError):
+            parser.parse_segments(["lett", "er"])
+
+    def test_randomized_segmentation_matches_reference(self):
+        import random
+        rng = random.Random(1520)
+        text = "key = 42; other=7 ; name = abc123 ; " * 20
+        parser = pp.Word(pp.alphas()) + pp.Literal("=") + pp.Regex(r"\w+")
+        expected = [(t.as_list(), start, end) for t, start, end in parser.scan_string(text)]
+        for _ in range(25):
+            cuts = sorted(rng.sample(range(1, len(text)), rng.randint(1, 40)))
+            parts = [text[i:j] for i, j in zip([0] + cuts, cuts + [len(text)])]
+            assert parser.search_segments(parts) == expected
+
+
+# ============================================================================
+# l. Random input generation
+# ============================================================================
+
+def apache_log_grammar():
+    num = pp.Word(pp.nums())
+    dot = pp.Literal(".")
+    ip = pp.Combine(num + dot + num + dot + num + dot + num)
+    user = pp.Word(pp.alphas()) | pp.Literal("-")
+    timestamp = pp.Combine(
+        pp.Literal("[") + num + pp.Literal("/") + pp.Word(pp.alphas())
+        + pp.Literal("/") + num + pp.Literal(":") + num + pp.Literal("]")
+    )
+    method = pp.Keyword("GET") | pp.Keyword("POST") | pp.Keyword("HEAD")
+    path = pp.Combine(pp.OneOrMore(pp.Literal("/") + pp.Word(pp.alphanums())))
+    request = pp.Suppress(pp.Literal('"')) + method + path + pp.Literal("HTTP/1.1") + pp.Suppress(pp.Literal('"'))
+    size = num | pp.Literal("-")
+    return ip + pp.Literal("-") + user + timestamp + request + num + size
+
+
+class TestGenerate:
+    """generate() produces strings the grammar accepts."""
+
+    def test_generated_samples_parse(self):
+        grammar = apache_log_grammar()
+        samples = pp.generate(grammar, rng_seed=7, count=1000)
+        assert len(samples) == 1000
+        for s in samples:
+            assert grammar.matches(s), s
+            grammar.parse_string(s)
+
+    def test_reproducible_for_seed(self):
+        grammar = apache_log_grammar()
+        assert pp.generate(grammar, rng_seed=42, count=20) == pp.generate(grammar, rng_seed=42, count=20)
+        assert pp.generate(grammar, rng_seed=42, count=20) != pp.generate(grammar, rng_seed=43, count=20)
+        assert isinstance(pp.generate(grammar, rng_seed=1), str)
+
+    def test_recursive_forward_with_base_case(self):
+        expr = pp.Forward()
+        expr <<= pp.Word(pp.alphas()) | pp.Literal("(") + expr + pp.Literal(")")
+        for s in pp.generate(expr, rng_seed=3, max_depth=8, count=50):
+            assert expr.matches(s), s
+
+    def test_refuses_regex(self):
+        import re
+        regex = pp.Regex(r"\d+")
+        # Named as str() names it, not by the Rust type behind it
+        with pytest.raises(ValueError, match=f"cannot generate input for {re.escape(str(regex))}$"):
+            pp.generate(pp.Literal("a") + regex)
+
+    def test_refuses_forward_cycle(self):
+        expr = pp.Forward()
+        expr <<= pp.Literal("(") + expr + pp.Literal(")")
+        with pytest.raises(ValueError, match="max_depth"):
+            pp.generate(expr, max_depth=5)
+
+
+# ============================================================================
+# m. Zero-width elements inside sequences
+# ============================================================================
+
+ZERO_WIDTH = [
+    ("Empty", lambda: pp.Empty()),
+    ("LineStart", lambda: pp.LineStart()),
+    ("StringStart", lambda: pp.StringStart()),
+    ("absent Optional", lambda: pp.Optional(pp.Literal("zz"))),
+    ("absent ZeroOrMore", lambda: pp.ZeroOrMore(pp.Literal("zz"))),
+    ("Suppress", lambda: pp.Suppress(pp.Literal(","))),
+]
+
+
+class TestZeroWidthResults:
+    """Zero-width elements contribute no tokens, whichever code path runs."""
+
+    def text_for(self, name):
+        return "a , b" if name == "Suppress" else "a b"
+
+    def test_and_neighbors(self):
+        for name, make in ZERO_WIDTH:
+            if name in ("LineStart", "StringStart"):
+                continue
+            expr = pp.Literal("a") + make() + pp.Literal("b")
+            text = self.text_for(name)
+            assert expr.parse_string(text) == ["a", "b"], name
+            assert expr.search_string(text) == [["a", "b"]], name
+
+    def test_leading_anchor(self):
+        for name, make in ZERO_WIDTH:
+            expr = make() + pp.Literal("a") + pp.Literal("b")
+            text = "a b" if name != "Suppress" else ", a b"
+            assert expr.parse_string(text) == ["a", "b"], name
+
+    def test_group_neighbors(self):
+        for name, make in ZERO_WIDTH:
+            if name in ("LineStart", "StringStart"):
+                continue
+            expr = pp.Group(pp.Literal("a") + make()) + pp.Literal("b")
+            assert expr.parse_string(self.text_for(name)) == [["a"], "b"], name
+
+    def test_repetition_neighbors(self):
+        for name, make in ZERO_WIDTH:
+            if name in ("LineStart", "StringStart", "Suppress"):
+                continue
+            expr = pp.OneOrMore(pp.Group(pp.Literal("a") + make()))
+            assert expr.parse_string("a a a") == [["a"], ["a"], ["a"]], name
+
+    def test_trailing_string_end_and_line_end(self):
+        for end in (pp.StringEnd(), pp.LineEnd()):
+            expr = pp.Literal("a") + pp.Literal("b") + end
+            assert expr.parse_string("a b") == ["a", "b"]
+            assert expr.search_string("a b") == [["a", "b"]]
+
+    def test_empty_normal_token_is_kept(self):
+        """A Normal element that matches empty text still yields its (empty) token."""
+        expr = pp.Keyword("KEY") + pp.rest_of_line()
+        assert expr.parse_string("KEY") == ["KEY", ""]
+        assert expr.search_string("KEY") == [["KEY", ""]]
+        assert expr.parse_batch(["KEY"]) == [["KEY", ""]]
+
+
+# ============================================================================
+# n. Batch literal predicates
+# ============================================================================
+
+class TestBatchStrPredicates:
+    """batch_str_predicates agrees with str.startswith/endswith/in."""
+
+    ALPHABET = ["a", "b", "A", "é", "É", "ß", "日", " "]
+
+    def random_strings(self, n, seed):
+        import random
+        rng = random.Random(seed)
+        return ["".join(rng.choice(self.ALPHABET) for _ in range(rng.randrange(0, 8)))
+                for _ in range(n)]
+
+    def expected(self, s, prefix, suffix, contains, caseless):
+        if caseless:
+            s = s.lower()
+            prefix, suffix, contains = (p.lower() if p is not None else None
+                                        for p in (prefix, suffix, contains))
+        return ((prefix is None or s.startswith(prefix))
+                and (suffix is None or s.endswith(suffix))
+                and (contains is None or contains in s))
+
+    def test_matches_python_on_random_inputs(self):
+        # 5000 inputs also exercises the multi-threaded path
+        strings = self.random_strings(5000, seed=7)
+        for prefix, suffix, contains in [("a", None, None), (None, "日", None),
+                                         (None, None, "é"), ("A", "b", "ß"),
+                                         ("", "", ""), (None, None, None)]:
+            for caseless in (False, True):
+                got = pp.batch_str_predicates(strings, prefix=prefix, suffix=suffix,
+                                              contains=contains, caseless=caseless)
+                want = [self.expected(s, prefix, suffix, contains, caseless) for s in strings]
+                assert got == want, (prefix, suffix, contains, caseless)
+
+    def test_num_threads(self):
+        strings = self.random_strings(9000, seed=3)
+        want = [self.expected(s, "a", None, "b", True) for s in strings]
+        for num_threads in (1, 2, 5):
+            got = pp.batch_str_predicates(strings, prefix="a", contains="b", caseless=True,
+                                          num_threads=num_threads)
+            assert got == want, num_threads
+        with pytest.raises(ValueError):
+            pp.batch_str_predicates(strings, prefix="a", num_threads=0)
+
+    def test_empty_inputs(self):
+        assert pp.batch_str_predicates([]) == []
+        assert pp.batch_str_predicates([""], prefix="x") == [False]
+        assert pp.batch_str_predicates([""], contains="") == [True]
+
+    def test_rejects_non_str(self):
+        with pytest.raises(ValueError):
+            pp.batch_str_predicates(["ok", 3], prefix="o")
+
+
+# ============================================================================
+# o. Sampled scans
+# ============================================================================
+
+class TestSampleScan:
+    """sample_scan estimates match counts from a reproducible subset."""
+
+    def corpus(self):
+        import random
+        rng = random.Random(11)
+        lines = ["ERROR disk full" if rng.random() < 0.2 else "ok" for _ in range(40000)]
+        return "\n".join(lines) + "\n", sum(l.startswith("ERROR") for l in lines)
+
+    def test_text_estimate_within_tolerance(self):
+        text, true = self.corpus()
+        report = pp.sample_scan(text, pp.Literal("ERROR"), fraction=0.05, seed=42)
+        assert report["units_total"] == 40000
+        assert report["units_sampled"] == 2000
+        assert abs(report["estimate"] - true) < 0.1 * true
+        assert report["ci_low"] <= report["estimate"] <= report["ci_high"]
+        assert report["examples"] == ["ERROR"] * 5
+
+    def test_same_seed_same_sample(self):
+        text, _ = self.corpus()
+        grammar = pp.Literal("ERROR")
+        first = pp.sample_scan(text, grammar, fraction=0.01, seed=7)
+        assert pp.sample_scan(text, grammar, fraction=0.01, seed=7) == first
+        assert pp.sample_scan(text, grammar, fraction=0.01, seed=8) != first
+
+    def test_files_by_aligned_windows(self):
+        import os
+        import tempfile
+        text, true = self.corpus()
+        with tempfile.TemporaryDirectory() as d:
+            paths = []
+            for i in range(2):
+                paths.append(os.path.join(d, f"part{i}.log"))
+                with open(paths[-1], "w") as f:
+                    f.write(text)
+            grammar = pp.Literal("ERROR")
+            # Sampling everything counts every line exactly once, whatever the window
+            for window in (7, 1000, 1 << 16):
+                full = pp.sample_scan(paths, grammar, fraction=1.0, window=window)
+                assert round(full["estimate"]) == 2 * true
+            report = pp.sample_scan(paths, grammar, fraction=0.2, seed=42, window=4096)
+            assert abs(report["estimate"] - 2 * true) < 0.1 * 2 * true
+            assert pp.sample_scan(paths, grammar, fraction=0.2, seed=42, window=4096) == report
+
+    def test_invalid_fraction(self):
+        with pytest.raises(ValueError):
+            pp.sample_scan("abc", pp.Literal("a"), fraction=0)
+
+
+# ============================================================================
+# p. Incremental re-scans
+# ============================================================================
+
+class TestIncrementalScanner:
+    """apply_edit keeps the spans identical to a fresh scan of the edited text."""
+
+    grammar = pp.Keyword("let") | pp.Word(pp.nums()) | pp.Literal("==")
+
+    def fresh_spans(self, text):
+        scanner = pp.IncrementalScanner(self.grammar)
+        scanner.set_text(text)
+        return scanner.spans()
+
+    def test_set_text_matches_search_string(self):
+        text = "let x == 12\nlet y == 345\n"
+        scanner = pp.IncrementalScanner(self.grammar)
+        scanner.set_text(text)
+        assert [[text[s:e]] for s, e in scanner.spans()] == self.grammar.search_string(text)
+
+    def test_delta_reports_only_real_changes(self):
+        scanner = pp.IncrementalScanner(self.grammar)
+        scanner.set_text("let a == 1\nlet b == 22\n")
+        removed, added = scanner.apply_edit(0, 3, "set")
+        assert removed == [(0, 3)]
+        assert added == []
+        removed, added = scanner.apply_edit(len("set a == "), 1, "789")
+        assert removed == [(9, 10)]
+        assert added == [(9, 12)]
+        assert scanner.text == "set a == 789\nlet b == 22\n"
+        assert scanner.spans() == self.fresh_spans(scanner.text)
+
+    def test_random_edits_match_fresh_scan(self):
+        import random
+        grammars = [
+            self.grammar,
+            pp.Word(pp.alphas()) + pp.Word(pp.nums()),
+            pp.Word(pp.alphas()) + "=" + pp.Word(pp.nums()),
+            pp.Keyword("let") + pp.Word(pp.alphas(), pp.alphanums()) + "==",
+        ]
+        for grammar in grammars:
+            rng = random.Random(1234)
+            alphabet = "let x=9 01\nab"
+            lines = []
+            for i in range(300):
+                lines.append(f"let v{i} == {rng.randint(0, 10**6)}" + "\n" * rng.randint(0, 1))
+            text = "\n".join(lines) + "\n"
+            scanner = pp.IncrementalScanner(grammar)
+            scanner.set_text(text)
+            for _ in range(300):
+                start = rng.randint(0, len(text))
+                old_len = rng.randint(0, min(8, len(text) - start))
+                new = "".join(rng.choice(alphabet) for _ in range(rng.randint(0, 8)))
+                before = scanner.spans()
+                removed, added = scanner.apply_edit(start, old_len, new)
+                text = text[:start] + new + text[start + old_len:]
+                assert scanner.text == text
+                assert scanner.spans() == [(a, b) for _, a, b in grammar.scan_string(text)], grammar
+                assert set(removed) <= set(before)
+                assert set(added) <= set(scanner.spans())
+
+    def test_starts_past_whitespace(self):
+        grammar = pp.Word(pp.alphas()) + pp.Word(pp.nums())
+        scanner = pp.IncrementalScanner(grammar)
+        scanner.set_text("x  ab\n 12 cd")
+        assert scanner.spans() == [(3, 9)]
+        removed, added = scanner.apply_edit(11, 1, "d 3")
+        assert (removed, added) == ([], [(10, 14)])
+
+    def test_invalid_edit(self):
+        scanner = pp.IncrementalScanner(self.grammar)
+        scanner.set_text("let 1")
+        with pytest.raises(ValueError):
+            scanner.apply_edit(3, 10, "")
+        with pytest.raises(ValueError):
+            scanner.apply_edit(6, 0, "x")
+
+
+# ============================================================================
+# q. Highlight spans
+# ============================================================================
+
+class TestHighlightSpans:
+    """highlight_spans tags matched regions with their element's class."""
+
+    def grammar(self):
+        keyword = pp.one_of("if else return").highlight_class("keyword")
+        ident = pp.Word(pp.alphas() + "_", pp.alphanums() + "_").highlight_class("name")
+        number = pp.Word(pp.nums()).highlight_class("number")
+        op = pp.one_of("== = + ( ) :").highlight_class("op")
+        return keyword | number | op | ident
+
+    def test_code_sample(self):
+        code = "if x == 10:\n    return x + 1\n"
+        spans = self.grammar().highlight_spans(code)
+        assert [(code[s:e], cls) for s, e, cls in spans] == [
+            ("if", "keyword"), ("x", "name"), ("==", "op"), ("10", "number"), (":", "op"),
+            ("return", "keyword"), ("x", "name"), ("+", "op"), ("1", "number"),
+        ]
+
+    def test_longest_match_then_first_declared(self):
+        # "iffy" is a longer match as a name than the keyword prefix "if"
+        spans = self.grammar().highlight_spans("iffy")
+        assert spans == [(0, 4, "name")]
+        # Equal lengths go to the element declared first
+        first = pp.Literal("if").highlight_class("keyword") | pp.Word(pp.alphas()).highlight_class("name")
+        assert first.highlight_spans("if") == [(0, 2, "keyword")]
+        swapped = pp.Word(pp.alphas()).highlight_class("name") | pp.Literal("if").highlight_class("keyword")
+        assert swapped.highlight_spans("if") == [(0, 2, "name")]
+
+    def test_unmatched_and_untagged_regions_absent(self):
+        grammar = pp.Word(pp.nums()).highlight_class("number") | pp.Word(pp.alphas())
+        assert grammar.highlight_spans("ab 12 ?? 3") == [(3, 5, "number"), (9, 10, "number")]
+        assert grammar.highlight_spans("") == []
+
+    def test_tagged_element_still_parses(self):
+        tagged = pp.HighlightClass(pp.Word(pp.nums()), "number")
+        assert tagged.class_name == "number"
+        assert (tagged + pp.Literal("!")).parse_string("42!") == ["42", "!"]
+
+
+# ============================================================================
+# r. Exception hierarchy
+# ============================================================================
+
+class TestExceptionHierarchy:
+    """Parse, pattern and file errors raise distinct PyparsingError subclasses."""
+
+    def test_hierarchy(self):
+        for exc in (pp.ParseError, pp.PatternError, pp.FileProcessingError):
+            assert issubclass(exc, pp.PyparsingError)
+        assert pp.ParseException is pp.ParseError
+        # Existing `except ValueError` handlers keep working
+        assert issubclass(pp.PyparsingError, ValueError)
+
+    def test_parse_error_position(self):
+        expr = pp.Word(pp.alphas()) + pp.Word(pp.nums())
+        with pytest.raises(pp.ParseError) as info:
+            expr.parse_string("ab\n  cd")
+        err = info.value
+        assert (err.loc, err.lineno, err.col, err.line) == (5, 2, 3, "  cd")
+
+    def test_parse_error_explain(self):
+        expr = pp.Word(pp.alphas()) + pp.Word(pp.nums())
+        with pytest.raises(pp.ParseException) as info:
+            expr.parse_string("ab\n  cd")
+        err = info.value
+        assert err.mark_input_line() == "  >!<cd"
+        assert err.mark_input_line("^") == "  ^cd"
+        assert err.explain() == "  cd\n  ^\nParseError: " + str(err)
+        # Columns count characters, not bytes
+        with pytest.raises(pp.ParseError) as info:
+            (pp.Literal("é") + pp.Literal("x")).parse_string("é y")
+        assert info.value.mark_input_line() == "é >!<y"
+
+    def test_parse_error_from_fast_paths(self):
+        for expr, text in [
+            (pp.Literal("x"), "  y"),
+            (pp.Keyword("if"), "iffy"),
+            (pp.Regex(r"\d+"), "abc"),
+            (pp.Literal("a") | pp.Literal("b"), "c"),
+            (pp.Group(pp.Literal("a")), "b"),
+        ]:
+            with pytest.raises(pp.ParseError):
+                expr.parse_string(text)
+
+    def test_pattern_errors(self):
+        with pytest.raises(pp.PatternError):
+            pp.Regex("(unclosed")
+        with pytest.raises(pp.PatternError):
+            pp.Word(pp.nums(), min=5, max=2)
+        with pytest.raises(pp.PatternError):
+            pp.one_of("")
+        with pytest.raises(pp.PatternError):
+            pp.Literal("a") + 3
+
+    def test_file_processing_error(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            missing = os.path.join(d, "missing.log")
+            with pytest.raises(pp.FileProcessingError) as info:
+                pp.sample_scan([missing], pp.Literal("x"))
+        err = info.value
+        assert err.path == missing
+        assert err.errno == 2
+        assert err.os_error
+        assert err.lineno is None
+
+
+# ============================================================================
+# s. Multi-pattern replace
+# ============================================================================
+
+class TestTransformMulti:
+    """transform_multi applies several replacement rules in one pass."""
+
+    def rules(self):
+        email = pp.Regex(r"[\w.+-]+@[\w-]+\.[\w.]+")
+        ipv4 = pp.Regex(r"\d{1,3}(?:\.\d{1,3}){3}")
+        card = pp.Regex(r"\d{4}(?:[ -]\d{4}){3}")
+        return [(email, "<EMAIL>"), (ipv4, "<IP>"), (card, lambda m: "****" + m[-4:])]
+
+    def document(self):
+        return (
+            "Contact jane.doe@example.com from 10.0.0.12,\n"
+            "card 4111 1111 1111 1234 — thanks! ünïcode stays.\n"
+            "Second: bob@test.org / 192.168.1.1\n"
+        )
+
+    def test_all_kinds_replaced(self):
+        out = pp.transform_multi(self.document(), self.rules())
+        assert out == (
+            "Contact <EMAIL> from <IP>,\n"
+            "card ****1234 — thanks! ünïcode stays.\n"
+            "Second: <EMAIL> / <IP>\n"
+        )
+
+    def test_overlap_policy(self):
+        short = pp.Literal("ab")
+        long = pp.Literal("abc")
+        # Longest match at the earliest position wins, whatever the rule order
+        assert pp.transform_multi("xabcx", [(short, "1"), (long, "2")]) == "x2x"
+        # Equal lengths go to the earlier rule
+        assert pp.transform_multi("ab", [(short, "1"), (pp.Word("ab"), "2")]) == "1"
+        # Replaced text is not rematched by later rules
+        assert pp.transform_multi("abc", [(long, "ab"), (short, "X")]) == "ab"
+
+    def test_no_matches_is_identity(self):
+        text = "nothing to see here\n"
+        assert pp.transform_multi(text, self.rules()) == text
+
+    def test_file_variant(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            src = os.path.join(d, "in.txt")
+            dst = os.path.join(d, "out.txt")
+            with open(src, "w", encoding="utf-8") as f:
+                f.write(self.document() * 50)
+            count = pp.transform_multi_file(src, dst, self.rules(), block_size=64)
+            with open(dst, encoding="utf-8") as f:
+                assert f.read() == pp.transform_multi(self.document(), self.rules()) * 50
+            assert count == 5 * 50
+            with pytest.raises(pp.FileProcessingError):
+                pp.transform_multi_file(os.path.join(d, "missing"), dst, self.rules())
+
+    def test_invalid_rules(self):
+        with pytest.raises(ValueError):
+            pp.transform_multi("abc", [(pp.Literal("a"), 3)])
+        with pytest.raises(ValueError):
+            pp.transform_multi("abc", [pp.Literal("a")])
+
+
+# ============================================================================
+# t. Introspectable signatures
+# ============================================================================
+
+class TestSignatures:
+    def public_callables(self):
+        import inspect
+        for name in dir(pp):
+            obj = getattr(pp, name)
+            if name.startswith("_") or not callable(obj):
+                continue
+            if inspect.isclass(obj) and issubclass(obj, Exception):
+                continue
+            yield name, obj
+            if inspect.isclass(obj):
+                for attr in vars(obj):
+                    method = getattr(obj, attr)
+                    if not attr.startswith("_") and callable(method):
+                        yield name + "." + attr, method
+
+    def test_every_callable_has_a_signature(self):
+        import inspect
+        for name, obj in self.public_callables():
+            sig = inspect.signature(obj)
+            for param in sig.parameters.values():
+                assert param.default is not Ellipsis, name
+                # with_attribute takes attribute names as keywords, like pyparsing's
+                if param.kind == param.VAR_KEYWORD and name != "with_attribute":
+                    raise AssertionError(name + " takes **kwargs")
+
+    def test_documented_parameter_names(self):
+        import inspect
+        assert list(inspect.signature(pp.Word.add_condition).parameters) == ["self", "fn", "message"]
+        assert inspect.signature(pp.transform_multi_file).parameters["block_size"].default == 1 << 20
+
+    @staticmethod
+    def signature_of(obj):
+        import inspect
+        try:
+            return str(inspect.signature(obj))
+        except (TypeError, ValueError):
+            return ""
+
+    @staticmethod
+    def summary_matches(doc, summary):
+        return " ".join((doc or "").split()).startswith(summary)
+
+    def test_api_summary_covers_the_module(self):
+        table = pp.api_summary()
+        names = [entry["name"] for entry in table]
+        assert len(names) == len(set(names))
+        public = {name for name in vars(pp) if not name.startswith("_")}
+        assert set(names) == public
+        assert pp.__all__ == names
+
+    def test_api_summary_matches_each_attribute(self):
+        import inspect
+        import types
+        for entry in pp.api_summary():
+            name, kind = entry["name"], entry["kind"]
+            obj = getattr(pp, name)
+            if kind == "alias":
+                assert obj is getattr(pp, entry["base"]), name
+                continue
+            if kind == "module":
+                assert isinstance(obj, types.ModuleType), name
+                assert obj.__doc__ == entry["summary"], name
+                continue
+            if kind == "constant":
+                assert not callable(obj), name
+                assert self.summary_matches(type(obj).__doc__, entry["summary"]), name
+                continue
+            assert self.summary_matches(obj.__doc__, entry["summary"]), name
+            if kind == "function":
+                assert inspect.isbuiltin(obj), name
+                assert self.signature_of(obj) == entry["signature"], name
+                continue
+            assert inspect.isclass(obj), name
+            assert issubclass(obj, BaseException) == (kind == "exception"), name
+            assert (obj.__mro__[1].__name__, entry["base"]) in ((entry["base"], entry["base"]), ("object", "")), name
+            if kind == "class":
+                assert self.signature_of(obj) == entry["signature"], name
+            own = [m for m, v in vars(obj).items()
+                   if not m.startswith("_") or (m.startswith("__") and m not in ("__new__", "__init__")
+                                                and type(v).__name__ in ("wrapper_descriptor", "method_descriptor"))]
+            # PyO3 doesn't keep properties in declaration order
+            assert sorted(m["name"] for m in entry["members"]) == sorted(own), name
+            for member in entry["members"]:
+                where = name + "." + member["name"]
+                raw = vars(obj)[member["name"]]
+                attr = getattr(obj, member["name"])
+                if member["kind"] == "property":
+                    assert inspect.isdatadescriptor(raw), where
+                    assert self.summary_matches(raw.__doc__, member["summary"]), where
+                    continue
+                assert isinstance(raw, staticmethod) == (member["kind"] == "staticmethod"), where
+                assert self.signature_of(attr) == member["signature"], where
+                assert self.summary_matches(getattr(raw, "func", attr).__doc__, member["summary"]), where
+
+    def test_stub_matches_api_summary(self):
+        import ast
+        import pathlib
+        stub = pathlib.Path(__file__).resolve().parent.parent / "pyparsing_rs.pyi"
+        if not stub.exists():
+            pytest.skip("pyparsing_rs.pyi is written by cargo build")
+        tree = ast.parse(stub.read_text())
+        defined = {}
+        for node in tree.body:
+            if isinstance(node, (ast.FunctionDef, ast.ClassDef)):
+                defined[node.name] = node
+            elif isinstance(node, ast.Assign):
+                defined[node.targets[0].id] = node
+            elif isinstance(node, ast.AnnAssign):
+                defined[node.target.id] = node
+
+        def params(fn):
+            return "(" + ast.unparse(fn.args) + ")"
+
+        table = pp.api_summary()
+        assert list(defined) == [entry["name"] for entry in table]
+        for entry in table:
+            node = defined[entry["name"]]
+            if entry["kind"] == "function":
+                assert params(node) == entry["signature"], entry["name"]
+                assert ast.get_docstring(node, clean=False) == entry["summary"], entry["name"]
+            elif entry["kind"] in ("class", "exception"):
+                methods = {n.name: n for n in node.body if isinstance(n, ast.FunctionDef)}
+                assert [ast.unparse(b) for b in node.bases] == [b for b in [entry["base"]] if b]
+                if entry["signature"]:
+                    init = params(methods.pop("__init__"))
+                    assert init.replace("(self, ", "(").replace("(self)", "()") == entry["signature"]
+                assert sorted(methods) == sorted(m["name"] for m in entry["members"]), entry["name"]
+                for member in entry["members"]:
+                    if member["kind"] != "property":
+                        assert params(methods[member["name"]]) == member["signature"]
+            elif entry["kind"] == "alias":
+                assert ast.unparse(node.value) == entry["base"]
+
+
+# ============================================================================
+# u. Checkpoints during long scans
+# ============================================================================
+
+class TestCheckpoints:
+    """Long parses and scans check for signals every set_checkpoint_interval() bytes,
+    and for their timeout_ms; batches poll for signals while the GIL is released."""
+
+    def grammar(self):
+        return pp.Literal("x") + pp.Word(pp.nums())
+
+    def backtracking_grammar(self):
+        # Each alternative parses the rest again: exponential in the run of "a"s
+        a = pp.Literal("a")
+        grammar = pp.Forward()
+        grammar <<= (a + grammar + "b") | (a + grammar) | a
+        return grammar
+
+    @pytest.mark.skipif(not hasattr(signal, "setitimer"), reason="needs SIGALRM timers")
+    def test_keyboard_interrupt_stops_long_scan(self):
+        grammar = self.grammar()
+        text = "a" * 1_000_000
+        start = time.perf_counter()
+        grammar.scan_string(text)
+        per_mb = time.perf_counter() - start
+        text *= int(1.0 / per_mb) + 1  # about a second of scanning
+
+        def interrupt(signum, frame):
+            raise KeyboardInterrupt
+
+        previous = signal.signal(signal.SIGALRM, interrupt)
+        pp.set_checkpoint_interval(1 << 16)
+        try:
+            for scan in (grammar.scan_string, grammar.search_string, grammar.transform_string):
+                signal.setitimer(signal.ITIMER_REAL, 0.02)
+                start = time.perf_counter()
+                with pytest.raises(KeyboardInterrupt):
+                    scan(text)
+                assert time.perf_counter() - start < 0.5
+        finally:
+            signal.setitimer(signal.ITIMER_REAL, 0)
+            signal.signal(signal.SIGALRM, previous)
+            pp.set_checkpoint_interval()
+        # Nothing is left pending for the next parse
+        assert grammar.scan_string("x1") == [(["x", "1"], 0, 2)]
+
+    def test_results_unchanged_without_interrupt(self):
+        grammar = self.grammar()
+        text = "x1 a x22 " * 1000
+        expected = (grammar.scan_string(text), grammar.search_string(text), grammar.parse_string(text))
+        pp.set_checkpoint_interval(7, yield_gil=True)
+        try:
+            assert (grammar.scan_string(text), grammar.search_string(text), grammar.parse_string(text)) == expected
+        finally:
+            pp.set_checkpoint_interval()
+
+    def test_interval_must_be_positive(self):
+        with pytest.raises(ValueError):
+            pp.set_checkpoint_interval(0)
+
+    def test_timeout_reports_position_reached(self):
+        # Each attempt reads the rest of the text: quadratic, and stuck behind the
+        # furthest position reached
+        grammar = pp.Word("a") + pp.Literal("b")
+        start = time.perf_counter()
+        grammar.search_string("a" * 4000)
+        per_attempt = (time.perf_counter() - start) / 4000 ** 2
+        text = "a" * int((1.0 / per_attempt) ** 0.5)  # about a second of scanning
+        for scan in (grammar.scan_string, grammar.search_string, grammar.transform_string):
+            start = time.perf_counter()
+            with pytest.raises(TimeoutError, match="timed out after 20 ms") as info:
+                scan(text, timeout_ms=20)
+            assert time.perf_counter() - start < 0.5
+            assert 0 < info.value.loc < len(text)
+        # Nothing is left pending for the next parse
+        assert grammar.search_string("aab", timeout_ms=1000) == [["aa", "b"]]
+
+    def test_results_unchanged_with_timeout(self):
+        grammar = self.grammar()
+        text = "x1 a x22 " * 1000
+        assert grammar.scan_string(text, timeout_ms=60000) == grammar.scan_string(text)
+        assert grammar.search_string(text, timeout_ms=60000) == grammar.search_string(text)
+        assert grammar.transform_string(text, "N", timeout_ms=60000) == grammar.transform_string(text, "N")
+        assert grammar.parse_string(text, timeout_ms=60000) == ["x", "1"]
+        assert pp.Literal("x").parse_string("x", timeout_ms=1) == ["x"]
+
+    def test_timeout_stops_single_element_scans(self):
+        # Single elements are searched without checkpoints unless a timeout is running
+        for grammar in (pp.Literal("ab"), pp.Keyword("ab"), pp.Word("ab"), pp.Regex("ab")):
+            text = "ab " * 1_000_000
+            start = time.perf_counter()
+            grammar.search_string(text)
+            text *= int(1.0 / (time.perf_counter() - start)) + 1  # about a second of scanning
+            for scan in (grammar.search_string, grammar.transform_string):
+                start = time.perf_counter()
+                with pytest.raises(TimeoutError) as info:
+                    scan(text, timeout_ms=20)
+                assert time.perf_counter() - start < 0.5, grammar
+                assert 0 < info.value.loc < len(text)
+
+    def test_timeout_stops_batches(self):
+        grammar = self.backtracking_grammar()
+        for batch in (grammar.parse_batch, grammar.parse_batch_count, grammar.matches_batch,
+                      grammar.find_all_positions_batch):
+            # Distinct strings: batches match repeats of one string once
+            inputs = ["a" * 10 + str(i) for i in range(10)]
+            while True:
+                start = time.perf_counter()
+                batch(inputs)
+                if time.perf_counter() - start > 0.05:
+                    break
+                inputs += [text + "." for text in inputs]
+            inputs = [text + str(i) for i in range(20) for text in inputs]  # about a second of work
+            start = time.perf_counter()
+            with pytest.raises(TimeoutError, match="timed out after 20 ms") as info:
+                batch(inputs, timeout_ms=20)
+            assert time.perf_counter() - start < 0.5, batch
+            assert info.value.loc is None
+        small = ["aab", "b"]
+        assert grammar.parse_batch(small, timeout_ms=60000) == grammar.parse_batch(small)
+        assert grammar.matches_batch(small, timeout_ms=60000) == [True, False]
+
+    def test_timeout_stops_file_functions(self):
+        import os
+        import tempfile
+        grammar = self.backtracking_grammar()
+        start = time.perf_counter()
+        grammar.search_string("a" * 10)
+        per_line = time.perf_counter() - start
+        lines = ["a" * 10 + str(i) for i in range(int(0.1 / per_line) + 1)]  # about a second in ten files
+        with tempfile.TemporaryDirectory() as d:
+            for i in range(10):
+                with open(os.path.join(d, f"part{i}.txt"), "w") as f:
+                    f.write("\n".join(lines))
+            path = os.path.join(d, "part0.txt")
+            # They stop between chunks, files or callbacks
+            calls = (
+                lambda: pp.split_file_process(path, grammar, num_chunks=100, num_threads=1, timeout_ms=20),
+                lambda: pp.scan_directory(d, grammar, num_threads=1, timeout_ms=20),
+                lambda: pp.process_file_streaming(path, grammar, lambda batch: None, batch_size=1, timeout_ms=20),
+            )
+            for call in calls:
+                start = time.perf_counter()
+                with pytest.raises(TimeoutError):
+                    call()
+                assert time.perf_counter() - start < 0.5
+            expected = pp.split_file_process(path, grammar)
+            assert pp.split_file_process(path, grammar, timeout_ms=60000) == expected
+
+    @pytest.mark.skipif(not hasattr(signal, "setitimer"), reason="needs SIGALRM timers")
+    def test_keyboard_interrupt_stops_parse_string(self):
+        grammar = pp.OneOrMore(self.grammar())
+        text = "x1 " * 300_000
+        start = time.perf_counter()
+        grammar.parse_string(text)
+        text *= int(1.0 / (time.perf_counter() - start)) + 1
+
+        def interrupt(signum, frame):
+            raise KeyboardInterrupt
+
+        previous = signal.signal(signal.SIGALRM, interrupt)
+        pp.set_checkpoint_interval(1 << 16)
+        try:
+            signal.setitimer(signal.ITIMER_REAL, 0.02)
+            start = time.perf_counter()
+            # Not a ParseError: the parse stopped, it didn't fail
+            with pytest.raises(KeyboardInterrupt):
+                grammar.parse_string(text)
+            assert time.perf_counter() - start < 0.5
+        finally:
+            signal.setitimer(signal.ITIMER_REAL, 0)
+            signal.signal(signal.SIGALRM, previous)
+            pp.set_checkpoint_interval()
+
+    @pytest.mark.skipif(not hasattr(signal, "setitimer"), reason="needs SIGALRM timers")
+    def test_keyboard_interrupt_stops_batches(self):
+        grammar = self.grammar()
+        # Each input is read to its end before failing, with nothing to build after
+        failing = pp.Word("a") + pp.Literal("b")
+        runs = (
+            (failing.parse_batch, "a" * 1000),
+            (lambda inputs: pp.batch_count_matches(grammar, inputs, num_threads=1), "x1 a x22 " * 100),
+            (lambda inputs: pp.batch_count_matches(grammar, inputs, num_threads=2), "x1 a x22 " * 100),
+        )
+
+        def interrupt(signum, frame):
+            raise KeyboardInterrupt
+
+        previous = signal.signal(signal.SIGALRM, interrupt)
+        try:
+            for run, text in runs:
+                start = time.perf_counter()
+                run([f"{text}{i}" for i in range(1000)])
+                copies = int(1.0 / (time.perf_counter() - start)) + 1
+                inputs = [f"{text}{i}" for i in range(1000 * copies)]  # about a second
+                signal.setitimer(signal.ITIMER_REAL, 0.02)
+                start = time.perf_counter()
+                with pytest.raises(KeyboardInterrupt):
+                    run(inputs)
+                assert time.perf_counter() - start < 0.5
+        finally:
+            signal.setitimer(signal.ITIMER_REAL, 0)
+            signal.signal(signal.SIGALRM, previous)
+        assert pp.batch_count_matches(grammar, ["x1 x2"]) == [2]
+
+
+
+# ============================================================================
+# v. Metrics
+# ============================================================================
+
+class TestMetrics:
+    def setup_method(self):
+        pp.reset_metrics()
+        pp.enable_metrics()
+
+    def teardown_method(self):
+        pp.enable_metrics(False)
+        pp.reset_metrics()
+
+    def test_counts_per_grammar(self):
+        num = pp.Word(pp.nums())("num")
+        num.parse_string("12")
+        with pytest.raises(pp.ParseError):
+            num.parse_string("ab")
+        num.parse_batch(["1", "x", "333"])
+        num.search_string("1 a 22")
+        pp.Literal("a").scan_string("a b a")
+        snap = pp.metrics_snapshot()
+        assert snap["enabled"] is True
+        m = snap["grammars"]["num"]
+        assert (m["parses"], m["scans"], m["failures"]) == (5, 1, 2)
+        assert m["matches"] == 2
+        assert m["bytes"] == 2 + 2 + 1 + 1 + 3 + 6
+        assert m["latency_us_buckets"]["+Inf"] == 6
+        # Grammars without a results name are labelled by str(), each on its own
+        unnamed = snap["grammars"][str(pp.Literal("a"))]
+        assert (unnamed["scans"], unnamed["matches"]) == (1, 2)
+        assert snap["totals"]["scans"] == 2
+        pp.Word(pp.alphas()).parse_string("b")
+        assert pp.metrics_snapshot()["grammars"][str(pp.Word(pp.alphas()))]["parses"] == 1
+        assert pp.metrics_snapshot()["grammars"][str(pp.Literal("a"))]["parses"] == 0
+
+    def test_buckets_are_cumulative(self):
+        lit = pp.Literal("a")
+        for _ in range(20):
+            lit.parse_string("a")
+        buckets = pp.metrics_snapshot()["grammars"][str(lit)]["latency_us_buckets"]
+        counts = list(buckets.values())
+        assert counts == sorted(counts)
+        assert list(buckets)[-1] == "+Inf" and counts[-1] == 20
+
+    def test_file_calls(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            src = os.path.join(d, "in.txt")
+            with open(src, "w", encoding="utf-8") as f:
+                f.write("a b a\n")
+            pp.transform_multi_file(src, os.path.join(d, "out.txt"), [(pp.Literal("a"), "x")])
+            with pytest.raises(pp.FileProcessingError):
+                pp.transform_multi_file(os.path.join(d, "missing"), src, [])
+        m = pp.metrics_snapshot()["grammars"]["transform_multi_file"]
+        assert (m["files"], m["failures"], m["matches"], m["bytes"]) == (2, 1, 2, 6)
+
+    def test_disabled_records_nothing(self):
+        pp.enable_metrics(False)
+        pp.Literal("a").parse_string("a")
+        snap = pp.metrics_snapshot()
+        assert snap["enabled"] is False and snap["grammars"] == {}
+        assert snap["totals"]["parses"] == 0
+
+    def test_batch_records_each_inputs_own_parse(self):
+        inputs = [" 3", "4", "x", "4", "1 2"]
+        grammars = [
+            pp.Word(pp.nums())("num"),
+            pp.Word(pp.nums()).set_parse_action(lambda t: int(t[0]))("num"),
+            (pp.Literal("a") | pp.OneOrMore(pp.Word(pp.nums())))("num"),
+        ]
+        for grammar in grammars:
+            pp.reset_metrics()
+            out = grammar.parse_batch(inputs)
+            m = pp.metrics_snapshot()["grammars"]["num"]
+            assert (m["parses"], m["failures"]) == (5, sum(1 for t in out if not t))
+            assert m["bytes"] == sum(len(s) for s in inputs)
+
+    def test_batch_matches_each_input_once(self):
+        num = pp.Word(pp.nums())
+        grammar = pp.OneOrMore(num | pp.Word(pp.alphas()))
+        inputs = ["1 a 2", "x", "3 4 b", "?"]
+
+        def element_calls():
+            pp.reset_profile_stats()
+            pp.enable_profiling()
+            try:
+                grammar.parse_batch(inputs)
+                stats = pp.get_profile_stats()
+            finally:
+                pp.enable_profiling(False)
+                pp.reset_profile_stats()
+            return sum(s["parse_calls"] + s["match_calls"] for s in stats)
+
+        pp.enable_metrics(False)
+        unmetered = element_calls()
+        pp.enable_metrics()
+        assert element_calls() == unmetered
+
+    def test_reset(self):
+        pp.Literal("a").parse_string("a")
+        pp.reset_metrics()
+        assert pp.metrics_snapshot()["grammars"] == {}
+
+    def test_results_unchanged(self):
+        # Fast paths keep their own output shapes and messages with metrics on
+        text = "x1 a x22"
+
+        def run(grammar):
+            try:
+                error = grammar.parse_string("1")
+            except pp.ParseError as e:
+                error = str(e)
+            return (grammar.parse_string("x1"), error, grammar.search_string(text),
+                    grammar.parse_batch(["x1", "a", "x1"]), grammar.scan_string(text))
+
+        for grammar in (pp.Word(pp.alphas()) + pp.Word(pp.nums()), pp.Word(pp.alphas()),
+                        pp.Regex(r"[a-z]+"), pp.MatchFirst([pp.Literal("x"), pp.Literal("a")])):
+            pp.enable_metrics(False)
+            expected = run(grammar)
+            pp.enable_metrics()
+            assert run(grammar) == expected
+
+
+
+# ============================================================================
+# w. Normalization pre-pass
+# ============================================================================
+
+class TestNormalize:
+    def test_collapses_whitespace_and_strips_zero_width(self):
+        original = "a  \t b\u200bc"
+        norm = pp.normalize(original)
+        assert norm.text == str(norm) == "a bc"
+        assert [norm.to_original_offset(i) for i in range(5)] == [0, 1, 5, 9, len(original.encode())]
+        # The collapsed space covers the whole run; the removed char is left out
+        assert norm.to_original_span(1, 2) == (1, 5)
+        assert norm.to_original_span(2, 3) == (5, 6)
+        assert norm.to_original_span(2, 4) == (5, 10)
+
+    def test_nfc_and_lowercase(self):
+        original = "Cafe\u0301 X"
+        norm = pp.normalize(original, lowercase=True)
+        assert norm.text == "caf\u00e9 x"
+        # The composed char maps to the whole decomposed run
+        assert norm.to_original_span(0, len("caf\u00e9".encode())) == (0, len("Cafe\u0301".encode()))
+        assert norm.to_original_offset(len("caf\u00e9 ".encode())) == original.encode().index(b"X")
+        assert pp.normalize(original, nfc=False).text == original
+
+    def test_options_off_is_identity(self):
+        original = "a  b\u200b"
+        norm = pp.normalize(original, collapse_whitespace=False, strip_zero_width=False)
+        assert norm.text == original
+        assert norm.to_original_span(0, len(original.encode())) == (0, len(original.encode()))
+
+    def test_out_of_range(self):
+        norm = pp.normalize("ab")
+        with pytest.raises(ValueError):
+            norm.to_original_offset(3)
+        with pytest.raises(ValueError):
+            norm.to_original_span(2, 1)
+
+    def test_scan_string_reports_original_offsets(self):
+        original = "foo \u200b  bar\u200bbaz"
+        matches = pp.Word(pp.alphas()).scan_string(original, normalize=True)
+        assert matches == [(["foo"], 0, 3), (["barbaz"], 9, 18)]
+        encoded = original.encode()
+        assert encoded[9:18].decode() == "bar\u200bbaz"
+
+    def test_parse_string(self):
+        assert pp.Literal("caf\u00e9").parse_string("cafe\u0301", normalize=True) == ["caf\u00e9"]
+        grammar = pp.Word(pp.alphas()) + pp.Literal("=") + pp.Word(pp.nums())
+        assert grammar.parse_string("x \u200b =\t\t1", normalize=True) == ["x", "=", "1"]
+        with pytest.raises(pp.ParseError) as e:
+            pp.Literal("x").parse_string("  \u200b  y", normalize=True)
+        assert e.value.loc == 7
+        with pytest.raises(pp.ParseError) as e:
+            pp.Literal("x").parse_string("x \u200b  y", parse_all=True, normalize=True)
+        assert e.value.loc == 7
+
+
+
+# ============================================================================
+# x. Whitespace characters
+# ============================================================================
+
+class TestWhitespaceChars:
+    TEXT = "ab cd\nef gh\n"
+
+    def words(self):
+        return pp.OneOrMore(pp.Word(pp.alphas()))
+
+    def test_default_set(self):
+        words = self.words()
+        assert words.parse_string(self.TEXT) == ["ab", "cd", "ef", "gh"]
+        previous = pp.set_default_whitespace_chars(" \t")
+        try:
+            assert sorted(previous) == sorted(" \t\n\r")
+            assert words.parse_string(self.TEXT) == ["ab", "cd"]
+            assert words.search_string(self.TEXT) == [["ab", "cd"], ["ef", "gh"]]
+            assert words.scan_string(self.TEXT) == [(["ab", "cd"], 0, 5), (["ef", "gh"], 6, 11)]
+            assert words.parse_batch([self.TEXT]) == [["ab", "cd"]]
+            # Elements built before the change follow it too
+            assert (pp.Word(pp.alphas()) + pp.Word(pp.nums())).search_string("a\n1 b 2") == [["b", "2"]]
+        finally:
+            pp.set_default_whitespace_chars()
+        assert words.parse_string(self.TEXT) == ["ab", "cd", "ef", "gh"]
+
+    def test_per_element(self):
+        words = self.words().set_whitespace_chars(" \t")
+        assert isinstance(words, pp.WhitespaceScope)
+        assert words.parse_string(self.TEXT) == ["ab", "cd"]
+        assert words.scan_string(self.TEXT) == [(["ab", "cd"], 0, 5), (["ef", "gh"], 6, 11)]
+        assert pp.WhitespaceScope(self.words(), " ").parse_string(self.TEXT) == ["ab", "cd"]
+        # The original element is unchanged
+        assert self.words().parse_string(self.TEXT) == ["ab", "cd", "ef", "gh"]
+
+    def test_nearest_setting_wins(self):
+        pair = pp.Word(pp.alphas()) + pp.Word(pp.nums())
+        assert pair.parse_string("a\n1") == ["a", "1"]
+        with pytest.raises(pp.ParseError):
+            pair.set_whitespace_chars(" ").parse_string("a\n1")
+        # An inner set overrides the outer one for the inner element only
+        inner = pp.OneOrMore(pp.Word(pp.alphas())).set_whitespace_chars(" ")
+        grammar = (pp.Literal("x") + inner + pp.Word(pp.nums())).set_whitespace_chars(" \n")
+        assert grammar.parse_string("x a b\n1") == ["x", "a", "b", "1"]
+        with pytest.raises(pp.ParseError):
+            grammar.parse_string("x\na b 1")
+        # Custom characters
+        dotted = (pp.Word(pp.alphas()) + pp.Word(pp.alphas())).set_whitespace_chars(".")
+        assert dotted.parse_string("ab..cd") == ["ab", "cd"]
+        with pytest.raises(pp.ParseError):
+            dotted.parse_string("ab cd")
+
+    def test_scoped_element_inside_and(self):
+        # The scope's token is the element's match, without the whitespace before it
+        grammar = pp.Literal("a") + pp.Word(pp.alphas()).set_whitespace_chars(" \t")
+        assert grammar.parse_string("a b") == ["a", "b"]
+        assert grammar.parse_string("a b") == pp.Group(grammar).parse_string("a b")[0]
+        assert grammar.search_string("a b") == [["a", "b"]]
+        assert grammar.scan_string("a b") == [(["a", "b"], 0, 3)]
+
+    def test_non_ascii_rejected(self):
+        with pytest.raises(ValueError):
+            pp.set_default_whitespace_chars("\u00a0")
+        with pytest.raises(ValueError):
+            pp.Word(pp.alphas()).set_whitespace_chars("\u3000")
+
+
+
+class TestLeaveWhitespace:
+    def test_adjacency(self):
+        adjacent = pp.Word(pp.alphas()) + pp.Word(pp.nums()).leave_whitespace()
+        assert adjacent.parse_string("foo1") == ["foo", "1"]
+        with pytest.raises(pp.ParseError):
+            adjacent.parse_string("foo  1")
+        assert adjacent.search_string("a 1 b2") == [["b", "2"]]
+        assert adjacent.scan_string("a 1 b2") == [(["b", "2"], 4, 6)]
+        # Not recursive by default for Word + Word failing on "foo  bar"
+        words = pp.Word(pp.alphas()) + pp.Word(pp.alphas()).leave_whitespace()
+        with pytest.raises(pp.ParseError):
+            words.parse_string("foo  bar")
+
+    def test_recursive(self):
+        pair = pp.Word(pp.alphas()) + pp.Word(pp.nums())
+        tight = pp.Literal("x") + pair.leave_whitespace()
+        assert tight.parse_string("xa1") == ["x", "a", "1"]
+        with pytest.raises(pp.ParseError):
+            tight.parse_string("xa 1")
+        with pytest.raises(pp.ParseError):
+            tight.parse_string("x a1")
+        # Only the leading whitespace is kept when not recursive
+        loose = pp.Literal("x") + pair.leave_whitespace(recursive=False)
+        assert loose.parse_string("xa 1") == ["x", "a", "1"]
+        with pytest.raises(pp.ParseError):
+            loose.parse_string("x a1")
+        assert loose.search_string("x a 1 xb 2") == [["x", "b", "2"]]
+
+    def test_ignore_whitespace(self):
+        combined = pp.Combine(pp.Literal("a") + pp.Literal("b").ignore_whitespace())
+        assert combined.parse_string("a  b") == ["a  b"]
+        assert pp.Combine(pp.Literal("a") + pp.Literal("b")).search_string("a  b ab") == [["ab"]]
+        inner = (pp.Word(pp.alphas()) + pp.Word(pp.nums())).ignore_whitespace()
+        grammar = pp.Combine(pp.Literal("x") + inner)
+        assert grammar.parse_string("x a 1") == ["x a 1"]
+        # The nearest scope wins
+        left = (pp.Word(pp.alphas()) + pp.Word(pp.nums())).leave_whitespace()
+        with pytest.raises(pp.ParseError):
+            left.ignore_whitespace().parse_string("a 1")
+        assert left.ignore_whitespace().parse_string("a1") == ["a", "1"]
+
+    def test_rescoped_element_inside_and(self):
+        grammar = pp.Literal("a") + pp.Word(pp.alphas()).leave_whitespace().ignore_whitespace()
+        assert grammar.parse_string("a x") == ["a", "x"]
+        assert grammar.search_string("a x") == [["a", "x"]]
+        adjacent = pp.Literal("a") + pp.Word(pp.alphas()).leave_whitespace()
+        assert adjacent.parse_string("ax") == ["a", "x"]
+
+
+# ============================================================================
+# y. Ignored expressions
+# ============================================================================
+
+class TestIgnore:
+    def setup_method(self):
+        stmt = pp.Word(pp.alphas()) + pp.Literal("=") + pp.Word(pp.nums()) + pp.Literal(";")
+        self.program = pp.OneOrMore(pp.Group(stmt)).ignore(pp.cpp_style_comment())
+
+    def test_comments_interleaved(self):
+        src = "// header\na = 1; // one\n/* block */ b /* mid */ = 2;\n// trailing"
+        expected = [["a", "=", "1", ";"], ["b", "=", "2", ";"]]
+        assert self.program.parse_string(src, parse_all=True) == expected
+        assert self.program.scan_string("x = 3; // c\n y = 4;") == [
+            ([["x", "=", "3", ";"], ["y", "=", "4", ";"]], 0, 19)
+        ]
+        with pytest.raises(pp.ParseError):
+            pp.Word(pp.alphas()).parse_string("a /* b */ c", parse_all=True)
+
+    def test_several_expressions(self):
+        words = pp.OneOrMore(pp.Word(pp.alphas()))
+        noisy = words.ignore(pp.python_style_comment(), pp.Literal(","))
+        assert noisy.parse_string("a, b # c\n, d") == ["a", "b", "d"]
+        assert words.ignore(pp.Literal(",")).ignore(pp.Literal(";")).parse_string("a,;b") == ["a", "b"]
+
+    def test_suppress_and_combine(self):
+        call = pp.Word(pp.alphas()) + pp.Suppress(pp.Literal("(") + pp.Literal(")"))
+        assert call.ignore(pp.c_style_comment()).parse_string("f ( /* x */ )") == ["f"]
+        # Combine doesn't skip whitespace inside, nor ignored expressions
+        number = pp.Combine(pp.Word(pp.nums()) + pp.Literal(".") + pp.Word(pp.nums()))
+        number = number.ignore(pp.c_style_comment())
+        assert number.parse_string("/* n */ 1.5") == ["1.5"]
+        with pytest.raises(pp.ParseError):
+            number.parse_string("1/* n */.5")
+
+    def test_nested_ignore(self):
+        hashes = pp.Literal("#").ignore(pp.Literal("#"))
+        assert pp.Literal("x").ignore(hashes).parse_string("## x") == ["x"]
+
+    def test_ignoring_element_inside_and(self):
+        # The skipped comment isn't part of the token
+        grammar = pp.Literal("a") + pp.Word(pp.alphas()).ignore(pp.python_style_comment())
+        assert grammar.parse_string("a #x\n b") == ["a", "b"]
+        assert grammar.search_string("a #x\n b") == [["a", "b"]]
+        assert pp.Group(grammar).parse_string("a #x\n b") == [["a", "b"]]
+
+
+# ============================================================================
+# z. Pattern inference
+# ============================================================================
+
+TIMESTAMPS = [
+    "2024-01-15 10:23:45",
+    "2023-12-31 23:59:59",
+    "2025-06-01 00:00:00",
+    "2024-02-29 12:00:01",
+    "2021-07-04 18:30:00",
+]
+TIMESTAMPS_HELD_OUT = ["2022-11-11 11:11:11", "1999-01-01 00:00:00", "2024-01-15T10:23:45"]
+
+IDS = ["ORD-10023", "ORD-99812", "INV-00017", "ORD-5", "INV-123456", "RMA-42"]
+IDS_HELD_OUT = ["ORD-77", "CRD-1", "ORD_77"]
+
+KEY_VALUES = ["user=alice", "id=42", "level=debug", "retries=3", "host=db01", "mode=a_b"]
+KEY_VALUES_HELD_OUT = ["name=bob", "port=5432", "path=/tmp", "flag"]
+
+
+class TestInferPattern:
+    def test_timestamps(self):
+        inferred = pp.infer_pattern(TIMESTAMPS, holdout=TIMESTAMPS_HELD_OUT)
+        assert inferred.description == (
+            'Word(nums()) + "-" + Word(nums()) + "-" + Word(nums()) + '
+            'Word(nums()) + ":" + Word(nums()) + ":" + Word(nums())'
+        )
+        assert inferred.fit_score == 1.0
+        assert inferred.generalization == 2 / 3
+        assert inferred.element.parse_string(TIMESTAMPS[0]) == [
+            "2024", "-", "01", "-", "15", "10", ":", "23", ":", "45"
+        ]
+
+    def test_ids(self):
+        inferred = pp.infer_pattern(IDS, holdout=IDS_HELD_OUT)
+        assert inferred.description == 'Word(alphas()) + "-" + Word(nums())'
+        assert inferred.generalization == 2 / 3
+        assert isinstance(inferred.element, pp.And)
+
+    def test_key_values(self):
+        inferred = pp.infer_pattern(KEY_VALUES, holdout=KEY_VALUES_HELD_OUT)
+        assert inferred.description == 'Word(alphas()) + "=" + Word(alphanums() + "_")'
+        assert inferred.fit_score == 1.0
+        assert inferred.generalization == 0.5
+        assert inferred.element.parse_string("port = 80") == ["port", "=", "80"]
+
+    def test_optional_regions(self):
+        inferred = pp.infer_pattern(["k=v", "k = v ; x"])
+        assert inferred.description == '"k" + "=" + "v" + Optional(";") + Optional("x")'
+        assert inferred.generalization is None
+        assert inferred.element.parse_string("k=v;") == ["k", "=", "v", ";"]
+
+    def test_non_ascii_words(self):
+        inferred = pp.infer_pattern(["héllo wörld", "añb x"])
+        assert inferred.description.startswith("Regex(")
+        assert inferred.element.parse_string("ça va") == ["ça", "va"]
+
+    def test_fit_is_always_complete(self):
+        samples = ["a", "a b", "x-1", "ab_c", "1 2 3", "(x)", "-"]
+        inferred = pp.infer_pattern(samples)
+        assert inferred.fit_score == 1.0
+        for sample in samples:
+            assert inferred.element.matches(sample)
+
+    def test_invalid_samples(self):
+        with pytest.raises(ValueError):
+            pp.infer_pattern([])
+        with pytest.raises(ValueError):
+            pp.infer_pattern(["a", "  "])
+
+
+# ============================================================================
+# aa. Debug tracing
+# ============================================================================
+
+class TestSetDebug:
+    def teardown_method(self):
+        pp.set_debug_actions()
+
+    def test_default_output(self):
+        number = pp.Word(pp.nums()).set_debug()
+        grammar = number + pp.Literal("+") + number
+        out = io.StringIO()
+        with contextlib.redirect_stdout(out):
+            assert grammar.parse_string("1 + 2") == ["1", "+", "2"]
+            with pytest.raises(pp.ParseError):
+                grammar.parse_string("1 + x")
+        lines = out.getvalue().splitlines()
+        assert lines[:4] == [
+            "Match W:(01234567...) at loc 0",
+            "Matched W:(01234567...) -> ['1']",
+            "Match W:(01234567...) at loc 4",
+            "Matched W:(01234567...) -> ['2']",
+        ]
+        assert lines[-1] == "Match failed W:(01234567...) at loc 4: Expected W:(01234567...)"
+
+    def test_debug_actions(self):
+        events = []
+        pp.set_debug_actions(
+            lambda s, loc, name: events.append(("start", loc, name)),
+            lambda s, start, end, name, tokens: events.append(("match", start, end, name, list(tokens))),
+            lambda s, loc, name, msg: events.append(("fail", loc, name, msg)),
+        )
+        pair = (pp.Word(pp.alphas()) + pp.Literal("=")).set_debug()
+        assert pair.parse_string("ab =") == ["ab", "="]
+        assert events == [
+            ("start", 0, "{W:(ABCDEFGH...) '='}"),
+            ("match", 0, 4, "{W:(ABCDEFGH...) '='}", ["ab", "="]),
+        ]
+        events.clear()
+        choice = pp.MatchFirst([pp.Literal("a"), pp.Literal("b")])("letter").set_debug()
+        with pytest.raises(pp.ParseError):
+            choice.parse_string("c")
+        assert events == [("start", 0, "letter"), ("fail", 0, "letter", "Expected 'a' | 'b'")]
+
+    def test_names(self):
+        events = []
+        pp.set_debug_actions(start_fn=lambda s, loc, name: events.append(name))
+        elements = [
+            pp.ZeroOrMore(pp.Literal("a")),
+            pp.OneOrMore(pp.Keyword("if")),
+            pp.Optional(pp.Regex("[0-9]+")),
+            pp.Group(pp.Char("xy")),
+        ]
+        out = io.StringIO()
+        with contextlib.redirect_stdout(out):
+            for element in elements:
+                element.set_debug().matches("")
+        assert events == ["['a']...", "{keyword 'if'}...", "[/[0-9]+/]", "Group:(one of 'xy')"]
+
+    def test_disable(self):
+        out = io.StringIO()
+        with contextlib.redirect_stdout(out):
+            traced = pp.Literal("a").set_debug()
+            assert traced.set_debug(False).parse_string("a") == ["a"]
+            assert pp.Literal("a").set_debug(False).parse_string("a") == ["a"]
+        assert out.getvalue() == ""
+
+    def test_action_errors_propagate(self):
+        def fail(s, loc, name):
+            raise RuntimeError("boom")
+        pp.set_debug_actions(start_fn=fail)
+        with contextlib.redirect_stdout(io.StringIO()):
+            with pytest.raises(RuntimeError):
+                pp.Literal("a").set_debug().parse_string("a")
+        with pytest.raises(ValueError):
+            pp.set_debug_actions(success_fn=42)
+
+
+# ============================================================================
+# ab. Grammar self-tests
+# ============================================================================
+
+class TestRunTests:
+    def setup_method(self):
+        number = pp.Word(pp.nums())
+        self.sum = number + pp.Literal("+") + number
+
+    def test_printed_report(self):
+        out = io.StringIO()
+        with contextlib.redirect_stdout(out):
+            passed, results = self.sum.run_tests("""
+                # addition
+                1 + 2
+
+                1 + x
+            """)
+        assert not passed
+        assert results[0] == ("1 + 2", ["1", "+", "2"])
+        assert results[1][0] == "1 + x"
+        assert isinstance(results[1][1], pp.ParseError)
+        assert out.getvalue().splitlines() == [
+            "# addition",
+            "1 + 2",
+            "['1', '+', '2']",
+            "",
+            "1 + x",
+            "1 + x",
+            "    ^",
+            "FAIL: Expected W:(01234567...) (at char 4), (line:1, col:5)",
+            "",
+        ]
+
+    def test_options(self):
+        out = io.StringIO()
+        with contextlib.redirect_stdout(out):
+            passed, results = self.sum.run_tests(["1+2", "3 + 4 5"], print_results=False)
+            assert not passed
+            assert [case for case, _ in results] == ["1+2", "3 + 4 5"]
+            passed, _ = self.sum.run_tests(["3 + 4 5"], parse_all=False, print_results=False)
+            assert passed
+            # With no comment marker, "#" lines are cases too
+            passed, _ = self.sum.run_tests("# 1+2", comment="", print_results=False)
+            assert not passed
+        assert out.getvalue() == ""
+
+    def test_full_dump(self):
+        pair = pp.Word(pp.alphas())("key") + pp.Literal("=") + pp.Word(pp.nums())("value")
+        out = io.StringIO()
+        with contextlib.redirect_stdout(out):
+            assert pair.run_tests("a = 1")[0]
+        assert out.getvalue().splitlines()[2:] == ["- key: 'a'", "- value: '1'", ""]
+        out = io.StringIO()
+        with contextlib.redirect_stdout(out):
+            assert pair.run_tests("a = 1", full_dump=False)[0]
+        assert "- key" not in out.getvalue()
+
+    def test_action_exceptions_fail(self):
+        def boom(tokens):
+            raise RuntimeError("boom")
+        grammar = pp.Word(pp.nums()).set_parse_action(boom)
+        out = io.StringIO()
+        with contextlib.redirect_stdout(out):
+            passed, results = grammar.run_tests("12")
+        assert not passed
+        assert isinstance(results[0][1], RuntimeError)
+        assert "FAIL-EXCEPTION: RuntimeError: boom" in out.getvalue()
+
+
+# ============================================================================
+# ac. Fatal errors
+# ============================================================================
+
+class TestErrorStop:
+    def setup_method(self):
+        expr = pp.Word(pp.alphas())
+        self.if_stmt = pp.Keyword("if") - pp.Literal("(") + expr + pp.Literal(")")
+
+    def test_raises_fatal(self):
+        with pytest.raises(pp.ParseFatalError) as exc_info:
+            self.if_stmt.parse_string("if x)")
+        assert "Expected '('" in str(exc_info.value)
+        assert exc_info.value.loc == 3
+        assert pp.ParseFatalException is pp.ParseFatalError
+        assert issubclass(pp.ParseFatalError, pp.ParseError)
+        assert self.if_stmt.parse_string("if (x)") == ["if", "(", "x", ")"]
+
+    def test_failure_before_stop_backtracks(self):
+        stmt = pp.MatchFirst([self.if_stmt, pp.Word(pp.alphas())])
+        assert stmt.parse_string("iffy") == ["iffy"]
+        with pytest.raises(pp.ParseError) as exc_info:
+            self.if_stmt.parse_string("while (x)")
+        assert not isinstance(exc_info.value, pp.ParseFatalError)
+
+    def test_alternatives_and_repetition_propagate(self):
+        word = pp.Word(pp.alphas())
+        for grammar in [
+            pp.MatchFirst([self.if_stmt, word]),
+            pp.Optional(self.if_stmt) + word,
+            pp.ZeroOrMore(self.if_stmt) + word,
+            pp.OneOrMore(self.if_stmt) + word,
+        ]:
+            with pytest.raises(pp.ParseFatalError):
+                grammar.parse_string("if x)")
+        # Without the stop, the alternative is tried
+        plain = pp.Keyword("if") + pp.Literal("(") + word + pp.Literal(")")
+        assert pp.MatchFirst([plain, word]).parse_string("if x)") == ["if"]
+
+    def test_flattening_keeps_stop(self):
+        word = pp.Word(pp.alphas())
+        tail = word - pp.Literal(";")
+        grammar = pp.MatchFirst([pp.Literal("(") + tail, word])
+        assert grammar.parse_string("x") == ["x"]
+        with pytest.raises(pp.ParseFatalError) as exc_info:
+            grammar.parse_string("( x")
+        assert exc_info.value.loc == 3
+
+
+# ============================================================================
+# ad. Packrat memoization
+# ============================================================================
+
+class TestPackrat:
+    @staticmethod
+    def nested_optionals():
+        # Without memoization each level re-parses the same atom three times
+        expr = pp.Forward()
+        atom = pp.Word(pp.nums()) | pp.Literal("(") + expr + pp.Literal(")")
+        expr <<= (
+            pp.Optional(atom + pp.Literal("+"))
+            + pp.Optional(atom + pp.Literal("-"))
+            + atom
+        )
+        return expr
+
+    def parse_count(self, cache_size, depth):
+        pp.enable_packrat(cache_size)
+        try:
+            text = "(" * depth + "1" + ")" * depth
+            tokens = self.nested_optionals().parse_string(text, parse_all=True)
+            assert tokens == ["("] * depth + ["1"] + [")"] * depth
+            return pp.packrat_stats()["misses"]
+        finally:
+            pp.disable_packrat()
+
+    def test_nested_optionals_parse_linearly(self):
+        # A one-entry cache is nearly no cache: parses grow exponentially
+        uncached = [self.parse_count(1, depth) for depth in (4, 8)]
+        assert uncached[1] > 50 * uncached[0]
+        cached = [self.parse_count(128, depth) for depth in (4, 8, 16)]
+        assert cached[1] <= 2 * cached[0] + 10
+        assert cached[2] <= 2 * cached[1] + 10
+        assert cached[2] < uncached[0]
+
+    def test_same_results_and_failures(self):
+        grammar = self.nested_optionals()
+        cases = ["1+2-3", "(1+(2))-3", "((1)", "1+"]
+        expected = []
+        for text in cases:
+            try:
+                expected.append(grammar.parse_string(text, parse_all=True))
+            except pp.ParseError as e:
+                expected.append(str(e))
+        pp.enable_packrat()
+        try:
+            for text, want in zip(cases, expected):
+                try:
+                    got = grammar.parse_string(text, parse_all=True)
+                except pp.ParseError as e:
+                    got = str(e)
+                assert got == want
+            assert pp.packrat_stats()["hits"] > 0
+        finally:
+            pp.disable_packrat()
+        assert not pp.packrat_stats()["enabled"]
+
+    def test_parse_actions_still_run(self):
+        calls = []
+        number = pp.Word(pp.nums()).set_parse_action(lambda t: calls.append(t[0]))
+        grammar = pp.MatchFirst([number + pp.Literal("+"), number + pp.Literal("-"), number])
+        pp.enable_packrat()
+        try:
+            assert grammar.parse_string("7") == ["7"]
+        finally:
+            pp.disable_packrat()
+        assert calls == ["7", "7", "7"]
+
+
+# ============================================================================
+# ae. Left recursion
+# ============================================================================
+
+class TestLeftRecursion:
+    def setup_method(self):
+        pp.enable_left_recursion()
+
+    def teardown_method(self):
+        pp.disable_left_recursion()
+
+    def test_left_associative(self):
+        term = pp.Word(pp.nums())
+        expr = pp.Forward()
+        expr <<= pp.MatchFirst([pp.Group(expr + pp.Literal("+") + term), term])
+        assert expr.parse_string("1+2+3") == [[["1", "+", "2"], "+", "3"]]
+        assert expr.parse_string("7", parse_all=True) == ["7"]
+        assert expr.search_string("a 1+2 b 3") == [[["1", "+", "2"]], ["3"]]
+        with pytest.raises(pp.ParseError):
+            expr.parse_string("+1")
+
+    def test_precedence_levels(self):
+        term = pp.Word(pp.nums())
+        mul = pp.Forward()
+        add = pp.Forward()
+        mul <<= pp.MatchFirst([pp.Group(mul + pp.Literal("*") + term), term])
+        add <<= pp.MatchFirst([pp.Group(add + pp.Literal("+") + mul), mul])
+        assert add.parse_string("1+2*3*4+5", parse_all=True) == [
+            [["1", "+", [["2", "*", "3"], "*", "4"]], "+", "5"]
+        ]
+
+    def test_exclusive_with_packrat(self):
+        with pytest.raises(RuntimeError):
+            pp.enable_packrat()
+        pp.enable_packrat(force=True)
+        try:
+            assert pp.packrat_stats()["enabled"]
+            with pytest.raises(RuntimeError):
+                pp.enable_left_recursion()
+        finally:
+            pp.disable_packrat()
+
+
+# ============================================================================
+# af. Arrow columns
+# ============================================================================
+
+class _ArrowSchema(ctypes.Structure):
+    _fields_ = [("format", ctypes.c_char_p), ("name", ctypes.c_char_p),
+                ("metadata", ctypes.c_char_p), ("flags", ctypes.c_int64),
+                ("n_children", ctypes.c_int64), ("children", ctypes.c_void_p),
+                ("dictionary", ctypes.c_void_p), ("release", ctypes.c_void_p),
+                ("private_data", ctypes.c_void_p)]
+
+
+class _ArrowArray(ctypes.Structure):
+    _fields_ = [("length", ctypes.c_int64), ("null_count", ctypes.c_int64),
+                ("offset", ctypes.c_int64), ("n_buffers", ctypes.c_int64),
+                ("n_children", ctypes.c_int64), ("buffers", ctypes.c_void_p),
+                ("children", ctypes.c_void_p), ("dictionary", ctypes.c_void_p),
+                ("release", ctypes.c_void_p), ("private_data", ctypes.c_void_p)]
+
+
+_GET_SCHEMA = ctypes.CFUNCTYPE(ctypes.c_int, ctypes.c_void_p, ctypes.POINTER(_ArrowSchema))
+_GET_NEXT = ctypes.CFUNCTYPE(ctypes.c_int, ctypes.c_void_p, ctypes.POINTER(_ArrowArray))
+_GET_LAST_ERROR = ctypes.CFUNCTYPE(ctypes.c_char_p, ctypes.c_void_p)
+_RELEASE = ctypes.CFUNCTYPE(None, ctypes.c_void_p)
+
+
+class _ArrowArrayStream(ctypes.Structure):
+    _fields_ = [("get_schema", _GET_SCHEMA), ("get_next", _GET_NEXT),
+                ("get_last_error", _GET_LAST_ERROR), ("release", _RELEASE),
+                ("private_data", ctypes.c_void_p)]
+
+
+@_RELEASE
+def _release(ptr):
+    # release is the 9th pointer-sized field of both ArrowSchema and ArrowArray
+    ctypes.cast(ptr, ctypes.POINTER(ctypes.c_void_p))[8] = None
+
+
+_capsule_new = ctypes.pythonapi.PyCapsule_New
+_capsule_new.restype = ctypes.py_object
+_capsule_new.argtypes = [ctypes.c_void_p, ctypes.c_char_p, ctypes.c_void_p]
+
+
+class ArrowStrings:
+    """Arrow string column built with ctypes, so the tests don't need pyarrow.
+    Several chunks are exported as a stream, like a table column."""
+
+    def __init__(self, *chunks, large=False, fmt=None):
+        self.chunks = chunks
+        self.format = fmt or (b"U" if large else b"u")
+        self.offset_type = ctypes.c_int64 if large else ctypes.c_int32
+        self.keep = []
+
+    def _schema(self, out):
+        out.format = self.format
+        out.flags = 2
+        out.release = ctypes.cast(_release, ctypes.c_void_p)
+
+    def _array(self, values, out):
+        data = "".join(v or "" for v in values).encode()
+        offsets, bits = [0], 0
+        for i, v in enumerate(values):
+            offsets.append(offsets[-1] + len((v or "").encode()))
+            bits |= (v is not None) << i
+        buffers = [
+            ctypes.create_string_buffer(bits.to_bytes(len(values) // 8 + 1, "little")),
+            (self.offset_type * len(offsets))(*offsets),
+            ctypes.create_string_buffer(data),
+        ]
+        pointers = (ctypes.c_void_p * 3)(*(ctypes.addressof(b) for b in buffers))
+        self.keep += [buffers, pointers]
+        out.length = len(values)
+        out.null_count = values.count(None)
+        out.n_buffers = 3
+        out.buffers = ctypes.addressof(pointers)
+        out.release = ctypes.cast(_release, ctypes.c_void_p)
+
+    def __arrow_c_array__(self, requested_schema=None):
+        (values,) = self.chunks
+        schema, array = _ArrowSchema(), _ArrowArray()
+        self._schema(schema)
+        self._array(values, array)
+        self.keep += [schema, array]
+        return (_capsule_new(ctypes.addressof(schema), b"arrow_schema", None),
+                _capsule_new(ctypes.addressof(array), b"arrow_array", None))
+
+
+class ArrowStringChunks(ArrowStrings):
+    def __arrow_c_stream__(self, requested_schema=None):
+        pending = list(self.chunks)
+
+        def get_schema(_, out):
+            self._schema(out.contents)
+            return 0
+
+        def get_next(_, out):
+            if pending:
+                self._array(pending.pop(0), out.contents)
+            else:
+                out.contents.release = None
+            return 0
+
+        def release(ptr):
+            ctypes.cast(ptr, ctypes.POINTER(_ArrowArrayStream)).contents.release = _RELEASE()
+
+        stream = _ArrowArrayStream(_GET_SCHEMA(get_schema), _GET_NEXT(get_next),
+                                   _GET_LAST_ERROR(), _RELEASE(release))
+        self.keep.append(stream)
+        return _capsule_new(ctypes.addressof(stream), b"arrow_array_stream", None)
+
+
+class TestArrowColumn:
+    def test_first_token_per_row(self):
+        column = ArrowStrings(["ab12 cd", "  x", "99", None, "é1 z"])
+        out = pp.parse_arrow_column(pp.Word(pp.alphas()), column)
+        assert isinstance(out, pp.ArrowColumn)
+        assert out.to_pylist() == ["ab", "x", None, None, None]
+        assert len(out) == 5
+        assert out.null_count == 3
+
+    def test_all_tokens(self):
+        grammar = pp.Word(pp.alphas()) + pp.Group(pp.Word(pp.nums())) + pp.Suppress(";")
+        column = ArrowStrings(["a 1;", "b;", "cc 22; d"], large=True)
+        out = pp.parse_arrow_column(grammar, column, all_tokens=True)
+        assert out.to_pylist() == [["a", "1"], None, ["cc", "22"]]
+        assert pp.parse_arrow_column(pp.Suppress("a"), ArrowStrings(["a", "b"])).to_pylist() == [None, None]
+
+    def test_chunked_stream(self):
+        column = ArrowStringChunks(["a1", None], [], ["b2", "3"])
+        out = pp.parse_arrow_column(pp.Word(pp.alphanums()), column, all_tokens=True)
+        assert out.to_pylist() == [["a1"], None, ["b2"], ["3"]]
+
+    def test_parse_actions_and_numbers(self):
+        num = pp.Word(pp.nums()).set_parse_action(lambda t: int(t[0]) * 2)
+        out = pp.parse_arrow_column(num + pp.Number(), ArrowStrings(["4 1.5", "x"]), all_tokens=True)
+        assert out.to_pylist() == [["8", "1.5"], None]
+
+    def test_output_round_trips_as_input(self):
+        words = pp.parse_arrow_column(pp.Word(pp.alphas()), ArrowStrings(["ab1", "2", "cd"]))
+        again = pp.parse_arrow_column(pp.Regex("[a-z]"), words, all_tokens=True)
+        assert again.to_pylist() == [["a"], None, ["c"]]
+        lists = pp.parse_arrow_column(pp.Word(pp.alphas()), ArrowStrings(["x"]), all_tokens=True)
+        with pytest.raises(ValueError, match="format"):
+            pp.parse_arrow_column(pp.Word(pp.alphas()), lists)
+
+    def test_rejects_other_columns(self):
+        with pytest.raises(TypeError):
+            pp.parse_arrow_column(pp.Word(pp.alphas()), ["a", "b"])
+        with pytest.raises(ValueError, match="format"):
+            pp.parse_arrow_column(pp.Word(pp.alphas()), ArrowStrings(["1"], fmt=b"i"))
+
+    def test_pyarrow_and_pandas_round_trip(self):
+        pa = pytest.importorskip("pyarrow")
+        pd = pytest.importorskip("pandas")
+        table = pa.table({"raw": pa.chunked_array([["id 7", "nope"], [None, "id 42"]])})
+        grammar = pp.Suppress("id") + pp.Word(pp.nums())
+        out = pa.array(pp.parse_arrow_column(grammar, table.column("raw")))
+        assert out.type == pa.string()
+        assert out.to_pylist() == ["7", None, None, "42"]
+        series = out.to_pandas()
+        assert series.isna().tolist() == [False, True, True, False]
+        lists = pa.array(pp.parse_arrow_column(grammar, table.column("raw"), all_tokens=True))
+        assert lists.type == pa.list_(pa.string())
+        assert pd.Series(lists.to_pylist()).tolist()[3] == ["42"]
+
+
+# ============================================================================
+# ag. Streaming file processing
+# ============================================================================
+
+class TestProcessFileStreaming:
+    def write(self, d, text):
+        import os
+        path = os.path.join(d, "in.log")
+        with open(path, "w", encoding="utf-8", newline="") as f:
+            f.write(text)
+        return path
+
+    def test_batches_of_matching_lines(self):
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            path = self.write(d, "".join(f"line {i} id={i * 7}\r\n" if i % 3 == 0 else "noise\n" for i in range(1, 31)))
+            grammar = pp.Suppress("id=") + pp.Word(pp.nums())
+            batches = []
+            count = pp.process_file_streaming(path, grammar, batches.append, batch_size=4)
+            assert count == 10
+            assert [len(b) for b in batches] == [4, 4, 2]
+            lines = [item for batch in batches for item in batch]
+            assert lines[0] == (3, ["21"])
+            assert lines[-1] == (30, ["210"])
+            assert all(isinstance(tokens, pp.ParseResults) for _, tokens in lines)
+
+    def test_pattern_string_and_early_stop(self):
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            path = self.write(d, "ERROR a\nok\nERROR b\nERROR c\n")
+            seen = []
+
+            def first_batch_only(batch):
+                seen.extend(batch)
+                return False
+
+            assert pp.process_file_streaming(path, "ERROR", first_batch_only, batch_size=2) == 2
+            assert seen == [(1, ["ERROR"]), (3, ["ERROR"])]
+            # Only an actual False stops; None keeps going
+            assert pp.process_file_streaming(path, "ERROR", lambda batch: None, batch_size=1) == 3
+
+    def test_callback_errors_propagate(self):
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            path = self.write(d, "a\na\n")
+
+            def fail(batch):
+                raise KeyError("stop")
+
+            with pytest.raises(KeyError):
+                pp.process_file_streaming(path, "a", fail, batch_size=1)
+
+    def test_parse_actions_and_errors(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            path = self.write(d, "x 1\ny 2\n")
+            num = pp.Word(pp.nums()).set_parse_action(lambda t: int(t[0]) + 1)
+            batches = []
+            pp.process_file_streaming(path, num, batches.append)
+            assert batches == [[(1, [2]), (2, [3])]]
+            with pytest.raises(pp.FileProcessingError):
+                pp.process_file_streaming(os.path.join(d, "missing"), num, batches.append)
+            with pytest.raises(ValueError):
+                pp.process_file_streaming(path, num, batches.append, batch_size=0)
+
+
+class TestSplitFileProcess:
+    def streamed(self, path, grammar):
+        batches = []
+        pp.process_file_streaming(path, grammar, batches.append)
+        return [item for batch in batches for item in batch]
+
+    def test_matches_single_threaded_scan_at_every_chunk_size(self):
+        import os
+        import tempfile
+        # Short lines, so chunk splits land on, just before and just after matches
+        text = "".join(("key=%d\n" % i) if i % 2 else "x\r\n" for i in range(60)) + "key=last"
+        grammar = pp.Suppress("key=") + pp.Word(pp.alphanums())
+        with tempfile.TemporaryDirectory() as d:
+            path = os.path.join(d, "in.txt")
+            with open(path, "w", encoding="utf-8", newline="") as f:
+                f.write(text)
+            expected = self.streamed(path, grammar)
+            assert len(expected) == 31 and expected[-1] == (61, ["last"])
+            for chunk_size in range(1, 40):
+                assert pp.split_file_process(path, grammar, chunk_size=chunk_size) == expected, chunk_size
+            for num_chunks in (1, 2, 3, 7, 500):
+                assert pp.split_file_process(path, grammar, num_chunks=num_chunks) == expected, num_chunks
+            assert pp.split_file_process(path, grammar) == expected
+            # More threads than CPUs still runs each chunk once, in file order
+            for num_threads in (1, 2, 3, 8):
+                for chunk_size in (1, 5, 16):
+                    got = pp.split_file_process(path, grammar, chunk_size=chunk_size, num_threads=num_threads)
+                    assert got == expected, (num_threads, chunk_size)
+
+    def test_parse_actions_and_arguments(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            path = os.path.join(d, "in.txt")
+            with open(path, "w", encoding="utf-8") as f:
+                f.write("a 1\nb\nc 3\n")
+            calls = []
+            num = pp.Word(pp.nums()).set_parse_action(lambda t: calls.append(t[0]) or int(t[0]))
+            assert pp.split_file_process(path, num, chunk_size=2) == [(1, [1]), (3, [3])]
+            assert calls == ["1", "3"]
+            for kwargs in ({"num_chunks": 0}, {"chunk_size": 0}, {"num_chunks": 2, "chunk_size": 2},
+                           {"num_threads": 0}):
+                with pytest.raises(ValueError):
+                    pp.split_file_process(path, num, **kwargs)
+            with pytest.raises(pp.FileProcessingError):
+                pp.split_file_process(os.path.join(d, "missing"), num)
+            empty = os.path.join(d, "empty.txt")
+            open(empty, "w").close()
+            assert pp.split_file_process(empty, num, num_chunks=4) == []
+
+    def test_error_line_numbers_are_global(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            path = os.path.join(d, "in.txt")
+            with open(path, "wb") as f:
+                f.write(b"a\n" * 10 + b"\xff\n" + b"a\n" * 10)
+            with pytest.raises(pp.FileProcessingError) as exc:
+                pp.split_file_process(path, "a", num_chunks=4, num_threads=4)
+            assert exc.value.lineno == 11
+
+
+# ============================================================================
+# ah. CSV fields
+# ============================================================================
+
+class TestProcessCsvField:
+    def write(self, d, text):
+        import os
+        path = os.path.join(d, "data.csv")
+        with open(path, "w", encoding="utf-8", newline="") as f:
+            f.write(text)
+        return path
+
+    def test_quoted_fields(self):
+        import csv
+        import tempfile
+        text = (
+            'id,note,amount\r\n'
+            '1,"plain",10\r\n'
+            '2,"has, comma",20\r\n'
+            '3,"two\nlines, ""quoted""",30\r\n'
+            '\r\n'
+            '4,x,"4,0"\r\n'
+            '5,short\r\n'
+            '6,"last",60'
+        )
+        with tempfile.TemporaryDirectory() as d:
+            path = self.write(d, text)
+            num = pp.Word(pp.nums())
+            assert pp.process_csv_field(path, 2, num) == [
+                (0, "10", ["10"]), (1, "20", ["20"]), (2, "30", ["30"]), (3, "4,0", ["4"]), (5, "60", ["60"]),
+            ]
+            notes = pp.process_csv_field(path, 1, pp.Regex(r"[\s\S]+"))
+            with open(path, newline="", encoding="utf-8") as f:
+                expected = [row[1] for row in csv.reader(f) if row][1:]
+            assert [value for _, value, _ in notes] == expected
+            assert notes[2][1] == 'two\nlines, "quoted"'
+            assert pp.process_csv_field(path, 0, pp.Literal("id"), has_header=False) == [(0, "id", ["id"])]
+
+    def test_delimiter_and_tokens(self):
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            path = self.write(d, 'a;b\nk=1;"x;y"\nk=2;z\n')
+            pair = pp.Word(pp.alphas()) + pp.Suppress("=") + pp.Word(pp.nums()).set_parse_action(lambda t: int(t[0]))
+            assert pp.process_csv_field(path, 0, pair, delimiter=";") == [(0, "k=1", ["k", 1]), (1, "k=2", ["k", 2])]
+            assert pp.process_csv_field(path, 1, "x;", delimiter=";")[0][1] == "x;y"
+            for bad in ("", ";;", '"', "\n"):
+                with pytest.raises(ValueError):
+                    pp.process_csv_field(path, 0, pair, delimiter=bad)
+
+    def test_errors(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as d:
+            path = self.write(d, 'a,b\n1,"open\n2,3\n')
+            with pytest.raises(pp.FileProcessingError) as exc:
+                pp.process_csv_field(path, 0, pp.Word(pp.nums()))
+            assert "unterminated" in str(exc.value)
+            with pytest.raises(pp.FileProcessingError):
+                pp.process_csv_field(os.path.join(d, "missing.csv"), 0, "a")
+
+
+
+# ============================================================================
+# ai. Compiled grammars
+# ============================================================================
+
+class TestCompile:
+    def grammars(self):
+        ident = pp.Word(pp.alphas(), pp.alphanums() + "_")
+        num = pp.Word(pp.nums())
+        value = num | ident | pp.Group(pp.Suppress("(") + pp.ZeroOrMore(num) + pp.Suppress(")"))
+        call = ident + pp.Suppress("(") + pp.Optional(value + pp.ZeroOrMore(pp.Suppress(",") + value)) + pp.Suppress(")")
+        stmt = pp.Keyword("let") + ident + "=" + (call | value) + pp.Optional(pp.Literal(";"))
+        return [
+            num,
+            pp.Literal("ab"),
+            pp.Keyword("if"),
+            value,
+            call,
+            stmt,
+            pp.ZeroOrMore(pp.Group(ident + pp.Optional(num))),
+            pp.ZeroOrMore(pp.Optional(num)),
+            pp.Literal("a") + pp.ZeroOrMore(pp.Literal("b") | pp.Literal("bc")) + pp.Literal("c"),
+            pp.Group(pp.Group(pp.Optional(num)) + pp.Suppress(pp.ZeroOrMore("x"))),
+            pp.Regex(r"[a-z]+\d*"),
+            pp.Regex(r"^\s*\d") | pp.Regex(r"[(](?:\d\s*)*\)"),
+            pp.Regex(r"\w+") + pp.Literal("=") + pp.Regex(r"[a-z]\w*|\d+") + pp.Optional(";"),
+        ]
+
+    corpus = [
+        "", "   ", "42", "  42 rest", "ab", "abc", "if", "iffy", "if x", "f()", "f( 1 , x , (2 3) )",
+        "f(1,", "let x = f(1, y);", "let x = 5", "let  x=(1 2 3) ;", "letx = 5", "x 1 y z 2",
+        "a b bc c", "abbc", "ab c", "1 x x", "x", "(((", "été 12 ab", "a\tb\n 7", "key=val;", "k = 12",
+    ]
+
+    def test_matches_interpreted(self):
+        for grammar in self.grammars():
+            compiled = pp.compile(grammar)
+            for text in self.corpus:
+                for parse_all in (False, True):
+                    try:
+                        expected = grammar.parse_string(text, parse_all=parse_all)
+                    except pp.ParseError as e:
+                        with pytest.raises(pp.ParseError) as exc:
+                            compiled.parse_string(text, parse_all=parse_all)
+                        assert exc.value.loc == e.loc, (grammar, text)
+                    else:
+                        assert compiled.parse_string(text, parse_all=parse_all) == expected, (grammar, text)
+                assert compiled.search_string(text) == grammar.search_string(text), (grammar, text)
+            # Some classes batch-parse through fast paths with their own output
+            # shape; a Group around the grammar takes the generic path
+            grouped = pp.Group(grammar).parse_batch(self.corpus)
+            assert compiled.parse_batch(self.corpus) == [r[0] if r else [] for r in grouped], grammar
+
+    def test_program(self):
+        grammar = pp.Literal("a") + pp.Optional(pp.Word(pp.nums()))
+        compiled = pp.compile(grammar)
+        assert len(compiled) > 0
+        assert "CompiledGrammar" in repr(compiled)
+        assert str(compiled.grammar) == str(grammar)
+        assert list(pp.compile("ab").parse_string(" ab")) == ["ab"]
+
+    def test_unsupported(self):
+        for grammar in (
+            pp.Regex(r"(a)+"),
+            pp.Regex("a+", as_match=True),
+            pp.OneOrMore("a"),
+            pp.Word(pp.nums()).set_parse_action(lambda t: int(t[0])),
+            pp.Word(pp.nums())("n"),
+            pp.ZeroOrMore("a", stop_on="b"),
+            pp.Literal("a") - pp.Literal("b"),
+        ):
+            with pytest.raises(pp.PyparsingError) as exc:
+                pp.compile(grammar)
+            assert "cannot compile" in str(exc.value)
+
+
+
+# ============================================================================
+# aj. Multi-literal scanning
+# ============================================================================
+
+class TestFastScanner:
+    literals = ["he", "she", "his", "hers", "her", "e", "HIS"]
+    texts = ["", "ushers", "she sells his hers", "her_s he, his!", "Shehis HERS", "ééhe hé"]
+
+    def naive(self, text, caseless=False, overlapping=False, whole_words=False):
+        def ident(c):
+            return c.isascii() and (c.isalnum() or c == "_")
+
+        data = text.encode()
+        fold = (lambda b: b.lower()) if caseless else (lambda b: b)
+        found = []
+        for i, lit in enumerate(self.literals):
+            pat = fold(lit.encode())
+            for start in range(len(data)):
+                end = start + len(pat)
+                if fold(data[start:end]) != pat:
+                    continue
+                if whole_words and (
+                    (start > 0 and ident(chr(data[start - 1]))) or (end < len(data) and ident(chr(data[end])))
+                ):
+                    continue
+                found.append((i, start, end))
+        if overlapping:
+            return sorted(found, key=lambda m: (m[1], m[2], m[0]))
+        taken, nxt = [], 0
+        for m in sorted(found, key=lambda m: (m[1], -m[2], m[0])):
+            if m[1] >= nxt:
+                taken.append(m)
+                nxt = m[2]
+        return taken
+
+    def test_matches_naive_scan(self):
+        for caseless in (False, True):
+            for overlapping in (False, True):
+                for whole_words in (False, True):
+                    opts = dict(caseless=caseless, overlapping=overlapping, whole_words=whole_words)
+                    scanner = pp.FastScanner(self.literals, **opts)
+                    for text in self.texts:
+                        expected = self.naive(text, **opts)
+                        assert scanner.scan(text) == expected, (opts, text)
+                        assert scanner.count(text) == len(expected), (opts, text)
+                    assert scanner.parallel_scan(self.texts) == [scanner.scan(t) for t in self.texts]
+                    assert scanner.parallel_scan(self.texts, num_threads=3) == [scanner.scan(t) for t in self.texts]
+
+    def test_one_text_in_chunks(self):
+        import random
+        rng = random.Random(7)
+        literals = ["ab", "bab", "abab", "b\nab", "abababababab", "é", "éa", "a b"]
+        # Dense, repetitive text so matches straddle many chunk boundaries, as one
+        # line and as many
+        unit = "".join(rng.choice(["a", "b", "ab", "é", " ", "\n"]) for _ in range(4000))
+        one_line = (unit.replace("\n", "b") * 260)[: 3_400_000]
+        lines = (unit * 260)[: 3_400_000]
+        for overlapping in (False, True):
+            for whole_words in (False, True):
+                scanner = pp.FastScanner(literals, overlapping=overlapping, whole_words=whole_words)
+                for text in (one_line, lines):
+                    expected = scanner.scan(text)
+                    for threads in (1, 7, 50):
+                        assert scanner.parallel_scan(text, num_threads=threads) == expected, (
+                            overlapping, whole_words, threads,
+                        )
+                assert scanner.parallel_scan(lines.encode(), num_threads=9) == scanner.scan(lines)
+        assert pp.FastScanner(["a"]).parallel_scan("") == []
+
+    def test_offsets_and_errors(self):
+        scanner = pp.FastScanner(["é", "ab"])
+        assert len(scanner) == 2
+        assert scanner.scan("xéab") == [(0, 1, 3), (1, 3, 5)]
+        with pytest.raises(pp.PatternError):
+            pp.FastScanner([])
+        with pytest.raises(pp.PatternError):
+            pp.FastScanner(["a", ""])
+        with pytest.raises(ValueError):
+            scanner.parallel_scan(["a"], num_threads=0)
+        with pytest.raises(ValueError):
+            scanner.parallel_scan(["a", 1])
+
+    def test_one_of_many_literals(self):
+        words = " ".join("kw%d" % i for i in range(30))
+        # Listed first wins, as with a short MatchFirst: kw1 masks kw10..kw19
+        kw = pp.one_of(words + " abc ab")
+        assert list(kw.parse_string("kw12")) == ["kw1"]
+        assert list(kw.parse_string("  ab")) == ["ab"]
+        assert list(kw.parse_string("abc")) == ["abc"]
+        assert [list(t) for t in kw.search_string("x kw29 ab kw3")] == [["kw2"], ["ab"], ["kw3"]]
+        assert kw.matches("kw0")
+        assert not kw.matches("zz")
+        with pytest.raises(pp.ParseError):
+            kw.parse_string("zz")
+        stmt = pp.Group(kw + pp.ZeroOrMore(kw))
+        assert list(stmt.parse_string("kw5 ab kw7")[0]) == ["kw5", "ab", "kw7"]
+
+
+# ============================================================================
+# ak. Character classes
+# ============================================================================
+
+class TestCharClass:
+    def test_ranges_categories_and_negation(self):
+        ident = pp.CharClass("a-zA-Z0-9_")
+        assert all(c in ident for c in "aZ09_")
+        assert "-" not in ident and "é" not in ident
+        letters = pp.CharClass(r"\p{L}")
+        assert all(c in letters for c in "aïé变Ω")
+        assert "1" not in letters and "_" not in letters
+        digits = pp.CharClass(r"\p{Nd}")
+        assert "7" in digits and "٣" in digits and "a" not in digits
+        not_digit = pp.CharClass("0-9", negate=True)
+        assert not_digit.spec == "^0-9"
+        assert "5" not in not_digit and "x" in not_digit and "变" in not_digit
+        assert "-" in pp.CharClass("^a-z") and "q" not in pp.CharClass("^a-z")
+        assert "x" in pp.CharClass("x")
+        assert repr(pp.CharClass("a-c")) == "CharClass('a-c')"
+
+    def test_errors(self):
+        for bad in ["z-a", r"\p{Bogus}", ""]:
+            with pytest.raises(pp.PatternError):
+                pp.CharClass(bad)
+        with pytest.raises(ValueError):
+            "ab" in pp.CharClass("a-z")
+        with pytest.raises(TypeError):
+            pp.Word(3)
+
+    def test_word_takes_a_class(self):
+        word = pp.Word(pp.CharClass(r"\p{L}"))
+        assert list(word.parse_string("naïve rest")) == ["naïve"]
+        assert list(word.parse_string("变量 = 1")) == ["变量"]
+        assert [list(t) for t in word.search_string("x1 ÿz 变量")] == [["x"], ["ÿz"], ["变量"]]
+        with pytest.raises(pp.ParseError):
+            word.parse_string("123")
+        ident = pp.Word(pp.CharClass(r"\p{L}_"), pp.CharClass(r"\p{L}\p{Nd}_"))
+        assert list(ident.parse_string("été_2x+")) == ["été_2x"]
+        assert [list(t) for t in ident.parse_batch(["a1", "Ωmega"])] == [["a1"], ["Ωmega"]]
+        assert list(pp.Word(pp.CharClass("a-z"), exclude_chars="q").parse_string("abqz")) == ["ab"]
+        # A plain string keeps pyparsing's meaning: the characters themselves
+        assert list(pp.Word("a-z").parse_string("a-zz")) == ["a-zz"]
+
+    def test_class_word_round_trips(self):
+        import pickle
+        word = pp.Word(pp.CharClass(r"\p{Lu}"), pp.CharClass(r"\p{Ll}"))
+        copied = pickle.loads(pickle.dumps(word))
+        assert list(copied.parse_string("Ñandú")) == ["Ñandú"]
+        with pytest.raises(pp.ParseError):
+            copied.parse_string("ñandú")
+
+
+# ============================================================================
+# al. Multi-regex batches
+# ============================================================================
+
+class TestBatchRegexSet:
+    patterns = [r"\d+", r"^ERROR", r"[A-Z]{3}", r"é+", r"foo|bar", r"\bx\b"]
+    strings = ["", "a", "ERROR 42", "café", "foobar ABC", "x", "no match here", "été 7 x"]
+
+    def test_matches_patterns_one_by_one(self):
+        import re
+        compiled = [re.compile(p) for p in self.patterns]
+        expected = [[i for i, r in enumerate(compiled) if r.search(s)] for s in self.strings]
+        assert pp.batch_regex_set(self.patterns, self.strings) == expected
+        strings = self.strings * 700
+        assert pp.batch_regex_set(self.patterns, strings, num_threads=3) == expected * 700
+
+    def test_spans(self):
+        import re
+        spans = pp.batch_regex_set(self.patterns, self.strings, spans=True)
+        for s, found in zip(self.strings, spans):
+            data = s.encode()
+            expected = []
+            for i, p in enumerate(self.patterns):
+                m = re.search(p.encode(), data)
+                if m:
+                    expected.append((i, m.start(), m.end()))
+            assert found == expected, s
+
+    def test_errors(self):
+        with pytest.raises(pp.PatternError) as exc:
+            pp.batch_regex_set(["ok", "(", "a", "[z-a]"], ["x"])
+        assert "pattern 1" in str(exc.value) and "pattern 3" in str(exc.value)
+        assert "pattern 0" not in str(exc.value)
+        with pytest.raises(ValueError):
+            pp.batch_regex_set(["a"], ["a", 1])
+        with pytest.raises(ValueError):
+            pp.batch_regex_set(["a"], ["a"], num_threads=0)
+        assert pp.batch_regex_set([], ["a", "b"]) == [[], []]
+
+
+# ============================================================================
+# am. Batch replacement
+# ============================================================================
+
+class TestBatchReplace:
+    logs = ["user=alice ip=10.0.0.1", "nothing here", "", "ip=1.2.3.4 ip=5.6.7.8 ip=9.9.9.9", "é ip=0.0.0.0"]
+
+    def test_literal_and_fixed_text(self):
+        out, counts = pp.batch_replace(["a.b.a", "xyz", "aaa"], "a", "[$0]")
+        assert out == ["[a].b.[a]", "xyz", "[a][a][a]"]
+        assert counts == [2, 0, 3]
+        # Regex metacharacters in a str pattern are literal
+        assert pp.batch_replace(["a.b"], ".", "-")[0] == ["a-b"]
+        assert pp.batch_replace(["cost"], "cost", "$$5 $$ $x")[0] == ["$5 $ $x"]
+
+    def test_regex_groups(self):
+        ip = pp.Regex(r"ip=(\d+)\.(\d+)\.\d+\.\d+")
+        out, counts = pp.batch_replace(self.logs, ip, "ip=$1.${2}x.x.x")
+        assert out == ["user=alice ip=10.0x.x.x", "nothing here", "",
+                       "ip=1.2x.x.x ip=5.6x.x.x ip=9.9x.x.x", "é ip=0.0x.x.x"]
+        assert counts == [1, 0, 0, 3, 1]
+        out, counts = pp.batch_replace(self.logs, ip, "<ip>", max_replacements=2)
+        assert out[3] == "<ip> <ip> ip=9.9.9.9" and counts[3] == 2
+        out, counts = pp.batch_replace(self.logs, ip, "<ip>", first_only=True)
+        assert out[3] == "<ip> ip=5.6.7.8 ip=9.9.9.9" and counts == [1, 0, 0, 1, 1]
+        # Optional groups that didn't take part render as nothing
+        assert pp.batch_replace(["ab", "b"], pp.Regex("(a)?b"), "[$1]")[0] == ["[a]", "[]"]
+        # Empty matches are not replaced
+        assert pp.batch_replace(["abc"], pp.Regex("x*"), "-") == (["abc"], [0])
+
+    def test_elements(self):
+        number = pp.Word(pp.nums())
+        out, counts = pp.batch_replace(["a1 b22 c", "none"], number, "#$0#")
+        assert out == ["a#1# b#22# c", "none"] and counts == [2, 0]
+        with pytest.raises(pp.PatternError):
+            pp.batch_replace(["a1"], number, "$1")
+
+    def test_unchanged_inputs_are_returned_as_is(self):
+        text = "no match " * 10
+        out, counts = pp.batch_replace([text], "zzz", "y")
+        assert out[0] is text and counts == [0]
+        out, counts = pp.batch_replace(["aXb"], "X", "Y", max_replacements=0)
+        assert out == ["aXb"] and counts == [0]
+
+    def test_threads_and_errors(self):
+        strings = self.logs * 1000
+        expected = pp.batch_replace(strings, "ip=", "IP:", num_threads=1)
+        assert pp.batch_replace(strings, "ip=", "IP:", num_threads=3) == expected
+        with pytest.raises(pp.PatternError):
+            pp.batch_replace(["a"], pp.Regex("(a)"), "$2")
+        with pytest.raises(pp.PatternError):
+            pp.batch_replace(["a"], "", "b")
+        with pytest.raises(ValueError):
+            pp.batch_replace(["a", 1], "a", "b")
+        with pytest.raises(ValueError):
+            pp.batch_replace(["a"], "a", "b", num_threads=0)
+
+
+# ============================================================================
+# an. Streaming batch counts
+# ============================================================================
+
+class TestStreamBatchCount:
+    def test_matches_parse_batch_count(self):
+        word = pp.Word(pp.alphas())
+        inputs = ["abc", "123", " x", "", "é", "Zed 9"] * 50
+        result = pp.stream_batch_count(word, iter(inputs), chunk_size=7)
+        assert result == {"inputs": len(inputs), "matches": word.parse_batch_count(inputs)}
+
+    def test_generators_bytes_and_patterns(self):
+        def lines():
+            for i in range(5000):
+                yield ("id=%d" % i) if i % 3 else ("name=%d" % i).encode()
+        ident = pp.Literal("id=") + pp.Word(pp.nums())
+        name = pp.Literal("name=")
+        result = pp.stream_batch_count([ident, name, "x"], lines(), chunk_size=999, num_threads=2)
+        assert result == {"inputs": 5000, "matches": 5000, "per_pattern": [3333, 1667, 0]}
+        assert pp.stream_batch_count("a", []) == {"inputs": 0, "matches": 0}
+        assert pp.stream_batch_count("é", [b"\xc3\xa9t\xc3\xa9"])["matches"] == 1
+
+    def test_errors(self):
+        with pytest.raises(ValueError, match="input 2 is not valid UTF-8"):
+            pp.stream_batch_count("a", ["a", b"b", b"\xff"])
+        with pytest.raises(ValueError):
+            pp.stream_batch_count("a", ["a", 1])
+        with pytest.raises(ValueError):
+            pp.stream_batch_count("a", ["a"], chunk_size=0)
+        with pytest.raises(TypeError):
+            pp.stream_batch_count("a", 5)
+
+        def failing():
+            yield "a"
+            raise RuntimeError("cursor closed")
+        with pytest.raises(RuntimeError, match="cursor closed"):
+            pp.stream_batch_count("a", failing())
+
+
+# ============================================================================
+# ao. Compact batch results
+# ============================================================================
+
+class TestCompactResults:
+    inputs = ["abc def", "", "123", "  éte x", "a", "变量 b", "!"]
+
+    def reference(self, grammar, inputs):
+        def flatten(items):
+            for item in items:
+                if isinstance(item, list) or hasattr(item, "as_list"):
+                    yield from flatten(item)
+                else:
+                    yield str(item)
+        rows = []
+        for s in inputs:
+            try:
+                rows.append(list(flatten(grammar.parse_string(s))))
+            except pp.ParseError:
+                rows.append([])
+        return rows
+
+    def test_matches_parse_string(self):
+        word = pp.Word(pp.CharClass(r"\p{L}"))
+        grammars = [word, pp.Group(word + pp.Optional(word)), pp.ZeroOrMore(word), "abc"]
+        for grammar in grammars:
+            element = grammar if not isinstance(grammar, str) else pp.Literal(grammar)
+            compact = pp.parse_batch_compact(grammar, self.inputs)
+            expected = self.reference(element, self.inputs)
+            assert len(compact) == len(self.inputs)
+            assert compact.to_list() == expected
+            assert [compact[i] for i in range(len(compact))] == expected
+            assert compact[-1] == expected[-1]
+            assert compact.token_count == sum(map(len, expected))
+        many = self.inputs * 1000
+        assert pp.parse_batch_compact(word, many, num_threads=3).to_list() == self.reference(word, self.inputs) * 1000
+
+    def test_buffers_round_trip(self):
+        compact = pp.parse_batch_compact(pp.OneOrMore(pp.Word(pp.CharClass(r"\p{L}"))), self.inputs)
+        buffers = compact.buffers()
+        assert buffers["inputs"] == len(self.inputs)
+        data = buffers["data"]
+        index, start, end = (memoryview(buffers[k]).cast("q").tolist() for k in ("input_index", "token_start", "token_end"))
+        rows = [[] for _ in range(buffers["inputs"])]
+        for i, a, b in zip(index, start, end):
+            rows[i].append(data[a:b].decode())
+        assert rows == compact.to_list()
+        decoded = pp.decode_compact(buffers)
+        assert decoded.to_list() == compact.to_list()
+        assert repr(decoded) == "CompactResults(7 inputs, %d tokens)" % compact.token_count
+
+    def test_errors(self):
+        compact = pp.parse_batch_compact(pp.Word(pp.alphas()), ["ab", "cd"])
+        with pytest.raises(IndexError):
+            compact[2]
+        buffers = compact.buffers()
+        for key, value in [("token_end", buffers["token_end"][:-1]), ("data", b"\xff\xfe\xfd\xfc"),
+                           ("inputs", 1), ("data", b"a")]:
+            bad = dict(buffers)
+            bad[key] = value
+            with pytest.raises(ValueError):
+                pp.decode_compact(bad)
+        del buffers["data"]
+        with pytest.raises(ValueError):
+            pp.decode_compact(buffers)
+        with pytest.raises(ValueError):
+            pp.parse_batch_compact("a", ["a", 1])
+
+
+# ============================================================================
+# ap. Aggregate match statistics
+# ============================================================================
+
+class TestAggregateStats:
+    inputs = ["abc 12 de", "", "1 2 3", "xyz", "  a  bb   ccc 4444", "!!"]
+
+    def reference(self, grammars, inputs):
+        per_pattern = [0] * len(grammars)
+        per_input, lengths, starts = [], [], []
+        for s in inputs:
+            found = 0
+            for i, g in enumerate(grammars):
+                spans = [(start, end) for _, start, end in g.scan_string(s)]
+                assert len(spans) == g.search_string_count(s)
+                per_pattern[i] += len(spans)
+                found += len(spans)
+                lengths += [end - start for start, end in spans]
+                starts += [start for start, _ in spans]
+            per_input.append(found)
+        return per_pattern, per_input, lengths, starts
+
+    def test_matches_reference(self):
+        word, number = pp.Word(pp.alphas()), pp.Word(pp.nums())
+        for grammar in [word, [word, number], [number, "b"]]:
+            grammars = grammar if isinstance(grammar, list) else [grammar]
+            elements = [pp.Literal(g) if isinstance(g, str) else g for g in grammars]
+            per_pattern, per_input, lengths, starts = self.reference(elements, self.inputs)
+            stats = pp.aggregate_stats(grammar, self.inputs)
+            assert stats["inputs"] == len(self.inputs)
+            assert stats["per_pattern"] == per_pattern
+            assert stats["per_input"] == per_input
+            assert stats["matches"] == sum(per_pattern)
+            assert stats["inputs_without_matches"] == per_input.count(0)
+            assert stats["match_length"] == {"min": min(lengths), "max": max(lengths),
+                                             "mean": sum(lengths) / len(lengths)}
+            edges = stats["start_positions"]["bin_edges"]
+            counts = stats["start_positions"]["counts"]
+            assert edges[:5] == [0, 1, 2, 4, 8][:len(edges)]
+            for lo, hi, count in zip(edges, edges[1:] + [float("inf")], counts):
+                assert count == sum(lo <= st < hi for st in starts)
+            assert sum(counts) == stats["matches"]
+
+    def test_threads_and_edge_cases(self):
+        word = pp.Word(pp.alphas())
+        many = self.inputs * 1000
+        assert pp.aggregate_stats(word, many, num_threads=3) == pp.aggregate_stats(word, many, num_threads=1)
+        empty = pp.aggregate_stats(word, ["123", ""])
+        assert empty["matches"] == 0 and empty["inputs_without_matches"] == 2
+        assert empty["match_length"] is None
+        assert empty["start_positions"] == {"bin_edges": [], "counts": []}
+        # Lengths are in characters
+        assert pp.aggregate_stats(pp.Word(pp.CharClass(r"\p{L}")), ["été"])["match_length"]["max"] == 3
+        with pytest.raises(ValueError):
+            pp.aggregate_stats(word, ["a", 1])
+
+    def test_search_counts_on_texts_with_a_repeated_start(self):
+        # Texts opening with two equal blocks but not periodic after that
+        for text in ["  a  bb   ccc 4444", "xxab b", "abab" * 3 + "b ab"]:
+            for g in [pp.Word(pp.alphas()), pp.Literal("b"), pp.Literal("ab")]:
+                n = len(list(g.scan_string(text)))
+                assert g.search_string_count(text) == n
+                assert len(g.search_string(text)) == n
+
+
+# ============================================================================
+# aq. Length histograms
+# ============================================================================
+
+class TestLengthHistogram:
+    inputs = ["", "a", "abcd", "été", "x" * 10000, "hello world", "ab cd efg"]
+
+    def test_input_lengths(self):
+        hist = pp.length_histogram(self.inputs, bin_width=2, num_bins=3)
+        assert hist["bin_edges"] == [0.0, 2.0, 4.0, 6.0]
+        # lengths 0, 1, 4, 3, 10000, 11, 9
+        assert hist["counts"] == [2, 1, 1]
+        assert hist["underflow"] == 0 and hist["overflow"] == 3
+        assert hist["total"] == len(self.inputs)
+        hist = pp.length_histogram(self.inputs, min=1, max=12)
+        assert len(hist["counts"]) == 11 and hist["underflow"] == 1 and hist["overflow"] == 1
+        assert hist["counts"][2] == 1 and hist["counts"][8] == 1 and hist["counts"][10] == 1
+        hist = pp.length_histogram(self.inputs, num_bins=4, max=20)
+        assert hist["bin_edges"] == [0.0, 5.0, 10.0, 15.0, 20.0]
+        assert hist["counts"] == [4, 1, 1, 0] and hist["overflow"] == 1
+        assert pp.length_histogram([], num_bins=2) == {
+            "bin_edges": [0.0, 1.0, 2.0], "counts": [0, 0], "underflow": 0, "overflow": 0, "total": 0}
+
+    def test_match_lengths(self):
+        word = pp.Word(pp.CharClass(r"\p{L}"))
+        hist = pp.length_histogram(self.inputs, word, max=6)
+        lengths = [len(tokens[0]) for t in self.inputs for tokens, _, _ in word.scan_string(t)]
+        assert hist["total"] == len(lengths)
+        assert hist["counts"] == [lengths.count(n) for n in range(6)]
+        assert hist["overflow"] == sum(n >= 6 for n in lengths)
+        many = self.inputs * 1000
+        assert pp.length_histogram(many, word, max=6, num_threads=3) == pp.length_histogram(many, word, max=6, num_threads=1)
+
+    def test_errors(self):
+        for kwargs in [{}, {"bin_width": 0, "num_bins": 2}, {"num_bins": 0}, {"max": 0},
+                       {"min": 5, "max": 3}, {"bin_width": 2, "num_bins": 2, "max": 5}]:
+            with pytest.raises(ValueError):
+                pp.length_histogram(["a"], **kwargs)
+        assert pp.length_histogram(["a"], bin_width=2, num_bins=2, max=4)["counts"] == [1, 0]
+        with pytest.raises(ValueError):
+            pp.length_histogram(["a", 1], num_bins=2)
+
+
+# ============================================================================
+# ar. Furthest failure
+# ============================================================================
+
+class TestFurthestFailure:
+    ident = pp.Word(pp.alphas())
+    stmt = pp.Group(ident + pp.Literal("=") + (pp.Word(pp.nums()) | ident) + pp.Literal(";"))
+    program = pp.OneOrMore(stmt)
+
+    def test_reported_past_final_error(self):
+        with pytest.raises(pp.ParseError) as exc:
+            self.program.parse_string("a = 1;\nb = 2;\nc = 3 d", parse_all=True)
+        assert exc.value.loc == 14
+        assert exc.value.msg.startswith("Expected end of text")
+        assert exc.value.msg.endswith("; farthest failure at line 3 col 7, expected ';'")
+
+    def test_expected_alternatives(self):
+        with pytest.raises(pp.ParseError) as exc:
+            self.program.parse_string("a = 1; b = ;", parse_all=True)
+        assert exc.value.msg.endswith(
+            "; farthest failure at line 1 col 12, expected one of: W:(01234567...), W:(ABCDEFGH...)")
+        with pytest.raises(pp.ParseError) as exc:
+            self.program.parse_string("x")
+        assert exc.value.msg == "Expected at least one match; farthest failure at line 1 col 2, expected '='"
+
+    def test_not_reported_at_final_error(self):
+        with pytest.raises(pp.ParseError) as exc:
+            self.program.parse_string("1")
+        assert exc.value.msg == "Expected at least one match"
+        with pytest.raises(pp.ParseError) as exc:
+            (pp.Literal("a") | pp.Literal("b")).parse_string("c")
+        assert exc.value.msg == "Expected 'a' | 'b'"
+
+    def test_reset_per_parse(self):
+        with pytest.raises(pp.ParseError):
+            self.program.parse_string("a = 1; b = 2; c = 3 d", parse_all=True)
+        with pytest.raises(pp.ParseError) as exc:
+            self.program.parse_string("a", parse_all=True)
+        assert "line 1 col 2" in exc.value.msg
+
+
+
+# ============================================================================
+# as. Line and column helpers
+# ============================================================================
+
+class TestLineColHelpers:
+    def test_ascii(self):
+        s = "ab\ncd\n\nef"
+        expected = [(1, 1, "ab"), (1, 2, "ab"), (1, 3, "ab"), (2, 1, "cd"), (2, 2, "cd"),
+                    (2, 3, "cd"), (3, 1, ""), (4, 1, "ef"), (4, 2, "ef"), (4, 3, "ef")]
+        assert [(pp.lineno(loc, s), pp.col(loc, s), pp.line(loc, s))
+                for loc in range(len(s) + 1)] == expected
+
+    def test_multibyte_columns(self):
+        s = "xé\nüñ z"
+        # byte offsets: x=0 é=1..3 \n=3 ü=4..6 ñ=6..8 ' '=8 z=9
+        assert [pp.col(loc, s) for loc in (0, 1, 2, 3, 4, 6, 8, 9)] == [1, 2, 2, 3, 1, 2, 3, 4]
+        assert pp.lineno(9, s) == 2 and pp.line(9, s) == "üñ z"
+        assert pp.lineno(100, s) == 2 and pp.col(100, s) == 5
+
+    def test_parse_with_tabs(self):
+        word = pp.Word(pp.alphas())
+        assert word.parse_with_tabs() is word
+        with pytest.raises(pp.ParseError) as exc:
+            (word + word).parse_with_tabs().parse_string("\tab\t1")
+        assert (exc.value.loc, exc.value.col) == (4, 5)
+
+
+
+# ============================================================================
+# at. Parsing files
+# ============================================================================
+
+class TestParseFile:
+    def write(self, d, data):
+        import os
+        path = os.path.join(d, "in.txt")
+        with open(path, "wb") as f:
+            f.write(data)
+        return path
+
+    def test_parse_file_like_parse_string(self):
+        import pathlib
+        import tempfile
+        grammar = pp.Word(pp.alphas()) + pp.Suppress("=") + pp.Word(pp.nums())
+        with tempfile.TemporaryDirectory() as d:
+            path = self.write(d, b"width = 80\n")
+            assert grammar.parse_file(path) == ["width", "80"]
+            assert grammar.parse_file(pathlib.Path(path), parse_all=True) == ["width", "80"]
+            path = self.write(d, b"width = 80\nheight = x\n")
+            with pytest.raises(pp.ParseError) as exc:
+                (grammar * (1, None)).parse_file(path, parse_all=True)
+            assert exc.value.lineno == 2
+
+    def test_encodings(self):
+        import tempfile
+        word = pp.Word(pp.alphas() + "éü")
+        with tempfile.TemporaryDirectory() as d:
+            path = self.write(d, "café\n".encode("utf-8"))
+            assert word.parse_file(path) == ["café"]
+            path = self.write(d, "ok\nmüde\n".encode("latin-1"))
+            assert word.search_file(path, encoding="latin-1") == [["ok"], ["müde"]]
+            with pytest.raises(pp.FileProcessingError) as exc:
+                word.parse_file(path)
+            assert "0xfc" in exc.value.os_error and exc.value.lineno == 2
+            with pytest.raises(pp.FileProcessingError):
+                word.parse_file(path, encoding="ascii")
+            with pytest.raises(LookupError):
+                word.parse_file(path, encoding="klingon")
+
+    def test_search_and_scan_file(self):
+        import os
+        import tempfile
+        num = pp.Word(pp.nums())
+        with tempfile.TemporaryDirectory() as d:
+            path = self.write(d, b"a 1\nb 22\nc 333\n")
+            assert num.search_file(path) == [["1"], ["22"], ["333"]]
+            matches = num.scan_file(path)
+            assert next(matches) == (["1"], 2, 3)
+            assert [(start, end) for _, start, end in matches] == [(6, 8), (11, 14)]
+            with pytest.raises(pp.FileProcessingError) as exc:
+                num.scan_file(os.path.join(d, "missing"))
+            assert exc.value.errno == 2
+
 if __name__ == "__main__":
     pytest.main([__file__, "-v"])
+
+
+# ============================================================================
+# au. Fixed columns
+# ============================================================================
+
+class TestGoToColumn:
+    """GoToColumn advances to a character column of the current line."""
+
+    def test_fixed_width_record(self):
+        name = pp.Word(pp.alphas())
+        amount = pp.Word(pp.nums())
+        record = name + pp.GoToColumn(20, suppress=True) + amount
+        report = "widgets            42\ngadgets            7\n"
+        assert (record * (1, None)).parse_string(report) == ["widgets", "42", "gadgets", "7"]
+
+    def test_skipped_text_is_token(self):
+        expr = pp.Word(pp.alphas()) + pp.GoToColumn(8) + pp.Word(pp.nums())
+        assert expr.parse_string("ab cd  12") == ["ab", " cd  ", "12"]
+        # Already at the column: nothing is passed over
+        assert (pp.Word(pp.alphas()) + pp.GoToColumn(3)).parse_string("ab") == ["ab", ""]
+
+    def test_past_column_or_short_line_fails(self):
+        expr = pp.Word(pp.alphas()) + pp.GoToColumn(3) + pp.Word(pp.nums())
+        with pytest.raises(pp.ParseError):
+            expr.parse_string("abcd 1")
+        with pytest.raises(pp.ParseError):
+            (pp.Word(pp.alphas()) + pp.GoToColumn(10)).parse_string("ab\n        1")
+
+    def test_columns_count_characters(self):
+        expr = pp.Word(pp.alphas() + "éü") + pp.GoToColumn(7, suppress=True) + pp.Word(pp.nums())
+        assert expr.parse_string("café  5") == ["café", "5"]
+        assert expr.search_string("x\nmüde  9") == [["müde", "9"]]
+
+    def test_invalid_column(self):
+        with pytest.raises(pp.PatternError):
+            pp.GoToColumn(0)
+        assert pp.GoToColumn(4).colno == 4
+
+    def test_pickle(self):
+        import pickle
+        expr = pp.Word(pp.alphas()) + pp.GoToColumn(5, suppress=True) + pp.Word(pp.nums())
+        assert pickle.loads(pickle.dumps(expr)).parse_string("ab  1") == ["ab", "1"]
+
+
+# ============================================================================
+# av. Fuzzy literals
+# ============================================================================
+
+class TestCloseMatch:
+    """CloseMatch accepts text with a few substituted characters."""
+
+    def test_near_miss_dna(self):
+        patt = pp.CloseMatch("ATCATCGAATGGA", 2)
+        result = patt.parse_string("ATCATCGAAXGGA")
+        assert result == ["ATCATCGAAXGGA"]
+        assert result["original"] == "ATCATCGAATGGA"
+        assert result["mismatches"] == [9]
+        assert patt.parse_string("ATCATCGAATGGA")["mismatches"] == []
+        assert patt.parse_string("ATCAXCGAAXGGA")["mismatches"] == [4, 9]
+        with pytest.raises(pp.ParseError):
+            patt.parse_string("ATCAXCGXAXGGA")
+        with pytest.raises(pp.ParseError):
+            patt.parse_string("ATCATCGAATG")
+
+    def test_search_and_sequence(self):
+        patt = pp.CloseMatch("GATTACA")
+        assert patt.search_string("xx GATTACA GATTAGA GCTTAGA") == [["GATTACA"], ["GATTAGA"]]
+        expr = pp.Literal("seq:") + patt("dna")
+        result = expr.parse_string("seq: GATXACA")
+        assert result == ["seq:", "GATXACA"]
+        assert result["dna"] == "GATXACA"
+        assert result["mismatches"] == [3]
+
+    def test_characters_not_bytes(self):
+        patt = pp.CloseMatch("café", 1)
+        assert patt.parse_string("cafe!") == ["cafe"]
+        assert patt.parse_string("cafè")["mismatches"] == [3]
+
+    def test_caseless_and_options(self):
+        assert pp.CloseMatch("hello", 0, caseless=True).parse_string("HeLLo") == ["HeLLo"]
+        with pytest.raises(pp.ParseError):
+            pp.CloseMatch("hello", 0).parse_string("HeLLo")
+        patt = pp.CloseMatch("abc", 2)
+        assert (patt.match_string, patt.max_mismatches) == ("abc", 2)
+        with pytest.raises(pp.PatternError):
+            pp.CloseMatch("")
+
+    def test_pickle(self):
+        import pickle
+        patt = pickle.loads(pickle.dumps(pp.CloseMatch("abcd", 1, caseless=True)))
+        assert patt.parse_string("ABxD")["mismatches"] == [2]
+
+
+# ============================================================================
+# aw. HTML and XML tags
+# ============================================================================
+
+
+class TestHtmlTags:
+    def test_external_hrefs(self):
+        html = """
+        <html><body>
+          <A HREF=/home class=nav>Home</A>
+          <a class="external"
+             href="https://example.com/one" >One</a>
+          <p>text with <a href='https://example.org/two' class='external'>two</a>
+          <a   href = "/local"   >local</a>
+          <a class="external" target=_blank href=https://example.net/three>three</a>
+        </body></html>
+        """
+        a_start, a_end = pp.make_html_tags("a")
+        external = a_start.add_condition(pp.with_attribute(**{"class": "external"}))
+        hrefs = [match["href"] for match in external.search_string(html)]
+        assert hrefs == [
+            "https://example.com/one",
+            "https://example.org/two",
+            "https://example.net/three",
+        ]
+
+    def test_attributes(self):
+        a_start, a_end = pp.make_html_tags("a")
+        result = a_start.parse_string('<A  HREF = "x.html"  Target=_top disabled>')
+        assert result == ['<A  HREF = "x.html"  Target=_top disabled>']
+        assert result["tag"] == "a"
+        assert result["href"] == "x.html"
+        assert result["target"] == "_top"
+        assert result["disabled"] == ""
+        assert result["empty"] is False
+        assert a_end.parse_string("</A >") == ["</A >"]
+        with pytest.raises(pp.ParseError):
+            a_start.parse_string("<abbr>")
+        with pytest.raises(pp.ParseError):
+            a_start.parse_string('<a href="unterminated>')
+
+    def test_self_closing_and_body(self):
+        br_start, _ = pp.make_html_tags("br")
+        assert br_start.parse_string("<br/>")["empty"] is True
+        assert br_start.parse_string("<BR class='x' />")["class"] == "x"
+        b_start, b_end = pp.make_html_tags("b")
+        expr = b_start + pp.SkipTo(b_end)("body") + b_end
+        assert expr.parse_string("<b id=1>bold text</b>")["body"] == "bold text"
+
+    def test_xml_tags(self):
+        start, end = pp.make_xml_tags("Item")
+        result = start.parse_string('<Item Key="a" Value=\'1\'/>')
+        assert (result["Key"], result["Value"], result["empty"]) == ("a", "1", True)
+        with pytest.raises(pp.ParseError):
+            start.parse_string("<item>")
+        with pytest.raises(pp.ParseError):
+            start.parse_string("<Item Key=a>")
+        assert end.parse_string("</Item>") == ["</Item>"]
+        with pytest.raises(pp.PatternError):
+            pp.make_xml_tags("")
+
+    def test_with_attribute(self):
+        td_start, _ = pp.make_html_tags("td")
+        html = '<td align=right>1</td><td>2</td><td align="left" class=num>3</td>'
+        any_align = td_start.add_condition(pp.with_attribute(align=pp.ANY_VALUE))
+        assert len(any_align.search_string(html)) == 2
+        as_action = td_start.add_parse_action(pp.with_attribute(align=pp.ANY_VALUE))
+        assert as_action.parse_string("<td align=center>")["align"] == "center"
+        with pytest.raises(pp.ParseError):
+            as_action.parse_string("<td>")
+        pairs = td_start.add_condition(pp.with_attribute(("align", "left"), ("class", "num")))
+        assert [m["align"] for m in pairs.search_string(html)] == ["left"]
+        with pytest.raises(pp.ParseError, match="align='center'"):
+            td_start.add_condition(pp.with_attribute(align="center")).parse_string("<td>")
+        with pytest.raises(TypeError):
+            pp.with_attribute(align=3)
+
+    def test_pickle(self):
+        import pickle
+        start, end = pp.make_html_tags("a")
+        expr = pickle.loads(pickle.dumps(start + pp.SkipTo(end) + end))
+        assert expr.parse_string("<a href=x>t</a>")["href"] == "x"
+
+
+# ============================================================================
+# ax. Literal and Keyword scanning
+# ============================================================================
+
+
+class TestLiteralScanning:
+    """Literal and Keyword jump between occurrences instead of trying every offset."""
+
+    HAYSTACK = ("lorem ipsum dolor sit amet " * 40 + "needle needles xneedle é\n") * 2000
+
+    def pairs(self):
+        # A one-element MatchFirst scans offset by offset
+        for fast in (pp.Literal("needle"), pp.Keyword("needle"), pp.Literal("é\nlorem")):
+            yield fast, pp.MatchFirst([fast, fast])
+
+    def test_same_results_as_offset_scan(self):
+        for fast, slow in self.pairs():
+            text = self.HAYSTACK[:5000]
+            assert fast.find_all_positions(text) == slow.find_all_positions(text)
+            assert fast.find_all_positions(text, overlap=True) == slow.find_all_positions(
+                text, overlap=True
+            )
+            assert fast.search_string(text) == slow.search_string(text)
+            assert fast.search_string_count(text) == slow.search_string_count(text)
+            assert fast.split(text) == slow.split(text)
+
+    def test_faster_on_sparse_matches(self):
+        for fast, slow in self.pairs():
+            timings = []
+            for elem in (fast, slow):
+                start = time.perf_counter()
+                positions = elem.find_all_positions(self.HAYSTACK)
+                timings.append(time.perf_counter() - start)
+            assert positions == fast.find_all_positions(self.HAYSTACK)
+            assert timings[0] < timings[1], type(fast).__name__
+
+
+# ============================================================================
+# ay. Regex cache and size limits
+# ============================================================================
+
+
+class TestRegexCache:
+    """Compiled regexes are shared process-wide; untrusted patterns can be bounded."""
+
+    def test_second_construction_hits_cache(self):
+        pp.clear_regex_cache()
+        first = pp.Regex(r"cache-[0-9]+-first")
+        stats = pp.regex_cache_stats()
+        assert stats["misses"] > 0
+        assert stats["hits"] == 0
+        second = pp.Regex(r"cache-[0-9]+-first")
+        again = pp.regex_cache_stats()
+        assert again["hits"] > 0
+        assert again["misses"] == stats["misses"]
+        assert again["size"] == stats["size"]
+        assert first.parse_string("cache-12-first") == second.parse_string("cache-12-first")
+
+    def test_batch_regex_set_uses_cache(self):
+        pp.clear_regex_cache()
+        patterns = [r"\bset-\d+", r"[a-z]+@[a-z]+"]
+        strings = ["set-1 x@y", "nothing"]
+        assert pp.batch_regex_set(patterns, strings) == [[0, 1], []]
+        misses = pp.regex_cache_stats()["misses"]
+        assert pp.batch_regex_set(patterns, strings) == [[0, 1], []]
+        stats = pp.regex_cache_stats()
+        assert stats["misses"] == misses
+        assert stats["hits"] > 0
+
+    def test_cache_is_bounded(self):
+        pp.clear_regex_cache()
+        capacity = pp.regex_cache_stats()["capacity"]
+        for i in range(capacity + 20):
+            pp.Regex(f"bounded{i}")
+        assert pp.regex_cache_stats()["size"] <= capacity
+
+    def test_over_limit_pattern_raises_naming_it(self):
+        previous = pp.set_regex_limits(size=20_000)
+        try:
+            assert pp.regex_cache_stats()["size_limit"] == 20_000
+            with pytest.raises(pp.PatternError, match=r"too big.*size limit of 20000"):
+                pp.Regex(r"[a-z]{500}")
+            with pytest.raises(pp.PatternError) as info:
+                pp.Regex(r"[a-z]{500}")
+            assert "[a-z]{500}" in str(info.value)
+            with pytest.raises(pp.PatternError, match=r"pattern 1: regex .*too big"):
+                pp.batch_regex_set(["ok", r"[a-z]{500}"], ["ok"])
+            # Small patterns still compile under the limit
+            assert pp.Regex(r"[a-z]{5}").matches("abcde")
+        finally:
+            assert pp.set_regex_limits(*previous) == (20_000, previous[1])
+        assert pp.Regex(r"[a-z]{500}").matches("a" * 500)
+
+    def test_default_limit_stops_huge_repetition(self):
+        with pytest.raises(pp.PatternError, match="too big"):
+            pp.Regex(r"\w{1000}{1000}")
+
+    def test_set_regex_limits_drops_cached_regexes(self):
+        pp.Regex(r"dropped-\d")
+        assert pp.regex_cache_stats()["size"] > 0
+        previous = pp.set_regex_limits()
+        assert pp.regex_cache_stats()["size"] == 0
+        assert previous == pp.set_regex_limits()
+        stats = pp.regex_cache_stats()
+        assert (stats["size_limit"], stats["dfa_size_limit"]) == previous
+
+    def test_syntax_errors_unchanged(self):
+        with pytest.raises(pp.PatternError, match="unclosed group"):
+            pp.Regex(r"(ab")
+
+
+# ============================================================================
+# az. Recursion limit
+# ============================================================================
+
+
+class TestRecursionLimit:
+    """Deeply nested input fails cleanly instead of overflowing the native stack."""
+
+    def nested(self):
+        expr = pp.Forward()
+        expr <<= pp.Group(pp.Suppress("(") + pp.Optional(expr) + pp.Suppress(")")) | pp.Word(
+            pp.alphas()
+        )
+        return expr
+
+    def test_pathological_nesting_raises(self):
+        text = "(" * 100_000 + ")" * 100_000
+        with pytest.raises(pp.ParseException, match="Recursion limit of 3000 reached at"):
+            self.nested().parse_string(text)
+        # Match-only paths treat the limit as no match rather than crashing
+        assert not self.nested().matches(text)
+        assert self.nested().search_string_count(text) <= 1
+        batch = pp.parse_batch_compact(self.nested(), [text] + ["x"] * 5000, num_threads=2)
+        assert len(batch) == 5001
+
+    def test_limit_is_fatal_through_alternatives(self):
+        deep = "(" * 10_000 + ")" * 10_000
+        grammar = self.nested() | pp.Regex(r"\(+\)+")
+        with pytest.raises(pp.ParseException, match="Recursion limit"):
+            grammar.parse_string(deep)
+
+    def test_moderate_nesting_still_parses(self):
+        text = "(" * 400 + "x" + ")" * 400
+        result = self.nested().parse_string(text)
+        for _ in range(400):
+            (result,) = result
+        assert result == ["x"]
+
+    def test_set_recursion_limit(self):
+        text = "(" * 50 + ")" * 50
+        previous = pp.set_recursion_limit(100)
+        try:
+            assert previous == 3000
+            with pytest.raises(pp.ParseException, match="Recursion limit of 100"):
+                self.nested().parse_string(text)
+            assert not self.nested().matches(text)
+            assert pp.set_recursion_limit(1000) == 100
+            assert self.nested().matches(text)
+        finally:
+            pp.set_recursion_limit(previous)
+        with pytest.raises(ValueError):
+            pp.set_recursion_limit(0)
+
+
+# ============================================================================
+# ba. Parsing lines of a stream
+# ============================================================================
+
+
+class TestParseLines:
+    """parse_lines pulls lines from an iterable lazily and yields the ones that parse."""
+
+    grammar = pp.Word(pp.alphas()) + pp.Word(pp.nums())
+
+    def test_str_and_bytes_lines(self):
+        lines = ["abc 1\n", b"def 2\n", "!!!\n", b"  ghi 33"]
+        found = list(self.grammar.parse_lines(lines))
+        assert found == [(0, ["abc", "1"]), (1, ["def", "2"]), (3, ["ghi", "33"])]
+
+    def test_matches_parse_string(self):
+        lines = [f"w{i} {i}" if i % 3 else "-" for i in range(50)]
+        found = dict(self.grammar.parse_lines(iter(lines), chunk_size=7))
+        for i, line in enumerate(lines):
+            if i % 3:
+                assert found[i] == self.grammar.parse_string(line)
+            else:
+                assert i not in found
+
+    def test_named_results(self):
+        grammar = pp.Word(pp.alphas())("key") + pp.Word(pp.nums())("value")
+        ((index, tokens),) = grammar.parse_lines(["x 1"])
+        assert index == 0
+        assert tokens["key"] == "x" and tokens["value"] == "1"
+
+    def test_first_failure_raises(self):
+        lines = iter(["a 1", "b 2", "oops", "c 3"])
+        found = self.grammar.parse_lines(lines, skip_failures=False)
+        assert next(found) == (0, ["a", "1"])
+        assert next(found) == (1, ["b", "2"])
+        with pytest.raises(pp.ParseException) as info:
+            next(found)
+        assert info.value.line_index == 2
+        assert list(found) == []
+
+    def test_pulls_one_chunk_at_a_time(self):
+        pulled = []
+
+        def lines():
+            for i in range(10_000_000):
+                pulled.append(i)
+                yield f"w {i}"
+
+        found = self.grammar.parse_lines(lines(), chunk_size=100)
+        assert next(found) == (0, ["w", "0"])
+        assert len(pulled) == 100
+        assert found.lines_read == 100
+        for _ in range(150):
+            next(found)
+        assert len(pulled) == 200
+
+    def test_parse_actions_run_once_per_line(self):
+        seen = []
+        word = pp.Word(pp.alphas()).add_parse_action(lambda t: seen.append(t[0]))
+        found = list(word.parse_lines(["a", "1", "b"]))
+        assert found == [(0, ["a"]), (2, ["b"])]
+        assert seen == ["a", "b"]
+
+    def test_bad_lines(self):
+        with pytest.raises(ValueError, match="input 1 is not valid UTF-8"):
+            list(self.grammar.parse_lines(["a 1", b"\xff"]))
+        with pytest.raises(ValueError, match="str or bytes"):
+            list(self.grammar.parse_lines([1]))
+        with pytest.raises(ValueError):
+            self.grammar.parse_lines([], chunk_size=0)
+        with pytest.raises(TypeError):
+            self.grammar.parse_lines(5)
+
+
+# ============================================================================
+# bb. JSON output
+# ============================================================================
+
+
+class TestJsonOutput:
+    """search_string_json and the to_json options build JSON in Rust."""
+
+    def test_search_string_json(self):
+        import json
+        grammar = pp.Word(pp.alphas())("word") + pp.Word(pp.nums())
+        text = "ab 1, cd 22"
+        records = json.loads(grammar.search_string_json(text))
+        assert records == [
+            {"tokens": ["ab", "1"], "named": {"word": "ab"}, "start": 0, "end": 4},
+            {"tokens": ["cd", "22"], "named": {"word": "cd"}, "start": 6, "end": 11},
+        ]
+        for record, (tokens, start, end) in zip(records, grammar.scan_string(text)):
+            assert record["tokens"] == list(tokens) and (record["start"], record["end"]) == (start, end)
+        assert json.loads(grammar.search_string_json(text, max_matches=1)) == records[:1]
+        assert grammar.search_string_json("--") == "[]"
+
+    def test_escaping(self):
+        import json
+        grammar = pp.Regex(r"\S+")
+        text = 'say "hi" C:\\path caf\u00e9 \u65e5\u672c tab\there'
+        tokens = [r["tokens"][0] for r in json.loads(grammar.search_string_json(text))]
+        assert tokens == text.replace("\t", " ").split()
+
+    def test_pretty_and_compact(self):
+        import json
+        grammar = pp.Word(pp.alphas())
+        compact = grammar.search_string_json("a b")
+        pretty = grammar.search_string_json("a b", pretty=True)
+        assert "\n" not in compact and "\n  " in pretty
+        assert json.loads(compact) == json.loads(pretty)
+
+    def test_values_from_parse_actions_and_groups(self):
+        import json
+        num = pp.Word(pp.nums()).set_parse_action(lambda t: int(t[0]))
+        grammar = pp.Group(num + num)("pair") + pp.Optional(pp.Word(pp.alphas()))
+        ((record,),) = [json.loads(grammar.search_string_json("1 2 x"))]
+        assert record["tokens"] == [[1, 2], "x"]
+        assert record["named"] == {"pair": [1, 2]}
+        real = pp.Regex(r"\d+\.\d+").set_parse_action(lambda t: {"value": float(t[0]), "ok": True})
+        assert json.loads(real.search_string_json("2.5"))[0]["tokens"] == [{"value": 2.5, "ok": True}]
+
+    def test_repeated_and_grouped_names(self):
+        import json
+        word = pp.Word(pp.alphas())
+        grammar = pp.OneOrMore(word("last")) + pp.OneOrMore(pp.Word(pp.nums())("n*"))
+        [record] = json.loads(grammar.search_string_json("a b 1 2"))
+        assert record["named"] == {"last": "b", "n": ["1", "2"]}
+        grouped = pp.Group(word("inner"))("outer")
+        [record] = json.loads(grouped.search_string_json("a"))
+        assert record["named"] == {"outer": ["a"]}
+
+    def test_parse_batch_to_json(self):
+        import json
+        for grammar in (pp.Literal("ab"), pp.Word(pp.alphas()), pp.Word(pp.alphas()) + pp.Word(pp.nums())):
+            inputs = ["ab 1", "1", "ab 2 rest"]
+            records = json.loads(grammar.parse_batch(inputs, to_json=True))
+            expected = [
+                (i, grammar.parse_string(s)) for i, s in enumerate(inputs) if grammar.matches(s, parse_all=False)
+            ]
+            assert [(r["index"], r["tokens"]) for r in records] == expected
+            assert all(list(r) == ["index", "tokens", "named", "end"] for r in records)
+        assert json.loads(pp.Word(pp.nums()).parse_batch(["1"] * 5000, to_json=True))[-1]["index"] == 4999
+
+    def test_jsonl_files(self):
+        import json
+        import os
+        import tempfile
+        grammar = pp.Word(pp.alphas()) + pp.Word(pp.nums())
+        with tempfile.TemporaryDirectory() as d:
+            out = os.path.join(d, "out.jsonl")
+
+            def written():
+                with open(out, encoding="utf-8") as f:
+                    return [json.loads(line) for line in f]
+
+            assert grammar.search_string_json("a 1 b 2 c", jsonl_path=out) == 2
+            assert [r["tokens"] for r in written()] == [["a", "1"], ["b", "2"]]
+            assert grammar.parse_batch(["x 1", "-", "y 2 \u00e9"], jsonl_path=out) == 2
+            assert [(r["index"], r["tokens"]) for r in written()] == [(0, ["x", "1"]), (2, ["y", "2"])]
+            source = os.path.join(d, "in.txt")
+            with open(source, "w", encoding="utf-8") as f:
+                f.write("k 1\nnothing\nk 2\n")
+            records = json.loads(pp.split_file_process(source, grammar, to_json=True))
+            assert [(r["line"], r["tokens"]) for r in records] == pp.split_file_process(source, grammar)
+            assert pp.split_file_process(source, grammar, jsonl_path=out) == 2
+            assert [r["line"] for r in written()] == [1, 3]
+            with pytest.raises(pp.FileProcessingError):
+                grammar.search_string_json("a 1", jsonl_path=os.path.join(d, "missing", "out.jsonl"))
+
+
+# ============================================================================
+# bc. bytes and buffer inputs
+# ============================================================================
+
+
+class TestBytesInputs:
+    """str, bytes and UTF-8 buffers parse alike; tokens are always str."""
+
+    grammar = pp.Word(pp.alphas()) + pp.Word(pp.nums())
+
+    def test_parse_and_search_accept_buffers(self):
+        import array
+        import mmap
+        text = "café 12 tea 3"
+        data = text.encode()
+        word = pp.Word(pp.CharClass(r"\p{L}"))
+        expected = word.search_string(text)
+        for source in (data, bytearray(data), memoryview(data), array.array("B", data)):
+            assert word.search_string(source) == expected
+            assert self.grammar.parse_string(source[9:]).as_list() == ["tea", "3"]
+            assert all(type(t) is str for t in word.parse_string(source).as_list())
+        with mmap.mmap(-1, len(data)) as m:
+            m.write(data)
+            assert word.search_string(m) == expected
+            assert word.search_string(memoryview(m)[:3]) == [["caf"]]
+
+    def test_batches_accept_mixed_inputs(self):
+        inputs = ["a 1", b"b 2", bytearray(b"c 3"), memoryview(b"- 4")]
+        as_str = [bytes(i).decode() if not isinstance(i, str) else i for i in inputs]
+        assert self.grammar.parse_batch_count(inputs) == self.grammar.parse_batch_count(as_str)
+        assert self.grammar.matches_batch(inputs) == [True, True, True, False]
+        out, counts = pp.batch_replace([b"a.b", "a"], "a", "x")
+        assert out == ["x.b", "x"] and counts == [1, 1]
+        assert all(type(s) is str for s in out)
+        word = pp.Word(pp.alphas())
+        assert pp.parse_batch_compact(word, [b"ab", "cd"]).to_list() == pp.parse_batch_compact(word, ["ab", "cd"]).to_list()
+
+    def test_invalid_utf8_names_the_offset(self):
+        with pytest.raises(ValueError, match="offset 2"):
+            self.grammar.parse_string(b"ab\xffc 1")
+        with pytest.raises(ValueError, match="offset 0"):
+            self.grammar.search_string(memoryview(b"\xc3("))
+        with pytest.raises(ValueError, match="input 1 .*offset 3"):
+            self.grammar.parse_batch_count(["a 1", b"a 1\x80"])
+
+    def test_rejects_other_objects(self):
+        with pytest.raises(TypeError):
+            self.grammar.parse_string(12)
+        with pytest.raises(TypeError):
+            self.grammar.parse_string(memoryview(b"a 1 b 2")[::2])
+
+
+# ============================================================================
+# bd. Directory scanning
+# ============================================================================
+
+
+class TestScanDirectory:
+    """scan_directory walks a tree in Rust and groups matching lines by file."""
+
+    grammar = pp.Literal("TODO") + pp.Word(pp.alphas())
+
+    def make_tree(self, root):
+        import os
+        files = {
+            "a.py": "x = 1\n# TODO fix\n",
+            "b.txt": "TODO later\n",
+            "pkg/c.py": "TODO one\nok\nTODO two\n",
+            "pkg/deep/d.py": "TODO deep\n",
+            "node_modules/e.py": "TODO vendored\n",
+            "bin.py": "TODO hidden\n\0\0",
+            "latin.py": "TODO caf\xe9\n",
+        }
+        for rel, text in files.items():
+            path = os.path.join(root, rel)
+            os.makedirs(os.path.dirname(path), exist_ok=True)
+            with open(path, "wb") as f:
+                f.write(text.encode("latin-1"))
+
+    def test_hits_grouped_by_file(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as root:
+            self.make_tree(root)
+            hits = pp.scan_directory(root, self.grammar, include="*.py", exclude=["*/node_modules/*"])
+            rel = {os.path.relpath(p, root): [(n, t.as_list()) for n, t in v] for p, v in hits.items()}
+            assert rel == {
+                "a.py": [(2, ["TODO", "fix"])],
+                os.path.join("pkg", "c.py"): [(1, ["TODO", "one"]), (3, ["TODO", "two"])],
+                os.path.join("pkg", "deep", "d.py"): [(1, ["TODO", "deep"])],
+            }
+            assert list(hits) == sorted(hits)
+            assert pp.scan_directory(root, self.grammar, num_threads=1) == pp.scan_directory(root, self.grammar)
+            shallow = pp.scan_directory(root, "TODO", include="*.py", max_depth=1, exclude="*/node_modules*")
+            assert sorted(os.path.relpath(p, root) for p in shallow) == ["a.py", os.path.join("pkg", "c.py")]
+
+    def test_max_results(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as root:
+            self.make_tree(root)
+            full = pp.scan_directory(root, self.grammar)
+            for limit in (0, 1, 2, 3):
+                hits = pp.scan_directory(root, self.grammar, max_results=limit, num_threads=2)
+                assert sum(len(v) for v in hits.values()) == limit
+                flat = [(p, n) for p, v in hits.items() for n, _ in v]
+                assert flat == [(p, n) for p, v in full.items() for n, _ in v][:limit]
+            assert os.path.join(root, "b.txt") in full
+
+    def test_symlinks(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as root:
+            self.make_tree(root)
+            os.symlink(root, os.path.join(root, "pkg", "loop"))
+            os.symlink(os.path.join(root, "a.py"), os.path.join(root, "link.py"))
+            plain = pp.scan_directory(root, self.grammar, include="*.py")
+            assert not any("loop" in p or "link" in p for p in plain)
+            followed = pp.scan_directory(root, self.grammar, include="*.py", follow_symlinks=True)
+            assert os.path.join(root, "link.py") in followed
+            assert len(followed) == len(plain) + 1
+
+    def test_errors(self):
+        import os
+        import tempfile
+        with pytest.raises(pp.FileProcessingError):
+            pp.scan_directory(os.path.join(tempfile.gettempdir(), "no-such-dir-pyrs"), self.grammar)
+        with pytest.raises(ValueError, match="include"):
+            pp.scan_directory(".", self.grammar, include=5)
+
+
+# ============================================================================
+# be. Key/value records
+# ============================================================================
+
+
+class TestKeyValueParser:
+    """KeyValueParser reads key=value records without a composed grammar."""
+
+    def test_parse_line(self):
+        kv = pp.KeyValueParser()
+        line = 'ts=2024-01-01 level=error msg="disk \\"sda\\" full\\n" count=3 empty= x.y-z=1'
+        assert kv.parse_line(line) == {
+            "ts": "2024-01-01", "level": "error", "msg": 'disk "sda" full\n',
+            "count": "3", "empty": "", "x.y-z": "1",
+        }
+        assert kv.parse_line("a=1 a=2\r\n") == {"a": "2"}
+        assert kv.parse_line(b"k=\"caf\xc3\xa9\"") == {"k": "caf\u00e9"}
+        assert kv.parse_line('path="C:\\dir"') == {"path": "C:\\dir"}
+        assert kv.parse_line("   ") == {}
+
+    def test_separator_assign_and_key_chars(self):
+        kv = pp.KeyValueParser(separator=",", assign=":")
+        assert kv.parse_line('a: 1 , b:"x, y" ,c:3,') == {"a": "1", "b": "x, y", "c": "3"}
+        digits = pp.KeyValueParser(key_chars=pp.CharClass("a-z"))
+        assert digits.parse_line("ab=1 a1=2 cd=3") == {"ab": "1", "cd": "3"}
+        assert pp.KeyValueParser(key_chars="xy").parse_line("xy=1 z=2") == {"xy": "1"}
+        for bad in ({"assign": " "}, {"assign": "a"}, {"separator": '"'}, {"separator": "=", "assign": "="}):
+            with pytest.raises(ValueError):
+                pp.KeyValueParser(**bad)
+
+    def test_malformed_pairs(self):
+        lenient = pp.KeyValueParser()
+        assert lenient.parse_line('a=1 junk =2 b="open c="x"y d=4') == {"a": "1", "d": "4"}
+        strict = pp.KeyValueParser(skip_malformed=False)
+        with pytest.raises(pp.ParseError, match="expected '=' after key 'junk'") as info:
+            strict.parse_line("a=1 junk")
+        assert info.value.loc == 8
+        with pytest.raises(pp.ParseError, match="unterminated"):
+            strict.parse_line('a="open')
+        with pytest.raises(pp.ParseError) as info:
+            strict.parse_batch(["a=1", "b=2", "=3"])
+        assert info.value.line_index == 2 and info.value.loc == 0
+
+    def test_parse_batch(self):
+        kv = pp.KeyValueParser()
+        lines = [f"id={i} level={'error' if i % 3 else 'info'} msg=\"m {i}\"" for i in range(5000)]
+        expected = [kv.parse_line(line) for line in lines]
+        assert kv.parse_batch(lines) == expected
+        assert kv.parse_batch(lines, num_threads=1) == expected
+        index, keys, values = kv.parse_batch(["a=1 b=2", "", b"c=3"], as_arrays=True)
+        assert (index, keys, values) == ([0, 0, 2], ["a", "b", "c"], ["1", "2", "3"])
+        batch = kv.parse_batch(lines[:2])
+        assert next(iter(batch[0])) is next(iter(batch[1]))
+
+
+# ============================================================================
+# bf. Per-input match counts
+# ============================================================================
+
+
+class TestBatchCountMatches:
+    """batch_count_matches counts like find_all_positions, one count per input."""
+
+    inputs = ["abc de", "", "aaaa", b"x aa y", "12 34"] * 1000
+
+    def test_counts_per_input(self):
+        word = pp.Word(pp.alphas())
+        for grammar, overlap in ((word, False), (word, True), ("aa", False), ("aa", True)):
+            elem = pp.Literal(grammar) if isinstance(grammar, str) else grammar
+            expected = [
+                len(elem.find_all_positions(s if isinstance(s, str) else s.decode(), overlap=overlap))
+                for s in self.inputs
+            ]
+            assert pp.batch_count_matches(grammar, self.inputs, overlap=overlap) == expected
+            assert pp.batch_count_matches(grammar, self.inputs, overlap=overlap, num_threads=3) == expected
+            assert pp.batch_count_matches(grammar, self.inputs, overlap=overlap, total=True) == sum(expected)
+        assert pp.batch_count_matches("aa", ["aaaa"], overlap=True) == [3]
+        assert pp.batch_count_matches(word, []) == []
+
+    def test_numpy_output(self):
+        np = pytest.importorskip("numpy")
+        counts = pp.batch_count_matches(pp.Word(pp.nums()), self.inputs, as_numpy=True)
+        assert counts.dtype == np.int64
+        assert counts.tolist() == pp.batch_count_matches(pp.Word(pp.nums()), self.inputs)
+
+    def test_errors(self):
+        with pytest.raises(ValueError, match="not both"):
+            pp.batch_count_matches("a", ["a"], total=True, as_numpy=True)
+        with pytest.raises(pp.PatternError):
+            pp.batch_count_matches("", ["a"])
+        with pytest.raises(ValueError, match="inputs"):
+            pp.batch_count_matches("a", ["a", 2])
+
+
+# ============================================================================
+# bg. Multi-pattern match indices
+# ============================================================================
+
+
+class TestMultiMatchIndices:
+    inputs = ["GET /index and POST /form", "", "PUT GETS", "POSTPOST"]
+    words = ["GET", "POST", "PUT", "POS"]
+
+    def test_literals_leftmost_longest(self):
+        assert pp.multi_match_indices(self.words, self.inputs) == [
+            (0, 0, 0, 3),
+            (0, 1, 15, 19),
+            (2, 2, 0, 3),
+            (2, 0, 4, 7),
+            (3, 1, 0, 4),
+            (3, 1, 4, 8),
+        ]
+        # equally long patterns at the same place go to the one listed first
+        assert pp.multi_match_indices(["ab", "ab", "a"], ["ab"]) == [(0, 0, 0, 2)]
+        assert pp.multi_match_indices(self.words, self.inputs, num_threads=3) == (
+            pp.multi_match_indices(self.words, self.inputs)
+        )
+
+    def test_elements_agree_with_literals(self):
+        elements = [pp.Literal(w) for w in self.words]
+        for report_all in (False, True):
+            assert pp.multi_match_indices(elements, self.inputs, report_all=report_all) == (
+                pp.multi_match_indices(self.words, self.inputs, report_all=report_all)
+            )
+
+    def test_mixed_patterns(self):
+        patterns = [pp.Word(pp.nums()), "id", pp.Word(pp.alphas())]
+        assert pp.multi_match_indices(patterns, ["id 42 idx"]) == [
+            (0, 1, 0, 2),
+            (0, 0, 3, 5),
+            (0, 2, 6, 9),
+        ]
+
+    def test_report_all(self):
+        assert pp.multi_match_indices(["aa", "a"], ["aaa"], report_all=True) == [
+            (0, 1, 0, 1),
+            (0, 0, 0, 2),
+            (0, 1, 1, 2),
+            (0, 0, 1, 3),
+            (0, 1, 2, 3),
+        ]
+        assert pp.multi_match_indices(["POS", "POST"], ["POST"], report_all=True) == [
+            (0, 0, 0, 3),
+            (0, 1, 0, 4),
+        ]
+
+    def test_numpy_output(self):
+        np = pytest.importorskip("numpy")
+        columns = pp.multi_match_indices(self.words, self.inputs, as_numpy=True)
+        assert len(columns) == 4
+        assert all(c.dtype == np.int64 for c in columns)
+        assert list(zip(*(c.tolist() for c in columns))) == pp.multi_match_indices(self.words, self.inputs)
+
+    def test_errors(self):
+        with pytest.raises(pp.PatternError):
+            pp.multi_match_indices([], ["a"])
+        with pytest.raises(pp.PatternError):
+            pp.multi_match_indices(["a", ""], ["a"])
+        with pytest.raises(ValueError, match="inputs"):
+            pp.multi_match_indices(["a"], ["a", 2])
+
+
+# ============================================================================
+# bh. ParserPipeline: grammar, files and sink in one call
+# ============================================================================
+
+
+class TestParserPipeline:
+    grammar = pp.Literal("ERROR") + pp.Word(pp.alphas())
+
+    def make_logs(self, root):
+        import os
+        files = {
+            "app.log": "INFO start\nERROR disk\nERROR net\n",
+            "db.log": "ERROR lock\n",
+            "old/app.log": "ERROR stale\nINFO ok\n",
+            "notes.txt": "ERROR ignored\n",
+            "core.log": "ERROR x\n\0\0",
+        }
+        for rel, text in files.items():
+            path = os.path.join(root, rel)
+            os.makedirs(os.path.dirname(path), exist_ok=True)
+            with open(path, "wb") as f:
+                f.write(text.encode())
+
+    def test_sources(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as root:
+            self.make_logs(root)
+            walked = pp.ParserPipeline(self.grammar, root, include="*.log", exclude="*/old/*")
+            assert [os.path.relpath(p, root) for p in walked.files()] == ["app.log", "core.log", "db.log"]
+            globbed = pp.ParserPipeline(self.grammar, os.path.join(root, "*.log"))
+            assert [os.path.relpath(p, root) for p in globbed.files()] == [
+                "app.log", "core.log", "db.log", os.path.join("old", "app.log"),
+            ]
+            listed = [os.path.join(root, "db.log"), os.path.join(root, "app.log")]
+            assert pp.ParserPipeline(self.grammar, listed).files() == listed
+
+    def test_return_sink(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as root:
+            self.make_logs(root)
+            summary = pp.ParserPipeline(self.grammar, os.path.join(root, "*.log"), num_threads=2).run()
+            results = summary.pop("results")
+            assert summary == {"files": 3, "lines": 8, "matches": 4, "skipped": 1, "errors": 0}
+            assert [(os.path.relpath(p, root), n, t.as_list()) for p, n, t in results] == [
+                ("app.log", 2, ["ERROR", "disk"]),
+                ("app.log", 3, ["ERROR", "net"]),
+                ("db.log", 1, ["ERROR", "lock"]),
+                (os.path.join("old", "app.log"), 1, ["ERROR", "stale"]),
+            ]
+
+    def test_transforms_and_match_line(self):
+        pipeline = pp.ParserPipeline(self.grammar, [], transforms=["downcase", ("join", ":")])
+        assert pipeline.match_line("at 3: ERROR Disk\n").as_list() == ["error:disk"]
+        assert pipeline.match_line("INFO ok") is None
+        assert pipeline.run() == {"files": 0, "lines": 0, "matches": 0, "skipped": 0, "errors": 0, "results": []}
+
+    def test_jsonl_and_count_sinks(self):
+        import json
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as root:
+            self.make_logs(root)
+            out = os.path.join(root, "out.jsonl")
+            grammar = pp.Literal("ERROR") + pp.Word(pp.alphas())("what")
+            summary = pp.ParserPipeline(
+                grammar, root, include="*.log", transforms=["upcase"], sink="jsonl", jsonl_path=out
+            ).run()
+            assert summary == {"files": 3, "lines": 8, "matches": 4, "skipped": 1, "errors": 0}
+            with open(out) as f:
+                records = [json.loads(line) for line in f]
+            assert [(os.path.relpath(r["path"], root), r["line"], r["tokens"]) for r in records][:2] == [
+                ("app.log", 2, ["ERROR", "DISK"]),
+                ("app.log", 3, ["ERROR", "NET"]),
+            ]
+            assert records[0]["named"] == {"what": "DISK"}
+            counted = pp.ParserPipeline(self.grammar, root, include="*.log", sink="count").run()
+            assert counted == summary
+
+    def test_unreadable_files_are_counted(self):
+        import os
+        import tempfile
+        with tempfile.TemporaryDirectory() as root:
+            self.make_logs(root)
+            files = [os.path.join(root, "db.log"), os.path.join(root, "missing.log")]
+            summary = pp.ParserPipeline(self.grammar, files, sink="count").run()
+            assert summary == {"files": 1, "lines": 1, "matches": 1, "skipped": 0, "errors": 1}
+            with pytest.raises(pp.FileProcessingError):
+                pp.ParserPipeline(self.grammar, os.path.join(root, "nope")).run()
+
+    def test_errors(self):
+        with pytest.raises(ValueError, match="jsonl_path"):
+            pp.ParserPipeline("a", [], sink="jsonl")
+        with pytest.raises(ValueError, match="jsonl_path"):
+            pp.ParserPipeline("a", [], jsonl_path="x.jsonl")
+        with pytest.raises(ValueError, match="sink"):
+            pp.ParserPipeline("a", [], sink="print")
+        with pytest.raises(ValueError, match="include"):
+            pp.ParserPipeline("a", ["x.log"], include="*.log")
+        with pytest.raises(ValueError, match="include"):
+            pp.ParserPipeline("a", "logs/*.log", include="*.txt")
+        with pytest.raises(pp.PatternError):
+            pp.ParserPipeline("a", [], transforms=["reverse"])
+        with pytest.raises(ValueError, match="transforms"):
+            pp.ParserPipeline("a", [], transforms=[1])
+
+
+# ============================================================================
+# bi. StreamParser: search_string over text fed in chunks
+# ============================================================================
+
+
+class TestStreamParser:
+    record = pp.Word(pp.alphas())("key") + "=" + pp.Word(pp.nums())("value") + ";"
+
+    def grammars(self):
+        return [
+            (pp.Word(pp.alphas()), "ab cd  éfg h1 xyz "),
+            (pp.Literal("ab") + pp.Optional(pp.Literal("cde")), "abcd abcde ab abc"),
+            (self.record, "a=1; bb = 22 ;c=3 d=4;é=5;"),
+            (pp.Keyword("if"), "if xif if_ (if) if"),
+            (pp.LineStart() + pp.Word(pp.nums()), "12 a\n34 b 56\n\n78"),
+            (pp.Regex(r'"[^"]*"'), 'say "hi" and "a b" "open'),
+            (pp.Literal("a") | pp.Literal("ab"), "aab ba"),
+        ]
+
+    def run_stream(self, grammar, chunks, **kwargs):
+        parser = pp.StreamParser(grammar, **kwargs)
+        found = []
+        for chunk in chunks:
+            found += parser.feed(chunk)
+        tail, _ = parser.close()
+        return [(t.as_list(), s, e) for t, s, e in found + tail]
+
+    def test_every_split_matches_one_shot(self):
+        for grammar, text in self.grammars():
+            expected = [(t.as_list(), s, e) for t, s, e in grammar.scan_string(text)]
+            assert [e[0] for e in expected] == [m.as_list() for m in grammar.search_string(text)]
+            data = text.encode()
+            for i in range(len(data) + 1):
+                assert self.run_stream(grammar, [data[:i], data[i:]]) == expected, (text, i)
+            for i in range(len(text) + 1):
+                assert self.run_stream(grammar, [text[:i], text[i:]]) == expected, (text, i)
+            assert self.run_stream(grammar, [data[i:i + 1] for i in range(len(data))]) == expected
+            assert self.run_stream(grammar, [text]) == expected
+
+    def test_zero_length_matches_skipped(self):
+        grammar = pp.Optional(pp.OneOrMore(pp.Optional(pp.Word(pp.nums()))))
+        text = "1 b  x\n 2"
+        expected = [(["1"], 0, 1), (["2"], 8, 9)]
+        assert [(t.as_list(), s, e) for t, s, e in grammar.scan_string(text)] == expected
+        assert self.run_stream(grammar, [text]) == expected
+        for i in range(len(text) + 1):
+            assert self.run_stream(grammar, [text[:i], text[i:]]) == expected, i
+
+    def test_three_way_splits(self):
+        grammar, text = self.record, "k=1;kk=22 ;x"
+        expected = [(t.as_list(), s, e) for t, s, e in grammar.scan_string(text)]
+        for i in range(len(text) + 1):
+            for j in range(i, len(text) + 1):
+                assert self.run_stream(grammar, [text[:i], text[i:j], text[j:]]) == expected
+
+    def test_matches_wait_until_settled(self):
+        parser = pp.StreamParser(pp.Literal("ab") + pp.Optional(pp.Literal("cde")))
+        assert parser.feed("xx abcd") == []
+        # the attempt at the space skips it into the held match
+        assert parser.position == 2 and parser.buffered == 5
+        [(tokens, start, end)] = parser.feed("e ab")
+        assert (tokens.as_list(), start, end) == (["ab", "cde"], 3, 8)
+        assert parser.feed("c") == []
+        [(tokens, start, end)], leftover = parser.close()
+        assert (tokens.as_list(), start, end, leftover) == (["ab"], 9, 11, True)
+        assert parser.closed
+
+    def test_named_results_and_leftover(self):
+        parser = pp.StreamParser(self.record)
+        [(tokens, _, _)] = parser.feed(b"a=1;b=")
+        assert tokens["key"] == "a" and tokens["value"] == "1"
+        assert parser.feed(bytearray(b"2;  b=")) [0][0].as_dict() == {"key": "b", "value": "2"}
+        assert parser.close() == ([], True)
+        parser = pp.StreamParser(self.record)
+        parser.feed("a=1;  ")
+        assert parser.close() == ([], False)
+        parser = pp.StreamParser(self.record)
+        parser.feed("a=1;".encode() + "é".encode()[:1])
+        assert parser.close()[1]
+
+    def test_max_buffer_bounds_held_text(self):
+        grammar = pp.Literal("[") + pp.SkipTo("]") + "]"
+        parser = pp.StreamParser(grammar, max_buffer=16)
+        for _ in range(20):
+            parser.feed("[never closed ")
+            assert parser.buffered <= 16 + 14
+        unbounded = pp.StreamParser(grammar)
+        unbounded.feed("[abc" * 20)
+        assert unbounded.buffered == 80
+        [(tokens, start, end)] = unbounded.feed("]")
+        assert (start, end) == (0, 81)
+
+    def test_errors(self):
+        parser = pp.StreamParser("a")
+        with pytest.raises(ValueError, match="offset 2"):
+            parser.feed(b"ab\xff")
+        parser.feed("é".encode()[:1])
+        with pytest.raises(ValueError, match="partway"):
+            parser.feed("a")
+        with pytest.raises(TypeError):
+            parser.feed(3)
+        parser.close()
+        with pytest.raises(ValueError, match="closed"):
+            parser.feed("a")
+        with pytest.raises(ValueError, match="closed"):
+            parser.close()
+        with pytest.raises(ValueError, match="max_buffer"):
+            pp.StreamParser("a", max_buffer=0)
+
+
+# ============================================================================
+# bj. MatchFirst dispatch on the next byte (large alternations)
+# ============================================================================
+
+
+class TestFirstByteDispatch:
+    @staticmethod
+    def alternatives():
+        return [
+            pp.Literal("<"),
+            pp.Literal("<="),
+            pp.Keyword("if"),
+            pp.CaselessKeyword("ELSE"),
+            pp.CaselessLiteral("then"),
+            pp.Regex(r"[0-9]+\.[0-9]+"),
+            pp.Char("0123456789"),
+            pp.Word(pp.alphas()),
+            pp.Word("éa", "xyz"),
+            pp.Suppress(pp.Literal(";")),
+            pp.Literal("e") + pp.Literal("!"),
+        ]
+
+    @staticmethod
+    def first_match(alternatives, text):
+        for alt in alternatives:
+            try:
+                return alt.parse_string(text).as_list()
+            except pp.ParseException:
+                pass
+        return None
+
+    def test_first_match_wins_as_without_dispatch(self):
+        alternatives = self.alternatives()
+        grammar = pp.MatchFirst(alternatives)
+        inputs = ["<=", "<", "if", "iffy", "IF", "else", "eLsE", "Then", "then",
+                  "3.25", "7", "é", "ézz", "axy", ";", "e !", "e", "?", "É"]
+        for text in inputs:
+            try:
+                got = grammar.parse_string(text).as_list()
+            except pp.ParseException:
+                got = None
+            assert got == self.first_match(alternatives, text), text
+
+    def test_scans_match_parses(self):
+        grammar = pp.MatchFirst(self.alternatives())
+        text = "if x <= 3.25 then é; ELSE e ! <"
+        spans = [(start, end) for _, start, end in grammar.scan_string(text)]
+        assert spans == [(0, 2), (3, 4), (5, 6), (8, 12), (13, 17), (18, 20),
+                         (20, 21), (22, 26), (27, 28), (31, 32)]
+        assert [tokens.as_list() for tokens in grammar.search_string(text)] == [
+            ["if"], ["x"], ["<"], ["3.25"], ["then"], ["é"], [], ["ELSE"], ["e"], ["<"]
+        ]
+        # "<" comes first, and Word(alphas) takes the "e" of "e !"
+        assert grammar.matches("<=", parse_all=False) and not grammar.matches("<=")
+        assert not grammar.matches("e !") and not grammar.matches("?")
+
+    def test_empty_alternative_matches_anywhere(self):
+        grammar = pp.MatchFirst(self.alternatives() + [pp.Empty()])
+        assert grammar.parse_string("?").as_list() == []
+        assert grammar.parse_string("").as_list() == []
+        assert grammar.parse_string("if").as_list() == ["if"]
+
+    def test_failure_lists_every_alternative(self):
+        grammar = pp.MatchFirst([pp.Keyword(w) for w in
+                                 ["and", "or", "not", "in", "is", "if", "for", "while"]])
+        with pytest.raises(pp.ParseException) as info:
+            grammar.parse_string("?")
+        for word in ["and", "or", "not", "in", "is", "if", "for", "while"]:
+            assert f"'{word}'" in str(info.value)
+        with pytest.raises(pp.ParseException):
+            grammar.parse_string("iffy")
+
+    def test_nested_alternations(self):
+        inner = pp.MatchFirst([pp.Literal(c) for c in "abcdefgh"])
+        grammar = pp.MatchFirst([pp.Literal("x" + str(i)) for i in range(8)] + [inner])
+        assert grammar.parse_string("x3").as_list() == ["x3"]
+        assert grammar.parse_string("h").as_list() == ["h"]
+        assert pp.OneOrMore(grammar).parse_string("a x7 b").as_list() == ["a", "x7", "b"]
+
+
+# ============================================================================
+# bk. Per-element profiling
+# ============================================================================
+
+class TestProfiling:
+    def setup_method(self):
+        pp.reset_profile_stats()
+        pp.enable_profiling()
+
+    def teardown_method(self):
+        pp.enable_profiling(False)
+        pp.reset_profile_stats()
+
+    @staticmethod
+    def stats_by_name():
+        return {s["name"]: s for s in pp.get_profile_stats()}
+
+    def test_counts_calls_successes_and_failures(self):
+        num = pp.Word(pp.nums())
+        ident = pp.Word(pp.alphas())
+        grammar = pp.OneOrMore(num | ident)
+        assert grammar.parse_string("1 a 2").as_list() == ["1", "a", "2"]
+        stats = self.stats_by_name()
+        words = stats[str(num)]
+        assert (words["parse_calls"], words["successes"], words["failures"]) == (4, 2, 2)
+        assert stats[str(ident)]["successes"] == 1
+        # The element parsing starts from is counted too
+        assert (stats[str(grammar)]["parse_calls"], stats[str(grammar)]["successes"]) == (1, 1)
+        assert stats[str(num | ident)]["parse_calls"] == 4
+
+    def test_counts_root_of_scans(self):
+        word = pp.Word(pp.alphas())
+        word.search_string("ab 12 cd")
+        assert self.stats_by_name()[str(word)]["successes"] == 2
+        pp.reset_profile_stats()
+        assert word.find_all_positions("ab 12 cd") == [(0, 2), (6, 8)]
+        stats = self.stats_by_name()[str(word)]
+        assert stats["successes"] == 2
+        assert stats["match_calls"] > 2
+
+    def test_counts_children_of_root_and(self):
+        num = pp.Word(pp.nums())
+        ident = pp.Word(pp.alphas())
+        grammar = ident + num
+        assert grammar.parse_string("x 1").as_list() == ["x", "1"]
+        stats = self.stats_by_name()
+        assert stats[str(grammar)]["parse_calls"] == 1
+        assert stats[str(ident)]["successes"] == 1
+        assert stats[str(num)]["successes"] == 1
+
+    def test_match_only_calls(self):
+        # SkipTo only probes for its target
+        grammar = pp.Literal("(") + pp.SkipTo(pp.Literal(")"))
+        grammar.parse_string("(12)")
+        stats = self.stats_by_name()
+        assert stats["')'"]["match_calls"] == 3
+        assert stats["')'"]["parse_calls"] == 0
+
+    def test_self_time_excludes_nested_elements(self):
+        inner = pp.Word(pp.nums())
+        outer = pp.Group(pp.OneOrMore(inner))
+        pp.OneOrMore(outer).parse_string(" ".join(["1"] * 200))
+        stats = pp.get_profile_stats()
+        assert [s["total_ns"] for s in stats] == sorted((s["total_ns"] for s in stats), reverse=True)
+        for s in stats:
+            assert 0 <= s["self_ns"] <= s["total_ns"]
+        by_name = self.stats_by_name()
+        assert by_name[str(outer)]["self_ns"] < by_name[str(outer)]["total_ns"]
+        assert by_name[str(inner)]["self_ns"] == by_name[str(inner)]["total_ns"]
+
+    def test_merged_across_threads(self):
+        num = pp.Word(pp.nums())
+        grammar = pp.OneOrMore(num | pp.Word(pp.alphas()))
+        threads = [threading.Thread(target=grammar.parse_string, args=("1 2 3",))
+                   for _ in range(4)]
+        for t in threads:
+            t.start()
+        for t in threads:
+            t.join()
+        assert self.stats_by_name()[str(num)]["successes"] == 12
+
+    def test_disabled_and_reset(self):
+        grammar = pp.OneOrMore(pp.Word(pp.nums()) | pp.Word(pp.alphas()))
+        grammar.parse_string("1 a")
+        assert pp.get_profile_stats()
+        pp.reset_profile_stats()
+        assert pp.get_profile_stats() == []
+        pp.enable_profiling(False)
+        grammar.parse_string("1 a")
+        assert pp.get_profile_stats() == []
+
+    def test_results_unchanged(self):
+        grammar = pp.OneOrMore(pp.Group(pp.Word(pp.alphas()) + pp.Optional(pp.Word(pp.nums()))))
+        text = "a 1 b c 2"
+        pp.enable_profiling(False)
+        expected = (grammar.parse_string(text).as_list(), grammar.matches(text),
+                    [t.as_list() for t in grammar.search_string(text)])
+        pp.enable_profiling()
+        assert (grammar.parse_string(text).as_list(), grammar.matches(text),
+                [t.as_list() for t in grammar.search_string(text)]) == expected
@@ -330,6 +330,174 @@ def run_comparison():
     print(f"  pyparsing_rs: {rs_ns/1e6:.1f} ms  (transform_string)")
     print(f"  speedup:      {speedup:.1f}x")
 
+    # =========================================================================
+    # 13. Literal predicates (100K strings) — list comprehension vs batch
+    # =========================================================================
+    print("\n--- Prefix/contains predicates (100K strings) ---")
+    pred_strings = ["GET /index.html", "POST /api/v1", "GET /api/v2", "HEAD /"] * 25000
+    def py_predicates_bench():
+        [s.startswith("GET") and "/api" in s for s in pred_strings]
+    py_ns = benchmark(py_predicates_bench)
+
+    def rs_predicates_bench():
+        pp_rs.batch_str_predicates(pred_strings, prefix="GET", contains="/api")
+    rs_ns = benchmark(rs_predicates_bench)
+
+    speedup = py_ns / rs_ns
+    results["str_predicates"] = speedup
+    print(f"  python:       {py_ns/1e6:.1f} ms  (list comprehension)")
+    print(f"  pyparsing_rs: {rs_ns/1e6:.1f} ms  (batch_str_predicates)")
+    print(f"  speedup:      {speedup:.1f}x")
+
+    # =========================================================================
+    # 14. Word with non-ASCII characters on a large alphanumeric input (1MB)
+    #     ASCII members are a bitmap test, the rest a sorted-set lookup
+    # =========================================================================
+    print("\n--- Unicode Word search_string (1MB text) ---")
+    uni_text = ("abc123 straße été42 " * 52429)[:1 << 20]
+    uni_chars = "abcdefghijklmnopqrstuvwxyz0123456789ßé"
+
+    pp_uni = pp.Word(uni_chars)
+    def pp_unicode_bench():
+        pp_uni.search_string(uni_text)
+    pp_ns = benchmark(pp_unicode_bench, iterations=3)
+
+    rs_uni = pp_rs.Word(uni_chars)
+    def rs_unicode_bench():
+        rs_uni.search_string(uni_text)
+    rs_ns = benchmark(rs_unicode_bench, iterations=3)
+
+    speedup = pp_ns / rs_ns
+    results["unicode_word_search"] = speedup
+    print(f"  pyparsing:    {pp_ns/1e6:.1f} ms")
+    print(f"  pyparsing_rs: {rs_ns/1e6:.1f} ms")
+    print(f"  speedup:      {speedup:.1f}x")
+
+    # =========================================================================
+    # 15. Token-heavy batch parse — ten tokens per line, borrowed from the input
+    #     until converted to Python strings
+    # =========================================================================
+    print("\n--- Token-heavy batch parse (10K lines, 10 tokens each) ---")
+    test_lines = [" ".join(f"w{i}x{j}" for j in range(10)) for i in range(10000)]
+
+    pp_tokens = pp.OneOrMore(pp.Word(pp.alphanums))
+    def pp_tokens_bench():
+        for line in test_lines:
+            pp_tokens.parse_string(line)
+    pp_ns = benchmark(pp_tokens_bench, iterations=3)
+
+    rs_tokens = pp_rs.OneOrMore(pp_rs.Word(pp_rs.alphanums()))
+    def rs_tokens_bench():
+        rs_tokens.parse_batch(test_lines)
+    rs_ns = benchmark(rs_tokens_bench, iterations=3)
+
+    if rs_tokens.parse_batch(test_lines[:1]) != [pp_tokens.parse_string(test_lines[0]).as_list()]:
+        print("  WARNING: token mismatch between pyparsing and pyparsing_rs!")
+    speedup = pp_ns / rs_ns
+    results["token_heavy_batch_parse"] = speedup
+    print(f"  pyparsing:    {pp_ns/1e6:.1f} ms  (10K parse_string calls)")
+    print(f"  pyparsing_rs: {rs_ns/1e6:.1f} ms  (parse_batch)")
+    print(f"  speedup:      {speedup:.1f}x")
+
+    # =========================================================================
+    # 16. key=value records — KeyValueParser vs the composed rs grammar
+    # =========================================================================
+    print("\n--- key=value records (10K lines) ---")
+    kv_lines = [
+        f'ts=2024-01-01T00:00:{i % 60:02d} level=error msg="boom {i}" count={i}'
+        for i in range(10000)
+    ]
+
+    key = pp_rs.Word(pp_rs.alphanums() + "_.-")
+    value = pp_rs.QuotedString('"', esc_char="\\") | pp_rs.Regex(r'[^\s"]+') | pp_rs.Empty()
+    rs_composed = pp_rs.ZeroOrMore(pp_rs.Group(key + pp_rs.Suppress("=") + value))
+    def rs_composed_bench():
+        rs_composed.parse_batch(kv_lines)
+    composed_ns = benchmark(rs_composed_bench, iterations=3)
+
+    rs_kv = pp_rs.KeyValueParser()
+    def rs_kv_bench():
+        rs_kv.parse_batch(kv_lines)
+    kv_ns = benchmark(rs_kv_bench, iterations=3)
+
+    if dict(rs_composed.parse_string(kv_lines[0]).as_list()) != rs_kv.parse_line(kv_lines[0]):
+        print("  WARNING: pair mismatch between the grammar and KeyValueParser!")
+    speedup = composed_ns / kv_ns
+    results["key_value_records"] = speedup
+    print(f"  composed grammar: {composed_ns/1e6:.1f} ms  (parse_batch)")
+    print(f"  KeyValueParser:   {kv_ns/1e6:.1f} ms  (parse_batch, dicts)")
+    print(f"  speedup:          {speedup:.1f}x")
+
+    # =========================================================================
+    # 17. Checkpoint overhead — scan_string with and without a timeout_ms
+    #     Guards throughput: timed scans read the clock at checkpoints
+    # =========================================================================
+    print("\n--- Checkpoint overhead (scan_string, 1 MB) ---")
+    scan_text = "x1 a x22 " * 120000
+    rs_scan = pp_rs.Literal("x") + pp_rs.Word(pp_rs.nums())
+    def rs_untimed_bench():
+        rs_scan.scan_string(scan_text)
+    untimed_ns = benchmark(rs_untimed_bench, iterations=5)
+
+    def rs_timed_bench():
+        rs_scan.scan_string(scan_text, timeout_ms=60000)
+    timed_ns = benchmark(rs_timed_bench, iterations=5)
+
+    overhead = timed_ns / untimed_ns - 1
+    if overhead > 0.10:
+        print("  WARNING: checkpoints cost more than 10% of scan throughput!")
+    print(f"  untimed:  {untimed_ns/1e6:.1f} ms  (scan_string)")
+    print(f"  timed:    {timed_ns/1e6:.1f} ms  (scan_string, timeout_ms)")
+    print(f"  overhead: {overhead:+.1%}")
+
+    # =========================================================================
+    # 18. Large alternation — 200 keywords, dispatched on the next byte vs
+    #     tried in turn (each wrapped in a Group, whose first bytes are unknown)
+    # =========================================================================
+    print("\n--- Large alternation (200 keywords, 300KB search_string) ---")
+    alt_words = [f"{a}{b}{c}" for a in "bcdfgkmprst" for b in "aeiou" for c in "lnrs"][:200]
+    alt_text = " ".join(
+        f"{alt_words[i % 200]} {alt_words[(i * 7) % 200]} {i}" for i in range(20000))
+    rs_dispatched = pp_rs.MatchFirst(
+        [pp_rs.Keyword(w) for w in alt_words] + [pp_rs.Word(pp_rs.nums())])
+    def rs_dispatched_bench():
+        rs_dispatched.search_string(alt_text)
+    dispatched_ns = benchmark(rs_dispatched_bench, iterations=5)
+
+    rs_in_turn = pp_rs.MatchFirst(
+        [pp_rs.Group(pp_rs.Keyword(w)) for w in alt_words] + [pp_rs.Word(pp_rs.nums())])
+    def rs_in_turn_bench():
+        rs_in_turn.search_string(alt_text)
+    in_turn_ns = benchmark(rs_in_turn_bench, iterations=5)
+
+    speedup = in_turn_ns / dispatched_ns
+    print(f"  tried in turn: {in_turn_ns/1e6:.1f} ms  (search_string)")
+    print(f"  dispatched:    {dispatched_ns/1e6:.1f} ms  (search_string)")
+    print(f"  speedup:       {speedup:.1f}x")
+
+    # =========================================================================
+    # 19. Profiling — a nested grammar parsed with element profiling off and on
+    #     Off is the path every parse takes, so it is compared to the previous
+    #     build's number for this line; on shows what a profiling run costs
+    # =========================================================================
+    print("\n--- Profiling (nested grammar, 2000 lines, parse_batch) ---")
+    prof_item = pp_rs.Group(pp_rs.Word(pp_rs.alphas()) + pp_rs.Optional(pp_rs.Word(pp_rs.nums())))
+    rs_profiled = pp_rs.OneOrMore(prof_item | pp_rs.Suppress(","))
+    prof_lines = [f"ab {i}, cd, ef {i * 7}, gh" for i in range(2000)]
+    def rs_profiled_bench():
+        rs_profiled.parse_batch(prof_lines)
+    off_ns = benchmark(rs_profiled_bench, iterations=5)
+
+    pp_rs.enable_profiling()
+    on_ns = benchmark(rs_profiled_bench, iterations=5)
+    pp_rs.enable_profiling(False)
+    prof_elements = len(pp_rs.get_profile_stats())
+    pp_rs.reset_profile_stats()
+
+    print(f"  profiling off: {off_ns/1e6:.1f} ms  (parse_batch)")
+    print(f"  profiling on:  {on_ns/1e6:.1f} ms  (parse_batch, {prof_elements} elements)")
+    print(f"  cost when on:  {on_ns / off_ns:.1f}x")
+
     # =========================================================================
     # Summary
     # =========================================================================